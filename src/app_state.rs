@@ -4,6 +4,43 @@ use crate::monsters::monster_manager::{MonsterManager, MonsterManagerFactory};
 use crate::game_loop::player_movement::PlayerMovementManager;
 use crate::game_loop::pokemon_collection::PokemonCollectionManager;
 use crate::combat::manager::BattleManager;
+use crate::game_loop::currency::CurrencyManager;
+use crate::game_loop::npc_services::NpcServices;
+use crate::game_loop::inventory::InventoryManager;
+use crate::game_loop::berries::BerryManager;
+use crate::game_loop::weather::OverworldWeather;
+use crate::game_loop::monster_spawner::RespawnCooldowns;
+use crate::game_loop::catch_chain::CatchChains;
+use crate::game_loop::lobby_buffs::LobbyBuffs;
+use crate::game_loop::item_pickups::ItemPickups;
+use crate::game_loop::lobby_events::LobbyEventLog;
+use crate::game_loop::occupancy::TileOccupancy;
+use crate::game_loop::latency::LatencyTracker;
+use crate::game_loop::monster_persistence;
+use crate::game_loop::fast_travel::FastTravelManager;
+use crate::game_loop::gates::GateManager;
+use crate::game_loop::badges::BadgeManager;
+use crate::game_loop::emotes::EmoteManager;
+use crate::game_loop::trading::TradeManager;
+use crate::game_loop::market::MarketManager;
+use crate::game_loop::auctions::AuctionManager;
+use crate::game_loop::mail::MailManager;
+use crate::game_loop::ranking::RankingManager;
+use crate::game_loop::battle_history::BattleHistoryManager;
+use crate::game_loop::player_settings::PlayerSettingsManager;
+use crate::game_loop::tutorial::TutorialManager;
+use crate::game_loop::blocks::BlockListManager;
+use crate::game_loop::friends::FriendListManager;
+use crate::game_loop::battle_chat::BattleChatManager;
+use crate::game_loop::battle_replays::BattleReplayManager;
+use crate::game_loop::lobby_snapshots::LobbySnapshotManager;
+use crate::game_loop::notifications::NotificationManager;
+use crate::game_loop::player_index::PlayerIndex;
+use crate::game_loop::time_sync::ServerClock;
+use crate::game_loop::webhooks::Webhooks;
+use crate::game_loop::audit_log::AuditLog;
+use crate::monsters::locales::Locales;
+use crate::redis_manager::PersistenceQueue;
 use dashmap::DashMap;
 use std::sync::Arc;
 use tokio::sync::broadcast;
@@ -11,26 +48,82 @@ use tokio::sync::broadcast;
 // Shared application state
 pub struct AppState {
     pub redis: redis::Client,
+    pub persistence: Arc<PersistenceQueue>,
     pub lobbies: DashMap<String, Arc<Lobby>>,
+    pub player_index: Arc<PlayerIndex>, // Connected players of every lobby, by username
+    pub clock: Arc<ServerClock>, // Server time and tick number sent to clients
+    pub locales: Arc<Locales>, // Translated species and move names
     pub config: Config,
     pub monster_manager: Option<Arc<MonsterManager>>,
     pub monster_manager_factory: Option<Arc<MonsterManagerFactory>>,
     pub player_movement_manager: Option<Arc<PlayerMovementManager>>,
     pub pokemon_collection_manager: Option<Arc<PokemonCollectionManager>>,
     pub battle_manager: Option<Arc<BattleManager>>,
+    pub currency_manager: Option<Arc<CurrencyManager>>,
+    pub npc_services: Option<Arc<NpcServices>>,
+    pub inventory_manager: Option<Arc<InventoryManager>>,
+    pub berry_manager: Option<Arc<BerryManager>>,
+    pub fast_travel_manager: Option<Arc<FastTravelManager>>,
+    pub gate_manager: Option<Arc<GateManager>>,
+    pub badge_manager: Option<Arc<BadgeManager>>,
+    pub emote_manager: Option<Arc<EmoteManager>>,
+    pub trade_manager: Option<Arc<TradeManager>>,
+    pub market_manager: Option<Arc<MarketManager>>,
+    pub auction_manager: Option<Arc<AuctionManager>>,
+    pub mail_manager: Option<Arc<MailManager>>,
+    pub ranking_manager: Option<Arc<RankingManager>>,
+    pub battle_history_manager: Option<Arc<BattleHistoryManager>>,
+    pub player_settings_manager: Option<Arc<PlayerSettingsManager>>,
+    pub block_list_manager: Option<Arc<BlockListManager>>,
+    pub friend_list_manager: Option<Arc<FriendListManager>>,
+    pub battle_chat_manager: Option<Arc<BattleChatManager>>,
+    pub battle_replay_manager: Option<Arc<BattleReplayManager>>,
+    pub lobby_snapshot_manager: Option<Arc<LobbySnapshotManager>>,
+    pub notification_manager: Option<Arc<NotificationManager>>,
+    pub tutorial_manager: Option<Arc<TutorialManager>>,
+    pub webhooks: Option<Arc<Webhooks>>,
+    pub audit_log: Option<Arc<AuditLog>>,
 }
 
 impl AppState {
     pub fn new(redis_client: redis::Client, config: Config) -> Arc<Self> {
         Arc::new(AppState {
             redis: redis_client,
+            persistence: Arc::new(PersistenceQueue::default()),
             lobbies: DashMap::new(),
+            player_index: Arc::new(PlayerIndex::default()),
+            clock: Arc::new(ServerClock::new(config.ticks.player_movement_interval_ms)),
+            locales: Arc::new(Locales::load(&config.monsters.locales_path)),
             config,
             monster_manager: None,
             monster_manager_factory: None,
             player_movement_manager: None,
             pokemon_collection_manager: None,
             battle_manager: None,
+            currency_manager: None,
+            npc_services: None,
+            inventory_manager: None,
+            berry_manager: None,
+            fast_travel_manager: None,
+            gate_manager: None,
+            badge_manager: None,
+            emote_manager: None,
+            trade_manager: None,
+            market_manager: None,
+            auction_manager: None,
+            mail_manager: None,
+            ranking_manager: None,
+            battle_history_manager: None,
+            player_settings_manager: None,
+            block_list_manager: None,
+            friend_list_manager: None,
+            battle_chat_manager: None,
+            battle_replay_manager: None,
+            lobby_snapshot_manager: None,
+            notification_manager: None,
+            tutorial_manager: None,
+            webhooks: None,
+            audit_log: None,
         })
     }
 
@@ -42,87 +135,263 @@ impl AppState {
         new_lobbies
     }
 
-    pub fn with_monster_manager(self: &Arc<Self>, monster_manager: Arc<MonsterManager>) -> Arc<Self> {
-        Arc::new(AppState {
+    // Copy of this state that builder methods modify before wrapping in a new Arc
+    fn rebuild(&self) -> AppState {
+        AppState {
             redis: self.redis.clone(),
+            persistence: self.persistence.clone(),
             lobbies: self.clone_lobbies(),
+            player_index: self.player_index.clone(),
+            clock: self.clock.clone(),
+            locales: self.locales.clone(),
             config: self.config.clone(),
-            monster_manager: Some(monster_manager),
+            monster_manager: self.monster_manager.clone(),
             monster_manager_factory: self.monster_manager_factory.clone(),
             player_movement_manager: self.player_movement_manager.clone(),
             pokemon_collection_manager: self.pokemon_collection_manager.clone(),
             battle_manager: self.battle_manager.clone(),
-        })
+            currency_manager: self.currency_manager.clone(),
+            npc_services: self.npc_services.clone(),
+            inventory_manager: self.inventory_manager.clone(),
+            berry_manager: self.berry_manager.clone(),
+            fast_travel_manager: self.fast_travel_manager.clone(),
+            gate_manager: self.gate_manager.clone(),
+            badge_manager: self.badge_manager.clone(),
+            emote_manager: self.emote_manager.clone(),
+            trade_manager: self.trade_manager.clone(),
+            market_manager: self.market_manager.clone(),
+            auction_manager: self.auction_manager.clone(),
+            mail_manager: self.mail_manager.clone(),
+            ranking_manager: self.ranking_manager.clone(),
+            battle_history_manager: self.battle_history_manager.clone(),
+            player_settings_manager: self.player_settings_manager.clone(),
+            block_list_manager: self.block_list_manager.clone(),
+            friend_list_manager: self.friend_list_manager.clone(),
+            battle_chat_manager: self.battle_chat_manager.clone(),
+            battle_replay_manager: self.battle_replay_manager.clone(),
+            lobby_snapshot_manager: self.lobby_snapshot_manager.clone(),
+            notification_manager: self.notification_manager.clone(),
+            tutorial_manager: self.tutorial_manager.clone(),
+            webhooks: self.webhooks.clone(),
+            audit_log: self.audit_log.clone(),
+        }
+    }
+
+    pub fn with_monster_manager(self: &Arc<Self>, monster_manager: Arc<MonsterManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.monster_manager = Some(monster_manager);
+        Arc::new(state)
     }
     
     pub fn with_monster_manager_factory(self: &Arc<Self>, factory: Arc<MonsterManagerFactory>) -> Arc<Self> {
-        Arc::new(AppState {
-            redis: self.redis.clone(),
-            lobbies: self.clone_lobbies(),
-            config: self.config.clone(),
-            monster_manager: self.monster_manager.clone(),
-            monster_manager_factory: Some(factory),
-            player_movement_manager: self.player_movement_manager.clone(),
-            pokemon_collection_manager: self.pokemon_collection_manager.clone(),
-            battle_manager: self.battle_manager.clone(),
-        })
+        let mut state = self.rebuild();
+        state.monster_manager_factory = Some(factory);
+        Arc::new(state)
     }
 
     pub fn with_player_movement_manager(self: &Arc<Self>, player_movement_manager: Arc<PlayerMovementManager>) -> Arc<Self> {
-        Arc::new(AppState {
-            redis: self.redis.clone(),
-            lobbies: self.clone_lobbies(),
-            config: self.config.clone(),
-            monster_manager: self.monster_manager.clone(),
-            monster_manager_factory: self.monster_manager_factory.clone(),
-            player_movement_manager: Some(player_movement_manager),
-            pokemon_collection_manager: self.pokemon_collection_manager.clone(),
-            battle_manager: self.battle_manager.clone(),
-        })
+        let mut state = self.rebuild();
+        state.player_movement_manager = Some(player_movement_manager);
+        Arc::new(state)
     }
 
     pub fn with_pokemon_collection_manager(self: &Arc<Self>, pokemon_collection_manager: Arc<PokemonCollectionManager>) -> Arc<Self> {
-        Arc::new(AppState {
-            redis: self.redis.clone(),
-            lobbies: self.clone_lobbies(),
-            config: self.config.clone(),
-            monster_manager: self.monster_manager.clone(),
-            monster_manager_factory: self.monster_manager_factory.clone(),
-            player_movement_manager: self.player_movement_manager.clone(),
-            pokemon_collection_manager: Some(pokemon_collection_manager),
-            battle_manager: self.battle_manager.clone(),
-        })
+        let mut state = self.rebuild();
+        state.pokemon_collection_manager = Some(pokemon_collection_manager);
+        Arc::new(state)
     }
 
     pub fn with_battle_manager(self: &Arc<Self>, battle_manager: Arc<BattleManager>) -> Arc<Self> {
-        Arc::new(AppState {
-            redis: self.redis.clone(),
-            lobbies: self.clone_lobbies(),
-            config: self.config.clone(),
-            monster_manager: self.monster_manager.clone(),
-            monster_manager_factory: self.monster_manager_factory.clone(),
-            player_movement_manager: self.player_movement_manager.clone(),
-            pokemon_collection_manager: self.pokemon_collection_manager.clone(),
-            battle_manager: Some(battle_manager),
-        })
+        let mut state = self.rebuild();
+        state.battle_manager = Some(battle_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_currency_manager(self: &Arc<Self>, currency_manager: Arc<CurrencyManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.currency_manager = Some(currency_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_npc_services(self: &Arc<Self>, npc_services: Arc<NpcServices>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.npc_services = Some(npc_services);
+        Arc::new(state)
+    }
+
+    pub fn with_inventory_manager(self: &Arc<Self>, inventory_manager: Arc<InventoryManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.inventory_manager = Some(inventory_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_berry_manager(self: &Arc<Self>, berry_manager: Arc<BerryManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.berry_manager = Some(berry_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_fast_travel_manager(self: &Arc<Self>, fast_travel_manager: Arc<FastTravelManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.fast_travel_manager = Some(fast_travel_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_gate_manager(self: &Arc<Self>, gate_manager: Arc<GateManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.gate_manager = Some(gate_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_badge_manager(self: &Arc<Self>, badge_manager: Arc<BadgeManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.badge_manager = Some(badge_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_emote_manager(self: &Arc<Self>, emote_manager: Arc<EmoteManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.emote_manager = Some(emote_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_trade_manager(self: &Arc<Self>, trade_manager: Arc<TradeManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.trade_manager = Some(trade_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_market_manager(self: &Arc<Self>, market_manager: Arc<MarketManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.market_manager = Some(market_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_auction_manager(self: &Arc<Self>, auction_manager: Arc<AuctionManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.auction_manager = Some(auction_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_mail_manager(self: &Arc<Self>, mail_manager: Arc<MailManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.mail_manager = Some(mail_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_ranking_manager(self: &Arc<Self>, ranking_manager: Arc<RankingManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.ranking_manager = Some(ranking_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_battle_history_manager(self: &Arc<Self>, battle_history_manager: Arc<BattleHistoryManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.battle_history_manager = Some(battle_history_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_player_settings_manager(self: &Arc<Self>, player_settings_manager: Arc<PlayerSettingsManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.player_settings_manager = Some(player_settings_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_block_list_manager(self: &Arc<Self>, block_list_manager: Arc<BlockListManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.block_list_manager = Some(block_list_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_friend_list_manager(self: &Arc<Self>, friend_list_manager: Arc<FriendListManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.friend_list_manager = Some(friend_list_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_battle_chat_manager(self: &Arc<Self>, battle_chat_manager: Arc<BattleChatManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.battle_chat_manager = Some(battle_chat_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_battle_replay_manager(self: &Arc<Self>, battle_replay_manager: Arc<BattleReplayManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.battle_replay_manager = Some(battle_replay_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_lobby_snapshot_manager(self: &Arc<Self>, lobby_snapshot_manager: Arc<LobbySnapshotManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.lobby_snapshot_manager = Some(lobby_snapshot_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_notification_manager(self: &Arc<Self>, notification_manager: Arc<NotificationManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.notification_manager = Some(notification_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_tutorial_manager(self: &Arc<Self>, tutorial_manager: Arc<TutorialManager>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.tutorial_manager = Some(tutorial_manager);
+        Arc::new(state)
+    }
+
+    pub fn with_webhooks(self: &Arc<Self>, webhooks: Arc<Webhooks>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.webhooks = Some(webhooks);
+        Arc::new(state)
+    }
+
+    pub fn with_audit_log(self: &Arc<Self>, audit_log: Arc<AuditLog>) -> Arc<Self> {
+        let mut state = self.rebuild();
+        state.audit_log = Some(audit_log);
+        Arc::new(state)
     }
 
     pub async fn initialize_default_lobbies(&self) {
         let default_lobbies = vec!["ABCD-1234", "EFGH-5678", "IJKL-9012"];
-        let monster_manager_factory = MonsterManagerFactory::new(&self.config.monsters.templates_path).await;
+        let monster_manager_factory = MonsterManagerFactory::new(&self.config.monsters.templates_path, &self.config.monsters.content_packs).await;
         for lobby_id in default_lobbies {
             let (lobby_tx, _) = broadcast::channel(self.config.performance.broadcast_channel_size);
-            self.lobbies.insert(lobby_id.to_string(), Arc::new(Lobby {
+            let lobby = Arc::new(Lobby {
                 id: lobby_id.to_string(),
                 player_positions: DashMap::new(),
                 player_last_active: DashMap::new(),
+                player_last_input: DashMap::new(),
                 tx: lobby_tx,
                 map_id: "map1".to_string(),
                 active_monsters: DashMap::new(),
                 monsters_by_spawn_point: DashMap::new(),
                 monster_manager: monster_manager_factory.create_monster_manager("map1").await.unwrap(),
                 player_connections: DashMap::new(),
-            }));
+                berry_plots: DashMap::new(),
+                weather: std::sync::RwLock::new(OverworldWeather::default()),
+                respawn_cooldowns: RespawnCooldowns::new(
+                    self.config.monsters.spawn_point_cooldown_sec,
+                    self.config.monsters.species_cooldown_sec,
+                ),
+                catch_chains: CatchChains::default(),
+                buffs: LobbyBuffs::default(),
+                item_pickups: ItemPickups::default(),
+                occupancy: TileOccupancy::default(),
+                monster_collision: self.config.game.monster_collision,
+                latency: LatencyTracker::default(),
+                events: LobbyEventLog::default(),
+                locales: self.locales.clone(),
+                player_locales: DashMap::new(),
+                player_protocols: DashMap::new(),
+                cleared_gates: DashMap::new(),
+                pending_challenges: DashMap::new(),
+            });
+
+            // Bring back the monsters that were roaming before the last restart
+            if let Err(e) = monster_persistence::restore_lobby_monsters(&self.redis, &lobby).await {
+                tracing::error!("Failed to restore monsters for lobby {}: {}", lobby_id, e);
+            }
+            self.lobbies.insert(lobby_id.to_string(), lobby);
         }
     }
 } 
\ No newline at end of file
@@ -0,0 +1,62 @@
+// Takes, lists and restores player data backups against the Redis in REDIS_URL:
+//   cargo run --bin backup -- create
+//   cargo run --bin backup -- list
+//   cargo run --bin backup -- restore <archive>
+//   cargo run --bin backup -- restore --bucket <archive name>
+// Archives are read from and written to BACKUP_DIR, or the directory given with --dir,
+// and new ones are also uploaded to BACKUP_S3_URL when it is set. `restore --bucket`
+// downloads the named archive from BACKUP_S3_URL into the directory before restoring it.
+use game_server::game_loop::backups::BackupManager;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: backup [--dir <directory>] (create | list | restore <archive> | restore --bucket <archive name>)";
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt::init();
+    let config = game_server::config::Config::from_env();
+
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let mut directory = config.backups.directory.clone();
+    if let Some(index) = args.iter().position(|arg| arg == "--dir") {
+        if index + 1 >= args.len() {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+        directory = Some(args.remove(index + 1));
+        args.remove(index);
+    }
+    let Some(directory) = directory else {
+        eprintln!("Set BACKUP_DIR or pass --dir");
+        return ExitCode::FAILURE;
+    };
+
+    let redis_url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
+    let redis_client = game_server::redis_manager::init_redis_client(&redis_url).await;
+    let backup_manager = BackupManager::new(redis_client, directory, config.backups.retain, config.backups.bucket.clone());
+
+    let result = match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["create"] => backup_manager.create_backup().await.map(|path| println!("{}", path.display())),
+        ["list"] => backup_manager.list_backups().await.map(|backups| {
+            for path in backups {
+                println!("{}", path.display());
+            }
+        }),
+        ["restore", "--bucket", name] => backup_manager.restore_from_bucket(name).await
+            .map(|count| println!("Restored {} keys", count)),
+        ["restore", archive] => backup_manager.restore_backup(&PathBuf::from(archive)).await
+            .map(|count| println!("Restored {} keys", count)),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,6 @@
+// Prints the websocket protocol's JSON Schema so CI can check the frontend's generated types are current:
+//   cargo run --bin protocol_schema > protocol.schema.json
+fn main() {
+    let schema = game_server::schema::protocol_schema();
+    println!("{}", serde_json::to_string_pretty(&schema).expect("Protocol schema is always serializable"));
+}
@@ -0,0 +1,136 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::combat::logic::battle_calculations::calculate_type_effectiveness;
+use crate::combat::logic::core::must_struggle;
+use crate::combat::state::{BattlePhase, BattlePokemon, PlayerAction, PvPBattleState, WildBattleState, WildPokemonAction};
+use crate::monsters::move_manager::{MoveCategory, MoveRepository};
+
+/// Same-type attack bonus used when scoring moves
+const STAB_MULTIPLIER: f32 = 1.5;
+
+/// Strength of the server bot in practice battles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PracticeDifficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl PracticeDifficulty {
+    /// Levels the bot's team is above (or below) the player's strongest Pokémon
+    pub fn level_offset(self) -> i32 {
+        match self {
+            PracticeDifficulty::Easy => -5,
+            PracticeDifficulty::Normal => 0,
+            PracticeDifficulty::Hard => 3,
+        }
+    }
+
+    /// Chance the bot plays its best move rather than a random one
+    fn best_move_chance(self) -> f64 {
+        match self {
+            PracticeDifficulty::Easy => 0.0,
+            PracticeDifficulty::Normal => 0.6,
+            PracticeDifficulty::Hard => 1.0,
+        }
+    }
+}
+
+/// Chooses the wild Pokémon's action: its first move with PP left, or Struggle
+pub fn choose_wild_action(battle_state: &WildBattleState) -> WildPokemonAction {
+    match battle_state.wild_pokemon.moves.iter().position(|m| m.current_pp > 0) {
+        Some(move_index) => WildPokemonAction::UseMove { move_index },
+        None => WildPokemonAction::Struggle,
+    }
+}
+
+/// Expected damage of a move relative to the others, ignoring random rolls and critical hits.
+/// Status moves and unknown moves score 0.
+fn move_score(attacker: &BattlePokemon, defender: &BattlePokemon, move_id: u32, move_repository: &MoveRepository) -> f32 {
+    let Some(move_data) = move_repository.get_move(move_id) else {
+        return 0.0;
+    };
+    let (attack, defense) = match move_data.damage_class {
+        MoveCategory::Physical => (attacker.calculated_stats.attack, defender.calculated_stats.defense),
+        MoveCategory::Special => (attacker.calculated_stats.special_attack, defender.calculated_stats.special_defense),
+        MoveCategory::Status => return 0.0,
+    };
+    let power = move_data.power.unwrap_or(0) as f32;
+    let accuracy = move_data.accuracy.map_or(1.0, |accuracy| accuracy as f32 / 100.0);
+    let effectiveness = calculate_type_effectiveness(Some(&move_repository.type_chart), &move_data.move_type, &defender.pokemon_types);
+    let stab = if attacker.pokemon_types.contains(&move_data.move_type) { STAB_MULTIPLIER } else { 1.0 };
+    power * accuracy * effectiveness * stab * attack as f32 / defense.max(1) as f32
+}
+
+/// Chooses the attacker's move with the highest expected damage against the defender.
+/// Falls back to the first move with PP left when nothing deals damage, and to the first move
+/// when every move is out of PP, which turns into Struggle.
+pub fn choose_best_move(attacker: &BattlePokemon, defender: &BattlePokemon, move_repository: Option<&MoveRepository>) -> usize {
+    if must_struggle(attacker) {
+        return 0;
+    }
+    let usable = attacker.moves.iter().enumerate().filter(|(_, m)| m.current_pp > 0);
+    let best = move_repository.and_then(|repo| {
+        usable.clone()
+            .map(|(index, m)| (index, move_score(attacker, defender, m.move_id, repo)))
+            .filter(|(_, score)| *score > 0.0)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index)
+    });
+    best.or_else(|| usable.map(|(index, _)| index).next()).unwrap_or(0)
+}
+
+/// Chooses the team member to send in after the active Pokémon fainted: the one with the most HP left
+pub fn choose_replacement(team: &[BattlePokemon], active_index: usize) -> Option<usize> {
+    team.iter()
+        .enumerate()
+        .filter(|(index, p)| *index != active_index && !p.is_fainted && p.current_hp > 0)
+        .max_by_key(|(_, p)| p.current_hp)
+        .map(|(index, _)| index)
+}
+
+/// The practice bot's action as player 2 of a practice battle
+pub fn choose_practice_action(battle_state: &PvPBattleState, difficulty: PracticeDifficulty) -> PlayerAction {
+    let bot = &battle_state.player2;
+    let opponent = &battle_state.player1;
+    if bot.must_switch {
+        if let Some(team_index) = choose_replacement(&bot.team, bot.active_pokemon_index) {
+            return PlayerAction::SwitchPokemon { team_index };
+        }
+    }
+    let active = &bot.team[bot.active_pokemon_index];
+    let mut rng = rand::thread_rng();
+    let move_index = if must_struggle(active) || rng.gen_bool(difficulty.best_move_chance()) {
+        choose_best_move(active, &opponent.team[opponent.active_pokemon_index], battle_state.move_repository.as_deref())
+    } else {
+        let usable = active.moves.iter()
+            .enumerate()
+            .filter(|(_, m)| m.current_pp > 0)
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        usable.choose(&mut rng).copied().unwrap_or(0)
+    };
+    PlayerAction::UseMove { move_index }
+}
+
+/// The action the server plays for a player who put their wild battle on auto,
+/// or None when the battle is not waiting on the player
+pub fn choose_auto_action(battle_state: &WildBattleState) -> Option<PlayerAction> {
+    let player = &battle_state.player;
+    match battle_state.battle_phase.get() {
+        BattlePhase::WaitingForPlayerAction => {
+            let move_index = choose_best_move(
+                &player.team[player.active_pokemon_index],
+                &battle_state.wild_pokemon,
+                battle_state.move_repository.as_deref(),
+            );
+            Some(PlayerAction::UseMove { move_index })
+        }
+        BattlePhase::WaitingForSwitch => choose_replacement(&player.team, player.active_pokemon_index)
+            .map(|team_index| PlayerAction::SwitchPokemon { team_index }),
+        _ => None,
+    }
+}
@@ -0,0 +1,277 @@
+use rand::seq::{IteratorRandom, SliceRandom};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::combat::state::BattleRules;
+use crate::combat::team_validation::Banlist;
+use crate::monsters::monster::PokemonType;
+use crate::monsters::monster_manager::MonsterTemplateRepository;
+
+/// Species offered in a draft pool
+pub const DRAFT_POOL_SIZE: usize = 16;
+/// Species each player removes from the pool before picking starts
+pub const DRAFT_BANS_PER_PLAYER: usize = 1;
+/// Species each player picks for their team
+pub const DRAFT_TEAM_SIZE: usize = 6;
+/// Rental team ID recorded for drafted teams, whose progress is never saved
+pub const DRAFT_TEAM_ID: &str = "draft";
+/// Seconds a player has for each ban or pick before one is made for them
+pub const DRAFT_PICK_SECS: u64 = 30;
+
+/// What the player on the clock does with their selection
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DraftAction {
+    Ban,
+    Pick,
+}
+
+/// One species on offer in a draft
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct DraftPoolEntry {
+    pub template_id: u32,
+    pub name: String,
+    pub types: Vec<PokemonType>,
+}
+
+/// A pick/ban draft between two players. Bans alternate first, then picks go in snake order
+/// (1-2-2-1-1-2...) until both teams are full. Drafted teams are lent like rental teams.
+#[derive(Debug, Clone)]
+pub struct DraftState {
+    pub draft_id: Uuid,
+    pub player_ids: [String; 2],
+    pub rules: BattleRules,
+    /// Crystals the players had equipped when the draft started, carried into the battle
+    pub crystal_types: [Option<PokemonType>; 2],
+    /// Level every drafted Pokémon is created at
+    pub level: u32,
+    pub pool: Vec<DraftPoolEntry>,
+    pub banned: Vec<u32>,
+    pub picks: [Vec<u32>; 2],
+    /// Bans and picks made so far
+    pub turn: usize,
+    /// Unix timestamp (seconds) when the current turn runs out
+    pub deadline: u64,
+}
+
+impl DraftState {
+    /// Draw a pool of distinct species, skipping banned species when the rules enforce the banlist.
+    /// Fails when there are not enough species left for every ban and pick.
+    pub fn new(
+        player_ids: [String; 2],
+        crystal_types: [Option<PokemonType>; 2],
+        rules: BattleRules,
+        level: u32,
+        template_repository: &MonsterTemplateRepository,
+        banlist: &Banlist,
+    ) -> Result<Self, String> {
+        let mut rng = rand::thread_rng();
+        let mut pool: Vec<DraftPoolEntry> = template_repository.templates.values()
+            .filter(|template| !rules.enforce_banlist || !banlist.species.contains(&template.id))
+            .choose_multiple(&mut rng, DRAFT_POOL_SIZE)
+            .into_iter()
+            .map(|template| DraftPoolEntry {
+                template_id: template.id,
+                name: template.name.clone(),
+                types: template.types.clone(),
+            })
+            .collect();
+        if pool.len() < 2 * (DRAFT_BANS_PER_PLAYER + DRAFT_TEAM_SIZE) {
+            return Err("Not enough species for a draft".to_string());
+        }
+        pool.shuffle(&mut rng);
+        Ok(Self {
+            draft_id: Uuid::new_v4(),
+            player_ids,
+            crystal_types,
+            rules,
+            level,
+            pool,
+            banned: Vec::new(),
+            picks: [Vec::new(), Vec::new()],
+            turn: 0,
+            deadline: 0,
+        })
+    }
+
+    /// The action due this turn and the index of the player making it, None once both teams are full
+    pub fn current_turn(&self) -> Option<(DraftAction, usize)> {
+        let bans = 2 * DRAFT_BANS_PER_PLAYER;
+        if self.turn < bans {
+            return Some((DraftAction::Ban, self.turn % 2));
+        }
+        let pick = self.turn - bans;
+        if pick >= 2 * DRAFT_TEAM_SIZE {
+            return None;
+        }
+        let (round, position) = (pick / 2, pick % 2);
+        Some((DraftAction::Pick, if round % 2 == 0 { position } else { 1 - position }))
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.current_turn().is_none()
+    }
+
+    /// Whether a species is still in the pool, neither banned nor picked
+    pub fn is_available(&self, template_id: u32) -> bool {
+        self.pool.iter().any(|entry| entry.template_id == template_id)
+            && !self.banned.contains(&template_id)
+            && !self.picks.iter().any(|picks| picks.contains(&template_id))
+    }
+
+    /// Ban or pick `template_id` for `player_id`, who must be on the clock
+    pub fn select(&mut self, player_id: &str, template_id: u32) -> Result<DraftAction, String> {
+        let (action, side) = self.current_turn().ok_or_else(|| "The draft is already over".to_string())?;
+        if self.player_ids[side] != player_id {
+            return Err("It is not your turn to draft".to_string());
+        }
+        if !self.is_available(template_id) {
+            return Err(format!("Species {} is not available in this draft", template_id));
+        }
+        match action {
+            DraftAction::Ban => self.banned.push(template_id),
+            DraftAction::Pick => self.picks[side].push(template_id),
+        }
+        self.turn += 1;
+        Ok(action)
+    }
+
+    /// Give the player on the clock DRAFT_PICK_SECS from `now` (Unix seconds) for their selection
+    pub fn start_clock(&mut self, now: u64) {
+        self.deadline = now + DRAFT_PICK_SECS;
+    }
+
+    /// Seconds left on the clock at `now`
+    pub fn remaining_secs(&self, now: u64) -> u64 {
+        self.deadline.saturating_sub(now)
+    }
+
+    /// A random species still in the pool, for a player who ran out of time
+    pub fn random_available(&self) -> Option<u32> {
+        self.pool.iter()
+            .map(|entry| entry.template_id)
+            .filter(|template_id| self.is_available(*template_id))
+            .choose(&mut rand::thread_rng())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn draft() -> DraftState {
+        let template_repository = MonsterTemplateRepository::for_tests();
+        DraftState::new(
+            ["p1".to_string(), "p2".to_string()],
+            [None, None],
+            BattleRules::default(),
+            50,
+            &template_repository,
+            &Banlist::default(),
+        ).unwrap()
+    }
+
+    /// Make every selection of the draft with the first available species, returning who made each
+    fn play_out(draft_state: &mut DraftState) -> Vec<(DraftAction, usize)> {
+        let mut turns = Vec::new();
+        while let Some((action, side)) = draft_state.current_turn() {
+            let template_id = draft_state.pool.iter()
+                .map(|entry| entry.template_id)
+                .find(|template_id| draft_state.is_available(*template_id))
+                .unwrap();
+            let player_id = draft_state.player_ids[side].clone();
+            assert_eq!(draft_state.select(&player_id, template_id), Ok(action));
+            turns.push((action, side));
+        }
+        turns
+    }
+
+    #[test]
+    fn bans_alternate_then_picks_go_in_snake_order() {
+        let mut draft_state = draft();
+        let turns = play_out(&mut draft_state);
+
+        let bans: Vec<usize> = turns.iter().filter(|(action, _)| *action == DraftAction::Ban).map(|(_, side)| *side).collect();
+        assert_eq!(bans, [0, 1].repeat(DRAFT_BANS_PER_PLAYER));
+        let picks: Vec<usize> = turns.iter().filter(|(action, _)| *action == DraftAction::Pick).map(|(_, side)| *side).collect();
+        assert_eq!(picks, [0, 1, 1, 0].repeat(DRAFT_TEAM_SIZE / 2));
+        // No ban comes after the first pick
+        assert!(turns.iter().skip(2 * DRAFT_BANS_PER_PLAYER).all(|(action, _)| *action == DraftAction::Pick));
+
+        assert!(draft_state.is_complete());
+        assert!(draft_state.picks.iter().all(|picks| picks.len() == DRAFT_TEAM_SIZE));
+        assert_eq!(draft_state.select("p1", draft_state.pool[0].template_id), Err("The draft is already over".to_string()));
+    }
+
+    #[test]
+    fn only_the_player_on_the_clock_can_select() {
+        let mut draft_state = draft();
+        let template_id = draft_state.pool[0].template_id;
+        assert_eq!(draft_state.select("p2", template_id), Err("It is not your turn to draft".to_string()));
+        assert_eq!(draft_state.turn, 0);
+        assert_eq!(draft_state.select("p1", template_id), Ok(DraftAction::Ban));
+    }
+
+    #[test]
+    fn banned_and_picked_species_leave_the_pool() {
+        let mut draft_state = draft();
+        let banned = draft_state.pool[0].template_id;
+        draft_state.select("p1", banned).unwrap();
+        assert!(!draft_state.is_available(banned));
+        assert!(draft_state.select("p2", banned).is_err());
+        assert_eq!(draft_state.banned, vec![banned]);
+
+        let ban = draft_state.pool[1].template_id;
+        draft_state.select("p2", ban).unwrap();
+        let picked = draft_state.pool[2].template_id;
+        assert_eq!(draft_state.select("p1", picked), Ok(DraftAction::Pick));
+        assert!(!draft_state.is_available(picked));
+        assert_eq!(draft_state.picks[0], vec![picked]);
+
+        // Species outside the pool can never be picked
+        let outside = MonsterTemplateRepository::for_tests().templates.keys()
+            .copied()
+            .find(|template_id| !draft_state.pool.iter().any(|entry| entry.template_id == *template_id))
+            .unwrap();
+        assert!(draft_state.select("p2", outside).is_err());
+    }
+
+    #[test]
+    fn banlisted_species_never_enter_the_pool() {
+        let template_repository = MonsterTemplateRepository::for_tests();
+        let banned: HashSet<u32> = template_repository.templates.keys().copied().take(20).collect();
+        let banlist = Banlist { species: banned.clone(), moves: HashSet::new() };
+        let rules = BattleRules { enforce_banlist: true, ..BattleRules::default() };
+        let draft_state = DraftState::new(["p1".to_string(), "p2".to_string()], [None, None], rules, 50, &template_repository, &banlist).unwrap();
+        assert!(draft_state.pool.iter().all(|entry| !banned.contains(&entry.template_id)));
+    }
+
+    #[test]
+    fn too_small_a_pool_is_rejected() {
+        let template_repository = MonsterTemplateRepository::for_tests();
+        let banlist = Banlist { species: template_repository.templates.keys().copied().collect(), moves: HashSet::new() };
+        let rules = BattleRules { enforce_banlist: true, ..BattleRules::default() };
+        let result = DraftState::new(["p1".to_string(), "p2".to_string()], [None, None], rules, 50, &template_repository, &banlist);
+        assert_eq!(result.err(), Some("Not enough species for a draft".to_string()));
+    }
+
+    #[test]
+    fn clock_runs_for_the_pick_time_and_times_out_with_an_available_species() {
+        let mut draft_state = draft();
+        draft_state.start_clock(1_000);
+        assert_eq!(draft_state.deadline, 1_000 + DRAFT_PICK_SECS);
+        assert_eq!(draft_state.remaining_secs(1_010), DRAFT_PICK_SECS - 10);
+        assert_eq!(draft_state.remaining_secs(1_000 + DRAFT_PICK_SECS + 5), 0);
+
+        // A timed out player gets a random species that is still available, until the draft is over
+        while let Some((_, side)) = draft_state.current_turn() {
+            let template_id = draft_state.random_available().unwrap();
+            assert!(draft_state.is_available(template_id));
+            let player_id = draft_state.player_ids[side].clone();
+            draft_state.select(&player_id, template_id).unwrap();
+        }
+        assert!(draft_state.is_complete());
+    }
+}
@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::combat::logic;
+use crate::combat::replay::TurnSnapshot;
+use crate::combat::rng;
+use crate::combat::state::{
+    BattleEvent, BattlePlayer, BattlePokemon, BattlePvPPhase, FieldState, PlayerAction, PvPBattleState,
+};
+use crate::monsters::monster_manager::MonsterTemplateRepository;
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Hash of a battle seed, sent when the battle starts so the seed revealed at the end
+/// can be checked against it
+pub fn seed_commitment(seed: u64) -> String {
+    sha256_hex(&seed.to_be_bytes())
+}
+
+/// RNG seed of the `turn_index`-th processed turn (0 being the first), derived from the battle seed
+pub fn turn_seed(battle_seed: u64, turn_index: usize) -> u64 {
+    let mut hasher = Sha256::new();
+    hasher.update(battle_seed.to_be_bytes());
+    hasher.update((turn_index as u64).to_be_bytes());
+    let digest = hasher.finalize();
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(bytes)
+}
+
+/// A player as they entered the battle, team included
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptPlayer {
+    #[serde(flatten)]
+    pub player: BattlePlayer,
+    /// Not part of the serialized `BattlePlayer`
+    pub team: Vec<BattlePokemon>,
+}
+
+impl TranscriptPlayer {
+    fn from_battle_player(player: &BattlePlayer) -> Self {
+        Self { player: player.clone(), team: player.team.clone() }
+    }
+
+    fn into_battle_player(self) -> BattlePlayer {
+        BattlePlayer { team: self.team, ..self.player }
+    }
+}
+
+/// The actions of one processed turn and the events they produced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptTurn {
+    pub turn_number: u32,
+    pub player1_action: Option<PlayerAction>,
+    pub player2_action: Option<PlayerAction>,
+    pub events: Vec<BattleEvent>,
+}
+
+/// Everything needed to re-simulate a finished PvP battle: the state it started from and
+/// every turn that was processed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BattleTranscript {
+    pub battle_id: Uuid,
+    pub player1: TranscriptPlayer,
+    pub player2: TranscriptPlayer,
+    pub field_state: FieldState,
+    pub turns: Vec<TranscriptTurn>,
+}
+
+impl BattleTranscript {
+    /// Build the transcript from the turn snapshots of a battle; None if no turn was processed
+    pub fn from_snapshots(battle_id: Uuid, snapshots: &[TurnSnapshot]) -> Option<Self> {
+        let first = snapshots.first()?;
+        Some(Self {
+            battle_id,
+            player1: TranscriptPlayer::from_battle_player(&first.player1),
+            player2: TranscriptPlayer::from_battle_player(&first.player2),
+            field_state: first.field_state.clone(),
+            turns: snapshots.iter()
+                .map(|snapshot| TranscriptTurn {
+                    turn_number: snapshot.turn_number,
+                    player1_action: snapshot.player1_action.clone(),
+                    player2_action: snapshot.player2_action.clone(),
+                    events: snapshot.events.clone(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Hash over every turn's actions and events
+    pub fn hash(&self) -> String {
+        sha256_hex(&serde_json::to_vec(&self.turns).unwrap_or_default())
+    }
+}
+
+/// What is revealed when a PvP battle ends
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct FairnessDisclosure {
+    pub battle_id: Uuid,
+    /// Sent as a string since JSON numbers lose precision above 2^53
+    #[serde(with = "seed_as_string")]
+    #[schemars(with = "String")]
+    pub seed: u64,
+    pub seed_commitment: String,
+    pub transcript_hash: String,
+}
+
+mod seed_as_string {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(seed: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&seed.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Result of checking a disclosure against a transcript
+#[derive(Debug, Clone, Serialize)]
+pub struct VerificationReport {
+    pub seed_matches_commitment: bool,
+    pub transcript_matches_hash: bool,
+    /// Every turn re-simulated from the seed produced the events in the transcript
+    pub replay_matches: bool,
+    /// First turn whose re-simulated events differ from the transcript
+    pub first_mismatched_turn: Option<u32>,
+    pub verified: bool,
+}
+
+/// Re-simulate the transcript with the disclosed seed and compare it with what was recorded
+pub fn verify(
+    transcript: &BattleTranscript,
+    disclosure: &FairnessDisclosure,
+    template_repository: &MonsterTemplateRepository,
+) -> VerificationReport {
+    let seed_matches_commitment = seed_commitment(disclosure.seed) == disclosure.seed_commitment;
+    let transcript_matches_hash = transcript.hash() == disclosure.transcript_hash;
+
+    let mut battle_state = PvPBattleState::new(
+        transcript.battle_id,
+        transcript.player1.clone().into_battle_player(),
+        transcript.player2.clone().into_battle_player(),
+        template_repository.move_repository.clone(),
+        template_repository.ability_repository.clone(),
+    );
+    battle_state.field_state = transcript.field_state.clone();
+
+    let mut first_mismatched_turn = None;
+    for (turn_index, turn) in transcript.turns.iter().enumerate() {
+        battle_state.turn_number = turn.turn_number;
+        battle_state.player1_action = turn.player1_action.clone();
+        battle_state.player2_action = turn.player2_action.clone();
+        battle_state.battle_phase.transition(BattlePvPPhase::ProcessingTurn);
+        let events = rng::with_seed(turn_seed(disclosure.seed, turn_index), || {
+            logic::process_pvp_turn(&mut battle_state, template_repository)
+        });
+        // Compare the serialized form, which is what was hashed and sent to the players
+        if serde_json::to_value(&events).ok() != serde_json::to_value(&turn.events).ok() {
+            first_mismatched_turn = Some(turn.turn_number);
+            break;
+        }
+    }
+
+    let replay_matches = first_mismatched_turn.is_none();
+    VerificationReport {
+        seed_matches_commitment,
+        transcript_matches_hash,
+        replay_matches,
+        first_mismatched_turn,
+        verified: seed_matches_commitment && transcript_matches_hash && replay_matches,
+    }
+}
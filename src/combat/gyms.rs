@@ -0,0 +1,300 @@
+use std::fs::File;
+use std::io::BufReader;
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::combat::ai::PracticeDifficulty;
+use crate::combat::rental_teams::RentalPokemon;
+use crate::game_loop::pokemon_collection::Pokemon;
+use crate::monsters::monster_manager::MonsterTemplateRepository;
+
+/// Tiles away from a gym leader a player may stand and still challenge them
+pub const GYM_CHALLENGE_RANGE_TILES: u32 = 2;
+/// Seconds after a player beats a gym leader before the leader offers them a rematch
+pub const DEFAULT_REMATCH_COOLDOWN_SECS: u64 = 24 * 60 * 60;
+/// Highest team tier a gym leader brings to rematches
+pub const MAX_REMATCH_TIER: u32 = 5;
+/// Levels a rematch team gains for every tier beyond the last one the gym defines
+const REMATCH_LEVEL_STEP: u32 = 10;
+const MAX_LEVEL: u32 = 100;
+/// Highest level a player's Pokémon obey at before they hold any badge
+pub const BASE_OBEDIENCE_LEVEL_CAP: u32 = 20;
+
+/// Badge awarded for defeating a gym leader
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, schemars::JsonSchema)]
+pub struct Badge {
+    pub id: String,
+    pub name: String,
+}
+
+/// An NPC gym leader standing on a map, who hands out their badge when defeated
+#[derive(Debug, Clone, Deserialize)]
+pub struct GymLeader {
+    pub id: String,
+    pub name: String,
+    pub map_id: String,
+    pub tile_x: u32,
+    pub tile_y: u32,
+    pub badge: Badge,
+    #[serde(default = "default_gym_difficulty")]
+    pub difficulty: PracticeDifficulty,
+    pub team: Vec<RentalPokemon>,
+    /// Stronger teams brought to rematches, one per tier starting at tier 1
+    #[serde(default)]
+    pub rematch_teams: Vec<Vec<RentalPokemon>>,
+    #[serde(default = "default_rematch_cooldown_secs")]
+    pub rematch_cooldown_secs: u64,
+    /// Highest level a player's Pokémon obey at once this is the last badge they earned.
+    /// Holding the last gym's badge lifts the cap entirely.
+    #[serde(default = "default_obedience_cap")]
+    pub obedience_cap: u32,
+}
+
+fn default_obedience_cap() -> u32 {
+    MAX_LEVEL
+}
+
+fn default_rematch_cooldown_secs() -> u64 {
+    DEFAULT_REMATCH_COOLDOWN_SECS
+}
+
+/// What players see of a gym leader standing on their map
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct GymLeaderView {
+    pub gym_id: String,
+    pub name: String,
+    pub x: u32,
+    pub y: u32,
+    pub badge: Badge,
+}
+
+impl From<&GymLeader> for GymLeaderView {
+    fn from(gym: &GymLeader) -> Self {
+        GymLeaderView {
+            gym_id: gym.id.clone(),
+            name: gym.name.clone(),
+            x: gym.tile_x,
+            y: gym.tile_y,
+            badge: gym.badge.clone(),
+        }
+    }
+}
+
+fn default_gym_difficulty() -> PracticeDifficulty {
+    PracticeDifficulty::Hard
+}
+
+impl GymLeader {
+    /// Whether a player at (`x`, `y`) stands close enough to challenge this leader
+    pub fn is_within_reach(&self, map_id: &str, x: u32, y: u32) -> bool {
+        self.map_id == map_id
+            && x.abs_diff(self.tile_x) <= GYM_CHALLENGE_RANGE_TILES
+            && y.abs_diff(self.tile_y) <= GYM_CHALLENGE_RANGE_TILES
+    }
+
+    /// Fresh Pokémon for the leader's team at `tier`: 0 for the first challenge, one more for
+    /// every rematch. Tiers past the rematch teams the gym defines reuse the last one with
+    /// higher levels. They exist only for one battle.
+    pub fn team(&self, template_repository: &MonsterTemplateRepository, tier: u32) -> Vec<Pokemon> {
+        let tier = tier.min(MAX_REMATCH_TIER) as usize;
+        let (members, defined_tier) = match tier {
+            0 => (&self.team, 0),
+            _ => match self.rematch_teams.get(tier - 1).or(self.rematch_teams.last()) {
+                Some(members) => (members, tier.min(self.rematch_teams.len())),
+                None => (&self.team, 0),
+            },
+        };
+        let extra_levels = (tier - defined_tier) as u32 * REMATCH_LEVEL_STEP;
+        members.iter()
+            .map(|member| {
+                let level = (member.level + extra_levels).min(MAX_LEVEL);
+                template_repository.pokemon_from_template(member.template_id, Some(level))
+            })
+            .collect()
+    }
+}
+
+/// What a gym leader offers a player who talks to them
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GymOffer {
+    /// The player has not beaten this leader yet
+    Challenge,
+    /// The player beat this leader before and may battle their team at `tier` again
+    Rematch { tier: u32 },
+    /// The leader is not ready for a rematch until `available_at` (Unix timestamp, seconds)
+    RematchCooldown { available_at: i64 },
+}
+
+/// Every gym leader across the maps, in the order their badges are meant to be earned
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Gyms {
+    #[serde(default)]
+    pub gyms: Vec<GymLeader>,
+}
+
+impl Gyms {
+    /// Load the gym leaders at `path`, dropping team members whose species is unknown and
+    /// leaders left without a team. No gyms are open when the file cannot be read.
+    pub fn load(path: &str, template_repository: &MonsterTemplateRepository) -> Self {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => {
+                warn!("Failed to open gyms {}: {}", path, e);
+                return Gyms::default();
+            }
+        };
+        let mut gyms = match serde_json::from_reader::<_, Gyms>(BufReader::new(file)) {
+            Ok(gyms) => gyms,
+            Err(e) => {
+                warn!("Failed to parse gyms {}: {}", path, e);
+                return Gyms::default();
+            }
+        };
+        for gym in &mut gyms.gyms {
+            let gym_id = gym.id.clone();
+            let known_species = |member: &RentalPokemon| {
+                let known = template_repository.templates.contains_key(&member.template_id);
+                if !known {
+                    warn!("Gym {}: skipping unknown species {}", gym_id, member.template_id);
+                }
+                known
+            };
+            gym.team.retain(known_species);
+            for rematch_team in &mut gym.rematch_teams {
+                rematch_team.retain(known_species);
+            }
+            gym.rematch_teams.retain(|rematch_team| !rematch_team.is_empty());
+        }
+        gyms.gyms.retain(|gym| !gym.team.is_empty());
+        info!("Loaded {} gyms from {}", gyms.gyms.len(), path);
+        gyms
+    }
+
+    pub fn get(&self, gym_id: &str) -> Option<&GymLeader> {
+        self.gyms.iter().find(|gym| gym.id == gym_id)
+    }
+
+    /// Highest level a player's Pokémon obey at with `badge_count` of these gyms' badges.
+    /// Players holding every badge, or playing without any gyms, are obeyed at every level.
+    pub fn obedience_level_cap(&self, badge_count: usize) -> u32 {
+        if badge_count >= self.gyms.len() {
+            return MAX_LEVEL;
+        }
+        match badge_count {
+            0 => BASE_OBEDIENCE_LEVEL_CAP,
+            _ => self.gyms[badge_count - 1].obedience_cap,
+        }
+    }
+
+    /// The gym whose leader awards `badge_id`
+    pub fn by_badge(&self, badge_id: &str) -> Option<&GymLeader> {
+        self.gyms.iter().find(|gym| gym.badge.id == badge_id)
+    }
+
+    /// Gym leaders standing on `map_id`
+    pub fn on_map<'a>(&'a self, map_id: &'a str) -> impl Iterator<Item = &'a GymLeader> + 'a {
+        self.gyms.iter().filter(move |gym| gym.map_id == map_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gyms() -> Gyms {
+        Gyms::load("resources/gyms.json", &MonsterTemplateRepository::for_tests())
+    }
+
+    fn load_json(json: &str) -> Gyms {
+        let path = std::env::temp_dir().join(format!("gyms-test-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, json).unwrap();
+        let gyms = Gyms::load(path.to_str().unwrap(), &MonsterTemplateRepository::for_tests());
+        std::fs::remove_file(&path).unwrap();
+        gyms
+    }
+
+    #[test]
+    fn obedience_cap_rises_with_each_gym_and_lifts_after_the_last() {
+        let gyms = gyms();
+        assert_eq!(gyms.gyms.len(), 3);
+        assert_eq!(gyms.obedience_level_cap(0), BASE_OBEDIENCE_LEVEL_CAP);
+        assert_eq!(gyms.obedience_level_cap(1), gyms.gyms[0].obedience_cap);
+        assert_eq!(gyms.obedience_level_cap(2), gyms.gyms[1].obedience_cap);
+        assert_eq!(gyms.obedience_level_cap(3), MAX_LEVEL);
+        assert_eq!(gyms.obedience_level_cap(10), MAX_LEVEL);
+        assert_eq!(Gyms::default().obedience_level_cap(0), MAX_LEVEL);
+    }
+
+    #[test]
+    fn leader_is_within_reach_only_nearby_on_their_map() {
+        let gyms = gyms();
+        let gym = gyms.get("town_gym").unwrap();
+        let (x, y) = (gym.tile_x, gym.tile_y);
+        assert!(gym.is_within_reach("map1", x, y));
+        assert!(gym.is_within_reach("map1", x + GYM_CHALLENGE_RANGE_TILES, y - GYM_CHALLENGE_RANGE_TILES));
+        assert!(!gym.is_within_reach("map1", x + GYM_CHALLENGE_RANGE_TILES + 1, y));
+        assert!(!gym.is_within_reach("map1", x, y - GYM_CHALLENGE_RANGE_TILES - 1));
+        assert!(!gym.is_within_reach("map2", x, y));
+    }
+
+    #[test]
+    fn load_drops_unknown_species_and_empty_teams() {
+        let gyms = load_json(r#"{ "gyms": [
+            { "id": "mixed", "name": "Mixed", "map_id": "map1", "tile_x": 1, "tile_y": 1,
+              "badge": { "id": "mixed", "name": "Mixed Badge" },
+              "team": [{ "template_id": 1, "level": 10 }, { "template_id": 9999, "level": 10 }],
+              "rematch_teams": [[{ "template_id": 9999, "level": 20 }], [{ "template_id": 2, "level": 30 }]] },
+            { "id": "unknown", "name": "Unknown", "map_id": "map1", "tile_x": 2, "tile_y": 2,
+              "badge": { "id": "unknown", "name": "Unknown Badge" },
+              "team": [{ "template_id": 9999, "level": 10 }] }
+        ] }"#);
+        assert_eq!(gyms.gyms.len(), 1);
+        let gym = &gyms.gyms[0];
+        assert_eq!(gym.team.len(), 1);
+        assert_eq!(gym.rematch_teams.len(), 1);
+        assert_eq!(gym.rematch_teams[0][0].template_id, 2);
+        assert_eq!((gym.difficulty, gym.rematch_cooldown_secs, gym.obedience_cap), (PracticeDifficulty::Hard, DEFAULT_REMATCH_COOLDOWN_SECS, MAX_LEVEL));
+    }
+
+    #[test]
+    fn load_opens_no_gyms_when_the_file_is_missing_or_invalid() {
+        assert!(Gyms::load("resources/no_such_gyms.json", &MonsterTemplateRepository::for_tests()).gyms.is_empty());
+        assert!(load_json("{ \"gyms\": 3 }").gyms.is_empty());
+    }
+
+    fn levels(gym: &GymLeader, tier: u32) -> Vec<(u32, u32)> {
+        gym.team(&MonsterTemplateRepository::for_tests(), tier).iter()
+            .map(|pokemon| (pokemon.template_id, pokemon.level))
+            .collect()
+    }
+
+    #[test]
+    fn rematch_tiers_use_defined_teams_then_level_up_the_last() {
+        let gyms = gyms();
+        let gym = gyms.get("town_gym").unwrap();
+        assert_eq!(levels(gym, 0), [(27, 12), (50, 14)]);
+        assert_eq!(levels(gym, 1), [(27, 24), (50, 26), (28, 25)]);
+        assert_eq!(levels(gym, 2), [(27, 34), (50, 36), (28, 35)]);
+        assert_eq!(levels(gym, MAX_REMATCH_TIER), [(27, 64), (50, 66), (28, 65)]);
+        assert_eq!(levels(gym, MAX_REMATCH_TIER + 3), levels(gym, MAX_REMATCH_TIER));
+    }
+
+    #[test]
+    fn gyms_without_rematch_teams_level_up_their_first_team() {
+        let gyms = gyms();
+        let gym = gyms.get("meadow_gym").unwrap();
+        assert!(gym.rematch_teams.is_empty());
+        assert_eq!(levels(gym, 0), [(23, 18), (42, 21)]);
+        assert_eq!(levels(gym, 1), [(23, 28), (42, 31)]);
+        assert_eq!(levels(gym, MAX_REMATCH_TIER), [(23, 68), (42, 71)]);
+    }
+
+    #[test]
+    fn rematch_levels_stop_at_the_max_level() {
+        let mut gym = gyms().get("grove_gym").unwrap().clone();
+        gym.team[0].level = 95;
+        assert_eq!(levels(&gym, MAX_REMATCH_TIER)[0].1, MAX_LEVEL);
+    }
+}
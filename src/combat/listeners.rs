@@ -0,0 +1,68 @@
+use crate::combat::state::{
+    BattleEndReason, BattlePokemonPublicView, BattlePokemonTeamOverview, CaptureAttempt, MoveUse,
+    PvPBattleOutcome, WildBattleOutcome,
+};
+use crate::combat::replay::BattleReplay;
+use crate::lobby::Lobby;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Summary of a finished wild battle handed to outcome listeners
+#[derive(Clone)]
+pub struct WildBattleSummary {
+    pub battle_id: Uuid,
+    pub lobby: Arc<Lobby>,
+    pub player_id: String,
+    pub player_team: Vec<BattlePokemonTeamOverview>,
+    pub wild_pokemon: BattlePokemonPublicView,
+    pub wild_shiny: bool,
+    pub wild_alpha: bool,
+    pub outcome: WildBattleOutcome,
+    pub reason: BattleEndReason,
+    pub turns: u32,
+    pub started_at: u64,
+    pub moves_used: Vec<MoveUse>,
+    pub capture_attempts: Vec<CaptureAttempt>,
+}
+
+/// Summary of a finished PvP battle handed to outcome listeners
+#[derive(Clone)]
+pub struct PvPBattleSummary {
+    pub battle_id: Uuid,
+    pub lobby: Arc<Lobby>,
+    pub player1_id: String,
+    pub player2_id: String,
+    pub player1_team: Vec<BattlePokemonTeamOverview>,
+    pub player2_team: Vec<BattlePokemonTeamOverview>,
+    pub player1_outcome: PvPBattleOutcome,
+    pub player2_outcome: PvPBattleOutcome,
+    pub turns: u32,
+    pub started_at: u64,
+    pub moves_used: Vec<MoveUse>,
+    /// Turn-by-turn replay, present only when the battle was flagged for review
+    pub replay: Option<BattleReplay>,
+}
+
+/// Summary of a finished gym battle handed to outcome listeners. The gym leader is played by
+/// the server bot, so only the challenger's side is reported.
+#[derive(Clone)]
+pub struct GymBattleSummary {
+    pub battle_id: Uuid,
+    pub lobby: Arc<Lobby>,
+    pub player_id: String,
+    pub gym_id: String,
+    pub outcome: PvPBattleOutcome,
+}
+
+/// Subsystems that react to finished battles (ranking, quests, stats, ...) implement this
+/// and register themselves on the `BattleManager`.
+///
+/// Callbacks run inline after the battle has been cleaned up, so implementations should be
+/// quick and spawn a task for any slow or async work.
+pub trait BattleOutcomeListener: Send + Sync {
+    fn on_wild_battle_finished(&self, _summary: &WildBattleSummary) {}
+
+    fn on_pvp_battle_finished(&self, _summary: &PvPBattleSummary) {}
+
+    fn on_gym_battle_finished(&self, _summary: &GymBattleSummary) {}
+}
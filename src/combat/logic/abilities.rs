@@ -0,0 +1,205 @@
+use crate::combat::logic::battle_effects::apply_effect;
+use crate::combat::logic::core::BattleSides;
+use crate::combat::logic::weather::{start_weather, WEATHER_TURNS};
+use crate::combat::state::{BattleEntityRef, BattleEvent, StatusCondition};
+use crate::monsters::ability_manager::{AbilityData, AbilityEffect};
+use crate::monsters::move_manager::{EffectData, EffectTarget, Stat, StatChangeParam};
+
+/// Ability data of `entity`, if its ability has battle effects
+fn ability_of<S: BattleSides>(battle_state: &S, entity: &BattleEntityRef) -> Option<(String, AbilityData)> {
+    let pokemon = battle_state.pokemon(entity)?;
+    let ability = battle_state.ability_repository()?.get_ability(&pokemon.ability)?;
+    Some((pokemon.ability.clone(), ability.clone()))
+}
+
+/// Tell the client that the ability of `entity` just took effect
+pub fn announce_ability<S: BattleSides>(battle_state: &S, battle_events: &mut Vec<BattleEvent>, entity: &BattleEntityRef) {
+    let (Some(pokemon), Some((ability_id, ability))) = (battle_state.pokemon(entity), ability_of(battle_state, entity)) else {
+        return;
+    };
+    battle_events.push(BattleEvent::GenericMessage { message: format!("[{}'s {}]", pokemon.name, ability.name) });
+    battle_events.push(BattleEvent::AbilityActivated { target: entity.clone(), ability_id, ability_name: ability.name });
+}
+
+fn stat_change(stat: Stat, stages: i8, target: EffectTarget) -> EffectData {
+    EffectData::StatChange { changes: vec![StatChangeParam { stat, stages }], target }
+}
+
+/// Trigger the abilities that act when `entity` enters battle, such as Intimidate and Drizzle
+pub fn on_switch_in<S: BattleSides>(battle_state: &mut S, battle_events: &mut Vec<BattleEvent>, entity: &BattleEntityRef) {
+    let Some((_, ability)) = ability_of(battle_state, entity) else {
+        return;
+    };
+    for effect in &ability.effects {
+        if let AbilityEffect::WeatherOnEntry { weather } = effect {
+            if battle_state.weather() != Some(*weather) {
+                announce_ability(battle_state, battle_events, entity);
+                start_weather(battle_state, battle_events, *weather, WEATHER_TURNS);
+            }
+        }
+    }
+    let Some(opponent) = battle_state.opponent_of(entity) else {
+        return;
+    };
+    if battle_state.pokemon(&opponent).is_none_or(|pokemon| pokemon.is_fainted) {
+        return;
+    }
+    for effect in &ability.effects {
+        if let AbilityEffect::OpponentStatOnEntry { stat, stages } = effect {
+            announce_ability(battle_state, battle_events, entity);
+            apply_effect(battle_state, battle_events, &stat_change(*stat, *stages, EffectTarget::Target), entity.clone(), opponent.clone());
+        }
+    }
+}
+
+/// Trigger the entry abilities of both Pokémon that start the battle
+pub fn on_battle_start<S: BattleSides>(battle_state: &mut S, battle_events: &mut Vec<BattleEvent>) {
+    for entity in battle_state.active_entities() {
+        on_switch_in(battle_state, battle_events, &entity);
+    }
+}
+
+/// Whether the ability of `entity` keeps it from getting `status`, announcing the ability if so
+pub fn blocks_status<S: BattleSides>(battle_state: &S, battle_events: &mut Vec<BattleEvent>, entity: &BattleEntityRef, status: StatusCondition) -> bool {
+    let blocked = ability_of(battle_state, entity).is_some_and(|(_, ability)| ability.prevents_status(status));
+    if blocked {
+        announce_ability(battle_state, battle_events, entity);
+    }
+    blocked
+}
+
+/// Trigger the abilities of the active Pokémon that act at the end of every turn, such as Speed Boost
+pub fn apply_end_of_turn_abilities<S: BattleSides>(battle_state: &mut S, battle_events: &mut Vec<BattleEvent>) {
+    for entity in battle_state.active_entities() {
+        if battle_state.pokemon(&entity).is_none_or(|pokemon| pokemon.is_fainted || pokemon.current_hp == 0) {
+            continue;
+        }
+        let Some((_, ability)) = ability_of(battle_state, &entity) else {
+            continue;
+        };
+        for effect in &ability.effects {
+            if let AbilityEffect::EndOfTurnStat { stat, stages } = effect {
+                announce_ability(battle_state, battle_events, &entity);
+                apply_effect(battle_state, battle_events, &stat_change(*stat, *stages, EffectTarget::User), entity.clone(), entity.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combat::logic::core::execute_move;
+    use crate::combat::rng::with_seed;
+    use crate::combat::state::{BattlePokemon, PvPBattleState};
+    use crate::combat::test_support::{pvp_battle, repositories, test_pokemon, wild_battle};
+    use crate::monsters::ability_manager::AbilityRepository;
+    use crate::monsters::move_manager::MoveRepository;
+    use crate::monsters::PokemonType;
+    use std::sync::Arc;
+
+    const PLAYER: BattleEntityRef = BattleEntityRef::Player { team_index: 0 };
+    const PLAYER1: BattleEntityRef = BattleEntityRef::Player1 { team_index: 0 };
+    const PLAYER2: BattleEntityRef = BattleEntityRef::Player2 { team_index: 0 };
+    const FLAMETHROWER: u32 = 53;
+    const EARTHQUAKE: u32 = 89;
+    const TACKLE: u32 = 33;
+
+    fn with_ability(mut pokemon: BattlePokemon, ability: &str) -> BattlePokemon {
+        pokemon.ability = ability.to_string();
+        pokemon
+    }
+
+    /// A PvP battle where player 1 has `ability1` and player 2 has `ability2`
+    fn battle(repositories: &(Arc<MoveRepository>, Arc<AbilityRepository>), ability1: &str, ability2: &str) -> PvPBattleState {
+        pvp_battle(
+            with_ability(test_pokemon("Ace", &[PokemonType::Normal], 200, &[TACKLE]), ability1),
+            with_ability(test_pokemon("Rival", &[PokemonType::Normal], 200, &[TACKLE]), ability2),
+            repositories,
+        )
+    }
+
+    /// HP the wild Pokémon with `ability` loses to one seeded hit of `move_id`
+    fn damage_taken(repositories: &(Arc<MoveRepository>, Arc<AbilityRepository>), ability: &str, move_id: u32, seed: u64) -> u32 {
+        let wild = with_ability(test_pokemon("Wild", &[PokemonType::Normal], 500, &[TACKLE]), ability);
+        let mut battle_state = wild_battle(test_pokemon("Ace", &[PokemonType::Normal], 200, &[move_id]), wild, repositories);
+        with_seed(seed, || execute_move(&mut battle_state, &mut Vec::new(), PLAYER, 0));
+        500 - battle_state.wild_pokemon.current_hp
+    }
+
+    fn activated(events: &[BattleEvent], ability: &str) -> bool {
+        events.iter().any(|event| matches!(event, BattleEvent::AbilityActivated { ability_id, .. } if ability_id == ability))
+    }
+
+    #[test]
+    fn intimidate_lowers_the_opponents_attack_on_switch_in() {
+        let repositories = repositories();
+        let mut battle_state = battle(&repositories, "intimidate", "");
+        let mut events = Vec::new();
+        on_switch_in(&mut battle_state, &mut events, &PLAYER1);
+        assert_eq!(battle_state.player2.team[0].stat_modifiers.battle_stats.attack, -1);
+        assert_eq!(battle_state.player1.team[0].stat_modifiers.battle_stats.attack, 0);
+        assert!(activated(&events, "intimidate"));
+
+        // Nothing to intimidate once the opponent has fainted
+        let mut battle_state = battle(&repositories, "intimidate", "");
+        battle_state.player2.team[0].is_fainted = true;
+        let mut events = Vec::new();
+        on_switch_in(&mut battle_state, &mut events, &PLAYER1);
+        assert_eq!(battle_state.player2.team[0].stat_modifiers.battle_stats.attack, 0);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn levitate_avoids_ground_moves() {
+        let repositories = repositories();
+        for seed in 0..10 {
+            assert!(damage_taken(&repositories, "", EARTHQUAKE, seed) > 0);
+            assert_eq!(damage_taken(&repositories, "levitate", EARTHQUAKE, seed), 0);
+            assert_eq!(damage_taken(&repositories, "levitate", TACKLE, seed), damage_taken(&repositories, "", TACKLE, seed));
+        }
+    }
+
+    #[test]
+    fn thick_fat_halves_fire_damage() {
+        let repositories = repositories();
+        for seed in 0..10 {
+            let normal = damage_taken(&repositories, "", FLAMETHROWER, seed);
+            let halved = damage_taken(&repositories, "thick-fat", FLAMETHROWER, seed);
+            assert!(halved.abs_diff(normal / 2) <= 1, "seed {}: {} against {}", seed, halved, normal);
+            assert_eq!(damage_taken(&repositories, "thick-fat", TACKLE, seed), damage_taken(&repositories, "", TACKLE, seed));
+        }
+    }
+
+    #[test]
+    fn status_immunities_block_only_their_statuses() {
+        let repositories = repositories();
+        let battle_state = battle(&repositories, "limber", "insomnia");
+        let mut events = Vec::new();
+        assert!(blocks_status(&battle_state, &mut events, &PLAYER1, StatusCondition::Paralysis));
+        assert!(activated(&events, "limber"));
+        assert!(blocks_status(&battle_state, &mut Vec::new(), &PLAYER2, StatusCondition::Sleep));
+
+        let mut events = Vec::new();
+        assert!(!blocks_status(&battle_state, &mut events, &PLAYER1, StatusCondition::Burn));
+        assert!(!blocks_status(&battle_state, &mut events, &PLAYER2, StatusCondition::Paralysis));
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn speed_boost_raises_speed_at_the_end_of_every_turn() {
+        let repositories = repositories();
+        let mut battle_state = battle(&repositories, "speed-boost", "");
+        let mut events = Vec::new();
+        apply_end_of_turn_abilities(&mut battle_state, &mut events);
+        apply_end_of_turn_abilities(&mut battle_state, &mut events);
+        assert_eq!(battle_state.player1.team[0].stat_modifiers.battle_stats.speed, 2);
+        assert_eq!(battle_state.player2.team[0].stat_modifiers.battle_stats.speed, 0);
+        assert!(activated(&events, "speed-boost"));
+
+        // Fainted Pokémon don't speed up
+        battle_state.player1.team[0].is_fainted = true;
+        apply_end_of_turn_abilities(&mut battle_state, &mut Vec::new());
+        assert_eq!(battle_state.player1.team[0].stat_modifiers.battle_stats.speed, 2);
+    }
+}
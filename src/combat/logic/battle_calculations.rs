@@ -1,10 +1,69 @@
 use std::collections::HashMap;
 
-use crate::combat::state::{WildBattleState, BattleEntityRef};
+use serde::{Deserialize, Serialize};
+
+use crate::combat::logic::weather;
+use crate::combat::state::{WildBattleState, BattleEntityRef, BattleRules, PlayerSideState, WeatherType};
+use crate::monsters::ability_manager::AbilityData;
 use crate::monsters::move_manager::MoveData;
 use crate::monsters::PokemonType;
 use crate::stats::CalculatedStats;
 use rand::Rng;
+use crate::combat::rng::with_rng;
+
+/// Every factor that went into a single damage calculation, for damage-calc overlays and debugging
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct DamageBreakdown {
+    pub damage: u32,
+    pub base_power: u32,
+    pub stab: f32,
+    pub type_multiplier: f32,
+    pub is_critical: bool,
+    pub critical_multiplier: f32,
+    /// Random roll as a percentage (85-100)
+    pub random_roll: u8,
+    /// Which of the 16 possible rolls was used, 0 being the lowest
+    pub random_roll_bucket: u8,
+    /// Reduction from Reflect / Light Screen on the target's side
+    pub screen_multiplier: f32,
+    /// Immunity (0) or reduction from the target's ability
+    pub ability_multiplier: f32,
+    /// Rain or harsh sunlight boosting or weakening the move
+    pub weather_multiplier: f32,
+}
+
+impl DamageBreakdown {
+    /// Breakdown for a move that deals no direct damage
+    fn no_damage(base_power: u32) -> Self {
+        DamageBreakdown {
+            damage: 0,
+            base_power,
+            stab: 1.0,
+            type_multiplier: 1.0,
+            is_critical: false,
+            critical_multiplier: 1.0,
+            random_roll: 100,
+            random_roll_bucket: 15,
+            screen_multiplier: 1.0,
+            ability_multiplier: 1.0,
+            weather_multiplier: 1.0,
+        }
+    }
+}
+
+/// Lowest random damage roll, as a percentage
+const MIN_RANDOM_ROLL: u8 = 85;
+
+/// Battle-wide state a damage calculation depends on
+pub struct DamageContext<'a> {
+    pub type_chart: Option<&'a HashMap<PokemonType, HashMap<PokemonType, f32>>>,
+    /// Side state of the defending player, `None` when the target is a wild Pokémon
+    pub target_side: Option<&'a PlayerSideState>,
+    pub rules: &'a BattleRules,
+    /// Ability of the defending Pokémon, if it has battle effects
+    pub target_ability: Option<&'a AbilityData>,
+    pub weather: Option<WeatherType>,
+}
 
 /// Calculate damage using the traditional Pokémon game formula
 pub fn calculate_damage(
@@ -14,25 +73,28 @@ pub fn calculate_damage(
     target_stats: &CalculatedStats,
     target_types: &Vec<PokemonType>,
     move_details: &MoveData,
-    type_chart: Option<&HashMap<PokemonType, HashMap<PokemonType, f32>>>
-) -> (u32, f32, bool) {
+    context: &DamageContext,
+) -> DamageBreakdown {
+    let DamageContext { type_chart, target_side, rules, target_ability, weather } = *context;
     // Get base power (already checked for Some in caller)
     let power = move_details.power.unwrap_or(0);
     if power == 0 {
-        return (0, 1.0, false);
+        return DamageBreakdown::no_damage(0);
     }
     
-    // Determine attack and defense stats based on move category
-    let (attack, defense) = match move_details.damage_class {
+    // Determine attack and defense stats based on move category, and whether a screen halves it
+    let (attack, defense, screen_up) = match move_details.damage_class {
         crate::monsters::move_manager::MoveCategory::Physical => (
             source_stats.attack,
-            target_stats.defense
+            target_stats.defense,
+            target_side.is_some_and(|side| side.reflect_turns > 0)
         ),
         crate::monsters::move_manager::MoveCategory::Special => (
             source_stats.special_attack,
-            target_stats.special_defense
+            target_stats.special_defense,
+            target_side.is_some_and(|side| side.light_screen_turns > 0)
         ),
-        _ => return (0, 1.0, false), // Status moves don't deal direct damage
+        _ => return DamageBreakdown::no_damage(power), // Status moves don't deal direct damage
     };
     
     // Calculate type effectiveness
@@ -50,30 +112,76 @@ pub fn calculate_damage(
     };
     
     // Determine if critical hit (simplified - 6.25% chance)
-    let is_critical = rand::thread_rng().gen_bool(0.0625);
+    let is_critical = !rules.disable_critical_hits && with_rng(|rng| rng.gen_bool(0.0625));
     let critical_mod = if is_critical { 1.5 } else { 1.0 };
     
-    // Random factor (between 0.85 and 1.0)
-    let random_factor = rand::thread_rng().gen_range(0.85..=1.0);
+    // Random factor (one of 16 rolls between 85% and 100%)
+    let random_roll = if rules.disable_random_roll {
+        100
+    } else {
+        with_rng(|rng| rng.gen_range(MIN_RANDOM_ROLL..=100))
+    };
+    let random_factor = random_roll as f32 / 100.0;
+
+    // Screens halve damage, but critical hits ignore them
+    let screen_mod = if screen_up && !is_critical { 0.5 } else { 1.0 };
+
+    // The target's ability may make it immune to the move's type or soften the hit
+    let ability_mod = match target_ability {
+        Some(ability) if ability.is_immune_to_type(move_details.move_type) => 0.0,
+        Some(ability) => ability.damage_taken_multiplier(move_details.move_type),
+        None => 1.0,
+    };
+
+    // Rain and harsh sunlight boost or weaken Water and Fire moves
+    let weather_mod = weather::damage_multiplier(weather, move_details.move_type);
     
     // Calculate final damage using the formula:
     // Damage = (((2 * Level / 5 + 2) * Power * A/D) / 50 + 2) * Modifier
     let base_damage = (((2.0 * source_level as f32 / 5.0 + 2.0) * power as f32 * attack as f32 / defense as f32) / 50.0 + 2.0);
     
-    // Apply modifiers: STAB, Type effectiveness, Critical, Random
-    let modifier = stab * type_effectiveness * critical_mod * random_factor;
+    // Apply modifiers: STAB, Type effectiveness, Critical, Random, Screens, Ability, Weather
+    let modifier = stab * type_effectiveness * critical_mod * random_factor * screen_mod * ability_mod * weather_mod;
     
     // Calculate final damage (round down)
     let final_damage = (base_damage * modifier).floor() as u32;
     
     // For zero effectiveness, ensure damage is 0
-    let damage = if type_effectiveness == 0.0 { 0 } else { final_damage };
+    let damage = if type_effectiveness == 0.0 || ability_mod == 0.0 { 0 } else { final_damage };
     
-    (damage, type_effectiveness, is_critical)
+    DamageBreakdown {
+        damage,
+        base_power: power,
+        stab,
+        type_multiplier: type_effectiveness,
+        is_critical,
+        critical_multiplier: critical_mod,
+        random_roll,
+        random_roll_bucket: random_roll - MIN_RANDOM_ROLL,
+        screen_multiplier: screen_mod,
+        ability_multiplier: ability_mod,
+        weather_multiplier: weather_mod,
+    }
+}
+
+/// Power of a move used as a super move, scaled up from its base power
+pub fn super_move_power(base_power: u32) -> u32 {
+    match base_power {
+        0..=55 => 100,
+        56..=65 => 120,
+        66..=75 => 140,
+        76..=85 => 160,
+        86..=95 => 175,
+        96..=100 => 180,
+        101..=110 => 185,
+        111..=125 => 190,
+        126..=130 => 195,
+        _ => 200,
+    }
 }
 
 /// Calculate type effectiveness based on the type chart
-fn calculate_type_effectiveness(
+pub fn calculate_type_effectiveness(
     type_chart: Option<&HashMap<PokemonType, HashMap<PokemonType, f32>>>,
     attack_type: &PokemonType,
     defender_types: &Vec<PokemonType>
@@ -1,9 +1,14 @@
-use crate::combat::state::{WildBattleState, BattleEvent, BattleEntityRef, StatusCondition};
-use crate::stats::StatName;
+use crate::combat::logic::abilities::blocks_status;
+use crate::combat::logic::core::BattleSides;
+use crate::combat::logic::status::initial_status_turns;
+use crate::combat::logic::switching::{hazard_for_field_effect, set_hazard};
+use crate::combat::logic::weather::{start_weather, weather_for_field_effect, WEATHER_TURNS};
+use crate::combat::state::{AnimationHint, BattleEvent, BattleEntityRef, StatusCondition};
+use crate::stats::{CalculatedStats, StatName};
 
 /// Helper function to apply move effects
-pub fn apply_effect(
-    battle_state: &mut WildBattleState,
+pub fn apply_effect<S: BattleSides>(
+    battle_state: &mut S,
     battle_events: &mut Vec<BattleEvent>,
     effect: &crate::monsters::move_manager::EffectData,
     source: BattleEntityRef,
@@ -16,38 +21,28 @@ pub fn apply_effect(
                 crate::monsters::move_manager::EffectTarget::Target => target.clone(),
             };
             
-            // Get target Pokémon name
-            let target_name = match actual_target {
-                BattleEntityRef::Player { team_index } => battle_state.player.team[team_index].name.clone(),
-                BattleEntityRef::Wild => battle_state.wild_pokemon.name.clone(),
-                _ => {
-                    panic!("Invalid target entity for move");
+            if blocks_status(battle_state, battle_events, &actual_target, *status) {
+                if let Some(pokemon) = battle_state.pokemon(&actual_target) {
+                    battle_events.push(BattleEvent::GenericMessage {
+                        message: format!("It doesn't affect {}...", pokemon.name)
+                    });
                 }
+                return;
+            }
+
+            let pokemon = match battle_state.pokemon_mut(&actual_target) {
+                Some(pokemon) => pokemon,
+                None => panic!("Invalid target entity for move"),
             };
+            let target_name = pokemon.name.clone();
             
             // Apply status condition
-            let status_applied = match actual_target {
-                BattleEntityRef::Player { team_index } => {
-                    let pokemon = &mut battle_state.player.team[team_index];
-                    if pokemon.status.is_none() {
-                        pokemon.status = Some(*status);
-                        true
-                    } else {
-                        false
-                    }
-                },
-                BattleEntityRef::Wild => {
-                    let pokemon = &mut battle_state.wild_pokemon;
-                    if pokemon.status.is_none() {
-                        pokemon.status = Some(*status);
-                        true
-                    } else {
-                        false
-                    }
-                },
-                _ => {
-                    panic!("Invalid target entity for move");
-                }
+            let status_applied = if pokemon.status.is_none() {
+                pokemon.status = Some(*status);
+                pokemon.status_turns = initial_status_turns(pokemon.status);
+                true
+            } else {
+                false
             };
             
             if status_applied {
@@ -80,13 +75,9 @@ pub fn apply_effect(
                 crate::monsters::move_manager::EffectTarget::Target => target.clone(),
             };
             
-            // Get target Pokémon name
-            let target_name = match actual_target {
-                BattleEntityRef::Player { team_index } => battle_state.player.team[team_index].name.clone(),
-                BattleEntityRef::Wild => battle_state.wild_pokemon.name.clone(),
-                _ => {
-                    panic!("Invalid target entity for move");
-                }
+            let target_name = match battle_state.pokemon(&actual_target) {
+                Some(pokemon) => pokemon.name.clone(),
+                None => panic!("Invalid target entity for move"),
             };
             
             // Apply stat changes
@@ -105,64 +96,35 @@ pub fn apply_effect(
                 let stages = change.stages;
                 let success = true; // Simplification - in reality there might be abilities that prevent stat changes
                 
-                let (new_stage, at_limit) = match actual_target {
-                    BattleEntityRef::Player { team_index } => {
-                        let pokemon = &mut battle_state.player.team[team_index];
-                        let current = match stat_name {
-                            StatName::Attack => pokemon.stat_modifiers.battle_stats.attack,
-                            StatName::Defense => pokemon.stat_modifiers.battle_stats.defense,
-                            StatName::SpecialAttack => pokemon.stat_modifiers.battle_stats.special_attack,
-                            StatName::SpecialDefense => pokemon.stat_modifiers.battle_stats.special_defense,
-                            StatName::Speed => pokemon.stat_modifiers.battle_stats.speed,
-                            StatName::Accuracy => pokemon.stat_modifiers.accuracy,
-                            StatName::Evasion => pokemon.stat_modifiers.evasion,
-                        };
-                        let new_stage = (current + stages).clamp(-6, 6);
-                        let at_limit = new_stage == current;
-                        
-                        // Update the stat
-                        match stat_name {
-                            StatName::Attack => pokemon.stat_modifiers.battle_stats.attack = new_stage,
-                            StatName::Defense => pokemon.stat_modifiers.battle_stats.defense = new_stage,
-                            StatName::SpecialAttack => pokemon.stat_modifiers.battle_stats.special_attack = new_stage,
-                            StatName::SpecialDefense => pokemon.stat_modifiers.battle_stats.special_defense = new_stage,
-                            StatName::Speed => pokemon.stat_modifiers.battle_stats.speed = new_stage,
-                            StatName::Accuracy => pokemon.stat_modifiers.accuracy = new_stage,
-                            StatName::Evasion => pokemon.stat_modifiers.evasion = new_stage,
-                        };
-                        
-                        (new_stage, at_limit)
-                    },
-                    BattleEntityRef::Wild => {
-                        let pokemon = &mut battle_state.wild_pokemon;
-                        let current = match stat_name {
-                            StatName::Attack => pokemon.stat_modifiers.battle_stats.attack,
-                            StatName::Defense => pokemon.stat_modifiers.battle_stats.defense,
-                            StatName::SpecialAttack => pokemon.stat_modifiers.battle_stats.special_attack,
-                            StatName::SpecialDefense => pokemon.stat_modifiers.battle_stats.special_defense,
-                            StatName::Speed => pokemon.stat_modifiers.battle_stats.speed,
-                            StatName::Accuracy => pokemon.stat_modifiers.accuracy,
-                            StatName::Evasion => pokemon.stat_modifiers.evasion,
-                        };
-                        let new_stage = (current + stages).clamp(-6, 6);
-                        let at_limit = new_stage == current;
-                        
-                        // Update the stat
-                        match stat_name {
-                            StatName::Attack => pokemon.stat_modifiers.battle_stats.attack = new_stage,
-                            StatName::Defense => pokemon.stat_modifiers.battle_stats.defense = new_stage,
-                            StatName::SpecialAttack => pokemon.stat_modifiers.battle_stats.special_attack = new_stage,
-                            StatName::SpecialDefense => pokemon.stat_modifiers.battle_stats.special_defense = new_stage,
-                            StatName::Speed => pokemon.stat_modifiers.battle_stats.speed = new_stage,
-                            StatName::Accuracy => pokemon.stat_modifiers.accuracy = new_stage,
-                            StatName::Evasion => pokemon.stat_modifiers.evasion = new_stage,
-                        };
-                        
-                        (new_stage, at_limit)
-                    },
-                    _ => {
-                        panic!("Invalid target entity for move");
-                    }
+                let (new_stage, at_limit) = {
+                    let pokemon = match battle_state.pokemon_mut(&actual_target) {
+                        Some(pokemon) => pokemon,
+                        None => panic!("Invalid target entity for move"),
+                    };
+                    let current = match stat_name {
+                        StatName::Attack => pokemon.stat_modifiers.battle_stats.attack,
+                        StatName::Defense => pokemon.stat_modifiers.battle_stats.defense,
+                        StatName::SpecialAttack => pokemon.stat_modifiers.battle_stats.special_attack,
+                        StatName::SpecialDefense => pokemon.stat_modifiers.battle_stats.special_defense,
+                        StatName::Speed => pokemon.stat_modifiers.battle_stats.speed,
+                        StatName::Accuracy => pokemon.stat_modifiers.accuracy,
+                        StatName::Evasion => pokemon.stat_modifiers.evasion,
+                    };
+                    let new_stage = (current + stages).clamp(-6, 6);
+                    let at_limit = new_stage == current;
+                    
+                    // Update the stat
+                    match stat_name {
+                        StatName::Attack => pokemon.stat_modifiers.battle_stats.attack = new_stage,
+                        StatName::Defense => pokemon.stat_modifiers.battle_stats.defense = new_stage,
+                        StatName::SpecialAttack => pokemon.stat_modifiers.battle_stats.special_attack = new_stage,
+                        StatName::SpecialDefense => pokemon.stat_modifiers.battle_stats.special_defense = new_stage,
+                        StatName::Speed => pokemon.stat_modifiers.battle_stats.speed = new_stage,
+                        StatName::Accuracy => pokemon.stat_modifiers.accuracy = new_stage,
+                        StatName::Evasion => pokemon.stat_modifiers.evasion = new_stage,
+                    };
+                    
+                    (new_stage, at_limit)
                 };
                 
                 if !at_limit {
@@ -209,6 +171,15 @@ pub fn apply_effect(
                 }
             }
         },
+        crate::monsters::move_manager::EffectData::ApplyFieldEffect { effect_type, duration, .. } if weather_for_field_effect(*effect_type).is_some() => {
+            let weather_type = weather_for_field_effect(*effect_type).unwrap();
+            if !start_weather(battle_state, battle_events, weather_type, duration.unwrap_or(WEATHER_TURNS)) {
+                battle_events.push(BattleEvent::GenericMessage { message: "But it failed!".to_string() });
+            }
+        },
+        crate::monsters::move_manager::EffectData::ApplyFieldEffect { effect_type, .. } if hazard_for_field_effect(*effect_type).is_some() => {
+            set_hazard(battle_state, battle_events, &target, hazard_for_field_effect(*effect_type).unwrap());
+        },
         crate::monsters::move_manager::EffectData::Heal { .. } => {
             battle_events.push(BattleEvent::GenericMessage { 
                 message: "Healing effect not fully implemented yet.".to_string() 
@@ -223,43 +194,71 @@ pub fn apply_effect(
 }
 
 /// Apply damage with proper effectiveness and critical hit information
-pub fn apply_damage_with_effectiveness(
-    battle_state: &mut WildBattleState,
+pub fn apply_damage_with_effectiveness<S: BattleSides>(
+    battle_state: &mut S,
     battle_events: &mut Vec<BattleEvent>,
     target: BattleEntityRef,
     damage: u32,
     effectiveness: f32,
     is_critical: bool
 ) {
-    match target {
-        BattleEntityRef::Player { team_index } => {
-            let pokemon = &mut battle_state.player.team[team_index];
-            let new_hp = pokemon.current_hp.saturating_sub(damage);
-            pokemon.current_hp = new_hp;
-            battle_events.push(BattleEvent::DamageDealt { 
-                target: target.clone(), 
-                damage, 
-                new_hp: pokemon.current_hp, 
-                max_hp: pokemon.max_hp, 
-                effectiveness, 
-                is_critical 
-            });
-        }
-        BattleEntityRef::Wild => {
-            let pokemon = &mut battle_state.wild_pokemon;
-            let new_hp = pokemon.current_hp.saturating_sub(damage);
-            pokemon.current_hp = new_hp;
-            battle_events.push(BattleEvent::DamageDealt { 
-                target: target.clone(), 
-                damage, 
-                new_hp: pokemon.current_hp, 
-                max_hp: pokemon.max_hp, 
-                effectiveness, 
-                is_critical 
-            });
-        },
-        _ => {
-            panic!("Invalid target entity for move");
+    apply_move_damage(battle_state, battle_events, target, damage, effectiveness, is_critical, None);
+}
+
+/// Apply damage dealt by a move, telling the client how to animate the hit
+pub fn apply_move_damage<S: BattleSides>(
+    battle_state: &mut S,
+    battle_events: &mut Vec<BattleEvent>,
+    target: BattleEntityRef,
+    damage: u32,
+    effectiveness: f32,
+    is_critical: bool,
+    animation: Option<AnimationHint>,
+) {
+    let pokemon = match battle_state.pokemon_mut(&target) {
+        Some(pokemon) => pokemon,
+        None => panic!("Invalid target entity for move"),
+    };
+    pokemon.current_hp = pokemon.current_hp.saturating_sub(damage);
+    battle_events.push(BattleEvent::DamageDealt { 
+        target: target.clone(), 
+        damage, 
+        new_hp: pokemon.current_hp, 
+        max_hp: pokemon.max_hp, 
+        effectiveness, 
+        is_critical,
+        animation,
+    });
+}
+
+/// Change the active Pokémon with weather forms into the form matching the current battle
+/// weather, or back into their own form once it no longer applies. Max HP never changes.
+pub fn update_weather_forms<S: BattleSides>(battle_state: &mut S, battle_events: &mut Vec<BattleEvent>) {
+    let weather = battle_state.weather();
+    for entity in battle_state.active_entities() {
+        let Some(pokemon) = battle_state.pokemon_mut(&entity) else {
+            continue;
+        };
+        if pokemon.is_fainted {
+            continue;
         }
+        let target_form = pokemon.weather_forms.iter()
+            .find(|form| weather.is_some() && form.weather == weather)
+            .or_else(|| pokemon.weather_forms.iter().find(|form| form.weather.is_none()));
+        let Some(target_form) = target_form.filter(|form| form.form != pokemon.form).cloned() else {
+            continue;
+        };
+
+        pokemon.form = target_form.form.clone();
+        pokemon.pokemon_types = target_form.types.clone();
+        pokemon.calculated_stats = CalculatedStats { hp: pokemon.calculated_stats.hp, ..target_form.stats };
+        battle_events.push(BattleEvent::GenericMessage {
+            message: format!("{} transformed!", pokemon.name),
+        });
+        battle_events.push(BattleEvent::FormChanged {
+            target: entity,
+            form: target_form.form,
+            types: target_form.types,
+        });
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,580 @@
+use crate::combat::logic::battle_calculations::{calculate_damage, super_move_power, DamageContext};
+use crate::combat::logic::abilities::announce_ability;
+use crate::combat::logic::battle_effects::{apply_damage_with_effectiveness, apply_effect, apply_move_damage};
+use crate::combat::logic::weather;
+use crate::combat::rng::with_rng;
+use crate::combat::state::{
+    AnimationHint, BattleEntityRef, BattleEvent, BattlePokemon, BattleRules, PlayerSideState, PvPBattleState, WeatherState, WeatherType, WildBattleState,
+};
+use crate::monsters::ability_manager::AbilityRepository;
+use crate::monsters::move_manager::{EffectData, MoveCategory, MoveData, MoveRepository, TargetType};
+use crate::monsters::PokemonType;
+use crate::stats::StatName;
+use rand::Rng;
+
+/// Move ID reported for Struggle
+pub const STRUGGLE_MOVE_ID: u32 = 165;
+
+/// Access to the Pokémon of a battle, so wild and PvP battles share one move execution path
+pub trait BattleSides {
+    fn pokemon(&self, entity: &BattleEntityRef) -> Option<&BattlePokemon>;
+    fn pokemon_mut(&mut self, entity: &BattleEntityRef) -> Option<&mut BattlePokemon>;
+    /// The active Pokémon a move from `entity` hits
+    fn opponent_of(&self, entity: &BattleEntityRef) -> Option<BattleEntityRef>;
+    /// Screens and other side conditions protecting `entity`, if it belongs to a trainer
+    fn side_of(&self, entity: &BattleEntityRef) -> Option<&PlayerSideState>;
+    fn side_of_mut(&mut self, entity: &BattleEntityRef) -> Option<&mut PlayerSideState>;
+    fn move_repository(&self) -> Option<&MoveRepository>;
+    fn ability_repository(&self) -> Option<&AbilityRepository>;
+    fn rules(&self) -> &BattleRules;
+    /// The Pokémon currently on the field, one per side
+    fn active_entities(&self) -> Vec<BattleEntityRef>;
+    fn weather(&self) -> Option<WeatherType>;
+    fn weather_state_mut(&mut self) -> &mut Option<WeatherState>;
+}
+
+impl BattleSides for WildBattleState {
+    fn pokemon(&self, entity: &BattleEntityRef) -> Option<&BattlePokemon> {
+        match entity {
+            BattleEntityRef::Player { team_index } => self.player.team.get(*team_index),
+            BattleEntityRef::Wild => Some(&self.wild_pokemon),
+            _ => None,
+        }
+    }
+
+    fn pokemon_mut(&mut self, entity: &BattleEntityRef) -> Option<&mut BattlePokemon> {
+        match entity {
+            BattleEntityRef::Player { team_index } => self.player.team.get_mut(*team_index),
+            BattleEntityRef::Wild => Some(&mut self.wild_pokemon),
+            _ => None,
+        }
+    }
+
+    fn opponent_of(&self, entity: &BattleEntityRef) -> Option<BattleEntityRef> {
+        match entity {
+            BattleEntityRef::Player { .. } => Some(BattleEntityRef::Wild),
+            BattleEntityRef::Wild => Some(BattleEntityRef::Player { team_index: self.player.active_pokemon_index }),
+            _ => None,
+        }
+    }
+
+    fn side_of(&self, entity: &BattleEntityRef) -> Option<&PlayerSideState> {
+        match entity {
+            BattleEntityRef::Player { .. } => Some(&self.player.side_effects),
+            _ => None,
+        }
+    }
+
+    fn side_of_mut(&mut self, entity: &BattleEntityRef) -> Option<&mut PlayerSideState> {
+        match entity {
+            BattleEntityRef::Player { .. } => Some(&mut self.player.side_effects),
+            _ => None,
+        }
+    }
+
+    fn move_repository(&self) -> Option<&MoveRepository> {
+        self.move_repository.as_deref()
+    }
+
+    fn ability_repository(&self) -> Option<&AbilityRepository> {
+        self.ability_repository.as_deref()
+    }
+
+    fn rules(&self) -> &BattleRules {
+        &self.field_state.rules
+    }
+
+    fn active_entities(&self) -> Vec<BattleEntityRef> {
+        vec![BattleEntityRef::Player { team_index: self.player.active_pokemon_index }, BattleEntityRef::Wild]
+    }
+
+    fn weather(&self) -> Option<WeatherType> {
+        self.field_state.weather.as_ref().map(|weather| weather.weather_type)
+    }
+
+    fn weather_state_mut(&mut self) -> &mut Option<WeatherState> {
+        &mut self.field_state.weather
+    }
+}
+
+impl BattleSides for PvPBattleState {
+    fn pokemon(&self, entity: &BattleEntityRef) -> Option<&BattlePokemon> {
+        match entity {
+            BattleEntityRef::Player1 { team_index } => self.player1.team.get(*team_index),
+            BattleEntityRef::Player2 { team_index } => self.player2.team.get(*team_index),
+            _ => None,
+        }
+    }
+
+    fn pokemon_mut(&mut self, entity: &BattleEntityRef) -> Option<&mut BattlePokemon> {
+        match entity {
+            BattleEntityRef::Player1 { team_index } => self.player1.team.get_mut(*team_index),
+            BattleEntityRef::Player2 { team_index } => self.player2.team.get_mut(*team_index),
+            _ => None,
+        }
+    }
+
+    fn opponent_of(&self, entity: &BattleEntityRef) -> Option<BattleEntityRef> {
+        match entity {
+            BattleEntityRef::Player1 { .. } => Some(BattleEntityRef::Player2 { team_index: self.player2.active_pokemon_index }),
+            BattleEntityRef::Player2 { .. } => Some(BattleEntityRef::Player1 { team_index: self.player1.active_pokemon_index }),
+            _ => None,
+        }
+    }
+
+    fn side_of(&self, entity: &BattleEntityRef) -> Option<&PlayerSideState> {
+        match entity {
+            BattleEntityRef::Player1 { .. } => Some(&self.player1.side_effects),
+            BattleEntityRef::Player2 { .. } => Some(&self.player2.side_effects),
+            _ => None,
+        }
+    }
+
+    fn side_of_mut(&mut self, entity: &BattleEntityRef) -> Option<&mut PlayerSideState> {
+        match entity {
+            BattleEntityRef::Player1 { .. } => Some(&mut self.player1.side_effects),
+            BattleEntityRef::Player2 { .. } => Some(&mut self.player2.side_effects),
+            _ => None,
+        }
+    }
+
+    fn move_repository(&self) -> Option<&MoveRepository> {
+        self.move_repository.as_deref()
+    }
+
+    fn ability_repository(&self) -> Option<&AbilityRepository> {
+        self.ability_repository.as_deref()
+    }
+
+    fn rules(&self) -> &BattleRules {
+        &self.field_state.rules
+    }
+
+    fn active_entities(&self) -> Vec<BattleEntityRef> {
+        vec![
+            BattleEntityRef::Player1 { team_index: self.player1.active_pokemon_index },
+            BattleEntityRef::Player2 { team_index: self.player2.active_pokemon_index },
+        ]
+    }
+
+    fn weather(&self) -> Option<WeatherType> {
+        self.field_state.weather.as_ref().map(|weather| weather.weather_type)
+    }
+
+    fn weather_state_mut(&mut self) -> &mut Option<WeatherState> {
+        &mut self.field_state.weather
+    }
+}
+
+/// Whether a Pokémon must Struggle because none of its moves have PP left
+pub fn must_struggle(pokemon: &BattlePokemon) -> bool {
+    pokemon.moves.iter().all(|mv| mv.current_pp == 0)
+}
+
+/// Check a move choice before accepting it. Choosing any move when every move is out of PP is
+/// allowed, since it turns into Struggle.
+pub fn validate_move_choice(pokemon: &BattlePokemon, move_index: usize) -> Result<(), String> {
+    let battle_move = pokemon.moves.get(move_index).ok_or_else(|| "Invalid move index".to_string())?;
+    if battle_move.current_pp == 0 && !must_struggle(pokemon) {
+        return Err("Move has no PP left".to_string());
+    }
+    Ok(())
+}
+
+/// Calculate and report the damage of `move_details` from `source` to `target`.
+/// Returns the damage, type multiplier and whether it was a critical hit.
+fn deal_move_damage<S: BattleSides>(
+    battle_state: &mut S,
+    battle_events: &mut Vec<BattleEvent>,
+    source: &BattleEntityRef,
+    target: &BattleEntityRef,
+    move_details: &MoveData,
+) -> (u32, f32, bool) {
+    let (Some(attacker), Some(defender)) = (battle_state.pokemon(source), battle_state.pokemon(target)) else {
+        return (0, 1.0, false);
+    };
+    let target_ability = battle_state.ability_repository().and_then(|repo| repo.get_ability(&defender.ability));
+    let breakdown = calculate_damage(
+        attacker.level,
+        &attacker.calculated_stats,
+        &attacker.pokemon_types,
+        &defender.calculated_stats,
+        &defender.pokemon_types,
+        move_details,
+        &DamageContext {
+            type_chart: battle_state.move_repository().map(|repo| &repo.type_chart),
+            target_side: battle_state.side_of(target),
+            rules: battle_state.rules(),
+            target_ability,
+            weather: battle_state.weather(),
+        },
+    );
+    // An ability that made the target immune counts as no effect, like a type immunity
+    let result = (breakdown.damage, breakdown.type_multiplier * breakdown.ability_multiplier, breakdown.is_critical);
+    if breakdown.ability_multiplier != 1.0 {
+        announce_ability(battle_state, battle_events, target);
+        if breakdown.ability_multiplier == 0.0 {
+            if let Some(defender) = battle_state.pokemon(target) {
+                battle_events.push(BattleEvent::GenericMessage { message: format!("It doesn't affect {}...", defender.name) });
+            }
+        }
+    }
+    battle_events.push(BattleEvent::DamageBreakdown { source: source.clone(), target: target.clone(), breakdown });
+    if result.0 > 0 {
+        let animation = Some(AnimationHint::for_move(move_details));
+        apply_move_damage(battle_state, battle_events, target.clone(), result.0, result.1, result.2, animation);
+    }
+    result
+}
+
+/// Roll whether a move from `source` hits `target`, from the move's accuracy under the current
+/// weather and the accuracy and evasion stages. Only weather-dependent moves that aim at the
+/// opponent can miss; see `weather::move_accuracy`.
+fn roll_hit<S: BattleSides>(battle_state: &S, source: &BattleEntityRef, target: &BattleEntityRef, move_details: &MoveData) -> bool {
+    if !matches!(move_details.target, TargetType::NormalOpponent | TargetType::AnyAdjacent | TargetType::AllAdjacentOpponents) {
+        return true;
+    }
+    let Some(accuracy) = weather::move_accuracy(battle_state.weather(), move_details) else {
+        return true;
+    };
+    let (Some(attacker), Some(defender)) = (battle_state.pokemon(source), battle_state.pokemon(target)) else {
+        return true;
+    };
+    let hit_chance = accuracy as f64 / 100.0
+        * attacker.stat_modifiers.get_multiplier(StatName::Accuracy) as f64
+        / defender.stat_modifiers.get_multiplier(StatName::Evasion) as f64;
+    hit_chance >= 1.0 || with_rng(|rng| rng.gen_bool(hit_chance.max(0.0)))
+}
+
+/// Executes a move, or Struggle if the Pokémon has no PP left in any move
+pub fn execute_move<S: BattleSides>(
+    battle_state: &mut S,
+    battle_events: &mut Vec<BattleEvent>,
+    source: BattleEntityRef,
+    move_index: usize,
+) {
+    use_move(battle_state, battle_events, source, move_index, false);
+}
+
+/// Executes a move as a super move, with its power boosted. The caller checks that the player
+/// may use one and marks it as used.
+pub fn execute_super_move<S: BattleSides>(
+    battle_state: &mut S,
+    battle_events: &mut Vec<BattleEvent>,
+    source: BattleEntityRef,
+    move_index: usize,
+) {
+    use_move(battle_state, battle_events, source, move_index, true);
+}
+
+fn use_move<S: BattleSides>(
+    battle_state: &mut S,
+    battle_events: &mut Vec<BattleEvent>,
+    source: BattleEntityRef,
+    move_index: usize,
+    super_move: bool,
+) {
+    let Some(pokemon) = battle_state.pokemon(&source) else {
+        battle_events.push(BattleEvent::GenericMessage { message: "Error: Invalid entity in move execution".to_string() });
+        return;
+    };
+    if must_struggle(pokemon) {
+        execute_struggle(battle_state, battle_events, source);
+        return;
+    }
+    let source_name = pokemon.name.clone();
+    let Some(move_id) = pokemon.moves.get(move_index).map(|mv| mv.move_id) else {
+        battle_events.push(BattleEvent::GenericMessage { message: format!("{} has no such move!", source_name) });
+        return;
+    };
+    let Some(target) = battle_state.opponent_of(&source) else {
+        return;
+    };
+
+    let mut move_details = battle_state.move_repository().and_then(|repo| repo.get_move(move_id)).cloned();
+    let move_name = move_details.as_ref()
+        .map(|details| details.name.clone())
+        .unwrap_or_else(|| format!("Move {}", move_id));
+    if super_move {
+        if let Some(details) = move_details.as_mut() {
+            let power = super_move_power(details.power.unwrap_or(0));
+            details.power = Some(power);
+            battle_events.push(BattleEvent::GenericMessage { message: format!("{} unleashed its crystal power!", source_name) });
+            battle_events.push(BattleEvent::SuperMoveUsed { source: source.clone(), move_id, power });
+        }
+    }
+    battle_events.push(BattleEvent::GenericMessage { message: format!("{} used {}!", source_name, move_name) });
+    let animation = move_details.as_ref().map(AnimationHint::for_move);
+
+    if let Some(mv) = battle_state.pokemon_mut(&source).and_then(|pokemon| pokemon.moves.get_mut(move_index)) {
+        mv.current_pp = mv.current_pp.saturating_sub(1);
+    }
+
+    let missed = move_details.as_ref().is_some_and(|details| !roll_hit(battle_state, &source, &target, details));
+    match move_details {
+        _ if missed => {
+            battle_events.push(BattleEvent::GenericMessage { message: format!("{}'s attack missed!", source_name) });
+            battle_events.push(BattleEvent::MoveFailed { source: source.clone(), reason: "missed".to_string() });
+        }
+        Some(move_details) if move_details.power.is_some() => {
+            let (damage, effectiveness, _) = deal_move_damage(battle_state, battle_events, &source, &target, &move_details);
+            if damage > 0 {
+                if let Some(secondary) = &move_details.secondary_effect {
+                    let roll = with_rng(|rng| rng.gen_range(1..=100));
+                    if roll <= secondary.chance {
+                        apply_effect(battle_state, battle_events, &secondary.effect, source.clone(), target.clone());
+                    }
+                }
+            } else if effectiveness != 0.0 {
+                // Immunity is already reported by the damage event
+                battle_events.push(BattleEvent::GenericMessage { message: "But it failed!".to_string() });
+            }
+        }
+        // Status move
+        Some(move_details) => apply_effect(battle_state, battle_events, &move_details.effect, source.clone(), target.clone()),
+        // Fallback to simple damage if move details not found
+        None => apply_damage_with_effectiveness(battle_state, battle_events, target.clone(), 10, 1.0, false),
+    }
+
+    battle_events.push(BattleEvent::MoveUsed { source, move_id, move_name, target, animation });
+}
+
+/// Executes Struggle: a typeless 50 power move that hurts the user for a quarter of the damage dealt
+pub fn execute_struggle<S: BattleSides>(
+    battle_state: &mut S,
+    battle_events: &mut Vec<BattleEvent>,
+    source: BattleEntityRef,
+) {
+    let (Some(source_name), Some(target)) = (
+        battle_state.pokemon(&source).map(|pokemon| pokemon.name.clone()),
+        battle_state.opponent_of(&source),
+    ) else {
+        battle_events.push(BattleEvent::GenericMessage { message: "Error: Invalid entity in Struggle".to_string() });
+        return;
+    };
+    battle_events.push(BattleEvent::GenericMessage { message: format!("{} used Struggle!", source_name) });
+
+    let struggle_move = MoveData {
+        id: STRUGGLE_MOVE_ID,
+        name: "Struggle".to_string(),
+        accuracy: Some(100),
+        power: Some(50),
+        pp: 1,
+        priority: 0,
+        move_type: PokemonType::Normal, // Typeless in effect, but Normal for calculation
+        damage_class: MoveCategory::Physical,
+        target: TargetType::NormalOpponent,
+        effect: EffectData::Damage {
+            multi_hit: None,
+            crit_stage_bonus: None,
+            drain_percent: None,
+            recoil_damage_percent: Some(25),
+        },
+        secondary_effect: None,
+        description: "Used only if all PP are gone. Hurts the user.".to_string(),
+        makes_contact: Some(true),
+    };
+
+    let (damage, _, is_critical) = deal_move_damage(battle_state, battle_events, &source, &target, &struggle_move);
+    if damage > 0 {
+        if is_critical {
+            battle_events.push(BattleEvent::GenericMessage { message: "A critical hit!".to_string() });
+        }
+        let recoil_damage = damage / 4;
+        if recoil_damage > 0 {
+            battle_events.push(BattleEvent::GenericMessage { message: format!("{} was damaged by recoil!", source_name) });
+            apply_damage_with_effectiveness(battle_state, battle_events, source.clone(), recoil_damage, 1.0, false);
+        }
+    }
+
+    battle_events.push(BattleEvent::MoveUsed {
+        source,
+        move_id: STRUGGLE_MOVE_ID,
+        move_name: "Struggle".to_string(),
+        target,
+        animation: Some(AnimationHint::for_move(&struggle_move)),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combat::rng::with_seed;
+    use crate::combat::state::BattleMove;
+    use crate::combat::test_support::{pvp_battle, repositories, wild_battle};
+    use crate::stats::nature::Nature;
+    use crate::stats::{BattleStatModifiers, StatSet};
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+    use serde_json::Value;
+    use std::collections::HashMap;
+
+    const SCRIPTS: u64 = 200;
+    const MAX_SCRIPT_STEPS: usize = 40;
+
+    /// Moves that play out the same whichever kind of battle they are used in. Side conditions
+    /// and forced switches depend on the wild Pokémon having no trainer, so they are left out.
+    fn shared_move_pool(move_repository: &MoveRepository) -> Vec<u32> {
+        let mut pool: Vec<u32> = move_repository.moves.values()
+            .filter(|move_data| !matches!(move_data.effect, EffectData::ApplyFieldEffect { .. } | EffectData::SwitchTarget {}))
+            .map(|move_data| move_data.id)
+            .collect();
+        pool.sort_unstable();
+        pool
+    }
+
+    fn random_pokemon(rng: &mut StdRng, name: &str, move_pool: &[u32], abilities: &[&String], is_wild: bool) -> BattlePokemon {
+        let mut stat = || rng.gen_range(20..=200);
+        let calculated_stats = StatSet { hp: stat() + 50, attack: stat(), defense: stat(), special_attack: stat(), special_defense: stat(), speed: stat() };
+        let types = [PokemonType::Normal, PokemonType::Fire, PokemonType::Water, PokemonType::Grass, PokemonType::Electric, PokemonType::Ground, PokemonType::Ghost];
+        let moves = move_pool.choose_multiple(rng, 4)
+            .map(|&move_id| BattleMove { move_id, current_pp: rng.gen_range(0..=3), max_pp: 10 })
+            .collect();
+        BattlePokemon {
+            template_id: 1,
+            name: name.to_string(),
+            level: rng.gen_range(5..=100),
+            pokemon_types: vec![*types.choose(rng).unwrap()],
+            ability: abilities.choose(rng).map(|ability| ability.to_string()).unwrap_or_default(),
+            moves,
+            instance_id: name.to_string(),
+            base_exp: 64,
+            exp: 0,
+            max_exp: 100,
+            current_hp: calculated_stats.hp,
+            max_hp: calculated_stats.hp,
+            calculated_stats,
+            ivs: StatSet::default(),
+            evs: StatSet::default(),
+            nature: Nature::Hardy,
+            status: None,
+            status_turns: 0,
+            volatile_statuses: HashMap::new(),
+            stat_modifiers: BattleStatModifiers::default(),
+            is_fainted: false,
+            position: 0,
+            is_wild,
+            form: None,
+            weather_forms: Vec::new(),
+            obedience_level: None,
+        }
+    }
+
+    /// Rewrite the wild battle's entity references to the PvP entities standing in for them
+    fn as_pvp_entities(value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                match map.get("entity_type").and_then(Value::as_str) {
+                    Some("player") => {
+                        map.insert("entity_type".to_string(), Value::from("player1"));
+                    }
+                    Some("wild") => {
+                        map.insert("entity_type".to_string(), Value::from("player2"));
+                        map.insert("team_index".to_string(), Value::from(0));
+                    }
+                    _ => {}
+                }
+                map.values_mut().for_each(as_pvp_entities);
+            }
+            Value::Array(values) => values.iter_mut().for_each(as_pvp_entities),
+            _ => {}
+        }
+    }
+
+    fn events_json(events: &[BattleEvent]) -> Value {
+        serde_json::to_value(events).expect("battle events serialize")
+    }
+
+    /// Replay random move scripts through a wild and a PvP battle with the same seeds, and check
+    /// both produce the same events with the wild entities mapped onto the PvP ones
+    #[test]
+    fn wild_and_pvp_move_execution_produce_the_same_events() {
+        let repositories = repositories();
+        let move_pool = shared_move_pool(&repositories.0);
+        let mut abilities: Vec<&String> = repositories.1.abilities.keys().collect();
+        abilities.sort();
+
+        for script_seed in 0..SCRIPTS {
+            let mut script_rng = StdRng::seed_from_u64(script_seed);
+            let player = random_pokemon(&mut script_rng, "Player Mon", &move_pool, &abilities, false);
+            let wild = random_pokemon(&mut script_rng, "Wild Mon", &move_pool, &abilities, true);
+            let mut wild_state = wild_battle(player.clone(), wild.clone(), &repositories);
+            let mut pvp_state = pvp_battle(player, wild, &repositories);
+
+            for step in 0..MAX_SCRIPT_STEPS {
+                let player_acts = script_rng.gen_bool(0.5);
+                let move_index = script_rng.gen_range(0..4);
+                let turn_seed = script_seed * 1000 + step as u64;
+                let (wild_source, pvp_source) = if player_acts {
+                    (BattleEntityRef::Player { team_index: 0 }, BattleEntityRef::Player1 { team_index: 0 })
+                } else {
+                    (BattleEntityRef::Wild, BattleEntityRef::Player2 { team_index: 0 })
+                };
+
+                let mut wild_events = Vec::new();
+                with_seed(turn_seed, || execute_move(&mut wild_state, &mut wild_events, wild_source, move_index));
+                let mut pvp_events = Vec::new();
+                with_seed(turn_seed, || execute_move(&mut pvp_state, &mut pvp_events, pvp_source, move_index));
+
+                let mut wild_json = events_json(&wild_events);
+                as_pvp_entities(&mut wild_json);
+                assert_eq!(wild_json, events_json(&pvp_events), "script {} diverged at step {}", script_seed, step);
+
+                if wild_state.player.team[0].is_fainted || wild_state.wild_pokemon.is_fainted {
+                    assert!(pvp_state.player1.team[0].is_fainted || pvp_state.player2.team[0].is_fainted);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Struggle goes through the same path in both battles
+    #[test]
+    fn wild_and_pvp_struggle_produce_the_same_events() {
+        let repositories = repositories();
+        let move_pool = shared_move_pool(&repositories.0);
+        let mut script_rng = StdRng::seed_from_u64(7);
+        let mut player = random_pokemon(&mut script_rng, "Player Mon", &move_pool, &[], false);
+        player.moves.iter_mut().for_each(|battle_move| battle_move.current_pp = 0);
+        let wild = random_pokemon(&mut script_rng, "Wild Mon", &move_pool, &[], true);
+        let mut wild_state = wild_battle(player.clone(), wild.clone(), &repositories);
+        let mut pvp_state = pvp_battle(player, wild, &repositories);
+
+        let mut wild_events = Vec::new();
+        with_seed(1, || execute_struggle(&mut wild_state, &mut wild_events, BattleEntityRef::Player { team_index: 0 }));
+        let mut pvp_events = Vec::new();
+        with_seed(1, || execute_struggle(&mut pvp_state, &mut pvp_events, BattleEntityRef::Player1 { team_index: 0 }));
+
+        let mut wild_json = events_json(&wild_events);
+        as_pvp_entities(&mut wild_json);
+        assert_eq!(wild_json, events_json(&pvp_events));
+        assert!(wild_events.iter().any(|event| matches!(event, BattleEvent::MoveUsed { move_id: STRUGGLE_MOVE_ID, .. })));
+    }
+
+    /// Thunder misses in harsh sunlight but never in rain, and moves that don't depend on the
+    /// weather never miss at all
+    #[test]
+    fn only_weather_accuracy_makes_moves_miss() {
+        let repositories = repositories();
+        let move_pool = shared_move_pool(&repositories.0);
+        let mut script_rng = StdRng::seed_from_u64(3);
+        let player = random_pokemon(&mut script_rng, "Player Mon", &move_pool, &[], false);
+        let wild = random_pokemon(&mut script_rng, "Wild Mon", &move_pool, &[], true);
+        let mut battle_state = wild_battle(player, wild, &repositories);
+        let thunder = repositories.0.moves[&weather::THUNDER_MOVE_ID].clone();
+        let hydro_pump = repositories.0.moves.values().find(|move_data| move_data.name == "hydro-pump").unwrap().clone();
+        let (source, target) = (BattleEntityRef::Player { team_index: 0 }, BattleEntityRef::Wild);
+        let hits = |battle_state: &WildBattleState, move_data: &MoveData| {
+            (0..200).filter(|seed| with_seed(*seed, || roll_hit(battle_state, &source, &target, move_data))).count()
+        };
+
+        assert_eq!(hits(&battle_state, &hydro_pump), 200);
+        *battle_state.weather_state_mut() = Some(WeatherState { weather_type: WeatherType::Rain, turns_left: 5 });
+        assert_eq!(hits(&battle_state, &thunder), 200);
+        *battle_state.weather_state_mut() = Some(WeatherState { weather_type: WeatherType::HarshSunlight, turns_left: 5 });
+        let sun_hits = hits(&battle_state, &thunder);
+        assert!(sun_hits > 50 && sun_hits < 150, "Thunder hit {} of 200 times in sunlight", sun_hits);
+        assert_eq!(hits(&battle_state, &hydro_pump), 200);
+    }
+}
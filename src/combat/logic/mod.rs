@@ -2,6 +2,12 @@ pub mod wild_battle;
 pub mod pvp_battle;
 pub mod battle_calculations;
 pub mod battle_effects;
+pub mod core;
+pub mod abilities;
+pub mod status;
+pub mod weather;
+pub mod switching;
+pub mod obedience;
 
 // Re-export the main entry points
 pub use wild_battle::process_turn;
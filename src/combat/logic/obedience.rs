@@ -0,0 +1,215 @@
+use rand::Rng;
+
+use crate::combat::logic::battle_effects::apply_damage_with_effectiveness;
+use crate::combat::logic::core::BattleSides;
+use crate::combat::logic::status::initial_status_turns;
+use crate::combat::rng::with_rng;
+use crate::combat::state::{BattleEntityRef, BattleEvent, DisobedienceKind, PlayerAction, StatusCondition};
+
+/// Levels a traded Pokémon obeys below its new trainer's badge cap, until it learns to trust them
+pub const TRADED_OBEDIENCE_PENALTY: u32 = 10;
+/// Power of the typeless hit a disobedient Pokémon deals itself
+const SELF_HIT_POWER: u32 = 40;
+
+/// Highest level a Pokémon obeys at for a trainer whose badges allow `level_cap`
+pub fn obedience_level(level_cap: u32, traded: bool) -> u32 {
+    if traded {
+        level_cap.saturating_sub(TRADED_OBEDIENCE_PENALTY)
+    } else {
+        level_cap
+    }
+}
+
+/// Roll in 0..=255 scaled by the Pokémon's level plus the level it obeys at
+fn scaled_roll(level: u32, obedience_level: u32) -> u32 {
+    with_rng(|rng| rng.gen_range(0..=255u32)) * (level + obedience_level) / 256
+}
+
+fn loaf_message(name: &str) -> String {
+    match with_rng(|rng| rng.gen_range(0..4)) {
+        0 => format!("{} is loafing around!", name),
+        1 => format!("{} won't obey!", name),
+        2 => format!("{} turned away!", name),
+        _ => format!("{} pretended not to notice!", name),
+    }
+}
+
+/// Check whether the Pokémon `entity` follows `action`. Pokémon at or below the level they
+/// obey at always do; above it, the further above the more likely they ignore the order.
+/// A disobedient Pokémon may use another move at random, which replaces the action (and
+/// keeps a chosen super move unused), or fall asleep, hurt itself or loaf around, in which
+/// case None is returned and it does nothing else this turn.
+pub fn check_obedience<S: BattleSides>(
+    battle_state: &mut S,
+    battle_events: &mut Vec<BattleEvent>,
+    entity: &BattleEntityRef,
+    action: PlayerAction,
+) -> Option<PlayerAction> {
+    let move_index = match action {
+        PlayerAction::UseMove { move_index } | PlayerAction::UseSuperMove { move_index } => move_index,
+        _ => return Some(action),
+    };
+    let Some(pokemon) = battle_state.pokemon(entity) else {
+        return Some(action);
+    };
+    let Some(obedience_level) = pokemon.obedience_level.filter(|&obedience_level| pokemon.level > obedience_level) else {
+        return Some(action);
+    };
+    let level = pokemon.level;
+    if scaled_roll(level, obedience_level) < obedience_level {
+        return Some(action);
+    }
+    let name = pokemon.name.clone();
+
+    // Use another move at random
+    let other_moves: Vec<usize> = pokemon.moves.iter().enumerate()
+        .filter(|(index, battle_move)| *index != move_index && battle_move.current_pp > 0)
+        .map(|(index, _)| index)
+        .collect();
+    if !other_moves.is_empty() && scaled_roll(level, obedience_level) < obedience_level {
+        let other_move = other_moves[with_rng(|rng| rng.gen_range(0..other_moves.len()))];
+        battle_events.push(BattleEvent::GenericMessage { message: format!("{} won't obey!", name) });
+        battle_events.push(BattleEvent::Disobeyed { target: entity.clone(), behavior: DisobedienceKind::UsedOtherMove });
+        return Some(PlayerAction::UseMove { move_index: other_move });
+    }
+
+    let level_gap = (level - obedience_level) as i32;
+    let roll = with_rng(|rng| rng.gen_range(0..=255i32)) - level_gap;
+    let behavior = if roll < 0 && pokemon.status.is_none() {
+        DisobedienceKind::Napped
+    } else if roll < level_gap {
+        DisobedienceKind::HurtItself
+    } else {
+        DisobedienceKind::Loafed
+    };
+    battle_events.push(BattleEvent::Disobeyed { target: entity.clone(), behavior });
+    match behavior {
+        DisobedienceKind::Napped => {
+            if let Some(pokemon) = battle_state.pokemon_mut(entity) {
+                pokemon.status = Some(StatusCondition::Sleep);
+                pokemon.status_turns = initial_status_turns(pokemon.status);
+            }
+            battle_events.push(BattleEvent::GenericMessage { message: format!("{} began to nap!", name) });
+            battle_events.push(BattleEvent::StatusApplied { target: entity.clone(), status: StatusCondition::Sleep });
+        }
+        DisobedienceKind::HurtItself => {
+            let damage = self_hit_damage(battle_state, entity);
+            battle_events.push(BattleEvent::GenericMessage { message: format!("{} won't obey! It hurt itself in its confusion!", name) });
+            apply_damage_with_effectiveness(battle_state, battle_events, entity.clone(), damage, 1.0, false);
+        }
+        DisobedienceKind::Loafed | DisobedienceKind::UsedOtherMove => {
+            battle_events.push(BattleEvent::GenericMessage { message: loaf_message(&name) });
+        }
+    }
+    None
+}
+
+/// Damage of a typeless physical hit from a Pokémon on itself
+fn self_hit_damage<S: BattleSides>(battle_state: &S, entity: &BattleEntityRef) -> u32 {
+    let Some(pokemon) = battle_state.pokemon(entity) else {
+        return 0;
+    };
+    let attack = pokemon.calculated_stats.attack.max(1);
+    let defense = pokemon.calculated_stats.defense.max(1);
+    (2 * pokemon.level / 5 + 2) * SELF_HIT_POWER * attack / defense / 50 + 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combat::rng::with_seed;
+    use crate::combat::state::WildBattleState;
+    use crate::combat::test_support::{repositories, test_pokemon, wild_battle};
+    use crate::monsters::ability_manager::AbilityRepository;
+    use crate::monsters::move_manager::MoveRepository;
+    use crate::monsters::PokemonType;
+    use std::sync::Arc;
+
+    const PLAYER: BattleEntityRef = BattleEntityRef::Player { team_index: 0 };
+
+    /// A battle whose player Pokémon is level `level`, obeys up to `obedience_level` and knows `moves`
+    fn battle(repositories: &(Arc<MoveRepository>, Arc<AbilityRepository>), level: u32, obedience_level: Option<u32>, moves: &[u32]) -> WildBattleState {
+        let mut pokemon = test_pokemon("Ace", &[PokemonType::Normal], 200, moves);
+        pokemon.level = level;
+        pokemon.obedience_level = obedience_level;
+        wild_battle(pokemon, test_pokemon("Wild", &[PokemonType::Normal], 200, &[1]), repositories)
+    }
+
+    #[test]
+    fn traded_pokemon_obey_fewer_levels() {
+        assert_eq!(obedience_level(40, false), 40);
+        assert_eq!(obedience_level(40, true), 40 - TRADED_OBEDIENCE_PENALTY);
+        assert_eq!(obedience_level(5, true), 0);
+    }
+
+    #[test]
+    fn pokemon_at_or_below_the_cap_always_obey() {
+        let repositories = repositories();
+        for (level, obedience_level) in [(50, Some(50)), (30, Some(50)), (100, None)] {
+            for seed in 0..50 {
+                let mut battle_state = battle(&repositories, level, obedience_level, &[1, 2]);
+                let mut events = Vec::new();
+                let action = with_seed(seed, || check_obedience(&mut battle_state, &mut events, &PLAYER, PlayerAction::UseMove { move_index: 0 }));
+                assert_eq!(action, Some(PlayerAction::UseMove { move_index: 0 }));
+                assert!(events.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn disobedient_pokemon_nap_hurt_themselves_or_loaf() {
+        let repositories = repositories();
+        let mut seen = Vec::new();
+        for seed in 0..200 {
+            let mut battle_state = battle(&repositories, 100, Some(1), &[1]);
+            let mut events = Vec::new();
+            let action = with_seed(seed, || check_obedience(&mut battle_state, &mut events, &PLAYER, PlayerAction::UseMove { move_index: 0 }));
+            let Some(behavior) = events.iter().find_map(|event| match event {
+                BattleEvent::Disobeyed { behavior, .. } => Some(*behavior),
+                _ => None,
+            }) else {
+                continue;
+            };
+            assert_eq!(action, None);
+            let pokemon = &battle_state.player.team[0];
+            match behavior {
+                DisobedienceKind::Napped => assert_eq!(pokemon.status, Some(StatusCondition::Sleep)),
+                DisobedienceKind::HurtItself => assert!(pokemon.current_hp < 200),
+                DisobedienceKind::Loafed => assert_eq!((pokemon.current_hp, pokemon.status), (200, None)),
+                DisobedienceKind::UsedOtherMove => panic!("a Pokémon with one move cannot use another"),
+            }
+            if !seen.contains(&behavior) {
+                seen.push(behavior);
+            }
+        }
+        assert_eq!(seen.len(), 3, "saw only {:?}", seen);
+    }
+
+    #[test]
+    fn disobedient_pokemon_may_use_another_move_instead_of_a_super_move() {
+        let repositories = repositories();
+        let mut used_other_move = false;
+        for seed in 0..200 {
+            let mut battle_state = battle(&repositories, 100, Some(1), &[1, 2]);
+            let mut events = Vec::new();
+            let action = with_seed(seed, || check_obedience(&mut battle_state, &mut events, &PLAYER, PlayerAction::UseSuperMove { move_index: 0 }));
+            if events.iter().any(|event| matches!(event, BattleEvent::Disobeyed { behavior: DisobedienceKind::UsedOtherMove, .. })) {
+                assert_eq!(action, Some(PlayerAction::UseMove { move_index: 1 }));
+                used_other_move = true;
+            }
+        }
+        assert!(used_other_move);
+    }
+
+    #[test]
+    fn moves_without_pp_are_never_picked_instead() {
+        let repositories = repositories();
+        for seed in 0..200 {
+            let mut battle_state = battle(&repositories, 100, Some(1), &[1, 2]);
+            battle_state.player.team[0].moves[1].current_pp = 0;
+            let mut events = Vec::new();
+            let action = with_seed(seed, || check_obedience(&mut battle_state, &mut events, &PLAYER, PlayerAction::UseMove { move_index: 0 }));
+            assert_ne!(action, Some(PlayerAction::UseMove { move_index: 1 }));
+        }
+    }
+}
@@ -1,24 +1,34 @@
 use crate::combat::state::{
     BattleEntityRef, BattleEvent, BattlePokemon, BattlePokemonPublicView, BattlePvPPhase,
     PlayerAction, PvPBattleEndReason, PvPBattleState, PvPTurnOrder,
-    StatusCondition,
 };
 use crate::monsters::monster_manager::MonsterTemplateRepository;
 use crate::stats::StatName;
 use rand::Rng;
+use crate::combat::rng::with_rng;
 use tracing::info;
 
-use super::battle_calculations::calculate_damage;
+use super::abilities::{apply_end_of_turn_abilities, on_battle_start, on_switch_in};
+use super::battle_effects::update_weather_forms;
+use super::core::{execute_move, execute_super_move};
+use super::weather::apply_weather_end_of_turn;
+use super::status::{apply_status_damage, check_can_act, effective_speed};
+use super::obedience::check_obedience;
+use super::switching::{apply_entry_hazards, reset_on_switch_out};
 
 /// Processes a single turn of a PvP battle
 pub fn process_pvp_turn(battle_state: &mut PvPBattleState, monster_repository: &MonsterTemplateRepository) -> Vec<BattleEvent> {
     let mut battle_events = Vec::new();
 
-    // --- 1. Pre-action checks (e.g., checking if Pokémon can move due to sleep/paralysis) ---
+    // --- 1. Pre-action checks ---
+    // Sleep, freeze and paralysis are checked as each move is about to be used, in execute_pvp_action
     // Add turn start event
     battle_events.push(BattleEvent::TurnStart {
         turn_number: battle_state.turn_number,
     });
+    if battle_state.turn_number == 1 {
+        on_battle_start(battle_state, &mut battle_events);
+    }
 
     // --- 2. Determine Turn Order ---
     let player1_pokemon = &battle_state.player1.team[battle_state.player1.active_pokemon_index];
@@ -29,9 +39,9 @@ pub fn process_pvp_turn(battle_state: &mut PvPBattleState, monster_repository: &
         .player1_action
         .as_ref()
         .map_or("unknown", |action| match action {
-            PlayerAction::UseMove { .. } => "move",
+            PlayerAction::UseMove { .. } | PlayerAction::UseSuperMove { .. } => "move",
             PlayerAction::SwitchPokemon { .. } => "switch",
-            PlayerAction::UseItem { .. } => "item",
+            PlayerAction::UseItem { .. } | PlayerAction::PreBattleModifier { .. } => "item",
             PlayerAction::Run => "run",
         });
 
@@ -39,9 +49,9 @@ pub fn process_pvp_turn(battle_state: &mut PvPBattleState, monster_repository: &
         .player2_action
         .as_ref()
         .map_or("unknown", |action| match action {
-            PlayerAction::UseMove { .. } => "move",
+            PlayerAction::UseMove { .. } | PlayerAction::UseSuperMove { .. } => "move",
             PlayerAction::SwitchPokemon { .. } => "switch",
-            PlayerAction::UseItem { .. } => "item",
+            PlayerAction::UseItem { .. } | PlayerAction::PreBattleModifier { .. } => "item",
             PlayerAction::Run => "run",
         });
 
@@ -66,13 +76,13 @@ pub fn process_pvp_turn(battle_state: &mut PvPBattleState, monster_repository: &
     } else if player1_action_type == "move" && player2_action_type == "move" {
         // Determine based on move priority, then speed
         // For simplicity, just using speed for now
-        if player1_pokemon.calculated_stats.speed > player2_pokemon.calculated_stats.speed {
+        if effective_speed(player1_pokemon) > effective_speed(player2_pokemon) {
             PvPTurnOrder::Player1First
-        } else if player2_pokemon.calculated_stats.speed > player1_pokemon.calculated_stats.speed {
+        } else if effective_speed(player2_pokemon) > effective_speed(player1_pokemon) {
             PvPTurnOrder::Player2First
         } else {
             // Speed tie - random for now
-            if rand::random::<bool>() {
+            if with_rng(|rng| rng.gen_bool(0.5)) {
                 PvPTurnOrder::Player1First
             } else {
                 PvPTurnOrder::Player2First
@@ -85,7 +95,7 @@ pub fn process_pvp_turn(battle_state: &mut PvPBattleState, monster_repository: &
     } else {
         // Both using same category (both switching, both using items) or some unhandled case
         // Use speed as tiebreaker
-        if player1_pokemon.calculated_stats.speed >= player2_pokemon.calculated_stats.speed {
+        if effective_speed(player1_pokemon) >= effective_speed(player2_pokemon) {
             PvPTurnOrder::Player1First
         } else {
             PvPTurnOrder::Player2First
@@ -116,7 +126,7 @@ pub fn process_pvp_turn(battle_state: &mut PvPBattleState, monster_repository: &
                 check_pvp_faints(battle_state, &mut battle_events, monster_repository);
 
                 // If battle is not over and player2's active Pokemon is not fainted, process player2's action
-                if battle_state.battle_phase != BattlePvPPhase::Finished
+                if battle_state.battle_phase.get() != BattlePvPPhase::Finished
                     && !battle_state.player2.team[battle_state.player2.active_pokemon_index]
                         .is_fainted
                 {
@@ -151,7 +161,7 @@ pub fn process_pvp_turn(battle_state: &mut PvPBattleState, monster_repository: &
                 check_pvp_faints(battle_state, &mut battle_events, monster_repository);
 
                 // If battle is not over and player1's active Pokemon is not fainted, process player1's action
-                if battle_state.battle_phase != BattlePvPPhase::Finished
+                if battle_state.battle_phase.get() != BattlePvPPhase::Finished
                     && !battle_state.player1.team[battle_state.player1.active_pokemon_index]
                         .is_fainted
                 {
@@ -186,7 +196,7 @@ pub fn process_pvp_turn(battle_state: &mut PvPBattleState, monster_repository: &
     check_pvp_battle_end(battle_state);
 
     // --- 6. Prepare for next turn / state change ---
-    if battle_state.battle_phase == BattlePvPPhase::ProcessingTurn {
+    if battle_state.battle_phase.get() == BattlePvPPhase::ProcessingTurn {
         // Increment turn number if the battle continues
         battle_state.turn_number += 1;
 
@@ -212,40 +222,40 @@ pub fn process_pvp_turn(battle_state: &mut PvPBattleState, monster_repository: &
         if player1_active_fainted && player2_active_fainted {
             // Both need to switch
             if can_player1_switch && can_player2_switch {
-                battle_state.battle_phase = BattlePvPPhase::WaitingForBothPlayersActions;
+                battle_state.battle_phase.transition(BattlePvPPhase::WaitingForBothPlayersActions);
                 battle_state.player1.must_switch = true;
                 battle_state.player2.must_switch = true;
             } else if can_player1_switch {
                 // Only player 1 can switch, player 2 lost
-                battle_state.battle_phase = BattlePvPPhase::Finished;
+                battle_state.battle_phase.transition(BattlePvPPhase::Finished);
             } else if can_player2_switch {
                 // Only player 2 can switch, player 1 lost
-                battle_state.battle_phase = BattlePvPPhase::Finished;
+                battle_state.battle_phase.transition(BattlePvPPhase::Finished);
             } else {
                 // Neither can switch - should be a draw but handled by check_pvp_battle_end
-                battle_state.battle_phase = BattlePvPPhase::Finished;
+                battle_state.battle_phase.transition(BattlePvPPhase::Finished);
             }
         } else if player1_active_fainted {
             // Only player 1 needs to switch
             if can_player1_switch {
-                battle_state.battle_phase = BattlePvPPhase::WaitingForPlayer1Switch;
+                battle_state.battle_phase.transition(BattlePvPPhase::WaitingForPlayer1Switch);
                 battle_state.player1.must_switch = true;
             } else {
                 // Player 1 lost
-                battle_state.battle_phase = BattlePvPPhase::Finished;
+                battle_state.battle_phase.transition(BattlePvPPhase::Finished);
             }
         } else if player2_active_fainted {
             // Only player 2 needs to switch
             if can_player2_switch {
-                battle_state.battle_phase = BattlePvPPhase::WaitingForPlayer2Switch;
+                battle_state.battle_phase.transition(BattlePvPPhase::WaitingForPlayer2Switch);
                 battle_state.player2.must_switch = true;
             } else {
                 // Player 2 lost
-                battle_state.battle_phase = BattlePvPPhase::Finished;
+                battle_state.battle_phase.transition(BattlePvPPhase::Finished);
             }
         } else {
             // Normal progression to next turn
-            battle_state.battle_phase = BattlePvPPhase::WaitingForBothPlayersActions;
+            battle_state.battle_phase.transition(BattlePvPPhase::WaitingForBothPlayersActions);
         }
     }
 
@@ -265,9 +275,24 @@ fn execute_pvp_action(
     action: PlayerAction,
     is_first_action: bool,
 ) {
+    let uses_move = matches!(action, PlayerAction::UseMove { .. } | PlayerAction::UseSuperMove { .. });
+    if uses_move && !check_can_act(battle_state, battle_events, &source_entity) {
+        return;
+    }
+    let Some(action) = check_obedience(battle_state, battle_events, &source_entity, action) else {
+        return;
+    };
     match action {
         PlayerAction::UseMove { move_index } => {
-            execute_pvp_move(battle_state, battle_events, source_entity, move_index)
+            execute_move(battle_state, battle_events, source_entity, move_index)
+        }
+        PlayerAction::UseSuperMove { move_index } => {
+            match source_entity {
+                BattleEntityRef::Player1 { .. } => battle_state.player1.super_move_used = true,
+                BattleEntityRef::Player2 { .. } => battle_state.player2.super_move_used = true,
+                _ => {}
+            }
+            execute_super_move(battle_state, battle_events, source_entity, move_index)
         }
         PlayerAction::SwitchPokemon { team_index } => {
             execute_pvp_switch(battle_state, battle_events, source_entity, team_index)
@@ -283,137 +308,10 @@ fn execute_pvp_action(
             is_capture_item,
         ),
         PlayerAction::Run => execute_pvp_surrender(battle_state, battle_events, source_entity),
+        // Rejected when submitted; wild battles only
+        PlayerAction::PreBattleModifier { .. } => {}
     }
 }
-/// Execute a move in a PvP battle
-fn execute_pvp_move(
-    battle_state: &mut PvPBattleState,
-    battle_events: &mut Vec<BattleEvent>,
-    source: BattleEntityRef,
-    move_index: usize,
-) {
-    // Get source info first before any mutable borrows
-    let (source_name, move_data, source_level, source_stats, source_types) = match source {
-        BattleEntityRef::Player1 { team_index } => {
-            let pokemon = &battle_state.player1.team[team_index];
-            (
-                pokemon.name.clone(),
-                pokemon.moves.get(move_index).cloned(),
-                pokemon.level,
-                pokemon.calculated_stats.clone(),
-                pokemon.pokemon_types.clone(),
-            )
-        }
-        BattleEntityRef::Player2 { team_index } => {
-            let pokemon = &battle_state.player2.team[team_index];
-            (
-                pokemon.name.clone(),
-                pokemon.moves.get(move_index).cloned(),
-                pokemon.level,
-                pokemon.calculated_stats.clone(),
-                pokemon.pokemon_types.clone(),
-            )
-        }
-        _ => {
-            // Handle unexpected entity types (should never happen in PvP)
-            battle_events.push(BattleEvent::GenericMessage {
-                message: "Error: Invalid entity type in PvP move execution".to_string(),
-            });
-            return;
-        }
-    };
-
-    // Get target info
-    let (target, target_stats, target_types) = match source {
-        BattleEntityRef::Player1 { .. } => {
-            let target = BattleEntityRef::Player2 {
-                team_index: battle_state.player2.active_pokemon_index,
-            };
-            let pokemon = &battle_state.player2.team[battle_state.player2.active_pokemon_index];
-            (target, pokemon.calculated_stats.clone(), pokemon.pokemon_types.clone())
-        }
-        BattleEntityRef::Player2 { .. } => {
-            let target = BattleEntityRef::Player1 {
-                team_index: battle_state.player1.active_pokemon_index,
-            };
-            let pokemon = &battle_state.player1.team[battle_state.player1.active_pokemon_index];
-            (target, pokemon.calculated_stats.clone(), pokemon.pokemon_types.clone())
-        }
-        _ => {
-            // Handle unexpected entity types
-            battle_events.push(BattleEvent::GenericMessage {
-                message: "Error: Invalid entity type in PvP move execution".to_string(),
-            });
-            return;
-        }
-    };
-
-    if let Some(move_data) = move_data {
-        let move_id = move_data.move_id;
-        
-        if let Some(move_details) = battle_state
-            .move_repository
-            .as_ref()
-            .and_then(|repo| repo.get_move(move_id))
-        {
-            let move_name = move_details.name.clone();
-
-            // Add move used message
-            battle_events.push(BattleEvent::GenericMessage {
-                message: format!("{} used {}!", source_name, move_name),
-            });
-
-            // Decrement PP - now safe since we have no active borrows
-            match source {
-                BattleEntityRef::Player1 { team_index } => {
-                    if let Some(mv) = battle_state.player1.team[team_index]
-                        .moves
-                        .get_mut(move_index)
-                    {
-                        if mv.current_pp > 0 {
-                            mv.current_pp -= 1;
-                        }
-                    }
-                }
-                BattleEntityRef::Player2 { team_index } => {
-                    if let Some(mv) = battle_state.player2.team[team_index]
-                        .moves
-                        .get_mut(move_index)
-                    {
-                        if mv.current_pp > 0 {
-                            mv.current_pp -= 1;
-                        }
-                    }
-                }
-                _ => {} // Should not happen in PvP
-            }
-
-            // Calculate and apply damage
-            let type_chart = battle_state.move_repository.as_ref().map(|repo| &repo.type_chart);
-            
-            let (damage, effectiveness, is_critical) = calculate_damage(
-                source_level,
-                &source_stats,
-                &source_types,
-                &target_stats,
-                &target_types,
-                &move_details,
-                type_chart
-            );
-
-            apply_pvp_damage(battle_state, battle_events, target.clone(), damage, effectiveness, is_critical);
-
-            // Record move used event
-            battle_events.push(BattleEvent::MoveUsed {
-                source: source.clone(),
-                move_id,
-                move_name,
-                target: target.clone(), 
-            });
-        }
-    }
-}
-
 /// Execute a switch in a PvP battle
 fn execute_pvp_switch(
     battle_state: &mut PvPBattleState,
@@ -446,16 +344,16 @@ fn execute_pvp_switch(
         }
     };
 
-    // TODO: Implement proper switch logic (reset stats, entry hazards, abilities)
-
-    // Update active Pokémon index
+    // Clear the outgoing Pokémon's stat stages and volatile statuses, then update the active Pokémon index
     match source {
         BattleEntityRef::Player1 { .. } => {
+            reset_on_switch_out(battle_state, battle_events, &BattleEntityRef::Player1 { team_index: outgoing_index });
             battle_state.player1.active_pokemon_index = team_index;
             // Reset must_switch flag if it was set
             battle_state.player1.must_switch = false;
         }
         BattleEntityRef::Player2 { .. } => {
+            reset_on_switch_out(battle_state, battle_events, &BattleEntityRef::Player2 { team_index: outgoing_index });
             battle_state.player2.active_pokemon_index = team_index;
             // Reset must_switch flag if it was set
             battle_state.player2.must_switch = false;
@@ -482,6 +380,7 @@ fn execute_pvp_switch(
                 current_hp_percent: new_pokemon.current_hp as f32 / new_pokemon.max_hp as f32,
                 max_hp: new_pokemon.max_hp,
                 types: new_pokemon.pokemon_types.clone(),
+                form: new_pokemon.form.clone(),
                 status: new_pokemon.status.clone(),
                 stat_modifiers: new_pokemon.stat_modifiers.clone(),
                 is_fainted: new_pokemon.is_fainted,
@@ -501,6 +400,7 @@ fn execute_pvp_switch(
                 current_hp_percent: new_pokemon.current_hp as f32 / new_pokemon.max_hp as f32,
                 max_hp: new_pokemon.max_hp,
                 types: new_pokemon.pokemon_types.clone(),
+                form: new_pokemon.form.clone(),
                 status: new_pokemon.status.clone(),
                 stat_modifiers: new_pokemon.stat_modifiers.clone(),
                 is_fainted: new_pokemon.is_fainted,
@@ -513,6 +413,13 @@ fn execute_pvp_switch(
         }
         _ => {} // Should not happen
     }
+
+    let incoming = match source {
+        BattleEntityRef::Player1 { .. } => BattleEntityRef::Player1 { team_index },
+        _ => BattleEntityRef::Player2 { team_index },
+    };
+    apply_entry_hazards(battle_state, battle_events, &incoming);
+    on_switch_in(battle_state, battle_events, &incoming);
 }
 
 /// Execute item use in a PvP battle
@@ -651,7 +558,7 @@ fn execute_pvp_surrender(
     });
 
     // Set battle end state
-    battle_state.battle_phase = BattlePvPPhase::Finished;
+    battle_state.battle_phase.transition(BattlePvPPhase::Finished);
 
     // Store the end reason (Optional: Add a field to PvPBattleState for this)
     // battle_state.end_reason = Some(reason);
@@ -671,111 +578,17 @@ fn execute_pvp_surrender(
         _ => {} // Other reasons handled elsewhere
     }
 }
-/// Apply damage in a PvP battle
-fn apply_pvp_damage(
-    battle_state: &mut PvPBattleState,
-    battle_events: &mut Vec<BattleEvent>,
-    target: BattleEntityRef,
-    damage: u32,
-    effectiveness: f32,
-    is_critical: bool,
-) {
-    match target {
-        BattleEntityRef::Player1 { team_index } => {
-            let pokemon = &mut battle_state.player1.team[team_index];
-            let new_hp = pokemon.current_hp.saturating_sub(damage);
-            pokemon.current_hp = new_hp;
-
-            // Store values before pushing event to avoid borrowing issues
-            let current_hp = pokemon.current_hp;
-            let max_hp = pokemon.max_hp;
-
-            battle_events.push(BattleEvent::DamageDealt {
-                target: target.clone(),
-                damage,
-                new_hp: current_hp,
-                max_hp,
-                effectiveness,
-                is_critical,
-            });
-        }
-        BattleEntityRef::Player2 { team_index } => {
-            let pokemon = &mut battle_state.player2.team[team_index];
-            let new_hp = pokemon.current_hp.saturating_sub(damage);
-            pokemon.current_hp = new_hp;
-
-            // Store values before pushing event to avoid borrowing issues
-            let current_hp = pokemon.current_hp;
-            let max_hp = pokemon.max_hp;
-
-            battle_events.push(BattleEvent::DamageDealt {
-                target: target.clone(),
-                damage,
-                new_hp: current_hp,
-                max_hp,
-                effectiveness,
-                is_critical,
-            });
-        }
-        _ => {
-            // Handle unexpected entity types
-            battle_events.push(BattleEvent::GenericMessage {
-                message: "Error: Invalid entity type in damage application".to_string(),
-            });
-        }
-    }
-}
-
 /// Apply end of turn effects in a PvP battle
 fn apply_pvp_end_of_turn_effects(
     battle_state: &mut PvPBattleState,
     battle_events: &mut Vec<BattleEvent>,
 ) {
-    // TODO: Implement proper EOT logic (weather, status, volatile status, field effects)
-
-    // Player 1's active Pokémon
-    let player1_idx = battle_state.player1.active_pokemon_index;
-    let player1_target = BattleEntityRef::Player1 {
-        team_index: player1_idx,
-    };
-
-    // Check for burn damage
-    if battle_state.player1.team[player1_idx].status == Some(StatusCondition::Burn) {
-        let damage = battle_state.player1.team[player1_idx].max_hp / 16; // Simplified burn damage
-        if damage > 0 {
-            apply_pvp_damage(battle_state, battle_events, player1_target.clone(), damage, 1.0, false);
-
-            battle_events.push(BattleEvent::StatusDamage {
-                target: player1_target,
-                status: StatusCondition::Burn,
-                damage,
-                new_hp: battle_state.player1.team[player1_idx].current_hp,
-                max_hp: battle_state.player1.team[player1_idx].max_hp,
-            });
-        }
-    }
-
-    // Player 2's active Pokémon
-    let player2_idx = battle_state.player2.active_pokemon_index;
-    let player2_target = BattleEntityRef::Player2 {
-        team_index: player2_idx,
-    };
+    // TODO: Implement proper EOT logic (volatile status, field effects)
+    apply_weather_end_of_turn(battle_state, battle_events);
+    apply_status_damage(battle_state, battle_events);
+    apply_end_of_turn_abilities(battle_state, battle_events);
 
-    // Check for burn damage
-    if battle_state.player2.team[player2_idx].status == Some(StatusCondition::Burn) {
-        let damage = battle_state.player2.team[player2_idx].max_hp / 16; // Simplified burn damage
-        if damage > 0 {
-            apply_pvp_damage(battle_state, battle_events, player2_target.clone(), damage, 1.0, false);
-
-            battle_events.push(BattleEvent::StatusDamage {
-                target: player2_target,
-                status: StatusCondition::Burn,
-                damage,
-                new_hp: battle_state.player2.team[player2_idx].current_hp,
-                max_hp: battle_state.player2.team[player2_idx].max_hp,
-            });
-        }
-    }
+    update_weather_forms(battle_state, battle_events);
 }
 
 /// Check for fainted Pokémon in a PvP battle
@@ -837,7 +650,7 @@ fn check_pvp_faints(
         let player1_has_pokemon_left = battle_state.player1.team.iter().any(|p| !p.is_fainted);
         if !player1_has_pokemon_left {
             // Player 1 has no usable Pokémon left, player 2 wins
-            battle_state.battle_phase = BattlePvPPhase::Finished;
+            battle_state.battle_phase.transition(BattlePvPPhase::Finished);
             battle_events.push(BattleEvent::GenericMessage {
                 message: format!(
                     "{} has no usable Pokémon left! {} wins the battle!",
@@ -898,7 +711,7 @@ fn check_pvp_faints(
         let player2_has_pokemon_left = battle_state.player2.team.iter().any(|p| !p.is_fainted);
         if !player2_has_pokemon_left {
             // Player 2 has no usable Pokémon left, player 1 wins
-            battle_state.battle_phase = BattlePvPPhase::Finished;
+            battle_state.battle_phase.transition(BattlePvPPhase::Finished);
             battle_events.push(BattleEvent::GenericMessage {
                 message: format!(
                     "{} has no usable Pokémon left! {} wins the battle!",
@@ -928,14 +741,19 @@ fn level_up_battle_pokemon(pokemon: &mut BattlePokemon, exp_gained: u64, monster
         // Recalculate stats for new level
         let old_max_hp = pokemon.max_hp;
         
-        // Recalculate all stats using the existing stat calculation system
-        pokemon.calculated_stats = crate::stats::calculate_stats(
-            &template.base_stats,
-            pokemon.level,
-            &pokemon.ivs,
-            &pokemon.evs,
-            &pokemon.nature,
+        // Recalculate all stats using the existing stat calculation system, for every form it can take
+        let (level, ivs, evs, nature) = (pokemon.level, pokemon.ivs.clone(), pokemon.evs.clone(), pokemon.nature);
+        let stats_for_form = |form: Option<&str>| crate::stats::calculate_stats(
+            template.base_stats_for_form(form),
+            level,
+            &ivs,
+            &evs,
+            &nature,
         );
+        pokemon.calculated_stats = stats_for_form(pokemon.form.as_deref());
+        for weather_form in &mut pokemon.weather_forms {
+            weather_form.stats = stats_for_form(weather_form.form.as_deref());
+        }
         
         // Update max HP and heal the difference
         pokemon.max_hp = pokemon.calculated_stats.hp;
@@ -943,7 +761,7 @@ fn level_up_battle_pokemon(pokemon: &mut BattlePokemon, exp_gained: u64, monster
         pokemon.current_hp += hp_gained;
         
         // Update max exp for next level
-        pokemon.max_exp = (pokemon.base_exp as f64 * 1.2_f64.powf(pokemon.level as f64)) as u64;
+        pokemon.max_exp = monster_repository.get_exp_for_next_level(pokemon.template_id, pokemon.level);
     }
     
     levels_gained
@@ -975,14 +793,14 @@ fn check_pvp_battle_end(battle_state: &mut PvPBattleState)  {
     if all_player1_fainted && all_player2_fainted {
         info!("Both teams fainted - it's a draw");
         // Both teams fainted - it's a draw
-        battle_state.battle_phase = BattlePvPPhase::Finished;
+        battle_state.battle_phase.transition(BattlePvPPhase::Finished);
     } else if all_player1_fainted {
         info!("Player 1 has lost");
         // Player 1 has lost
-        battle_state.battle_phase = BattlePvPPhase::Finished;
+        battle_state.battle_phase.transition(BattlePvPPhase::Finished);
     } else if all_player2_fainted {
         info!("Player 2 has lost");
         // Player 2 has lost
-        battle_state.battle_phase = BattlePvPPhase::Finished;
+        battle_state.battle_phase.transition(BattlePvPPhase::Finished);
     }
 }
@@ -0,0 +1,283 @@
+use crate::combat::logic::battle_effects::apply_damage_with_effectiveness;
+use crate::combat::logic::core::BattleSides;
+use crate::combat::rng::with_rng;
+use crate::combat::state::{BattleEntityRef, BattleEvent, BattlePokemon, StatusCondition};
+use rand::Rng;
+
+/// Fewest and most turns a Pokémon sleeps for before waking up
+pub const SLEEP_MIN_TURNS: u8 = 1;
+pub const SLEEP_MAX_TURNS: u8 = 3;
+/// Chance a frozen Pokémon thaws out when it tries to act
+pub const FREEZE_THAW_CHANCE: f64 = 0.2;
+/// Chance a paralyzed Pokémon is fully paralyzed and cannot act
+pub const FULL_PARALYSIS_CHANCE: f64 = 0.25;
+/// Badly poisoned damage stops growing after this many sixteenths of max HP
+pub const TOXIC_MAX_STACKS: u8 = 15;
+
+/// Turn counter a Pokémon starts with when it gets `status`: the turns left asleep for
+/// sleep, and 0 for everything else
+pub fn initial_status_turns(status: Option<StatusCondition>) -> u8 {
+    match status {
+        Some(StatusCondition::Sleep) => with_rng(|rng| rng.gen_range(SLEEP_MIN_TURNS..=SLEEP_MAX_TURNS)),
+        _ => 0,
+    }
+}
+
+/// Speed used for turn order, halved while paralyzed
+pub fn effective_speed(pokemon: &BattlePokemon) -> u32 {
+    match pokemon.status {
+        Some(StatusCondition::Paralysis) => pokemon.calculated_stats.speed / 2,
+        _ => pokemon.calculated_stats.speed,
+    }
+}
+
+/// Badly poisoned damage builds back up from the start after switching out
+pub fn reset_status_on_switch_out(pokemon: &mut BattlePokemon) {
+    if pokemon.status == Some(StatusCondition::Toxic) {
+        pokemon.status_turns = 0;
+    }
+}
+
+fn remove_status(pokemon: &mut BattlePokemon, entity: BattleEntityRef, status: StatusCondition, message: String, battle_events: &mut Vec<BattleEvent>) {
+    pokemon.status = None;
+    pokemon.status_turns = 0;
+    battle_events.push(BattleEvent::GenericMessage { message });
+    battle_events.push(BattleEvent::StatusRemoved { target: entity, status });
+}
+
+/// Check whether `entity` can use a move this turn. Sleeping Pokémon count down and wake up,
+/// frozen Pokémon may thaw out and paralyzed Pokémon may be fully paralyzed. Returns false
+/// when the status stops the move.
+pub fn check_can_act<S: BattleSides>(
+    battle_state: &mut S,
+    battle_events: &mut Vec<BattleEvent>,
+    entity: &BattleEntityRef,
+) -> bool {
+    let Some(pokemon) = battle_state.pokemon_mut(entity) else {
+        return true;
+    };
+    let Some(status) = pokemon.status else {
+        return true;
+    };
+    let name = pokemon.name.clone();
+    let prevented = match status {
+        StatusCondition::Sleep => {
+            if pokemon.status_turns == 0 {
+                remove_status(pokemon, entity.clone(), status, format!("{} woke up!", name), battle_events);
+                false
+            } else {
+                pokemon.status_turns -= 1;
+                battle_events.push(BattleEvent::GenericMessage { message: format!("{} is fast asleep.", name) });
+                true
+            }
+        }
+        StatusCondition::Freeze => {
+            if with_rng(|rng| rng.gen_bool(FREEZE_THAW_CHANCE)) {
+                remove_status(pokemon, entity.clone(), status, format!("{} thawed out!", name), battle_events);
+                false
+            } else {
+                battle_events.push(BattleEvent::GenericMessage { message: format!("{} is frozen solid!", name) });
+                true
+            }
+        }
+        StatusCondition::Paralysis => {
+            let fully_paralyzed = with_rng(|rng| rng.gen_bool(FULL_PARALYSIS_CHANCE));
+            if fully_paralyzed {
+                battle_events.push(BattleEvent::GenericMessage { message: format!("{} is paralyzed! It can't move!", name) });
+            }
+            fully_paralyzed
+        }
+        StatusCondition::Burn | StatusCondition::Poison | StatusCondition::Toxic => false,
+    };
+    if prevented {
+        battle_events.push(BattleEvent::StatusPreventedMove { target: entity.clone(), status });
+    }
+    !prevented
+}
+
+/// Deal end-of-turn burn and poison damage to every active Pokémon. Badly poisoned damage
+/// grows by a sixteenth of max HP each turn.
+pub fn apply_status_damage<S: BattleSides>(battle_state: &mut S, battle_events: &mut Vec<BattleEvent>) {
+    for entity in battle_state.active_entities() {
+        let Some(pokemon) = battle_state.pokemon_mut(&entity) else {
+            continue;
+        };
+        let Some(status) = pokemon.status.filter(|_| !pokemon.is_fainted && pokemon.current_hp > 0) else {
+            continue;
+        };
+        let sixteenths = match status {
+            StatusCondition::Burn => 1,
+            StatusCondition::Poison => 2,
+            StatusCondition::Toxic => {
+                pokemon.status_turns = (pokemon.status_turns + 1).min(TOXIC_MAX_STACKS);
+                pokemon.status_turns as u32
+            }
+            StatusCondition::Freeze | StatusCondition::Paralysis | StatusCondition::Sleep => continue,
+        };
+        let damage = (pokemon.max_hp * sixteenths / 16).max(1);
+        let message = match status {
+            StatusCondition::Burn => format!("{} was hurt by its burn!", pokemon.name),
+            _ => format!("{} was hurt by poison!", pokemon.name),
+        };
+        battle_events.push(BattleEvent::GenericMessage { message });
+        apply_damage_with_effectiveness(battle_state, battle_events, entity.clone(), damage, 1.0, false);
+        let Some(pokemon) = battle_state.pokemon(&entity) else {
+            continue;
+        };
+        battle_events.push(BattleEvent::StatusDamage {
+            target: entity,
+            status,
+            damage,
+            new_hp: pokemon.current_hp,
+            max_hp: pokemon.max_hp,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combat::logic::switching::reset_on_switch_out;
+    use crate::combat::rng::with_seed;
+    use crate::combat::state::{PvPBattleState, WildBattleState};
+    use crate::combat::test_support::{pvp_battle, repositories, test_pokemon, wild_battle};
+    use crate::monsters::ability_manager::AbilityRepository;
+    use crate::monsters::move_manager::MoveRepository;
+    use crate::monsters::PokemonType;
+    use std::sync::Arc;
+
+    const PLAYER: BattleEntityRef = BattleEntityRef::Player { team_index: 0 };
+    const PLAYER1: BattleEntityRef = BattleEntityRef::Player1 { team_index: 0 };
+    const PLAYER2: BattleEntityRef = BattleEntityRef::Player2 { team_index: 0 };
+
+    /// A wild battle whose player Pokémon has `status` with `status_turns` on its counter
+    fn wild_battle_with(repositories: &(Arc<MoveRepository>, Arc<AbilityRepository>), status: StatusCondition, status_turns: u8) -> WildBattleState {
+        let mut pokemon = test_pokemon("Ace", &[PokemonType::Normal], 160, &[1]);
+        pokemon.status = Some(status);
+        pokemon.status_turns = status_turns;
+        wild_battle(pokemon, test_pokemon("Wild", &[PokemonType::Normal], 160, &[1]), repositories)
+    }
+
+    fn pvp_battle_with(status: StatusCondition, status_turns: u8) -> PvPBattleState {
+        let mut pokemon = test_pokemon("Ace", &[PokemonType::Normal], 160, &[1]);
+        pokemon.status = Some(status);
+        pokemon.status_turns = status_turns;
+        pvp_battle(pokemon, test_pokemon("Rival", &[PokemonType::Normal], 160, &[1]), &repositories())
+    }
+
+    /// How many of `tries` seeded checks let a fresh Pokémon with `status` act
+    fn acting_count(status: StatusCondition, tries: u64) -> usize {
+        let repositories = repositories();
+        (0..tries)
+            .filter(|seed| {
+                let mut battle_state = wild_battle_with(&repositories, status, 0);
+                with_seed(*seed, || check_can_act(&mut battle_state, &mut Vec::new(), &PLAYER))
+            })
+            .count()
+    }
+
+    #[test]
+    fn sleep_lasts_between_the_minimum_and_maximum_turns() {
+        let turns: Vec<u8> = (0..100).map(|seed| with_seed(seed, || initial_status_turns(Some(StatusCondition::Sleep)))).collect();
+        assert!(turns.iter().all(|turns| (SLEEP_MIN_TURNS..=SLEEP_MAX_TURNS).contains(turns)));
+        assert!(turns.contains(&SLEEP_MIN_TURNS) && turns.contains(&SLEEP_MAX_TURNS));
+        assert_eq!(initial_status_turns(Some(StatusCondition::Burn)), 0);
+        assert_eq!(initial_status_turns(None), 0);
+    }
+
+    #[test]
+    fn sleeping_pokemon_count_down_then_wake_up() {
+        let repositories = repositories();
+        let mut battle_state = wild_battle_with(&repositories, StatusCondition::Sleep, 2);
+        let mut events = Vec::new();
+        assert!(!check_can_act(&mut battle_state, &mut events, &PLAYER));
+        assert!(!check_can_act(&mut battle_state, &mut events, &PLAYER));
+        assert_eq!(battle_state.player.team[0].status_turns, 0);
+        assert!(events.iter().any(|event| matches!(event, BattleEvent::StatusPreventedMove { status: StatusCondition::Sleep, .. })));
+
+        assert!(check_can_act(&mut battle_state, &mut events, &PLAYER));
+        assert_eq!(battle_state.player.team[0].status, None);
+        assert!(matches!(events.last(), Some(BattleEvent::StatusRemoved { status: StatusCondition::Sleep, .. })));
+    }
+
+    #[test]
+    fn frozen_pokemon_sometimes_thaw_out() {
+        let repositories = repositories();
+        let thawed = acting_count(StatusCondition::Freeze, 1000);
+        assert!((150..250).contains(&thawed), "thawed {} of 1000 times", thawed);
+
+        for seed in 0..20 {
+            let mut battle_state = wild_battle_with(&repositories, StatusCondition::Freeze, 0);
+            let acted = with_seed(seed, || check_can_act(&mut battle_state, &mut Vec::new(), &PLAYER));
+            // Thawing out is the only way a frozen Pokémon acts
+            assert_eq!(acted, battle_state.player.team[0].status.is_none());
+        }
+    }
+
+    #[test]
+    fn paralyzed_pokemon_are_sometimes_fully_paralyzed() {
+        let repositories = repositories();
+        let acted = acting_count(StatusCondition::Paralysis, 1000);
+        assert!((700..800).contains(&acted), "acted {} of 1000 times", acted);
+
+        // Paralysis stays either way and halves speed
+        let mut battle_state = wild_battle_with(&repositories, StatusCondition::Paralysis, 0);
+        for seed in 0..20 {
+            with_seed(seed, || check_can_act(&mut battle_state, &mut Vec::new(), &PLAYER));
+        }
+        assert_eq!(battle_state.player.team[0].status, Some(StatusCondition::Paralysis));
+        assert_eq!(effective_speed(&battle_state.player.team[0]), 50);
+    }
+
+    #[test]
+    fn damaging_statuses_never_stop_moves() {
+        let repositories = repositories();
+        for status in [StatusCondition::Burn, StatusCondition::Poison, StatusCondition::Toxic] {
+            let mut battle_state = wild_battle_with(&repositories, status, 0);
+            let mut events = Vec::new();
+            assert!(check_can_act(&mut battle_state, &mut events, &PLAYER));
+            assert!(events.is_empty());
+        }
+    }
+
+    #[test]
+    fn toxic_damage_grows_each_turn_up_to_the_cap() {
+        let repositories = repositories();
+        let mut battle_state = wild_battle_with(&repositories, StatusCondition::Toxic, 0);
+        let mut hp = Vec::new();
+        for _ in 0..3 {
+            apply_status_damage(&mut battle_state, &mut Vec::new());
+            hp.push(battle_state.player.team[0].current_hp);
+        }
+        assert_eq!(hp, [150, 130, 100]);
+
+        let mut battle_state = wild_battle_with(&repositories, StatusCondition::Toxic, TOXIC_MAX_STACKS);
+        apply_status_damage(&mut battle_state, &mut Vec::new());
+        assert_eq!(battle_state.player.team[0].status_turns, TOXIC_MAX_STACKS);
+        assert_eq!(battle_state.player.team[0].current_hp, 160 - 160 * TOXIC_MAX_STACKS as u32 / 16);
+    }
+
+    #[test]
+    fn toxic_counter_resets_on_switch_out() {
+        let mut battle_state = pvp_battle_with(StatusCondition::Toxic, 4);
+        reset_on_switch_out(&mut battle_state, &mut Vec::new(), &PLAYER1);
+        assert_eq!(battle_state.player1.team[0].status, Some(StatusCondition::Toxic));
+        assert_eq!(battle_state.player1.team[0].status_turns, 0);
+
+        // Sleep keeps counting down where it left off
+        let mut battle_state = pvp_battle_with(StatusCondition::Sleep, 2);
+        reset_on_switch_out(&mut battle_state, &mut Vec::new(), &PLAYER1);
+        assert_eq!(battle_state.player1.team[0].status_turns, 2);
+    }
+
+    #[test]
+    fn pvp_pokemon_are_checked_on_their_own_side() {
+        let mut battle_state = pvp_battle_with(StatusCondition::Sleep, 1);
+        let mut events = Vec::new();
+        assert!(!check_can_act(&mut battle_state, &mut events, &PLAYER1));
+        assert!(check_can_act(&mut battle_state, &mut events, &PLAYER2));
+        assert!(matches!(events.last(), Some(BattleEvent::StatusPreventedMove { target: BattleEntityRef::Player1 { team_index: 0 }, .. })));
+        assert_eq!(battle_state.player1.team[0].status_turns, 0);
+        assert!(check_can_act(&mut battle_state, &mut events, &PLAYER1));
+    }
+}
@@ -0,0 +1,215 @@
+use crate::combat::logic::battle_calculations::calculate_type_effectiveness;
+use crate::combat::logic::battle_effects::{apply_damage_with_effectiveness, apply_effect};
+use crate::combat::logic::core::BattleSides;
+use crate::combat::logic::status::reset_status_on_switch_out;
+use crate::combat::state::{BattleEntityRef, BattleEvent, FieldEffectType, StatusCondition};
+use crate::monsters::move_manager::{EffectData, EffectTarget};
+use crate::monsters::PokemonType;
+use crate::stats::BattleStatModifiers;
+
+/// Most layers of Spikes and Toxic Spikes a side can have
+pub const MAX_SPIKES_LAYERS: u8 = 3;
+pub const MAX_TOXIC_SPIKES_LAYERS: u8 = 2;
+
+/// Entry hazard a field effect from move data lays, if it is one
+pub fn hazard_for_field_effect(effect_type: crate::monsters::move_manager::FieldEffectType) -> Option<FieldEffectType> {
+    match effect_type {
+        crate::monsters::move_manager::FieldEffectType::StealthRock => Some(FieldEffectType::StealthRock),
+        crate::monsters::move_manager::FieldEffectType::Spikes => Some(FieldEffectType::Spikes),
+        crate::monsters::move_manager::FieldEffectType::ToxicSpikes => Some(FieldEffectType::ToxicSpikes),
+        _ => None,
+    }
+}
+
+/// Clear what a Pokémon only keeps while it stays in: stat stages, volatile statuses and the
+/// badly poisoned counter
+pub fn reset_on_switch_out<S: BattleSides>(battle_state: &mut S, battle_events: &mut Vec<BattleEvent>, entity: &BattleEntityRef) {
+    let Some(pokemon) = battle_state.pokemon_mut(entity) else {
+        return;
+    };
+    pokemon.stat_modifiers = BattleStatModifiers::default();
+    pokemon.volatile_statuses.clear();
+    reset_status_on_switch_out(pokemon);
+    battle_events.push(BattleEvent::SwitchOut { target: entity.clone() });
+}
+
+/// Lay `hazard` on the side of `target`. Fails once the side has as many layers as the hazard
+/// allows, and against wild Pokémon, which never switch.
+pub fn set_hazard<S: BattleSides>(battle_state: &mut S, battle_events: &mut Vec<BattleEvent>, target: &BattleEntityRef, hazard: FieldEffectType) {
+    let Some(side) = battle_state.side_of_mut(target) else {
+        battle_events.push(BattleEvent::GenericMessage { message: "But it failed!".to_string() });
+        return;
+    };
+    let layers = match hazard {
+        FieldEffectType::StealthRock if !side.stealth_rock => {
+            side.stealth_rock = true;
+            1
+        }
+        FieldEffectType::Spikes if side.spikes_layers < MAX_SPIKES_LAYERS => {
+            side.spikes_layers += 1;
+            side.spikes_layers
+        }
+        FieldEffectType::ToxicSpikes if side.toxic_spikes_layers < MAX_TOXIC_SPIKES_LAYERS => {
+            side.toxic_spikes_layers += 1;
+            side.toxic_spikes_layers
+        }
+        _ => {
+            battle_events.push(BattleEvent::GenericMessage { message: "But it failed!".to_string() });
+            return;
+        }
+    };
+    let message = match hazard {
+        FieldEffectType::StealthRock => "Pointed stones float in the air around the opposing team!",
+        FieldEffectType::ToxicSpikes => "Poison spikes were scattered on the ground around the opposing team!",
+        _ => "Spikes were scattered on the ground around the opposing team!",
+    };
+    battle_events.push(BattleEvent::GenericMessage { message: message.to_string() });
+    battle_events.push(BattleEvent::HazardSet { side: target.clone(), hazard, layers });
+}
+
+/// Whether the hazards on the ground (Spikes, Toxic Spikes) reach `entity`: Flying types and
+/// Pokémon whose ability makes them immune to Ground moves float over them
+fn is_grounded<S: BattleSides>(battle_state: &S, entity: &BattleEntityRef) -> bool {
+    let Some(pokemon) = battle_state.pokemon(entity) else {
+        return false;
+    };
+    let levitating = battle_state.ability_repository()
+        .and_then(|repo| repo.get_ability(&pokemon.ability))
+        .is_some_and(|ability| ability.is_immune_to_type(PokemonType::Ground));
+    !pokemon.pokemon_types.contains(&PokemonType::Flying) && !levitating
+}
+
+fn hurt_by_hazard<S: BattleSides>(battle_state: &mut S, battle_events: &mut Vec<BattleEvent>, entity: &BattleEntityRef, hazard: FieldEffectType, damage: u32) {
+    let Some(name) = battle_state.pokemon(entity).map(|pokemon| pokemon.name.clone()) else {
+        return;
+    };
+    let message = match hazard {
+        FieldEffectType::StealthRock => format!("Pointed stones dug into {}!", name),
+        _ => format!("{} is hurt by the spikes!", name),
+    };
+    battle_events.push(BattleEvent::GenericMessage { message });
+    apply_damage_with_effectiveness(battle_state, battle_events, entity.clone(), damage, 1.0, false);
+    if let Some(pokemon) = battle_state.pokemon(entity) {
+        battle_events.push(BattleEvent::HazardDamage {
+            target: entity.clone(),
+            hazard,
+            damage,
+            new_hp: pokemon.current_hp,
+            max_hp: pokemon.max_hp,
+        });
+    }
+}
+
+/// Hit `entity` with the hazards on its side as it comes in. Stealth Rock deals Rock-type scaled
+/// damage, Spikes deal more with every layer, and Toxic Spikes poison, or badly poison with two
+/// layers. A grounded Poison type soaks up Toxic Spikes instead.
+pub fn apply_entry_hazards<S: BattleSides>(battle_state: &mut S, battle_events: &mut Vec<BattleEvent>, entity: &BattleEntityRef) {
+    let Some(side) = battle_state.side_of(entity).cloned() else {
+        return;
+    };
+    let Some(pokemon) = battle_state.pokemon(entity) else {
+        return;
+    };
+    if pokemon.is_fainted || pokemon.current_hp == 0 {
+        return;
+    }
+    let max_hp = pokemon.max_hp;
+    let types = pokemon.pokemon_types.clone();
+    let grounded = is_grounded(battle_state, entity);
+
+    if side.stealth_rock {
+        let type_chart = battle_state.move_repository().map(|repo| &repo.type_chart);
+        let effectiveness = calculate_type_effectiveness(type_chart, &PokemonType::Rock, &types);
+        let damage = ((max_hp as f32 * effectiveness / 8.0) as u32).max(1);
+        hurt_by_hazard(battle_state, battle_events, entity, FieldEffectType::StealthRock, damage);
+    }
+
+    if grounded && side.spikes_layers > 0 {
+        let divisor = match side.spikes_layers {
+            1 => 8,
+            2 => 6,
+            _ => 4,
+        };
+        hurt_by_hazard(battle_state, battle_events, entity, FieldEffectType::Spikes, (max_hp / divisor).max(1));
+    }
+
+    if grounded && side.toxic_spikes_layers > 0 {
+        if types.contains(&PokemonType::Poison) {
+            if let Some(side) = battle_state.side_of_mut(entity) {
+                side.toxic_spikes_layers = 0;
+            }
+            if let Some(pokemon) = battle_state.pokemon(entity) {
+                battle_events.push(BattleEvent::GenericMessage { message: format!("{} absorbed the poison spikes!", pokemon.name) });
+            }
+            battle_events.push(BattleEvent::HazardRemoved { side: entity.clone(), hazard: FieldEffectType::ToxicSpikes });
+        } else if !types.contains(&PokemonType::Steel) && battle_state.pokemon(entity).is_some_and(|pokemon| pokemon.status.is_none() && pokemon.current_hp > 0) {
+            let status = if side.toxic_spikes_layers >= MAX_TOXIC_SPIKES_LAYERS { StatusCondition::Toxic } else { StatusCondition::Poison };
+            apply_effect(battle_state, battle_events, &EffectData::ApplyStatus { status, target: EffectTarget::Target }, entity.clone(), entity.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combat::state::PlayerSideState;
+    use crate::combat::test_support::{pvp_battle, repositories, test_pokemon};
+
+    const PLAYER1: BattleEntityRef = BattleEntityRef::Player1 { team_index: 0 };
+
+    fn hp_after_hazards(types: &[PokemonType], side: PlayerSideState) -> (u32, Option<StatusCondition>, PlayerSideState) {
+        let repositories = repositories();
+        let mut battle_state = pvp_battle(test_pokemon("Target", types, 96, &[]), test_pokemon("Other", &[PokemonType::Normal], 96, &[]), &repositories);
+        battle_state.player1.side_effects = side;
+        apply_entry_hazards(&mut battle_state, &mut Vec::new(), &PLAYER1);
+        let pokemon = &battle_state.player1.team[0];
+        (pokemon.current_hp, pokemon.status, battle_state.player1.side_effects.clone())
+    }
+
+    #[test]
+    fn stealth_rock_scales_with_rock_effectiveness() {
+        let side = PlayerSideState { stealth_rock: true, ..Default::default() };
+        assert_eq!(hp_after_hazards(&[PokemonType::Normal], side.clone()).0, 96 - 12);
+        assert_eq!(hp_after_hazards(&[PokemonType::Fire, PokemonType::Flying], side.clone()).0, 96 - 48);
+        assert_eq!(hp_after_hazards(&[PokemonType::Steel], side).0, 96 - 6);
+    }
+
+    #[test]
+    fn spikes_hurt_more_per_layer_and_miss_flying_types() {
+        for (layers, damage) in [(1, 12), (2, 16), (3, 24)] {
+            let side = PlayerSideState { spikes_layers: layers, ..Default::default() };
+            assert_eq!(hp_after_hazards(&[PokemonType::Normal], side).0, 96 - damage);
+        }
+        let side = PlayerSideState { spikes_layers: 3, ..Default::default() };
+        assert_eq!(hp_after_hazards(&[PokemonType::Flying], side).0, 96);
+    }
+
+    #[test]
+    fn toxic_spikes_poison_or_get_absorbed() {
+        let one_layer = PlayerSideState { toxic_spikes_layers: 1, ..Default::default() };
+        let two_layers = PlayerSideState { toxic_spikes_layers: 2, ..Default::default() };
+        assert_eq!(hp_after_hazards(&[PokemonType::Normal], one_layer.clone()).1, Some(StatusCondition::Poison));
+        assert_eq!(hp_after_hazards(&[PokemonType::Normal], two_layers.clone()).1, Some(StatusCondition::Toxic));
+        assert_eq!(hp_after_hazards(&[PokemonType::Steel], two_layers.clone()).1, None);
+        assert_eq!(hp_after_hazards(&[PokemonType::Flying], two_layers.clone()).1, None);
+
+        let (_, status, side) = hp_after_hazards(&[PokemonType::Poison], two_layers);
+        assert_eq!(status, None);
+        assert_eq!(side.toxic_spikes_layers, 0);
+    }
+
+    #[test]
+    fn hazards_stack_up_to_their_layer_limit() {
+        let repositories = repositories();
+        let mut battle_state = pvp_battle(test_pokemon("A", &[PokemonType::Normal], 96, &[]), test_pokemon("B", &[PokemonType::Normal], 96, &[]), &repositories);
+        let mut events = Vec::new();
+        for _ in 0..5 {
+            set_hazard(&mut battle_state, &mut events, &PLAYER1, FieldEffectType::Spikes);
+            set_hazard(&mut battle_state, &mut events, &PLAYER1, FieldEffectType::ToxicSpikes);
+            set_hazard(&mut battle_state, &mut events, &PLAYER1, FieldEffectType::StealthRock);
+        }
+        let side = &battle_state.player1.side_effects;
+        assert_eq!((side.spikes_layers, side.toxic_spikes_layers, side.stealth_rock), (MAX_SPIKES_LAYERS, MAX_TOXIC_SPIKES_LAYERS, true));
+        assert_eq!(events.iter().filter(|event| matches!(event, BattleEvent::HazardSet { .. })).count(), 6);
+    }
+}
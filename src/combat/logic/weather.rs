@@ -0,0 +1,174 @@
+use crate::combat::logic::battle_effects::{apply_damage_with_effectiveness, update_weather_forms};
+use crate::combat::logic::core::BattleSides;
+use crate::combat::state::{BattleEvent, WeatherState, WeatherType};
+use crate::monsters::move_manager::{FieldEffectType, MoveData};
+use crate::monsters::PokemonType;
+
+/// Turns weather lasts when a move or ability starts it
+pub const WEATHER_TURNS: u8 = 5;
+/// `turns_left` of weather that never runs out, like weather carried over from the overworld
+pub const INDEFINITE_WEATHER_TURNS: u8 = u8::MAX;
+
+/// Moves whose accuracy depends on the weather
+pub const THUNDER_MOVE_ID: u32 = 87;
+pub const BLIZZARD_MOVE_ID: u32 = 59;
+pub const HURRICANE_MOVE_ID: u32 = 542;
+/// Accuracy of Thunder and Hurricane in harsh sunlight
+const SUN_WEAKENED_ACCURACY: u8 = 50;
+
+/// Weather a field effect from move data starts, if it is a weather
+pub fn weather_for_field_effect(effect_type: FieldEffectType) -> Option<WeatherType> {
+    match effect_type {
+        FieldEffectType::Rain => Some(WeatherType::Rain),
+        FieldEffectType::HarshSunlight => Some(WeatherType::HarshSunlight),
+        FieldEffectType::Sandstorm => Some(WeatherType::Sandstorm),
+        FieldEffectType::Hail => Some(WeatherType::Hail),
+        _ => None,
+    }
+}
+
+fn start_message(weather_type: WeatherType) -> &'static str {
+    match weather_type {
+        WeatherType::Rain => "It started to rain!",
+        WeatherType::HarshSunlight => "The sunlight turned harsh!",
+        WeatherType::Sandstorm => "A sandstorm kicked up!",
+        WeatherType::Hail => "It started to hail!",
+    }
+}
+
+fn continue_message(weather_type: WeatherType) -> &'static str {
+    match weather_type {
+        WeatherType::Rain => "Rain continues to fall.",
+        WeatherType::HarshSunlight => "The sunlight is strong.",
+        WeatherType::Sandstorm => "The sandstorm is raging.",
+        WeatherType::Hail => "Hail continues to fall.",
+    }
+}
+
+fn end_message(weather_type: WeatherType) -> &'static str {
+    match weather_type {
+        WeatherType::Rain => "The rain stopped.",
+        WeatherType::HarshSunlight => "The harsh sunlight faded.",
+        WeatherType::Sandstorm => "The sandstorm subsided.",
+        WeatherType::Hail => "The hail stopped.",
+    }
+}
+
+/// Replace the battle weather with `weather_type` for `turns` turns. Returns false, changing
+/// nothing, when that weather is already active.
+pub fn start_weather<S: BattleSides>(battle_state: &mut S, battle_events: &mut Vec<BattleEvent>, weather_type: WeatherType, turns: u8) -> bool {
+    if battle_state.weather() == Some(weather_type) {
+        return false;
+    }
+    *battle_state.weather_state_mut() = Some(WeatherState { weather_type, turns_left: turns });
+    battle_events.push(BattleEvent::GenericMessage { message: start_message(weather_type).to_string() });
+    battle_events.push(BattleEvent::WeatherStarted { weather_type });
+    update_weather_forms(battle_state, battle_events);
+    true
+}
+
+/// Multiplier the weather puts on damage from moves of `move_type`: rain strengthens Water
+/// and weakens Fire, harsh sunlight the other way round
+pub fn damage_multiplier(weather: Option<WeatherType>, move_type: PokemonType) -> f32 {
+    match (weather, move_type) {
+        (Some(WeatherType::Rain), PokemonType::Water) | (Some(WeatherType::HarshSunlight), PokemonType::Fire) => 1.5,
+        (Some(WeatherType::Rain), PokemonType::Fire) | (Some(WeatherType::HarshSunlight), PokemonType::Water) => 0.5,
+        _ => 1.0,
+    }
+}
+
+/// Accuracy of `move_details` under the current weather; None means it can't miss.
+/// Only the weather-dependent moves roll for accuracy: they can't miss in the weather that
+/// helps them and use their listed accuracy otherwise. Every other move always hits.
+pub fn move_accuracy(weather: Option<WeatherType>, move_details: &MoveData) -> Option<u8> {
+    match (move_details.id, weather) {
+        (THUNDER_MOVE_ID | HURRICANE_MOVE_ID, Some(WeatherType::Rain)) => None,
+        (THUNDER_MOVE_ID | HURRICANE_MOVE_ID, Some(WeatherType::HarshSunlight)) => Some(SUN_WEAKENED_ACCURACY),
+        (BLIZZARD_MOVE_ID, Some(WeatherType::Hail)) => None,
+        (THUNDER_MOVE_ID | HURRICANE_MOVE_ID | BLIZZARD_MOVE_ID, _) => move_details.accuracy,
+        _ => None,
+    }
+}
+
+/// Whether a Pokémon of these types is hurt by the weather at the end of each turn
+fn hurt_by_weather(weather_type: WeatherType, types: &[PokemonType]) -> bool {
+    match weather_type {
+        WeatherType::Sandstorm => !types.iter().any(|t| matches!(t, PokemonType::Rock | PokemonType::Ground | PokemonType::Steel)),
+        WeatherType::Hail => !types.contains(&PokemonType::Ice),
+        WeatherType::Rain | WeatherType::HarshSunlight => false,
+    }
+}
+
+/// Deal sandstorm and hail damage to every active Pokémon, then count the weather down and
+/// end it once its turns run out
+pub fn apply_weather_end_of_turn<S: BattleSides>(battle_state: &mut S, battle_events: &mut Vec<BattleEvent>) {
+    let Some(weather_type) = battle_state.weather() else {
+        return;
+    };
+    battle_events.push(BattleEvent::GenericMessage { message: continue_message(weather_type).to_string() });
+
+    for entity in battle_state.active_entities() {
+        let Some(pokemon) = battle_state.pokemon(&entity) else {
+            continue;
+        };
+        if pokemon.is_fainted || pokemon.current_hp == 0 || !hurt_by_weather(weather_type, &pokemon.pokemon_types) {
+            continue;
+        }
+        let damage = (pokemon.max_hp / 16).max(1);
+        let message = match weather_type {
+            WeatherType::Sandstorm => format!("{} is buffeted by the sandstorm!", pokemon.name),
+            _ => format!("{} is buffeted by the hail!", pokemon.name),
+        };
+        battle_events.push(BattleEvent::GenericMessage { message });
+        apply_damage_with_effectiveness(battle_state, battle_events, entity, damage, 1.0, false);
+    }
+
+    let weather = battle_state.weather_state_mut();
+    let Some(state) = weather.as_mut() else {
+        return;
+    };
+    if state.turns_left == INDEFINITE_WEATHER_TURNS {
+        return;
+    }
+    state.turns_left = state.turns_left.saturating_sub(1);
+    if state.turns_left == 0 {
+        *weather = None;
+        battle_events.push(BattleEvent::GenericMessage { message: end_message(weather_type).to_string() });
+        battle_events.push(BattleEvent::WeatherEnded);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monsters::move_manager::MoveRepository;
+
+    #[test]
+    fn only_weather_dependent_moves_can_miss() {
+        let move_repository = MoveRepository::new("resources/moves.json", "resources/types.json", &[]);
+        let thunder = &move_repository.moves[&THUNDER_MOVE_ID];
+        let hurricane = &move_repository.moves[&HURRICANE_MOVE_ID];
+        let blizzard = &move_repository.moves[&BLIZZARD_MOVE_ID];
+        let hydro_pump = move_repository.moves.values().find(|move_data| move_data.name == "hydro-pump").unwrap();
+
+        for move_data in [thunder, hurricane] {
+            assert_eq!(move_accuracy(Some(WeatherType::Rain), move_data), None);
+            assert_eq!(move_accuracy(Some(WeatherType::HarshSunlight), move_data), Some(SUN_WEAKENED_ACCURACY));
+            assert_eq!(move_accuracy(None, move_data), move_data.accuracy);
+        }
+        assert_eq!(move_accuracy(Some(WeatherType::Hail), blizzard), None);
+        assert_eq!(move_accuracy(Some(WeatherType::Rain), blizzard), blizzard.accuracy);
+        assert_eq!(move_accuracy(None, hydro_pump), None);
+        assert_eq!(move_accuracy(Some(WeatherType::HarshSunlight), hydro_pump), None);
+    }
+
+    #[test]
+    fn rain_and_sun_change_water_and_fire_damage() {
+        assert_eq!(damage_multiplier(Some(WeatherType::Rain), PokemonType::Water), 1.5);
+        assert_eq!(damage_multiplier(Some(WeatherType::Rain), PokemonType::Fire), 0.5);
+        assert_eq!(damage_multiplier(Some(WeatherType::HarshSunlight), PokemonType::Fire), 1.5);
+        assert_eq!(damage_multiplier(Some(WeatherType::HarshSunlight), PokemonType::Water), 0.5);
+        assert_eq!(damage_multiplier(Some(WeatherType::Sandstorm), PokemonType::Water), 1.0);
+        assert_eq!(damage_multiplier(None, PokemonType::Fire), 1.0);
+    }
+}
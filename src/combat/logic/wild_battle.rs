@@ -1,15 +1,24 @@
-use crate::combat::state::{WildBattleState, BattleEvent, BattlePhase, TurnOrder, PlayerAction, WildPokemonAction, BattleEntityRef, StatusCondition, BattlePokemonPublicView, BallType};
-use crate::combat::logic::battle_calculations::calculate_damage;
-use crate::combat::logic::battle_effects::{apply_effect, apply_damage_with_effectiveness};
+use crate::combat::state::{WildBattleState, BattleEvent, BattlePhase, TurnOrder, PlayerAction, WildPokemonAction, BattleEntityRef, BattlePokemonPublicView, BallType, WildEncounterModifier, CaptureOdds, StatusCondition};
+use crate::combat::logic::battle_effects::update_weather_forms;
+use crate::combat::logic::weather::apply_weather_end_of_turn;
+use crate::combat::logic::abilities::{apply_end_of_turn_abilities, on_battle_start, on_switch_in};
+use crate::combat::logic::core::{execute_move, execute_struggle, execute_super_move};
+use crate::combat::logic::status::{apply_status_damage, check_can_act, effective_speed};
+use crate::combat::logic::obedience::check_obedience;
+use crate::combat::logic::switching::{apply_entry_hazards, reset_on_switch_out};
 use crate::combat::CaptureAttempt;
 use rand::Rng;
+use crate::combat::rng::with_rng;
 
 /// Processes a single turn of the battle
 pub fn process_turn(battle_state: &mut WildBattleState) -> Vec<BattleEvent> {
     let mut battle_events = Vec::new();
 
-    // --- 1. Pre-action checks (e.g., checking if Pokémon can move due to sleep/paralysis) --- 
-    // TODO: Implement pre-action checks
+    // --- 1. Pre-action checks ---
+    // Sleep, freeze and paralysis are checked as each move is about to be used, in execute_action
+    if battle_state.turn_number == 1 {
+        on_battle_start(battle_state, &mut battle_events);
+    }
 
     // --- 2. Determine Turn Order --- 
     // Basic speed check for now
@@ -17,7 +26,7 @@ pub fn process_turn(battle_state: &mut WildBattleState) -> Vec<BattleEvent> {
     let wild_pokemon = &battle_state.wild_pokemon;
     
     // TODO: Incorporate priority moves, Trick Room, items (Quick Claw), etc.
-    let turn_order = if player_pokemon.calculated_stats.speed >= wild_pokemon.calculated_stats.speed {
+    let turn_order = if effective_speed(player_pokemon) >= effective_speed(wild_pokemon) {
         // TODO: Handle speed ties (random or other rule?)
         TurnOrder::PlayerFirst
     } else {
@@ -69,7 +78,7 @@ pub fn process_turn(battle_state: &mut WildBattleState) -> Vec<BattleEvent> {
     check_battle_end(battle_state);
 
     // --- 6. Prepare for next turn / state change --- 
-    if battle_state.battle_phase == BattlePhase::ProcessingTurn { // Only if not already ended/waiting switch
+    if battle_state.battle_phase.get() == BattlePhase::ProcessingTurn { // Only if not already ended/waiting switch
         // Increment turn number if the battle continues
         battle_state.turn_number += 1;
         
@@ -78,15 +87,15 @@ pub fn process_turn(battle_state: &mut WildBattleState) -> Vec<BattleEvent> {
         let can_player_switch = battle_state.player.team.iter().any(|p| !p.is_fainted && p.current_hp > 0);
 
         if player_active_fainted && can_player_switch {
-            battle_state.battle_phase = BattlePhase::WaitingForSwitch;
+            battle_state.battle_phase.transition(BattlePhase::WaitingForSwitch);
             battle_state.player.must_switch = true;
         } else if player_active_fainted && !can_player_switch {
             // Player's active fainted and no others left -> Battle End
-            battle_state.battle_phase = BattlePhase::Finished;
+            battle_state.battle_phase.transition(BattlePhase::Finished);
             // Reason will be set in check_battle_end or handled when sending BattleEnd message
         } else {
             // Normal progression to next turn
-            battle_state.battle_phase = BattlePhase::WaitingForPlayerAction;
+            battle_state.battle_phase.transition(BattlePhase::WaitingForPlayerAction);
         }
     }
 
@@ -98,6 +107,28 @@ pub fn process_turn(battle_state: &mut WildBattleState) -> Vec<BattleEvent> {
     battle_events
 }
 
+/// Sends in a replacement for the player's fainted Pokémon. This does not use up a turn,
+/// so the wild Pokémon does not act. Entry hazards can knock the replacement out as well, in
+/// which case the player is asked for another one or loses once nothing is left.
+pub fn process_replacement_switch(battle_state: &mut WildBattleState, team_index: usize) -> Vec<BattleEvent> {
+    let mut battle_events = Vec::new();
+    battle_state.battle_phase.transition(BattlePhase::ProcessingTurn);
+    execute_switch(battle_state, &mut battle_events, team_index);
+    battle_state.player.must_switch = false;
+    check_faints(battle_state, &mut battle_events);
+    check_battle_end(battle_state);
+
+    if battle_state.battle_phase.get() == BattlePhase::ProcessingTurn {
+        if battle_state.player.team[battle_state.player.active_pokemon_index].is_fainted {
+            battle_state.battle_phase.transition(BattlePhase::WaitingForSwitch);
+            battle_state.player.must_switch = true;
+        } else {
+            battle_state.battle_phase.transition(BattlePhase::WaitingForPlayerAction);
+        }
+    }
+    battle_events
+}
+
 /// Executes a single action (move, switch, item, run) for an entity
 fn execute_action(
     battle_state: &mut WildBattleState, 
@@ -109,8 +140,19 @@ fn execute_action(
     match source_entity {
         BattleEntityRef::Player { .. } => {
             // Player Action
+            let uses_move = matches!(action, PlayerAction::UseMove { .. } | PlayerAction::UseSuperMove { .. });
+            if uses_move && !check_can_act(battle_state, battle_events, &source_entity) {
+                return;
+            }
+            let Some(action) = check_obedience(battle_state, battle_events, &source_entity, action) else {
+                return;
+            };
             match action {
                 PlayerAction::UseMove { move_index } => execute_move(battle_state, battle_events, source_entity, move_index),
+                PlayerAction::UseSuperMove { move_index } => {
+                    battle_state.player.super_move_used = true;
+                    execute_super_move(battle_state, battle_events, source_entity, move_index);
+                },
                 PlayerAction::SwitchPokemon { team_index } => execute_switch(battle_state, battle_events, team_index),
                 PlayerAction::UseItem { item_id, is_capture_item } => {
                     if is_capture_item {
@@ -120,12 +162,17 @@ fn execute_action(
                     }
                 },
                 PlayerAction::Run => execute_run(battle_state, battle_events),
+                PlayerAction::PreBattleModifier { modifier } => execute_encounter_modifier(battle_state, battle_events, modifier),
             }
         }
         BattleEntityRef::Wild => {
             // Wild Action - Currently mapped through PlayerAction::Run placeholder
             // We need the actual WildPokemonAction here
             let wild_action = battle_state.wild_action.clone().unwrap_or(WildPokemonAction::Struggle); // Default to struggle if somehow missing
+            let uses_move = !matches!(wild_action, WildPokemonAction::Flee);
+            if uses_move && !check_can_act(battle_state, battle_events, &source_entity) {
+                return;
+            }
             match wild_action {
                  WildPokemonAction::UseMove { move_index } => execute_move(battle_state, battle_events, source_entity, move_index),
                  WildPokemonAction::Struggle => execute_struggle(battle_state, battle_events, source_entity),
@@ -138,312 +185,6 @@ fn execute_action(
     }
 }
 
-/// Executes a move
-fn execute_move(
-    battle_state: &mut WildBattleState, 
-    battle_events: &mut Vec<BattleEvent>,
-    source: BattleEntityRef,
-    move_index: usize
-) {
-    // Get source and target Pokémon names
-    let (source_name, move_data) = match source {
-        BattleEntityRef::Player { team_index } => {
-            let pokemon = &battle_state.player.team[team_index];
-            let move_data = pokemon.moves.get(move_index).cloned();
-            (pokemon.name.clone(), move_data)
-        },
-        BattleEntityRef::Wild => {
-            let pokemon = &battle_state.wild_pokemon;
-            let move_data = pokemon.moves.get(move_index).cloned();
-            (pokemon.name.clone(), move_data)
-        },
-        _ => {
-            panic!("Invalid target entity for move");
-        }
-    };
-    
-    // Get the move name from the move repository
-    let (move_id, move_name) = if let Some(move_data) = move_data {
-        let move_id = move_data.move_id;
-        // Get move name from repository if available
-        let move_name = battle_state.move_repository
-            .as_ref()
-            .and_then(|repo| repo.get_move(move_id))
-            .map(|m| m.name.clone())
-            .unwrap_or_else(|| format!("Move {}", move_id));
-        (move_id, move_name)
-    } else {
-        (0, "Unknown Move".to_string())
-    };
-    
-    // Add a more descriptive message
-    battle_events.push(BattleEvent::GenericMessage { 
-        message: format!("{} used {}!", source_name, move_name) 
-    });
-    
-    // Decrement PP
-    match source {
-        BattleEntityRef::Player { team_index } => {
-            if let Some(mv) = battle_state.player.team[team_index].moves.get_mut(move_index) {
-                if mv.current_pp > 0 {
-                    mv.current_pp -= 1;
-                }
-            }
-        },
-        BattleEntityRef::Wild => {
-             if let Some(mv) = battle_state.wild_pokemon.moves.get_mut(move_index) {
-                 if mv.current_pp > 0 {
-                    mv.current_pp -= 1;
-                 }
-             }
-        },
-        _ => {
-            panic!("Invalid target entity for move");
-        }
-    }
-    
-    // Get target
-    let target = match source {
-        BattleEntityRef::Player { .. } => BattleEntityRef::Wild,
-        BattleEntityRef::Wild => BattleEntityRef::Player { team_index: battle_state.player.active_pokemon_index },
-        _ => {
-            panic!("Invalid target entity for move");
-        }
-        };
-    
-    // Get detailed move data from move repository
-    let move_details = battle_state.move_repository
-        .as_ref()
-        .and_then(|repo| repo.get_move(move_id));
-    
-    if let Some(move_details) = move_details {
-        // Clone the effects needed before potentially modifying battle_state
-        let primary_effect = move_details.effect.clone();
-        let secondary_effect_data = move_details.secondary_effect.clone();
-
-        // If it's a damage-dealing move
-        if let Some(power) = move_details.power {
-            // Calculate damage using proper formula
-            let source_level = match source {
-                BattleEntityRef::Player { team_index } => battle_state.player.team[team_index].level,
-                BattleEntityRef::Wild { .. } => battle_state.wild_pokemon.level,
-                _ => {
-                    panic!("Invalid target entity for move");
-                }
-            };
-
-            let (source_stats, source_types) = match source {
-                BattleEntityRef::Player { team_index } => (&battle_state.player.team[team_index].calculated_stats, &battle_state.player.team[team_index].pokemon_types),
-                BattleEntityRef::Wild { .. } => (&battle_state.wild_pokemon.calculated_stats, &battle_state.wild_pokemon.pokemon_types),
-                _ => {
-                    panic!("Invalid target entity for move");
-                }
-            };
-
-            let (target_stats, target_types) = match target {
-                BattleEntityRef::Player { team_index } => (&battle_state.player.team[team_index].calculated_stats, &battle_state.player.team[team_index].pokemon_types),
-                BattleEntityRef::Wild { .. } => (&battle_state.wild_pokemon.calculated_stats, &battle_state.wild_pokemon.pokemon_types),
-                _ => {
-                    panic!("Invalid target entity for move");
-                }
-            };
-
-            let type_chart = battle_state.move_repository.as_ref().map(|repo| &repo.type_chart);
-
-            let (damage, effectiveness, is_critical) = calculate_damage(
-                source_level,
-                source_stats,
-                source_types,
-                target_stats, 
-                target_types,
-                &move_details,
-                type_chart
-            );
-            // Apply the calculated damage
-            if damage > 0 {
-                apply_damage_with_effectiveness(
-                    battle_state, 
-                    battle_events, 
-                    target.clone(), 
-                    damage, 
-                    effectiveness, 
-                    is_critical
-                );
-                
-                // Check for secondary effects using the cloned data
-                if let Some(secondary) = secondary_effect_data {
-                    let proc_chance = secondary.chance;
-                    let roll = rand::thread_rng().gen_range(1..=100);
-                    
-                    if roll <= proc_chance {
-                        // Apply secondary effect using the cloned effect
-                        apply_effect(battle_state, battle_events, &secondary.effect, source.clone(), target.clone());
-                    }
-                }
-            } else {
-                // Damage was 0 (due to immunity or calculation result)
-                 if effectiveness == 0.0 {
-                     // Message already added above for immunity
-                 } else {
-                     // Generic fail message if damage was calculated as 0 but not due to immunity
-                     battle_events.push(BattleEvent::GenericMessage { 
-                        message: "But it failed!".to_string() 
-                     });
-                }
-            }
-        } else {
-            // Status move - apply primary effect using the cloned effect
-            apply_effect(battle_state, battle_events, &primary_effect, source.clone(), target.clone());
-        }
-    } else {
-        // Fallback to simple damage if move details not found
-        apply_damage(battle_state, battle_events, target.clone(), 10);
-    }
-
-    // Record the move used event with proper move details
-    battle_events.push(BattleEvent::MoveUsed { 
-        source, 
-        move_id, 
-        move_name, 
-        target 
-    });
-}
-
-/// Executes Struggle
-fn execute_struggle(
-    battle_state: &mut WildBattleState, 
-    battle_events: &mut Vec<BattleEvent>,
-    source: BattleEntityRef
-) {
-    // Get source name for more meaningful message
-    let source_name = match source {
-        BattleEntityRef::Player { team_index } => battle_state.player.team[team_index].name.clone(),
-        BattleEntityRef::Wild => battle_state.wild_pokemon.name.clone(),
-        _ => {
-            panic!("Invalid source entity for Struggle");
-        }
-    };
-    
-    battle_events.push(BattleEvent::GenericMessage { 
-        message: format!("{} used Struggle!", source_name) 
-    });
-    
-    let target = match source {
-        BattleEntityRef::Player { .. } => BattleEntityRef::Wild,
-        BattleEntityRef::Wild => BattleEntityRef::Player { team_index: battle_state.player.active_pokemon_index },
-        _ => {
-            panic!("Invalid source entity for Struggle");
-        }
-    };
-    
-    // Struggle is a typeless move with base power 50
-    // Create a temporary MoveData for Struggle to use with damage calculation
-    let struggle_move = crate::monsters::move_manager::MoveData {
-        id: 165,
-        name: "Struggle".to_string(),
-        accuracy: Some(100),
-        power: Some(50),
-        pp: 1,
-        priority: 0,
-        move_type: crate::monsters::PokemonType::Normal, // Typeless in effect, but Normal for calculation
-        damage_class: crate::monsters::move_manager::MoveCategory::Physical,
-        target: crate::monsters::move_manager::TargetType::NormalOpponent,
-        effect: crate::monsters::move_manager::EffectData::Damage { 
-            multi_hit: None,
-            crit_stage_bonus: None,
-            drain_percent: None,
-            recoil_damage_percent: Some(25), // 1/4 of damage dealt
-        },
-        secondary_effect: None,
-        description: "Used only if all PP are gone. Hurts the user.".to_string(),
-    };
-    
-    // Calculate damage using our formula
-    let (source_level, source_stats, source_types) = match source {
-        BattleEntityRef::Player { team_index } => {
-            let pokemon = &battle_state.player.team[team_index];
-            (pokemon.level, &pokemon.calculated_stats, &pokemon.pokemon_types)
-        },
-        BattleEntityRef::Wild { .. } => {
-            let pokemon = &battle_state.wild_pokemon;
-            (pokemon.level, &pokemon.calculated_stats, &pokemon.pokemon_types)
-        }
-        _ => {
-            panic!("Invalid source entity for Struggle");
-        }
-    };
-
-    let (target_stats, target_types) = match target {
-        BattleEntityRef::Player { team_index } => {
-            let pokemon = &battle_state.player.team[team_index];
-            (&pokemon.calculated_stats, &pokemon.pokemon_types)
-        },
-        BattleEntityRef::Wild { .. } => {
-            let pokemon = &battle_state.wild_pokemon;
-            (&pokemon.calculated_stats, &pokemon.pokemon_types)
-        }
-        _ => {
-            panic!("Invalid target entity for Struggle");
-        }
-    };
-
-    let (damage, effectiveness, is_critical) = calculate_damage(
-        source_level,
-        source_stats,
-        source_types,
-        target_stats,
-        target_types,
-        &struggle_move,
-        battle_state.move_repository.as_ref().map(|repo| &repo.type_chart) // Pass proper type chart from repository
-    );
-    
-    // Apply the calculated damage
-    if damage > 0 {
-        apply_damage_with_effectiveness(
-            battle_state,
-            battle_events,
-            target.clone(),
-            damage,
-            effectiveness,
-            is_critical
-        );
-        
-        // Add critical hit message
-        if is_critical {
-            battle_events.push(BattleEvent::GenericMessage { 
-                message: "A critical hit!".to_string() 
-            });
-        }
-        
-        // Calculate and apply recoil damage (1/4 of damage dealt)
-        let recoil_damage = damage / 4;
-        if recoil_damage > 0 {
-            battle_events.push(BattleEvent::GenericMessage { 
-                message: format!("{} was damaged by recoil!", source_name) 
-            });
-            
-            apply_damage_with_effectiveness(
-                battle_state,
-                battle_events,
-                source.clone(),
-                recoil_damage,
-                1.0, // Recoil is always neutral effectiveness
-                false // Recoil is never critical
-            );
-        }
-    }
-    
-    // Get struggle move ID and proper move name
-    let struggle_move_id = 165; // Standard ID for Struggle
-    
-    battle_events.push(BattleEvent::MoveUsed { 
-        source, 
-        move_id: struggle_move_id, 
-        move_name: "Struggle".to_string(), 
-        target 
-    });
-}
-
 /// Executes a switch
 fn execute_switch(
     battle_state: &mut WildBattleState, 
@@ -454,12 +195,8 @@ fn execute_switch(
     let outgoing_pokemon_name = battle_state.player.team[battle_state.player.active_pokemon_index].name.clone();
     let incoming_pokemon_name = battle_state.player.team[team_index].name.clone();
     
-    // TODO: Implement switch logic
-    // 1. Reset volatile statuses, stat stages for the outgoing Pokémon
-    // 2. Update active_pokemon_index
-    // 3. Apply entry hazards (Stealth Rock, Spikes) to incoming Pokémon
-    // 4. Trigger abilities on switch-in (Intimidate)
-    // 5. Add BattleEvents (SwitchOut, SwitchIn)
+    let outgoing = BattleEntityRef::Player { team_index: battle_state.player.active_pokemon_index };
+    reset_on_switch_out(battle_state, battle_events, &outgoing);
     battle_state.player.active_pokemon_index = team_index;
     
     // Add a descriptive message
@@ -476,12 +213,15 @@ fn execute_switch(
          current_hp_percent: new_pokemon.current_hp as f32 / new_pokemon.max_hp as f32,
          max_hp: new_pokemon.max_hp,
          types: new_pokemon.pokemon_types.clone(),
+         form: new_pokemon.form.clone(),
          status: new_pokemon.status.clone(),
          stat_modifiers: new_pokemon.stat_modifiers.clone(),
          is_fainted: new_pokemon.is_fainted,
          is_wild: false,
     };
     battle_events.push(BattleEvent::SwitchIn { pokemon_view: view, team_index });
+    apply_entry_hazards(battle_state, battle_events, &BattleEntityRef::Player { team_index });
+    on_switch_in(battle_state, battle_events, &BattleEntityRef::Player { team_index });
 }
 
 /// Executes item use
@@ -532,43 +272,115 @@ fn execute_item(
     });
 }
 
+/// Shake checks a ball makes; it catches only when every one passes
+const SHAKE_CHECKS: u32 = 4;
+/// Shake checks roll a random number below this
+const SHAKE_CHECK_RANGE: u32 = 65536;
+/// Modified catch rate from which a ball always catches
+const GUARANTEED_CATCH_RATE: f64 = 255.0;
+
+/// Multiplier a wild Pokémon's status puts on its catch rate
+fn status_catch_multiplier(status: Option<StatusCondition>) -> f64 {
+    match status {
+        Some(StatusCondition::Sleep | StatusCondition::Freeze) => 2.0,
+        Some(StatusCondition::Paralysis | StatusCondition::Poison | StatusCondition::Toxic | StatusCondition::Burn) => 1.5,
+        None => 1.0,
+    }
+}
+
+/// Odds of `ball` thrown at a wild Pokémon, with the values they come from. The species
+/// catch rate is scaled by the HP the Pokémon has left, from about a third at full HP up to
+/// all of it at 1 HP, then by the ball, status and encounter multipliers. The result out of
+/// 255 is the chance to catch, spread over four shake checks.
+pub fn capture_odds(
+    current_hp: u32,
+    max_hp: u32,
+    catch_rate: u8,
+    status: Option<StatusCondition>,
+    ball: &BallType,
+    encounter_modifier: Option<WildEncounterModifier>,
+) -> CaptureOdds {
+    let max_hp = max_hp.max(1);
+    let current_hp = current_hp.min(max_hp);
+    let hp_fraction = current_hp as f64 / max_hp as f64;
+    let ball_multiplier = ball.catch_multiplier();
+    let status_multiplier = status_catch_multiplier(status);
+    let encounter_multiplier = encounter_modifier.map_or(1.0, |modifier| modifier.capture_multiplier());
+    let hp_factor = (3 * max_hp - 2 * current_hp.saturating_sub(1)) as f64 / (3 * max_hp) as f64;
+    let modified_catch_rate = hp_factor * catch_rate as f64 * ball_multiplier * status_multiplier * encounter_multiplier;
+    let catch_probability = (modified_catch_rate / GUARANTEED_CATCH_RATE).min(1.0);
+    CaptureOdds {
+        catch_probability,
+        catch_rate,
+        hp_fraction,
+        ball_multiplier,
+        status_multiplier,
+        encounter_multiplier,
+        modified_catch_rate,
+        shake_threshold: shake_threshold(modified_catch_rate),
+    }
+}
+
+/// Threshold each shake check must roll under: 1048560 / (16711680 / a)^(1/4) for a modified
+/// catch rate `a`, so all four pass with a chance of a / 255
+fn shake_threshold(modified_catch_rate: f64) -> u32 {
+    if modified_catch_rate >= GUARANTEED_CATCH_RATE {
+        return SHAKE_CHECK_RANGE;
+    }
+    if modified_catch_rate <= 0.0 {
+        return 0;
+    }
+    (1_048_560.0 / (16_711_680.0 / modified_catch_rate).sqrt().sqrt()) as u32
+}
+
+/// Chance `ball` thrown at a wild Pokémon succeeds
+pub fn capture_chance(
+    current_hp: u32,
+    max_hp: u32,
+    catch_rate: u8,
+    status: Option<StatusCondition>,
+    ball: &BallType,
+    encounter_modifier: Option<WildEncounterModifier>,
+) -> f64 {
+    capture_odds(current_hp, max_hp, catch_rate, status, ball, encounter_modifier).catch_probability
+}
+
 /// Executes capture attempt
 fn execute_capture(
     battle_state: &mut WildBattleState, 
     battle_events: &mut Vec<BattleEvent>,
     ball_id: String
 ) {
-    // TODO: Implement capture logic
-    // 1. Check if target is wild
-    // 2. Calculate capture chance (HP, status, ball bonus, catch rate)
-    // 3. Simulate shakes
-    // 4. Determine success
-    // 5. Add BattleEvents (CaptureAttempt)
-    // 6. If successful, set battle phase to Finished
-    
     // Get player name and wild Pokémon name for better messages
     let player_name = battle_state.player.name.clone();
     let wild_pokemon_name = battle_state.wild_pokemon.name.clone();
     
     // Convert ball_id to a ball type and readable name
-    let (ball_type, ball_name) = match ball_id.as_str() {
-        "poke_ball" => (BallType::PokeBall, "Poké Ball"),
-        "great_ball" => (BallType::GreatBall, "Great Ball"),
-        "ultra_ball" => (BallType::UltraBall, "Ultra Ball"),
-        _ => (BallType::PokeBall, "Poké Ball"),
-    };
+    let (ball_type, ball_name) = BallType::from_item_id(&ball_id).unwrap_or((BallType::PokeBall, "Poké Ball"));
     
     battle_events.push(BattleEvent::GenericMessage { 
         message: format!("{} threw a {} at the wild {}!", player_name, ball_name, wild_pokemon_name) 
     });
     
-    // Calculate success chance based on HP percentage
-    let hp_percentage = battle_state.wild_pokemon.current_hp as f64 / battle_state.wild_pokemon.max_hp as f64;
-    let base_chance = 0.3; // 30% base chance
-    let hp_bonus = 0.4 * (1.0 - hp_percentage); // Up to 40% bonus for low HP
-    let success = rand::thread_rng().gen_bool(base_chance + hp_bonus);
-    
-    let shakes = if success { 3 } else { rand::thread_rng().gen_range(0..=2) };
+    let wild_pokemon = &battle_state.wild_pokemon;
+    let mut odds = capture_odds(
+        wild_pokemon.current_hp,
+        wild_pokemon.max_hp,
+        battle_state.wild_catch_rate,
+        wild_pokemon.status,
+        &ball_type,
+        battle_state.encounter_modifier,
+    );
+    if battle_state.scripted {
+        odds.catch_probability = 1.0;
+        odds.shake_threshold = SHAKE_CHECK_RANGE;
+    }
+    // The ball shakes once for every check passed before the first one fails
+    let passed_checks = with_rng(|rng| (0..SHAKE_CHECKS)
+        .take_while(|_| rng.gen_range(0..SHAKE_CHECK_RANGE) < odds.shake_threshold)
+        .count() as u32);
+    let success = passed_checks == SHAKE_CHECKS;
+    let shakes = passed_checks.min(3) as u8;
     
     let capture_event = BattleEvent::CaptureAttempt { ball_type: ball_type.clone(), shake_count: shakes, success };
     battle_events.push(capture_event.clone());
@@ -579,7 +391,7 @@ fn execute_capture(
         battle_events.push(BattleEvent::GenericMessage { 
             message: format!("Gotcha! {} was caught!", wild_pokemon_name) 
         });
-        battle_state.battle_phase = BattlePhase::Finished;
+        battle_state.battle_phase.transition(BattlePhase::Finished);
        
         
         
@@ -591,9 +403,27 @@ fn execute_capture(
             _ => format!("The {} broke free!", wild_pokemon_name),
         };
         battle_events.push(BattleEvent::GenericMessage { message: shake_message });
+        battle_events.push(BattleEvent::CapturePreview { odds });
     }
 }
 
+/// Throws bait or mud at the wild Pokémon, affecting flee and capture chances for the rest of the encounter
+fn execute_encounter_modifier(
+    battle_state: &mut WildBattleState,
+    battle_events: &mut Vec<BattleEvent>,
+    modifier: WildEncounterModifier,
+) {
+    let player_name = battle_state.player.name.clone();
+    let wild_pokemon_name = battle_state.wild_pokemon.name.clone();
+    battle_state.encounter_modifier = Some(modifier);
+
+    let message = match modifier {
+        WildEncounterModifier::Bait => format!("{} threw some bait! The wild {} is busy eating.", player_name, wild_pokemon_name),
+        WildEncounterModifier::Mud => format!("{} threw mud! The wild {} is slowed down.", player_name, wild_pokemon_name),
+    };
+    battle_events.push(BattleEvent::GenericMessage { message });
+}
+
 /// Executes run attempt
 fn execute_run(
     battle_state: &mut WildBattleState, 
@@ -619,7 +449,7 @@ fn execute_run(
     
     battle_events.push(BattleEvent::PlayerRanAway { success });
     if success {
-        battle_state.battle_phase = BattlePhase::Finished;
+        battle_state.battle_phase.transition(BattlePhase::Finished);
     }
 }
 
@@ -633,7 +463,8 @@ fn execute_wild_flee(
     // Get wild Pokémon name for better messages
     let wild_pokemon_name = battle_state.wild_pokemon.name.clone();
     
-    let success = rand::thread_rng().gen_bool(0.1); // Placeholder 10% chance
+    let flee_multiplier = battle_state.encounter_modifier.map_or(1.0, |modifier| modifier.flee_multiplier());
+    let success = with_rng(|rng| rng.gen_bool(0.1 * flee_multiplier)); // Placeholder 10% chance
     
     // Add descriptive message
     battle_events.push(BattleEvent::GenericMessage { 
@@ -642,7 +473,7 @@ fn execute_wild_flee(
     
      battle_events.push(BattleEvent::WildPokemonFled);
     if success {
-        battle_state.battle_phase = BattlePhase::Finished;
+        battle_state.battle_phase.transition(BattlePhase::Finished);
     }
 }
 
@@ -651,69 +482,15 @@ fn apply_end_of_turn_effects(
     battle_state: &mut WildBattleState, 
     battle_events: &mut Vec<BattleEvent>
 ) {
-    // TODO: Implement EOT logic
-    // 1. Weather damage/effects (Rain, Sun, Sand, Hail)
-    // 2. Status damage (Burn, Poison, Badly Poisoned)
-    // 3. Volatile status effects (Leech Seed drain, Bind damage, Confusion check/damage)
-    // 4. Field effect timer decrement (Reflect, Light Screen, Tailwind, Trick Room)
-    // 5. Status timer decrement (Sleep)
-    // 6. Add BattleEvents
-
-    // Example: Burn damage
-    let player_index = battle_state.player.active_pokemon_index;
-    let player_target = BattleEntityRef::Player { team_index: player_index };
-    let mut player_took_burn_damage = false;
-    let mut player_burn_damage_amount = 0;
-
-    // Check condition without holding the borrow for too long
-    if battle_state.player.team[player_index].status == Some(StatusCondition::Burn) {
-        let damage = battle_state.player.team[player_index].max_hp / 16; // Simplified burn damage
-        if damage > 0 { // Only apply if damage is non-zero
-            apply_damage(battle_state, battle_events, player_target.clone(), damage);
-            player_took_burn_damage = true;
-            player_burn_damage_amount = damage;
-        }
-    }
+    // TODO: Implement remaining EOT logic
+    // 1. Volatile status effects (Leech Seed drain, Bind damage, Confusion check/damage)
+    // 2. Field effect timer decrement (Reflect, Light Screen, Tailwind, Trick Room)
 
-    // Push event *after* apply_damage call
-    if player_took_burn_damage {
-        // Re-borrow immutably to get updated values
-        let player_pokemon = &battle_state.player.team[player_index];
-        battle_events.push(BattleEvent::StatusDamage { 
-            target: player_target, 
-            status: StatusCondition::Burn, 
-            damage: player_burn_damage_amount, 
-            new_hp: player_pokemon.current_hp, 
-            max_hp: player_pokemon.max_hp 
-        });
-    }
-    
-    let wild_target = BattleEntityRef::Wild;
-    let mut wild_took_burn_damage = false;
-    let mut wild_burn_damage_amount = 0;
-
-    // Check condition without holding the borrow for too long
-    if battle_state.wild_pokemon.status == Some(StatusCondition::Burn) {
-         let damage = battle_state.wild_pokemon.max_hp / 16; // Simplified burn damage
-         if damage > 0 {
-            apply_damage(battle_state, battle_events, wild_target.clone(), damage);
-            wild_took_burn_damage = true;
-            wild_burn_damage_amount = damage;
-         }
-    }
-    
-    // Push event *after* apply_damage call
-    if wild_took_burn_damage {
-        // Re-borrow immutably to get updated values
-        let wild_pokemon = &battle_state.wild_pokemon;
-        battle_events.push(BattleEvent::StatusDamage { 
-            target: wild_target, 
-            status: StatusCondition::Burn, 
-            damage: wild_burn_damage_amount, 
-            new_hp: wild_pokemon.current_hp, 
-            max_hp: wild_pokemon.max_hp 
-        });
-    }
+    apply_weather_end_of_turn(battle_state, battle_events);
+    apply_status_damage(battle_state, battle_events);
+    apply_end_of_turn_abilities(battle_state, battle_events);
+
+    update_weather_forms(battle_state, battle_events);
 }
 
 /// Checks for faints
@@ -722,6 +499,13 @@ fn check_faints(battle_state: &mut WildBattleState, battle_events: &mut Vec<Batt
     let mut fainted = false;
     let player_index = battle_state.player.active_pokemon_index;
     
+    // The tutorial battle cannot be lost
+    if battle_state.scripted && battle_state.player.team[player_index].current_hp == 0 {
+        let pokemon = &mut battle_state.player.team[player_index];
+        pokemon.current_hp = 1;
+        battle_events.push(BattleEvent::GenericMessage { message: format!("{} hung on!", pokemon.name) });
+    }
+
     // Check Player Pokemon
     if !battle_state.player.team[player_index].is_fainted && battle_state.player.team[player_index].current_hp == 0 {
         battle_state.player.team[player_index].is_fainted = true;
@@ -734,7 +518,7 @@ fn check_faints(battle_state: &mut WildBattleState, battle_events: &mut Vec<Batt
         battle_state.wild_pokemon.is_fainted = true;
         battle_events.push(BattleEvent::PokemonFainted { target: BattleEntityRef::Wild });
         // Wild fainted -> Battle End
-        battle_state.battle_phase = BattlePhase::Finished;
+        battle_state.battle_phase.transition(BattlePhase::Finished);
         fainted = true;
     }
     fainted
@@ -746,41 +530,102 @@ fn check_battle_end(battle_state: &mut WildBattleState) {
     // Check if all player Pokemon fainted
     let all_player_fainted = battle_state.player.team.iter().all(|p| p.is_fainted);
     if all_player_fainted {
-        battle_state.battle_phase = BattlePhase::Finished;
+        battle_state.battle_phase.transition(BattlePhase::Finished);
     }
 }
 
-/// Helper to apply damage and update HP (without effectiveness info)
-fn apply_damage(battle_state: &mut WildBattleState, battle_events: &mut Vec<BattleEvent>, target: BattleEntityRef, damage: u32) {
-     match target {
-         BattleEntityRef::Player { team_index } => {
-            let pokemon = &mut battle_state.player.team[team_index];
-            let new_hp = pokemon.current_hp.saturating_sub(damage);
-            pokemon.current_hp = new_hp;
-            // Push a simplified damage event if needed, or rely on the caller (like EOT effects) to push specific events
-            // For now, adding a placeholder event for consistency
-            battle_events.push(BattleEvent::DamageDealt { 
-                target: target.clone(), 
-                damage, 
-                new_hp: pokemon.current_hp, 
-                max_hp: pokemon.max_hp, 
-                effectiveness: 1.0, // Placeholder - This function doesn't calculate effectiveness
-                is_critical: false // Placeholder
-            });
-         },
-         BattleEntityRef::Wild => {
-            let pokemon = &mut battle_state.wild_pokemon;
-            let new_hp = pokemon.current_hp.saturating_sub(damage);
-            pokemon.current_hp = new_hp;
-             battle_events.push(BattleEvent::DamageDealt { 
-                target: target.clone(), 
-                damage, 
-                new_hp: pokemon.current_hp, 
-                max_hp: pokemon.max_hp, 
-                effectiveness: 1.0, // Placeholder
-                is_critical: false // Placeholder
-            });
-         },
-         _ => {}
-     }
-} 
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combat::state::{BattlePokemon, PhaseMachine};
+    use crate::combat::test_support::{repositories, test_pokemon, wild_battle};
+    use crate::monsters::PokemonType;
+
+    /// A battle waiting for the player to replace their fainted lead, with Stealth Rock on
+    /// their side and the given bench
+    fn waiting_for_replacement(bench: Vec<BattlePokemon>) -> WildBattleState {
+        let repositories = repositories();
+        let mut lead = test_pokemon("Lead", &[PokemonType::Normal], 80, &[]);
+        lead.current_hp = 0;
+        lead.is_fainted = true;
+        let mut battle_state = wild_battle(lead, test_pokemon("Wild", &[PokemonType::Normal], 80, &[]), &repositories);
+        battle_state.player.team.extend(bench);
+        battle_state.player.side_effects.stealth_rock = true;
+        battle_state.player.must_switch = true;
+        battle_state.battle_phase = PhaseMachine::new(battle_state.battle_id, BattlePhase::WaitingForSwitch);
+        battle_state
+    }
+
+    fn worn_down(name: &str, hp: u32) -> BattlePokemon {
+        let mut pokemon = test_pokemon(name, &[PokemonType::Normal], 80, &[]);
+        pokemon.current_hp = hp;
+        pokemon
+    }
+
+    #[test]
+    fn replacement_that_survives_hazards_gets_to_act() {
+        let mut battle_state = waiting_for_replacement(vec![worn_down("Healthy", 80)]);
+        process_replacement_switch(&mut battle_state, 1);
+        assert_eq!(battle_state.player.team[1].current_hp, 70);
+        assert_eq!(battle_state.battle_phase.get(), BattlePhase::WaitingForPlayerAction);
+        assert!(!battle_state.player.must_switch);
+    }
+
+    #[test]
+    fn replacement_knocked_out_by_hazards_must_be_replaced_again() {
+        let mut battle_state = waiting_for_replacement(vec![worn_down("Worn", 1), worn_down("Healthy", 80)]);
+        let events = process_replacement_switch(&mut battle_state, 1);
+        assert!(battle_state.player.team[1].is_fainted);
+        assert!(events.iter().any(|event| matches!(event, BattleEvent::PokemonFainted { target: BattleEntityRef::Player { team_index: 1 } })));
+        assert_eq!(battle_state.battle_phase.get(), BattlePhase::WaitingForSwitch);
+        assert!(battle_state.player.must_switch);
+    }
+
+    #[test]
+    fn last_pokemon_knocked_out_by_hazards_ends_the_battle() {
+        let mut battle_state = waiting_for_replacement(vec![worn_down("Worn", 1)]);
+        process_replacement_switch(&mut battle_state, 1);
+        assert_eq!(battle_state.battle_phase.get(), BattlePhase::Finished);
+    }
+
+    fn modified_catch_rate(current_hp: u32, catch_rate: u8, status: Option<StatusCondition>, ball: BallType) -> f64 {
+        capture_odds(current_hp, 100, catch_rate, status, &ball, None).modified_catch_rate
+    }
+
+    #[test]
+    fn highest_catch_rate_at_one_hp_always_catches() {
+        let odds = capture_odds(1, 100, 255, None, &BallType::PokeBall, None);
+        assert_eq!(odds.catch_probability, 1.0);
+        assert_eq!(odds.shake_threshold, SHAKE_CHECK_RANGE);
+        assert!(capture_chance(100, 100, 255, None, &BallType::PokeBall, None) < 0.35);
+    }
+
+    #[test]
+    fn sleep_and_freeze_double_the_catch_rate_other_statuses_add_half() {
+        let base = modified_catch_rate(50, 45, None, BallType::PokeBall);
+        for status in [StatusCondition::Sleep, StatusCondition::Freeze] {
+            assert_eq!(modified_catch_rate(50, 45, Some(status), BallType::PokeBall), base * 2.0);
+        }
+        for status in [StatusCondition::Paralysis, StatusCondition::Poison, StatusCondition::Toxic, StatusCondition::Burn] {
+            assert_eq!(modified_catch_rate(50, 45, Some(status), BallType::PokeBall), base * 1.5);
+        }
+    }
+
+    #[test]
+    fn better_balls_multiply_the_catch_rate() {
+        let base = modified_catch_rate(50, 45, None, BallType::PokeBall);
+        assert_eq!(modified_catch_rate(50, 45, None, BallType::GreatBall), base * 1.5);
+        assert_eq!(modified_catch_rate(50, 45, None, BallType::UltraBall), base * 2.0);
+        assert_eq!(capture_chance(100, 100, 3, None, &BallType::MasterBall, None), 1.0);
+    }
+
+    #[test]
+    fn four_shake_checks_pass_with_the_modified_catch_rate_out_of_255() {
+        assert_eq!(shake_threshold(255.0), SHAKE_CHECK_RANGE);
+        assert_eq!(shake_threshold(0.0), 0);
+        for modified_catch_rate in [1.0, 30.0, 100.0, 200.0, 254.0] {
+            let pass_all = (shake_threshold(modified_catch_rate) as f64 / SHAKE_CHECK_RANGE as f64).powi(SHAKE_CHECKS as i32);
+            assert!((pass_all - modified_catch_rate / 255.0).abs() < 0.001, "{} passes all checks with {}", modified_catch_rate, pass_all);
+        }
+    }
+}
@@ -1,25 +1,118 @@
-use crate::combat::state::{WildBattleState, PvPBattleState, BattlePlayer, BattlePokemon, BattlePhase, BattlePvPPhase, PlayerSideState, FieldState, BattlePokemonTeamOverview, BattlePokemonPrivateView, BattlePokemonPublicView, PlayerAction, WildBattleOutcome, BattleEndReason, SwitchReason, PvPBattleOutcome};
+use crate::combat::state::{WildBattleState, PvPBattleState, PhaseMachine, BattleRules, BattlePlayer, BattlePokemon, BattlePhase, BattlePvPPhase, PlayerSideState, FieldState, BattlePokemonTeamOverview, BattlePokemonPrivateView, BattlePokemonPublicView, PlayerAction, WildBattleOutcome, BattleEndReason, SwitchReason, PvPBattleOutcome, CapturedPokemonView};
 use crate::combat::{utils, BattleEvent};
-use crate::game_loop::pokemon_collection::{Pokemon, PokemonCollectionManager, PokemonUpdate};
+use crate::game_loop::pokemon_collection::{Pokemon, PokemonCollectionManager, StorageLocation, MAX_POKEMONS};
+use crate::game_loop::lobby_events::LobbyEventKind;
 use crate::lobby::Lobby;
 use crate::models::{DisplayPokemon, ServerMessage};
-use crate::monsters::monster::MonsterMove;
+use crate::monsters::monster::{Monster, DEFAULT_CATCH_RATE};
 use crate::monsters::monster_manager::MonsterTemplateRepository;
 use crate::combat::logic;
+use crate::combat::logic::battle_calculations::calculate_type_effectiveness;
+use crate::combat::state::{EffectivenessHint, MoveCategory};
+use crate::monsters::{MoveRepository, PokemonType};
+use crate::combat::listeners::{BattleOutcomeListener, WildBattleSummary, PvPBattleSummary, GymBattleSummary};
+use crate::combat::replay::{BattleReplay, ReplayFlag, TurnSnapshot, SUSPICIOUS_REJECTED_ACTIONS};
+use crate::combat::rng;
+use crate::combat::ai::{self, PracticeDifficulty};
+use crate::combat::fairness;
+use crate::combat::team_validation::{self, Banlist};
+use crate::combat::rental_teams::RentalTeams;
+use crate::combat::gyms::{GymLeader, Gyms};
+use crate::combat::draft::{DraftState, DRAFT_BANS_PER_PLAYER, DRAFT_PICK_SECS, DRAFT_TEAM_ID, DRAFT_TEAM_SIZE};
+use crate::combat::state::FLAT_RULES_LEVEL;
 
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use uuid::Uuid;
 use tracing::{info, error, warn};
-use rand;
+
+/// How long a disconnected PvP player has to reconnect before forfeiting
+pub const PVP_DISCONNECT_GRACE_SECS: u64 = 60;
+
+/// Wild battles waiting this long for the player's action are ended as if the player ran
+const WILD_BATTLE_IDLE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+
+/// Pause before each turn the server plays on auto-battle, so clients can show the previous one
+const AUTO_BATTLE_TURN_DELAY: std::time::Duration = std::time::Duration::from_millis(1500);
+
+/// Player ID prefix of the server bot in practice battles, followed by the battle ID
+const PRACTICE_BOT_ID_PREFIX: &str = "practice_bot:";
+const PRACTICE_BOT_NAME: &str = "Practice Bot";
+/// Player ID prefix of a gym leader played by the server bot, followed by the gym and battle IDs
+const GYM_LEADER_ID_PREFIX: &str = "gym_leader:";
+
+/// Whether a PvP participant is played by the server bot (the practice bot or a gym leader),
+/// which has no connection to send to
+fn is_server_bot(player_id: &str) -> bool {
+    player_id.starts_with(PRACTICE_BOT_ID_PREFIX) || player_id.starts_with(GYM_LEADER_ID_PREFIX)
+}
+
+/// The opponent the server bot plays in a practice or gym battle
+struct ServerBotOpponent {
+    player_id: String,
+    name: String,
+    team_id: String,
+    pokemons: Vec<Pokemon>,
+    difficulty: PracticeDifficulty,
+    gym_id: Option<String>,
+}
 
 /// Manages active battle instances
 pub struct BattleManager {
     // Maps battle ID to battle state
     active_battles: DashMap<Uuid, Arc<Mutex<WildBattleState>>>,
     active_pvp_battles: DashMap<Uuid, Arc<Mutex<PvPBattleState>>>, // New map for PvP battles
+    // Maps disconnected player ID to (PvP battle ID, disconnect token) during the reconnect grace period
+    pvp_disconnected_players: DashMap<String, (Uuid, Uuid)>,
+    // Maps player ID to the wild and PvP battles they are in, kept in step with the battle maps
+    player_battles: DashMap<String, Vec<Uuid>>,
     template_repository: Arc<MonsterTemplateRepository>,
+    // Subsystems notified whenever a battle finishes
+    outcome_listeners: std::sync::RwLock<Vec<Arc<dyn BattleOutcomeListener>>>,
+    // Players who asked for per-hit damage breakdown events
+    damage_breakdown_players: DashSet<String>,
+    // Players who enabled move effectiveness hints (loaded from their settings on join)
+    effectiveness_hint_players: DashSet<String>,
+    // Highest level each player's Pokémon obey at, from their badges (loaded on join)
+    obedience_level_caps: DashMap<String, u32>,
+    // Players and monsters flagged in_combat without a battle in the previous consistency sweep
+    suspected_combat_orphans: Mutex<HashSet<String>>,
+    // Number of player / monster in_combat flags repaired by the consistency sweep
+    repaired_player_flags: AtomicU64,
+    repaired_monster_flags: AtomicU64,
+    // Species and moves rejected in PvP battles that enforce the banlist
+    banlist: Banlist,
+    // Teams lent to players who cannot field six Pokémon in PvP
+    rental_teams: RentalTeams,
+    // Gym leaders players can challenge for badges
+    gyms: Gyms,
+    // Capture odds are left out of the events sent to players
+    hide_capture_odds: bool,
+    // Pick/ban drafts running before a PvP battle, and the draft each player is in
+    drafts: DashMap<Uuid, Arc<Mutex<DraftState>>>,
+    player_drafts: DashMap<String, Uuid>,
+}
+
+/// What a player brings to a PvP battle besides their team
+#[derive(Debug, Clone, Default)]
+pub struct PvPLoadout {
+    /// Type of the equipped crystal, which enables the player's super move
+    pub crystal_type: Option<PokemonType>,
+    /// Battle with a rental team instead of the player's own
+    pub use_rental: bool,
+    /// Team picked in a draft, used instead of the player's own
+    pub drafted_team: Option<Vec<Pokemon>>,
+}
+
+/// Totals of in_combat flags cleared by the consistency sweep since startup
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CombatRepairMetrics {
+    pub players_repaired: u64,
+    pub monsters_repaired: u64,
 }
 
 impl BattleManager {
@@ -28,21 +121,293 @@ impl BattleManager {
         BattleManager {
             active_battles: DashMap::new(),
             active_pvp_battles: DashMap::new(),
+            pvp_disconnected_players: DashMap::new(),
+            player_battles: DashMap::new(),
             template_repository,
+            outcome_listeners: std::sync::RwLock::new(Vec::new()),
+            damage_breakdown_players: DashSet::new(),
+            effectiveness_hint_players: DashSet::new(),
+            obedience_level_caps: DashMap::new(),
+            suspected_combat_orphans: Mutex::new(HashSet::new()),
+            repaired_player_flags: AtomicU64::new(0),
+            repaired_monster_flags: AtomicU64::new(0),
+            banlist: Banlist::default(),
+            rental_teams: RentalTeams::default(),
+            gyms: Gyms::default(),
+            hide_capture_odds: false,
+            drafts: DashMap::new(),
+            player_drafts: DashMap::new(),
+        }
+    }
+
+    /// Use `banlist` for PvP battles that enforce it
+    pub fn with_banlist(mut self, banlist: Banlist) -> Self {
+        self.banlist = banlist;
+        self
+    }
+
+    /// Lend `rental_teams` to players who ask for one in PvP
+    pub fn with_rental_teams(mut self, rental_teams: RentalTeams) -> Self {
+        self.rental_teams = rental_teams;
+        self
+    }
+
+    /// Open `gyms` for players to challenge
+    pub fn with_gyms(mut self, gyms: Gyms) -> Self {
+        self.gyms = gyms;
+        self
+    }
+
+    /// Gym leaders players can challenge
+    pub fn gyms(&self) -> &Gyms {
+        &self.gyms
+    }
+
+    /// Leave the odds of failed capture attempts out of battle events when `hidden`
+    pub fn with_capture_odds_hidden(mut self, hidden: bool) -> Self {
+        self.hide_capture_odds = hidden;
+        self
+    }
+
+    /// Record that a player takes part in a battle
+    fn index_battle(&self, player_id: &str, battle_id: Uuid) {
+        self.player_battles.entry(player_id.to_string()).or_default().push(battle_id);
+    }
+
+    /// Forget a player's participation in a battle, dropping the entry once they have none left
+    fn unindex_battle(&self, player_id: &str, battle_id: Uuid) {
+        self.player_battles.remove_if_mut(player_id, |_, battles| {
+            battles.retain(|id| *id != battle_id);
+            battles.is_empty()
+        });
+    }
+
+    /// How many stuck in_combat flags the consistency sweep has repaired
+    pub fn combat_repair_metrics(&self) -> CombatRepairMetrics {
+        CombatRepairMetrics {
+            players_repaired: self.repaired_player_flags.load(Ordering::Relaxed),
+            monsters_repaired: self.repaired_monster_flags.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Compare the lobbies' in_combat flags against the active battles and clear the orphans.
+    ///
+    /// A flag is only cleared once it has been orphaned for two sweeps in a row, so players
+    /// who are flagged right before their battle is registered are left alone.
+    pub async fn repair_orphaned_combat_flags(&self, lobbies: &DashMap<String, Arc<Lobby>>) -> CombatRepairMetrics {
+        // Everyone currently taking part in a battle or drafting for one
+        let battling_players: HashSet<String> = self.player_battles.iter().map(|entry| entry.key().clone())
+            .chain(self.player_drafts.iter().map(|entry| entry.key().clone()))
+            .collect();
+        let mut battling_monsters = HashSet::new();
+        let wild_battles: Vec<Arc<Mutex<WildBattleState>>> = self.active_battles.iter().map(|entry| entry.value().clone()).collect();
+        for battle_mutex in wild_battles {
+            let battle_state = battle_mutex.lock().await;
+            battling_monsters.insert(battle_state.wild_pokemon.instance_id.clone());
+        }
+
+        let mut suspected = self.suspected_combat_orphans.lock().await;
+        let mut still_suspected = HashSet::new();
+        let mut repaired = CombatRepairMetrics { players_repaired: 0, monsters_repaired: 0 };
+        let lobbies: Vec<Arc<Lobby>> = lobbies.iter().map(|entry| entry.value().clone()).collect();
+
+        for lobby in lobbies {
+            for mut player_state in lobby.player_positions.iter_mut() {
+                if !player_state.in_combat || battling_players.contains(player_state.key()) {
+                    continue;
+                }
+                let key = format!("player:{}", player_state.key());
+                if suspected.contains(&key) {
+                    player_state.in_combat = false;
+                    warn!("Cleared stuck in_combat flag of player {} in lobby {}", player_state.key(), lobby.id);
+                    repaired.players_repaired += 1;
+                } else {
+                    still_suspected.insert(key);
+                }
+            }
+
+            let monsters: Vec<(String, Arc<Mutex<Monster>>)> = lobby.active_monsters.iter()
+                .map(|entry| (entry.key().clone(), entry.value().clone()))
+                .collect();
+            for (monster_id, monster_mutex) in monsters {
+                let mut monster = monster_mutex.lock().await;
+                if !monster.in_combat || battling_monsters.contains(&monster_id) {
+                    continue;
+                }
+                let key = format!("monster:{}", monster_id);
+                if suspected.contains(&key) {
+                    monster.in_combat = false;
+                    warn!("Cleared stuck in_combat flag of monster {} in lobby {}", monster_id, lobby.id);
+                    repaired.monsters_repaired += 1;
+                } else {
+                    still_suspected.insert(key);
+                }
+            }
+        }
+        *suspected = still_suspected;
+
+        self.repaired_player_flags.fetch_add(repaired.players_repaired, Ordering::Relaxed);
+        self.repaired_monster_flags.fetch_add(repaired.monsters_repaired, Ordering::Relaxed);
+        repaired
+    }
+
+    /// Re-simulate a finished PvP battle from its transcript and check it against the disclosed seed
+    pub fn verify_battle(&self, transcript: &fairness::BattleTranscript, disclosure: &fairness::FairnessDisclosure) -> fairness::VerificationReport {
+        fairness::verify(transcript, disclosure, &self.template_repository)
+    }
+
+    /// Turn damage breakdown events on or off for a player
+    pub fn set_damage_breakdown(&self, player_id: &str, enabled: bool) {
+        if enabled {
+            self.damage_breakdown_players.insert(player_id.to_string());
+        } else {
+            self.damage_breakdown_players.remove(player_id);
+        }
+    }
+
+    /// Turn move effectiveness hints on or off for a player
+    pub fn set_effectiveness_hints(&self, player_id: &str, enabled: bool) {
+        if enabled {
+            self.effectiveness_hint_players.insert(player_id.to_string());
+        } else {
+            self.effectiveness_hint_players.remove(player_id);
         }
     }
 
-    /// Start a PvP battle between two players
+    /// Set the highest level a player's Pokémon obey at, or forget it with None. Pokémon of
+    /// players without a known cap always obey.
+    pub fn set_obedience_level_cap(&self, player_id: &str, level_cap: Option<u32>) {
+        match level_cap {
+            Some(level_cap) => {
+                self.obedience_level_caps.insert(player_id.to_string(), level_cap);
+            }
+            None => {
+                self.obedience_level_caps.remove(player_id);
+            }
+        }
+    }
+
+    /// Give the battle copies in `team` of the player's own `pokemons` the level they obey at,
+    /// which is lower for Pokémon the player got in a trade
+    fn apply_obedience(&self, player_id: &str, pokemons: &[Pokemon], team: &mut [BattlePokemon]) {
+        let Some(level_cap) = self.obedience_level_caps.get(player_id).map(|cap| *cap) else {
+            return;
+        };
+        for (pokemon, battle_pokemon) in pokemons.iter().zip(team.iter_mut()) {
+            battle_pokemon.obedience_level = Some(logic::obedience::obedience_level(level_cap, pokemon.is_traded(player_id)));
+        }
+    }
+
+    /// Private view of a player's active Pokémon, with effectiveness hints against
+    /// the opponent's Pokémon if the player enabled them
+    fn private_view_for(
+        &self,
+        player_id: &str,
+        pokemon: &BattlePokemon,
+        opponent: &BattlePokemon,
+        move_repository: Option<&Arc<MoveRepository>>,
+    ) -> BattlePokemonPrivateView {
+        let mut view = BattlePokemonPrivateView::from_battle_pokemon(pokemon, move_repository);
+        if self.effectiveness_hint_players.contains(player_id) {
+            let type_chart = move_repository.map(|repo| &repo.type_chart);
+            for battle_move in view.moves.iter_mut().filter(|m| m.category != MoveCategory::Status) {
+                let multiplier = calculate_type_effectiveness(type_chart, &battle_move.move_type, &opponent.pokemon_types);
+                battle_move.effectiveness = Some(EffectivenessHint::from_multiplier(multiplier));
+            }
+        }
+        view
+    }
+
+    /// Events of a turn as a given player should receive them
+    fn events_for_player(&self, player_id: &str, events: &[BattleEvent]) -> Vec<BattleEvent> {
+        let show_breakdown = self.damage_breakdown_players.contains(player_id);
+        events.iter()
+            .filter(|event| match event {
+                BattleEvent::DamageBreakdown { .. } => show_breakdown,
+                BattleEvent::CapturePreview { .. } => !self.hide_capture_odds,
+                _ => true,
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Register a listener that is notified whenever a battle finishes
+    pub fn register_outcome_listener(&self, listener: Arc<dyn BattleOutcomeListener>) {
+        self.outcome_listeners.write().unwrap().push(listener);
+    }
+
+    fn notify_wild_battle_finished(&self, summary: &WildBattleSummary) {
+        let listeners = self.outcome_listeners.read().unwrap().clone();
+        for listener in listeners {
+            listener.on_wild_battle_finished(summary);
+        }
+    }
+
+    /// The Pokémon a player brings to a PvP battle: their active team, or a freshly lent rental team
+    /// when they asked for one and cannot field six Pokémon. Also returns the rental team's ID.
+    async fn pvp_team(
+        &self,
+        player_id: &str,
+        loadout: &mut PvPLoadout,
+        pokemon_collection_manager: &Arc<PokemonCollectionManager>,
+    ) -> Result<(Vec<Pokemon>, Option<String>), String> {
+        if let Some(drafted_team) = loadout.drafted_team.take() {
+            return Ok((drafted_team, Some(DRAFT_TEAM_ID.to_string())));
+        }
+        let use_rental = loadout.use_rental;
+        let pokemons = pokemon_collection_manager.get_active_pokemons(player_id).await
+            .map_err(|e| format!("Failed to fetch Pokémon for player {}: {}", player_id, e))?;
+        if use_rental {
+            if pokemons.len() >= MAX_POKEMONS {
+                return Err(format!("Player {} has a full team and cannot use a rental team", player_id));
+            }
+            let (rental_team_id, rental_pokemons) = self.rental_teams.lend_team(&self.template_repository, None)
+                .ok_or_else(|| "No rental teams are available".to_string())?;
+            info!("Player {} is using rental team {}", player_id, rental_team_id);
+            return Ok((rental_pokemons, Some(rental_team_id)));
+        }
+        if pokemons.is_empty() {
+            return Err(format!("Player {} has no active Pokémon", player_id));
+        }
+        Ok((pokemons, None))
+    }
+
+    /// Send a message to a PvP participant, skipping the server bot
+    async fn send_to_battler(&self, lobby: &Arc<Lobby>, player_id: &str, message: &ServerMessage) -> Result<(), String> {
+        if is_server_bot(player_id) {
+            return Ok(());
+        }
+        lobby.send_to_player(player_id, message).await
+    }
+
+    fn notify_pvp_battle_finished(&self, summary: &PvPBattleSummary) {
+        let listeners = self.outcome_listeners.read().unwrap().clone();
+        for listener in listeners {
+            listener.on_pvp_battle_finished(summary);
+        }
+    }
+
+    fn notify_gym_battle_finished(&self, summary: &GymBattleSummary) {
+        let listeners = self.outcome_listeners.read().unwrap().clone();
+        for listener in listeners {
+            listener.on_gym_battle_finished(summary);
+        }
+    }
+
+    /// Start a PvP battle between two players, each bringing their `loadouts`
     pub async fn start_pvp_battle(
         &self,
         player1_id: &str,
         player2_id: &str,
         lobby: &Arc<Lobby>,
         pokemon_collection_manager: &Arc<PokemonCollectionManager>,
+        rules: BattleRules,
+        mut loadouts: (PvPLoadout, PvPLoadout),
     ) -> Result<Uuid, String> {
         // Generate a new battle ID
         let battle_id = Uuid::new_v4();
-        info!("Starting PvP battle {}: player {} vs player {}", battle_id, player1_id, player2_id);
+        info!("Starting PvP battle {}: player {} vs player {} with rules {:?}", battle_id, player1_id, player2_id, rules);
+        rules.validate()?;
         
         // 1. Get player usernames for messaging
         let player1_username = match lobby.player_positions.get(player1_id) {
@@ -55,40 +420,68 @@ impl BattleManager {
             None => return Err(format!("Player {} not found in lobby", player2_id)),
         };
         
-        // 2. Fetch both players' active Pokémon
-        let player1_pokemons = match pokemon_collection_manager.get_active_pokemons(player1_id).await {
-            Ok(pokemons) => {
-                if pokemons.is_empty() {
-                    return Err(format!("Player {} has no active Pokémon", player1_id));
-                }
-                pokemons
-            },
-            Err(e) => return Err(format!("Failed to fetch Pokémon for player {}: {}", player1_id, e)),
-        };
-        
-        let player2_pokemons = match pokemon_collection_manager.get_active_pokemons(player2_id).await {
-            Ok(pokemons) => {
-                if pokemons.is_empty() {
-                    return Err(format!("Player {} has no active Pokémon", player2_id));
-                }
-                pokemons
-            },
-            Err(e) => return Err(format!("Failed to fetch Pokémon for player {}: {}", player2_id, e)),
-        };
+        // 2. Fetch both players' active Pokémon, or the rental teams they asked for
+        let (player1_pokemons, player1_rental_team) = self.pvp_team(player1_id, &mut loadouts.0, pokemon_collection_manager).await?;
+        let (player2_pokemons, player2_rental_team) = self.pvp_team(player2_id, &mut loadouts.1, pokemon_collection_manager).await?;
         
         // 3. Convert Pokémon to battle format
-        let battle_pokemon1 = player1_pokemons.iter().enumerate()
+        let mut battle_pokemon1 = player1_pokemons.iter().enumerate()
             .map(|(idx, pokemon)| {
                 utils::convert_player_pokemon_to_battle_pokemon(pokemon, idx, &self.template_repository)
             })
             .collect::<Vec<_>>();
         
-        let battle_pokemon2 = player2_pokemons.iter().enumerate()
+        let mut battle_pokemon2 = player2_pokemons.iter().enumerate()
             .map(|(idx, pokemon)| {
                 utils::convert_player_pokemon_to_battle_pokemon(pokemon, idx, &self.template_repository)
             })
             .collect::<Vec<_>>();
         
+        // Reject teams that break the agreed rules before anyone is put in battle
+        let violations1 = team_validation::validate_team(&battle_pokemon1, &rules, &self.banlist, &self.template_repository);
+        let violations2 = team_validation::validate_team(&battle_pokemon2, &rules, &self.banlist, &self.template_repository);
+        if !violations1.is_empty() || !violations2.is_empty() {
+            info!(
+                "Rejected PvP battle between {} and {}: {} and {} team violations",
+                player1_id, player2_id, violations1.len(), violations2.len()
+            );
+            let rejected_msgs = [
+                (player1_id, &player1_username, violations1),
+                (player2_id, &player2_username, violations2),
+            ]
+                .into_iter()
+                .filter(|(_, _, violations)| !violations.is_empty())
+                .map(|(player_id, username, violations)| ServerMessage::PvPTeamRejected {
+                    player_id: player_id.to_string(),
+                    username: username.clone(),
+                    violations,
+                })
+                .collect::<Vec<_>>();
+            for msg in &rejected_msgs {
+                for recipient in [player1_id, player2_id] {
+                    if let Err(e) = lobby.send_to_player(recipient, msg).await {
+                        error!("Failed to send team rejection to player {}: {}", recipient, e);
+                    }
+                }
+            }
+            return Err("A team breaks the battle rules".to_string());
+        }
+
+        // Flat rules only change the battle copies, after the level cap was checked against real levels
+        if let Some(flat_level) = rules.flat_level {
+            for battle_pokemon in battle_pokemon1.iter_mut().chain(battle_pokemon2.iter_mut()) {
+                utils::normalize_battle_pokemon_level(battle_pokemon, flat_level, &self.template_repository);
+            }
+        } else {
+            // Rental and drafted Pokémon always obey
+            if player1_rental_team.is_none() {
+                self.apply_obedience(player1_id, &player1_pokemons, &mut battle_pokemon1);
+            }
+            if player2_rental_team.is_none() {
+                self.apply_obedience(player2_id, &player2_pokemons, &mut battle_pokemon2);
+            }
+        }
+
         // 4. Create BattlePlayer structs for both players
         let battle_player1 = BattlePlayer {
             player_id: player1_id.to_string(),
@@ -98,6 +491,11 @@ impl BattleManager {
             side_effects: PlayerSideState::default(),
             last_action_submitted: None,
             must_switch: false,
+            sent_team_overview: Vec::new(),
+            team_revision: 0,
+            crystal_type: loadouts.0.crystal_type,
+            super_move_used: false,
+            rental_team: player1_rental_team,
         };
         
         let battle_player2 = BattlePlayer {
@@ -108,19 +506,33 @@ impl BattleManager {
             side_effects: PlayerSideState::default(),
             last_action_submitted: None,
             must_switch: false,
+            sent_team_overview: Vec::new(),
+            team_revision: 0,
+            crystal_type: loadouts.1.crystal_type,
+            super_move_used: false,
+            rental_team: player2_rental_team,
         };
         
         // 5. Create the PvP battle state
-        let pvp_battle_state = PvPBattleState::new(
+        let mut pvp_battle_state = PvPBattleState::new(
             battle_id,
             battle_player1,
             battle_player2,
             self.template_repository.move_repository.clone(),
+            self.template_repository.ability_repository.clone(),
         );
+        pvp_battle_state.field_state.rules = rules;
         
         // 6. Store the battle in the manager
         let battle_mutex = Arc::new(Mutex::new(pvp_battle_state));
         self.active_pvp_battles.insert(battle_id, battle_mutex.clone());
+        self.index_battle(player1_id, battle_id);
+        self.index_battle(player2_id, battle_id);
+        lobby.events.record(LobbyEventKind::PvPBattleStarted {
+            battle_id,
+            player1_id: player1_id.to_string(),
+            player2_id: player2_id.to_string(),
+        });
         
         // Mark both players as in combat
         if let Some(mut player1_state) = lobby.player_positions.get_mut(player1_id) {
@@ -150,14 +562,17 @@ impl BattleManager {
         
         // 7.2 Active Pokémon details
         let active_pokemon1 = &battle_state.player1.team[battle_state.player1.active_pokemon_index];
-        let active_pokemon1_private_view = BattlePokemonPrivateView::from_battle_pokemon(
+        let active_pokemon2 = &battle_state.player2.team[battle_state.player2.active_pokemon_index];
+        let active_pokemon1_private_view = self.private_view_for(
+            player1_id,
             active_pokemon1,
+            active_pokemon2,
             self.template_repository.move_repository.as_ref()
         );
-        
-        let active_pokemon2 = &battle_state.player2.team[battle_state.player2.active_pokemon_index];
-        let active_pokemon2_private_view = BattlePokemonPrivateView::from_battle_pokemon(
+        let active_pokemon2_private_view = self.private_view_for(
+            player2_id,
             active_pokemon2,
+            active_pokemon1,
             self.template_repository.move_repository.as_ref()
         );
         
@@ -167,6 +582,7 @@ impl BattleManager {
         
         // 7.4 Create field state
         let field_state = FieldState::default();
+        let seed_commitment = fairness::seed_commitment(battle_state.rng_seed);
                 
         // Release battle state lock
         drop(battle_state);
@@ -183,6 +599,7 @@ impl BattleManager {
             initial_field_state: field_state.clone(),
             player1_id: player1_id.to_string(),
             player2_id: player2_id.to_string(),
+            seed_commitment: seed_commitment.clone(),
         };
         
         if let Err(e) = lobby.send_to_player(player1_id, &pvp_start_message1).await {
@@ -196,6 +613,8 @@ impl BattleManager {
             }
             // Remove the battle from the active_pvp_battles map
             self.active_pvp_battles.remove(&battle_id);
+            self.unindex_battle(player1_id, battle_id);
+            self.unindex_battle(player2_id, battle_id);
             return Err(format!("Failed to send battle start message: {}", e));
         }
         
@@ -210,6 +629,7 @@ impl BattleManager {
             initial_field_state: field_state.clone(),
             player1_id: player1_id.to_string(),
             player2_id: player2_id.to_string(),
+            seed_commitment,
         };
         
         if let Err(e) = lobby.send_to_player(player2_id, &pvp_start_message2).await {
@@ -223,6 +643,8 @@ impl BattleManager {
             }
             // Remove the battle from the active_pvp_battles map
             self.active_pvp_battles.remove(&battle_id);
+            self.unindex_battle(player1_id, battle_id);
+            self.unindex_battle(player2_id, battle_id);
             return Err(format!("Failed to send battle start message: {}", e));
         }
         
@@ -234,19 +656,413 @@ impl BattleManager {
         
         // For now, just updating the battle state to indicate waiting for both players
         let mut battle_state = battle_mutex.lock().await;
-        battle_state.battle_phase = BattlePvPPhase::WaitingForBothPlayersActions;
+        battle_state.battle_phase.transition(BattlePvPPhase::WaitingForBothPlayersActions);
         drop(battle_state);
         
         Ok(battle_id)
     }
 
-    /// Start a wild battle between a player and a monster
+    /// Open a pick/ban draft between two players who accepted a draft challenge. Both teams are
+    /// drafted from a shared pool, and the PvP battle starts with them once the draft is over.
+    pub async fn start_draft(
+        self: &Arc<Self>,
+        player1_id: &str,
+        player2_id: &str,
+        lobby: &Arc<Lobby>,
+        pokemon_collection_manager: &Arc<PokemonCollectionManager>,
+        rules: BattleRules,
+        crystal_types: [Option<PokemonType>; 2],
+    ) -> Result<Uuid, String> {
+        rules.validate()?;
+        if self.player_drafts.contains_key(player1_id) || self.player_drafts.contains_key(player2_id) {
+            return Err("A player is already in a draft".to_string());
+        }
+        // Drafted Pokémon are created at the flat rules level, lowered to the level cap if there is one
+        let level = rules.flat_level.unwrap_or(FLAT_RULES_LEVEL).min(rules.level_cap.unwrap_or(u32::MAX)).max(1);
+        let draft_state = DraftState::new(
+            [player1_id.to_string(), player2_id.to_string()],
+            crystal_types,
+            rules,
+            level,
+            &self.template_repository,
+            &self.banlist,
+        )?;
+        let draft_id = draft_state.draft_id;
+        info!("Starting draft {} between {} and {} at level {}", draft_id, player1_id, player2_id, level);
+
+        let started_msg = ServerMessage::DraftStarted {
+            draft_id,
+            player_ids: draft_state.player_ids.clone(),
+            pool: draft_state.pool.clone(),
+            level,
+            bans_per_player: DRAFT_BANS_PER_PLAYER,
+            team_size: DRAFT_TEAM_SIZE,
+            pick_secs: DRAFT_PICK_SECS,
+        };
+        for player_id in [player1_id, player2_id] {
+            if let Err(e) = lobby.send_to_player(player_id, &started_msg).await {
+                error!("Failed to send DraftStarted to player {}: {}", player_id, e);
+            }
+        }
+
+        let draft_mutex = Arc::new(Mutex::new(draft_state));
+        self.drafts.insert(draft_id, draft_mutex.clone());
+        self.player_drafts.insert(player1_id.to_string(), draft_id);
+        self.player_drafts.insert(player2_id.to_string(), draft_id);
+        self.announce_draft_turn(&mut *draft_mutex.lock().await, lobby).await;
+
+        let battle_manager = self.clone();
+        let lobby = lobby.clone();
+        let pokemon_collection_manager = pokemon_collection_manager.clone();
+        tokio::spawn(async move {
+            battle_manager.run_draft_clock(draft_id, lobby, pokemon_collection_manager).await;
+        });
+        Ok(draft_id)
+    }
+
+    /// Ban or pick a species for a player on the clock in a draft
+    pub async fn handle_draft_selection(
+        &self,
+        player_id: &str,
+        draft_id: Uuid,
+        template_id: u32,
+        lobby: &Arc<Lobby>,
+        pokemon_collection_manager: &Arc<PokemonCollectionManager>,
+    ) -> Result<(), String> {
+        let draft_mutex = self.drafts.get(&draft_id)
+            .map(|entry| entry.value().clone())
+            .ok_or_else(|| format!("Draft {} not found", draft_id))?;
+        let mut draft_state = draft_mutex.lock().await;
+        let action = draft_state.select(player_id, template_id)?;
+        let selection_msg = ServerMessage::DraftSelection {
+            draft_id,
+            player_id: player_id.to_string(),
+            action,
+            template_id,
+            timed_out: false,
+        };
+        self.send_to_drafters(&draft_state, &selection_msg, lobby).await;
+        self.advance_draft(draft_state, lobby, pokemon_collection_manager).await;
+        Ok(())
+    }
+
+    /// Make the selection for players who run out of time, until the draft is over.
+    /// The draft is called off when either player has left the lobby.
+    async fn run_draft_clock(
+        &self,
+        draft_id: Uuid,
+        lobby: Arc<Lobby>,
+        pokemon_collection_manager: Arc<PokemonCollectionManager>,
+    ) {
+        loop {
+            let Some(draft_mutex) = self.drafts.get(&draft_id).map(|entry| entry.value().clone()) else {
+                return;
+            };
+            let (turn, remaining_secs) = {
+                let draft_state = draft_mutex.lock().await;
+                (draft_state.turn, draft_state.remaining_secs(chrono::Utc::now().timestamp() as u64))
+            };
+            tokio::time::sleep(std::time::Duration::from_secs(remaining_secs.max(1))).await;
+
+            let mut draft_state = draft_mutex.lock().await;
+            if !self.drafts.contains_key(&draft_id) || draft_state.turn != turn {
+                continue;
+            }
+            if let Some(absent_id) = draft_state.player_ids.iter().find(|player_id| !lobby.player_positions.contains_key(*player_id)) {
+                info!("Draft {} cancelled, player {} left", draft_id, absent_id);
+                self.cancel_draft(&draft_state, &lobby).await;
+                return;
+            }
+            let (Some((action, side)), Some(template_id)) = (draft_state.current_turn(), draft_state.random_available()) else {
+                return;
+            };
+            let player_id = draft_state.player_ids[side].clone();
+            if let Err(e) = draft_state.select(&player_id, template_id) {
+                error!("Failed to make timed out draft selection for player {} in draft {}: {}", player_id, draft_id, e);
+                return;
+            }
+            let selection_msg = ServerMessage::DraftSelection { draft_id, player_id, action, template_id, timed_out: true };
+            self.send_to_drafters(&draft_state, &selection_msg, &lobby).await;
+            self.advance_draft(draft_state, &lobby, &pokemon_collection_manager).await;
+        }
+    }
+
+    /// Call off the draft a disconnecting player is in right away, instead of when their turn times out
+    pub fn abandon_draft(self: &Arc<Self>, player_id: &str, lobby: &Arc<Lobby>) {
+        let Some(draft_id) = self.player_drafts.get(player_id).map(|entry| *entry.value()) else {
+            return;
+        };
+        let battle_manager = self.clone();
+        let lobby = lobby.clone();
+        let player_id = player_id.to_string();
+        tokio::spawn(async move {
+            let Some(draft_mutex) = battle_manager.drafts.get(&draft_id).map(|entry| entry.value().clone()) else {
+                return;
+            };
+            let draft_state = draft_mutex.lock().await;
+            // The draft may have finished while we waited for the lock
+            if !battle_manager.drafts.contains_key(&draft_id) {
+                return;
+            }
+            info!("Draft {} cancelled, player {} disconnected", draft_id, player_id);
+            battle_manager.cancel_draft(&draft_state, &lobby).await;
+        });
+    }
+
+    /// Tell both drafters the draft is off and free them to move again
+    async fn cancel_draft(&self, draft_state: &DraftState, lobby: &Arc<Lobby>) {
+        let cancelled_msg = ServerMessage::DraftCancelled { draft_id: draft_state.draft_id, reason: "A player left the draft".to_string() };
+        self.send_to_drafters(draft_state, &cancelled_msg, lobby).await;
+        self.forget_draft(draft_state);
+        for player_id in &draft_state.player_ids {
+            if let Some(mut player_state) = lobby.player_positions.get_mut(player_id) {
+                player_state.value_mut().in_combat = false;
+            }
+        }
+    }
+
+    /// Move a draft on after a selection: announce the next turn, or start the battle once both teams are full
+    async fn advance_draft(
+        &self,
+        mut draft_state: tokio::sync::MutexGuard<'_, DraftState>,
+        lobby: &Arc<Lobby>,
+        pokemon_collection_manager: &Arc<PokemonCollectionManager>,
+    ) {
+        if !draft_state.is_complete() {
+            self.announce_draft_turn(&mut draft_state, lobby).await;
+            return;
+        }
+        let draft_id = draft_state.draft_id;
+        // The drafters stay flagged in_combat while the battle is set up
+        self.forget_draft(&draft_state);
+        let [team1, team2] = draft_state.picks.clone().map(|picks| picks.into_iter()
+            .map(|template_id| self.template_repository.pokemon_from_template(template_id, Some(draft_state.level)))
+            .collect::<Vec<_>>());
+        let [crystal1, crystal2] = draft_state.crystal_types;
+        let [player1_id, player2_id] = draft_state.player_ids.clone();
+        let rules = draft_state.rules;
+        drop(draft_state);
+
+        let loadouts = (
+            PvPLoadout { crystal_type: crystal1, use_rental: false, drafted_team: Some(team1) },
+            PvPLoadout { crystal_type: crystal2, use_rental: false, drafted_team: Some(team2) },
+        );
+        match self.start_pvp_battle(&player1_id, &player2_id, lobby, pokemon_collection_manager, rules, loadouts).await {
+            Ok(battle_id) => info!("Draft {} finished, PvP battle {} started", draft_id, battle_id),
+            Err(e) => {
+                error!("Failed to start PvP battle after draft {}: {}", draft_id, e);
+                let error_msg = ServerMessage::Error { message: format!("Failed to start battle: {}", e) };
+                for player_id in [&player1_id, &player2_id] {
+                    if let Err(send_err) = lobby.send_to_player(player_id, &error_msg).await {
+                        error!("Failed to send error message to player {}: {}", player_id, send_err);
+                    }
+                    if let Some(mut player_state) = lobby.player_positions.get_mut(player_id) {
+                        player_state.value_mut().in_combat = false;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Start the clock on the next ban or pick and tell both drafters whose turn it is
+    async fn announce_draft_turn(&self, draft_state: &mut DraftState, lobby: &Arc<Lobby>) {
+        let Some((action, side)) = draft_state.current_turn() else {
+            return;
+        };
+        draft_state.start_clock(chrono::Utc::now().timestamp() as u64);
+        let turn_msg = ServerMessage::DraftTurn {
+            draft_id: draft_state.draft_id,
+            player_id: draft_state.player_ids[side].clone(),
+            action,
+            deadline: draft_state.deadline,
+        };
+        self.send_to_drafters(draft_state, &turn_msg, lobby).await;
+    }
+
+    async fn send_to_drafters(&self, draft_state: &DraftState, message: &ServerMessage, lobby: &Arc<Lobby>) {
+        for player_id in &draft_state.player_ids {
+            if let Err(e) = lobby.send_to_player(player_id, message).await {
+                error!("Failed to send draft {} update to player {}: {}", draft_state.draft_id, player_id, e);
+            }
+        }
+    }
+
+    /// Stop tracking a draft that is over or called off
+    fn forget_draft(&self, draft_state: &DraftState) {
+        self.drafts.remove(&draft_state.draft_id);
+        for player_id in &draft_state.player_ids {
+            self.player_drafts.remove_if(player_id, |_, id| *id == draft_state.draft_id);
+        }
+    }
+
+    /// Start a practice battle in which the server bot plays player 2 at `difficulty`, with a rental
+    /// team around the level of the player's strongest Pokémon. Nothing from a practice battle is
+    /// saved, and rankings, payouts and history ignore it.
+    pub async fn start_practice_battle(
+        &self,
+        player_id: &str,
+        difficulty: PracticeDifficulty,
+        lobby: &Arc<Lobby>,
+        pokemon_collection_manager: &Arc<PokemonCollectionManager>,
+        crystal: Option<PokemonType>,
+    ) -> Result<Uuid, String> {
+        let battle_id = Uuid::new_v4();
+        info!("Starting practice battle {} for player {} at {:?} difficulty", battle_id, player_id, difficulty);
+
+        let (pokemons, _) = self.pvp_team(player_id, &mut PvPLoadout::default(), pokemon_collection_manager).await?;
+        let strongest_level = pokemons.iter().map(|p| p.level).max().unwrap_or(1);
+        let bot_level = (strongest_level as i32 + difficulty.level_offset()).clamp(1, 100) as u32;
+        let (bot_team_id, bot_pokemons) = self.rental_teams.lend_team(&self.template_repository, Some(bot_level))
+            .ok_or_else(|| "No practice teams are available".to_string())?;
+
+        let bot = ServerBotOpponent {
+            player_id: format!("{}{}", PRACTICE_BOT_ID_PREFIX, battle_id),
+            name: PRACTICE_BOT_NAME.to_string(),
+            team_id: bot_team_id,
+            pokemons: bot_pokemons,
+            difficulty,
+            gym_id: None,
+        };
+        self.start_server_bot_battle(battle_id, player_id, pokemons, crystal, bot, lobby).await
+    }
+
+    /// Start a battle against the leader of `gym`, played by the server bot with the leader's
+    /// team at `tier` (0 for the first challenge, higher for rematches). The player fights with
+    /// their own team and keeps the experience earned, and outcome listeners hear about the
+    /// result through `on_gym_battle_finished`.
+    pub async fn start_gym_battle(
+        &self,
+        player_id: &str,
+        gym: &GymLeader,
+        tier: u32,
+        lobby: &Arc<Lobby>,
+        pokemon_collection_manager: &Arc<PokemonCollectionManager>,
+        crystal: Option<PokemonType>,
+    ) -> Result<Uuid, String> {
+        let battle_id = Uuid::new_v4();
+        info!("Starting gym battle {} for player {} against {} at tier {}", battle_id, player_id, gym.id, tier);
+
+        let (pokemons, _) = self.pvp_team(player_id, &mut PvPLoadout::default(), pokemon_collection_manager).await?;
+        let bot = ServerBotOpponent {
+            player_id: format!("{}{}:{}", GYM_LEADER_ID_PREFIX, gym.id, battle_id),
+            name: gym.name.clone(),
+            team_id: gym.id.clone(),
+            pokemons: gym.team(&self.template_repository, tier),
+            difficulty: gym.difficulty,
+            gym_id: Some(gym.id.clone()),
+        };
+        self.start_server_bot_battle(battle_id, player_id, pokemons, crystal, bot, lobby).await
+    }
+
+    /// Start a PvP battle in which the server bot plays player 2 as `bot`
+    async fn start_server_bot_battle(
+        &self,
+        battle_id: Uuid,
+        player_id: &str,
+        pokemons: Vec<Pokemon>,
+        crystal: Option<PokemonType>,
+        opponent: ServerBotOpponent,
+        lobby: &Arc<Lobby>,
+    ) -> Result<Uuid, String> {
+        let username = match lobby.player_positions.get(player_id) {
+            Some(state) => state.value().username.clone(),
+            None => return Err(format!("Player {} not found in lobby", player_id)),
+        };
+
+        let to_battle_team = |pokemons: &[Pokemon]| pokemons.iter().enumerate()
+            .map(|(idx, pokemon)| utils::convert_player_pokemon_to_battle_pokemon(pokemon, idx, &self.template_repository))
+            .collect::<Vec<_>>();
+        let mut team = to_battle_team(&pokemons);
+        self.apply_obedience(player_id, &pokemons, &mut team);
+        let player = BattlePlayer {
+            player_id: player_id.to_string(),
+            name: username,
+            team,
+            active_pokemon_index: 0,
+            side_effects: PlayerSideState::default(),
+            last_action_submitted: None,
+            must_switch: false,
+            sent_team_overview: Vec::new(),
+            team_revision: 0,
+            crystal_type: crystal,
+            super_move_used: false,
+            rental_team: None,
+        };
+        let bot_id = opponent.player_id;
+        let bot = BattlePlayer {
+            player_id: bot_id.clone(),
+            name: opponent.name.clone(),
+            team: to_battle_team(&opponent.pokemons),
+            active_pokemon_index: 0,
+            side_effects: PlayerSideState::default(),
+            last_action_submitted: None,
+            must_switch: false,
+            sent_team_overview: Vec::new(),
+            team_revision: 0,
+            crystal_type: None,
+            super_move_used: false,
+            rental_team: Some(opponent.team_id),
+        };
+
+        let mut battle_state = PvPBattleState::new(
+            battle_id,
+            player,
+            bot,
+            self.template_repository.move_repository.clone(),
+            self.template_repository.ability_repository.clone(),
+        );
+        battle_state.practice = Some(opponent.difficulty);
+        battle_state.gym_id = opponent.gym_id;
+
+        let player_pokemon = &battle_state.player1.team[0];
+        let bot_pokemon = &battle_state.player2.team[0];
+        let start_message = ServerMessage::PvPBattleStart {
+            battle_id,
+            player_team: battle_state.player1.team.iter().map(BattlePokemonTeamOverview::from_battle_pokemon).collect(),
+            initial_pokemon: self.private_view_for(player_id, player_pokemon, bot_pokemon, self.template_repository.move_repository.as_ref()),
+            opponent_id: bot_id.clone(),
+            opponent_username: opponent.name,
+            opponent_initial_pokemon: BattlePokemonPublicView::from_battle_pokemon(bot_pokemon),
+            initial_field_state: FieldState::default(),
+            player1_id: player_id.to_string(),
+            player2_id: bot_id.clone(),
+            seed_commitment: fairness::seed_commitment(battle_state.rng_seed),
+        };
+
+        match lobby.player_positions.get_mut(player_id) {
+            Some(mut player_state) => player_state.value_mut().in_combat = true,
+            None => return Err(format!("Player {} not found in lobby state", player_id)),
+        }
+        self.active_pvp_battles.insert(battle_id, Arc::new(Mutex::new(battle_state)));
+        self.index_battle(player_id, battle_id);
+        lobby.events.record(LobbyEventKind::PvPBattleStarted {
+            battle_id,
+            player1_id: player_id.to_string(),
+            player2_id: bot_id,
+        });
+
+        if let Err(e) = lobby.send_to_player(player_id, &start_message).await {
+            if let Some(mut player_state) = lobby.player_positions.get_mut(player_id) {
+                player_state.value_mut().in_combat = false;
+            }
+            self.active_pvp_battles.remove(&battle_id);
+            self.unindex_battle(player_id, battle_id);
+            return Err(format!("Failed to send battle start message: {}", e));
+        }
+        Ok(battle_id)
+    }
+
+    /// Start a wild battle between a player and a monster. `crystal` is the type of the
+    /// crystal the player has equipped, which enables their super move. `scripted` makes it
+    /// the tutorial's first battle, which the player cannot lose.
     pub async fn start_wild_battle(
         &self,
         player_id: &str,
         monster_instance_id: &str,
         lobby: &Arc<Lobby>,
         pokemon_collection_manager: &Arc<PokemonCollectionManager>,
+        crystal: Option<PokemonType>,
+        scripted: bool,
     ) -> Result<Uuid, String> {
         // Generate a new battle ID
         let battle_id = Uuid::new_v4();
@@ -276,13 +1092,17 @@ impl BattleManager {
         if monster.in_combat {
           return Err("Monster is already in combat".to_string());
         }
+        if monster.despawning {
+          return Err("Monster is no longer available".to_string());
+        }
         
         // 3. Convert Pokémon to battle format
-        let battle_pokemon = player_pokemons.iter().enumerate()
+        let mut battle_pokemon = player_pokemons.iter().enumerate()
             .map(|(idx, pokemon)| {
                 utils::convert_player_pokemon_to_battle_pokemon(pokemon, idx, &self.template_repository)
             })
             .collect::<Vec<_>>();
+        self.apply_obedience(player_id, &player_pokemons, &mut battle_pokemon);
         
         let wild_pokemon = utils::convert_wild_monster_to_battle_pokemon(&monster, &self.template_repository);
         
@@ -295,6 +1115,11 @@ impl BattleManager {
             side_effects: PlayerSideState::default(),
             last_action_submitted: None,
             must_switch: false,
+            sent_team_overview: Vec::new(),
+            team_revision: 0,
+            crystal_type: crystal,
+            super_move_used: false,
+            rental_team: None,
         };
         
         // 5. Create the battle state
@@ -303,19 +1128,40 @@ impl BattleManager {
             player: battle_player,
             wild_pokemon,
             turn_number: 1,
-            battle_phase: BattlePhase::WaitingForPlayerAction,
+            battle_phase: PhaseMachine::new(battle_id, BattlePhase::WaitingForPlayerAction),
             player_action: None,
             wild_action: None,
             turn_order: None,
-            field_state: FieldState::default(),
+            // Encounters inherit the overworld weather
+            field_state: FieldState {
+                weather: lobby.weather().battle_weather(),
+                ..FieldState::default()
+            },
             battle_log: Vec::new(),
             capture_attempts: Vec::new(),
+            encounter_modifier: None,
+            started_at: chrono::Utc::now().timestamp() as u64,
+            last_activity_at: std::time::Instant::now(),
+            timed_out: false,
+            auto_battle: false,
+            auto_battle_run: 0,
             move_repository: self.template_repository.move_repository.clone(),
+            ability_repository: self.template_repository.ability_repository.clone(),
+            scripted,
+            wild_catch_rate: self.template_repository.templates.get(&monster.template_id).map_or(DEFAULT_CATCH_RATE, |template| template.catch_rate),
+            wild_shiny: monster.is_shiny,
+            wild_alpha: monster.is_alpha,
         };
         
         // 6. Store the battle in the manager
         let battle_mutex = Arc::new(Mutex::new(battle_state));
         self.active_battles.insert(battle_id, battle_mutex.clone());
+        self.index_battle(player_id, battle_id);
+        lobby.events.record(LobbyEventKind::WildBattleStarted {
+            battle_id,
+            player_id: player_id.to_string(),
+            monster_instance_id: monster_instance_id.to_string(),
+        });
         
         // 7. Mark player and monster as in combat
         if let Some(mut player_state) = lobby.player_positions.get_mut(player_id) {
@@ -333,7 +1179,7 @@ impl BattleManager {
         }
         
         // 8. Send initial battle messages to player
-        let battle_state_for_messages = battle_mutex.lock().await;
+        let mut battle_state_for_messages = battle_mutex.lock().await;
         
         // 8.1 Create team overview for client
         let team_overview = battle_state_for_messages.player.team.iter()
@@ -341,6 +1187,7 @@ impl BattleManager {
                 template_id: pokemon.template_id,
                 name: pokemon.name.clone(),
                 level: pokemon.level,
+                form: pokemon.form.clone(),
                 current_hp_percent: pokemon.current_hp as f32 / pokemon.max_hp as f32,
                 current_hp: pokemon.current_hp,
                 max_hp: pokemon.max_hp,
@@ -352,8 +1199,10 @@ impl BattleManager {
         
         // 8.2 Active Pokémon detail
         let active_pokemon = &battle_state_for_messages.player.team[battle_state_for_messages.player.active_pokemon_index];
-        let active_pokemon_view = BattlePokemonPrivateView::from_battle_pokemon(
+        let active_pokemon_view = self.private_view_for(
+            player_id,
             active_pokemon,
+            &battle_state_for_messages.wild_pokemon,
             battle_state_for_messages.move_repository.as_ref()
         );
         
@@ -376,11 +1225,14 @@ impl BattleManager {
             // Continue anyway, we've already set up the battle
         }
         
-        // 8.5 Send request action message
+        // 8.5 Send request action message (the first overview is always the full team)
+        let (team_revision, team_overview_is_delta, team_overview) = next_team_overview(&mut battle_state_for_messages.player, false);
         let request_action_message = ServerMessage::RequestAction {
             turn_number: battle_state_for_messages.turn_number,
             active_pokemon_state: active_pokemon_view,
             team_overview,
+            team_revision,
+            team_overview_is_delta,
             other_pokemon_state: wild_pokemon_view,
             can_switch: true, // Usually true at start of battle
             must_switch: false,
@@ -403,35 +1255,70 @@ impl BattleManager {
         self.active_battles.get(&battle_id).map(|entry| entry.value().clone())
     }
 
+    /// Get the logged events of a battle the player participates in, with the current turn number
+    pub async fn get_battle_log(&self, battle_id: Uuid, player_id: &str) -> Result<(u32, Vec<BattleEvent>), String> {
+        if let Some(battle_mutex) = self.get_battle_state(battle_id) {
+            let battle_state = battle_mutex.lock().await;
+            if battle_state.player.player_id != player_id {
+                return Err("Player ID does not match the battle".to_string());
+            }
+            return Ok((battle_state.turn_number, battle_state.battle_log.clone()));
+        }
+
+        if let Some(battle_mutex) = self.get_pvp_battle_state(battle_id) {
+            let battle_state = battle_mutex.lock().await;
+            if battle_state.get_player_by_id(player_id).is_none() {
+                return Err("Player ID does not match any player in this battle".to_string());
+            }
+            return Ok((battle_state.turn_number, battle_state.battle_log.clone()));
+        }
+
+        Err(format!("Battle {} not found", battle_id))
+    }
+
     /// Find all battle IDs in which a player is participating
     pub fn find_battles_for_player(&self, player_id: &str) -> Vec<Uuid> {
-        let mut battles = Vec::new();
-        
-        // Check wild battles
-        self.active_battles.iter().for_each(|battle_entry| {
-            let battle_id = *battle_entry.key();
-            let battle_mutex = battle_entry.value();
-            
-            if let Ok(battle_state) = battle_mutex.try_lock() {
-                if battle_state.player.player_id == player_id {
-                    battles.push(battle_id);
-                }
-            }
-        });
-        
-        // Check PvP battles
-        self.active_pvp_battles.iter().for_each(|battle_entry| {
-            let battle_id = *battle_entry.key();
-            let battle_mutex = battle_entry.value();
-            
-            if let Ok(battle_state) = battle_mutex.try_lock() {
-                if battle_state.player1.player_id == player_id || battle_state.player2.player_id == player_id {
-                    battles.push(battle_id);
+        self.player_battles.get(player_id)
+            .map(|battles| battles.value().clone())
+            .unwrap_or_default()
+    }
+
+    /// Run players out of wild battles they have stopped acting in, freeing the wild monster.
+    /// Returns the number of battles ended.
+    pub async fn expire_idle_wild_battles(
+        &self,
+        lobbies: &DashMap<String, Arc<Lobby>>,
+        pokemon_collection_manager: &Arc<PokemonCollectionManager>,
+    ) -> usize {
+        let battles: Vec<(Uuid, Arc<Mutex<WildBattleState>>)> = self.active_battles.iter()
+            .map(|entry| (*entry.key(), entry.value().clone()))
+            .collect();
+
+        let mut expired = 0;
+        for (battle_id, battle_mutex) in battles {
+            let player_id = {
+                let mut battle_state = battle_mutex.lock().await;
+                let waiting_on_player = matches!(battle_state.battle_phase.get(), BattlePhase::WaitingForPlayerAction | BattlePhase::WaitingForSwitch);
+                if !waiting_on_player || battle_state.last_activity_at.elapsed() < WILD_BATTLE_IDLE_TIMEOUT {
+                    continue;
                 }
+                battle_state.timed_out = true;
+                battle_state.battle_phase.transition(BattlePhase::Finished);
+                battle_state.player.player_id.clone()
+            };
+
+            let Some(lobby) = crate::lobby::find_player_lobby(lobbies, &player_id) else {
+                // The player already left; the disconnect cleanup owns this battle
+                continue;
+            };
+            info!("Wild battle {} timed out waiting for player {}", battle_id, player_id);
+            if let Err(e) = self.end_battle(battle_id, &lobby, pokemon_collection_manager, false).await {
+                error!("Failed to end idle wild battle {}: {}", battle_id, e);
+                continue;
             }
-        });
-        
-        battles
+            expired += 1;
+        }
+        expired
     }
 
     /// End a battle and clean up resources
@@ -445,7 +1332,7 @@ impl BattleManager {
         info!("Ending battle {} (Disconnect: {})", battle_id, is_disconnect);
 
         // --- 1. Retrieve Battle State and Extract Data within a limited scope ---
-        let (player_id, wild_monster_id, outcome, reason, exp_gained, captured_pokemon_view) = {
+        let (player_id, wild_monster_id, outcome, reason, exp_gained, captured_pokemon_view, summary, player_team) = {
             let battle_mutex = self.active_battles.get(&battle_id)
                 .ok_or_else(|| format!("Battle {} not found for ending.", battle_id))?
                 .value().clone();
@@ -464,16 +1351,22 @@ impl BattleManager {
             let determined_reason;
             let mut determined_exp_gained = None;
             let mut determined_captured_pokemon_view = None;
+            let (wild_shiny, wild_alpha) = (battle_state.wild_shiny, battle_state.wild_alpha);
 
             if is_disconnect {
                 determined_outcome = WildBattleOutcome::PlayerDisconnected;
                 determined_reason = BattleEndReason::PlayerDisconnected;
+            } else if battle_state.timed_out {
+                determined_outcome = WildBattleOutcome::PlayerRan;
+                determined_reason = BattleEndReason::InactivityTimeout;
             } else if let Some(last_attempt) = battle_state.capture_attempts.last() {
                  if last_attempt.success {
                     determined_outcome = WildBattleOutcome::Captured;
                     determined_reason = BattleEndReason::WildPokemonCaptured;
 
                     // --- Pokemon Creation and Saving ---
+                    // A weather form only lasts for the battle
+                    let (form, types) = battle_state.wild_pokemon.base_form();
                     let captured_pokemon = Pokemon {
                         id: Uuid::new_v4().to_string(),
                         template_id: battle_state.wild_pokemon.template_id,
@@ -484,19 +1377,29 @@ impl BattleManager {
                         capture_date: chrono::Utc::now().timestamp() as u64,
                         current_hp: battle_state.wild_pokemon.current_hp,
                         status_condition: battle_state.wild_pokemon.status.clone(), // Clone status
-                        types: battle_state.wild_pokemon.pokemon_types.clone(),
+                        types,
+                        form,
                         ability: battle_state.wild_pokemon.ability.clone(),
-                        // Clone moves correctly
-                        moves: battle_state.wild_pokemon.moves.iter().map(|m| MonsterMove { id: m.move_id, pp_remaining: m.current_pp }).collect(),
+                        // Keep the battle moves only where they are legal for the species at this level
+                        moves: utils::capture_moveset(&battle_state.wild_pokemon, &self.template_repository),
                         ivs: battle_state.wild_pokemon.ivs.clone(),
-                        evs: crate::stats::StatSet::default(), // TODO: Get EVs
-                        nature: crate::stats::nature::Nature::Hardy, // TODO: Get Nature
+                        evs: battle_state.wild_pokemon.evs.clone(),
+                        nature: battle_state.wild_pokemon.nature,
+                        is_shiny: wild_shiny,
+                        original_trainer: Some(player_id.clone()),
                     };
                     // Use a separate async block if needed, but await here is fine if not blocking excessively
-                    match pokemon_collection_manager.add_pokemon(&player_id, captured_pokemon.clone()).await {
-                        Ok(_) => info!("Saved captured Pokemon {} for player {}", captured_pokemon.id, player_id),
-                        Err(e) => error!("Failed to save captured Pokemon: {}", e),
-                    }
+                    // The save waits for the player's answer to the nickname prompt
+                    let destination = match pokemon_collection_manager.hold_captured_pokemon(&player_id, captured_pokemon.clone()).await {
+                        Ok(destination) => {
+                            info!("Stored captured Pokemon {} for player {} ({:?})", captured_pokemon.id, player_id, destination);
+                            Some(destination)
+                        },
+                        Err(e) => {
+                            error!("Failed to save captured Pokemon: {}", e);
+                            None
+                        },
+                    };
                     let active_pokemons = pokemon_collection_manager.get_active_pokemons(&player_id).await.unwrap();
                     // send to player
                     let active_pokemons_msg = ServerMessage::ActivePokemons { 
@@ -506,8 +1409,18 @@ impl BattleManager {
                         error!("Failed to send active Pokémon collection to player {}: {}", player_id, e);
                     }
 
-                     // TODO: Create PrivateView from captured_pokemon if needed
-                     determined_captured_pokemon_view = None; // Placeholder view
+                    let chain = lobby.catch_chains.record_capture(&player_id, captured_pokemon.template_id);
+                    let chain_msg = ServerMessage::CatchChainUpdated { template_id: chain.template_id, chain_length: chain.length };
+                    if let Err(e) = lobby.send_to_player(&player_id, &chain_msg).await {
+                        error!("Failed to send catch chain to player {}: {}", player_id, e);
+                    }
+
+                     determined_captured_pokemon_view = Some(CapturedPokemonView::from_captured(
+                         &captured_pokemon,
+                         &battle_state.wild_pokemon,
+                         destination,
+                         battle_state.move_repository.as_ref(),
+                     ));
                      // --- End Pokemon Creation ---
 
                  } else if battle_state.wild_pokemon.is_fainted {
@@ -551,6 +1464,22 @@ impl BattleManager {
                 }
             }
 
+            let summary = WildBattleSummary {
+                battle_id,
+                lobby: lobby.clone(),
+                player_id: player_id.clone(),
+                player_team: battle_state.player.team.iter().map(BattlePokemonTeamOverview::from_battle_pokemon).collect(),
+                wild_pokemon: BattlePokemonPublicView::from_battle_pokemon(&battle_state.wild_pokemon),
+                wild_shiny,
+                wild_alpha,
+                outcome: determined_outcome.clone(),
+                reason: determined_reason.clone(),
+                turns: battle_state.turn_number,
+                started_at: battle_state.started_at,
+                moves_used: battle_state.moves_used(),
+                capture_attempts: battle_state.capture_attempts.clone(),
+            };
+
             info!("Releasing lock for battle state extraction in battle {}", battle_id);
             // Return the extracted data; the lock is released at the end of this scope
             (
@@ -560,10 +1489,13 @@ impl BattleManager {
                 determined_reason,
                 determined_exp_gained,
                 determined_captured_pokemon_view,
+                summary,
+                battle_state.player.team.clone(),
             )
         }; // <- battle_state lock is released here
 
         // --- 2. Remove Battle from Active Battles Map ---
+        self.unindex_battle(&player_id, battle_id);
         if self.active_battles.remove(&battle_id).is_none() {
             warn!("Battle {} was already removed.", battle_id);
             // If the battle was already removed, it might have been ended by another process.
@@ -575,7 +1507,17 @@ impl BattleManager {
         }
 
 
-        // --- 3. Send BattleEnd Message (only if not a disconnect) ---
+        // --- 3. Despawn the monster if the battle took it out of the world ---
+        // Done before any message to the player so nobody else can engage a monster that is about to vanish
+        let should_despawn = matches!(outcome, WildBattleOutcome::Victory | WildBattleOutcome::Captured);
+        if should_despawn && !wild_monster_id.is_empty() {
+            self.despawn_battled_monster(&wild_monster_id, lobby).await;
+        }
+
+        // Save the team's progress before any exp from this battle is added on top of it
+        self.persist_team_progress(&player_id, &player_team, pokemon_collection_manager).await;
+
+        // --- 4. Send BattleEnd Message (only if not a disconnect) ---
         if !is_disconnect {
             let end_message = ServerMessage::BattleEnd {
                 outcome: outcome.clone(),
@@ -662,7 +1604,7 @@ impl BattleManager {
             info!("Skipping BattleEnd message send for battle {} due to disconnect for player {}", battle_id, player_id);
         }
 
-        // --- 4. Cleanup Lobby State (No BattleState lock held) ---
+        // --- 5. Cleanup Lobby State (No BattleState lock held) ---
 
         // --- Player State Update ---
         if !is_disconnect {
@@ -683,63 +1625,103 @@ impl BattleManager {
         }
 
         // --- Monster State Update ---
-        if !wild_monster_id.is_empty() {
-            info!("Attempting to update monster state for {} in lobby {}", wild_monster_id, lobby.id);
-            
-            // Determine if despawn should happen based on outcome *before* locking the monster
-            let should_despawn = match outcome {
-                WildBattleOutcome::Victory | WildBattleOutcome::Captured => true,
-                _ => false,
-            };
-
-            // Mark monster as no longer in combat first
+        // Monsters that survived the battle go back to roaming; defeated or captured ones were despawned above
+        if wild_monster_id.is_empty() {
+            warn!("Wild monster ID was empty during cleanup for battle {}", battle_id);
+        } else if !should_despawn {
             if let Some(monster_ref) = lobby.active_monsters.get(&wild_monster_id) {
                 if let Ok(mut monster_lock) = monster_ref.value().try_lock() {
                     monster_lock.in_combat = false;
                     info!("Marked monster {} as no longer in combat in lobby {}", wild_monster_id, lobby.id);
-                    // No longer setting despawn_time here
                 } else {
-                     warn!("Failed to acquire monster lock (try_lock) for {} in lobby {} during combat state update", wild_monster_id, lobby.id);
-                 }
-             } else {
-                  warn!("Monster {} not found in lobby active_monsters for combat state update in lobby {}", wild_monster_id, lobby.id);
-             }
-
-            // If the outcome dictates a despawn, call the despawn function now
-            if should_despawn {
-                info!("Outcome requires despawn for monster {} in lobby {}. Calling despawn_monster.", wild_monster_id, lobby.id);
-                // Call despawn_monster using the lobby's monster_manager
-                // This removes the monster from the lobby's active_monsters and monsters_by_spawn_point
-                match lobby.monster_manager.despawn_monster(&wild_monster_id, lobby).await {
-                    Some(_) => {
-                        info!("Successfully despawned monster {} from lobby {} via end_battle", wild_monster_id, lobby.id);
-                        
-                        // Send MonsterDespawned message to all clients in the lobby
-                        let despawn_msg = ServerMessage::MonsterDespawned { instance_id: wild_monster_id.clone() };
-                        if let Err(e) = lobby.broadcast_except(&despawn_msg, &[]).await {
-                            error!("Failed to broadcast despawn message for {}: {}", wild_monster_id, e);
-                        } else {
-                            info!("Broadcast monster despawn message for {} to all players in lobby {}", wild_monster_id, lobby.id);
-                        }
-                    }
-                    None => {
-                        warn!("despawn_monster called for {}, but it was already removed or not found in lobby {}", wild_monster_id, lobby.id);
-                    }
+                    warn!("Failed to acquire monster lock (try_lock) for {} in lobby {} during combat state update", wild_monster_id, lobby.id);
                 }
             } else {
-                info!("Outcome does not require despawn for monster {} in lobby {}.", wild_monster_id, lobby.id);
+                warn!("Monster {} not found in lobby active_monsters for combat state update in lobby {}", wild_monster_id, lobby.id);
             }
+        }
 
-            info!("Finished monster state update attempt for {} in lobby {}", wild_monster_id, lobby.id);
-         } else {
-             warn!("Wild monster ID was empty during cleanup for battle {}", battle_id);
-         }
-
+        lobby.events.record(LobbyEventKind::WildBattleEnded {
+            battle_id,
+            player_id: summary.player_id.clone(),
+            outcome: summary.outcome.clone(),
+        });
+        self.notify_wild_battle_finished(&summary);
 
         info!("Battle {} ended processing. Final Outcome: {:?}, Reason: {:?}", battle_id, outcome, reason);
         Ok(())
     }
 
+    /// Write the post-battle state of a player's team back to their collection.
+    /// Returns the team members that gained levels during the battle.
+    async fn persist_team_progress(
+        &self,
+        player_id: &str,
+        team: &[BattlePokemon],
+        pokemon_collection_manager: &Arc<PokemonCollectionManager>,
+    ) -> Vec<BattlePokemon> {
+        let collection = match pokemon_collection_manager.get_collection(player_id).await {
+            Ok(collection) => collection,
+            Err(e) => {
+                error!("Failed to load collection of player {} to save battle progress: {}", player_id, e);
+                return Vec::new();
+            }
+        };
+
+        let mut leveled_pokemon = Vec::new();
+        for battle_pokemon in team {
+            let collection_pokemon = match collection.pokemons.get(&battle_pokemon.instance_id) {
+                Some(pokemon) => pokemon,
+                None => continue,
+            };
+            let update = match utils::post_battle_update(battle_pokemon, collection_pokemon) {
+                Some(update) => update,
+                None => continue,
+            };
+            if let Err(e) = pokemon_collection_manager.update_pokemon(player_id, &battle_pokemon.instance_id, &update).await {
+                error!("Failed to update pokemon stats after battle: {}", e);
+                continue;
+            }
+            if battle_pokemon.level > collection_pokemon.level {
+                leveled_pokemon.push(battle_pokemon.clone());
+            }
+        }
+        leveled_pokemon
+    }
+
+    /// Remove a defeated or captured monster from the lobby and tell every player.
+    /// The monster stays in_combat and is flagged as despawning first, so interaction
+    /// attempts racing with the removal are rejected instead of finding a ghost.
+    async fn despawn_battled_monster(&self, wild_monster_id: &str, lobby: &Arc<Lobby>) {
+        let monster_mutex = match lobby.active_monsters.get(wild_monster_id) {
+            Some(entry) => entry.value().clone(),
+            None => {
+                warn!("despawn requested for {}, but it was already removed from lobby {}", wild_monster_id, lobby.id);
+                return;
+            }
+        };
+        monster_mutex.lock().await.despawning = true;
+
+        let spawn_point_id = lobby.spawn_point_of(wild_monster_id);
+        let monster = match lobby.monster_manager.despawn_monster(wild_monster_id, lobby).await {
+            Some(monster) => monster,
+            None => {
+                warn!("despawn_monster called for {}, but it was already removed or not found in lobby {}", wild_monster_id, lobby.id);
+                return;
+            }
+        };
+        info!("Despawned monster {} from lobby {} via end_battle", wild_monster_id, lobby.id);
+
+        let despawn_msg = ServerMessage::MonsterDespawned { instance_id: wild_monster_id.to_string() };
+        if let Err(e) = lobby.broadcast_except(&despawn_msg, &[]).await {
+            error!("Failed to broadcast despawn message for {}: {}", wild_monster_id, e);
+        }
+
+        // Keep the spawn point and species from refilling straight away
+        let template_id = monster.lock().await.template_id;
+        lobby.respawn_cooldowns.start(spawn_point_id.as_deref(), template_id);
+    }
+
     /// Handle a player action received from the client
     pub async fn handle_player_action(
         &self, 
@@ -761,6 +1743,20 @@ impl BattleManager {
         }
         
         // If not, proceed with handling wild battle action
+        self.handle_wild_action(player_id, battle_id, action, lobby, pokemon_collection_manager, false).await
+    }
+
+    /// Play one wild battle action, either sent by the player or chosen by auto-battle.
+    /// An action from the player takes back control from auto-battle.
+    async fn handle_wild_action(
+        &self,
+        player_id: &str,
+        battle_id: Uuid,
+        action: PlayerAction,
+        lobby: &Arc<Lobby>,
+        pokemon_collection_manager: &Arc<PokemonCollectionManager>,
+        from_auto_battle: bool,
+    ) -> Result<(), String> {
         let battle_mutex = self.get_battle_state(battle_id)
             .ok_or_else(|| format!("Battle {} not found", battle_id))?;
             
@@ -770,100 +1766,295 @@ impl BattleManager {
         if battle_state.player.player_id != player_id {
             return Err("Player ID does not match the battle".to_string());
         }
-        if battle_state.battle_phase != BattlePhase::WaitingForPlayerAction {
-             return Err(format!("Not expecting player action in phase {:?}", battle_state.battle_phase));
+        if !from_auto_battle && battle_state.auto_battle {
+            battle_state.auto_battle = false;
+            info!("Player {} took back control of battle {} from auto-battle", player_id, battle_id);
+            let status_message = ServerMessage::AutoBattleStatus { battle_id, enabled: false };
+            if let Err(e) = lobby.send_to_player(player_id, &status_message).await {
+                error!("Failed to send AutoBattleStatus message for battle {}: {}", battle_id, e);
+            }
+        }
+
+        // A fainted Pokémon is replaced without using up a turn
+        if battle_state.battle_phase.get() == BattlePhase::WaitingForSwitch {
+            let PlayerAction::SwitchPokemon { team_index } = action else {
+                return Err("Your fainted Pokémon must be replaced first".to_string());
+            };
+            if let Err(e) = validate_player_action(&battle_state, &action) {
+                return Err(format!("Invalid action: {}", e));
+            }
+            battle_state.last_activity_at = std::time::Instant::now();
+            let events = logic::wild_battle::process_replacement_switch(&mut battle_state, team_index);
+            battle_state.record_events(&events);
+            let turn_update_message = ServerMessage::TurnUpdate {
+                turn_number: battle_state.turn_number,
+                events: self.events_for_player(player_id, &events),
+            };
+            if let Err(e) = lobby.send_to_player(player_id, &turn_update_message).await {
+                error!("Failed to send TurnUpdate message for battle {}: {}", battle_id, e);
+            }
+            // Entry hazards can knock out the replacement too
+            return self.continue_wild_battle(battle_state, battle_id, lobby, pokemon_collection_manager).await;
+        }
+
+        if battle_state.battle_phase.get() != BattlePhase::WaitingForPlayerAction {
+             return Err(format!("Not expecting player action in phase {:?}", battle_state.battle_phase.get()));
         }
         let validation_result = validate_player_action(&battle_state, &action);
         if let Err(e) = validation_result {
             return Err(format!("Invalid action: {}", e));
         }
+        battle_state.last_activity_at = std::time::Instant::now();
         
         // Store actions and set phase
         battle_state.player_action = Some(action.clone());
-        let wild_action = determine_wild_action(&battle_state);
+        let wild_action = ai::choose_wild_action(&battle_state);
         battle_state.wild_action = Some(wild_action.clone());
-        battle_state.battle_phase = BattlePhase::ProcessingTurn;
+        battle_state.battle_phase.transition(BattlePhase::ProcessingTurn);
         let current_turn = battle_state.turn_number;
         info!("Stored actions for turn {} battle {}. Processing...", current_turn, battle_id);
 
         // Process the turn
         let events = logic::process_turn(&mut battle_state);
+        battle_state.record_events(&events);
         info!("Finished processing turn {} for battle {}. Generated {} events. New phase: {:?}", 
-            current_turn, battle_id, events.len(), battle_state.battle_phase);
+            current_turn, battle_id, events.len(), battle_state.battle_phase.get());
         
         // Send Turn Update
         let turn_update_message = ServerMessage::TurnUpdate {
             turn_number: current_turn, // Send the number of the turn that just finished
-            events,
+            events: self.events_for_player(player_id, &events),
         };
         if let Err(e) = lobby.send_to_player(player_id, &turn_update_message).await {
              error!("Failed to send TurnUpdate message for battle {}: {}", battle_id, e);
              // Don't stop processing, but log error
         }
 
-        // Handle Post-Turn State (Send RequestAction, RequestSwitch, or BattleEnd)
-        match battle_state.battle_phase {
-            BattlePhase::WaitingForPlayerAction => {
-                // Need to construct view models again for the RequestAction message
-                let team_overview = battle_state.player.team.iter()
-                    .map(|p| BattlePokemonTeamOverview::from_battle_pokemon(p))
-                    .collect::<Vec<_>>();
-                let active_pokemon_view = BattlePokemonPrivateView::from_battle_pokemon(
-                    &battle_state.player.team[battle_state.player.active_pokemon_index], 
-                    battle_state.move_repository.as_ref()
-                );
-                let wild_pokemon_view = BattlePokemonPublicView::from_battle_pokemon(
-                    &battle_state.wild_pokemon
-                );
-                
-                let request_action_message = ServerMessage::RequestAction {
-                    turn_number: battle_state.turn_number,
-                    active_pokemon_state: active_pokemon_view,
-                    team_overview,
-                    other_pokemon_state: wild_pokemon_view,
-                    can_switch: battle_state.player.team.iter().filter(|p| !p.is_fainted).count() > 1,
-                    must_switch: false, // Reset must_switch flag if applicable
-                    field_state: battle_state.field_state.clone(),
-                };
-                if let Err(e) = lobby.send_to_player(player_id, &request_action_message).await {
-                    error!("Failed to send RequestAction message for battle {}: {}", battle_id, e);
-                }
+        self.continue_wild_battle(battle_state, battle_id, lobby, pokemon_collection_manager).await
+    }
+
+    /// Ask the player for what the wild battle needs next after events were played: an
+    /// action, a replacement for a fainted Pokémon, or nothing because the battle ended
+    async fn continue_wild_battle(
+        &self,
+        mut battle_state: tokio::sync::MutexGuard<'_, WildBattleState>,
+        battle_id: Uuid,
+        lobby: &Arc<Lobby>,
+        pokemon_collection_manager: &Arc<PokemonCollectionManager>,
+    ) -> Result<(), String> {
+        let player_id = battle_state.player.player_id.clone();
+        let player_id = player_id.as_str();
+
+        // Handle Post-Turn State (Send RequestAction, RequestSwitch, or BattleEnd)
+        match battle_state.battle_phase.get() {
+            BattlePhase::WaitingForPlayerAction => {
+                if let Err(e) = self.send_wild_request_action(&mut battle_state, lobby, false).await {
+                    error!("Failed to send RequestAction message for battle {}: {}", battle_id, e);
+                }
+            }
+            BattlePhase::WaitingForSwitch => {
+                 // Create list of available switches
+                 let available_switches = battle_state.player.team.iter()
+                    .filter(|p| !p.is_fainted)
+                    .map(|p| BattlePokemonTeamOverview::from_battle_pokemon(p))
+                    .collect::<Vec<_>>();
+
+                 let request_switch_message = ServerMessage::RequestSwitch {
+                     reason: SwitchReason::Fainted, // Assuming faint is the only reason for now
+                     available_switches,
+                 };
+                 if let Err(e) = lobby.send_to_player(player_id, &request_switch_message).await {
+                     error!("Failed to send RequestSwitch message for battle {}: {}", battle_id, e);
+                 }
+            }
+            BattlePhase::Finished => {
+                 // Battle End logic is now handled by calling end_battle
+                 // Need to drop the lock before calling end_battle to avoid deadlock
+                 drop(battle_state);
+                 if let Err(e) = self.end_battle(battle_id, lobby, pokemon_collection_manager, false).await {
+                      error!("Error during battle cleanup for {}: {}", battle_id, e);
+                 }
+                 // NOTE: end_battle removes the battle from the map, so further access will fail
+                 return Ok(()); // Exit early as the battle is over and state removed
+            }
+            _ => { // CaptureMechanics, ProcessingTurn (shouldn't happen here)
+                error!("Unexpected battle phase {:?} after turn processing for battle {}", battle_state.battle_phase.get(), battle_id);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Switch auto-battle on or off for a player's wild battle. While it is on, the server
+    /// plays the player's turns until the battle ends or the player sends an action themselves.
+    pub async fn set_auto_battle(
+        self: &Arc<Self>,
+        player_id: &str,
+        battle_id: Uuid,
+        enabled: bool,
+        lobby: &Arc<Lobby>,
+        pokemon_collection_manager: &Arc<PokemonCollectionManager>,
+    ) -> Result<(), String> {
+        if self.active_pvp_battles.contains_key(&battle_id) {
+            return Err("Auto-battle is only available in wild battles".to_string());
+        }
+        let battle_mutex = self.get_battle_state(battle_id)
+            .ok_or_else(|| format!("Battle {} not found", battle_id))?;
+        let mut battle_state = battle_mutex.lock().await;
+        if battle_state.player.player_id != player_id {
+            return Err("Player ID does not match the battle".to_string());
+        }
+
+        if enabled && !battle_state.auto_battle {
+            battle_state.auto_battle = true;
+            battle_state.auto_battle_run += 1;
+            let battle_manager = self.clone();
+            let run = battle_state.auto_battle_run;
+            let lobby = lobby.clone();
+            let pokemon_collection_manager = pokemon_collection_manager.clone();
+            tokio::spawn(async move {
+                battle_manager.run_auto_battle(battle_id, run, lobby, pokemon_collection_manager).await;
+            });
+            info!("Player {} switched on auto-battle for battle {}", player_id, battle_id);
+        } else if !enabled {
+            battle_state.auto_battle = false;
+        }
+
+        let status_message = ServerMessage::AutoBattleStatus { battle_id, enabled };
+        lobby.send_to_player(player_id, &status_message).await
+    }
+
+    /// Play the player's turns of a wild battle until it ends or auto-battle is switched off
+    async fn run_auto_battle(
+        &self,
+        battle_id: Uuid,
+        run: u32,
+        lobby: Arc<Lobby>,
+        pokemon_collection_manager: Arc<PokemonCollectionManager>,
+    ) {
+        loop {
+            tokio::time::sleep(AUTO_BATTLE_TURN_DELAY).await;
+            let Some(battle_mutex) = self.get_battle_state(battle_id) else {
+                return;
+            };
+            let (player_id, action) = {
+                let mut battle_state = battle_mutex.lock().await;
+                if !battle_state.auto_battle || battle_state.auto_battle_run != run {
+                    return;
+                }
+                match ai::choose_auto_action(&battle_state) {
+                    Some(action) => (battle_state.player.player_id.clone(), action),
+                    None => {
+                        battle_state.auto_battle = false;
+                        return;
+                    }
+                }
+            };
+            if let Err(e) = self.handle_wild_action(&player_id, battle_id, action, &lobby, &pokemon_collection_manager, true).await {
+                warn!("Auto-battle stopped for battle {}: {}", battle_id, e);
+                if let Some(battle_mutex) = self.get_battle_state(battle_id) {
+                    battle_mutex.lock().await.auto_battle = false;
+                }
+                let status_message = ServerMessage::AutoBattleStatus { battle_id, enabled: false };
+                if let Err(e) = lobby.send_to_player(&player_id, &status_message).await {
+                    error!("Failed to send AutoBattleStatus message for battle {}: {}", battle_id, e);
+                }
+                return;
+            }
+        }
+    }
+
+    /// Send the action request for the next turn of a wild battle.
+    /// The team overview only carries changed entries unless `full` is set or the client predates deltas.
+    async fn send_wild_request_action(
+        &self,
+        battle_state: &mut WildBattleState,
+        lobby: &Arc<Lobby>,
+        full: bool,
+    ) -> Result<(), String> {
+        let full = full || lobby.is_legacy_client(&battle_state.player.player_id);
+        let (team_revision, team_overview_is_delta, team_overview) = next_team_overview(&mut battle_state.player, full);
+        let active_pokemon_view = self.private_view_for(
+            &battle_state.player.player_id,
+            &battle_state.player.team[battle_state.player.active_pokemon_index],
+            &battle_state.wild_pokemon,
+            battle_state.move_repository.as_ref()
+        );
+        let wild_pokemon_view = BattlePokemonPublicView::from_battle_pokemon(
+            &battle_state.wild_pokemon
+        );
+        
+        let request_action_message = ServerMessage::RequestAction {
+            turn_number: battle_state.turn_number,
+            active_pokemon_state: active_pokemon_view,
+            team_overview,
+            team_revision,
+            team_overview_is_delta,
+            other_pokemon_state: wild_pokemon_view,
+            can_switch: battle_state.player.team.iter().filter(|p| !p.is_fainted).count() > 1,
+            must_switch: false, // Reset must_switch flag if applicable
+            field_state: battle_state.field_state.clone(),
+        };
+        lobby.send_to_player(&battle_state.player.player_id, &request_action_message).await
+    }
+
+    /// Re-send the pending request of a battle with the full team overview,
+    /// used when a client lost track of the delta revisions
+    pub async fn resend_battle_request(&self, battle_id: Uuid, player_id: &str, lobby: &Arc<Lobby>) -> Result<(), String> {
+        if let Some(battle_mutex) = self.get_battle_state(battle_id) {
+            let mut battle_state = battle_mutex.lock().await;
+            if battle_state.player.player_id != player_id {
+                return Err("Player ID does not match the battle".to_string());
             }
-            BattlePhase::WaitingForSwitch => {
-                 // Create list of available switches
-                 let available_switches = battle_state.player.team.iter()
-                    .filter(|p| !p.is_fainted)
-                    .map(|p| BattlePokemonTeamOverview::from_battle_pokemon(p))
-                    .collect::<Vec<_>>();
+            return match battle_state.battle_phase.get() {
+                BattlePhase::WaitingForPlayerAction => self.send_wild_request_action(&mut battle_state, lobby, true).await,
+                BattlePhase::WaitingForSwitch => {
+                    let request_switch_message = ServerMessage::RequestSwitch {
+                        reason: SwitchReason::Fainted,
+                        available_switches: battle_state.player.team.iter()
+                            .filter(|p| !p.is_fainted)
+                            .map(BattlePokemonTeamOverview::from_battle_pokemon)
+                            .collect(),
+                    };
+                    lobby.send_to_player(player_id, &request_switch_message).await
+                }
+                phase => Err(format!("No pending request in phase {:?}", phase)),
+            };
+        }
 
-                 let request_switch_message = ServerMessage::RequestSwitch {
-                     reason: SwitchReason::Fainted, // Assuming faint is the only reason for now
-                     available_switches,
-                 };
-                 if let Err(e) = lobby.send_to_player(player_id, &request_switch_message).await {
-                     error!("Failed to send RequestSwitch message for battle {}: {}", battle_id, e);
-                 }
-            }
-            BattlePhase::Finished => {
-                 // Battle End logic is now handled by calling end_battle
-                 // Need to drop the lock before calling end_battle to avoid deadlock
-                 drop(battle_state);
-                 if let Err(e) = self.end_battle(battle_id, lobby, pokemon_collection_manager, false).await {
-                      error!("Error during battle cleanup for {}: {}", battle_id, e);
-                 }
-                 // NOTE: end_battle removes the battle from the map, so further access will fail
-                 return Ok(()); // Exit early as the battle is over and state removed
-            }
-            _ => { // CaptureMechanics, ProcessingTurn (shouldn't happen here)
-                error!("Unexpected battle phase {:?} after turn processing for battle {}", battle_state.battle_phase, battle_id);
+        if let Some(battle_mutex) = self.get_pvp_battle_state(battle_id) {
+            let mut battle_state = battle_mutex.lock().await;
+            if battle_state.get_player_by_id(player_id).is_none() {
+                return Err("Player ID does not match any player in this battle".to_string());
             }
+            return self.send_pvp_pending_request(&mut battle_state, player_id, lobby).await;
         }
 
-        Ok(())
+        Err(format!("Battle {} not found", battle_id))
     }
 
-    /// Handle a player action for a PvP battle
+    /// Handle a player action for a PvP battle, counting rejected actions towards flagging the battle
     pub async fn handle_pvp_player_action(
+        &self,
+        player_id: &str,
+        battle_id: Uuid,
+        action: PlayerAction,
+        lobby: &Arc<Lobby>,
+        pokemon_collection_manager: &Arc<PokemonCollectionManager>,
+    ) -> Result<(), String> {
+        let result = self.apply_pvp_player_action(player_id, battle_id, action, lobby, pokemon_collection_manager).await;
+        if result.is_err() {
+            if let Some(battle_mutex) = self.get_pvp_battle_state(battle_id) {
+                let mut battle_state = battle_mutex.lock().await;
+                if battle_state.get_player_by_id(player_id).is_some() {
+                    *battle_state.rejected_actions.entry(player_id.to_string()).or_insert(0) += 1;
+                }
+            }
+        }
+        result
+    }
+
+    async fn apply_pvp_player_action(
         &self, 
         player_id: &str,
         battle_id: Uuid, 
@@ -880,13 +2071,28 @@ impl BattleManager {
         // Determine which player is submitting the action
         let is_player1 = battle_state.player1.player_id == player_id;
         let is_player2 = battle_state.player2.player_id == player_id;
+
+        if matches!(action, PlayerAction::PreBattleModifier { .. }) {
+            return Err("Bait and mud can only be thrown in wild battles".to_string());
+        }
         
         if !is_player1 && !is_player2 {
             return Err("Player ID does not match any player in this battle".to_string());
         }
+
+        let player = if is_player1 { &battle_state.player1 } else { &battle_state.player2 };
+        match action {
+            PlayerAction::UseMove { move_index } => {
+                logic::core::validate_move_choice(&player.team[player.active_pokemon_index], move_index)?;
+            }
+            PlayerAction::UseSuperMove { move_index } => {
+                player.validate_super_move(move_index, battle_state.move_repository.as_deref())?;
+            }
+            _ => {}
+        }
         
         // Check if this player's action is expected in the current phase
-        match battle_state.battle_phase {
+        match battle_state.battle_phase.get() {
             BattlePvPPhase::WaitingForBothPlayersActions => {
                 // Both players can submit actions
                 if is_player1 {
@@ -948,7 +2154,7 @@ impl BattleManager {
                 }
             },
             _ => {
-                return Err(format!("Not expecting player action in phase {:?}", battle_state.battle_phase));
+                return Err(format!("Not expecting player action in phase {:?}", battle_state.battle_phase.get()));
             }
         }
         
@@ -956,51 +2162,72 @@ impl BattleManager {
         if battle_state.ready_for_processing() {
             info!("All required actions received for PvP battle {}. Processing turn...", battle_id);
             
-            battle_state.battle_phase = BattlePvPPhase::ProcessingTurn;
+            battle_state.battle_phase.transition(BattlePvPPhase::ProcessingTurn);
             let current_turn = battle_state.turn_number;
             
-            // Process the turn using the PvP-specific function
-            let events = logic::process_pvp_turn(&mut battle_state, &self.template_repository);
+            // Process the turn with the next seed derived from the battle's, keeping the state going in so the turn can be replayed
+            let rng_seed = fairness::turn_seed(battle_state.rng_seed, battle_state.turn_snapshots.len());
+            let snapshot = TurnSnapshot {
+                turn_number: current_turn,
+                rng_seed,
+                player1_action: battle_state.player1_action.clone(),
+                player2_action: battle_state.player2_action.clone(),
+                player1: battle_state.player1.clone(),
+                player2: battle_state.player2.clone(),
+                field_state: battle_state.field_state.clone(),
+                events: Vec::new(),
+            };
+            let events = rng::with_seed(rng_seed, || logic::process_pvp_turn(&mut battle_state, &self.template_repository));
+            battle_state.record_events(&events);
+            battle_state.turn_snapshots.push(TurnSnapshot { events: events.clone(), ..snapshot });
             
             info!("Finished processing turn {} for PvP battle {}. Generated {} events. New phase: {:?}", 
-                current_turn, battle_id, events.len(), battle_state.battle_phase);
+                current_turn, battle_id, events.len(), battle_state.battle_phase.get());
             
             // Send Turn Update to both players
             let turn_update_message = ServerMessage::TurnUpdate {
                 turn_number: current_turn,
-                events: events.clone(),
+                events: self.events_for_player(&battle_state.player1.player_id, &events),
             };
             
             // Send to player 1
-            if let Err(e) = lobby.send_to_player(&battle_state.player1.player_id, &turn_update_message).await {
+            if let Err(e) = self.send_to_battler(lobby, &battle_state.player1.player_id, &turn_update_message).await {
                 error!("Failed to send TurnUpdate message to player 1 for battle {}: {}", battle_id, e);
             }
             
             // Send to player 2
-            if let Err(e) = lobby.send_to_player(&battle_state.player2.player_id, &turn_update_message).await {
+            let turn_update_message = ServerMessage::TurnUpdate {
+                turn_number: current_turn,
+                events: self.events_for_player(&battle_state.player2.player_id, &events),
+            };
+            if let Err(e) = self.send_to_battler(lobby, &battle_state.player2.player_id, &turn_update_message).await {
                 error!("Failed to send TurnUpdate message to player 2 for battle {}: {}", battle_id, e);
             }
             
             // Determine next steps based on the new battle phase
-            match battle_state.battle_phase {
+            match battle_state.battle_phase.get() {
                 BattlePvPPhase::WaitingForBothPlayersActions => {
                     // Send RequestAction to both players for the next turn
-                    self.send_pvp_request_actions(&battle_state, lobby).await?;
+                    self.send_pvp_request_actions(&mut battle_state, lobby).await?;
                 },
                 BattlePvPPhase::WaitingForPlayer1Switch => {
                     // Send switch request to player 1
                     self.send_pvp_switch_request(&battle_state, &battle_state.player1.player_id, lobby).await?;
                 },
                 BattlePvPPhase::WaitingForPlayer2Switch => {
+                    if let Some(difficulty) = battle_state.practice {
+                        // The practice bot sends in its replacement right away
+                        let bot_action = ai::choose_practice_action(&battle_state, difficulty);
+                        let bot_id = battle_state.player2.player_id.clone();
+                        drop(battle_state);
+                        drop(battle_entry);
+                        return Box::pin(self.apply_pvp_player_action(&bot_id, battle_id, bot_action, lobby, pokemon_collection_manager)).await;
+                    }
                     // Send switch request to player 2
                     self.send_pvp_switch_request(&battle_state, &battle_state.player2.player_id, lobby).await?;
                 },
                 BattlePvPPhase::Finished => {
                     info!("PvP battle {} finished. Determining outcome...", battle_id);
-                    // End the battle
-                    let player1_id = battle_state.player1.player_id.clone();
-                    let player2_id = battle_state.player2.player_id.clone();
-                    
                     // Determine the outcome
                     let (player1_outcome, player2_outcome) = {
                         let all_player1_fainted = battle_state.player1.team.iter().all(|p| p.is_fainted);
@@ -1023,87 +2250,6 @@ impl BattleManager {
                     };
                     info!("PvP battle {} ended. Player 1 outcome: {:?}, Player 2 outcome: {:?}", battle_id, player1_outcome, player2_outcome);
                     
-                    // Track which pokemon leveled up for each player
-                    let mut player1_leveled_pokemon = Vec::new();
-                    let mut player2_leveled_pokemon = Vec::new();
-                    
-                    // Check and update pokemon levels/exp for both players
-                    for (player_id, team, leveled_pokemon) in [
-                        (player1_id.clone(), &battle_state.player1.team, &mut player1_leveled_pokemon), 
-                        (player2_id.clone(), &battle_state.player2.team, &mut player2_leveled_pokemon)
-                    ] {
-                        // Get player's collection
-                        if let Ok(collection) = pokemon_collection_manager.get_collection(&player_id).await {
-                            for battle_pokemon in team.iter() {
-                                // Find matching pokemon in collection
-                                if let Some(collection_pokemon) = collection.pokemons.get(&battle_pokemon.instance_id) {
-                                    // Check if level or exp changed during battle
-                                    if battle_pokemon.level != collection_pokemon.level 
-                                        || battle_pokemon.exp != collection_pokemon.exp {
-                                        
-                                        let update = PokemonUpdate {
-                                            name: None,
-                                            level: Some(battle_pokemon.level),
-                                            exp: Some(battle_pokemon.exp),
-                                            max_exp: Some(battle_pokemon.max_exp),
-                                            current_hp: Some(battle_pokemon.max_hp),
-                                        };
-
-                                        if let Err(e) = pokemon_collection_manager.update_pokemon(
-                                            &player_id, 
-                                            &battle_pokemon.instance_id, 
-                                            &update
-                                        ).await {
-                                            error!("Failed to update pokemon stats after battle: {}", e);
-                                        }
-                                        
-                                        // Track if pokemon leveled up
-                                        if battle_pokemon.level > collection_pokemon.level {
-                                            leveled_pokemon.push(battle_pokemon.clone());
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    // Send collection updates for leveled pokemon
-                    if !player1_leveled_pokemon.is_empty() {
-                      let collection = pokemon_collection_manager.get_active_pokemons(&player1_id).await.unwrap();
-                        let collection_update = ServerMessage::ActivePokemons {
-                            pokemons: collection.iter()
-                                .map(|p| pokemon_collection_manager.pokemon_to_display_pokemon(p))
-                                .collect()
-                        };
-                        if let Err(e) = lobby.send_to_player(&player1_id, &collection_update).await {
-                            error!("Failed to send collection update to player 1: {}", e);
-                        }
-                    }
-
-                    if !player2_leveled_pokemon.is_empty() {
-                        let collection = pokemon_collection_manager.get_active_pokemons(&player2_id).await.unwrap();
-                        let collection_update = ServerMessage::ActivePokemons {
-                            pokemons: collection.iter()
-                                .map(|p| pokemon_collection_manager.pokemon_to_display_pokemon(p))
-                                .collect()
-                        };
-                        if let Err(e) = lobby.send_to_player(&player2_id, &collection_update).await {
-                            error!("Failed to send collection update to player 2: {}", e);
-                        }
-                    }
-
-                    // Prepare battle end messages
-                    let player1_end_message = ServerMessage::BattleEnd {
-                        outcome: self.convert_pvp_outcome_to_wild(player1_outcome),
-                        reason: BattleEndReason::AllPlayerPokemonFainted, // Simplified for now
-                        pokemon_captured: None, // No captures in PvP
-                    };
-                    
-                    let player2_end_message = ServerMessage::BattleEnd {
-                        outcome: self.convert_pvp_outcome_to_wild(player2_outcome),
-                        reason: BattleEndReason::AllPlayerPokemonFainted, // Simplified for now
-                        pokemon_captured: None, // No captures in PvP
-                    };
-                                        
                     // Drop lock before any external operations to avoid deadlocks
                     drop(battle_state);
                     
@@ -1111,112 +2257,442 @@ impl BattleManager {
                     // This prevents deadlock when trying to remove while still holding a reference
                     drop(battle_entry);
                     
-                    // Now remove the battle from active battles
-                    self.active_pvp_battles.remove(&battle_id);
-                    
-                    // Reset combat flags for both players
-                    if let Some(mut player1_state) = lobby.player_positions.get_mut(&player1_id) {
-                        player1_state.value_mut().in_combat = false;
-                    }
-                    if let Some(mut player2_state) = lobby.player_positions.get_mut(&player2_id) {
-                        player2_state.value_mut().in_combat = false;
-                    }
-                    
-                    // Send messages to players
-                    if let Err(e) = lobby.send_to_player(&player1_id, &player1_end_message).await {
-                        error!("Failed to send battle end message to player 1: {}", e);
-                    }
-                    if let Err(e) = lobby.send_to_player(&player2_id, &player2_end_message).await {
-                        error!("Failed to send battle end message to player 2: {}", e);
-                    }
-                    
-                    info!("PvP battle {} ended", battle_id);
+                    self.finish_pvp_battle(
+                        battle_id,
+                        lobby,
+                        pokemon_collection_manager,
+                        (player1_outcome, BattleEndReason::AllPlayerPokemonFainted),
+                        (player2_outcome, BattleEndReason::AllPlayerPokemonFainted),
+                    ).await?;
                     return Ok(());
                 },
                 _ => {
-                    error!("Unexpected battle phase {:?} after turn processing for PvP battle {}", battle_state.battle_phase, battle_id);
+                    error!("Unexpected battle phase {:?} after turn processing for PvP battle {}", battle_state.battle_phase.get(), battle_id);
                 }
             }
         } else {
             // Still waiting for the other player's action
             // Determine which player we're still waiting for
             if battle_state.player1_action.is_none() && battle_state.player2_action.is_some() {
-                battle_state.battle_phase = BattlePvPPhase::WaitingForPlayer1Action;
+                battle_state.battle_phase.transition(BattlePvPPhase::WaitingForPlayer1Action);
                 info!("Waiting for Player 1's action in PvP battle {}", battle_id);
             } else if battle_state.player1_action.is_some() && battle_state.player2_action.is_none() {
-                battle_state.battle_phase = BattlePvPPhase::WaitingForPlayer2Action;
+                battle_state.battle_phase.transition(BattlePvPPhase::WaitingForPlayer2Action);
                 info!("Waiting for Player 2's action in PvP battle {}", battle_id);
+                if let Some(difficulty) = battle_state.practice {
+                    // The practice bot answers as soon as the player has chosen
+                    let bot_action = ai::choose_practice_action(&battle_state, difficulty);
+                    let bot_id = battle_state.player2.player_id.clone();
+                    drop(battle_state);
+                    drop(battle_entry);
+                    return Box::pin(self.apply_pvp_player_action(&bot_id, battle_id, bot_action, lobby, pokemon_collection_manager)).await;
+                }
             }
         }
         
         Ok(())
     }
     
-    /// Send request actions to both players in a PvP battle
-    async fn send_pvp_request_actions(
+    /// Finish a PvP battle: persist progress, clear combat flags and notify both players
+    async fn finish_pvp_battle(
         &self,
-        battle_state: &PvPBattleState,
+        battle_id: Uuid,
         lobby: &Arc<Lobby>,
+        pokemon_collection_manager: &Arc<PokemonCollectionManager>,
+        player1_result: (PvPBattleOutcome, BattleEndReason),
+        player2_result: (PvPBattleOutcome, BattleEndReason),
     ) -> Result<(), String> {
-        // Prepare data for player 1
-        let player1_team_overview = battle_state.player1.team.iter()
-            .map(|p| BattlePokemonTeamOverview::from_battle_pokemon(p))
-            .collect::<Vec<_>>();
-        
-        let player1_active_pokemon = &battle_state.player1.team[battle_state.player1.active_pokemon_index];
-        let player1_active_view = BattlePokemonPrivateView::from_battle_pokemon(
-            player1_active_pokemon,
-            battle_state.move_repository.as_ref()
-        );
-        
-        let player2_active_pokemon = &battle_state.player2.team[battle_state.player2.active_pokemon_index];
-        let player1_opponent_view = BattlePokemonPublicView::from_battle_pokemon(player2_active_pokemon);
-        
-        // Prepare data for player 2
-        let player2_team_overview = battle_state.player2.team.iter()
-            .map(|p| BattlePokemonTeamOverview::from_battle_pokemon(p))
-            .collect::<Vec<_>>();
+        // Remove the battle first so concurrent paths (turns, grace timers) cannot finish it twice
+        let battle_mutex = match self.active_pvp_battles.remove(&battle_id) {
+            Some((_, battle_mutex)) => battle_mutex,
+            None => return Err(format!("PvP Battle {} not found", battle_id)),
+        };
+        let mut battle_state = battle_mutex.lock().await;
+        let player1_id = battle_state.player1.player_id.clone();
+        let player2_id = battle_state.player2.player_id.clone();
+        self.unindex_battle(&player1_id, battle_id);
+        self.unindex_battle(&player2_id, battle_id);
+        self.pvp_disconnected_players.remove(&player1_id);
+        self.pvp_disconnected_players.remove(&player2_id);
+
+        let (player1_outcome, player1_reason) = player1_result;
+        let (player2_outcome, player2_reason) = player2_result;
+
+        // Reveal the seed so the players (or anyone they share the transcript with) can verify the battle
+        let fairness_message = fairness::BattleTranscript::from_snapshots(battle_id, &battle_state.turn_snapshots)
+            .map(|transcript| ServerMessage::BattleFairness {
+                disclosure: fairness::FairnessDisclosure {
+                    battle_id,
+                    seed: battle_state.rng_seed,
+                    seed_commitment: fairness::seed_commitment(battle_state.rng_seed),
+                    transcript_hash: transcript.hash(),
+                },
+                transcript,
+            });
+
+        // Keep a replay of battles that may be disputed
+        let mut flags = Vec::new();
+        let disconnect_reasons = [BattleEndReason::PlayerDisconnected, BattleEndReason::OpponentDisconnected];
+        if disconnect_reasons.contains(&player1_reason) || disconnect_reasons.contains(&player2_reason) {
+            flags.push(ReplayFlag::Disconnect);
+        }
+        for (player_id, &rejected_actions) in &battle_state.rejected_actions {
+            if rejected_actions >= SUSPICIOUS_REJECTED_ACTIONS {
+                flags.push(ReplayFlag::SuspectedCheating { player_id: player_id.clone(), rejected_actions });
+            }
+        }
+        let replay = if flags.is_empty() {
+            None
+        } else {
+            Some(BattleReplay {
+                battle_id,
+                player1_id: player1_id.clone(),
+                player2_id: player2_id.clone(),
+                flags,
+                started_at: battle_state.started_at,
+                ended_at: chrono::Utc::now().timestamp() as u64,
+                turns: std::mem::take(&mut battle_state.turn_snapshots),
+            })
+        };
+
+        let summary = PvPBattleSummary {
+            battle_id,
+            lobby: lobby.clone(),
+            player1_id: player1_id.clone(),
+            player2_id: player2_id.clone(),
+            player1_team: battle_state.player1.team.iter().map(BattlePokemonTeamOverview::from_battle_pokemon).collect(),
+            player2_team: battle_state.player2.team.iter().map(BattlePokemonTeamOverview::from_battle_pokemon).collect(),
+            player1_outcome: player1_outcome.clone(),
+            player2_outcome: player2_outcome.clone(),
+            turns: battle_state.turn_number,
+            started_at: battle_state.started_at,
+            moves_used: battle_state.moves_used(),
+            replay,
+        };
+
+        // Write progress back to both collections and track which pokemon leveled up.
+        // Rental Pokémon are not in any collection, so their exp is simply dropped,
+        // and nothing from a practice battle or a flat rules battle is kept. Gym battles
+        // are the exception among practice battles: the player keeps their progress.
+        let practice = battle_state.practice.is_some();
+        let gym_id = battle_state.gym_id.clone();
+        let keep_progress = (!practice || gym_id.is_some()) && battle_state.field_state.rules.flat_level.is_none();
+        let player1_leveled_pokemon = if !keep_progress || battle_state.player1.rental_team.is_some() {
+            Vec::new()
+        } else {
+            self.persist_team_progress(&player1_id, &battle_state.player1.team, pokemon_collection_manager).await
+        };
+        let player2_leveled_pokemon = if !keep_progress || battle_state.player2.rental_team.is_some() {
+            Vec::new()
+        } else {
+            self.persist_team_progress(&player2_id, &battle_state.player2.team, pokemon_collection_manager).await
+        };
+        // Drop lock before any external operations to avoid deadlocks
+        drop(battle_state);
+
+        // Send collection updates for leveled pokemon
+        for (player_id, leveled_pokemon) in [(&player1_id, &player1_leveled_pokemon), (&player2_id, &player2_leveled_pokemon)] {
+            if leveled_pokemon.is_empty() {
+                continue;
+            }
+            if let Ok(collection) = pokemon_collection_manager.get_active_pokemons(player_id).await {
+                let collection_update = ServerMessage::ActivePokemons {
+                    pokemons: collection.iter()
+                        .map(|p| pokemon_collection_manager.pokemon_to_display_pokemon(p))
+                        .collect()
+                };
+                if let Err(e) = lobby.send_to_player(player_id, &collection_update).await {
+                    error!("Failed to send collection update to player {}: {}", player_id, e);
+                }
+            }
+        }
+
+        // Prepare battle end messages
+        let player1_end_message = ServerMessage::BattleEnd {
+            outcome: self.convert_pvp_outcome_to_wild(player1_outcome),
+            reason: player1_reason,
+            pokemon_captured: None, // No captures in PvP
+        };
         
-        let player2_active_view = BattlePokemonPrivateView::from_battle_pokemon(
-            player2_active_pokemon,
-            battle_state.move_repository.as_ref()
-        );
+        let player2_end_message = ServerMessage::BattleEnd {
+            outcome: self.convert_pvp_outcome_to_wild(player2_outcome),
+            reason: player2_reason,
+            pokemon_captured: None, // No captures in PvP
+        };
         
-        let player2_opponent_view = BattlePokemonPublicView::from_battle_pokemon(player1_active_pokemon);
+        // Reset combat flags for both players
+        if let Some(mut player1_state) = lobby.player_positions.get_mut(&player1_id) {
+            player1_state.value_mut().in_combat = false;
+        }
+        if let Some(mut player2_state) = lobby.player_positions.get_mut(&player2_id) {
+            player2_state.value_mut().in_combat = false;
+        }
         
-        // For now, reuse the existing RequestAction message
-        // In the future, we might want a dedicated PvPRequestAction message
+        // Send messages to players
+        if let Err(e) = self.send_to_battler(lobby, &player1_id, &player1_end_message).await {
+            error!("Failed to send battle end message to player 1: {}", e);
+        }
+        if let Err(e) = self.send_to_battler(lobby, &player2_id, &player2_end_message).await {
+            error!("Failed to send battle end message to player 2: {}", e);
+        }
+        if let Some(fairness_message) = &fairness_message {
+            for player_id in [&player1_id, &player2_id] {
+                if let Err(e) = self.send_to_battler(lobby, player_id, fairness_message).await {
+                    error!("Failed to send battle fairness disclosure to player {}: {}", player_id, e);
+                }
+            }
+        }
         
-        // Send request to player 1
-        let player1_request = ServerMessage::RequestAction {
-            turn_number: battle_state.turn_number,
-            active_pokemon_state: player1_active_view,
-            team_overview: player1_team_overview,
-            other_pokemon_state: player1_opponent_view, // Using opponent's public view here
-            can_switch: battle_state.player1.team.iter().filter(|p| !p.is_fainted).count() > 1,
-            must_switch: battle_state.player1.must_switch,
-            field_state: battle_state.field_state.clone(),
+        lobby.events.record(LobbyEventKind::PvPBattleEnded {
+            battle_id,
+            player1_id: player1_id.clone(),
+            player2_id: player2_id.clone(),
+            player1_outcome: summary.player1_outcome.clone(),
+        });
+
+        // Rankings, payouts, history and telemetry only track real battles
+        if let Some(gym_id) = gym_id {
+            self.notify_gym_battle_finished(&GymBattleSummary {
+                battle_id,
+                lobby: lobby.clone(),
+                player_id: player1_id.clone(),
+                gym_id,
+                outcome: summary.player1_outcome.clone(),
+            });
+        } else if !practice {
+            self.notify_pvp_battle_finished(&summary);
+        }
+
+        info!("PvP battle {} ended", battle_id);
+        Ok(())
+    }
+
+    /// Start the reconnect grace period for a player who dropped out of a PvP battle.
+    /// If they have not resumed when it expires, the opponent wins by disconnect.
+    pub async fn handle_pvp_disconnect(
+        self: &Arc<Self>,
+        battle_id: Uuid,
+        player_id: &str,
+        lobby: &Arc<Lobby>,
+        pokemon_collection_manager: &Arc<PokemonCollectionManager>,
+    ) {
+        let opponent_id = match self.find_pvp_opponent(battle_id, player_id) {
+            Some(opponent_id) => opponent_id,
+            None => {
+                warn!("Player {} is not part of PvP battle {}", player_id, battle_id);
+                return;
+            }
         };
-        
-        if let Err(e) = lobby.send_to_player(&battle_state.player1.player_id, &player1_request).await {
-            error!("Failed to send action request to player 1: {}", e);
-            return Err(format!("Failed to send action request to player 1: {}", e));
+
+        // A fresh token identifies this disconnect, so a reconnect-then-disconnect restarts the timer
+        let disconnect_token = Uuid::new_v4();
+        self.pvp_disconnected_players.insert(player_id.to_string(), (battle_id, disconnect_token));
+        info!("Player {} disconnected from PvP battle {}, waiting {}s for reconnect", player_id, battle_id, PVP_DISCONNECT_GRACE_SECS);
+
+        let notice = ServerMessage::OpponentDisconnected { battle_id, grace_period_secs: PVP_DISCONNECT_GRACE_SECS };
+        if let Err(e) = self.send_to_battler(lobby, &opponent_id, &notice).await {
+            error!("Failed to notify player {} about opponent disconnect: {}", opponent_id, e);
+        }
+
+        let battle_manager = self.clone();
+        let lobby = lobby.clone();
+        let pokemon_collection_manager = pokemon_collection_manager.clone();
+        let player_id = player_id.to_string();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_secs(PVP_DISCONNECT_GRACE_SECS)).await;
+
+            let still_disconnected = battle_manager.pvp_disconnected_players
+                .remove_if(&player_id, |_, (_, token)| *token == disconnect_token)
+                .is_some();
+            if !still_disconnected {
+                return;
+            }
+
+            let (player1_id, opponent_also_gone) = match battle_manager.get_pvp_battle_state(battle_id) {
+                Some(battle_mutex) => {
+                    let battle_state = battle_mutex.lock().await;
+                    (battle_state.player1.player_id.clone(), battle_manager.pvp_disconnected_players.contains_key(&opponent_id))
+                }
+                None => return,
+            };
+
+            // If both players are gone nobody earns the win
+            let (disconnected_result, remaining_result) = if opponent_also_gone {
+                ((PvPBattleOutcome::Draw, BattleEndReason::PlayerDisconnected), (PvPBattleOutcome::Draw, BattleEndReason::PlayerDisconnected))
+            } else {
+                ((PvPBattleOutcome::Disconnected, BattleEndReason::PlayerDisconnected), (PvPBattleOutcome::OpponentDisconnected, BattleEndReason::OpponentDisconnected))
+            };
+            let (player1_result, player2_result) = if player1_id == player_id {
+                (disconnected_result, remaining_result)
+            } else {
+                (remaining_result, disconnected_result)
+            };
+
+            info!("Reconnect grace period expired for player {} in PvP battle {}", player_id, battle_id);
+            if let Err(e) = battle_manager.finish_pvp_battle(battle_id, &lobby, &pokemon_collection_manager, player1_result, player2_result).await {
+                error!("Failed to finish PvP battle {} after disconnect: {}", battle_id, e);
+            }
+        });
+    }
+
+    /// PvP battle a disconnected player can still rejoin, if their grace period is running
+    pub fn pending_pvp_battle(&self, player_id: &str) -> Option<Uuid> {
+        self.pvp_disconnected_players.get(player_id).map(|entry| entry.value().0)
+    }
+
+    /// Resume a PvP battle for a player reconnecting within the grace period.
+    /// Returns the battle ID if one was resumed.
+    pub async fn resume_pvp_battle(&self, player_id: &str, lobby: &Arc<Lobby>) -> Option<Uuid> {
+        let (battle_id, _) = self.pvp_disconnected_players.remove(player_id)?.1;
+        let battle_mutex = self.get_pvp_battle_state(battle_id)?;
+        let mut battle_state = battle_mutex.lock().await;
+
+        let (player, opponent) = if battle_state.player1.player_id == player_id {
+            (&battle_state.player1, &battle_state.player2)
+        } else {
+            (&battle_state.player2, &battle_state.player1)
+        };
+
+        if let Some(mut player_state) = lobby.player_positions.get_mut(player_id) {
+            player_state.value_mut().in_combat = true;
+        }
+
+        // Re-send the battle start so the client can rebuild its battle screen
+        let resume_message = ServerMessage::PvPBattleStart {
+            battle_id,
+            player_team: player.team.iter().map(BattlePokemonTeamOverview::from_battle_pokemon).collect(),
+            initial_pokemon: self.private_view_for(
+                player_id,
+                &player.team[player.active_pokemon_index],
+                &opponent.team[opponent.active_pokemon_index],
+                battle_state.move_repository.as_ref()
+            ),
+            opponent_id: opponent.player_id.clone(),
+            opponent_username: opponent.name.clone(),
+            opponent_initial_pokemon: BattlePokemonPublicView::from_battle_pokemon(&opponent.team[opponent.active_pokemon_index]),
+            initial_field_state: battle_state.field_state.clone(),
+            player1_id: battle_state.player1.player_id.clone(),
+            player2_id: battle_state.player2.player_id.clone(),
+            seed_commitment: fairness::seed_commitment(battle_state.rng_seed),
+        };
+        let opponent_id = opponent.player_id.clone();
+        if let Err(e) = self.send_to_battler(lobby, player_id, &resume_message).await {
+            error!("Failed to send PvP resume message to player {}: {}", player_id, e);
+        }
+
+        let pending_result = self.send_pvp_pending_request(&mut battle_state, player_id, lobby).await;
+        if let Err(e) = pending_result {
+            error!("Failed to re-send pending request on PvP resume: {}", e);
         }
+
+        let notice = ServerMessage::OpponentReconnected { battle_id };
+        if let Err(e) = self.send_to_battler(lobby, &opponent_id, &notice).await {
+            error!("Failed to notify player {} about opponent reconnect: {}", opponent_id, e);
+        }
+
+        info!("Player {} resumed PvP battle {}", player_id, battle_id);
+        Some(battle_id)
+    }
+
+    /// Send whatever a PvP player still owes the battle (a switch or an action) with full state
+    async fn send_pvp_pending_request(
+        &self,
+        battle_state: &mut PvPBattleState,
+        player_id: &str,
+        lobby: &Arc<Lobby>,
+    ) -> Result<(), String> {
+        let is_player1 = battle_state.player1.player_id == player_id;
+        match battle_state.battle_phase.get() {
+            BattlePvPPhase::WaitingForPlayer1Switch if is_player1 => {
+                self.send_pvp_switch_request(battle_state, player_id, lobby).await
+            }
+            BattlePvPPhase::WaitingForPlayer2Switch if !is_player1 => {
+                self.send_pvp_switch_request(battle_state, player_id, lobby).await
+            }
+            BattlePvPPhase::WaitingForBothPlayersActions
+            | BattlePvPPhase::WaitingForPlayer1Action
+            | BattlePvPPhase::WaitingForPlayer2Action => {
+                let action_pending = if is_player1 { battle_state.player1_action.is_none() } else { battle_state.player2_action.is_none() };
+                if action_pending {
+                    self.send_pvp_request_action(battle_state, player_id, lobby, true).await
+                } else {
+                    Ok(())
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Send request actions to both players in a PvP battle
+    async fn send_pvp_request_actions(
+        &self,
+        battle_state: &mut PvPBattleState,
+        lobby: &Arc<Lobby>,
+    ) -> Result<(), String> {
+        let player1_id = battle_state.player1.player_id.clone();
+        let player2_id = battle_state.player2.player_id.clone();
+        self.send_pvp_request_action(battle_state, &player1_id, lobby, false).await?;
+        self.send_pvp_request_action(battle_state, &player2_id, lobby, false).await?;
+        Ok(())
+    }
+
+    /// Send a request action to one player in a PvP battle.
+    /// The team overview only carries changed entries unless `full` is set or the client predates deltas.
+    async fn send_pvp_request_action(
+        &self,
+        battle_state: &mut PvPBattleState,
+        player_id: &str,
+        lobby: &Arc<Lobby>,
+        full: bool,
+    ) -> Result<(), String> {
+        let move_repository = battle_state.move_repository.clone();
+        let field_state = battle_state.field_state.clone();
+        let turn_number = battle_state.turn_number;
+        let battle_id = battle_state.battle_id;
+        let battle_started_at = battle_state.started_at;
+        let (player, opponent) = if battle_state.player1.player_id == player_id {
+            (&mut battle_state.player1, &battle_state.player2)
+        } else if battle_state.player2.player_id == player_id {
+            (&mut battle_state.player2, &battle_state.player1)
+        } else {
+            return Err("Player ID not found in battle".to_string());
+        };
+
+        let full = full || lobby.is_legacy_client(player_id);
+        let (team_revision, team_overview_is_delta, team_overview) = next_team_overview(player, full);
+        
+        let active_view = self.private_view_for(
+            player_id,
+            &player.team[player.active_pokemon_index],
+            &opponent.team[opponent.active_pokemon_index],
+            move_repository.as_ref()
+        );
         
-        // Send request to player 2
-        let player2_request = ServerMessage::RequestAction {
-            turn_number: battle_state.turn_number,
-            active_pokemon_state: player2_active_view,
-            team_overview: player2_team_overview,
-            other_pokemon_state: player2_opponent_view, // Using opponent's public view here
-            can_switch: battle_state.player2.team.iter().filter(|p| !p.is_fainted).count() > 1,
-            must_switch: battle_state.player2.must_switch,
-            field_state: battle_state.field_state.clone(),
+        let opponent_view = BattlePokemonPublicView::from_battle_pokemon(&opponent.team[opponent.active_pokemon_index]);
+        
+        let request = ServerMessage::PvPRequestAction {
+            battle_id,
+            turn_number,
+            active_pokemon_state: active_view,
+            team_overview,
+            team_revision,
+            team_overview_is_delta,
+            opponent_pokemon_state: opponent_view,
+            opponent_team_size: opponent.team.len(),
+            opponent_remaining: opponent.team.iter().filter(|p| !p.is_fainted).count(),
+            own_side: player.side_effects.clone(),
+            opponent_side: opponent.side_effects.clone(),
+            can_switch: player.team.iter().filter(|p| !p.is_fainted).count() > 1,
+            must_switch: player.must_switch,
+            opponent_must_switch: opponent.must_switch,
+            field_state,
+            battle_started_at,
+            disconnect_grace_secs: PVP_DISCONNECT_GRACE_SECS,
         };
         
-        if let Err(e) = lobby.send_to_player(&battle_state.player2.player_id, &player2_request).await {
-            error!("Failed to send action request to player 2: {}", e);
-            return Err(format!("Failed to send action request to player 2: {}", e));
+        if let Err(e) = self.send_to_battler(lobby, player_id, &request).await {
+            error!("Failed to send action request to player {}: {}", player_id, e);
+            return Err(format!("Failed to send action request to player {}: {}", player_id, e));
         }
         
         Ok(())
@@ -1252,7 +2728,7 @@ impl BattleManager {
             available_switches,
         };
         
-        if let Err(e) = lobby.send_to_player(player_id, &switch_request).await {
+        if let Err(e) = self.send_to_battler(lobby, player_id, &switch_request).await {
             error!("Failed to send switch request to player {}: {}", player_id, e);
             return Err(format!("Failed to send switch request to player: {}", e));
         }
@@ -1295,20 +2771,38 @@ impl BattleManager {
     }
 }
 
+/// Build the team overview for an action request: only the entries that changed since the
+/// last request unless `full` is set. Returns (revision, is_delta, overview).
+fn next_team_overview(player: &mut BattlePlayer, full: bool) -> (u32, bool, Vec<BattlePokemonTeamOverview>) {
+    let current = player.team.iter()
+        .map(BattlePokemonTeamOverview::from_battle_pokemon)
+        .collect::<Vec<_>>();
+    let is_delta = !full && player.sent_team_overview.len() == current.len();
+    let overview = if is_delta {
+        current.iter()
+            .zip(player.sent_team_overview.iter())
+            .filter(|(now, sent)| now != sent)
+            .map(|(now, _)| now.clone())
+            .collect()
+    } else {
+        current.clone()
+    };
+    player.team_revision += 1;
+    player.sent_team_overview = current;
+    (player.team_revision, is_delta, overview)
+}
+
 // Placeholder validation function
 fn validate_player_action(battle_state: &WildBattleState, action: &PlayerAction) -> Result<(), String> {
     match action {
         PlayerAction::UseMove { move_index } => {
             let active_pokemon = &battle_state.player.team[battle_state.player.active_pokemon_index];
-            if *move_index >= active_pokemon.moves.len() {
-                return Err("Invalid move index".to_string());
-            }
-            let battle_move = &active_pokemon.moves[*move_index];
-            if battle_move.current_pp == 0 {
-                return Err("Move has no PP left".to_string());
-            }
+            logic::core::validate_move_choice(active_pokemon, *move_index)?;
             // TODO: Add more checks (imprisoned, disabled, taunted etc.)
         },
+        PlayerAction::UseSuperMove { move_index } => {
+            battle_state.player.validate_super_move(*move_index, battle_state.move_repository.as_deref())?;
+        },
         PlayerAction::SwitchPokemon { team_index } => {
             if *team_index >= battle_state.player.team.len() {
                 return Err("Invalid team index for switch".to_string());
@@ -1329,21 +2823,18 @@ fn validate_player_action(battle_state: &WildBattleState, action: &PlayerAction)
         PlayerAction::Run => {
             // Running is always a valid *choice*, success is determined later
         }
+        PlayerAction::PreBattleModifier { .. } => {
+            if battle_state.turn_number != 1 {
+                return Err("Bait and mud can only be thrown on the first turn".to_string());
+            }
+            if battle_state.encounter_modifier.is_some() {
+                return Err("Something was already thrown this encounter".to_string());
+            }
+        }
     }
     Ok(())
 }
 
-// Placeholder AI function
-fn determine_wild_action(battle_state: &WildBattleState) -> crate::combat::state::WildPokemonAction {
-    // Very basic: just use the first available move
-    if let Some(first_move) = battle_state.wild_pokemon.moves.iter().position(|m| m.current_pp > 0) {
-        crate::combat::state::WildPokemonAction::UseMove { move_index: first_move }
-    } else {
-        // If no moves have PP, use Struggle
-        crate::combat::state::WildPokemonAction::Struggle
-    }
-}
-
 // Add helper From implementations for view structs (can be moved to state.rs or utils.rs)
 impl BattlePokemonTeamOverview {
     fn from_battle_pokemon(pokemon: &BattlePokemon) -> Self {
@@ -1351,6 +2842,7 @@ impl BattlePokemonTeamOverview {
              template_id: pokemon.template_id,
              name: pokemon.name.clone(),
              level: pokemon.level,
+             form: pokemon.form.clone(),
              current_hp_percent: if pokemon.max_hp == 0 { 0.0 } else { pokemon.current_hp as f32 / pokemon.max_hp as f32 },
              current_hp: pokemon.current_hp,
              max_hp: pokemon.max_hp,
@@ -1370,6 +2862,7 @@ impl BattlePokemonPublicView {
              current_hp_percent: if pokemon.max_hp == 0 { 0.0 } else { pokemon.current_hp as f32 / pokemon.max_hp as f32 },
              max_hp: pokemon.max_hp,
              types: pokemon.pokemon_types.clone(),
+             form: pokemon.form.clone(),
              status: pokemon.status.clone(),
              stat_modifiers: pokemon.stat_modifiers.clone(),
              is_fainted: pokemon.is_fainted,
@@ -1388,6 +2881,7 @@ impl BattlePokemonPrivateView {
              current_hp_percent: if pokemon.max_hp == 0 { 0.0 } else { pokemon.current_hp as f32 / pokemon.max_hp as f32 },
              max_hp: pokemon.max_hp,
              types: pokemon.pokemon_types.clone(),
+             form: pokemon.form.clone(),
              ability: pokemon.ability.clone(),
              status: pokemon.status.clone(),
              volatile_statuses: pokemon.volatile_statuses.keys().cloned().collect(),
@@ -1413,6 +2907,7 @@ impl BattlePokemonPrivateView {
                              power: move_data.power,
                              accuracy: move_data.accuracy,
                              description: move_data.description.clone(),
+                             effectiveness: None,
                          }
                      } else {
                          // Fallback for unknown move
@@ -1426,6 +2921,7 @@ impl BattlePokemonPrivateView {
                              power: Some(50),
                              accuracy: Some(100),
                              description: "".to_string(),
+                             effectiveness: None,
                          }
                      }
                  } else {
@@ -1440,6 +2936,7 @@ impl BattlePokemonPrivateView {
                          power: Some(50),
                          accuracy: Some(100),
                          description: "".to_string(),
+                         effectiveness: None,
                      }
                  }
              }).collect(),
@@ -1447,4 +2944,35 @@ impl BattlePokemonPrivateView {
              team_index: pokemon.position,
          }
      }
-} 
\ No newline at end of file
+}
+
+impl CapturedPokemonView {
+     fn from_captured(
+         pokemon: &Pokemon,
+         battle_pokemon: &BattlePokemon,
+         destination: Option<StorageLocation>,
+         move_repo: Option<&Arc<crate::monsters::move_manager::MoveRepository>>,
+     ) -> Self {
+         // Reuse the private view's move mapping so the confirmation screen matches the battle UI
+         let private_view = BattlePokemonPrivateView::from_battle_pokemon(battle_pokemon, move_repo);
+         CapturedPokemonView {
+             pokemon_id: pokemon.id.clone(),
+             template_id: pokemon.template_id,
+             name: pokemon.name.clone(),
+             level: pokemon.level,
+             current_hp: pokemon.current_hp,
+             max_hp: battle_pokemon.max_hp,
+             types: pokemon.types.clone(),
+             ability: pokemon.ability.clone(),
+             nature: pokemon.nature,
+             ivs: pokemon.ivs.clone(),
+             calculated_stats: battle_pokemon.calculated_stats.clone(),
+             moves: private_view.moves,
+             active_index: match destination {
+                 Some(StorageLocation::Party { slot }) => Some(slot),
+                 _ => None,
+             },
+             destination,
+         }
+     }
+}
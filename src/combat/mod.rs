@@ -3,6 +3,17 @@ pub mod messages;
 pub mod manager;
 pub mod utils;
 pub mod logic;
+pub mod listeners;
+pub mod rng;
+pub mod replay;
+pub mod ai;
+pub mod fairness;
+pub mod team_validation;
+pub mod rental_teams;
+pub mod gyms;
+pub mod draft;
+#[cfg(test)]
+pub(crate) mod test_support;
 
 // Re-export key types from state module
 pub use state::{
@@ -28,4 +39,6 @@ pub use state::{
     BattlePokemonPrivateView,
     BattleMoveView,
     BattlePokemonTeamOverview,
-}; 
\ No newline at end of file
+    CapturedPokemonView,
+};
+pub use listeners::{BattleOutcomeListener, WildBattleSummary, PvPBattleSummary}; 
\ No newline at end of file
@@ -0,0 +1,74 @@
+use std::fs::File;
+use std::io::BufReader;
+
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::game_loop::pokemon_collection::Pokemon;
+use crate::monsters::monster_manager::MonsterTemplateRepository;
+
+/// One member of a rental team
+#[derive(Debug, Clone, Deserialize)]
+pub struct RentalPokemon {
+    pub template_id: u32,
+    pub level: u32,
+}
+
+/// A server-defined team lent to players who cannot field six Pokémon of their own
+#[derive(Debug, Clone, Deserialize)]
+pub struct RentalTeam {
+    pub id: String,
+    pub name: String,
+    pub pokemon: Vec<RentalPokemon>,
+}
+
+/// Every rental team players can be lent in PvP
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RentalTeams {
+    #[serde(default)]
+    pub teams: Vec<RentalTeam>,
+}
+
+impl RentalTeams {
+    /// Load the rental teams at `path`, dropping members whose species is unknown and teams left empty.
+    /// No rental teams are offered when the file cannot be read.
+    pub fn load(path: &str, template_repository: &MonsterTemplateRepository) -> Self {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => {
+                warn!("Failed to open rental teams {}: {}", path, e);
+                return RentalTeams::default();
+            }
+        };
+        let mut rental_teams = match serde_json::from_reader::<_, RentalTeams>(BufReader::new(file)) {
+            Ok(rental_teams) => rental_teams,
+            Err(e) => {
+                warn!("Failed to parse rental teams {}: {}", path, e);
+                return RentalTeams::default();
+            }
+        };
+        for team in &mut rental_teams.teams {
+            team.pokemon.retain(|member| {
+                let known = template_repository.templates.contains_key(&member.template_id);
+                if !known {
+                    warn!("Rental team {}: skipping unknown species {}", team.id, member.template_id);
+                }
+                known
+            });
+        }
+        rental_teams.teams.retain(|team| !team.pokemon.is_empty());
+        info!("Loaded {} rental teams from {}", rental_teams.teams.len(), path);
+        rental_teams
+    }
+
+    /// Fresh Pokémon for a randomly chosen rental team, all at `level` when given. They exist
+    /// only for one battle and are never added to a collection.
+    pub fn lend_team(&self, template_repository: &MonsterTemplateRepository, level: Option<u32>) -> Option<(String, Vec<Pokemon>)> {
+        let team = self.teams.choose(&mut rand::thread_rng())?;
+        let pokemons = team.pokemon.iter()
+            .map(|member| template_repository.pokemon_from_template(member.template_id, Some(level.unwrap_or(member.level))))
+            .collect();
+        Some((team.id.clone(), pokemons))
+    }
+}
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::combat::state::{BattleEvent, BattlePlayer, FieldState, PlayerAction};
+
+/// Rejected actions from one player after which a battle is flagged for review
+pub const SUSPICIOUS_REJECTED_ACTIONS: u32 = 5;
+
+/// Why a finished battle's replay was kept for review
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReplayFlag {
+    /// A player dropped out and the battle was decided by disconnect
+    Disconnect,
+    /// A player kept submitting actions the server had to reject
+    SuspectedCheating { player_id: String, rejected_actions: u32 },
+}
+
+/// One processed turn: the state going into it, the actions taken, the RNG seed it ran with
+/// and the events it produced. Re-running the turn from this data gives the same events.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnSnapshot {
+    pub turn_number: u32,
+    pub rng_seed: u64,
+    pub player1_action: Option<PlayerAction>,
+    pub player2_action: Option<PlayerAction>,
+    pub player1: BattlePlayer,
+    pub player2: BattlePlayer,
+    pub field_state: FieldState,
+    pub events: Vec<BattleEvent>,
+}
+
+/// Turn-by-turn record of a flagged PvP battle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BattleReplay {
+    pub battle_id: Uuid,
+    pub player1_id: String,
+    pub player2_id: String,
+    pub flags: Vec<ReplayFlag>,
+    pub started_at: u64,
+    pub ended_at: u64,
+    pub turns: Vec<TurnSnapshot>,
+}
+
+/// Listing entry for a stored replay, without the turn data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplaySummary {
+    pub battle_id: Uuid,
+    pub player1_id: String,
+    pub player2_id: String,
+    pub flags: Vec<ReplayFlag>,
+    pub turns: usize,
+    pub ended_at: u64,
+}
+
+impl BattleReplay {
+    pub fn summary(&self) -> ReplaySummary {
+        ReplaySummary {
+            battle_id: self.battle_id,
+            player1_id: self.player1_id.clone(),
+            player2_id: self.player2_id.clone(),
+            flags: self.flags.clone(),
+            turns: self.turns.len(),
+            ended_at: self.ended_at,
+        }
+    }
+}
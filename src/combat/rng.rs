@@ -0,0 +1,20 @@
+use rand::{rngs::StdRng, SeedableRng};
+use std::cell::RefCell;
+
+thread_local! {
+    static BATTLE_RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// Run `f` with the battle RNG seeded from `seed`, so every roll it makes can be reproduced.
+/// Turn processing is synchronous, so the seeded RNG never leaks into another battle.
+pub fn with_seed<T>(seed: u64, f: impl FnOnce() -> T) -> T {
+    let previous = BATTLE_RNG.with(|rng| rng.replace(StdRng::seed_from_u64(seed)));
+    let result = f();
+    BATTLE_RNG.with(|rng| rng.replace(previous));
+    result
+}
+
+/// Source of every random roll in battle logic
+pub fn with_rng<T>(f: impl FnOnce(&mut StdRng) -> T) -> T {
+    BATTLE_RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
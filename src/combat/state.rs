@@ -3,10 +3,63 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use uuid::Uuid;
 
+use crate::combat::logic::battle_calculations::DamageBreakdown;
+use crate::game_loop::pokemon_collection::StorageLocation;
+use crate::monsters::move_manager::{MoveData, TargetType};
 use crate::monsters::PokemonType;
 use crate::stats::nature::Nature;
 use crate::stats::{BaseStats, BattleStatModifiers, CalculatedStats, StatName, StatSet};
 
+/// Maximum number of events kept in a battle's log; older events are dropped first
+pub const MAX_BATTLE_LOG_EVENTS: usize = 500;
+
+/// Append events to a battle log, trimming the oldest entries beyond the cap
+fn append_to_battle_log(battle_log: &mut Vec<BattleEvent>, events: &[BattleEvent]) {
+    // Damage breakdowns are for live overlays only and are not worth keeping
+    battle_log.extend(events.iter().filter(|event| !matches!(event, BattleEvent::DamageBreakdown { .. })).cloned());
+    if battle_log.len() > MAX_BATTLE_LOG_EVENTS {
+        let overflow = battle_log.len() - MAX_BATTLE_LOG_EVENTS;
+        battle_log.drain(..overflow);
+    }
+}
+
+/// Phase enums whose allowed transitions are spelled out
+pub trait Phase: Copy + Eq + std::fmt::Debug {
+    /// Whether a battle may move from this phase to `next`. Staying in the same phase is always allowed.
+    fn can_transition_to(self, next: Self) -> bool;
+}
+
+/// Current phase of a battle. The phase can only change through `transition`, which
+/// rejects and logs moves the phase's transition table does not allow.
+#[derive(Debug, Clone, Copy)]
+pub struct PhaseMachine<P: Phase> {
+    battle_id: Uuid,
+    phase: P,
+}
+
+impl<P: Phase> PhaseMachine<P> {
+    pub fn new(battle_id: Uuid, initial: P) -> Self {
+        Self { battle_id, phase: initial }
+    }
+
+    pub fn get(&self) -> P {
+        self.phase
+    }
+
+    /// Move to `next` if allowed, returning whether the phase is now `next`
+    pub fn transition(&mut self, next: P) -> bool {
+        if self.phase == next {
+            return true;
+        }
+        if !self.phase.can_transition_to(next) {
+            tracing::error!("Rejected illegal phase transition {:?} -> {:?} in battle {}", self.phase, next, self.battle_id);
+            return false;
+        }
+        self.phase = next;
+        true
+    }
+}
+
 /// Main Battle State Container for a wild Pokémon encounter
 #[derive(Debug)]
 pub struct WildBattleState {
@@ -14,14 +67,25 @@ pub struct WildBattleState {
     pub player: BattlePlayer,
     pub wild_pokemon: BattlePokemon,
     pub turn_number: u32,
-    pub battle_phase: BattlePhase,
+    pub battle_phase: PhaseMachine<BattlePhase>,
     pub player_action: Option<PlayerAction>, // Stores submitted action for the turn
     pub wild_action: Option<WildPokemonAction>, // Store action for wild Pokémon
     pub turn_order: Option<TurnOrder>, // Determined after actions are submitted
     pub field_state: FieldState,
     pub battle_log: Vec<BattleEvent>, // Log of events for client
     pub capture_attempts: Vec<CaptureAttempt>, // Track Poké Ball throws
+    pub encounter_modifier: Option<WildEncounterModifier>, // Bait or mud thrown on the first turn
+    pub started_at: u64, // Unix timestamp (seconds) when the battle began
+    pub last_activity_at: std::time::Instant, // Last time the player acted, for the inactivity timeout
+    pub timed_out: bool, // Set when the battle was ended because the player stopped acting
+    pub auto_battle: bool, // Whether the server plays the player's turns
+    pub auto_battle_run: u32, // Bumped every time auto-battle is switched on, so a stale auto-battle task stops
     pub move_repository: Option<std::sync::Arc<crate::monsters::move_manager::MoveRepository>>, // Reference to move repository for move info
+    pub ability_repository: Option<std::sync::Arc<crate::monsters::ability_manager::AbilityRepository>>, // Battle effects of the Pokémon's abilities
+    pub scripted: bool, // Tutorial battle: the player's Pokémon cannot faint and every ball catches
+    pub wild_catch_rate: u8, // Species catch rate of the wild Pokémon, from 1 (hardest) to 255 (easiest)
+    pub wild_shiny: bool, // Copied from the overworld monster when the battle starts
+    pub wild_alpha: bool, // Copied from the overworld monster when the battle starts
 }
 
 /// Main Battle State Container for a PvP battle between two players
@@ -31,13 +95,20 @@ pub struct PvPBattleState {
     pub player1: BattlePlayer,
     pub player2: BattlePlayer,
     pub turn_number: u32,
-    pub battle_phase: BattlePvPPhase,
+    pub battle_phase: PhaseMachine<BattlePvPPhase>,
     pub player1_action: Option<PlayerAction>, // Stores submitted action for player 1
     pub player2_action: Option<PlayerAction>, // Stores submitted action for player 2
     pub turn_order: Option<PvPTurnOrder>, // Determined after actions are submitted
     pub field_state: FieldState,
     pub battle_log: Vec<BattleEvent>, // Log of events for client
+    pub started_at: u64, // Unix timestamp (seconds) when the battle began
     pub move_repository: Option<std::sync::Arc<crate::monsters::move_manager::MoveRepository>>, // Reference to move repository for move info
+    pub ability_repository: Option<std::sync::Arc<crate::monsters::ability_manager::AbilityRepository>>, // Battle effects of the Pokémon's abilities
+    pub turn_snapshots: Vec<crate::combat::replay::TurnSnapshot>, // Kept in memory and persisted only if the battle gets flagged
+    pub rejected_actions: HashMap<String, u32>, // Player ID → actions the server rejected
+    pub rng_seed: u64, // Secret until the battle ends; every turn's seed is derived from it
+    pub practice: Option<crate::combat::ai::PracticeDifficulty>, // Set for practice battles, where player 2 is the server bot
+    pub gym_id: Option<String>, // Set for gym battles, a kind of practice battle where the server bot plays the gym leader
 }
 
 
@@ -71,6 +142,21 @@ pub enum BattlePvPPhase {
     Finished,                     // Battle has ended
 }
 
+impl Phase for BattlePvPPhase {
+    fn can_transition_to(self, next: Self) -> bool {
+        use BattlePvPPhase::*;
+        match (self, next) {
+            (Finished, _) => false,
+            // Surrender, disconnects and timeouts can end a battle at any point
+            (_, Finished) => true,
+            (WaitingForBothPlayersActions, WaitingForPlayer1Action | WaitingForPlayer2Action | ProcessingTurn) => true,
+            (WaitingForPlayer1Action | WaitingForPlayer2Action | WaitingForPlayer1Switch | WaitingForPlayer2Switch, ProcessingTurn) => true,
+            (ProcessingTurn, WaitingForBothPlayersActions | WaitingForPlayer1Switch | WaitingForPlayer2Switch) => true,
+            _ => false,
+        }
+    }
+}
+
 /// Reason the PvP battle ended
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
@@ -86,7 +172,7 @@ pub enum PvPBattleEndReason {
 }
 
 /// Outcome of a PvP battle
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, schemars::JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum PvPBattleOutcome {
     Victory,                // You won the battle
@@ -109,6 +195,44 @@ pub struct BattlePlayer {
     pub side_effects: PlayerSideState, // Effects specific to this player's side
     pub last_action_submitted: Option<PlayerAction>, // Track submitted action
     pub must_switch: bool, // Flag if the player needs to switch due to faint/Roar etc.
+    #[serde(skip)]
+    pub sent_team_overview: Vec<BattlePokemonTeamOverview>, // Last team overview sent, used for deltas
+    #[serde(skip)]
+    pub team_revision: u32, // Incremented every time a team overview is sent
+    /// Type of the crystal the player had equipped when the battle started
+    #[serde(default)]
+    pub crystal_type: Option<PokemonType>,
+    /// A super move can only be used once per battle
+    #[serde(default)]
+    pub super_move_used: bool,
+    /// Rental team lent for this battle; progress of its Pokémon is never saved
+    #[serde(default)]
+    pub rental_team: Option<String>,
+}
+
+impl BattlePlayer {
+    /// Check that the player can turn the active Pokémon's move into a super move
+    pub fn validate_super_move(&self, move_index: usize, move_repository: Option<&crate::monsters::move_manager::MoveRepository>) -> Result<(), String> {
+        if self.super_move_used {
+            return Err("A super move was already used this battle".to_string());
+        }
+        let crystal_type = self.crystal_type.ok_or_else(|| "No crystal equipped".to_string())?;
+        let pokemon = &self.team[self.active_pokemon_index];
+        if !pokemon.pokemon_types.contains(&crystal_type) {
+            return Err("The equipped crystal does not match the active Pokémon's type".to_string());
+        }
+        let battle_move = pokemon.moves.get(move_index).ok_or_else(|| "Invalid move index".to_string())?;
+        if battle_move.current_pp == 0 {
+            return Err("Move has no PP left".to_string());
+        }
+        let is_damaging = move_repository
+            .and_then(|repo| repo.get_move(battle_move.move_id))
+            .is_some_and(|move_data| move_data.power.is_some_and(|power| power > 0));
+        if !is_damaging {
+            return Err("Only damaging moves can become super moves".to_string());
+        }
+        Ok(())
+    }
 }
 
 /// Represents a Pokémon in battle with all its dynamic state
@@ -141,6 +265,34 @@ pub struct BattlePokemon {
     pub is_fainted: bool,
     pub position: usize, // Position in the team array (0-5) - useful for client UI
     pub is_wild: bool,   // Indicates if this is a wild Pokémon
+    pub form: Option<String>, // Current form ID, None for the species' regular form
+    /// Forms this Pokémon switches between as the battle weather changes, including the one
+    /// it returns to without matching weather. Empty for species without weather forms.
+    pub weather_forms: Vec<WeatherForm>,
+    /// Highest level at which this Pokémon obeys its trainer; None when it always obeys,
+    /// like wild, rental and server bot Pokémon
+    #[serde(default)]
+    pub obedience_level: Option<u32>,
+}
+
+impl BattlePokemon {
+    /// Form and types the Pokémon has outside battle weather, which is what gets saved after battle
+    pub fn base_form(&self) -> (Option<String>, Vec<PokemonType>) {
+        match self.weather_forms.iter().find(|form| form.weather.is_none()) {
+            Some(base) => (base.form.clone(), base.types.clone()),
+            None => (self.form.clone(), self.pokemon_types.clone()),
+        }
+    }
+}
+
+/// A form a Pokémon takes on in battle, with the types and stats it has in that form
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeatherForm {
+    /// Weather that triggers this form; None for the form it has without matching weather
+    pub weather: Option<WeatherType>,
+    pub form: Option<String>,
+    pub types: Vec<PokemonType>,
+    pub stats: CalculatedStats,
 }
 
 /// A move in battle with PP tracking
@@ -153,7 +305,7 @@ pub struct BattleMove {
 }
 
 /// Temporary status effects that can be applied to Pokémon
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, schemars::JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum VolatileStatusType {
     Confusion,
@@ -165,7 +317,7 @@ pub enum VolatileStatusType {
     // Other volatile statuses can be added as needed
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, Copy, schemars::JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum StatusCondition {
     Burn, Freeze, Paralysis, Poison, Sleep, Toxic, // Toxic is distinct for damage calculation
@@ -179,7 +331,7 @@ pub struct VolatileStatusData {
 }
 
 /// Categorizes moves as physical, special, or status
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Copy)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Copy, schemars::JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum MoveCategory {
     Physical,
@@ -187,8 +339,18 @@ pub enum MoveCategory {
     Status,
 }
 
+impl From<crate::monsters::move_manager::MoveCategory> for MoveCategory {
+    fn from(category: crate::monsters::move_manager::MoveCategory) -> Self {
+        match category {
+            crate::monsters::move_manager::MoveCategory::Physical => MoveCategory::Physical,
+            crate::monsters::move_manager::MoveCategory::Special => MoveCategory::Special,
+            crate::monsters::move_manager::MoveCategory::Status => MoveCategory::Status,
+        }
+    }
+}
+
 /// Side effects specific to the player's side of the field
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, schemars::JsonSchema)]
 pub struct PlayerSideState {
     pub reflect_turns: u8,
     pub light_screen_turns: u8,
@@ -201,22 +363,73 @@ pub struct PlayerSideState {
 }
 
 /// Global field state affecting both sides
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, schemars::JsonSchema)]
 pub struct FieldState {
     pub weather: Option<WeatherState>,
     pub trick_room_turns: u8,
+    pub rules: BattleRules,
     // Other field-wide effects can be added as needed
 }
 
+/// Level every Pokémon is brought to under flat rules
+pub const FLAT_RULES_LEVEL: u32 = 50;
+
+/// Format rules agreed on when a battle is set up
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(default)]
+pub struct BattleRules {
+    /// Every hit uses the highest damage roll instead of a random one
+    pub disable_random_roll: bool,
+    pub disable_critical_hits: bool,
+    /// No two team members may be the same species
+    pub species_clause: bool,
+    /// Species and moves on the server banlist are not allowed
+    pub enforce_banlist: bool,
+    /// Highest level a team member may be
+    pub level_cap: Option<u32>,
+    /// Every team member battles at this level, with its stats recalculated for it.
+    /// Stored Pokémon keep their own level and nothing from the battle is written back.
+    pub flat_level: Option<u32>,
+    /// Teams are drafted from a shared species pool instead of brought along
+    pub draft: bool,
+}
+
+impl BattleRules {
+    /// Tournament deterministic mode: no damage variance at all
+    pub fn deterministic() -> Self {
+        BattleRules {
+            disable_random_roll: true,
+            disable_critical_hits: true,
+            ..BattleRules::default()
+        }
+    }
+
+    /// Reject rules no battle can be played under
+    pub fn validate(&self) -> Result<(), String> {
+        if self.flat_level.is_some_and(|level| !(1..=100).contains(&level)) {
+            return Err("Flat rules level must be between 1 and 100".to_string());
+        }
+        Ok(())
+    }
+
+    /// Flat rules: everyone battles at level 50 regardless of progression
+    pub fn flat() -> Self {
+        BattleRules {
+            flat_level: Some(FLAT_RULES_LEVEL),
+            ..BattleRules::default()
+        }
+    }
+}
+
 /// Weather state with type and duration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct WeatherState {
     pub weather_type: WeatherType,
     pub turns_left: u8, // Can be indefinite for ability-induced weather initially
 }
 
 /// Types of weather
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Copy)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Copy, schemars::JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum WeatherType {
     Rain,
@@ -236,8 +449,23 @@ pub enum BattlePhase {
     Finished,               // Battle has ended
 }
 
+impl Phase for BattlePhase {
+    fn can_transition_to(self, next: Self) -> bool {
+        use BattlePhase::*;
+        match (self, next) {
+            (Finished, _) => false,
+            // Running, capture and timeouts can end a battle at any point
+            (_, Finished) => true,
+            (WaitingForPlayerAction | WaitingForSwitch, ProcessingTurn) => true,
+            (ProcessingTurn, WaitingForPlayerAction | WaitingForSwitch | CaptureMechanics) => true,
+            (CaptureMechanics, ProcessingTurn) => true,
+            _ => false,
+        }
+    }
+}
+
 /// Action that a player can take during their turn
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, schemars::JsonSchema)]
 #[serde(tag = "action_type", rename_all = "snake_case")]
 pub enum PlayerAction {
     UseMove {
@@ -251,6 +479,66 @@ pub enum PlayerAction {
         is_capture_item: bool,
     },
     Run,
+    /// Use a move with boosted power; once per battle, with a crystal matching the active Pokémon's type
+    UseSuperMove {
+        move_index: usize,
+    },
+    /// Wild battles only, on the first turn: throw bait or mud at the wild Pokémon
+    PreBattleModifier {
+        modifier: WildEncounterModifier,
+    },
+}
+
+/// Pre-battle options thrown at a wild Pokémon; they last for the rest of the encounter
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WildEncounterModifier {
+    /// Keeps the wild Pokémon busy eating, making it less likely to flee
+    Bait,
+    /// Slows the wild Pokémon down, making it easier to catch
+    Mud,
+}
+
+impl WildEncounterModifier {
+    /// Multiplier on the wild Pokémon's chance to flee
+    pub fn flee_multiplier(&self) -> f64 {
+        match self {
+            WildEncounterModifier::Bait => 0.25,
+            WildEncounterModifier::Mud => 1.0,
+        }
+    }
+
+    /// Multiplier on the capture chance of every ball thrown
+    pub fn capture_multiplier(&self) -> f64 {
+        match self {
+            WildEncounterModifier::Bait => 1.0,
+            WildEncounterModifier::Mud => 1.5,
+        }
+    }
+}
+
+/// How the odds of a capture attempt were worked out, so players can see what makes a catch more likely
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CaptureOdds {
+    /// Chance the ball catches, from 0.0 to 1.0
+    pub catch_probability: f64,
+    /// Species catch rate of the wild Pokémon, from 1 (hardest) to 255 (easiest)
+    pub catch_rate: u8,
+    /// Remaining HP of the wild Pokémon as a fraction of its max HP
+    pub hp_fraction: f64,
+    /// Multiplier of the ball thrown
+    pub ball_multiplier: f64,
+    /// Multiplier from the wild Pokémon's status: 2 when asleep or frozen, 1.5 when
+    /// paralyzed, poisoned or burned
+    pub status_multiplier: f64,
+    /// Multiplier from bait or mud thrown earlier in the encounter
+    pub encounter_multiplier: f64,
+    /// Catch rate after HP, ball, status and encounter modifiers; the ball always catches
+    /// from 255 up
+    pub modified_catch_rate: f64,
+    /// A shake check passes when a random number below 65536 is under this threshold.
+    /// The ball shakes once per passed check and catches when all four pass.
+    pub shake_threshold: u32,
 }
 
 /// Tracks a capture attempt with a Poké Ball
@@ -263,13 +551,38 @@ pub struct CaptureAttempt {
 }
 
 /// Types of Poké Balls
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, schemars::JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum BallType {
     PokeBall,
     GreatBall,
     UltraBall,
-    // Other ball types can be added as needed
+    /// Catches without fail
+    MasterBall,
+}
+
+impl BallType {
+    /// The ball an inventory item throws, with its display name
+    pub fn from_item_id(item_id: &str) -> Option<(BallType, &'static str)> {
+        match item_id {
+            "poke_ball" => Some((BallType::PokeBall, "Poké Ball")),
+            "great_ball" => Some((BallType::GreatBall, "Great Ball")),
+            "ultra_ball" => Some((BallType::UltraBall, "Ultra Ball")),
+            "master_ball" => Some((BallType::MasterBall, "Master Ball")),
+            _ => None,
+        }
+    }
+
+    /// Multiplier the ball puts on the species catch rate
+    pub fn catch_multiplier(&self) -> f64 {
+        match self {
+            BallType::PokeBall => 1.0,
+            BallType::GreatBall => 1.5,
+            BallType::UltraBall => 2.0,
+            // Reaches the guaranteed catch rate at full HP even for a catch rate of 1
+            BallType::MasterBall => 1000.0,
+        }
+    }
 }
 
 /// Action that a wild Pokémon can take
@@ -284,7 +597,7 @@ pub enum WildPokemonAction {
 }
 
 /// Reason for requesting a switch
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, schemars::JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum SwitchReason {
     Fainted,
@@ -292,7 +605,7 @@ pub enum SwitchReason {
 }
 
 /// Reason the battle ended
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, schemars::JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum BattleEndReason {
     WildPokemonDefeated,
@@ -301,10 +614,12 @@ pub enum BattleEndReason {
     PlayerRanAway,
     AllPlayerPokemonFainted,
     PlayerDisconnected,
+    OpponentDisconnected,
+    InactivityTimeout,
 }
 
 /// Outcome of a wild battle
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, schemars::JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum WildBattleOutcome {
     Victory,    // Player defeated the wild Pokémon
@@ -315,16 +630,95 @@ pub enum WildBattleOutcome {
     PlayerDisconnected, // Player disconnected from battle
 }
 
+/// Where a move's animation plays, relative to the Pokémon using it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AnimationTarget {
+    User,
+    Opponent,
+    AllOpponents,
+    UserSide,
+    OpponentSide,
+    Field,
+}
+
+impl From<TargetType> for AnimationTarget {
+    fn from(target: TargetType) -> Self {
+        match target {
+            TargetType::User | TargetType::Ally | TargetType::UserOrAlly | TargetType::UserAndAllies => AnimationTarget::User,
+            TargetType::NormalOpponent | TargetType::AnyAdjacent | TargetType::RandomOpponent | TargetType::Adjacent => AnimationTarget::Opponent,
+            TargetType::AllAdjacentOpponents | TargetType::AllOpponents | TargetType::AllOtherPokemon => AnimationTarget::AllOpponents,
+            TargetType::UserSide => AnimationTarget::UserSide,
+            TargetType::OpponentSide => AnimationTarget::OpponentSide,
+            TargetType::WholeField | TargetType::AllPokemon => AnimationTarget::Field,
+        }
+    }
+}
+
+/// What the client needs to pick an animation for a move without its own move database
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, schemars::JsonSchema)]
+pub struct AnimationHint {
+    pub category: MoveCategory,
+    pub move_type: PokemonType,
+    pub contact: bool,
+    pub target: AnimationTarget,
+}
+
+impl AnimationHint {
+    pub fn for_move(move_data: &MoveData) -> Self {
+        Self {
+            category: move_data.damage_class.into(),
+            move_type: move_data.move_type,
+            contact: move_data.makes_contact(),
+            target: move_data.target.into(),
+        }
+    }
+}
+
 /// Event that occurs during battle for client-side animation/logging
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 #[serde(tag = "event_type", content = "details", rename_all = "snake_case")]
 pub enum BattleEvent {
-    MoveUsed { source: BattleEntityRef, move_id: u32, move_name: String, target: BattleEntityRef },
-    DamageDealt { target: BattleEntityRef, damage: u32, new_hp: u32, max_hp: u32, effectiveness: f32, is_critical: bool },
+    /// `animation` is missing only for moves without move data
+    MoveUsed {
+        source: BattleEntityRef,
+        move_id: u32,
+        move_name: String,
+        target: BattleEntityRef,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        animation: Option<AnimationHint>,
+    },
+    /// Sent before the `MoveUsed` of a move used as a super move
+    SuperMoveUsed { source: BattleEntityRef, move_id: u32, power: u32 },
+    /// `animation` is set for damage dealt by a move, not for recoil, status or hazard damage
+    DamageDealt {
+        target: BattleEntityRef,
+        damage: u32,
+        new_hp: u32,
+        max_hp: u32,
+        effectiveness: f32,
+        is_critical: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        animation: Option<AnimationHint>,
+    },
     Heal { target: BattleEntityRef, amount: u32, new_hp: u32, max_hp: u32 },
     StatusApplied { target: BattleEntityRef, status: StatusCondition },
     StatusRemoved { target: BattleEntityRef, status: StatusCondition },
     StatusDamage { target: BattleEntityRef, status: StatusCondition, damage: u32, new_hp: u32, max_hp: u32 },
+    /// A Pokémon left the field; its stat stages and volatile statuses were cleared
+    SwitchOut { target: BattleEntityRef },
+    /// An entry hazard was laid on the side `side` (the active Pokémon there) belongs to
+    HazardSet { side: BattleEntityRef, hazard: FieldEffectType, layers: u8 },
+    /// An entry hazard was cleared from the side `side` belongs to
+    HazardRemoved { side: BattleEntityRef, hazard: FieldEffectType },
+    /// An entry hazard hurt a Pokémon as it switched in
+    HazardDamage { target: BattleEntityRef, hazard: FieldEffectType, damage: u32, new_hp: u32, max_hp: u32 },
+    /// A Pokémon's ability took effect; sent before the events it causes
+    AbilityActivated { target: BattleEntityRef, ability_id: String, ability_name: String },
+    /// Sleep, freeze or full paralysis stopped a Pokémon from using its move this turn
+    StatusPreventedMove { target: BattleEntityRef, status: StatusCondition },
+    /// A Pokémon above the level it obeys at ignored its trainer's order
+    Disobeyed { target: BattleEntityRef, behavior: DisobedienceKind },
     VolatileStatusApplied { target: BattleEntityRef, volatile_status: VolatileStatusType },
     VolatileStatusRemoved { target: BattleEntityRef, volatile_status: VolatileStatusType },
     StatChange { target: BattleEntityRef, stat: StatName, stages: i8, new_stage: i8, success: bool },
@@ -334,18 +728,24 @@ pub enum BattleEvent {
     FieldEffectEnded { effect_type: FieldEffectType, target_side: EffectTargetSide },
     WeatherStarted { weather_type: WeatherType },
     WeatherEnded,
+    /// A Pokémon changed form mid-battle; its stats changed too, but not its max HP
+    FormChanged { target: BattleEntityRef, form: Option<String>, types: Vec<PokemonType> },
     MoveFailed { source: BattleEntityRef, reason: String },
     ItemUsed { item_id: String, item_name: String, target: Option<BattleEntityRef> },
     CaptureAttempt { ball_type: BallType, shake_count: u8, success: bool },
+    /// Follows a failed capture attempt, unless the server hides capture odds
+    CapturePreview { odds: CaptureOdds },
     WildPokemonFled,
     PlayerRanAway { success: bool },
     GenericMessage { message: String },
     TurnStart { turn_number: u32 },
     ExpGained { source: BattleEntityRef, amount: u64 },
+    /// Only delivered to players who turned on damage breakdowns
+    DamageBreakdown { source: BattleEntityRef, target: BattleEntityRef, breakdown: DamageBreakdown },
 }
 
 /// Reference to either player's Pokémon or wild Pokémon
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, schemars::JsonSchema)]
 #[serde(tag = "entity_type", rename_all = "snake_case")]
 pub enum BattleEntityRef {
     Player { team_index: usize },
@@ -354,8 +754,22 @@ pub enum BattleEntityRef {
     Player2 { team_index: usize },
 }
 
+/// What a disobedient Pokémon did instead of following its trainer's order
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DisobedienceKind {
+    /// Did nothing this turn
+    Loafed,
+    /// Used a different move than the one chosen
+    UsedOtherMove,
+    /// Fell asleep
+    Napped,
+    /// Hurt itself
+    HurtItself,
+}
+
 /// Types of field effects
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Copy)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Copy, schemars::JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum FieldEffectType {
     Reflect,
@@ -369,7 +783,7 @@ pub enum FieldEffectType {
 }
 
 /// Target side for field effects
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Copy)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Copy, schemars::JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum EffectTargetSide {
     Player,
@@ -380,7 +794,7 @@ pub enum EffectTargetSide {
 // View structs for client communication
 
 /// Public view of a Pokémon safe to show to others
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct BattlePokemonPublicView {
     pub template_id: u32,
     pub name: String,
@@ -388,6 +802,7 @@ pub struct BattlePokemonPublicView {
     pub current_hp_percent: f32,
     pub max_hp: u32, // Needed for HP bar rendering
     pub types: Vec<PokemonType>,
+    pub form: Option<String>,
     pub status: Option<StatusCondition>,
     pub stat_modifiers: BattleStatModifiers,
     pub is_fainted: bool,
@@ -395,7 +810,7 @@ pub struct BattlePokemonPublicView {
 }
 
 /// Private view with full details for the player's own Pokémon
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct BattlePokemonPrivateView {
     pub template_id: u32,
     pub name: String,
@@ -404,6 +819,7 @@ pub struct BattlePokemonPrivateView {
     pub current_hp_percent: f32,
     pub max_hp: u32,
     pub types: Vec<PokemonType>,
+    pub form: Option<String>,
     pub ability: String,
     pub status: Option<StatusCondition>,
     pub volatile_statuses: Vec<VolatileStatusType>,
@@ -414,7 +830,7 @@ pub struct BattlePokemonPrivateView {
 }
 
 /// View of a move with details needed for UI
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct BattleMoveView {
     pub move_id: u32,
     pub name: String,
@@ -425,14 +841,68 @@ pub struct BattleMoveView {
     pub power: Option<u32>,
     pub accuracy: Option<u8>,
     pub description: String, // For tooltips
+    /// Only filled in for players who enabled effectiveness hints
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effectiveness: Option<EffectivenessHint>,
+}
+
+/// How effective a damaging move is against the current opponent
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct EffectivenessHint {
+    pub multiplier: f32,
+    pub rating: EffectivenessRating,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EffectivenessRating {
+    NoEffect,
+    NotVeryEffective,
+    Neutral,
+    SuperEffective,
+}
+
+impl EffectivenessHint {
+    pub fn from_multiplier(multiplier: f32) -> Self {
+        let rating = if multiplier == 0.0 {
+            EffectivenessRating::NoEffect
+        } else if multiplier < 1.0 {
+            EffectivenessRating::NotVeryEffective
+        } else if multiplier > 1.0 {
+            EffectivenessRating::SuperEffective
+        } else {
+            EffectivenessRating::Neutral
+        };
+        Self { multiplier, rating }
+    }
+}
+
+/// Full details of a freshly captured Pokémon for the capture confirmation screen
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct CapturedPokemonView {
+    pub pokemon_id: String, // Collection ID, used by the nickname prompt
+    pub template_id: u32,
+    pub name: String,
+    pub level: u32,
+    pub current_hp: u32,
+    pub max_hp: u32,
+    pub types: Vec<PokemonType>,
+    pub ability: String,
+    pub nature: Nature,
+    pub ivs: StatSet<u8>,
+    pub calculated_stats: CalculatedStats,
+    pub moves: Vec<BattleMoveView>,
+    pub active_index: Option<usize>, // Party slot, None if the party was full
+    pub destination: Option<StorageLocation>, // Party slot or box the capture was stored in; a box means the client can offer a party swap
 }
 
 /// Minimal info for team sidebar UI
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
 pub struct BattlePokemonTeamOverview {
     pub template_id: u32,
     pub name: String,
     pub level: u32,
+    pub form: Option<String>,
     pub current_hp_percent: f32,
     pub current_hp: u32,
     pub max_hp: u32,
@@ -441,27 +911,83 @@ pub struct BattlePokemonTeamOverview {
     pub team_index: usize,
 }
 
+/// A move used in battle by a Pokémon of the given species
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MoveUse {
+    pub template_id: u32,
+    pub move_id: u32,
+}
+
+/// Moves used according to a battle log; `species_of` resolves the Pokémon that used each one.
+/// Only covers what is still in the (capped) log.
+fn moves_used_in_log(battle_log: &[BattleEvent], species_of: impl Fn(&BattleEntityRef) -> Option<u32>) -> Vec<MoveUse> {
+    battle_log.iter()
+        .filter_map(|event| match event {
+            BattleEvent::MoveUsed { source, move_id, .. } => species_of(source).map(|template_id| MoveUse { template_id, move_id: *move_id }),
+            _ => None,
+        })
+        .collect()
+}
+
+impl WildBattleState {
+    /// Record a turn's events in the battle log
+    pub fn record_events(&mut self, events: &[BattleEvent]) {
+        append_to_battle_log(&mut self.battle_log, events);
+    }
+
+    /// Every move used so far in this battle
+    pub fn moves_used(&self) -> Vec<MoveUse> {
+        moves_used_in_log(&self.battle_log, |source| match source {
+            BattleEntityRef::Player { team_index } => self.player.team.get(*team_index).map(|p| p.template_id),
+            BattleEntityRef::Wild => Some(self.wild_pokemon.template_id),
+            _ => None,
+        })
+    }
+}
+
 // Extension methods for PvPBattleState
 impl PvPBattleState {
+    /// Record a turn's events in the battle log
+    pub fn record_events(&mut self, events: &[BattleEvent]) {
+        append_to_battle_log(&mut self.battle_log, events);
+    }
+
+    /// Every move used so far in this battle
+    pub fn moves_used(&self) -> Vec<MoveUse> {
+        moves_used_in_log(&self.battle_log, |source| match source {
+            BattleEntityRef::Player1 { team_index } => self.player1.team.get(*team_index).map(|p| p.template_id),
+            BattleEntityRef::Player2 { team_index } => self.player2.team.get(*team_index).map(|p| p.template_id),
+            _ => None,
+        })
+    }
+
     /// Create a new PvP battle state
     pub fn new(
         battle_id: Uuid,
         player1: BattlePlayer,
         player2: BattlePlayer,
         move_repository: Option<std::sync::Arc<crate::monsters::move_manager::MoveRepository>>,
+        ability_repository: Option<std::sync::Arc<crate::monsters::ability_manager::AbilityRepository>>,
     ) -> Self {
         PvPBattleState {
             battle_id,
             player1,
             player2,
             turn_number: 1,
-            battle_phase: BattlePvPPhase::WaitingForBothPlayersActions,
+            battle_phase: PhaseMachine::new(battle_id, BattlePvPPhase::WaitingForBothPlayersActions),
             player1_action: None,
             player2_action: None,
             turn_order: None,
             field_state: FieldState::default(),
             battle_log: Vec::new(),
+            started_at: chrono::Utc::now().timestamp() as u64,
             move_repository,
+            ability_repository,
+            turn_snapshots: Vec::new(),
+            rejected_actions: HashMap::new(),
+            rng_seed: rand::random(),
+            practice: None,
+            gym_id: None,
         }
     }
 
@@ -505,11 +1031,119 @@ impl PvPBattleState {
 
     /// Check if a battle is ready to be processed
     pub fn ready_for_processing(&self) -> bool {
-        match self.battle_phase {
+        match self.battle_phase.get() {
             BattlePvPPhase::WaitingForBothPlayersActions => self.both_actions_submitted(),
             BattlePvPPhase::WaitingForPlayer1Action => self.player1_action.is_some(),
             BattlePvPPhase::WaitingForPlayer2Action => self.player2_action.is_some(),
+            BattlePvPPhase::WaitingForPlayer1Switch => self.player1_action.is_some(),
+            BattlePvPPhase::WaitingForPlayer2Switch => self.player2_action.is_some(),
             _ => false,
         }
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WILD_PHASES: [BattlePhase; 5] = [
+        BattlePhase::WaitingForPlayerAction,
+        BattlePhase::ProcessingTurn,
+        BattlePhase::WaitingForSwitch,
+        BattlePhase::CaptureMechanics,
+        BattlePhase::Finished,
+    ];
+
+    const PVP_PHASES: [BattlePvPPhase; 7] = [
+        BattlePvPPhase::WaitingForBothPlayersActions,
+        BattlePvPPhase::WaitingForPlayer1Action,
+        BattlePvPPhase::WaitingForPlayer2Action,
+        BattlePvPPhase::ProcessingTurn,
+        BattlePvPPhase::WaitingForPlayer1Switch,
+        BattlePvPPhase::WaitingForPlayer2Switch,
+        BattlePvPPhase::Finished,
+    ];
+
+    fn allowed_wild_transitions() -> Vec<(BattlePhase, BattlePhase)> {
+        use BattlePhase::*;
+        vec![
+            (WaitingForPlayerAction, ProcessingTurn),
+            (WaitingForPlayerAction, Finished),
+            (ProcessingTurn, WaitingForPlayerAction),
+            (ProcessingTurn, WaitingForSwitch),
+            (ProcessingTurn, CaptureMechanics),
+            (ProcessingTurn, Finished),
+            (WaitingForSwitch, ProcessingTurn),
+            (WaitingForSwitch, Finished),
+            (CaptureMechanics, ProcessingTurn),
+            (CaptureMechanics, Finished),
+        ]
+    }
+
+    fn allowed_pvp_transitions() -> Vec<(BattlePvPPhase, BattlePvPPhase)> {
+        use BattlePvPPhase::*;
+        vec![
+            (WaitingForBothPlayersActions, WaitingForPlayer1Action),
+            (WaitingForBothPlayersActions, WaitingForPlayer2Action),
+            (WaitingForBothPlayersActions, ProcessingTurn),
+            (WaitingForBothPlayersActions, Finished),
+            (WaitingForPlayer1Action, ProcessingTurn),
+            (WaitingForPlayer1Action, Finished),
+            (WaitingForPlayer2Action, ProcessingTurn),
+            (WaitingForPlayer2Action, Finished),
+            (ProcessingTurn, WaitingForBothPlayersActions),
+            (ProcessingTurn, WaitingForPlayer1Switch),
+            (ProcessingTurn, WaitingForPlayer2Switch),
+            (ProcessingTurn, Finished),
+            (WaitingForPlayer1Switch, ProcessingTurn),
+            (WaitingForPlayer1Switch, Finished),
+            (WaitingForPlayer2Switch, ProcessingTurn),
+            (WaitingForPlayer2Switch, Finished),
+        ]
+    }
+
+    /// Try every pair of phases and check exactly the allowed ones go through. Rejected
+    /// transitions leave the phase where it was; staying put is always allowed.
+    fn check_transition_table<P: Phase>(phases: &[P], allowed: &[(P, P)]) {
+        for &from in phases {
+            for &to in phases {
+                let mut machine = PhaseMachine::new(Uuid::nil(), from);
+                let expected = from == to || allowed.contains(&(from, to));
+                assert_eq!(machine.transition(to), expected, "{:?} -> {:?}", from, to);
+                assert_eq!(machine.get(), if expected { to } else { from }, "{:?} -> {:?}", from, to);
+            }
+        }
+    }
+
+    #[test]
+    fn wild_battle_phases_follow_the_transition_table() {
+        check_transition_table(&WILD_PHASES, &allowed_wild_transitions());
+    }
+
+    #[test]
+    fn pvp_battle_phases_follow_the_transition_table() {
+        check_transition_table(&PVP_PHASES, &allowed_pvp_transitions());
+    }
+
+    #[test]
+    fn flat_level_must_be_a_real_level() {
+        assert!(BattleRules::flat().validate().is_ok());
+        for flat_level in [1, 100] {
+            assert!(BattleRules { flat_level: Some(flat_level), ..BattleRules::default() }.validate().is_ok());
+        }
+        for flat_level in [0, 101] {
+            assert!(BattleRules { flat_level: Some(flat_level), ..BattleRules::default() }.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn finished_battles_stay_finished() {
+        let mut wild = PhaseMachine::new(Uuid::nil(), BattlePhase::Finished);
+        assert!(!wild.transition(BattlePhase::WaitingForPlayerAction));
+        assert_eq!(wild.get(), BattlePhase::Finished);
+
+        let mut pvp = PhaseMachine::new(Uuid::nil(), BattlePvPPhase::Finished);
+        assert!(!pvp.transition(BattlePvPPhase::ProcessingTurn));
+        assert_eq!(pvp.get(), BattlePvPPhase::Finished);
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::BufReader;
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::combat::state::{BattlePokemon, BattleRules};
+use crate::monsters::monster_manager::MonsterTemplateRepository;
+
+/// Species and moves not allowed in battles played with `enforce_banlist`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Banlist {
+    #[serde(default)]
+    pub species: HashSet<u32>,
+    #[serde(default)]
+    pub moves: HashSet<u32>,
+}
+
+impl Banlist {
+    /// Load the banlist at `path`; nothing is banned when it cannot be read
+    pub fn load(path: &str) -> Self {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => {
+                warn!("Failed to open banlist {}: {}", path, e);
+                return Banlist::default();
+            }
+        };
+        match serde_json::from_reader::<_, Banlist>(BufReader::new(file)) {
+            Ok(banlist) => {
+                info!("Loaded banlist from {}: {} species and {} moves", path, banlist.species.len(), banlist.moves.len());
+                banlist
+            }
+            Err(e) => {
+                warn!("Failed to parse banlist {}: {}", path, e);
+                Banlist::default()
+            }
+        }
+    }
+}
+
+/// One way a team breaks the battle rules
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct TeamViolation {
+    /// Position of the offending Pokémon in the team
+    pub team_index: usize,
+    pub reason: String,
+}
+
+/// Check a team against the rules of a battle, returning every violation found
+pub fn validate_team(
+    team: &[BattlePokemon],
+    rules: &BattleRules,
+    banlist: &Banlist,
+    template_repository: &MonsterTemplateRepository,
+) -> Vec<TeamViolation> {
+    let species_name = |template_id: u32| {
+        template_repository.templates.get(&template_id)
+            .map_or_else(|| format!("#{}", template_id), |template| template.name.clone())
+    };
+    let move_name = |move_id: u32| {
+        template_repository.move_repository.as_ref()
+            .and_then(|repo| repo.get_move(move_id))
+            .map_or_else(|| format!("#{}", move_id), |move_data| move_data.name.clone())
+    };
+
+    let mut violations = Vec::new();
+    // Species → position of its first team member, for the species clause
+    let mut first_of_species: HashMap<u32, usize> = HashMap::new();
+    for (team_index, pokemon) in team.iter().enumerate() {
+        if rules.species_clause {
+            if let Some(first_index) = first_of_species.get(&pokemon.template_id) {
+                violations.push(TeamViolation {
+                    team_index,
+                    reason: format!(
+                        "{} is the same species ({}) as team member {}",
+                        pokemon.name, species_name(pokemon.template_id), first_index + 1
+                    ),
+                });
+            } else {
+                first_of_species.insert(pokemon.template_id, team_index);
+            }
+        }
+
+        if let Some(level_cap) = rules.level_cap {
+            if pokemon.level > level_cap {
+                violations.push(TeamViolation {
+                    team_index,
+                    reason: format!("{} is level {}, above the level cap of {}", pokemon.name, pokemon.level, level_cap),
+                });
+            }
+        }
+
+        if rules.enforce_banlist {
+            if banlist.species.contains(&pokemon.template_id) {
+                violations.push(TeamViolation {
+                    team_index,
+                    reason: format!("{} is a banned species ({})", pokemon.name, species_name(pokemon.template_id)),
+                });
+            }
+            for battle_move in pokemon.moves.iter().filter(|m| banlist.moves.contains(&m.move_id)) {
+                violations.push(TeamViolation {
+                    team_index,
+                    reason: format!("{} knows the banned move {}", pokemon.name, move_name(battle_move.move_id)),
+                });
+            }
+        }
+    }
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::combat::utils::convert_player_pokemon_to_battle_pokemon;
+
+    fn team(template_repository: &Arc<MonsterTemplateRepository>, members: &[(u32, u32)]) -> Vec<BattlePokemon> {
+        members.iter().enumerate()
+            .map(|(index, &(template_id, level))| {
+                let pokemon = template_repository.pokemon_from_template(template_id, Some(level));
+                convert_player_pokemon_to_battle_pokemon(&pokemon, index, template_repository)
+            })
+            .collect()
+    }
+
+    fn species(template_repository: &MonsterTemplateRepository) -> (u32, u32) {
+        let mut ids: Vec<u32> = template_repository.templates.keys().copied().collect();
+        ids.sort();
+        (ids[0], ids[1])
+    }
+
+    #[test]
+    fn species_clause_flags_every_repeat_after_the_first() {
+        let template_repository = Arc::new(MonsterTemplateRepository::for_tests());
+        let (first, second) = species(&template_repository);
+        let team = team(&template_repository, &[(first, 10), (second, 10), (first, 10), (first, 10)]);
+
+        let rules = BattleRules { species_clause: true, ..BattleRules::default() };
+        let violations = validate_team(&team, &rules, &Banlist::default(), &template_repository);
+        assert_eq!(violations.iter().map(|violation| violation.team_index).collect::<Vec<_>>(), vec![2, 3]);
+        assert!(violations[0].reason.contains("team member 1"));
+
+        // Without the clause repeats are fine
+        assert!(validate_team(&team, &BattleRules::default(), &Banlist::default(), &template_repository).is_empty());
+    }
+
+    #[test]
+    fn banned_species_only_count_when_the_banlist_is_enforced() {
+        let template_repository = Arc::new(MonsterTemplateRepository::for_tests());
+        let (allowed, banned) = species(&template_repository);
+        let team = team(&template_repository, &[(allowed, 10), (banned, 10)]);
+        let banlist = Banlist { species: HashSet::from([banned]), moves: HashSet::new() };
+
+        let rules = BattleRules { enforce_banlist: true, ..BattleRules::default() };
+        let violations = validate_team(&team, &rules, &banlist, &template_repository);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].team_index, 1);
+        assert!(violations[0].reason.contains("banned species"));
+
+        assert!(validate_team(&team, &BattleRules::default(), &banlist, &template_repository).is_empty());
+    }
+
+    #[test]
+    fn members_above_the_level_cap_are_rejected() {
+        let template_repository = Arc::new(MonsterTemplateRepository::for_tests());
+        let (first, second) = species(&template_repository);
+        let team = team(&template_repository, &[(first, 50), (second, 51)]);
+
+        let rules = BattleRules { level_cap: Some(50), ..BattleRules::default() };
+        let violations = validate_team(&team, &rules, &Banlist::default(), &template_repository);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].team_index, 1);
+        assert!(violations[0].reason.contains("above the level cap of 50"));
+    }
+}
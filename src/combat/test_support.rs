@@ -0,0 +1,111 @@
+//! Battle states for tests that play out moves, switches and turns
+use crate::combat::state::{
+    BattleMove, BattlePhase, BattlePlayer, BattlePokemon, BattlePvPPhase, FieldState, PhaseMachine, PlayerSideState,
+    PvPBattleState, WildBattleState,
+};
+use crate::monsters::ability_manager::AbilityRepository;
+use crate::monsters::move_manager::MoveRepository;
+use crate::monsters::PokemonType;
+use crate::stats::nature::Nature;
+use crate::stats::{BattleStatModifiers, StatSet};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Move and ability data from the bundled resources
+pub fn repositories() -> (Arc<MoveRepository>, Arc<AbilityRepository>) {
+    (
+        MoveRepository::new("resources/moves.json", "resources/types.json", &[]),
+        AbilityRepository::new("resources/abilities.json"),
+    )
+}
+
+/// A level 50 Pokémon with every stat at 100, `hp` hit points and full PP in `moves`
+pub fn test_pokemon(name: &str, types: &[PokemonType], hp: u32, moves: &[u32]) -> BattlePokemon {
+    BattlePokemon {
+        template_id: 1,
+        name: name.to_string(),
+        level: 50,
+        pokemon_types: types.to_vec(),
+        ability: String::new(),
+        moves: moves.iter().map(|&move_id| BattleMove { move_id, current_pp: 10, max_pp: 10 }).collect(),
+        instance_id: name.to_string(),
+        base_exp: 64,
+        exp: 0,
+        max_exp: 100,
+        calculated_stats: StatSet { hp, attack: 100, defense: 100, special_attack: 100, special_defense: 100, speed: 100 },
+        ivs: StatSet::default(),
+        evs: StatSet::default(),
+        nature: Nature::Hardy,
+        current_hp: hp,
+        max_hp: hp,
+        status: None,
+        status_turns: 0,
+        volatile_statuses: HashMap::new(),
+        stat_modifiers: BattleStatModifiers::default(),
+        is_fainted: false,
+        position: 0,
+        is_wild: false,
+        form: None,
+        weather_forms: Vec::new(),
+        obedience_level: None,
+    }
+}
+
+pub fn battle_player(player_id: &str, pokemon: BattlePokemon) -> BattlePlayer {
+    BattlePlayer {
+        player_id: player_id.to_string(),
+        name: player_id.to_string(),
+        team: vec![pokemon],
+        active_pokemon_index: 0,
+        side_effects: PlayerSideState::default(),
+        last_action_submitted: None,
+        must_switch: false,
+        sent_team_overview: Vec::new(),
+        team_revision: 0,
+        crystal_type: None,
+        super_move_used: false,
+        rental_team: None,
+    }
+}
+
+pub fn wild_battle(player: BattlePokemon, wild: BattlePokemon, repositories: &(Arc<MoveRepository>, Arc<AbilityRepository>)) -> WildBattleState {
+    let battle_id = Uuid::nil();
+    WildBattleState {
+        battle_id,
+        player: battle_player("player", player),
+        wild_pokemon: wild,
+        turn_number: 1,
+        battle_phase: PhaseMachine::new(battle_id, BattlePhase::WaitingForPlayerAction),
+        player_action: None,
+        wild_action: None,
+        turn_order: None,
+        field_state: FieldState::default(),
+        battle_log: Vec::new(),
+        capture_attempts: Vec::new(),
+        encounter_modifier: None,
+        started_at: 0,
+        last_activity_at: std::time::Instant::now(),
+        timed_out: false,
+        auto_battle: false,
+        auto_battle_run: 0,
+        move_repository: Some(repositories.0.clone()),
+        ability_repository: Some(repositories.1.clone()),
+        scripted: false,
+        wild_catch_rate: 45,
+        wild_shiny: false,
+        wild_alpha: false,
+    }
+}
+
+pub fn pvp_battle(player1: BattlePokemon, player2: BattlePokemon, repositories: &(Arc<MoveRepository>, Arc<AbilityRepository>)) -> PvPBattleState {
+    let mut battle_state = PvPBattleState::new(
+        Uuid::nil(),
+        battle_player("player1", player1),
+        battle_player("player2", player2),
+        Some(repositories.0.clone()),
+        Some(repositories.1.clone()),
+    );
+    battle_state.battle_phase = PhaseMachine::new(Uuid::nil(), BattlePvPPhase::ProcessingTurn);
+    battle_state
+}
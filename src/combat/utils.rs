@@ -1,12 +1,126 @@
-use crate::combat::state::{BattlePokemon, BattleMove};
-use crate::game_loop::pokemon_collection::Pokemon;
+use crate::combat::logic::status::initial_status_turns;
+use crate::combat::state::{BattlePokemon, BattleMove, WeatherForm};
+use crate::game_loop::pokemon_collection::{Pokemon, PokemonUpdate};
 use crate::monsters::Monster;
-use crate::monsters::monster::PokemonType;
+use crate::monsters::monster::{MonsterMove, MonsterTemplate, PokemonType, ALPHA_REWARD_MULTIPLIER};
 use crate::monsters::monster_manager::MonsterTemplateRepository;
 use crate::stats::{BaseStats, CalculatedStats, BattleStatModifiers};
 use std::collections::HashMap;
 use std::sync::Arc;
 
+/// Most moves a Pokemon can know at once
+const MAX_KNOWN_MOVES: usize = 4;
+
+/// Moveset a captured wild Pokemon keeps. Its battle moves are kept when its species can
+/// know them at its level, those used during the battle first; any illegal moves are
+/// replaced with moves regenerated from the template.
+pub fn capture_moveset(wild_pokemon: &BattlePokemon, template_repository: &Arc<MonsterTemplateRepository>) -> Vec<MonsterMove> {
+    let to_monster_move = |battle_move: &BattleMove| MonsterMove {
+        id: battle_move.move_id,
+        pp_remaining: battle_move.current_pp,
+        pp_ups: 0,
+    };
+    let template = match template_repository.templates.get(&wild_pokemon.template_id) {
+        Some(template) => template,
+        None => return wild_pokemon.moves.iter().map(to_monster_move).collect(),
+    };
+    let is_legal = |move_id: u32| template.moves.iter()
+        .any(|(id, level_learned)| *id == move_id && *level_learned <= wild_pokemon.level);
+
+    if wild_pokemon.moves.len() <= MAX_KNOWN_MOVES && wild_pokemon.moves.iter().all(|battle_move| is_legal(battle_move.move_id)) {
+        return wild_pokemon.moves.iter().map(to_monster_move).collect();
+    }
+
+    // Moves that spent PP were used in the battle; the sort is stable so ties keep their slot order
+    let mut legal_battle_moves: Vec<&BattleMove> = wild_pokemon.moves.iter()
+        .filter(|battle_move| is_legal(battle_move.move_id))
+        .collect();
+    legal_battle_moves.sort_by_key(|battle_move| battle_move.current_pp >= battle_move.max_pp);
+
+    let mut moves: Vec<MonsterMove> = Vec::new();
+    let candidates = legal_battle_moves.into_iter()
+        .map(to_monster_move)
+        .chain(template_repository.pokemon_moves_from_template(template, wild_pokemon.level));
+    for candidate in candidates {
+        if moves.len() >= MAX_KNOWN_MOVES {
+            break;
+        }
+        if !moves.iter().any(|known: &MonsterMove| known.id == candidate.id) {
+            moves.push(candidate);
+        }
+    }
+
+    tracing::info!("Regenerated moveset of captured {} (level {}) from its template", wild_pokemon.name, wild_pokemon.level);
+    moves
+}
+
+/// Build the collection update for a team member after a battle, or None if nothing changed.
+/// Progress made in battle (level, exp, EVs, ability, moves) carries over, while HP, PP
+/// and status are restored since battles leave no lasting damage.
+pub fn post_battle_update(battle_pokemon: &BattlePokemon, stored: &Pokemon) -> Option<PokemonUpdate> {
+    let moves: Vec<MonsterMove> = battle_pokemon.moves.iter().map(|battle_move| MonsterMove {
+        id: battle_move.move_id,
+        pp_remaining: battle_move.max_pp,
+        // PP Ups live only on the stored move
+        pp_ups: stored.moves.iter()
+            .find(|stored_move| stored_move.id == battle_move.move_id)
+            .map(|stored_move| stored_move.pp_ups)
+            .unwrap_or(0),
+    }).collect();
+
+    let changed = battle_pokemon.level != stored.level
+        || battle_pokemon.exp != stored.exp
+        || battle_pokemon.max_exp != stored.max_exp
+        || battle_pokemon.max_hp != stored.current_hp
+        || battle_pokemon.evs != stored.evs
+        || battle_pokemon.ability != stored.ability
+        || moves != stored.moves
+        || stored.status_condition.is_some();
+    if !changed {
+        return None;
+    }
+
+    Some(PokemonUpdate {
+        name: None,
+        level: Some(battle_pokemon.level),
+        exp: Some(battle_pokemon.exp),
+        max_exp: Some(battle_pokemon.max_exp),
+        current_hp: Some(battle_pokemon.max_hp),
+        moves: Some(moves),
+        status_condition: Some(None),
+        evs: Some(battle_pokemon.evs.clone()),
+        ability: Some(battle_pokemon.ability.clone()),
+    })
+}
+
+/// Forms a battle Pokemon switches between with the battle weather, starting with the one it
+/// is in. Empty when its species has no weather forms.
+fn weather_forms(
+    template: &MonsterTemplate,
+    form: Option<&str>,
+    types: &[PokemonType],
+    stats: &CalculatedStats,
+    stats_for_form: impl Fn(&BaseStats) -> CalculatedStats,
+) -> Vec<WeatherForm> {
+    let mut forms: Vec<WeatherForm> = template.forms.iter()
+        .filter_map(|weather_form| weather_form.battle_weather.map(|weather| WeatherForm {
+            weather: Some(weather),
+            form: Some(weather_form.id.clone()),
+            types: template.types_for_form(Some(&weather_form.id)).to_vec(),
+            stats: stats_for_form(template.base_stats_for_form(Some(&weather_form.id))),
+        }))
+        .collect();
+    if !forms.is_empty() {
+        forms.insert(0, WeatherForm {
+            weather: None,
+            form: form.map(str::to_string),
+            types: types.to_vec(),
+            stats: stats.clone(),
+        });
+    }
+    forms
+}
+
 /// Convert a player-owned Pokemon to a battle Pokemon
 pub fn convert_player_pokemon_to_battle_pokemon(
     pokemon: &Pokemon, 
@@ -18,13 +132,15 @@ pub fn convert_player_pokemon_to_battle_pokemon(
         .expect("Template not found for player pokemon"); // Better error handling might be needed
 
     // Calculate full stats
-    let calculated_stats = crate::stats::calculate_stats(
-        &template.base_stats,
+    let stats_for_form = |base_stats: &BaseStats| crate::stats::calculate_stats(
+        base_stats,
         pokemon.level,
         &pokemon.ivs,
         &pokemon.evs,
         &pokemon.nature,
     );
+    let calculated_stats = stats_for_form(template.base_stats_for_form(pokemon.form.as_deref()));
+    let weather_forms = weather_forms(template, pokemon.form.as_deref(), &pokemon.types, &calculated_stats, stats_for_form);
 
     BattlePokemon {
         template_id: pokemon.template_id,
@@ -35,8 +151,8 @@ pub fn convert_player_pokemon_to_battle_pokemon(
         ability: pokemon.ability.clone(),
         moves: pokemon.moves.iter().map(|m| {
             let max_pp =  match template_repository.move_repository {
-                Some(ref move_repo) => move_repo.get_move(m.id).map(|m| m.pp).unwrap_or(20),
-                None => 20,
+                Some(ref move_repo) => move_repo.get_move(m.id).map(|move_data| m.max_pp(move_data.pp)).unwrap_or(20),
+                None => m.max_pp(20),
             };
             BattleMove {
                 move_id: m.id,
@@ -50,12 +166,15 @@ pub fn convert_player_pokemon_to_battle_pokemon(
         current_hp: pokemon.current_hp,
         max_hp: calculated_stats.hp, // Max HP comes from calculated stats
         status: pokemon.status_condition,
-        status_turns: 0,
+        status_turns: initial_status_turns(pokemon.status_condition),
         volatile_statuses: HashMap::new(),
         stat_modifiers: BattleStatModifiers::default(),
         is_fainted: pokemon.current_hp == 0,
         position,
         is_wild: false,
+        form: pokemon.form.clone(),
+        weather_forms,
+        obedience_level: None,
         instance_id: pokemon.id.clone(),
         base_exp: template.base_experience,
         exp: pokemon.exp,
@@ -63,10 +182,48 @@ pub fn convert_player_pokemon_to_battle_pokemon(
     }
 }
 
+/// Bring a battle Pokemon to `level` for flat rules battles, recalculating its stats from the
+/// template and keeping the same fraction of HP. The stored Pokemon is left untouched.
+pub fn normalize_battle_pokemon_level(
+    battle_pokemon: &mut BattlePokemon,
+    level: u32,
+    template_repository: &Arc<MonsterTemplateRepository>,
+) {
+    let Some(template) = template_repository.templates.get(&battle_pokemon.template_id) else {
+        return;
+    };
+    let stats_for_form = |form: Option<&str>| crate::stats::calculate_stats(
+        template.base_stats_for_form(form),
+        level,
+        &battle_pokemon.ivs,
+        &battle_pokemon.evs,
+        &battle_pokemon.nature,
+    );
+    let calculated_stats = stats_for_form(battle_pokemon.form.as_deref());
+    let weather_form_stats: Vec<CalculatedStats> = battle_pokemon.weather_forms.iter()
+        .map(|weather_form| stats_for_form(weather_form.form.as_deref()))
+        .collect();
+    for (weather_form, stats) in battle_pokemon.weather_forms.iter_mut().zip(weather_form_stats) {
+        weather_form.stats = stats;
+    }
+
+    if battle_pokemon.max_hp > 0 && !battle_pokemon.is_fainted {
+        let scaled_hp = (battle_pokemon.current_hp as u64 * calculated_stats.hp as u64 / battle_pokemon.max_hp as u64) as u32;
+        battle_pokemon.current_hp = scaled_hp.clamp(1, calculated_stats.hp);
+    }
+    battle_pokemon.level = level;
+    battle_pokemon.max_hp = calculated_stats.hp;
+    battle_pokemon.calculated_stats = calculated_stats;
+}
+
 /// Convert a wild Monster to a battle Pokemon
 pub fn convert_wild_monster_to_battle_pokemon(monster: &Monster, template_repository: &Arc<MonsterTemplateRepository>) -> BattlePokemon {
   let template = template_repository.templates.get(&monster.template_id)
     .expect("Template not found for wild monster");
+    let weather_forms = weather_forms(template, monster.form.as_deref(), &monster.types, &monster.calculated_stats, |base_stats| {
+        let stats = crate::stats::calculate_stats(base_stats, monster.level, &monster.ivs, &monster.evs, &monster.nature);
+        if monster.is_alpha { Monster::apply_alpha_stats(stats) } else { stats }
+    });
     BattlePokemon {
         template_id: monster.template_id,
         name: monster.name.clone(),
@@ -76,8 +233,8 @@ pub fn convert_wild_monster_to_battle_pokemon(monster: &Monster, template_reposi
         ability: monster.ability.clone(),
         moves: monster.moves.iter().map(|m| {
             let max_pp =  match template_repository.move_repository {
-                Some(ref move_repo) => move_repo.get_move(m.id).map(|m| m.pp).unwrap_or(20),
-                None => 20,
+                Some(ref move_repo) => move_repo.get_move(m.id).map(|move_data| m.max_pp(move_data.pp)).unwrap_or(20),
+                None => m.max_pp(20),
             };
             BattleMove {
                 move_id: m.id,
@@ -91,14 +248,18 @@ pub fn convert_wild_monster_to_battle_pokemon(monster: &Monster, template_reposi
         current_hp: monster.current_hp,
         max_hp: monster.calculated_stats.hp,
         status: monster.status_condition,
-        status_turns: 0,
+        status_turns: initial_status_turns(monster.status_condition),
         volatile_statuses: HashMap::new(),
         stat_modifiers: BattleStatModifiers::default(),
         is_fainted: monster.current_hp == 0,
         position: 0, // Wild Pokemon is always at position 0
         is_wild: true,
+        form: monster.form.clone(),
+        weather_forms,
+        obedience_level: None,
         instance_id: monster.instance_id.clone(),
-        base_exp: template.base_experience,
+        // Alphas are worth more experience
+        base_exp: if monster.is_alpha { template.base_experience * ALPHA_REWARD_MULTIPLIER } else { template.base_experience },
         exp: 0,
         max_exp: 0,
     }
@@ -109,9 +270,9 @@ pub fn calculate_exp_gain(
     wild_pokemon: &crate::combat::state::BattlePokemon,
     template_repository: &Arc<MonsterTemplateRepository>
 ) -> u32 {
-    // Get wild Pokémon's template for base experience
+    // Base experience was copied from the template (and boosted for alphas) when the battle started
     let wild_template = template_repository.templates.get(&wild_pokemon.template_id);
-    let wild_base_exp = wild_template.map(|t| t.base_experience).unwrap_or(50);
+    let wild_base_exp = wild_pokemon.base_exp;
     let wild_level = wild_pokemon.level;
     
     // Calculate EXP: (Base EXP × Wild Pokémon Level) / 7
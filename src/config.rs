@@ -3,26 +3,127 @@ use std::env;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use tracing::info;
 
+use crate::game_loop::backups::BackupBucket;
+use crate::game_loop::occupancy::MonsterCollision;
+use crate::game_loop::telemetry::TelemetrySinkConfig;
+use crate::game_loop::webhooks::WebhookEndpoint;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
     pub server: ServerConfig,
     pub game: GameConfig,
     pub performance: PerformanceConfig,
     pub monsters: MonstersConfig,
+    pub ticks: TickConfig,
+    pub telemetry: TelemetryConfig,
+    pub webhooks: WebhookConfig,
+    pub backups: BackupConfig,
+}
+
+// Anonymized battle statistics for balancing moves and species
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TelemetryConfig {
+    // Telemetry is off when no sink is set
+    pub sink: Option<TelemetrySinkConfig>,
+    // Longest time an event waits before being written
+    pub flush_interval_ms: u64,
+}
+
+// Outbound notifications of key events for external services
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct WebhookConfig {
+    // Every event is POSTed to each endpoint; webhooks are off when there are none
+    pub endpoints: Vec<WebhookEndpoint>,
+    // Key the request bodies are signed with, so endpoints can tell the events came from us
+    pub secret: Option<String>,
+}
+
+// Keeps the secret out of logs
+impl std::fmt::Debug for WebhookConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookConfig")
+            .field("endpoints", &self.endpoints)
+            .field("secret", &self.secret.as_ref().map(|_| "<hidden>"))
+            .finish()
+    }
 }
 
+// Scheduled archives of player data
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupConfig {
+    // Scheduled backups are off when no directory is set
+    pub directory: Option<String>,
+    pub interval_ms: u64,
+    // Newest archives kept, 0 to keep all
+    pub retain: usize,
+    // Bucket every archive is also uploaded to
+    pub bucket: Option<BackupBucket>,
+}
+
+// Intervals of the background game loops
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TickConfig {
+    pub monster_movement_interval_ms: u64,
+    pub spawner_interval_ms: u64,
+    pub player_movement_interval_ms: u64,
+    // How often each lobby's overworld monsters are saved so they survive a restart
+    pub monster_save_interval_ms: u64,
+    // How often clients are sent the server time
+    pub time_sync_interval_ms: u64,
+}
+
+// Bounds every tick interval must fall within
+const MIN_TICK_INTERVAL_MS: u64 = 10;
+const MAX_TICK_INTERVAL_MS: u64 = 60_000;
+// Backups scan every player key, so they may not run more often than this
+const MIN_BACKUP_INTERVAL_MS: u64 = 60_000;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MonstersConfig {
     pub templates_path: String,
     pub moves_path: String,
     pub type_chart_path: String,
+    // Battle effects of abilities, keyed by ability ID
+    pub abilities_path: String,
+    // Overlay files applied over the templates and moves in order, e.g. seasonal events or regional forms
+    pub content_packs: Vec<String>,
+    // Species and moves banned from PvP battles that enforce the banlist
+    pub banlist_path: String,
+    // Teams lent to players without six Pokémon for PvP
+    pub rental_teams_path: String,
+    // Gym leaders on each map and the badges they award
+    pub gyms_path: String,
+    // Directory of `<locale>.json` translations of species and move names
+    pub locales_path: String,
+    // Seconds a spawn point stays empty after one of its monsters is captured or defeated
+    pub spawn_point_cooldown_sec: u64,
+    // Seconds before a captured or defeated species can spawn again in the same lobby
+    pub species_cooldown_sec: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct ServerConfig {
     pub host: IpAddr,
     pub port: u16,
     pub cors_origins: Vec<String>,
+    // Largest websocket message (and frame) accepted from a client; bigger ones close the connection
+    pub max_message_bytes: usize,
+    // Bearer token for the /admin API; the admin API is disabled when unset
+    #[serde(skip_serializing)]
+    pub admin_token: Option<String>,
+}
+
+// Written by hand so the admin token never ends up in logs
+impl std::fmt::Debug for ServerConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServerConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("cors_origins", &self.cors_origins)
+            .field("max_message_bytes", &self.max_message_bytes)
+            .field("admin_token", &self.admin_token.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -30,6 +131,16 @@ pub struct GameConfig {
     pub max_players: usize,
     pub update_rate_ms: u64,
     pub inactive_timeout_sec: u64,
+    // Players sending nothing but pings for this long are shown as AFK
+    pub afk_after_sec: u64,
+    // Players sending nothing but pings for this long are disconnected, 0 keeps them connected
+    pub idle_timeout_sec: u64,
+    // Collision between players and wild monsters for newly created lobbies
+    pub monster_collision: MonsterCollision,
+    // Aggressive monsters start encounters with players who end a move next to them
+    pub aggressive_encounters: bool,
+    // Leave capture odds out of battle events, e.g. while running a competitive catching event
+    pub hide_capture_odds: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -44,11 +155,18 @@ impl Default for Config {
                 host: IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
                 port: 8080,
                 cors_origins: vec!["*".to_string()],
+                max_message_bytes: 16 * 1024,
+                admin_token: None,
             },
             game: GameConfig {
                 max_players: 50,
                 update_rate_ms: 100,
                 inactive_timeout_sec: 315_360_000, // 10 years (60*60*24*365*10 seconds)
+                afk_after_sec: 300,
+                idle_timeout_sec: 1800,
+                monster_collision: MonsterCollision::default(),
+                aggressive_encounters: false,
+                hide_capture_odds: false,
             },
             performance: PerformanceConfig {
                 broadcast_channel_size: 100,
@@ -57,6 +175,32 @@ impl Default for Config {
                 templates_path: "resources/pokemon.json".to_string(),
                 moves_path: "resources/moves.json".to_string(),
                 type_chart_path: "resources/types.json".to_string(),
+                abilities_path: "resources/abilities.json".to_string(),
+                content_packs: Vec::new(),
+                banlist_path: "resources/banlist.json".to_string(),
+                rental_teams_path: "resources/rental_teams.json".to_string(),
+                gyms_path: "resources/gyms.json".to_string(),
+                locales_path: "resources/locales".to_string(),
+                spawn_point_cooldown_sec: 60,
+                species_cooldown_sec: 120,
+            },
+            ticks: TickConfig {
+                monster_movement_interval_ms: 2000,
+                spawner_interval_ms: 10000,
+                player_movement_interval_ms: 50,
+                monster_save_interval_ms: 30000,
+                time_sync_interval_ms: 5000,
+            },
+            telemetry: TelemetryConfig {
+                sink: None,
+                flush_interval_ms: 10000,
+            },
+            webhooks: WebhookConfig::default(),
+            backups: BackupConfig {
+                directory: None,
+                interval_ms: 6 * 60 * 60 * 1000,
+                retain: 28,
+                bucket: None,
             },
         }
     }
@@ -86,6 +230,18 @@ impl Config {
             config.server.cors_origins = cors.split(',').map(|s| s.trim().to_string()).collect();
         }
 
+        if let Ok(max_message_bytes) = env::var("MAX_MESSAGE_BYTES") {
+            if let Ok(max_message_bytes) = max_message_bytes.parse::<usize>() {
+                config.server.max_message_bytes = max_message_bytes;
+            }
+        }
+
+        if let Ok(token) = env::var("ADMIN_TOKEN") {
+            if !token.is_empty() {
+                config.server.admin_token = Some(token);
+            }
+        }
+
         // Game config
         if let Ok(max_players) = env::var("MAX_PLAYERS") {
             if let Ok(max_players) = max_players.parse::<usize>() {
@@ -105,6 +261,36 @@ impl Config {
             }
         }
 
+        if let Ok(afk_after) = env::var("AFK_AFTER_SEC") {
+            if let Ok(afk_after) = afk_after.parse::<u64>() {
+                config.game.afk_after_sec = afk_after;
+            }
+        }
+
+        if let Ok(timeout) = env::var("IDLE_TIMEOUT_SEC") {
+            if let Ok(timeout) = timeout.parse::<u64>() {
+                config.game.idle_timeout_sec = timeout;
+            }
+        }
+
+        if let Ok(collision) = env::var("MONSTER_COLLISION") {
+            if let Some(collision) = MonsterCollision::parse(&collision) {
+                config.game.monster_collision = collision;
+            }
+        }
+
+        if let Ok(enabled) = env::var("AGGRESSIVE_ENCOUNTERS") {
+            if let Ok(enabled) = enabled.parse::<bool>() {
+                config.game.aggressive_encounters = enabled;
+            }
+        }
+
+        if let Ok(hidden) = env::var("HIDE_CAPTURE_ODDS") {
+            if let Ok(hidden) = hidden.parse::<bool>() {
+                config.game.hide_capture_odds = hidden;
+            }
+        }
+
         // Performance config
         if let Ok(channel_size) = env::var("BROADCAST_CHANNEL_SIZE") {
             if let Ok(channel_size) = channel_size.parse::<usize>() {
@@ -121,10 +307,159 @@ impl Config {
             config.monsters.moves_path = moves_path;
         }
 
+        if let Ok(abilities_path) = env::var("ABILITIES_PATH") {
+            config.monsters.abilities_path = abilities_path;
+        }
+
+        if let Ok(locales_path) = env::var("LOCALES_PATH") {
+            config.monsters.locales_path = locales_path;
+        }
+
+        if let Ok(cooldown) = env::var("SPAWN_POINT_COOLDOWN_SEC") {
+            if let Ok(cooldown) = cooldown.parse::<u64>() {
+                config.monsters.spawn_point_cooldown_sec = cooldown;
+            }
+        }
+
+        if let Ok(packs) = env::var("CONTENT_PACKS") {
+            config.monsters.content_packs = packs.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect();
+        }
+
+        if let Ok(banlist_path) = env::var("BANLIST_PATH") {
+            config.monsters.banlist_path = banlist_path;
+        }
+
+        if let Ok(rental_teams_path) = env::var("RENTAL_TEAMS_PATH") {
+            config.monsters.rental_teams_path = rental_teams_path;
+        }
+
+        if let Ok(gyms_path) = env::var("GYMS_PATH") {
+            config.monsters.gyms_path = gyms_path;
+        }
+
+        if let Ok(cooldown) = env::var("SPECIES_COOLDOWN_SEC") {
+            if let Ok(cooldown) = cooldown.parse::<u64>() {
+                config.monsters.species_cooldown_sec = cooldown;
+            }
+        }
+
+        // Tick config
+        if let Ok(interval) = env::var("MONSTER_MOVEMENT_INTERVAL_MS") {
+            if let Ok(interval) = interval.parse::<u64>() {
+                config.ticks.monster_movement_interval_ms = interval;
+            }
+        }
+
+        if let Ok(interval) = env::var("SPAWNER_INTERVAL_MS") {
+            if let Ok(interval) = interval.parse::<u64>() {
+                config.ticks.spawner_interval_ms = interval;
+            }
+        }
+
+        if let Ok(interval) = env::var("PLAYER_MOVEMENT_INTERVAL_MS") {
+            if let Ok(interval) = interval.parse::<u64>() {
+                config.ticks.player_movement_interval_ms = interval;
+            }
+        }
+
+        if let Ok(interval) = env::var("TIME_SYNC_INTERVAL_MS") {
+            if let Ok(interval) = interval.parse::<u64>() {
+                config.ticks.time_sync_interval_ms = interval;
+            }
+        }
+
+        if let Ok(interval) = env::var("MONSTER_SAVE_INTERVAL_MS") {
+            if let Ok(interval) = interval.parse::<u64>() {
+                config.ticks.monster_save_interval_ms = interval;
+            }
+        }
+
+        // Telemetry config
+        if let Ok(sink) = env::var("TELEMETRY_SINK") {
+            match TelemetrySinkConfig::parse(&sink) {
+                Some(sink) => config.telemetry.sink = Some(sink),
+                None => tracing::warn!("Ignoring unrecognized TELEMETRY_SINK {:?}", sink),
+            }
+        }
+
+        if let Ok(interval) = env::var("TELEMETRY_FLUSH_INTERVAL_MS") {
+            if let Ok(interval) = interval.parse::<u64>() {
+                config.telemetry.flush_interval_ms = interval;
+            }
+        }
+
+        // Webhook config, a comma-separated list of URLs
+        if let Ok(urls) = env::var("WEBHOOK_URLS") {
+            for url in urls.split(',').filter(|url| !url.trim().is_empty()) {
+                match WebhookEndpoint::parse(url) {
+                    Some(endpoint) => config.webhooks.endpoints.push(endpoint),
+                    None => tracing::warn!("Ignoring unrecognized webhook URL {:?}", url),
+                }
+            }
+        }
+        if let Ok(secret) = env::var("WEBHOOK_SECRET") {
+            if !secret.is_empty() {
+                config.webhooks.secret = Some(secret);
+            }
+        }
+
+        // Backup config
+        if let Ok(directory) = env::var("BACKUP_DIR") {
+            if !directory.trim().is_empty() {
+                config.backups.directory = Some(directory);
+            }
+        }
+
+        if let Ok(interval) = env::var("BACKUP_INTERVAL_MS") {
+            if let Ok(interval) = interval.parse::<u64>() {
+                config.backups.interval_ms = interval;
+            }
+        }
+
+        if let Ok(retain) = env::var("BACKUP_RETAIN") {
+            if let Ok(retain) = retain.parse::<usize>() {
+                config.backups.retain = retain;
+            }
+        }
+
+        if let Ok(url) = env::var("BACKUP_S3_URL") {
+            let region = env::var("BACKUP_S3_REGION").unwrap_or_else(|_| "us-east-1".to_string());
+            let access_key_id = env::var("BACKUP_S3_ACCESS_KEY_ID").unwrap_or_default();
+            let secret_access_key = env::var("BACKUP_S3_SECRET_ACCESS_KEY").unwrap_or_default();
+            match BackupBucket::parse(&url, &region, &access_key_id, &secret_access_key) {
+                Some(bucket) => config.backups.bucket = Some(bucket),
+                None => tracing::warn!("Ignoring BACKUP_S3_URL {:?}: expected http(s)://host[:port]/bucket and both access keys", url),
+            }
+        }
+
         info!("Configuration loaded: {:?}", config);
         config
     }
 
+    // Check that configured values are usable
+    pub fn validate(&self) -> Result<(), String> {
+        let ticks = [
+            ("MONSTER_MOVEMENT_INTERVAL_MS", self.ticks.monster_movement_interval_ms),
+            ("SPAWNER_INTERVAL_MS", self.ticks.spawner_interval_ms),
+            ("PLAYER_MOVEMENT_INTERVAL_MS", self.ticks.player_movement_interval_ms),
+            ("MONSTER_SAVE_INTERVAL_MS", self.ticks.monster_save_interval_ms),
+            ("TIME_SYNC_INTERVAL_MS", self.ticks.time_sync_interval_ms),
+            ("TELEMETRY_FLUSH_INTERVAL_MS", self.telemetry.flush_interval_ms),
+        ];
+        for (name, interval) in ticks {
+            if !(MIN_TICK_INTERVAL_MS..=MAX_TICK_INTERVAL_MS).contains(&interval) {
+                return Err(format!(
+                    "{} must be between {} and {} ms, got {}",
+                    name, MIN_TICK_INTERVAL_MS, MAX_TICK_INTERVAL_MS, interval
+                ));
+            }
+        }
+        if self.backups.interval_ms < MIN_BACKUP_INTERVAL_MS {
+            return Err(format!("BACKUP_INTERVAL_MS must be at least {} ms, got {}", MIN_BACKUP_INTERVAL_MS, self.backups.interval_ms));
+        }
+        Ok(())
+    }
+
     pub fn server_addr(&self) -> SocketAddr {
         SocketAddr::new(self.server.host, self.server.port)
     }
@@ -0,0 +1,431 @@
+use dashmap::DashMap;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::Duration;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::game_loop::event_scheduler::ScheduledEvent;
+use crate::app_state::AppState;
+use crate::game_loop::berries::BerryType;
+use crate::game_loop::currency::{CurrencyManager, TransactionReason};
+use crate::game_loop::fast_travel::TRAVEL_ITEM_ID;
+use crate::game_loop::inventory::InventoryManager;
+use crate::game_loop::notifications::NotificationManager;
+use crate::game_loop::player_settings::NotificationKind;
+use crate::game_loop::pokemon_collection::PokemonCollectionManager;
+use crate::lobby::{find_player_lobby, Lobby};
+use crate::models::ServerMessage;
+
+// How long an auction runs before it settles
+const AUCTION_DURATION_SECS: u64 = 15 * 60;
+// Pause between the end of one auction and the start of the next
+const AUCTION_INTERVAL_SECS: u64 = 45 * 60;
+// Bids placed this close to the end push the end time back to this far from now
+const ANTI_SNIPE_WINDOW_SECS: u64 = 30;
+// How often the scheduler checks for auctions to start or settle
+const SCHEDULER_TICK_SECS: u64 = 5;
+// Redis hash holding every running auction (auction ID → JSON)
+const AUCTIONS_KEY: &str = "auctions";
+
+// Rare prizes the scheduler picks from, with their starting bid and minimum raise
+fn auction_catalog() -> Vec<(AuctionPrize, u64, u64)> {
+    vec![
+        (AuctionPrize::Pokemon { template_id: 6, level: 36 }, 5000, 250),
+        (AuctionPrize::Pokemon { template_id: 9, level: 36 }, 5000, 250),
+        (AuctionPrize::Pokemon { template_id: 3, level: 32 }, 5000, 250),
+        (AuctionPrize::Pokemon { template_id: 38, level: 30 }, 4000, 200),
+        (AuctionPrize::Item { item_id: TRAVEL_ITEM_ID.to_string(), quantity: 3 }, 1500, 100),
+        (AuctionPrize::Item { item_id: BerryType::Sitrus.item_id().to_string(), quantity: 10 }, 1000, 50),
+    ]
+}
+
+// What the winner of an auction receives
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuctionPrize {
+    Pokemon { template_id: u32, level: u32 },
+    Item { item_id: String, quantity: u32 },
+}
+
+// The highest bid on an auction; the amount is held by the server until the bidder is outbid
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+pub struct AuctionBid {
+    pub player_id: String,
+    pub username: String,
+    pub amount: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+pub struct Auction {
+    pub auction_id: Uuid,
+    pub prize: AuctionPrize,
+    pub starting_bid: u64,
+    pub min_increment: u64,
+    pub highest_bid: Option<AuctionBid>,
+    pub ends_at: u64,
+}
+
+impl Auction {
+    // Smallest amount the next bid has to reach
+    pub fn minimum_bid(&self) -> u64 {
+        match &self.highest_bid {
+            Some(bid) => bid.amount + self.min_increment,
+            None => self.starting_bid,
+        }
+    }
+}
+
+// Result of an accepted bid
+pub struct BidOutcome {
+    pub auction: Auction,
+    pub bidder_balance: u64,
+    // Player whose escrowed bid was refunded, with the refunded amount
+    pub outbid: Option<(String, u64)>,
+}
+
+// Runs server auctions: escrows bids, extends late auctions and hands out prizes
+pub struct AuctionManager {
+    redis_client: redis::Client,
+    pokemon_collection_manager: Arc<PokemonCollectionManager>,
+    currency_manager: Arc<CurrencyManager>,
+    inventory_manager: Arc<InventoryManager>,
+    auctions: DashMap<Uuid, Auction>,
+    // Bids are processed one at a time so escrow and refunds never interleave
+    bid_lock: Mutex<()>,
+}
+
+impl AuctionManager {
+    pub fn new(
+        redis_client: redis::Client,
+        pokemon_collection_manager: Arc<PokemonCollectionManager>,
+        currency_manager: Arc<CurrencyManager>,
+        inventory_manager: Arc<InventoryManager>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            redis_client,
+            pokemon_collection_manager,
+            currency_manager,
+            inventory_manager,
+            auctions: DashMap::new(),
+            bid_lock: Mutex::new(()),
+        })
+    }
+
+    async fn connection(&self) -> Result<redis::aio::Connection, String> {
+        self.redis_client
+            .get_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection error: {}", e))
+    }
+
+    fn notifications_key(player_id: &str) -> String {
+        format!("auction_notifications:{}", player_id)
+    }
+
+    // Restore auctions that were running when the server stopped
+    pub async fn load_auctions(&self) -> Result<usize, String> {
+        let mut con = self.connection().await?;
+        let saved: HashMap<String, String> = redis::cmd("HGETALL")
+            .arg(AUCTIONS_KEY)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+
+        for json in saved.values() {
+            match serde_json::from_str::<Auction>(json) {
+                Ok(auction) => {
+                    self.auctions.insert(auction.auction_id, auction);
+                }
+                Err(e) => error!("Failed to deserialize auction: {}", e),
+            }
+        }
+        Ok(self.auctions.len())
+    }
+
+    async fn save_auction(&self, auction: &Auction) -> Result<(), String> {
+        let json = serde_json::to_string(auction)
+            .map_err(|e| format!("Failed to serialize auction: {}", e))?;
+        let mut con = self.connection().await?;
+        redis::cmd("HSET")
+            .arg(AUCTIONS_KEY)
+            .arg(auction.auction_id.to_string())
+            .arg(json)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))
+    }
+
+    async fn delete_auction(&self, auction_id: Uuid) -> Result<(), String> {
+        let mut con = self.connection().await?;
+        redis::cmd("HDEL")
+            .arg(AUCTIONS_KEY)
+            .arg(auction_id.to_string())
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))
+    }
+
+    pub fn active_auctions(&self) -> Vec<Auction> {
+        self.auctions.iter().map(|entry| entry.value().clone()).collect()
+    }
+
+    // Start a new auction for a random prize from the catalog
+    pub async fn start_auction(&self) -> Result<Auction, String> {
+        let (prize, starting_bid, min_increment) = auction_catalog()
+            .choose(&mut rand::thread_rng())
+            .cloned()
+            .ok_or("Auction catalog is empty")?;
+        let auction = Auction {
+            auction_id: Uuid::new_v4(),
+            prize,
+            starting_bid,
+            min_increment,
+            highest_bid: None,
+            ends_at: chrono::Utc::now().timestamp() as u64 + AUCTION_DURATION_SECS,
+        };
+
+        self.save_auction(&auction).await?;
+        self.auctions.insert(auction.auction_id, auction.clone());
+        info!("Started auction {} for {:?}", auction.auction_id, auction.prize);
+        Ok(auction)
+    }
+
+    // Place a bid, escrowing the amount and refunding the previous highest bidder
+    pub async fn place_bid(&self, player_id: &str, username: &str, auction_id: Uuid, amount: u64) -> Result<BidOutcome, String> {
+        let _guard = self.bid_lock.lock().await;
+
+        let mut auction = self.auctions.get(&auction_id)
+            .map(|entry| entry.value().clone())
+            .ok_or("Auction is no longer running")?;
+        let now = chrono::Utc::now().timestamp() as u64;
+        if auction.ends_at <= now {
+            return Err("Auction has already ended".to_string());
+        }
+        if amount < auction.minimum_bid() {
+            return Err(format!("Bid must be at least {}", auction.minimum_bid()));
+        }
+
+        let bidder_balance = self.currency_manager.debit(player_id, amount, TransactionReason::AuctionBid).await?;
+
+        let previous_bid = auction.highest_bid.replace(AuctionBid {
+            player_id: player_id.to_string(),
+            username: username.to_string(),
+            amount,
+        });
+        if auction.ends_at - now < ANTI_SNIPE_WINDOW_SECS {
+            auction.ends_at = now + ANTI_SNIPE_WINDOW_SECS;
+            info!("Auction {} extended to {} after a late bid", auction_id, auction.ends_at);
+        }
+
+        if let Err(e) = self.save_auction(&auction).await {
+            if let Err(refund_err) = self.currency_manager.credit(player_id, amount, TransactionReason::AuctionRefund).await {
+                error!("Failed to refund bid of {} to player {}: {}", amount, player_id, refund_err);
+            }
+            return Err(e);
+        }
+        self.auctions.insert(auction_id, auction.clone());
+
+        let mut bidder_balance = bidder_balance;
+        let outbid = match previous_bid {
+            Some(previous) => {
+                match self.currency_manager.credit(&previous.player_id, previous.amount, TransactionReason::AuctionRefund).await {
+                    Ok(balance) if previous.player_id == player_id => bidder_balance = balance,
+                    Ok(_) => {}
+                    Err(e) => error!("Failed to refund bid of {} to player {}: {}", previous.amount, previous.player_id, e),
+                }
+                Some((previous.player_id, previous.amount)).filter(|(id, _)| id != player_id)
+            }
+            None => None,
+        };
+
+        info!("Player {} bid {} on auction {}", player_id, amount, auction_id);
+        Ok(BidOutcome { auction, bidder_balance, outbid })
+    }
+
+    // Close every auction past its end time, handing prizes to the winners
+    pub async fn settle_ended_auctions(&self) -> Vec<Auction> {
+        let _guard = self.bid_lock.lock().await;
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let ended: Vec<Uuid> = self.auctions.iter()
+            .filter(|entry| entry.value().ends_at <= now)
+            .map(|entry| *entry.key())
+            .collect();
+
+        let mut settled = Vec::new();
+        for auction_id in ended {
+            let Some(auction) = self.auctions.get(&auction_id).map(|entry| entry.value().clone()) else {
+                continue;
+            };
+            if let Some(bid) = &auction.highest_bid {
+                // The auction stays open until its prize is delivered, so a failure is retried next round
+                if let Err(e) = self.deliver_prize(&auction.prize, &bid.player_id).await {
+                    error!("Failed to deliver auction {} prize to player {}, retrying: {}", auction_id, bid.player_id, e);
+                    continue;
+                }
+                info!("Auction {} won by {} for {}", auction_id, bid.player_id, bid.amount);
+            } else {
+                info!("Auction {} ended without bids", auction_id);
+            }
+
+            // Forget the auction even when Redis fails, so the prize is never handed out twice
+            if let Err(e) = self.delete_auction(auction_id).await {
+                error!("Failed to remove settled auction {}: {}", auction_id, e);
+            }
+            self.auctions.remove(&auction_id);
+            settled.push(auction);
+        }
+        settled
+    }
+
+    async fn deliver_prize(&self, prize: &AuctionPrize, player_id: &str) -> Result<(), String> {
+        match prize {
+            AuctionPrize::Pokemon { template_id, level } => {
+                let pokemon = self.pokemon_collection_manager.pokemon_from_template(*template_id, *level)?;
+                self.pokemon_collection_manager.add_pokemon(player_id, pokemon).await.map(|_| ())
+            }
+            AuctionPrize::Item { item_id, quantity } => {
+                self.inventory_manager.add_item(player_id, item_id, *quantity).await.map(|_| ())
+            }
+        }
+    }
+
+    // Keep a settlement message for a player who was offline when their auction ended
+    pub async fn queue_notification(&self, player_id: &str, message: &ServerMessage) -> Result<(), String> {
+        let json = serde_json::to_string(message)
+            .map_err(|e| format!("Failed to serialize auction notification: {}", e))?;
+        let mut con = self.connection().await?;
+        redis::cmd("RPUSH")
+            .arg(Self::notifications_key(player_id))
+            .arg(json)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))
+    }
+
+    // Take every queued notification for a player, oldest first
+    pub async fn take_notifications(&self, player_id: &str) -> Result<Vec<ServerMessage>, String> {
+        let key = Self::notifications_key(player_id);
+        let mut con = self.connection().await?;
+        let (saved, _): (Vec<String>, ()) = redis::pipe()
+            .atomic()
+            .cmd("LRANGE").arg(&key).arg(0).arg(-1)
+            .cmd("DEL").arg(&key).ignore()
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+
+        Ok(saved
+            .iter()
+            .filter_map(|json| match serde_json::from_str(json) {
+                Ok(message) => Some(message),
+                Err(e) => {
+                    error!("Failed to deserialize auction notification: {}", e);
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+async fn broadcast_to_all(lobbies: &DashMap<String, Arc<Lobby>>, message: &ServerMessage) {
+    let lobbies: Vec<Arc<Lobby>> = lobbies.iter().map(|entry| entry.value().clone()).collect();
+    for lobby in lobbies {
+        if let Err(e) = lobby.broadcast_except(message, &[]).await {
+            error!("Failed to broadcast auction message to lobby {}: {}", lobby.id, e);
+        }
+    }
+}
+
+// Tell everyone about the new highest bid and the outbid player about their refund
+pub async fn announce_bid(lobbies: &DashMap<String, Arc<Lobby>>, notification_manager: &NotificationManager, outcome: &BidOutcome) {
+    broadcast_to_all(lobbies, &ServerMessage::AuctionUpdated { auction: outcome.auction.clone() }).await;
+
+    if let Some((outbid_player_id, refunded)) = &outcome.outbid {
+        let outbid_msg = ServerMessage::AuctionOutbid {
+            auction_id: outcome.auction.auction_id,
+            refunded: *refunded,
+        };
+        notification_manager.notify(lobbies, outbid_player_id, NotificationKind::AuctionOutbid, outbid_msg).await;
+    }
+}
+
+// Scheduled event for auctions: starts one after the previous has been closed for a while,
+// settles finished ones and notifies winners now or on their next login
+pub struct AuctionEvents {
+    state: Arc<AppState>,
+    auction_manager: Arc<AuctionManager>,
+    next_auction_at: u64,
+}
+
+impl AuctionEvents {
+    // Restores the auctions that were running before a restart
+    pub async fn start(state: Arc<AppState>, auction_manager: Arc<AuctionManager>) -> Self {
+        match auction_manager.load_auctions().await {
+            Ok(count) => info!("Restored {} running auctions", count),
+            Err(e) => error!("Failed to restore auctions: {}", e),
+        }
+        let next_auction_at = chrono::Utc::now().timestamp() as u64 + AUCTION_INTERVAL_SECS;
+        Self { state, auction_manager, next_auction_at }
+    }
+
+    async fn tick(&mut self) {
+        let now = chrono::Utc::now().timestamp() as u64;
+
+        for auction in self.auction_manager.settle_ended_auctions().await {
+            broadcast_to_all(&self.state.lobbies, &ServerMessage::AuctionEnded {
+                auction_id: auction.auction_id,
+                winner_username: auction.highest_bid.as_ref().map(|bid| bid.username.clone()),
+            }).await;
+            self.next_auction_at = now + AUCTION_INTERVAL_SECS;
+
+            let Some(bid) = auction.highest_bid.clone() else {
+                continue;
+            };
+            let won_msg = ServerMessage::AuctionWon {
+                auction_id: auction.auction_id,
+                prize: auction.prize.clone(),
+                amount: bid.amount,
+            };
+            let delivered = match find_player_lobby(&self.state.lobbies, &bid.player_id) {
+                Some(lobby) => lobby.send_to_player(&bid.player_id, &won_msg).await.is_ok(),
+                None => false,
+            };
+            if !delivered {
+                if let Err(e) = self.auction_manager.queue_notification(&bid.player_id, &won_msg).await {
+                    error!("Failed to queue auction notification for player {}: {}", bid.player_id, e);
+                }
+            }
+        }
+
+        if self.auction_manager.auctions.is_empty() && now >= self.next_auction_at {
+            match self.auction_manager.start_auction().await {
+                Ok(auction) => broadcast_to_all(&self.state.lobbies, &ServerMessage::AuctionUpdated { auction }).await,
+                Err(e) => {
+                    error!("Failed to start auction: {}", e);
+                    self.next_auction_at = now + AUCTION_INTERVAL_SECS;
+                }
+            }
+        }
+    }
+}
+
+impl ScheduledEvent for AuctionEvents {
+    fn name(&self) -> &'static str {
+        "auctions"
+    }
+
+    fn period(&self) -> Duration {
+        Duration::from_secs(SCHEDULER_TICK_SECS)
+    }
+
+    fn run(&mut self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        Box::pin(self.tick())
+    }
+}
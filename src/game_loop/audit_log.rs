@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::game_loop::currency::TransactionReason;
+
+// Redis stream every audit entry is appended to; it is never trimmed
+const AUDIT_STREAM_KEY: &str = "audit_log";
+// Most entries returned by one query
+pub const MAX_AUDIT_QUERY_LIMIT: usize = 1000;
+// Newest entries looked through when a query filters by actor or action
+const AUDIT_QUERY_SCAN: usize = 10_000;
+
+// Who performed an audited mutation when it was not a player
+pub const ADMIN_ACTOR: &str = "admin";
+
+// A sensitive change to player data or game state
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum AuditAction {
+    CurrencyChanged { player_id: String, reason: TransactionReason },
+    TradeCompleted { trade_id: Uuid, player_ids: [String; 2] },
+    // `command` names the admin endpoint, `target` the lobby or snapshot it acted on
+    AdminCommand { command: String, target: String },
+}
+
+impl AuditAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AuditAction::CurrencyChanged { .. } => "currency_changed",
+            AuditAction::TradeCompleted { .. } => "trade_completed",
+            AuditAction::AdminCommand { .. } => "admin_command",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AuditEntry {
+    // Unix time in milliseconds
+    pub at: i64,
+    // Player ID, or ADMIN_ACTOR for admin API calls
+    pub actor: String,
+    #[serde(flatten)]
+    pub action: AuditAction,
+    // State of what changed, before and after the change, where it applies
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
+
+impl AuditEntry {
+    pub fn new(actor: &str, action: AuditAction) -> Self {
+        Self {
+            at: chrono::Utc::now().timestamp_millis(),
+            actor: actor.to_string(),
+            action,
+            before: None,
+            after: None,
+        }
+    }
+
+    pub fn with_change<B: Serialize, A: Serialize>(mut self, before: &B, after: &A) -> Self {
+        self.before = serde_json::to_value(before).ok();
+        self.after = serde_json::to_value(after).ok();
+        self
+    }
+}
+
+// Filters of an audit log query; empty filters match everything
+#[derive(Deserialize, Default, Debug)]
+pub struct AuditQuery {
+    pub actor: Option<String>,
+    pub action: Option<String>,
+    // Only entries at or after this Unix time in milliseconds
+    pub since: Option<i64>,
+    pub limit: Option<usize>,
+}
+
+// Append-only trail of sensitive mutations, stored as a Redis stream
+pub struct AuditLog {
+    redis_client: redis::Client,
+}
+
+impl AuditLog {
+    pub fn new(redis_client: redis::Client) -> Arc<Self> {
+        Arc::new(Self { redis_client })
+    }
+
+    async fn connection(&self) -> Result<redis::aio::Connection, String> {
+        self.redis_client
+            .get_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection error: {}", e))
+    }
+
+    // Append an entry. Failures are logged rather than returned so that auditing never
+    // undoes a change that already happened.
+    pub async fn record(&self, entry: AuditEntry) {
+        if let Err(e) = self.append(&entry).await {
+            error!("Failed to write audit entry {:?}: {}", entry, e);
+        }
+    }
+
+    async fn append(&self, entry: &AuditEntry) -> Result<(), String> {
+        let json = serde_json::to_string(entry).map_err(|e| format!("Failed to serialize audit entry: {}", e))?;
+        let mut con = self.connection().await?;
+        redis::cmd("XADD")
+            .arg(AUDIT_STREAM_KEY)
+            .arg("*")
+            .arg("entry")
+            .arg(json)
+            .query_async::<_, String>(&mut con)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Redis query error: {}", e))
+    }
+
+    // Entries matching the query, newest first
+    pub async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>, String> {
+        let limit = query.limit.unwrap_or(100).min(MAX_AUDIT_QUERY_LIMIT);
+        let filtered = query.actor.is_some() || query.action.is_some();
+        let count = if filtered { AUDIT_QUERY_SCAN } else { limit };
+        let start = query.since.map(|since| since.to_string()).unwrap_or_else(|| "-".to_string());
+        let mut con = self.connection().await?;
+        let entries: Vec<(String, Vec<(String, String)>)> = redis::cmd("XREVRANGE")
+            .arg(AUDIT_STREAM_KEY)
+            .arg("+")
+            .arg(start)
+            .arg("COUNT")
+            .arg(count)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        Ok(entries.into_iter()
+            .flat_map(|(_, fields)| fields.into_iter().map(|(_, json)| json))
+            .filter_map(|json| serde_json::from_str::<AuditEntry>(&json).ok())
+            .filter(|entry| query.actor.as_ref().is_none_or(|actor| entry.actor == *actor))
+            .filter(|entry| query.action.as_deref().is_none_or(|action| entry.action.name() == action))
+            .take(limit)
+            .collect())
+    }
+}
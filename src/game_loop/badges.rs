@@ -0,0 +1,243 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::combat::gyms::{Badge, GymLeader, GymOffer, MAX_REMATCH_TIER};
+use crate::combat::listeners::{BattleOutcomeListener, GymBattleSummary};
+use crate::combat::manager::BattleManager;
+use crate::combat::state::PvPBattleOutcome;
+use crate::models::ServerMessage;
+
+// Badges each player needs before PvP battles count towards the ranked ladder
+pub const RANKED_MIN_BADGES: usize = 1;
+
+// When a player last beat a gym leader and the team tier the leader brings next
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GymRecord {
+    pub defeated_at: i64, // Unix timestamp (seconds)
+    pub tier: u32,
+}
+
+// Badges players earned by defeating gym leaders, kept on the player's profile, and their
+// record against each leader for rematches
+pub struct BadgeManager {
+    redis_client: redis::Client,
+    battle_manager: Arc<BattleManager>,
+}
+
+impl BadgeManager {
+    pub fn new(redis_client: redis::Client, battle_manager: Arc<BattleManager>) -> Arc<Self> {
+        Arc::new(Self {
+            redis_client,
+            battle_manager,
+        })
+    }
+
+    fn badges_key(player_id: &str) -> String {
+        format!("badges:{}", player_id)
+    }
+
+    fn gym_records_key(player_id: &str) -> String {
+        format!("gym_records:{}", player_id)
+    }
+
+    async fn connection(&self) -> Result<redis::aio::Connection, String> {
+        self.redis_client
+            .get_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection error: {}", e))
+    }
+
+    async fn badge_ids(&self, player_id: &str) -> Result<Vec<String>, String> {
+        let mut con = self.connection().await?;
+        redis::cmd("SMEMBERS")
+            .arg(Self::badges_key(player_id))
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))
+    }
+
+    // Badges the player holds, in the order the gyms are meant to be beaten
+    pub async fn badges(&self, player_id: &str) -> Result<Vec<Badge>, String> {
+        let badge_ids = self.badge_ids(player_id).await?;
+        Ok(self.battle_manager.gyms().gyms.iter()
+            .filter(|gym| badge_ids.contains(&gym.badge.id))
+            .map(|gym| gym.badge.clone())
+            .collect())
+    }
+
+    // Number of badges the player holds from gyms that are still open
+    pub async fn badge_count(&self, player_id: &str) -> Result<usize, String> {
+        Ok(self.badges(player_id).await?.len())
+    }
+
+    // Highest level the player's Pokémon obey at with `badge_count` badges
+    pub fn obedience_level_cap(&self, badge_count: usize) -> u32 {
+        self.battle_manager.gyms().obedience_level_cap(badge_count)
+    }
+
+    pub async fn has_badge(&self, player_id: &str, badge_id: &str) -> Result<bool, String> {
+        let mut con = self.connection().await?;
+        redis::cmd("SISMEMBER")
+            .arg(Self::badges_key(player_id))
+            .arg(badge_id)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))
+    }
+
+    // Display name of a badge, falling back to its ID for badges no gym awards
+    pub fn badge_name(&self, badge_id: &str) -> String {
+        self.battle_manager.gyms().by_badge(badge_id)
+            .map(|gym| gym.badge.name.clone())
+            .unwrap_or_else(|| format!("{} badge", badge_id))
+    }
+
+    async fn gym_record(&self, player_id: &str, gym_id: &str) -> Result<Option<GymRecord>, String> {
+        let mut con = self.connection().await?;
+        let record: Option<String> = redis::cmd("HGET")
+            .arg(Self::gym_records_key(player_id))
+            .arg(gym_id)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        record
+            .map(|record| serde_json::from_str(&record).map_err(|e| format!("Failed to parse gym record: {}", e)))
+            .transpose()
+    }
+
+    // The player's record against `gym`. Players who got the badge before records were kept
+    // count as having beaten the leader once, long ago.
+    async fn gym_record_or_badge(&self, player_id: &str, gym: &GymLeader) -> Result<Option<GymRecord>, String> {
+        if let Some(record) = self.gym_record(player_id, &gym.id).await? {
+            return Ok(Some(record));
+        }
+        if self.has_badge(player_id, &gym.badge.id).await? {
+            return Ok(Some(GymRecord { defeated_at: 0, tier: 1 }));
+        }
+        Ok(None)
+    }
+
+    // What `gym`'s leader offers the player: the first challenge, or a rematch with a stronger
+    // team once the cooldown since the player last beat them is over
+    pub async fn gym_offer(&self, player_id: &str, gym: &GymLeader) -> Result<GymOffer, String> {
+        let Some(record) = self.gym_record_or_badge(player_id, gym).await? else {
+            return Ok(GymOffer::Challenge);
+        };
+        let available_at = record.defeated_at + gym.rematch_cooldown_secs as i64;
+        if chrono::Utc::now().timestamp() < available_at {
+            return Ok(GymOffer::RematchCooldown { available_at });
+        }
+        Ok(GymOffer::Rematch { tier: record.tier })
+    }
+
+    // Team tier the player would battle `gym` at now, or why they cannot
+    pub async fn challenge_tier(&self, player_id: &str, gym: &GymLeader) -> Result<u32, String> {
+        match self.gym_offer(player_id, gym).await? {
+            GymOffer::Challenge => Ok(0),
+            GymOffer::Rematch { tier } => Ok(tier),
+            GymOffer::RematchCooldown { .. } => Err(format!("{} is not ready for a rematch yet", gym.name)),
+        }
+    }
+
+    // Remember that the player beat `gym` just now, so the leader brings their next team tier
+    // to the rematch
+    pub async fn record_gym_defeat(&self, player_id: &str, gym: &GymLeader) -> Result<GymRecord, String> {
+        let tier = self.gym_record_or_badge(player_id, gym).await?.map_or(0, |record| record.tier);
+        let record = GymRecord {
+            defeated_at: chrono::Utc::now().timestamp(),
+            tier: (tier + 1).min(MAX_REMATCH_TIER),
+        };
+        let record_json = serde_json::to_string(&record).map_err(|e| format!("Failed to serialize gym record: {}", e))?;
+        let mut con = self.connection().await?;
+        redis::cmd("HSET")
+            .arg(Self::gym_records_key(player_id))
+            .arg(&gym.id)
+            .arg(record_json)
+            .query_async::<_, ()>(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        Ok(record)
+    }
+
+    // Give the player a badge, returning false when they already had it
+    pub async fn award_badge(&self, player_id: &str, badge_id: &str) -> Result<bool, String> {
+        let mut con = self.connection().await?;
+        let added: i64 = redis::cmd("SADD")
+            .arg(Self::badges_key(player_id))
+            .arg(badge_id)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        Ok(added > 0)
+    }
+}
+
+// Records gym battle wins for rematches and awards the gym leader's badge the first time
+pub struct BadgeListener {
+    badge_manager: Arc<BadgeManager>,
+}
+
+impl BadgeListener {
+    pub fn new(badge_manager: Arc<BadgeManager>) -> Self {
+        Self { badge_manager }
+    }
+}
+
+impl BattleOutcomeListener for BadgeListener {
+    fn on_gym_battle_finished(&self, summary: &GymBattleSummary) {
+        if !matches!(summary.outcome, PvPBattleOutcome::Victory | PvPBattleOutcome::OpponentSurrendered | PvPBattleOutcome::OpponentDisconnected) {
+            return;
+        }
+        let Some(gym) = self.badge_manager.battle_manager.gyms().get(&summary.gym_id).cloned() else {
+            return;
+        };
+        let badge_manager = self.badge_manager.clone();
+        let lobby = summary.lobby.clone();
+        let player_id = summary.player_id.clone();
+
+        tokio::spawn(async move {
+            match badge_manager.record_gym_defeat(&player_id, &gym).await {
+                Ok(record) => {
+                    info!("Player {} beat {}, next rematch at tier {}", player_id, gym.id, record.tier);
+                    let message = ServerMessage::GymLeaderOffer {
+                        gym_id: gym.id.clone(),
+                        offer: GymOffer::RematchCooldown { available_at: record.defeated_at + gym.rematch_cooldown_secs as i64 },
+                    };
+                    if let Err(e) = lobby.send_to_player(&player_id, &message).await {
+                        error!("Failed to send gym leader offer to player {}: {}", player_id, e);
+                    }
+                }
+                Err(e) => error!("Failed to record gym {} defeat for player {}: {}", gym.id, player_id, e),
+            }
+
+            let badge = gym.badge;
+            match badge_manager.award_badge(&player_id, &badge.id).await {
+                Ok(true) => {}
+                Ok(false) => return,
+                Err(e) => {
+                    error!("Failed to award badge {} to player {}: {}", badge.id, player_id, e);
+                    return;
+                }
+            }
+            info!("Player {} earned the {}", player_id, badge.name);
+            let badge_count = match badge_manager.badge_count(&player_id).await {
+                Ok(badge_count) => badge_count,
+                Err(e) => {
+                    error!("Failed to count badges of player {}: {}", player_id, e);
+                    return;
+                }
+            };
+            let level_cap = badge_manager.obedience_level_cap(badge_count);
+            badge_manager.battle_manager.set_obedience_level_cap(&player_id, Some(level_cap));
+            let message = ServerMessage::BadgeEarned {
+                badge,
+                badge_count,
+                obedience_level_cap: level_cap,
+            };
+            if let Err(e) = lobby.send_to_player(&player_id, &message).await {
+                error!("Failed to send badge to player {}: {}", player_id, e);
+            }
+        });
+    }
+}
@@ -0,0 +1,110 @@
+use dashmap::DashMap;
+use std::time::{Duration, Instant};
+use std::sync::Arc;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::combat::manager::BattleManager;
+use crate::combat::{BattleOutcomeListener, PvPBattleSummary};
+use crate::lobby::Lobby;
+use crate::models::ServerMessage;
+
+// Canned messages players can send during a battle; there is no free text to keep battles friendly
+pub const QUICK_MESSAGES: [&str; 8] = [
+    "hello", "good_luck", "nice_move", "oops", "well_played", "gg", "thanks", "rematch",
+];
+// Minimum time between two chat messages from the same player
+const CHAT_COOLDOWN: Duration = Duration::from_secs(2);
+// How long after a PvP battle its players can still exchange messages (e.g. "gg")
+const POST_BATTLE_CHAT_WINDOW: Duration = Duration::from_secs(30);
+
+// Quick-message chat between the two players of a PvP battle
+pub struct BattleChatManager {
+    battle_manager: Arc<BattleManager>,
+    last_message_at: DashMap<String, Instant>,
+    // Battle ID → (player 1, player 2, end time) for recently finished battles
+    finished_battles: DashMap<Uuid, (String, String, Instant)>,
+}
+
+impl BattleChatManager {
+    pub fn new(battle_manager: Arc<BattleManager>) -> Arc<Self> {
+        Arc::new(Self {
+            battle_manager,
+            last_message_at: DashMap::new(),
+            finished_battles: DashMap::new(),
+        })
+    }
+
+    // Both players of a live or just finished PvP battle
+    async fn participants(&self, battle_id: Uuid) -> Option<(String, String)> {
+        if let Some(battle_mutex) = self.battle_manager.get_pvp_battle_state(battle_id) {
+            let battle_state = battle_mutex.lock().await;
+            return Some((battle_state.player1.player_id.clone(), battle_state.player2.player_id.clone()));
+        }
+        self.finished_battles.get(&battle_id)
+            .filter(|entry| entry.2.elapsed() < POST_BATTLE_CHAT_WINDOW)
+            .map(|entry| (entry.0.clone(), entry.1.clone()))
+    }
+
+    // Send a quick message to both players of the battle, skipping anyone `is_muted` filters out
+    pub async fn send_message(
+        &self,
+        lobby: &Lobby,
+        battle_id: Uuid,
+        player_id: &str,
+        message_id: &str,
+        is_muted: impl Fn(&str) -> bool,
+    ) -> Result<(), String> {
+        if !QUICK_MESSAGES.contains(&message_id) {
+            return Err(format!("Unknown quick message {}", message_id));
+        }
+
+        let (player1_id, player2_id) = self.participants(battle_id).await
+            .ok_or_else(|| "Battle chat is closed".to_string())?;
+        if player_id != player1_id && player_id != player2_id {
+            return Err("You are not part of this battle".to_string());
+        }
+
+        let now = Instant::now();
+        match self.last_message_at.get(player_id) {
+            Some(last) if now.duration_since(*last) < CHAT_COOLDOWN => {
+                return Err("You are sending messages too quickly".to_string());
+            }
+            _ => {}
+        }
+        self.last_message_at.insert(player_id.to_string(), now);
+
+        let chat_msg = ServerMessage::BattleChat {
+            battle_id,
+            sender_id: player_id.to_string(),
+            message_id: message_id.to_string(),
+        };
+        for recipient in [&player1_id, &player2_id] {
+            if recipient != player_id && is_muted(recipient) {
+                continue;
+            }
+            if let Err(e) = lobby.send_to_player(recipient, &chat_msg).await {
+                error!("Failed to send battle chat to player {}: {}", recipient, e);
+            }
+        }
+
+        info!("Player {} sent quick message {} in battle {}", player_id, message_id, battle_id);
+        Ok(())
+    }
+
+    // Drop rate limit state for a player who left
+    pub fn forget_player(&self, player_id: &str) {
+        self.last_message_at.remove(player_id);
+    }
+}
+
+impl BattleOutcomeListener for BattleChatManager {
+    // Keep the chat open briefly after the battle so players can say "gg"
+    fn on_pvp_battle_finished(&self, summary: &PvPBattleSummary) {
+        self.finished_battles.retain(|_, (_, _, ended_at)| ended_at.elapsed() < POST_BATTLE_CHAT_WINDOW);
+        self.finished_battles.insert(
+            summary.battle_id,
+            (summary.player1_id.clone(), summary.player2_id.clone(), Instant::now()),
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::combat::listeners::{BattleOutcomeListener, PvPBattleSummary, WildBattleSummary};
+use crate::combat::state::{BattlePokemonPublicView, BattlePokemonTeamOverview, PvPBattleOutcome, WildBattleOutcome};
+
+// Number of battles kept per player
+const MAX_HISTORY_LENGTH: isize = 100;
+const DEFAULT_PAGE_SIZE: usize = 20;
+const MAX_PAGE_SIZE: usize = 50;
+
+// Who the player fought
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BattleOpponent {
+    Wild { pokemon: BattlePokemonPublicView },
+    Trainer {
+        player_id: String,
+        username: String,
+        team: Vec<BattlePokemonTeamOverview>,
+    },
+}
+
+// Outcome from the recording player's point of view
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+#[serde(untagged)]
+pub enum BattleRecordOutcome {
+    Wild(WildBattleOutcome),
+    Trainer(PvPBattleOutcome),
+}
+
+// Summary of one finished battle as shown on a player's profile
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+pub struct BattleRecord {
+    pub battle_id: Uuid,
+    pub opponent: BattleOpponent,
+    pub outcome: BattleRecordOutcome,
+    pub turns: u32,
+    pub started_at: u64,
+    pub duration_secs: u64,
+    // The player's team as it was when the battle ended
+    pub team: Vec<BattlePokemonTeamOverview>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+pub struct BattleHistoryPage {
+    pub records: Vec<BattleRecord>,
+    pub page: usize,
+    pub page_size: usize,
+    pub total: usize,
+}
+
+// Stores finished battles per player in Redis, newest first
+pub struct BattleHistoryManager {
+    redis_client: redis::Client,
+}
+
+impl BattleHistoryManager {
+    pub fn new(redis_client: redis::Client) -> Arc<Self> {
+        Arc::new(Self { redis_client })
+    }
+
+    fn history_key(player_id: &str) -> String {
+        format!("battle_history:{}", player_id)
+    }
+
+    pub async fn record(&self, player_id: &str, record: &BattleRecord) -> Result<(), String> {
+        let json = serde_json::to_string(record)
+            .map_err(|e| format!("Failed to serialize battle record: {}", e))?;
+        let key = Self::history_key(player_id);
+        let mut con = self.redis_client
+            .get_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection error: {}", e))?;
+        redis::pipe()
+            .cmd("LPUSH").arg(&key).arg(json).ignore()
+            .cmd("LTRIM").arg(&key).arg(0).arg(MAX_HISTORY_LENGTH - 1).ignore()
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))
+    }
+
+    // Inclusive LRANGE bounds of a page, None if the page lies past any index Redis can hold
+    fn page_bounds(page: usize, page_size: usize) -> Option<(isize, isize)> {
+        let start = page.checked_mul(page_size)?;
+        let end = start.checked_add(page_size - 1)?;
+        Some((isize::try_from(start).ok()?, isize::try_from(end).ok()?))
+    }
+
+    // One page of a player's battles; pages start at 0
+    pub async fn get_history(&self, player_id: &str, page: Option<usize>, page_size: Option<usize>) -> Result<BattleHistoryPage, String> {
+        let page = page.unwrap_or(0);
+        let page_size = page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        let key = Self::history_key(player_id);
+
+        let mut con = self.redis_client
+            .get_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection error: {}", e))?;
+        let (saved, total): (Vec<String>, usize) = match Self::page_bounds(page, page_size) {
+            Some((start, end)) => redis::pipe()
+                .cmd("LRANGE").arg(&key).arg(start).arg(end)
+                .cmd("LLEN").arg(&key)
+                .query_async(&mut con)
+                .await
+                .map_err(|e| format!("Redis query error: {}", e))?,
+            // Far past the end of any history, so the page is empty
+            None => {
+                let total = redis::cmd("LLEN").arg(&key)
+                    .query_async(&mut con)
+                    .await
+                    .map_err(|e| format!("Redis query error: {}", e))?;
+                (Vec::new(), total)
+            }
+        };
+
+        let records = saved
+            .iter()
+            .filter_map(|json| match serde_json::from_str(json) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    error!("Failed to deserialize battle record: {}", e);
+                    None
+                }
+            })
+            .collect();
+        Ok(BattleHistoryPage { records, page, page_size, total })
+    }
+
+    fn record_in_background(self: &Arc<Self>, player_id: String, record: BattleRecord) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = manager.record(&player_id, &record).await {
+                error!("Failed to record battle {} for player {}: {}", record.battle_id, player_id, e);
+            }
+        });
+    }
+}
+
+// Records every finished battle into the participants' histories
+pub struct BattleHistoryListener {
+    history_manager: Arc<BattleHistoryManager>,
+}
+
+impl BattleHistoryListener {
+    pub fn new(history_manager: Arc<BattleHistoryManager>) -> Self {
+        Self { history_manager }
+    }
+}
+
+impl BattleOutcomeListener for BattleHistoryListener {
+    fn on_wild_battle_finished(&self, summary: &WildBattleSummary) {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let record = BattleRecord {
+            battle_id: summary.battle_id,
+            opponent: BattleOpponent::Wild { pokemon: summary.wild_pokemon.clone() },
+            outcome: BattleRecordOutcome::Wild(summary.outcome.clone()),
+            turns: summary.turns,
+            started_at: summary.started_at,
+            duration_secs: now.saturating_sub(summary.started_at),
+            team: summary.player_team.clone(),
+        };
+        self.history_manager.record_in_background(summary.player_id.clone(), record);
+    }
+
+    fn on_pvp_battle_finished(&self, summary: &PvPBattleSummary) {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let username_of = |player_id: &str| {
+            summary.lobby.player_positions.get(player_id)
+                .map(|state| state.username.clone())
+                .unwrap_or_else(|| player_id.to_string())
+        };
+
+        let sides = [
+            (&summary.player1_id, &summary.player1_team, &summary.player1_outcome, &summary.player2_id, &summary.player2_team),
+            (&summary.player2_id, &summary.player2_team, &summary.player2_outcome, &summary.player1_id, &summary.player1_team),
+        ];
+        for (player_id, team, outcome, opponent_id, opponent_team) in sides {
+            let record = BattleRecord {
+                battle_id: summary.battle_id,
+                opponent: BattleOpponent::Trainer {
+                    player_id: opponent_id.clone(),
+                    username: username_of(opponent_id),
+                    team: opponent_team.clone(),
+                },
+                outcome: BattleRecordOutcome::Trainer(outcome.clone()),
+                turns: summary.turns,
+                started_at: summary.started_at,
+                duration_secs: now.saturating_sub(summary.started_at),
+                team: team.clone(),
+            };
+            self.history_manager.record_in_background(player_id.clone(), record);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_bounds_cover_one_page() {
+        assert_eq!(BattleHistoryManager::page_bounds(0, 20), Some((0, 19)));
+        assert_eq!(BattleHistoryManager::page_bounds(2, 20), Some((40, 59)));
+    }
+
+    #[test]
+    fn out_of_range_page_has_no_bounds() {
+        assert_eq!(BattleHistoryManager::page_bounds(usize::MAX, MAX_PAGE_SIZE), None);
+        assert_eq!(BattleHistoryManager::page_bounds(usize::MAX / MAX_PAGE_SIZE, MAX_PAGE_SIZE), None);
+        assert_eq!(BattleHistoryManager::page_bounds(isize::MAX as usize, 1), Some((isize::MAX, isize::MAX)));
+    }
+}
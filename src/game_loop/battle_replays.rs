@@ -0,0 +1,108 @@
+use std::sync::Arc;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::combat::listeners::{BattleOutcomeListener, PvPBattleSummary};
+use crate::combat::replay::{BattleReplay, ReplaySummary};
+
+// How long a flagged battle's replay is kept for review
+const REPLAY_TTL_SECS: u64 = 60 * 60 * 24 * 30;
+// Number of replays listed in the review index
+const MAX_INDEXED_REPLAYS: isize = 500;
+const INDEX_KEY: &str = "battle_replays";
+
+// Stores replays of flagged battles in Redis for admins to step through
+pub struct BattleReplayManager {
+    redis_client: redis::Client,
+}
+
+impl BattleReplayManager {
+    pub fn new(redis_client: redis::Client) -> Arc<Self> {
+        Arc::new(Self { redis_client })
+    }
+
+    fn replay_key(battle_id: Uuid) -> String {
+        format!("battle_replay:{}", battle_id)
+    }
+
+    async fn connection(&self) -> Result<redis::aio::Connection, String> {
+        self.redis_client
+            .get_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection error: {}", e))
+    }
+
+    pub async fn save(&self, replay: &BattleReplay) -> Result<(), String> {
+        let replay_json = serde_json::to_string(replay)
+            .map_err(|e| format!("Failed to serialize battle replay: {}", e))?;
+        let summary_json = serde_json::to_string(&replay.summary())
+            .map_err(|e| format!("Failed to serialize replay summary: {}", e))?;
+        let mut con = self.connection().await?;
+        redis::pipe()
+            .cmd("SET").arg(Self::replay_key(replay.battle_id)).arg(replay_json).arg("EX").arg(REPLAY_TTL_SECS).ignore()
+            .cmd("LPUSH").arg(INDEX_KEY).arg(summary_json).ignore()
+            .cmd("LTRIM").arg(INDEX_KEY).arg(0).arg(MAX_INDEXED_REPLAYS - 1).ignore()
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))
+    }
+
+    // Most recently flagged battles first
+    pub async fn list(&self) -> Result<Vec<ReplaySummary>, String> {
+        let mut con = self.connection().await?;
+        let saved: Vec<String> = redis::cmd("LRANGE")
+            .arg(INDEX_KEY)
+            .arg(0)
+            .arg(MAX_INDEXED_REPLAYS - 1)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        Ok(saved
+            .iter()
+            .filter_map(|json| match serde_json::from_str(json) {
+                Ok(summary) => Some(summary),
+                Err(e) => {
+                    error!("Failed to deserialize replay summary: {}", e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    pub async fn get(&self, battle_id: Uuid) -> Result<Option<BattleReplay>, String> {
+        let mut con = self.connection().await?;
+        let json: Option<String> = redis::cmd("GET")
+            .arg(Self::replay_key(battle_id))
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        json.map(|json| serde_json::from_str(&json).map_err(|e| format!("Failed to deserialize battle replay: {}", e)))
+            .transpose()
+    }
+}
+
+// Persists the replay of every PvP battle that finished flagged
+pub struct BattleReplayListener {
+    replay_manager: Arc<BattleReplayManager>,
+}
+
+impl BattleReplayListener {
+    pub fn new(replay_manager: Arc<BattleReplayManager>) -> Self {
+        Self { replay_manager }
+    }
+}
+
+impl BattleOutcomeListener for BattleReplayListener {
+    fn on_pvp_battle_finished(&self, summary: &PvPBattleSummary) {
+        let Some(replay) = summary.replay.clone() else {
+            return;
+        };
+        let replay_manager = self.replay_manager.clone();
+        tokio::spawn(async move {
+            match replay_manager.save(&replay).await {
+                Ok(()) => info!("Saved replay of flagged battle {} ({} turns, flags {:?})", replay.battle_id, replay.turns.len(), replay.flags),
+                Err(e) => error!("Failed to save replay of battle {}: {}", replay.battle_id, e),
+            }
+        });
+    }
+}
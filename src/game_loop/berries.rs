@@ -0,0 +1,309 @@
+use dashmap::DashMap;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::time::Duration;
+use tracing::{error, info};
+
+use crate::game_loop::inventory::InventoryManager;
+use crate::lobby::Lobby;
+use crate::models::ServerMessage;
+
+// How often the growth task checks plots
+const GROWTH_TICK_SECS: u64 = 30;
+
+// Berries that can be planted
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BerryType {
+    Oran,
+    Pecha,
+    Cheri,
+    Rawst,
+    Sitrus,
+}
+
+impl BerryType {
+    // Inventory item id of this berry
+    pub fn item_id(&self) -> &'static str {
+        match self {
+            BerryType::Oran => "oran_berry",
+            BerryType::Pecha => "pecha_berry",
+            BerryType::Cheri => "cheri_berry",
+            BerryType::Rawst => "rawst_berry",
+            BerryType::Sitrus => "sitrus_berry",
+        }
+    }
+
+    // Seconds spent in each growth stage
+    pub fn stage_duration_secs(&self) -> u64 {
+        match self {
+            BerryType::Oran | BerryType::Pecha | BerryType::Cheri | BerryType::Rawst => 15 * 60,
+            BerryType::Sitrus => 30 * 60,
+        }
+    }
+
+    // Minimum and maximum number of berries a ripe plant yields
+    pub fn yield_range(&self) -> (u32, u32) {
+        match self {
+            BerryType::Sitrus => (2, 3),
+            _ => (2, 5),
+        }
+    }
+}
+
+// Growth stages of a planted berry
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BerryStage {
+    Planted,
+    Sprouted,
+    Taller,
+    Flowering,
+    Ripe,
+}
+
+impl BerryStage {
+    fn next(&self) -> Option<BerryStage> {
+        match self {
+            BerryStage::Planted => Some(BerryStage::Sprouted),
+            BerryStage::Sprouted => Some(BerryStage::Taller),
+            BerryStage::Taller => Some(BerryStage::Flowering),
+            BerryStage::Flowering => Some(BerryStage::Ripe),
+            BerryStage::Ripe => None,
+        }
+    }
+}
+
+// A berry growing on a soil tile
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+pub struct BerryPlot {
+    pub x: u32,
+    pub y: u32,
+    pub berry: BerryType,
+    pub stage: BerryStage,
+    pub planted_by: String,
+    pub stage_changed_at: u64, // Unix timestamp of the last stage change
+}
+
+// Manages berry plots: planting, growth, harvesting and per-lobby persistence
+pub struct BerryManager {
+    redis_client: redis::Client,
+    inventory_manager: Arc<InventoryManager>,
+}
+
+impl BerryManager {
+    pub fn new(redis_client: redis::Client, inventory_manager: Arc<InventoryManager>) -> Arc<Self> {
+        Arc::new(Self {
+            redis_client,
+            inventory_manager,
+        })
+    }
+
+    fn plots_key(lobby_id: &str) -> String {
+        format!("berry_plots:{}", lobby_id)
+    }
+
+    fn plot_field(x: u32, y: u32) -> String {
+        format!("{},{}", x, y)
+    }
+
+    async fn connection(&self) -> Result<redis::aio::Connection, String> {
+        self.redis_client
+            .get_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection error: {}", e))
+    }
+
+    // Load the lobby's saved plots into memory
+    pub async fn load_lobby_plots(&self, lobby: &Lobby) -> Result<(), String> {
+        let mut con = self.connection().await?;
+        let saved: HashMap<String, String> = redis::cmd("HGETALL")
+            .arg(Self::plots_key(&lobby.id))
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+
+        for plot_json in saved.values() {
+            match serde_json::from_str::<BerryPlot>(plot_json) {
+                Ok(plot) => {
+                    lobby.berry_plots.insert((plot.x, plot.y), plot);
+                }
+                Err(e) => error!("Failed to deserialize berry plot in lobby {}: {}", lobby.id, e),
+            }
+        }
+
+        info!("Loaded {} berry plots for lobby {}", lobby.berry_plots.len(), lobby.id);
+        Ok(())
+    }
+
+    // Swap every plot in the lobby for the given ones, in memory and in Redis
+    pub async fn replace_lobby_plots(&self, lobby: &Lobby, plots: Vec<BerryPlot>) -> Result<(), String> {
+        let mut pipe = redis::pipe();
+        pipe.cmd("DEL").arg(Self::plots_key(&lobby.id)).ignore();
+        for plot in &plots {
+            let plot_json = serde_json::to_string(plot)
+                .map_err(|e| format!("Failed to serialize berry plot: {}", e))?;
+            pipe.cmd("HSET").arg(Self::plots_key(&lobby.id)).arg(Self::plot_field(plot.x, plot.y)).arg(plot_json).ignore();
+        }
+        let mut con = self.connection().await?;
+        pipe.query_async::<_, ()>(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+
+        lobby.berry_plots.clear();
+        for plot in plots {
+            lobby.berry_plots.insert((plot.x, plot.y), plot);
+        }
+        Ok(())
+    }
+
+    async fn save_plot(&self, lobby_id: &str, plot: &BerryPlot) -> Result<(), String> {
+        let plot_json = serde_json::to_string(plot)
+            .map_err(|e| format!("Failed to serialize berry plot: {}", e))?;
+        let mut con = self.connection().await?;
+        redis::cmd("HSET")
+            .arg(Self::plots_key(lobby_id))
+            .arg(Self::plot_field(plot.x, plot.y))
+            .arg(plot_json)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))
+    }
+
+    async fn delete_plot(&self, lobby_id: &str, x: u32, y: u32) -> Result<(), String> {
+        let mut con = self.connection().await?;
+        redis::cmd("HDEL")
+            .arg(Self::plots_key(lobby_id))
+            .arg(Self::plot_field(x, y))
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))
+    }
+
+    // Check the player is standing on or next to the tile
+    fn check_in_reach(lobby: &Lobby, player_id: &str, x: u32, y: u32) -> Result<(), String> {
+        let player = lobby.player_positions.get(player_id)
+            .ok_or_else(|| format!("Player {} not found in lobby", player_id))?;
+        if player.in_combat {
+            return Err("Cannot tend berries while in combat".to_string());
+        }
+        if player.x.abs_diff(x) > 1 || player.y.abs_diff(y) > 1 {
+            return Err("Too far away from the soil".to_string());
+        }
+        Ok(())
+    }
+
+    // Give back the berry taken for a planting that did not go through
+    async fn refund_seed(&self, player_id: &str, berry: BerryType) {
+        if let Err(e) = self.inventory_manager.add_item(player_id, berry.item_id(), 1).await {
+            error!("Failed to refund {:?} berry to player {}: {}", berry, player_id, e);
+        }
+    }
+
+    // Plant one of the player's berries on an empty soil tile
+    pub async fn plant(&self, lobby: &Lobby, player_id: &str, x: u32, y: u32, berry: BerryType) -> Result<BerryPlot, String> {
+        if !lobby.monster_manager.map_data.is_soil_tile(x, y) {
+            return Err("Berries can only be planted in soft soil".to_string());
+        }
+        Self::check_in_reach(lobby, player_id, x, y)?;
+        if lobby.berry_plots.contains_key(&(x, y)) {
+            return Err("Something is already planted here".to_string());
+        }
+        self.inventory_manager.remove_item(player_id, berry.item_id(), 1).await?;
+
+        let plot = BerryPlot {
+            x,
+            y,
+            berry,
+            stage: BerryStage::Planted,
+            planted_by: player_id.to_string(),
+            stage_changed_at: chrono::Utc::now().timestamp() as u64,
+        };
+
+        match lobby.berry_plots.entry((x, y)) {
+            dashmap::mapref::entry::Entry::Occupied(_) => {
+                self.refund_seed(player_id, berry).await;
+                return Err("Something is already planted here".to_string());
+            }
+            dashmap::mapref::entry::Entry::Vacant(entry) => {
+                entry.insert(plot.clone());
+            }
+        }
+
+        if let Err(e) = self.save_plot(&lobby.id, &plot).await {
+            lobby.berry_plots.remove(&(x, y));
+            self.refund_seed(player_id, berry).await;
+            return Err(e);
+        }
+
+        info!("Player {} planted {:?} at ({}, {}) in lobby {}", player_id, berry, x, y, lobby.id);
+        let _ = lobby.broadcast_except(&ServerMessage::BerryPlotUpdated { plot: plot.clone() }, &[]).await;
+        Ok(plot)
+    }
+
+    // Pick a ripe plant, adding its berries to the player's inventory
+    pub async fn harvest(&self, lobby: &Lobby, player_id: &str, x: u32, y: u32) -> Result<(BerryType, u32), String> {
+        Self::check_in_reach(lobby, player_id, x, y)?;
+
+        let plot = match lobby.berry_plots.remove_if(&(x, y), |_, plot| plot.stage == BerryStage::Ripe) {
+            Some((_, plot)) => plot,
+            None if lobby.berry_plots.contains_key(&(x, y)) => return Err("This berry is not ripe yet".to_string()),
+            None => return Err("Nothing is planted here".to_string()),
+        };
+
+        let (min_yield, max_yield) = plot.berry.yield_range();
+        let quantity = rand::thread_rng().gen_range(min_yield..=max_yield);
+
+        if let Err(e) = self.delete_plot(&lobby.id, x, y).await {
+            error!("Failed to delete harvested berry plot in lobby {}: {}", lobby.id, e);
+        }
+        self.inventory_manager.add_item(player_id, plot.berry.item_id(), quantity).await?;
+
+        info!("Player {} harvested {} {:?} berries at ({}, {}) in lobby {}", player_id, quantity, plot.berry, x, y, lobby.id);
+        let _ = lobby.broadcast_except(&ServerMessage::BerryPlotRemoved { x, y }, &[]).await;
+        Ok((plot.berry, quantity))
+    }
+
+    // Move plots whose stage timer has elapsed on to their next stage
+    pub async fn advance_growth(&self, lobby: &Lobby) {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let mut grown = Vec::new();
+
+        for mut entry in lobby.berry_plots.iter_mut() {
+            let plot = entry.value_mut();
+            if now < plot.stage_changed_at + plot.berry.stage_duration_secs() {
+                continue;
+            }
+            if let Some(next_stage) = plot.stage.next() {
+                plot.stage = next_stage;
+                plot.stage_changed_at = now;
+                grown.push(plot.clone());
+            }
+        }
+
+        for plot in grown {
+            if let Err(e) = self.save_plot(&lobby.id, &plot).await {
+                error!("Failed to save berry plot in lobby {}: {}", lobby.id, e);
+            }
+            let _ = lobby.broadcast_except(&ServerMessage::BerryPlotUpdated { plot }, &[]).await;
+        }
+    }
+}
+
+// Periodically grows berries in every lobby
+pub async fn run_berry_growth(
+    lobbies: Arc<DashMap<String, Arc<Lobby>>>,
+    berry_manager: Arc<BerryManager>,
+) {
+    info!("Starting berry growth task");
+    let mut interval = tokio::time::interval(Duration::from_secs(GROWTH_TICK_SECS));
+    loop {
+        interval.tick().await;
+        let lobbies: Vec<Arc<Lobby>> = lobbies.iter().map(|entry| entry.value().clone()).collect();
+        for lobby in lobbies {
+            berry_manager.advance_growth(&lobby).await;
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use dashmap::DashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+// Most players one player can block
+const MAX_BLOCKED_PLAYERS: usize = 200;
+
+// Per-player block lists. Blocking someone hides their emotes and battle chat and turns away their
+// challenges and trade requests, in both directions.
+pub struct BlockListManager {
+    redis_client: redis::Client,
+    // Player ID → blocked player IDs, cached while the player is online so checks stay off Redis
+    blocked: DashMap<String, HashSet<String>>,
+}
+
+impl BlockListManager {
+    pub fn new(redis_client: redis::Client) -> Arc<Self> {
+        Arc::new(Self {
+            redis_client,
+            blocked: DashMap::new(),
+        })
+    }
+
+    fn blocked_key(player_id: &str) -> String {
+        format!("blocked_players:{}", player_id)
+    }
+
+    async fn connection(&self) -> Result<redis::aio::Connection, String> {
+        self.redis_client
+            .get_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection error: {}", e))
+    }
+
+    // Get the players someone has blocked, loading them from Redis on first use
+    pub async fn blocked_players(&self, player_id: &str) -> Result<HashSet<String>, String> {
+        if let Some(blocked) = self.blocked.get(player_id) {
+            return Ok(blocked.clone());
+        }
+        let mut con = self.connection().await?;
+        let blocked: HashSet<String> = redis::cmd("SMEMBERS")
+            .arg(Self::blocked_key(player_id))
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        self.blocked.insert(player_id.to_string(), blocked.clone());
+        Ok(blocked)
+    }
+
+    pub async fn block(&self, player_id: &str, target_player_id: &str) -> Result<HashSet<String>, String> {
+        if player_id == target_player_id {
+            return Err("You cannot block yourself".to_string());
+        }
+        let mut blocked = self.blocked_players(player_id).await?;
+        if blocked.contains(target_player_id) {
+            return Ok(blocked);
+        }
+        if blocked.len() >= MAX_BLOCKED_PLAYERS {
+            return Err(format!("You can block at most {} players", MAX_BLOCKED_PLAYERS));
+        }
+        let mut con = self.connection().await?;
+        redis::cmd("SADD")
+            .arg(Self::blocked_key(player_id))
+            .arg(target_player_id)
+            .query_async::<_, ()>(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        blocked.insert(target_player_id.to_string());
+        self.blocked.insert(player_id.to_string(), blocked.clone());
+        Ok(blocked)
+    }
+
+    pub async fn unblock(&self, player_id: &str, target_player_id: &str) -> Result<HashSet<String>, String> {
+        let mut blocked = self.blocked_players(player_id).await?;
+        let mut con = self.connection().await?;
+        redis::cmd("SREM")
+            .arg(Self::blocked_key(player_id))
+            .arg(target_player_id)
+            .query_async::<_, ()>(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        blocked.remove(target_player_id);
+        self.blocked.insert(player_id.to_string(), blocked.clone());
+        Ok(blocked)
+    }
+
+    // Whether either player has blocked the other; only lists of online (cached) players are checked
+    pub fn is_blocked_between(&self, player_a: &str, player_b: &str) -> bool {
+        let blocks = |by: &str, other: &str| self.blocked.get(by).is_some_and(|blocked| blocked.contains(other));
+        blocks(player_a, player_b) || blocks(player_b, player_a)
+    }
+
+    // Drop cached data for a player who left
+    pub fn forget_player(&self, player_id: &str) {
+        self.blocked.remove(player_id);
+    }
+}
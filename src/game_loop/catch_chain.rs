@@ -0,0 +1,68 @@
+use dashmap::DashMap;
+use serde::Serialize;
+
+// Odds of a wild monster being shiny without any chain
+const BASE_SHINY_CHANCE: f64 = 1.0 / 4096.0;
+// Chain length past which captures stop improving the bonuses
+const MAX_CHAIN_BONUS_LENGTH: u32 = 10;
+// IV floor gained per capture in a chain
+const IV_FLOOR_PER_CAPTURE: u8 = 3;
+
+// A player's run of consecutive captures of one species
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct CatchChain {
+    pub template_id: u32,
+    pub length: u32,
+}
+
+// Bonuses a chain grants to new spawns of its species
+#[derive(Clone, Copy, Debug)]
+pub struct ChainBonus {
+    pub shiny_chance: f64,
+    pub iv_floor: u8,
+}
+
+impl ChainBonus {
+    fn for_length(length: u32) -> Self {
+        let length = length.min(MAX_CHAIN_BONUS_LENGTH);
+        Self {
+            shiny_chance: BASE_SHINY_CHANCE * (1 + length) as f64,
+            iv_floor: IV_FLOOR_PER_CAPTURE * length as u8,
+        }
+    }
+}
+
+// Catch chains of the players in a lobby; a chain only lives while its player stays in the lobby
+#[derive(Default)]
+pub struct CatchChains {
+    chains: DashMap<String, CatchChain>, // Player ID → current chain
+}
+
+impl CatchChains {
+    // Extend the player's chain, or start a new one if they caught a different species
+    pub fn record_capture(&self, player_id: &str, template_id: u32) -> CatchChain {
+        let mut chain = self.chains
+            .entry(player_id.to_string())
+            .or_insert(CatchChain { template_id, length: 0 });
+        if chain.template_id != template_id {
+            *chain = CatchChain { template_id, length: 0 };
+        }
+        chain.length += 1;
+        *chain
+    }
+
+    pub fn reset(&self, player_id: &str) {
+        self.chains.remove(player_id);
+    }
+
+    // Bonus for a new spawn of a species, taken from the longest chain on it in the lobby
+    pub fn bonus_for(&self, template_id: u32) -> ChainBonus {
+        let longest = self.chains
+            .iter()
+            .filter(|chain| chain.template_id == template_id)
+            .map(|chain| chain.length)
+            .max()
+            .unwrap_or(0);
+        ChainBonus::for_length(longest)
+    }
+}
@@ -0,0 +1,181 @@
+use serde_json::{json, Value};
+
+use crate::game_loop::pokemon_collection::{BOX_SIZE, MAX_POKEMONS};
+
+// Version written with every collection saved by this server.
+// Bump it and add a step to `migrate_collection` whenever the stored format changes.
+pub const COLLECTION_SCHEMA_VERSION: u32 = 4;
+
+// Collections saved before versioning existed have no schema_version field
+pub const LEGACY_SCHEMA_VERSION: u32 = 1;
+
+// Upgrade a stored collection one version at a time until it matches COLLECTION_SCHEMA_VERSION.
+// Returns the upgraded JSON and the version it was stored with.
+pub fn migrate_collection(mut collection: Value) -> Result<(Value, u32), String> {
+    let stored_version = match collection.get("schema_version") {
+        Some(version) => version.as_u64().ok_or("Invalid collection schema version")? as u32,
+        None => LEGACY_SCHEMA_VERSION,
+    };
+    if stored_version > COLLECTION_SCHEMA_VERSION {
+        return Err(format!(
+            "Collection schema version {} is newer than the supported version {}",
+            stored_version, COLLECTION_SCHEMA_VERSION
+        ));
+    }
+
+    let mut version = stored_version;
+    while version < COLLECTION_SCHEMA_VERSION {
+        match version {
+            1 => v1_to_v2(&mut collection)?,
+            2 => v2_to_v3(&mut collection)?,
+            3 => v3_to_v4(&mut collection)?,
+            _ => return Err(format!("No migration from collection schema version {}", version)),
+        }
+        version += 1;
+    }
+    collection["schema_version"] = json!(version);
+
+    Ok((collection, stored_version))
+}
+
+// v2 added PC boxes: pokemon owned outside the party used to be stored nowhere in particular,
+// so they are placed into boxes where the player can reach them again
+fn v1_to_v2(collection: &mut Value) -> Result<(), String> {
+    let object = collection.as_object_mut().ok_or("Collection is not an object")?;
+
+    let mut active: Vec<String> = object.get("active_pokemons")
+        .and_then(Value::as_array)
+        .map(|ids| ids.iter().filter_map(|id| id.as_str().map(str::to_string)).collect())
+        .unwrap_or_default();
+    let mut stored: Vec<String> = object.get("pokemons")
+        .and_then(Value::as_object)
+        .map(|pokemons| pokemons.keys().filter(|id| !active.contains(id)).cloned().collect())
+        .unwrap_or_default();
+    // Keep the box order stable between loads
+    stored.sort();
+
+    // Fill empty party slots first, the way captures do
+    let free_slots = MAX_POKEMONS.saturating_sub(active.len()).min(stored.len());
+    active.extend(stored.drain(..free_slots));
+
+    let boxes: Vec<Vec<String>> = stored.chunks(BOX_SIZE).map(|chunk| chunk.to_vec()).collect();
+    object.insert("active_pokemons".to_string(), json!(active));
+    object.insert("boxes".to_string(), json!(boxes));
+    Ok(())
+}
+
+// v3 added PP Ups to every known move
+fn v2_to_v3(collection: &mut Value) -> Result<(), String> {
+    let pokemons = match collection.get_mut("pokemons").and_then(Value::as_object_mut) {
+        Some(pokemons) => pokemons,
+        None => return Ok(()),
+    };
+    for pokemon in pokemons.values_mut() {
+        let moves = match pokemon.get_mut("moves").and_then(Value::as_array_mut) {
+            Some(moves) => moves,
+            None => continue,
+        };
+        for monster_move in moves.iter_mut().filter_map(Value::as_object_mut) {
+            monster_move.entry("pp_ups").or_insert(json!(0));
+        }
+    }
+    Ok(())
+}
+
+// v4 added the original trainer of every pokemon. Where they came from before is unknown, so
+// the player who has them now is taken to be their original trainer.
+fn v3_to_v4(collection: &mut Value) -> Result<(), String> {
+    let player_id = collection.get("player_id").cloned().unwrap_or(Value::Null);
+    let pokemons = match collection.get_mut("pokemons").and_then(Value::as_object_mut) {
+        Some(pokemons) => pokemons,
+        None => return Ok(()),
+    };
+    for pokemon in pokemons.values_mut().filter_map(Value::as_object_mut) {
+        pokemon.entry("original_trainer").or_insert(player_id.clone());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_loop::pokemon_collection::PlayerCollection;
+
+    fn fixture(version: u32) -> Value {
+        let blob = match version {
+            1 => include_str!("fixtures/collection_v1.json"),
+            2 => include_str!("fixtures/collection_v2.json"),
+            3 => include_str!("fixtures/collection_v3.json"),
+            4 => include_str!("fixtures/collection_v4.json"),
+            _ => panic!("no collection fixture for version {}", version),
+        };
+        serde_json::from_str(blob).expect("collection fixture is valid JSON")
+    }
+
+    // Migrate a stored blob and load it the way the collection manager does
+    fn load(blob: Value) -> (PlayerCollection, u32) {
+        let (migrated, stored_version) = migrate_collection(blob).expect("collection migrates");
+        let collection: PlayerCollection = serde_json::from_value(migrated).expect("migrated collection loads");
+        assert_eq!(collection.schema_version, COLLECTION_SCHEMA_VERSION);
+        (collection, stored_version)
+    }
+
+    fn assert_owned_by_player(collection: &PlayerCollection) {
+        for pokemon in collection.pokemons.values() {
+            assert_eq!(pokemon.original_trainer.as_deref(), Some(collection.player_id.as_str()));
+        }
+    }
+
+    #[test]
+    fn legacy_collection_without_version_fills_party_then_boxes() {
+        let (collection, stored_version) = load(fixture(1));
+        assert_eq!(stored_version, LEGACY_SCHEMA_VERSION);
+        assert_eq!(collection.active_pokemons, ["mon-3", "mon-0", "mon-1", "mon-2", "mon-4", "mon-5"]);
+        assert_eq!(collection.boxes, [["mon-6", "mon-7"]]);
+        assert!(collection.pokemons.values().flat_map(|pokemon| &pokemon.moves).all(|monster_move| monster_move.pp_ups == 0));
+        assert_owned_by_player(&collection);
+    }
+
+    #[test]
+    fn v2_collection_keeps_its_boxes_and_gains_pp_ups() {
+        let (collection, stored_version) = load(fixture(2));
+        assert_eq!(stored_version, 2);
+        assert_eq!(collection.active_pokemons, ["mon-0"]);
+        assert_eq!(collection.boxes, [["mon-1", "mon-2"]]);
+        assert!(collection.pokemons.values().flat_map(|pokemon| &pokemon.moves).all(|monster_move| monster_move.pp_ups == 0));
+        assert_owned_by_player(&collection);
+    }
+
+    #[test]
+    fn v3_collection_keeps_pp_ups_and_gains_original_trainers() {
+        let (collection, stored_version) = load(fixture(3));
+        assert_eq!(stored_version, 3);
+        assert!(collection.pokemons["mon-1"].moves.iter().all(|monster_move| monster_move.pp_ups == 1));
+        assert_owned_by_player(&collection);
+    }
+
+    #[test]
+    fn current_collection_is_left_as_is() {
+        let blob = fixture(COLLECTION_SCHEMA_VERSION);
+        let (migrated, stored_version) = migrate_collection(blob.clone()).expect("collection migrates");
+        assert_eq!(stored_version, COLLECTION_SCHEMA_VERSION);
+        assert_eq!(migrated, blob);
+        let (collection, _) = load(blob);
+        assert!(collection.pokemons["mon-1"].is_traded(&collection.player_id));
+        assert!(!collection.pokemons["mon-0"].is_traded(&collection.player_id));
+    }
+
+    #[test]
+    fn newer_collection_is_rejected() {
+        let mut blob = fixture(COLLECTION_SCHEMA_VERSION);
+        blob["schema_version"] = json!(COLLECTION_SCHEMA_VERSION + 1);
+        assert!(migrate_collection(blob).is_err());
+    }
+
+    #[test]
+    fn invalid_version_is_rejected() {
+        let mut blob = fixture(COLLECTION_SCHEMA_VERSION);
+        blob["schema_version"] = json!("four");
+        assert!(migrate_collection(blob).is_err());
+    }
+}
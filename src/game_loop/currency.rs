@@ -0,0 +1,223 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::combat::listeners::{BattleOutcomeListener, PvPBattleSummary};
+use crate::combat::state::PvPBattleOutcome;
+use crate::game_loop::audit_log::{AuditAction, AuditEntry, AuditLog};
+use crate::models::ServerMessage;
+
+// Money awarded per level of the strongest Pokemon on the defeated trainer's team
+const PVP_PAYOUT_PER_LEVEL: u64 = 20;
+// Number of transactions kept per player for auditing
+const MAX_LOGGED_TRANSACTIONS: isize = 200;
+
+// Atomically subtract ARGV[1] from the balance at KEYS[1], refusing to go negative
+const DEBIT_SCRIPT: &str = r#"
+local balance = tonumber(redis.call('GET', KEYS[1]) or '0')
+local amount = tonumber(ARGV[1])
+if balance < amount then
+    return -1
+end
+return redis.call('DECRBY', KEYS[1], amount)
+"#;
+
+// Why a player's balance changed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionReason {
+    BattlePayout,
+    ItemSold,
+    ShopPurchase,
+    NameRater,
+    MoveRelearner,
+    Trade,
+    MarketSale,
+    MarketPurchase,
+    AuctionBid,
+    AuctionRefund,
+}
+
+// A single logged balance change
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CurrencyTransaction {
+    pub delta: i64,
+    pub balance: u64,
+    pub reason: TransactionReason,
+    pub timestamp: u64,
+}
+
+// Manages player currency balances stored in Redis
+pub struct CurrencyManager {
+    redis_client: redis::Client,
+    audit_log: Arc<AuditLog>,
+}
+
+impl CurrencyManager {
+    pub fn new(redis_client: redis::Client, audit_log: Arc<AuditLog>) -> Arc<Self> {
+        Arc::new(Self { redis_client, audit_log })
+    }
+
+    fn balance_key(player_id: &str) -> String {
+        format!("currency:{}", player_id)
+    }
+
+    fn transactions_key(player_id: &str) -> String {
+        format!("currency_transactions:{}", player_id)
+    }
+
+    async fn connection(&self) -> Result<redis::aio::Connection, String> {
+        self.redis_client
+            .get_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection error: {}", e))
+    }
+
+    // Get a player's current balance (0 if they never earned anything)
+    pub async fn get_balance(&self, player_id: &str) -> Result<u64, String> {
+        let mut con = self.connection().await?;
+        let balance: Option<u64> = redis::cmd("GET")
+            .arg(Self::balance_key(player_id))
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        Ok(balance.unwrap_or(0))
+    }
+
+    // Add money to a player's balance, returning the new balance
+    pub async fn credit(&self, player_id: &str, amount: u64, reason: TransactionReason) -> Result<u64, String> {
+        let mut con = self.connection().await?;
+        let balance: u64 = redis::cmd("INCRBY")
+            .arg(Self::balance_key(player_id))
+            .arg(amount)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+
+        self.log_transaction(&mut con, player_id, amount as i64, balance, reason).await;
+        Ok(balance)
+    }
+
+    // Take money from a player's balance, failing without changes if they cannot afford it
+    pub async fn debit(&self, player_id: &str, amount: u64, reason: TransactionReason) -> Result<u64, String> {
+        let mut con = self.connection().await?;
+        let result: i64 = redis::Script::new(DEBIT_SCRIPT)
+            .key(Self::balance_key(player_id))
+            .arg(amount)
+            .invoke_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis script error: {}", e))?;
+
+        if result < 0 {
+            return Err(format!("Insufficient funds: {} required", amount));
+        }
+
+        let balance = result as u64;
+        self.log_transaction(&mut con, player_id, -(amount as i64), balance, reason).await;
+        Ok(balance)
+    }
+
+    // Get the most recent transactions for a player, newest first
+    pub async fn get_transactions(&self, player_id: &str, count: isize) -> Result<Vec<CurrencyTransaction>, String> {
+        let mut con = self.connection().await?;
+        let entries: Vec<String> = redis::cmd("LRANGE")
+            .arg(Self::transactions_key(player_id))
+            .arg(0)
+            .arg(count - 1)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+
+        Ok(entries
+            .iter()
+            .filter_map(|entry| serde_json::from_str(entry).ok())
+            .collect())
+    }
+
+    // Append a transaction to the player's capped audit log
+    async fn log_transaction(
+        &self,
+        con: &mut redis::aio::Connection,
+        player_id: &str,
+        delta: i64,
+        balance: u64,
+        reason: TransactionReason,
+    ) {
+        info!("Currency transaction for player {}: {:+} ({:?}), balance {}", player_id, delta, reason, balance);
+
+        let previous_balance = (balance as i64 - delta) as u64;
+        let audit_entry = AuditEntry::new(player_id, AuditAction::CurrencyChanged { player_id: player_id.to_string(), reason: reason.clone() })
+            .with_change(&previous_balance, &balance);
+        self.audit_log.record(audit_entry).await;
+
+        let transaction = CurrencyTransaction {
+            delta,
+            balance,
+            reason,
+            timestamp: chrono::Utc::now().timestamp() as u64,
+        };
+        let json = match serde_json::to_string(&transaction) {
+            Ok(json) => json,
+            Err(e) => {
+                error!("Failed to serialize currency transaction: {}", e);
+                return;
+            }
+        };
+
+        let key = Self::transactions_key(player_id);
+        let result: redis::RedisResult<()> = redis::pipe()
+            .cmd("LPUSH").arg(&key).arg(json).ignore()
+            .cmd("LTRIM").arg(&key).arg(0).arg(MAX_LOGGED_TRANSACTIONS - 1).ignore()
+            .query_async(con)
+            .await;
+        if let Err(e) = result {
+            error!("Failed to log currency transaction for player {}: {}", player_id, e);
+        }
+    }
+}
+
+// Pays out the winner of a trainer (PvP) battle
+pub struct BattlePayoutListener {
+    currency_manager: Arc<CurrencyManager>,
+}
+
+impl BattlePayoutListener {
+    pub fn new(currency_manager: Arc<CurrencyManager>) -> Self {
+        Self { currency_manager }
+    }
+}
+
+impl BattleOutcomeListener for BattlePayoutListener {
+    fn on_pvp_battle_finished(&self, summary: &PvPBattleSummary) {
+        let (winner_id, loser_team) = match (&summary.player1_outcome, &summary.player2_outcome) {
+            (PvPBattleOutcome::Victory | PvPBattleOutcome::OpponentDisconnected | PvPBattleOutcome::OpponentSurrendered, _) => {
+                (summary.player1_id.clone(), &summary.player2_team)
+            }
+            (_, PvPBattleOutcome::Victory | PvPBattleOutcome::OpponentDisconnected | PvPBattleOutcome::OpponentSurrendered) => {
+                (summary.player2_id.clone(), &summary.player1_team)
+            }
+            _ => return,
+        };
+
+        let highest_level = loser_team.iter().map(|p| p.level).max().unwrap_or(1) as u64;
+        let payout = PVP_PAYOUT_PER_LEVEL * highest_level;
+        let currency_manager = self.currency_manager.clone();
+        let lobby = summary.lobby.clone();
+
+        tokio::spawn(async move {
+            match currency_manager.credit(&winner_id, payout, TransactionReason::BattlePayout).await {
+                Ok(balance) => {
+                    let msg = ServerMessage::BalanceUpdate {
+                        balance,
+                        delta: payout as i64,
+                        reason: Some(TransactionReason::BattlePayout),
+                    };
+                    if let Err(e) = lobby.send_to_player(&winner_id, &msg).await {
+                        error!("Failed to send balance update to player {}: {}", winner_id, e);
+                    }
+                }
+                Err(e) => error!("Failed to pay out battle winnings to player {}: {}", winner_id, e),
+            }
+        });
+    }
+}
@@ -0,0 +1,75 @@
+use dashmap::DashMap;
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+
+use crate::lobby::Lobby;
+use crate::models::ServerMessage;
+
+// Emotes clients are allowed to send
+pub const EMOTE_CATALOG: [&str; 8] = [
+    "wave", "cheer", "laugh", "heart", "surprised", "sad", "angry", "thinking",
+];
+// Players within this many tiles see an emote
+const EMOTE_RADIUS: u32 = 10;
+// Minimum time between two emotes from the same player
+const EMOTE_COOLDOWN: Duration = Duration::from_millis(1500);
+
+// Validates, rate limits and delivers player emotes
+pub struct EmoteManager {
+    last_emote_at: DashMap<String, Instant>,
+}
+
+impl Default for EmoteManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EmoteManager {
+    pub fn new() -> Self {
+        Self {
+            last_emote_at: DashMap::new(),
+        }
+    }
+
+    // Show an emote to everyone near the player (including the player themself), except those `is_muted` filters out
+    pub async fn send_emote(&self, lobby: &Lobby, player_id: &str, emote_id: &str, is_muted: impl Fn(&str) -> bool) -> Result<(), String> {
+        if !EMOTE_CATALOG.contains(&emote_id) {
+            return Err(format!("Unknown emote {}", emote_id));
+        }
+
+        let now = Instant::now();
+        match self.last_emote_at.get(player_id) {
+            Some(last) if now.duration_since(*last) < EMOTE_COOLDOWN => {
+                return Err("You are sending emotes too quickly".to_string());
+            }
+            _ => {}
+        }
+        self.last_emote_at.insert(player_id.to_string(), now);
+
+        let (x, y) = match lobby.player_positions.get(player_id) {
+            Some(state) => (state.x, state.y),
+            None => return Err(format!("Player {} not found in lobby", player_id)),
+        };
+
+        let emote_msg = ServerMessage::PlayerEmote {
+            player_id: player_id.to_string(),
+            emote_id: emote_id.to_string(),
+        };
+        let mut recipients = lobby.players_near(x, y, EMOTE_RADIUS);
+        recipients.retain(|recipient| !is_muted(recipient));
+        for recipient in &recipients {
+            if let Err(e) = lobby.send_to_player(recipient, &emote_msg).await {
+                error!("Failed to send emote to player {}: {}", recipient, e);
+            }
+        }
+
+        info!("Player {} sent emote {} to {} players in lobby {}", player_id, emote_id, recipients.len(), lobby.id);
+        Ok(())
+    }
+
+    // Drop rate limit state for a player who left
+    pub fn forget_player(&self, player_id: &str) {
+        self.last_emote_at.remove(player_id);
+    }
+}
@@ -0,0 +1,102 @@
+use std::future::Future;
+use std::pin::Pin;
+use tokio::time::{Duration, Instant};
+use tracing::info;
+
+// A recurring world event: weather rotation, auctions, ranked seasons
+pub trait ScheduledEvent: Send {
+    fn name(&self) -> &'static str;
+    // Time between two runs
+    fn period(&self) -> Duration;
+    fn run(&mut self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>>;
+}
+
+// Runs every registered event on its own period from a single task.
+// Events run one after the other, so a slow event delays the others instead of overlapping them.
+#[derive(Default)]
+pub struct EventScheduler {
+    // Each event with the time it is next due
+    events: Vec<(Box<dyn ScheduledEvent>, Instant)>,
+}
+
+impl EventScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Add an event; it first runs as soon as the scheduler starts
+    pub fn add(&mut self, event: impl ScheduledEvent + 'static) {
+        self.events.push((Box::new(event), Instant::now()));
+    }
+
+    // Run the events due at `now` and return when the next one is due
+    async fn run_due(&mut self, now: Instant) -> Option<Instant> {
+        for (event, due_at) in self.events.iter_mut() {
+            if *due_at <= now {
+                event.run().await;
+                *due_at = now + event.period();
+            }
+        }
+        self.events.iter().map(|(_, due_at)| *due_at).min()
+    }
+
+    pub async fn run(mut self) {
+        let names: Vec<&str> = self.events.iter().map(|(event, _)| event.name()).collect();
+        info!("Starting event scheduler with {:?}", names);
+        while let Some(next_due_at) = self.run_due(Instant::now()).await {
+            tokio::time::sleep_until(next_due_at).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct Counter {
+        period: Duration,
+        runs: Arc<AtomicUsize>,
+    }
+
+    impl ScheduledEvent for Counter {
+        fn name(&self) -> &'static str {
+            "counter"
+        }
+
+        fn period(&self) -> Duration {
+            self.period
+        }
+
+        fn run(&mut self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+            Box::pin(async move {
+                self.runs.fetch_add(1, Ordering::SeqCst);
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn events_run_on_their_own_periods() {
+        let (fast_runs, slow_runs) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let mut scheduler = EventScheduler::new();
+        scheduler.add(Counter { period: Duration::from_secs(5), runs: fast_runs.clone() });
+        scheduler.add(Counter { period: Duration::from_secs(60), runs: slow_runs.clone() });
+        let start = Instant::now();
+
+        // Both run right away, then the fast event is the next one due
+        assert_eq!(scheduler.run_due(start).await, Some(start + Duration::from_secs(5)));
+        assert_eq!((fast_runs.load(Ordering::SeqCst), slow_runs.load(Ordering::SeqCst)), (1, 1));
+
+        // Nothing is due yet
+        scheduler.run_due(start + Duration::from_secs(1)).await;
+        assert_eq!((fast_runs.load(Ordering::SeqCst), slow_runs.load(Ordering::SeqCst)), (1, 1));
+
+        let later = start + Duration::from_secs(5);
+        assert_eq!(scheduler.run_due(later).await, Some(later + Duration::from_secs(5)));
+        assert_eq!((fast_runs.load(Ordering::SeqCst), slow_runs.load(Ordering::SeqCst)), (2, 1));
+
+        scheduler.run_due(start + Duration::from_secs(60)).await;
+        assert_eq!((fast_runs.load(Ordering::SeqCst), slow_runs.load(Ordering::SeqCst)), (3, 2));
+    }
+}
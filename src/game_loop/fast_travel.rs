@@ -0,0 +1,165 @@
+use dashmap::DashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::game_loop::inventory::InventoryManager;
+use crate::game_loop::pokemon_collection::PokemonCollectionManager;
+use crate::lobby::Lobby;
+use crate::models::PlayerState;
+use crate::monsters::monster::PokemonType;
+use crate::monsters::monster_manager::Landmark;
+use crate::redis_manager;
+
+// Item that allows fast travel without a Flying-type Pokemon
+pub const TRAVEL_ITEM_ID: &str = "travel_wing";
+// How close (in tiles) a player must get to a landmark to unlock it
+const LANDMARK_VISIT_RADIUS: u32 = 1;
+
+// Tracks the landmarks each player has visited and moves players between them
+pub struct FastTravelManager {
+    redis_client: redis::Client,
+    pokemon_collection_manager: Arc<PokemonCollectionManager>,
+    inventory_manager: Arc<InventoryManager>,
+    // Player ID → visited landmark IDs, cached so movement checks stay off Redis
+    visited: DashMap<String, HashSet<String>>,
+}
+
+impl FastTravelManager {
+    pub fn new(
+        redis_client: redis::Client,
+        pokemon_collection_manager: Arc<PokemonCollectionManager>,
+        inventory_manager: Arc<InventoryManager>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            redis_client,
+            pokemon_collection_manager,
+            inventory_manager,
+            visited: DashMap::new(),
+        })
+    }
+
+    fn visited_key(player_id: &str) -> String {
+        format!("visited_landmarks:{}", player_id)
+    }
+
+    // Get the landmarks a player has visited, loading them from Redis on first use
+    pub async fn visited_landmarks(&self, player_id: &str) -> Result<HashSet<String>, String> {
+        if let Some(visited) = self.visited.get(player_id) {
+            return Ok(visited.clone());
+        }
+
+        let mut con = self.redis_client
+            .get_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection error: {}", e))?;
+        let visited: HashSet<String> = redis::cmd("SMEMBERS")
+            .arg(Self::visited_key(player_id))
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+
+        self.visited.insert(player_id.to_string(), visited.clone());
+        Ok(visited)
+    }
+
+    // Unlock a landmark the player is standing next to, returning it if it was newly visited
+    pub async fn visit_nearby_landmark(&self, lobby: &Lobby, player_id: &str, x: u32, y: u32) -> Option<Landmark> {
+        let landmark = lobby.monster_manager.map_data.landmarks.values()
+            .find(|landmark| {
+                landmark.tile_x.abs_diff(x) <= LANDMARK_VISIT_RADIUS
+                    && landmark.tile_y.abs_diff(y) <= LANDMARK_VISIT_RADIUS
+            })?
+            .clone();
+
+        let visited = match self.visited_landmarks(player_id).await {
+            Ok(visited) => visited,
+            Err(e) => {
+                error!("Failed to load visited landmarks for player {}: {}", player_id, e);
+                return None;
+            }
+        };
+        if visited.contains(&landmark.id) {
+            return None;
+        }
+
+        let mut con = match self.redis_client.get_async_connection().await {
+            Ok(con) => con,
+            Err(e) => {
+                error!("Redis connection error while saving landmark visit: {}", e);
+                return None;
+            }
+        };
+        let result: redis::RedisResult<()> = redis::cmd("SADD")
+            .arg(Self::visited_key(player_id))
+            .arg(&landmark.id)
+            .query_async(&mut con)
+            .await;
+        if let Err(e) = result {
+            error!("Failed to save landmark visit for player {}: {}", player_id, e);
+            return None;
+        }
+
+        if let Some(mut visited) = self.visited.get_mut(player_id) {
+            visited.insert(landmark.id.clone());
+        }
+        info!("Player {} discovered landmark {}", player_id, landmark.id);
+        Some(landmark)
+    }
+
+    // Whether the player has a Flying-type Pokemon on their team or a travel item
+    async fn can_fly(&self, player_id: &str) -> Result<bool, String> {
+        let pokemons = self.pokemon_collection_manager.get_active_pokemons(player_id).await?;
+        if pokemons.iter().any(|p| p.types.contains(&PokemonType::Flying)) {
+            return Ok(true);
+        }
+        Ok(self.inventory_manager.get_item_count(player_id, TRAVEL_ITEM_ID).await? > 0)
+    }
+
+    // Teleport the player to a visited landmark, returning their new state
+    pub async fn fast_travel(&self, lobby: &Lobby, player_id: &str, landmark_id: &str) -> Result<PlayerState, String> {
+        let current_state = lobby.player_positions.get(player_id)
+            .map(|entry| entry.value().clone())
+            .ok_or_else(|| format!("Player {} not found in lobby", player_id))?;
+        if current_state.in_combat {
+            return Err("Cannot fast travel while in combat".to_string());
+        }
+
+        let landmark = lobby.monster_manager.map_data.landmarks.get(landmark_id)
+            .ok_or_else(|| format!("Unknown landmark {}", landmark_id))?;
+        if !self.visited_landmarks(player_id).await?.contains(landmark_id) {
+            return Err(format!("You have not visited {} yet", landmark.name));
+        }
+        if !self.can_fly(player_id).await? {
+            return Err("You need a Flying-type Pokemon or a travel item to fast travel".to_string());
+        }
+
+        let new_state = PlayerState {
+            x: landmark.tile_x,
+            y: landmark.tile_y,
+            direction: "down".to_string(),
+            ..current_state
+        };
+
+        // Update the lobby only if the player did not start a battle meanwhile
+        match lobby.player_positions.get_mut(player_id) {
+            Some(mut state) if !state.in_combat => *state = new_state.clone(),
+            _ => return Err("Cannot fast travel while in combat".to_string()),
+        }
+
+        if let Ok(mut redis_conn) = self.redis_client.get_async_connection().await {
+            let player_json = serde_json::to_string(&new_state).unwrap();
+            if let Err(e) = redis_manager::store_player_state(&mut redis_conn, &lobby.id, player_id, &player_json).await {
+                error!("Failed to persist player state after fast travel: {}", e);
+            }
+        }
+
+        info!("Player {} fast traveled to {} in lobby {}", player_id, landmark_id, lobby.id);
+        Ok(new_state)
+    }
+
+    // Drop cached data for a player who left
+    pub fn forget_player(&self, player_id: &str) {
+        self.visited.remove(player_id);
+    }
+}
@@ -0,0 +1,127 @@
+use dashmap::DashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+// Most players one player can add as friends
+const MAX_FRIENDS: usize = 200;
+
+// Per-player friend lists. Adding someone is one-sided; two players are friends once both added each other.
+pub struct FriendListManager {
+    redis_client: redis::Client,
+    // Player ID → player IDs they added, cached while the player is online
+    added: DashMap<String, HashSet<String>>,
+}
+
+// A player's friends and the players they added who have not added them back yet
+pub struct FriendList {
+    pub friends: Vec<String>,
+    pub pending: Vec<String>,
+}
+
+impl FriendListManager {
+    pub fn new(redis_client: redis::Client) -> Arc<Self> {
+        Arc::new(Self {
+            redis_client,
+            added: DashMap::new(),
+        })
+    }
+
+    fn friends_key(player_id: &str) -> String {
+        format!("friends:{}", player_id)
+    }
+
+    async fn connection(&self) -> Result<redis::aio::Connection, String> {
+        self.redis_client
+            .get_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection error: {}", e))
+    }
+
+    // Get the players someone has added, loading them from Redis on first use
+    async fn added_players(&self, player_id: &str) -> Result<HashSet<String>, String> {
+        if let Some(added) = self.added.get(player_id) {
+            return Ok(added.clone());
+        }
+        let mut con = self.connection().await?;
+        let added: HashSet<String> = redis::cmd("SMEMBERS")
+            .arg(Self::friends_key(player_id))
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        self.added.insert(player_id.to_string(), added.clone());
+        Ok(added)
+    }
+
+    // Whether `player_id` added `other_id`; offline players are looked up without caching them
+    async fn has_added(&self, player_id: &str, other_id: &str) -> Result<bool, String> {
+        if let Some(added) = self.added.get(player_id) {
+            return Ok(added.contains(other_id));
+        }
+        let mut con = self.connection().await?;
+        redis::cmd("SISMEMBER")
+            .arg(Self::friends_key(player_id))
+            .arg(other_id)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))
+    }
+
+    // Whether both players added each other
+    pub async fn are_friends(&self, player_a: &str, player_b: &str) -> Result<bool, String> {
+        Ok(self.has_added(player_a, player_b).await? && self.has_added(player_b, player_a).await?)
+    }
+
+    pub async fn friend_list(&self, player_id: &str) -> Result<FriendList, String> {
+        let mut list = FriendList { friends: Vec::new(), pending: Vec::new() };
+        for other_id in self.added_players(player_id).await? {
+            if self.has_added(&other_id, player_id).await? {
+                list.friends.push(other_id);
+            } else {
+                list.pending.push(other_id);
+            }
+        }
+        Ok(list)
+    }
+
+    pub async fn add_friend(&self, player_id: &str, target_player_id: &str) -> Result<FriendList, String> {
+        if player_id == target_player_id {
+            return Err("You cannot add yourself as a friend".to_string());
+        }
+        let mut added = self.added_players(player_id).await?;
+        if added.contains(target_player_id) {
+            return self.friend_list(player_id).await;
+        }
+        if added.len() >= MAX_FRIENDS {
+            return Err(format!("You can add at most {} friends", MAX_FRIENDS));
+        }
+        let mut con = self.connection().await?;
+        redis::cmd("SADD")
+            .arg(Self::friends_key(player_id))
+            .arg(target_player_id)
+            .query_async::<_, ()>(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        added.insert(target_player_id.to_string());
+        self.added.insert(player_id.to_string(), added);
+        self.friend_list(player_id).await
+    }
+
+    pub async fn remove_friend(&self, player_id: &str, target_player_id: &str) -> Result<FriendList, String> {
+        let mut added = self.added_players(player_id).await?;
+        let mut con = self.connection().await?;
+        redis::cmd("SREM")
+            .arg(Self::friends_key(player_id))
+            .arg(target_player_id)
+            .query_async::<_, ()>(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        added.remove(target_player_id);
+        self.added.insert(player_id.to_string(), added);
+        self.friend_list(player_id).await
+    }
+
+    // Drop cached data for a player who left
+    pub fn forget_player(&self, player_id: &str) {
+        self.added.remove(player_id);
+    }
+}
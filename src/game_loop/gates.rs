@@ -0,0 +1,276 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::game_loop::badges::BadgeManager;
+use crate::game_loop::inventory::InventoryManager;
+use crate::lobby::Lobby;
+use crate::monsters::monster_manager::{GateRequirement, MapGate};
+
+// Remembers which gates each player has cleared, so a cut tree or opened door stays that way
+// for them. Lobbies keep a copy of the cleared gates of their players for movement checks.
+pub struct GateManager {
+    redis_client: redis::Client,
+    inventory_manager: Arc<InventoryManager>,
+    badge_manager: Arc<BadgeManager>,
+}
+
+impl GateManager {
+    pub fn new(redis_client: redis::Client, inventory_manager: Arc<InventoryManager>, badge_manager: Arc<BadgeManager>) -> Arc<Self> {
+        Arc::new(Self {
+            redis_client,
+            inventory_manager,
+            badge_manager,
+        })
+    }
+
+    fn cleared_key(player_id: &str, map_id: &str) -> String {
+        format!("cleared_gates:{}:{}", player_id, map_id)
+    }
+
+    async fn connection(&self) -> Result<redis::aio::Connection, String> {
+        self.redis_client
+            .get_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection error: {}", e))
+    }
+
+    // Load the gates a joining player has cleared on the lobby's map into the lobby
+    pub async fn load_cleared_gates(&self, lobby: &Lobby, player_id: &str) -> Result<HashSet<String>, String> {
+        load_cleared_gates(self, lobby, player_id).await
+    }
+
+    // Clear a gate the player is standing next to using the key item or badge it needs. Key
+    // items are not used up.
+    pub async fn clear_gate(&self, lobby: &Lobby, player_id: &str, gate_id: &str) -> Result<MapGate, String> {
+        clear_gate(self, lobby, player_id, gate_id).await
+    }
+}
+
+// Where cleared gates are remembered and the key items and badges gates ask for are looked up.
+// Gate logic only goes through this, so it can be tested without Redis.
+trait GateStore {
+    async fn cleared_gates(&self, player_id: &str, map_id: &str) -> Result<HashSet<String>, String>;
+    async fn mark_cleared(&self, player_id: &str, map_id: &str, gate_id: &str) -> Result<(), String>;
+    async fn has_key_item(&self, player_id: &str, item_id: &str) -> Result<bool, String>;
+    async fn has_badge(&self, player_id: &str, badge_id: &str) -> Result<bool, String>;
+    fn badge_name(&self, badge_id: &str) -> String;
+}
+
+impl GateStore for GateManager {
+    async fn cleared_gates(&self, player_id: &str, map_id: &str) -> Result<HashSet<String>, String> {
+        let mut con = self.connection().await?;
+        redis::cmd("SMEMBERS")
+            .arg(Self::cleared_key(player_id, map_id))
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))
+    }
+
+    async fn mark_cleared(&self, player_id: &str, map_id: &str, gate_id: &str) -> Result<(), String> {
+        let mut con = self.connection().await?;
+        redis::cmd("SADD")
+            .arg(Self::cleared_key(player_id, map_id))
+            .arg(gate_id)
+            .query_async::<_, ()>(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))
+    }
+
+    async fn has_key_item(&self, player_id: &str, item_id: &str) -> Result<bool, String> {
+        Ok(self.inventory_manager.get_item_count(player_id, item_id).await? > 0)
+    }
+
+    async fn has_badge(&self, player_id: &str, badge_id: &str) -> Result<bool, String> {
+        self.badge_manager.has_badge(player_id, badge_id).await
+    }
+
+    fn badge_name(&self, badge_id: &str) -> String {
+        self.badge_manager.badge_name(badge_id)
+    }
+}
+
+async fn load_cleared_gates(store: &impl GateStore, lobby: &Lobby, player_id: &str) -> Result<HashSet<String>, String> {
+    let cleared = store.cleared_gates(player_id, &lobby.map_id).await?;
+    lobby.cleared_gates.insert(player_id.to_string(), cleared.clone());
+    Ok(cleared)
+}
+
+// Whether the player meets the gate's requirement
+async fn meets_requirement(store: &impl GateStore, player_id: &str, requirement: &GateRequirement) -> Result<(), String> {
+    match requirement {
+        GateRequirement::KeyItem(item_id) => {
+            if !store.has_key_item(player_id, item_id).await? {
+                return Err(format!("You need {} to get through here", item_id));
+            }
+            Ok(())
+        }
+        GateRequirement::Badge(badge_id) => {
+            if !store.has_badge(player_id, badge_id).await? {
+                return Err(format!("You need the {} to get through here", store.badge_name(badge_id)));
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn clear_gate(store: &impl GateStore, lobby: &Lobby, player_id: &str, gate_id: &str) -> Result<MapGate, String> {
+    let gate = lobby.monster_manager.map_data.gates.get(gate_id)
+        .ok_or_else(|| format!("Unknown gate {}", gate_id))?;
+    if lobby.cleared_gates.get(player_id).is_some_and(|cleared| cleared.contains(gate_id)) {
+        return Err("You already cleared this".to_string());
+    }
+    {
+        let player = lobby.player_positions.get(player_id)
+            .ok_or_else(|| format!("Player {} not found in lobby", player_id))?;
+        if player.in_combat {
+            return Err("Cannot use key items while in combat".to_string());
+        }
+        if !gate.is_within_reach(player.x, player.y) {
+            return Err("Too far away".to_string());
+        }
+    }
+    meets_requirement(store, player_id, &gate.requirement).await?;
+
+    store.mark_cleared(player_id, &lobby.map_id, gate_id).await?;
+    lobby.cleared_gates.entry(player_id.to_string()).or_default().insert(gate_id.to_string());
+
+    info!("Player {} cleared gate {} in lobby {}", player_id, gate_id, lobby.id);
+    Ok(gate.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_loop::player_movement::PlayerMovementManager;
+    use crate::models::PlayerState;
+    use crate::monsters::monster_manager::MapData;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    // Cleared gates, key items and badges in memory
+    #[derive(Default)]
+    struct MemoryStore {
+        cleared: Mutex<HashMap<(String, String), HashSet<String>>>,
+        key_items: HashSet<(String, String)>,
+        badges: HashSet<(String, String)>,
+    }
+
+    impl GateStore for MemoryStore {
+        async fn cleared_gates(&self, player_id: &str, map_id: &str) -> Result<HashSet<String>, String> {
+            Ok(self.cleared.lock().unwrap().get(&(player_id.to_string(), map_id.to_string())).cloned().unwrap_or_default())
+        }
+
+        async fn mark_cleared(&self, player_id: &str, map_id: &str, gate_id: &str) -> Result<(), String> {
+            self.cleared.lock().unwrap().entry((player_id.to_string(), map_id.to_string())).or_default().insert(gate_id.to_string());
+            Ok(())
+        }
+
+        async fn has_key_item(&self, player_id: &str, item_id: &str) -> Result<bool, String> {
+            Ok(self.key_items.contains(&(player_id.to_string(), item_id.to_string())))
+        }
+
+        async fn has_badge(&self, player_id: &str, badge_id: &str) -> Result<bool, String> {
+            Ok(self.badges.contains(&(player_id.to_string(), badge_id.to_string())))
+        }
+
+        fn badge_name(&self, badge_id: &str) -> String {
+            format!("{} badge", badge_id)
+        }
+    }
+
+    // A tree on (2, 1) that needs hm_cut and a door on (2, 3) that needs the venom badge
+    fn lobby() -> Lobby {
+        let mut map_data = MapData::for_tests(5, 5, &[]);
+        for (id, requirement, tile_y) in [
+            ("tree", GateRequirement::KeyItem("hm_cut".to_string()), 1),
+            ("door", GateRequirement::Badge("venom".to_string()), 3),
+        ] {
+            map_data.gates.insert(id.to_string(), MapGate { id: id.to_string(), requirement, tile_x: 2, tile_y, width: 1, height: 1 });
+        }
+        Lobby::for_tests(map_data)
+    }
+
+    fn place_player(lobby: &Lobby, player_id: &str, (x, y): (u32, u32)) -> PlayerState {
+        let state = PlayerState { id: player_id.to_string(), username: player_id.to_string(), x, y, direction: "down".to_string(), in_combat: false, afk: false };
+        lobby.player_positions.insert(player_id.to_string(), state.clone());
+        state
+    }
+
+    fn can_step(lobby: &Lobby, player_id: &str, from: (u32, u32), to: (u32, u32)) -> bool {
+        let state = place_player(lobby, player_id, from);
+        PlayerMovementManager::new().validate_movement(player_id, &state, to.0, to.1, lobby, false).is_some()
+    }
+
+    fn store_holding(key_items: &[(&str, &str)], badges: &[(&str, &str)]) -> MemoryStore {
+        let owned = |pairs: &[(&str, &str)]| pairs.iter().map(|(a, b)| (a.to_string(), b.to_string())).collect();
+        MemoryStore { key_items: owned(key_items), badges: owned(badges), ..MemoryStore::default() }
+    }
+
+    #[tokio::test]
+    async fn gates_block_movement_until_the_player_clears_them() {
+        let lobby = lobby();
+        let store = store_holding(&[("ash", "hm_cut")], &[]);
+        assert!(!can_step(&lobby, "ash", (1, 1), (2, 1)));
+
+        clear_gate(&store, &lobby, "ash", "tree").await.unwrap();
+        assert!(can_step(&lobby, "ash", (1, 1), (2, 1)));
+        // Other gates and other players are not affected
+        assert!(!can_step(&lobby, "ash", (1, 3), (2, 3)));
+        assert!(!can_step(&lobby, "misty", (1, 1), (2, 1)));
+    }
+
+    #[tokio::test]
+    async fn key_item_gates_need_the_item() {
+        let lobby = lobby();
+        let store = store_holding(&[("ash", "hm_cut")], &[]);
+        place_player(&lobby, "misty", (1, 1));
+        assert_eq!(clear_gate(&store, &lobby, "misty", "tree").await.unwrap_err(), "You need hm_cut to get through here");
+
+        place_player(&lobby, "ash", (1, 1));
+        assert_eq!(clear_gate(&store, &lobby, "ash", "tree").await.unwrap().id, "tree");
+    }
+
+    #[tokio::test]
+    async fn badge_gates_need_the_badge() {
+        let lobby = lobby();
+        let store = store_holding(&[("ash", "hm_cut")], &[("misty", "venom")]);
+        place_player(&lobby, "ash", (2, 2));
+        assert_eq!(clear_gate(&store, &lobby, "ash", "door").await.unwrap_err(), "You need the venom badge to get through here");
+
+        place_player(&lobby, "misty", (2, 2));
+        assert!(clear_gate(&store, &lobby, "misty", "door").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn gates_can_only_be_cleared_from_next_to_them() {
+        let lobby = lobby();
+        let store = store_holding(&[("ash", "hm_cut")], &[]);
+        place_player(&lobby, "ash", (0, 1));
+        assert_eq!(clear_gate(&store, &lobby, "ash", "tree").await.unwrap_err(), "Too far away");
+        assert!(clear_gate(&store, &lobby, "ash", "gate").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn cleared_gates_are_kept_per_player() {
+        let lobby = lobby();
+        let store = store_holding(&[("ash", "hm_cut")], &[]);
+        place_player(&lobby, "ash", (1, 1));
+        clear_gate(&store, &lobby, "ash", "tree").await.unwrap();
+        assert_eq!(clear_gate(&store, &lobby, "ash", "tree").await.unwrap_err(), "You already cleared this");
+
+        // Rejoining loads the gates cleared earlier
+        let rejoined = self::lobby();
+        assert!(!can_step(&rejoined, "ash", (1, 1), (2, 1)));
+        assert_eq!(load_cleared_gates(&store, &rejoined, "ash").await.unwrap(), HashSet::from(["tree".to_string()]));
+        assert!(can_step(&rejoined, "ash", (1, 1), (2, 1)));
+        assert!(load_cleared_gates(&store, &rejoined, "misty").await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn map1_has_gates() {
+        let map_data = MapData::new("map1", "resources/map1.json").unwrap();
+        assert!(map_data.gates.values().any(|gate| matches!(gate.requirement, GateRequirement::KeyItem(_))));
+        assert!(map_data.gates.values().any(|gate| matches!(gate.requirement, GateRequirement::Badge(_))));
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::monsters::PokemonType;
+
+// Crystals are named after the type they power up, e.g. `fire_crystal`
+const CRYSTAL_SUFFIX: &str = "_crystal";
+
+// Type a crystal item powers up, or None if the item is not a crystal
+pub fn crystal_type(item_id: &str) -> Option<PokemonType> {
+    let type_name = item_id.strip_suffix(CRYSTAL_SUFFIX)?;
+    serde_json::from_value(serde_json::Value::String(type_name.to_string())).ok()
+}
+
+// Atomically remove ARGV[1] of item KEYS[2] from the inventory hash KEYS[1], refusing to go negative
+const REMOVE_ITEM_SCRIPT: &str = r#"
+local count = tonumber(redis.call('HGET', KEYS[1], KEYS[2]) or '0')
+local amount = tonumber(ARGV[1])
+if count < amount then
+    return -1
+end
+local remaining = redis.call('HINCRBY', KEYS[1], KEYS[2], -amount)
+if remaining == 0 then
+    redis.call('HDEL', KEYS[1], KEYS[2])
+end
+return remaining
+"#;
+
+// Manages player item inventories stored in Redis (item id -> quantity)
+pub struct InventoryManager {
+    redis_client: redis::Client,
+}
+
+impl InventoryManager {
+    pub fn new(redis_client: redis::Client) -> Arc<Self> {
+        Arc::new(Self { redis_client })
+    }
+
+    fn inventory_key(player_id: &str) -> String {
+        format!("inventory:{}", player_id)
+    }
+
+    fn equipped_crystal_key(player_id: &str) -> String {
+        format!("equipped_crystal:{}", player_id)
+    }
+
+    async fn connection(&self) -> Result<redis::aio::Connection, String> {
+        self.redis_client
+            .get_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection error: {}", e))
+    }
+
+    // Get every item the player holds
+    pub async fn get_inventory(&self, player_id: &str) -> Result<HashMap<String, u32>, String> {
+        let mut con = self.connection().await?;
+        redis::cmd("HGETALL")
+            .arg(Self::inventory_key(player_id))
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))
+    }
+
+    // Get how many of an item the player holds
+    pub async fn get_item_count(&self, player_id: &str, item_id: &str) -> Result<u32, String> {
+        let mut con = self.connection().await?;
+        let count: Option<u32> = redis::cmd("HGET")
+            .arg(Self::inventory_key(player_id))
+            .arg(item_id)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        Ok(count.unwrap_or(0))
+    }
+
+    // Give the player some of an item, returning the new quantity
+    pub async fn add_item(&self, player_id: &str, item_id: &str, quantity: u32) -> Result<u32, String> {
+        let mut con = self.connection().await?;
+        let count: u32 = redis::cmd("HINCRBY")
+            .arg(Self::inventory_key(player_id))
+            .arg(item_id)
+            .arg(quantity)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+
+        info!("Added {} x{} to player {}'s inventory (now {})", item_id, quantity, player_id, count);
+        Ok(count)
+    }
+
+    // Take some of an item from the player, failing without changes if they do not have enough
+    pub async fn remove_item(&self, player_id: &str, item_id: &str, quantity: u32) -> Result<u32, String> {
+        let mut con = self.connection().await?;
+        let remaining: i64 = redis::Script::new(REMOVE_ITEM_SCRIPT)
+            .key(Self::inventory_key(player_id))
+            .key(item_id)
+            .arg(quantity)
+            .invoke_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis script error: {}", e))?;
+
+        if remaining < 0 {
+            return Err(format!("Not enough {} in inventory", item_id));
+        }
+
+        info!("Removed {} x{} from player {}'s inventory (now {})", item_id, quantity, player_id, remaining);
+        Ok(remaining as u32)
+    }
+
+    // Equip a crystal the player holds, or unequip with None
+    pub async fn equip_crystal(&self, player_id: &str, item_id: Option<&str>) -> Result<(), String> {
+        let mut con = self.connection().await?;
+        let Some(item_id) = item_id else {
+            return redis::cmd("DEL")
+                .arg(Self::equipped_crystal_key(player_id))
+                .query_async(&mut con)
+                .await
+                .map_err(|e| format!("Redis query error: {}", e));
+        };
+        if crystal_type(item_id).is_none() {
+            return Err(format!("{} is not a crystal", item_id));
+        }
+        if self.get_item_count(player_id, item_id).await? == 0 {
+            return Err(format!("No {} in inventory", item_id));
+        }
+        redis::cmd("SET")
+            .arg(Self::equipped_crystal_key(player_id))
+            .arg(item_id)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))
+    }
+
+    // The crystal the player has equipped, as long as they still hold one
+    pub async fn get_equipped_crystal(&self, player_id: &str) -> Result<Option<String>, String> {
+        let mut con = self.connection().await?;
+        let item_id: Option<String> = redis::cmd("GET")
+            .arg(Self::equipped_crystal_key(player_id))
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        match item_id {
+            Some(item_id) if self.get_item_count(player_id, &item_id).await? > 0 => Ok(Some(item_id)),
+            _ => Ok(None),
+        }
+    }
+}
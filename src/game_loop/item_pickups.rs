@@ -0,0 +1,237 @@
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::time::Duration;
+use tracing::info;
+
+use crate::game_loop::inventory::InventoryManager;
+use crate::lobby::Lobby;
+use crate::models::ServerMessage;
+use crate::monsters::monster_manager::ItemSpawn;
+
+// How often the respawn task checks for items due back
+const RESPAWN_TICK_SECS: u64 = 5;
+// Tiles away from a pickup a player may stand and still reach it
+const PICKUP_RANGE_TILES: u32 = 1;
+
+// An item lying on the map that any player in the lobby can pick up
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+pub struct ItemPickup {
+    pub spawn_id: String,
+    pub item_id: String,
+    pub quantity: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl From<&ItemSpawn> for ItemPickup {
+    fn from(spawn: &ItemSpawn) -> Self {
+        ItemPickup {
+            spawn_id: spawn.id.clone(),
+            item_id: spawn.item_id.clone(),
+            quantity: spawn.quantity,
+            x: spawn.tile_x,
+            y: spawn.tile_y,
+        }
+    }
+}
+
+// Item spawns of a lobby that were picked up and are waiting to come back
+#[derive(Default)]
+pub struct ItemPickups {
+    taken: DashMap<String, i64>, // Item spawn ID → Unix timestamp (seconds) when it respawns
+}
+
+impl ItemPickups {
+    // Items currently lying on the lobby's map
+    pub fn visible(&self, lobby: &Lobby) -> Vec<ItemPickup> {
+        let mut pickups: Vec<ItemPickup> = lobby.monster_manager.map_data.item_spawns.values()
+            .filter(|spawn| !self.taken.contains_key(&spawn.id))
+            .map(ItemPickup::from)
+            .collect();
+        pickups.sort_by(|a, b| a.spawn_id.cmp(&b.spawn_id));
+        pickups
+    }
+
+    // Pick up the item at `spawn_id`, adding it to the player's inventory. Only one player can
+    // take an item; it comes back for everyone after the spawn's respawn time.
+    pub async fn pick_up(&self, lobby: &Lobby, inventory_manager: &InventoryManager, player_id: &str, spawn_id: &str) -> Result<ItemPickup, String> {
+        let (spawn, respawn_at) = self.claim(lobby, player_id, spawn_id)?;
+
+        if let Err(e) = inventory_manager.add_item(player_id, &spawn.item_id, spawn.quantity).await {
+            self.taken.remove(spawn_id);
+            return Err(e);
+        }
+
+        info!("Player {} picked up {} x{} at ({}, {}) in lobby {}", player_id, spawn.item_id, spawn.quantity, spawn.tile_x, spawn.tile_y, lobby.id);
+        let _ = lobby.broadcast_except(&ServerMessage::ItemPickedUp {
+            spawn_id: spawn_id.to_string(),
+            player_id: player_id.to_string(),
+            respawn_at,
+        }, &[]).await;
+        Ok(ItemPickup::from(spawn))
+    }
+
+    // Mark the item at `spawn_id` as taken by a player in reach of it, returning the spawn and
+    // when it comes back
+    fn claim<'a>(&self, lobby: &'a Lobby, player_id: &str, spawn_id: &str) -> Result<(&'a ItemSpawn, i64), String> {
+        let spawn = lobby.monster_manager.map_data.item_spawns.get(spawn_id)
+            .ok_or_else(|| format!("Unknown item spawn {}", spawn_id))?;
+        {
+            let player = lobby.player_positions.get(player_id)
+                .ok_or_else(|| format!("Player {} not found in lobby", player_id))?;
+            if player.in_combat {
+                return Err("Cannot pick up items while in combat".to_string());
+            }
+            if player.x.abs_diff(spawn.tile_x) > PICKUP_RANGE_TILES || player.y.abs_diff(spawn.tile_y) > PICKUP_RANGE_TILES {
+                return Err("Too far away from the item".to_string());
+            }
+        }
+
+        let respawn_at = chrono::Utc::now().timestamp() + spawn.respawn_secs as i64;
+        match self.taken.entry(spawn_id.to_string()) {
+            dashmap::mapref::entry::Entry::Occupied(_) => Err("Someone already picked this item up".to_string()),
+            dashmap::mapref::entry::Entry::Vacant(entry) => {
+                entry.insert(respawn_at);
+                Ok((spawn, respawn_at))
+            }
+        }
+    }
+
+    // Put back items whose respawn time has passed, telling the lobby about each
+    pub async fn respawn_due(&self, lobby: &Lobby) {
+        let now = chrono::Utc::now().timestamp();
+        let mut respawned = Vec::new();
+        self.taken.retain(|spawn_id, respawn_at| {
+            let due = *respawn_at <= now;
+            if due {
+                respawned.push(spawn_id.clone());
+            }
+            !due
+        });
+
+        for spawn_id in respawned {
+            if let Some(spawn) = lobby.monster_manager.map_data.item_spawns.get(&spawn_id) {
+                let _ = lobby.broadcast_except(&ServerMessage::ItemRespawned { pickup: ItemPickup::from(spawn) }, &[]).await;
+            }
+        }
+    }
+}
+
+// Periodically brings picked-up items back in every lobby
+pub async fn run_item_respawns(lobbies: Arc<DashMap<String, Arc<Lobby>>>) {
+    info!("Starting item respawn task");
+    let mut interval = tokio::time::interval(Duration::from_secs(RESPAWN_TICK_SECS));
+    loop {
+        interval.tick().await;
+        let lobbies: Vec<Arc<Lobby>> = lobbies.iter().map(|entry| entry.value().clone()).collect();
+        for lobby in lobbies {
+            lobby.item_pickups.respawn_due(&lobby).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PlayerState;
+    use crate::monsters::monster_manager::MapData;
+
+    const POTION: (u32, u32) = (5, 5);
+
+    // Lobby with a potion that respawns after `respawn_secs` and an instant one at (1, 1)
+    fn lobby(respawn_secs: u64) -> Lobby {
+        let mut map_data = MapData::for_tests(10, 10, &[]);
+        for (id, (tile_x, tile_y), respawn_secs) in [("potion", POTION, respawn_secs), ("instant", (1, 1), 0)] {
+            map_data.item_spawns.insert(id.to_string(), ItemSpawn {
+                id: id.to_string(),
+                item_id: "potion".to_string(),
+                quantity: 1,
+                tile_x,
+                tile_y,
+                respawn_secs,
+            });
+        }
+        Lobby::for_tests(map_data)
+    }
+
+    fn place_player(lobby: &Lobby, player_id: &str, (x, y): (u32, u32)) {
+        lobby.player_positions.insert(player_id.to_string(), PlayerState {
+            id: player_id.to_string(),
+            username: player_id.to_string(),
+            x,
+            y,
+            direction: "down".to_string(),
+            in_combat: false,
+            afk: false,
+        });
+    }
+
+    fn visible_ids(lobby: &Lobby) -> Vec<String> {
+        lobby.item_pickups.visible(lobby).into_iter().map(|pickup| pickup.spawn_id).collect()
+    }
+
+    #[test]
+    fn items_are_only_reachable_from_neighbouring_tiles() {
+        let lobby = lobby(600);
+        place_player(&lobby, "far", (POTION.0 + 2, POTION.1));
+        assert_eq!(lobby.item_pickups.claim(&lobby, "far", "potion").unwrap_err(), "Too far away from the item");
+
+        place_player(&lobby, "near", (POTION.0 + 1, POTION.1 + 1));
+        assert!(lobby.item_pickups.claim(&lobby, "near", "potion").is_ok());
+    }
+
+    #[test]
+    fn items_cannot_be_picked_up_in_combat_or_from_unknown_spawns() {
+        let lobby = lobby(600);
+        place_player(&lobby, "player", POTION);
+        lobby.player_positions.get_mut("player").unwrap().in_combat = true;
+        assert!(lobby.item_pickups.claim(&lobby, "player", "potion").is_err());
+        assert!(lobby.item_pickups.claim(&lobby, "player", "missing").is_err());
+        assert_eq!(visible_ids(&lobby), ["instant", "potion"]);
+    }
+
+    #[test]
+    fn an_item_is_only_picked_up_once() {
+        let lobby = lobby(600);
+        place_player(&lobby, "first", POTION);
+        place_player(&lobby, "second", POTION);
+
+        lobby.item_pickups.claim(&lobby, "first", "potion").unwrap();
+        assert_eq!(lobby.item_pickups.claim(&lobby, "second", "potion").unwrap_err(), "Someone already picked this item up");
+        assert_eq!(visible_ids(&lobby), ["instant"]);
+    }
+
+    #[tokio::test]
+    async fn an_item_goes_back_when_the_inventory_cannot_take_it() {
+        let lobby = lobby(600);
+        place_player(&lobby, "player", POTION);
+        // Nothing listens on port 1, so adding to the inventory fails
+        let inventory_manager = InventoryManager::new(redis::Client::open("redis://127.0.0.1:1/").expect("valid redis URL"));
+
+        assert!(lobby.item_pickups.pick_up(&lobby, &inventory_manager, "player", "potion").await.is_err());
+        assert_eq!(visible_ids(&lobby), ["instant", "potion"]);
+    }
+
+    #[tokio::test]
+    async fn items_respawn_once_their_time_has_passed() {
+        let lobby = lobby(600);
+        place_player(&lobby, "player", (2, 2));
+        lobby.item_pickups.claim(&lobby, "player", "instant").unwrap();
+        place_player(&lobby, "player", POTION);
+        lobby.item_pickups.claim(&lobby, "player", "potion").unwrap();
+        assert!(visible_ids(&lobby).is_empty());
+
+        lobby.item_pickups.respawn_due(&lobby).await;
+        assert_eq!(visible_ids(&lobby), ["instant"]);
+    }
+
+    #[test]
+    fn map1_has_item_spawns_on_open_tiles() {
+        let map_data = MapData::new("map1", "resources/map1.json").unwrap();
+        assert!(!map_data.item_spawns.is_empty());
+        for spawn in map_data.item_spawns.values() {
+            assert!(map_data.is_valid_position(spawn.tile_x, spawn.tile_y), "item spawn {} is blocked", spawn.id);
+        }
+    }
+}
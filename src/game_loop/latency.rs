@@ -0,0 +1,75 @@
+use dashmap::DashMap;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::time::Duration;
+use tracing::info;
+
+use crate::lobby::Lobby;
+
+// Weight of the newest sample in a player's moving average
+const AVERAGE_WEIGHT: f64 = 0.2;
+// Samples kept per player for percentiles
+const MAX_RECENT_SAMPLES: usize = 20;
+// Reported round trips above this are treated as bogus and ignored
+pub const MAX_REPORTED_RTT_MS: u32 = 10_000;
+const REPORT_INTERVAL_SECS: u64 = 60;
+
+struct PlayerLatency {
+    average_ms: f64,
+    recent: VecDeque<u32>,
+}
+
+// Round-trip times of the players in a lobby, as measured by their clients with ping/pong
+#[derive(Default)]
+pub struct LatencyTracker {
+    players: DashMap<String, PlayerLatency>,
+}
+
+impl LatencyTracker {
+    // Record a round trip and return the player's new moving average
+    pub fn record(&self, player_id: &str, rtt_ms: u32) -> u32 {
+        let mut latency = self.players.entry(player_id.to_string()).or_insert_with(|| PlayerLatency {
+            average_ms: rtt_ms as f64,
+            recent: VecDeque::with_capacity(MAX_RECENT_SAMPLES),
+        });
+        latency.average_ms += AVERAGE_WEIGHT * (rtt_ms as f64 - latency.average_ms);
+        if latency.recent.len() == MAX_RECENT_SAMPLES {
+            latency.recent.pop_front();
+        }
+        latency.recent.push_back(rtt_ms);
+        latency.average_ms.round() as u32
+    }
+
+    // Moving average round trip of a player, if their client has reported any
+    pub fn average_ms(&self, player_id: &str) -> Option<u32> {
+        self.players.get(player_id).map(|latency| latency.average_ms.round() as u32)
+    }
+
+    pub fn remove(&self, player_id: &str) {
+        self.players.remove(player_id);
+    }
+
+    // 95th percentile of the recent round trips of everyone in the lobby
+    pub fn p95_ms(&self) -> Option<u32> {
+        let mut samples: Vec<u32> = self.players.iter().flat_map(|latency| latency.recent.clone()).collect();
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_unstable();
+        let index = (samples.len() * 95).div_ceil(100) - 1;
+        Some(samples[index])
+    }
+}
+
+// Periodically log each lobby's p95 latency
+pub async fn run_latency_report(lobbies: Arc<DashMap<String, Arc<Lobby>>>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(REPORT_INTERVAL_SECS));
+    loop {
+        interval.tick().await;
+        for lobby in lobbies.iter() {
+            if let Some(p95) = lobby.latency.p95_ms() {
+                info!("Lobby {} latency p95: {} ms across {} players", lobby.id, p95, lobby.latency.players.len());
+            }
+        }
+    }
+}
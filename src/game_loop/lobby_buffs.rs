@@ -0,0 +1,90 @@
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+
+// Items any player can use to buff their whole lobby: (item ID, buff name, spawn rate multiplier, seconds)
+const BUFF_ITEMS: &[(&str, &str, f32, u64)] = &[
+    ("incense", "Incense", 1.5, 300),
+    ("super_incense", "Super Incense", 2.0, 300),
+];
+// Combined spawn rate multiplier of every active buff is capped at this
+pub const MAX_SPAWN_RATE_MULTIPLIER: f32 = 4.0;
+// Longest a buff can run, however often it is extended
+pub const MAX_BUFF_DURATION_SECS: u64 = 3600;
+
+// A temporary boost to spawns in one lobby, from an item a player used or a live event
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+pub struct LobbyBuff {
+    // Item ID or event name; using the same buff again extends it rather than stacking
+    pub id: String,
+    pub name: String,
+    // Spawn points are picked this many times as often and refill this many times as fast
+    pub spawn_rate_multiplier: f32,
+    // Player who used the item, None for events
+    pub activated_by: Option<String>,
+    // Unix timestamp (seconds) when the buff wears off
+    pub expires_at: i64,
+    // Seconds left when this was sent
+    #[serde(default)]
+    pub remaining_secs: u64,
+}
+
+// Buff an item gives when used, None for items that don't buff the lobby
+pub fn buff_for_item(item_id: &str) -> Option<(&'static str, f32, u64)> {
+    BUFF_ITEMS.iter()
+        .find(|(id, ..)| *id == item_id)
+        .map(|(_, name, multiplier, duration)| (*name, *multiplier, *duration))
+}
+
+// Buffs running in a lobby
+#[derive(Default)]
+pub struct LobbyBuffs {
+    buffs: DashMap<String, LobbyBuff>, // Buff ID → buff
+}
+
+impl LobbyBuffs {
+    // Start a buff, or add `duration_secs` to it if it is already running
+    pub fn activate(&self, id: &str, name: &str, spawn_rate_multiplier: f32, duration_secs: u64, activated_by: Option<String>) -> LobbyBuff {
+        let now = chrono::Utc::now().timestamp();
+        let mut buff = self.buffs.entry(id.to_string()).or_insert_with(|| LobbyBuff {
+            id: id.to_string(),
+            name: name.to_string(),
+            spawn_rate_multiplier,
+            activated_by: None,
+            expires_at: now,
+            remaining_secs: 0,
+        });
+        buff.name = name.to_string();
+        buff.spawn_rate_multiplier = spawn_rate_multiplier;
+        buff.activated_by = activated_by;
+        buff.expires_at = (buff.expires_at.max(now) + duration_secs as i64).min(now + MAX_BUFF_DURATION_SECS as i64);
+        buff.remaining_secs = (buff.expires_at - now) as u64;
+        buff.clone()
+    }
+
+    // Drop buffs that wore off. Returns true if any did.
+    pub fn prune_expired(&self) -> bool {
+        let now = chrono::Utc::now().timestamp();
+        let before = self.buffs.len();
+        self.buffs.retain(|_, buff| buff.expires_at > now);
+        self.buffs.len() != before
+    }
+
+    // Buffs still running, soonest to wear off first
+    pub fn active(&self) -> Vec<LobbyBuff> {
+        let now = chrono::Utc::now().timestamp();
+        let mut buffs: Vec<LobbyBuff> = self.buffs.iter()
+            .filter(|buff| buff.expires_at > now)
+            .map(|buff| LobbyBuff { remaining_secs: (buff.expires_at - now) as u64, ..buff.clone() })
+            .collect();
+        buffs.sort_by(|a, b| a.expires_at.cmp(&b.expires_at).then_with(|| a.id.cmp(&b.id)));
+        buffs
+    }
+
+    // Combined multiplier the spawner applies, 1.0 without buffs
+    pub fn spawn_rate_multiplier(&self) -> f32 {
+        self.active().iter()
+            .map(|buff| buff.spawn_rate_multiplier.max(0.0))
+            .product::<f32>()
+            .min(MAX_SPAWN_RATE_MULTIPLIER)
+    }
+}
@@ -0,0 +1,52 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use crate::combat::state::{PvPBattleOutcome, WildBattleOutcome};
+
+// Events kept per lobby; older ones are dropped first
+const MAX_LOBBY_EVENTS: usize = 500;
+
+// Something worth knowing about when debugging what happened in a lobby
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LobbyEventKind {
+    PlayerJoined { player_id: String, username: String },
+    // `reason` is "disconnected" or "inactive"
+    PlayerLeft { player_id: String, reason: &'static str },
+    MonsterSpawned { instance_id: String, template_id: u32, level: u32, spawn_point_id: String },
+    WildBattleStarted { battle_id: Uuid, player_id: String, monster_instance_id: String },
+    WildBattleEnded { battle_id: Uuid, player_id: String, outcome: WildBattleOutcome },
+    PvPBattleStarted { battle_id: Uuid, player1_id: String, player2_id: String },
+    PvPBattleEnded { battle_id: Uuid, player1_id: String, player2_id: String, player1_outcome: PvPBattleOutcome },
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct LobbyEvent {
+    // Unix time in milliseconds
+    pub at: i64,
+    #[serde(flatten)]
+    pub kind: LobbyEventKind,
+}
+
+// Ring buffer of the most recent events of a lobby, served to admins
+#[derive(Default)]
+pub struct LobbyEventLog {
+    events: Mutex<VecDeque<LobbyEvent>>,
+}
+
+impl LobbyEventLog {
+    pub fn record(&self, kind: LobbyEventKind) {
+        let mut events = self.events.lock().unwrap();
+        if events.len() == MAX_LOBBY_EVENTS {
+            events.pop_front();
+        }
+        events.push_back(LobbyEvent { at: chrono::Utc::now().timestamp_millis(), kind });
+    }
+
+    // Recorded events, oldest first
+    pub fn recent(&self) -> Vec<LobbyEvent> {
+        self.events.lock().unwrap().iter().cloned().collect()
+    }
+}
@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::game_loop::berries::{BerryManager, BerryPlot};
+use crate::game_loop::monster_persistence::{collect_monsters, place_monsters};
+use crate::game_loop::weather::OverworldWeather;
+use crate::lobby::Lobby;
+use crate::models::ServerMessage;
+use crate::monsters::Monster;
+
+const INDEX_KEY: &str = "lobby_snapshots";
+
+// World state of a lobby at one moment, without its players
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LobbySnapshot {
+    pub name: String,
+    pub lobby_id: String,
+    pub map_id: String,
+    pub taken_at: u64,
+    pub weather: OverworldWeather,
+    pub monsters: Vec<Monster>,
+    pub monsters_by_spawn_point: HashMap<String, Vec<String>>, // Spawn point ID → Monster IDs
+    pub berry_plots: Vec<BerryPlot>,
+}
+
+// What a restore changed in the lobby
+#[derive(Serialize, Debug)]
+pub struct SnapshotRestoreSummary {
+    pub monsters_removed: usize,
+    pub monsters_restored: usize,
+    // Monsters left alone because a battle with them was in progress
+    pub monsters_in_combat: usize,
+    pub berry_plots: usize,
+}
+
+// Named lobby snapshots kept in Redis so admins can stage events and reproduce bugs
+pub struct LobbySnapshotManager {
+    redis_client: redis::Client,
+}
+
+impl LobbySnapshotManager {
+    pub fn new(redis_client: redis::Client) -> Arc<Self> {
+        Arc::new(Self { redis_client })
+    }
+
+    fn snapshot_key(name: &str) -> String {
+        format!("lobby_snapshot:{}", name)
+    }
+
+    async fn connection(&self) -> Result<redis::aio::Connection, String> {
+        self.redis_client
+            .get_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection error: {}", e))
+    }
+
+    // Capture the lobby's monsters, berry plots and weather
+    pub async fn capture(lobby: &Lobby, name: &str) -> LobbySnapshot {
+        let (monsters, monsters_by_spawn_point) = collect_monsters(lobby).await;
+        LobbySnapshot {
+            name: name.to_string(),
+            lobby_id: lobby.id.clone(),
+            map_id: lobby.map_id.clone(),
+            taken_at: chrono::Utc::now().timestamp() as u64,
+            weather: lobby.weather(),
+            monsters,
+            monsters_by_spawn_point,
+            berry_plots: lobby.berry_plots.iter().map(|entry| entry.value().clone()).collect(),
+        }
+    }
+
+    // Store a snapshot under its name, replacing any older snapshot with that name
+    pub async fn save(&self, snapshot: &LobbySnapshot) -> Result<(), String> {
+        let snapshot_json = serde_json::to_string(snapshot)
+            .map_err(|e| format!("Failed to serialize lobby snapshot: {}", e))?;
+        let mut con = self.connection().await?;
+        redis::pipe()
+            .cmd("SET").arg(Self::snapshot_key(&snapshot.name)).arg(snapshot_json).ignore()
+            .cmd("SADD").arg(INDEX_KEY).arg(&snapshot.name).ignore()
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))
+    }
+
+    pub async fn get(&self, name: &str) -> Result<Option<LobbySnapshot>, String> {
+        let mut con = self.connection().await?;
+        let snapshot_json: Option<String> = redis::cmd("GET")
+            .arg(Self::snapshot_key(name))
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        snapshot_json
+            .map(|json| serde_json::from_str(&json).map_err(|e| format!("Failed to parse lobby snapshot {}: {}", name, e)))
+            .transpose()
+    }
+
+    // Names of all stored snapshots, sorted
+    pub async fn list(&self) -> Result<Vec<String>, String> {
+        let mut con = self.connection().await?;
+        let mut names: Vec<String> = redis::cmd("SMEMBERS")
+            .arg(INDEX_KEY)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        names.sort();
+        Ok(names)
+    }
+
+    // Replace the lobby's monsters, berry plots and weather with the snapshot's and tell its players.
+    // Players, and monsters currently in a battle, are left untouched.
+    pub async fn restore(lobby: &Arc<Lobby>, snapshot: LobbySnapshot, berry_manager: &BerryManager) -> Result<SnapshotRestoreSummary, String> {
+        if snapshot.map_id != lobby.map_id {
+            return Err(format!("Snapshot {} is of map {}, but lobby {} uses map {}", snapshot.name, snapshot.map_id, lobby.id, lobby.map_id));
+        }
+
+        berry_manager.replace_lobby_plots(lobby, snapshot.berry_plots).await?;
+        let berry_plots = lobby.berry_plots.len();
+        let plots = lobby.berry_plots.iter().map(|entry| entry.value().clone()).collect();
+        let _ = lobby.broadcast_except(&ServerMessage::BerryPlots { plots }, &[]).await;
+
+        *lobby.weather.write().unwrap() = snapshot.weather;
+        let _ = lobby.broadcast_except(&ServerMessage::WeatherChanged { weather: snapshot.weather }, &[]).await;
+
+        let monster_ids: Vec<String> = lobby.active_monsters.iter().map(|entry| entry.key().clone()).collect();
+        let mut monsters_removed = 0;
+        let mut monsters_in_combat = 0;
+        for monster_id in monster_ids {
+            let monster_mutex = match lobby.active_monsters.get(&monster_id) {
+                Some(entry) => entry.value().clone(),
+                None => continue,
+            };
+            if monster_mutex.lock().await.in_combat {
+                monsters_in_combat += 1;
+                continue;
+            }
+            if lobby.monster_manager.despawn_monster(&monster_id, lobby).await.is_some() {
+                monsters_removed += 1;
+                let _ = lobby.broadcast_except(&ServerMessage::MonsterDespawned { instance_id: monster_id }, &[]).await;
+            }
+        }
+
+        let placed = place_monsters(lobby, snapshot.monsters, snapshot.monsters_by_spawn_point);
+        for monster in &placed {
+            let _ = lobby.broadcast_except(&ServerMessage::MonsterSpawned { monster: monster.to_display() }, &[]).await;
+        }
+        if monsters_in_combat > 0 {
+            warn!("Kept {} monsters in battle while restoring snapshot {} into lobby {}", monsters_in_combat, snapshot.name, lobby.id);
+        }
+        info!("Restored snapshot {} into lobby {}: {} monsters, {} berry plots", snapshot.name, lobby.id, placed.len(), berry_plots);
+
+        Ok(SnapshotRestoreSummary {
+            monsters_removed,
+            monsters_restored: placed.len(),
+            monsters_in_combat,
+            berry_plots,
+        })
+    }
+}
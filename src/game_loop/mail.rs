@@ -0,0 +1,341 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::game_loop::friends::FriendListManager;
+use crate::game_loop::inventory::InventoryManager;
+
+// Maximum number of mails a mailbox holds before new mail is refused
+const MAX_MAILBOX_SIZE: usize = 50;
+// Maximum number of attachments per mail
+const MAX_ATTACHMENTS: usize = 5;
+const MAX_SUBJECT_LENGTH: usize = 64;
+const MAX_BODY_LENGTH: usize = 1000;
+// Sender ID used for mail sent by the server itself
+pub const SYSTEM_SENDER_ID: &str = "system";
+
+// Items attached to a mail, held by the mailbox until the recipient claims them
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+pub struct MailAttachment {
+    pub item_id: String,
+    pub quantity: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+pub struct Mail {
+    pub mail_id: Uuid,
+    pub sender_id: String,
+    pub sender_username: String,
+    pub subject: String,
+    pub body: String,
+    pub attachments: Vec<MailAttachment>,
+    pub sent_at: u64,
+    pub read: bool,
+    // Filled in when the mailbox is read; true once the attachments have been claimed
+    #[serde(default)]
+    pub claimed: bool,
+}
+
+// Redis-backed mailboxes that work whether or not the recipient is online
+pub struct MailManager {
+    redis_client: redis::Client,
+    inventory_manager: Arc<InventoryManager>,
+    friend_list_manager: Arc<FriendListManager>,
+    // Held while attachments are claimed so the same mail cannot be claimed twice
+    claim_lock: Mutex<()>,
+}
+
+impl MailManager {
+    pub fn new(
+        redis_client: redis::Client,
+        inventory_manager: Arc<InventoryManager>,
+        friend_list_manager: Arc<FriendListManager>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            redis_client,
+            inventory_manager,
+            friend_list_manager,
+            claim_lock: Mutex::new(()),
+        })
+    }
+
+    async fn connection(&self) -> Result<redis::aio::Connection, String> {
+        self.redis_client
+            .get_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection error: {}", e))
+    }
+
+    // Mail ID → mail JSON
+    fn mailbox_key(player_id: &str) -> String {
+        format!("mailbox:{}", player_id)
+    }
+
+    // Mail ID → unclaimed attachments JSON
+    fn attachments_key(player_id: &str) -> String {
+        format!("mail_attachments:{}", player_id)
+    }
+
+    // All mail for a player, newest first
+    pub async fn get_mailbox(&self, player_id: &str) -> Result<Vec<Mail>, String> {
+        let mut con = self.connection().await?;
+        let (saved, unclaimed): (HashMap<String, String>, Vec<String>) = redis::pipe()
+            .cmd("HGETALL").arg(Self::mailbox_key(player_id))
+            .cmd("HKEYS").arg(Self::attachments_key(player_id))
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+
+        let mut mails: Vec<Mail> = saved
+            .values()
+            .filter_map(|json| match serde_json::from_str::<Mail>(json) {
+                Ok(mut mail) => {
+                    mail.claimed = !unclaimed.contains(&mail.mail_id.to_string());
+                    Some(mail)
+                }
+                Err(e) => {
+                    error!("Failed to deserialize mail: {}", e);
+                    None
+                }
+            })
+            .collect();
+        mails.sort_by_key(|mail| std::cmp::Reverse(mail.sent_at));
+        Ok(mails)
+    }
+
+    pub async fn unread_count(&self, player_id: &str) -> Result<usize, String> {
+        Ok(self.get_mailbox(player_id).await?.iter().filter(|mail| !mail.read).count())
+    }
+
+    async fn get_mail(&self, player_id: &str, mail_id: Uuid) -> Result<Mail, String> {
+        let mut con = self.connection().await?;
+        let json: Option<String> = redis::cmd("HGET")
+            .arg(Self::mailbox_key(player_id))
+            .arg(mail_id.to_string())
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        let json = json.ok_or("Mail not found")?;
+        serde_json::from_str(&json).map_err(|e| format!("Failed to deserialize mail: {}", e))
+    }
+
+    async fn deliver(&self, recipient_id: &str, mail: &Mail) -> Result<(), String> {
+        let mut con = self.connection().await?;
+        let size: usize = redis::cmd("HLEN")
+            .arg(Self::mailbox_key(recipient_id))
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        if size >= MAX_MAILBOX_SIZE {
+            return Err("Recipient's mailbox is full".to_string());
+        }
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for (key, field, value) in Self::mail_writes(recipient_id, mail)? {
+            pipe.cmd("HSET").arg(key).arg(field).arg(value).ignore();
+        }
+        pipe.query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))
+    }
+
+    // The HSETs that put a mail in a mailbox, as (hash key, field, value)
+    fn mail_writes(recipient_id: &str, mail: &Mail) -> Result<Vec<(String, String, String)>, String> {
+        let mail_json = serde_json::to_string(mail)
+            .map_err(|e| format!("Failed to serialize mail: {}", e))?;
+        let mut writes = vec![(Self::mailbox_key(recipient_id), mail.mail_id.to_string(), mail_json)];
+        if !mail.attachments.is_empty() {
+            let attachments_json = serde_json::to_string(&mail.attachments)
+                .map_err(|e| format!("Failed to serialize mail attachments: {}", e))?;
+            writes.push((Self::attachments_key(recipient_id), mail.mail_id.to_string(), attachments_json));
+        }
+        Ok(writes)
+    }
+
+    // The writes of a system mail, for callers that must store it in one transaction with their own
+    // writes. Unlike send_system_mail the mailbox size limit is not checked, so the mail always arrives.
+    pub fn system_mail_writes(&self, recipient_id: &str, subject: &str, body: &str, attachments: Vec<MailAttachment>) -> Result<Vec<(String, String, String)>, String> {
+        let mail = Self::new_mail(SYSTEM_SENDER_ID, "Professor", subject.to_string(), body.to_string(), attachments)?;
+        Self::mail_writes(recipient_id, &mail)
+    }
+
+    fn new_mail(sender_id: &str, sender_username: &str, subject: String, body: String, attachments: Vec<MailAttachment>) -> Result<Mail, String> {
+        let subject = subject.trim().to_string();
+        if subject.is_empty() || subject.chars().count() > MAX_SUBJECT_LENGTH {
+            return Err(format!("Subject must be between 1 and {} characters", MAX_SUBJECT_LENGTH));
+        }
+        if body.chars().count() > MAX_BODY_LENGTH {
+            return Err(format!("Message must be at most {} characters", MAX_BODY_LENGTH));
+        }
+        if attachments.len() > MAX_ATTACHMENTS {
+            return Err(format!("A mail can carry at most {} attachments", MAX_ATTACHMENTS));
+        }
+        if attachments.iter().any(|attachment| attachment.quantity == 0) {
+            return Err("Attachment quantity must be above zero".to_string());
+        }
+
+        Ok(Mail {
+            mail_id: Uuid::new_v4(),
+            sender_id: sender_id.to_string(),
+            sender_username: sender_username.to_string(),
+            subject,
+            body,
+            attachments,
+            sent_at: chrono::Utc::now().timestamp() as u64,
+            read: false,
+            claimed: false,
+        })
+    }
+
+    // Send mail from one player to a friend, taking the attached items out of the sender's inventory
+    pub async fn send_mail(
+        &self,
+        sender_id: &str,
+        sender_username: &str,
+        recipient_id: &str,
+        subject: String,
+        body: String,
+        attachments: Vec<MailAttachment>,
+    ) -> Result<Mail, String> {
+        if Uuid::parse_str(recipient_id).is_err() {
+            return Err(format!("Unknown player {}", recipient_id));
+        }
+        if sender_id == recipient_id {
+            return Err("You cannot send mail to yourself".to_string());
+        }
+        // Only players who connected can add friends, so this also rules out made-up recipients
+        if !self.friend_list_manager.are_friends(sender_id, recipient_id).await? {
+            return Err("You can only send mail to friends".to_string());
+        }
+        let mail = Self::new_mail(sender_id, sender_username, subject, body, attachments)?;
+
+        let mut taken: Vec<&MailAttachment> = Vec::new();
+        for attachment in &mail.attachments {
+            if let Err(e) = self.inventory_manager.remove_item(sender_id, &attachment.item_id, attachment.quantity).await {
+                self.return_items(sender_id, &taken).await;
+                return Err(e);
+            }
+            taken.push(attachment);
+        }
+
+        if let Err(e) = self.deliver(recipient_id, &mail).await {
+            self.return_items(sender_id, &taken).await;
+            return Err(e);
+        }
+
+        info!("Player {} sent mail {} to {}", sender_id, mail.mail_id, recipient_id);
+        Ok(mail)
+    }
+
+    // Send mail from the server, e.g. rewards; attachments are created rather than taken from anyone
+    pub async fn send_system_mail(&self, recipient_id: &str, subject: &str, body: &str, attachments: Vec<MailAttachment>) -> Result<Mail, String> {
+        let mail = Self::new_mail(SYSTEM_SENDER_ID, "Professor", subject.to_string(), body.to_string(), attachments)?;
+        self.deliver(recipient_id, &mail).await?;
+        info!("Sent system mail {} to {}", mail.mail_id, recipient_id);
+        Ok(mail)
+    }
+
+    async fn return_items(&self, player_id: &str, items: &[&MailAttachment]) {
+        for item in items {
+            if let Err(e) = self.inventory_manager.add_item(player_id, &item.item_id, item.quantity).await {
+                error!("Failed to return {} x{} to player {}: {}", item.item_id, item.quantity, player_id, e);
+            }
+        }
+    }
+
+    // Undo a partial claim so the attachments stay in the mail
+    async fn take_back_items(&self, player_id: &str, items: &[&MailAttachment]) {
+        for item in items {
+            if let Err(e) = self.inventory_manager.remove_item(player_id, &item.item_id, item.quantity).await {
+                error!("Failed to take back {} x{} from player {}: {}", item.item_id, item.quantity, player_id, e);
+            }
+        }
+    }
+
+    pub async fn mark_read(&self, player_id: &str, mail_id: Uuid) -> Result<(), String> {
+        let mut mail = self.get_mail(player_id, mail_id).await?;
+        if mail.read {
+            return Ok(());
+        }
+        mail.read = true;
+        let json = serde_json::to_string(&mail)
+            .map_err(|e| format!("Failed to serialize mail: {}", e))?;
+        let mut con = self.connection().await?;
+        redis::cmd("HSET")
+            .arg(Self::mailbox_key(player_id))
+            .arg(mail_id.to_string())
+            .arg(json)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))
+    }
+
+    // Move a mail's attachments into the player's inventory; each mail can be claimed once.
+    // The items are added before the attachments are removed, so a failed claim can be retried.
+    pub async fn claim_attachments(&self, player_id: &str, mail_id: Uuid) -> Result<Vec<MailAttachment>, String> {
+        let _guard = self.claim_lock.lock().await;
+        let key = Self::attachments_key(player_id);
+        let mut con = self.connection().await?;
+        let json: Option<String> = redis::cmd("HGET")
+            .arg(&key)
+            .arg(mail_id.to_string())
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        let json = json.ok_or("This mail has no attachments to claim")?;
+
+        let attachments: Vec<MailAttachment> = serde_json::from_str(&json)
+            .map_err(|e| format!("Failed to deserialize mail attachments: {}", e))?;
+        let mut added: Vec<&MailAttachment> = Vec::new();
+        for attachment in &attachments {
+            if let Err(e) = self.inventory_manager.add_item(player_id, &attachment.item_id, attachment.quantity).await {
+                self.take_back_items(player_id, &added).await;
+                return Err(e);
+            }
+            added.push(attachment);
+        }
+
+        let removed: Result<u32, String> = redis::cmd("HDEL")
+            .arg(&key)
+            .arg(mail_id.to_string())
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e));
+        if let Err(e) = removed {
+            self.take_back_items(player_id, &added).await;
+            return Err(e);
+        }
+
+        info!("Player {} claimed attachments of mail {}", player_id, mail_id);
+        Ok(attachments)
+    }
+
+    // Delete a mail once its attachments have been claimed
+    pub async fn delete_mail(&self, player_id: &str, mail_id: Uuid) -> Result<(), String> {
+        let mut con = self.connection().await?;
+        let unclaimed: bool = redis::cmd("HEXISTS")
+            .arg(Self::attachments_key(player_id))
+            .arg(mail_id.to_string())
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        if unclaimed {
+            return Err("Claim the attachments before deleting this mail".to_string());
+        }
+
+        let removed: u32 = redis::cmd("HDEL")
+            .arg(Self::mailbox_key(player_id))
+            .arg(mail_id.to_string())
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        if removed == 0 {
+            return Err("Mail not found".to_string());
+        }
+        Ok(())
+    }
+}
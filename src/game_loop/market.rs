@@ -0,0 +1,718 @@
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::time::Duration;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::game_loop::currency::{CurrencyManager, TransactionReason};
+use crate::game_loop::inventory::InventoryManager;
+use crate::game_loop::pokemon_collection::{Pokemon, PokemonCollectionManager};
+use crate::game_loop::notifications::NotificationManager;
+use crate::game_loop::player_settings::NotificationKind;
+use crate::lobby::Lobby;
+use crate::models::ServerMessage;
+
+// How long a listing stays on the market
+const LISTING_DURATION_SECS: u64 = 3 * 24 * 60 * 60;
+// Maximum number of open listings per player
+const MAX_LISTINGS_PER_PLAYER: usize = 10;
+// Maximum number of listings returned by a search
+const MAX_SEARCH_RESULTS: usize = 50;
+// How often expired listings are returned to their sellers
+const EXPIRY_SWEEP_SECS: u64 = 5 * 60;
+// Redis hash holding every open listing (listing ID → JSON)
+const LISTINGS_KEY: &str = "market_listings";
+
+// What a player wants to put up for sale
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ListingRequest {
+    Pokemon { pokemon_id: String },
+    Item { item_id: String, quantity: u32 },
+}
+
+// Goods held by the market while they are listed
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ListedGoods {
+    Pokemon { pokemon: Box<Pokemon> },
+    Item { item_id: String, quantity: u32 },
+}
+
+// What the seller asks for in return
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AskingCondition {
+    Currency { amount: u64 },
+    Pokemon { template_id: u32, min_level: u32 },
+    Item { item_id: String, quantity: u32 },
+}
+
+// A listing on the global trade station
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+pub struct MarketListing {
+    pub listing_id: Uuid,
+    pub seller_id: String,
+    pub seller_username: String,
+    pub goods: ListedGoods,
+    pub asking: AskingCondition,
+    pub created_at: u64,
+    pub expires_at: u64,
+}
+
+// Filters for searching the market; unset fields match everything
+#[derive(Serialize, Deserialize, Clone, Debug, Default, schemars::JsonSchema)]
+pub struct MarketSearchQuery {
+    pub template_id: Option<u32>,
+    pub item_id: Option<String>,
+    pub max_price: Option<u64>,
+    pub seller_id: Option<String>,
+}
+
+impl MarketSearchQuery {
+    fn matches(&self, listing: &MarketListing) -> bool {
+        if let Some(template_id) = self.template_id {
+            if !matches!(&listing.goods, ListedGoods::Pokemon { pokemon } if pokemon.template_id == template_id) {
+                return false;
+            }
+        }
+        if let Some(item_id) = &self.item_id {
+            if !matches!(&listing.goods, ListedGoods::Item { item_id: listed, .. } if listed == item_id) {
+                return false;
+            }
+        }
+        if let Some(max_price) = self.max_price {
+            if !matches!(listing.asking, AskingCondition::Currency { amount } if amount <= max_price) {
+                return false;
+            }
+        }
+        if let Some(seller_id) = &self.seller_id {
+            if &listing.seller_id != seller_id {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// Asynchronous marketplace: goods are escrowed on listing and settle without the seller online
+pub struct MarketManager {
+    redis_client: redis::Client,
+    pokemon_collection_manager: Arc<PokemonCollectionManager>,
+    currency_manager: Arc<CurrencyManager>,
+    inventory_manager: Arc<InventoryManager>,
+}
+
+impl MarketManager {
+    pub fn new(
+        redis_client: redis::Client,
+        pokemon_collection_manager: Arc<PokemonCollectionManager>,
+        currency_manager: Arc<CurrencyManager>,
+        inventory_manager: Arc<InventoryManager>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            redis_client,
+            pokemon_collection_manager,
+            currency_manager,
+            inventory_manager,
+        })
+    }
+
+    async fn connection(&self) -> Result<redis::aio::Connection, String> {
+        self.redis_client
+            .get_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection error: {}", e))
+    }
+
+    async fn all_listings(&self) -> Result<Vec<MarketListing>, String> {
+        let mut con = self.connection().await?;
+        let saved: HashMap<String, String> = redis::cmd("HGETALL")
+            .arg(LISTINGS_KEY)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+
+        Ok(saved
+            .values()
+            .filter_map(|json| match serde_json::from_str::<MarketListing>(json) {
+                Ok(listing) => Some(listing),
+                Err(e) => {
+                    error!("Failed to deserialize market listing: {}", e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    async fn get_listing(&self, listing_id: Uuid) -> Result<MarketListing, String> {
+        let mut con = self.connection().await?;
+        let json: Option<String> = redis::cmd("HGET")
+            .arg(LISTINGS_KEY)
+            .arg(listing_id.to_string())
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        let json = json.ok_or("Listing is no longer available")?;
+        serde_json::from_str(&json).map_err(|e| format!("Failed to deserialize market listing: {}", e))
+    }
+
+    // Search open listings, cheapest currency listings first
+    pub async fn search(&self, query: &MarketSearchQuery) -> Result<Vec<MarketListing>, String> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let mut listings: Vec<MarketListing> = self.all_listings().await?
+            .into_iter()
+            .filter(|listing| listing.expires_at > now && query.matches(listing))
+            .collect();
+
+        listings.sort_by_key(|listing| match listing.asking {
+            AskingCondition::Currency { amount } => (0, amount, listing.created_at),
+            _ => (1, 0, listing.created_at),
+        });
+        listings.truncate(MAX_SEARCH_RESULTS);
+        Ok(listings)
+    }
+
+    // Put a Pokemon or item up for sale, moving it out of the seller's collection or inventory
+    pub async fn create_listing(
+        &self,
+        lobby: &Lobby,
+        seller_id: &str,
+        seller_username: &str,
+        request: ListingRequest,
+        asking: AskingCondition,
+    ) -> Result<MarketListing, String> {
+        check_asking(&asking)?;
+        let open_listings = self.all_listings().await?
+            .iter()
+            .filter(|listing| listing.seller_id == seller_id)
+            .count();
+        if open_listings >= MAX_LISTINGS_PER_PLAYER {
+            return Err(format!("You can have at most {} listings", MAX_LISTINGS_PER_PLAYER));
+        }
+
+        let goods = match request {
+            ListingRequest::Pokemon { pokemon_id } => {
+                if in_combat(lobby, seller_id) {
+                    return Err("You cannot list a pokemon while in combat".to_string());
+                }
+                let collection = self.pokemon_collection_manager.get_collection(seller_id).await?;
+                let pokemon = collection.pokemons.get(&pokemon_id)
+                    .ok_or_else(|| format!("You do not own pokemon {}", pokemon_id))?;
+                if collection.pokemons.len() <= 1 {
+                    return Err("You cannot list your last pokemon".to_string());
+                }
+                ListedGoods::Pokemon { pokemon: Box::new(pokemon.clone()) }
+            }
+            ListingRequest::Item { item_id, quantity } => {
+                if quantity == 0 {
+                    return Err("Quantity must be above zero".to_string());
+                }
+                ListedGoods::Item { item_id, quantity }
+            }
+        };
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let listing = MarketListing {
+            listing_id: Uuid::new_v4(),
+            seller_id: seller_id.to_string(),
+            seller_username: seller_username.to_string(),
+            goods,
+            asking,
+            created_at: now,
+            expires_at: now + LISTING_DURATION_SECS,
+        };
+        list(self, &listing).await?;
+
+        info!("Player {} listed {:?} for {:?} as {}", seller_id, listing.goods, listing.asking, listing.listing_id);
+        Ok(listing)
+    }
+
+    // Take a listing down and return the goods to the seller
+    pub async fn cancel_listing(&self, seller_id: &str, listing_id: Uuid) -> Result<(), String> {
+        // Check ownership before claiming, so other players never take a listing off the market
+        if self.get_listing(listing_id).await?.seller_id != seller_id {
+            return Err("You can only cancel your own listings".to_string());
+        }
+        close_listing(self, listing_id).await?;
+        info!("Player {} cancelled market listing {}", seller_id, listing_id);
+        Ok(())
+    }
+
+    // Buy a listing by meeting its asking condition. The seller is paid directly into their
+    // balance, inventory or collection, so they do not need to be online.
+    pub async fn accept_listing(&self, lobby: &Lobby, buyer_id: &str, listing_id: Uuid, offered_pokemon_id: Option<String>) -> Result<MarketListing, String> {
+        let listing = self.get_listing(listing_id).await?;
+        let payment = self.payment_for(lobby, buyer_id, &listing, offered_pokemon_id).await?;
+        let listing = buy(self, buyer_id, listing_id, &payment).await?;
+        info!("Player {} bought market listing {} from {}", buyer_id, listing_id, listing.seller_id);
+        Ok(listing)
+    }
+
+    // What the buyer hands over for a listing, checked against its asking condition
+    async fn payment_for(&self, lobby: &Lobby, buyer_id: &str, listing: &MarketListing, offered_pokemon_id: Option<String>) -> Result<Transfer, String> {
+        match &listing.asking {
+            AskingCondition::Currency { amount } => Ok(Transfer::Currency { amount: *amount }),
+            AskingCondition::Item { item_id, quantity } => Ok(Transfer::Item { item_id: item_id.clone(), quantity: *quantity }),
+            AskingCondition::Pokemon { template_id, min_level } => {
+                let pokemon_id = offered_pokemon_id.ok_or("This listing asks for a pokemon in return")?;
+                if in_combat(lobby, buyer_id) {
+                    return Err("You cannot give away a pokemon while in combat".to_string());
+                }
+                let collection = self.pokemon_collection_manager.get_collection(buyer_id).await?;
+                let offered = collection.pokemons.get(&pokemon_id)
+                    .ok_or_else(|| format!("You do not own pokemon {}", pokemon_id))?;
+                if offered.template_id != *template_id || offered.level < *min_level {
+                    return Err("The offered pokemon does not match what the seller asks for".to_string());
+                }
+                if collection.pokemons.len() <= 1 && !matches!(listing.goods, ListedGoods::Pokemon { .. }) {
+                    return Err("You cannot give away your last pokemon".to_string());
+                }
+                Ok(Transfer::Pokemon { pokemon: Box::new(offered.clone()) })
+            }
+        }
+    }
+
+    // Return the goods of every expired listing to its seller
+    pub async fn expire_listings(&self) -> Result<usize, String> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let mut expired = 0;
+        for listing in self.all_listings().await? {
+            if listing.expires_at > now {
+                continue;
+            }
+            // Another task may have bought or cancelled it meanwhile
+            match close_listing(self, listing.listing_id).await {
+                Ok(listing) => {
+                    info!("Market listing {} expired and was returned to {}", listing.listing_id, listing.seller_id);
+                    expired += 1;
+                }
+                Err(e) => error!("Failed to return expired market listing {}: {}", listing.listing_id, e),
+            }
+        }
+        Ok(expired)
+    }
+}
+
+// Something that changes hands on the market: a payment or listed goods
+#[derive(Clone, Debug)]
+enum Transfer {
+    Currency { amount: u64 },
+    Item { item_id: String, quantity: u32 },
+    Pokemon { pokemon: Box<Pokemon> },
+}
+
+impl From<&ListedGoods> for Transfer {
+    fn from(goods: &ListedGoods) -> Self {
+        match goods {
+            ListedGoods::Pokemon { pokemon } => Transfer::Pokemon { pokemon: pokemon.clone() },
+            ListedGoods::Item { item_id, quantity } => Transfer::Item { item_id: item_id.clone(), quantity: *quantity },
+        }
+    }
+}
+
+// Where listings are kept and how goods move between players. Settlement only goes through
+// this, so the rollback rules below can be tested without Redis.
+trait MarketLedger {
+    // Remove a listing from the market; only one caller can ever claim a given listing
+    async fn claim_listing(&self, listing_id: Uuid) -> Result<MarketListing, String>;
+    async fn save_listing(&self, listing: &MarketListing) -> Result<(), String>;
+    // Take from a player, failing without changes if they do not have it
+    async fn take(&self, player_id: &str, transfer: &Transfer, reason: TransactionReason) -> Result<(), String>;
+    async fn give(&self, player_id: &str, transfer: &Transfer, reason: TransactionReason) -> Result<(), String>;
+}
+
+impl MarketLedger for MarketManager {
+    async fn claim_listing(&self, listing_id: Uuid) -> Result<MarketListing, String> {
+        let listing = self.get_listing(listing_id).await?;
+
+        let mut con = self.connection().await?;
+        let removed: u32 = redis::cmd("HDEL")
+            .arg(LISTINGS_KEY)
+            .arg(listing_id.to_string())
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        if removed == 0 {
+            return Err("Listing is no longer available".to_string());
+        }
+        Ok(listing)
+    }
+
+    async fn save_listing(&self, listing: &MarketListing) -> Result<(), String> {
+        let json = serde_json::to_string(listing)
+            .map_err(|e| format!("Failed to serialize market listing: {}", e))?;
+        let mut con = self.connection().await?;
+        redis::cmd("HSET")
+            .arg(LISTINGS_KEY)
+            .arg(listing.listing_id.to_string())
+            .arg(json)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))
+    }
+
+    async fn take(&self, player_id: &str, transfer: &Transfer, reason: TransactionReason) -> Result<(), String> {
+        match transfer {
+            Transfer::Currency { amount } => self.currency_manager.debit(player_id, *amount, reason).await.map(|_| ()),
+            Transfer::Item { item_id, quantity } => self.inventory_manager.remove_item(player_id, item_id, *quantity).await.map(|_| ()),
+            Transfer::Pokemon { pokemon } => self.pokemon_collection_manager.remove_pokemon(player_id, &pokemon.id).await.map(|_| ()),
+        }
+    }
+
+    async fn give(&self, player_id: &str, transfer: &Transfer, reason: TransactionReason) -> Result<(), String> {
+        match transfer {
+            Transfer::Currency { amount } => self.currency_manager.credit(player_id, *amount, reason).await.map(|_| ()),
+            Transfer::Item { item_id, quantity } => self.inventory_manager.add_item(player_id, item_id, *quantity).await.map(|_| ()),
+            Transfer::Pokemon { pokemon } => self.pokemon_collection_manager.add_pokemon(player_id, pokemon.as_ref().clone()).await.map(|_| ()),
+        }
+    }
+}
+
+fn check_asking(asking: &AskingCondition) -> Result<(), String> {
+    match asking {
+        AskingCondition::Currency { amount: 0 } => Err("Asking price must be above zero".to_string()),
+        AskingCondition::Item { quantity: 0, .. } => Err("Asking quantity must be above zero".to_string()),
+        _ => Ok(()),
+    }
+}
+
+// Move the goods out of the seller's hands and onto the market
+async fn list(ledger: &impl MarketLedger, listing: &MarketListing) -> Result<(), String> {
+    let goods = Transfer::from(&listing.goods);
+    ledger.take(&listing.seller_id, &goods, TransactionReason::MarketSale).await?;
+    if let Err(e) = ledger.save_listing(listing).await {
+        if let Err(return_err) = ledger.give(&listing.seller_id, &goods, TransactionReason::MarketSale).await {
+            error!("Failed to return market goods {:?} to player {}: {}", listing.goods, listing.seller_id, return_err);
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+// Take a listing off the market and hand the goods back to the seller. If they cannot be
+// handed back the listing stays on the market, so it can be cancelled or expire again later.
+async fn close_listing(ledger: &impl MarketLedger, listing_id: Uuid) -> Result<MarketListing, String> {
+    let listing = ledger.claim_listing(listing_id).await?;
+    if let Err(e) = ledger.give(&listing.seller_id, &Transfer::from(&listing.goods), TransactionReason::MarketSale).await {
+        restore_listing(ledger, &listing).await;
+        return Err(e);
+    }
+    Ok(listing)
+}
+
+// Claim a listing and settle it; if settling fails the listing goes back on the market
+async fn buy(ledger: &impl MarketLedger, buyer_id: &str, listing_id: Uuid, payment: &Transfer) -> Result<MarketListing, String> {
+    let listing = ledger.claim_listing(listing_id).await?;
+    if let Err(e) = settle(ledger, buyer_id, &listing, payment).await {
+        restore_listing(ledger, &listing).await;
+        return Err(e);
+    }
+    Ok(listing)
+}
+
+// Pay the seller and hand the goods to the buyer. A step that fails undoes the steps before it,
+// so on an error the buyer keeps their payment and the seller their goods.
+async fn settle(ledger: &impl MarketLedger, buyer_id: &str, listing: &MarketListing, payment: &Transfer) -> Result<(), String> {
+    if buyer_id == listing.seller_id {
+        return Err("You cannot buy your own listing".to_string());
+    }
+    if listing.expires_at <= chrono::Utc::now().timestamp() as u64 {
+        return Err("Listing has expired".to_string());
+    }
+
+    ledger.take(buyer_id, payment, TransactionReason::MarketPurchase).await?;
+    if let Err(e) = ledger.give(&listing.seller_id, payment, TransactionReason::MarketSale).await {
+        refund_buyer(ledger, buyer_id, listing, payment).await;
+        return Err(e);
+    }
+    if let Err(e) = ledger.give(buyer_id, &Transfer::from(&listing.goods), TransactionReason::MarketPurchase).await {
+        match ledger.take(&listing.seller_id, payment, TransactionReason::MarketSale).await {
+            Ok(()) => refund_buyer(ledger, buyer_id, listing, payment).await,
+            Err(reclaim_err) => error!("Failed to take back payment for market listing {} from {}: {}", listing.listing_id, listing.seller_id, reclaim_err),
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+async fn refund_buyer(ledger: &impl MarketLedger, buyer_id: &str, listing: &MarketListing, payment: &Transfer) {
+    if let Err(e) = ledger.give(buyer_id, payment, TransactionReason::MarketPurchase).await {
+        error!("Failed to refund player {} for market listing {}: {}", buyer_id, listing.listing_id, e);
+    }
+}
+
+// Put a claimed listing back on the market, or return the goods to the seller if that fails
+async fn restore_listing(ledger: &impl MarketLedger, listing: &MarketListing) {
+    if let Err(e) = ledger.save_listing(listing).await {
+        error!("Failed to restore market listing {}: {}", listing.listing_id, e);
+        if let Err(e) = ledger.give(&listing.seller_id, &Transfer::from(&listing.goods), TransactionReason::MarketSale).await {
+            error!("Failed to return market goods {:?} to player {}: {}", listing.goods, listing.seller_id, e);
+        }
+    }
+}
+
+// Whether the player is in a battle, so their pokemon may not leave the collection
+fn in_combat(lobby: &Lobby, player_id: &str) -> bool {
+    lobby.player_positions.get(player_id).map(|state| state.in_combat).unwrap_or(false)
+}
+
+// Tell the seller that their listing sold, now or in their next login digest
+pub async fn notify_seller(lobbies: &DashMap<String, Arc<Lobby>>, notification_manager: &NotificationManager, listing: &MarketListing) {
+    let sold_msg = ServerMessage::MarketListingSold { listing_id: listing.listing_id };
+    notification_manager.notify(lobbies, &listing.seller_id, NotificationKind::MarketSold, sold_msg).await;
+}
+
+// Periodically returns expired listings to their sellers
+pub async fn run_market_expiry(market_manager: Arc<MarketManager>) {
+    info!("Starting market expiry task");
+    let mut interval = tokio::time::interval(Duration::from_secs(EXPIRY_SWEEP_SECS));
+    loop {
+        interval.tick().await;
+        if let Err(e) = market_manager.expire_listings().await {
+            error!("Failed to expire market listings: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::Mutex;
+
+    // Listings, balances and items in memory; gives of a kind to a player can be made to fail
+    #[derive(Default)]
+    struct MemoryLedger {
+        listings: Mutex<HashMap<Uuid, MarketListing>>,
+        balances: Mutex<HashMap<String, u64>>,
+        items: Mutex<HashMap<(String, String), u32>>,
+        failing_gives: Mutex<HashSet<(String, &'static str)>>,
+        failing_saves: Mutex<bool>,
+    }
+
+    impl MemoryLedger {
+        fn balance(&self, player_id: &str) -> u64 {
+            self.balances.lock().unwrap().get(player_id).copied().unwrap_or(0)
+        }
+
+        fn items(&self, player_id: &str, item_id: &str) -> u32 {
+            self.items.lock().unwrap().get(&(player_id.to_string(), item_id.to_string())).copied().unwrap_or(0)
+        }
+
+        fn fail_gives(&self, player_id: &str, kind: &'static str) {
+            self.failing_gives.lock().unwrap().insert((player_id.to_string(), kind));
+        }
+
+        fn is_listed(&self, listing_id: Uuid) -> bool {
+            self.listings.lock().unwrap().contains_key(&listing_id)
+        }
+    }
+
+    fn kind(transfer: &Transfer) -> &'static str {
+        match transfer {
+            Transfer::Currency { .. } => "currency",
+            Transfer::Item { .. } => "item",
+            Transfer::Pokemon { .. } => "pokemon",
+        }
+    }
+
+    impl MarketLedger for MemoryLedger {
+        async fn claim_listing(&self, listing_id: Uuid) -> Result<MarketListing, String> {
+            self.listings.lock().unwrap().remove(&listing_id).ok_or("Listing is no longer available".to_string())
+        }
+
+        async fn save_listing(&self, listing: &MarketListing) -> Result<(), String> {
+            if *self.failing_saves.lock().unwrap() {
+                return Err("Redis query error: connection refused".to_string());
+            }
+            self.listings.lock().unwrap().insert(listing.listing_id, listing.clone());
+            Ok(())
+        }
+
+        async fn take(&self, player_id: &str, transfer: &Transfer, _reason: TransactionReason) -> Result<(), String> {
+            match transfer {
+                Transfer::Currency { amount } => {
+                    let mut balances = self.balances.lock().unwrap();
+                    let balance = balances.entry(player_id.to_string()).or_default();
+                    *balance = balance.checked_sub(*amount).ok_or(format!("Insufficient funds: {} required", amount))?;
+                }
+                Transfer::Item { item_id, quantity } => {
+                    let mut items = self.items.lock().unwrap();
+                    let count = items.entry((player_id.to_string(), item_id.clone())).or_default();
+                    *count = count.checked_sub(*quantity).ok_or(format!("Not enough {}", item_id))?;
+                }
+                Transfer::Pokemon { .. } => return Err("Pokemon are not kept in memory".to_string()),
+            }
+            Ok(())
+        }
+
+        async fn give(&self, player_id: &str, transfer: &Transfer, _reason: TransactionReason) -> Result<(), String> {
+            if self.failing_gives.lock().unwrap().contains(&(player_id.to_string(), kind(transfer))) {
+                return Err("Redis query error: connection refused".to_string());
+            }
+            match transfer {
+                Transfer::Currency { amount } => *self.balances.lock().unwrap().entry(player_id.to_string()).or_default() += amount,
+                Transfer::Item { item_id, quantity } => *self.items.lock().unwrap().entry((player_id.to_string(), item_id.clone())).or_default() += quantity,
+                Transfer::Pokemon { .. } => return Err("Pokemon are not kept in memory".to_string()),
+            }
+            Ok(())
+        }
+    }
+
+    // A seller with 3 potions and a buyer with 100 coins
+    fn ledger() -> MemoryLedger {
+        let ledger = MemoryLedger::default();
+        ledger.balances.lock().unwrap().insert("buyer".to_string(), 100);
+        ledger.items.lock().unwrap().insert(("seller".to_string(), "potion".to_string()), 3);
+        ledger
+    }
+
+    // Two potions for 40 coins
+    fn potion_listing() -> MarketListing {
+        let now = chrono::Utc::now().timestamp() as u64;
+        MarketListing {
+            listing_id: Uuid::new_v4(),
+            seller_id: "seller".to_string(),
+            seller_username: "Seller".to_string(),
+            goods: ListedGoods::Item { item_id: "potion".to_string(), quantity: 2 },
+            asking: AskingCondition::Currency { amount: 40 },
+            created_at: now,
+            expires_at: now + LISTING_DURATION_SECS,
+        }
+    }
+
+    const PAYMENT: Transfer = Transfer::Currency { amount: 40 };
+
+    #[test]
+    fn listing_rejects_empty_asking_conditions() {
+        assert!(check_asking(&AskingCondition::Currency { amount: 0 }).is_err());
+        assert!(check_asking(&AskingCondition::Item { item_id: "potion".to_string(), quantity: 0 }).is_err());
+        assert!(check_asking(&AskingCondition::Currency { amount: 1 }).is_ok());
+    }
+
+    #[tokio::test]
+    async fn listing_escrows_the_goods() {
+        let ledger = ledger();
+        let listing = potion_listing();
+        list(&ledger, &listing).await.unwrap();
+        assert_eq!(ledger.items("seller", "potion"), 1);
+        assert!(ledger.is_listed(listing.listing_id));
+
+        // Only one more potion left to list
+        assert!(list(&ledger, &potion_listing()).await.is_err());
+        assert_eq!(ledger.items("seller", "potion"), 1);
+    }
+
+    #[tokio::test]
+    async fn listing_returns_the_goods_when_it_cannot_be_saved() {
+        let ledger = ledger();
+        *ledger.failing_saves.lock().unwrap() = true;
+        let listing = potion_listing();
+        assert!(list(&ledger, &listing).await.is_err());
+        assert_eq!(ledger.items("seller", "potion"), 3);
+        assert!(!ledger.is_listed(listing.listing_id));
+    }
+
+    #[tokio::test]
+    async fn claiming_a_listing_succeeds_only_once() {
+        let ledger = ledger();
+        let listing = potion_listing();
+        list(&ledger, &listing).await.unwrap();
+
+        buy(&ledger, "buyer", listing.listing_id, &PAYMENT).await.unwrap();
+        assert_eq!(buy(&ledger, "buyer", listing.listing_id, &PAYMENT).await.unwrap_err(), "Listing is no longer available");
+        assert!(close_listing(&ledger, listing.listing_id).await.is_err());
+        assert_eq!(ledger.balance("buyer"), 60);
+        assert_eq!(ledger.items("buyer", "potion"), 2);
+    }
+
+    #[tokio::test]
+    async fn closing_a_listing_returns_the_goods_to_the_seller() {
+        let ledger = ledger();
+        let listing = potion_listing();
+        list(&ledger, &listing).await.unwrap();
+
+        close_listing(&ledger, listing.listing_id).await.unwrap();
+        assert_eq!(ledger.items("seller", "potion"), 3);
+        assert!(!ledger.is_listed(listing.listing_id));
+    }
+
+    #[tokio::test]
+    async fn closing_keeps_the_listing_when_the_goods_cannot_be_returned() {
+        let ledger = ledger();
+        let listing = potion_listing();
+        list(&ledger, &listing).await.unwrap();
+        ledger.fail_gives("seller", "item");
+
+        assert!(close_listing(&ledger, listing.listing_id).await.is_err());
+        assert!(ledger.is_listed(listing.listing_id));
+        assert_eq!(ledger.items("seller", "potion"), 1);
+    }
+
+    #[tokio::test]
+    async fn paying_moves_the_payment_and_the_goods() {
+        let ledger = ledger();
+        let listing = potion_listing();
+        list(&ledger, &listing).await.unwrap();
+
+        let bought = buy(&ledger, "buyer", listing.listing_id, &PAYMENT).await.unwrap();
+        assert_eq!(bought.listing_id, listing.listing_id);
+        assert_eq!((ledger.balance("buyer"), ledger.balance("seller")), (60, 40));
+        assert_eq!((ledger.items("buyer", "potion"), ledger.items("seller", "potion")), (2, 1));
+        assert!(!ledger.is_listed(listing.listing_id));
+    }
+
+    #[tokio::test]
+    async fn paying_without_enough_funds_keeps_the_listing() {
+        let ledger = ledger();
+        let listing = potion_listing();
+        list(&ledger, &listing).await.unwrap();
+
+        assert!(buy(&ledger, "buyer", listing.listing_id, &Transfer::Currency { amount: 500 }).await.is_err());
+        assert_eq!(ledger.balance("buyer"), 100);
+        assert!(ledger.is_listed(listing.listing_id));
+    }
+
+    #[tokio::test]
+    async fn paying_refunds_the_buyer_when_the_seller_cannot_be_paid() {
+        let ledger = ledger();
+        let listing = potion_listing();
+        list(&ledger, &listing).await.unwrap();
+        ledger.fail_gives("seller", "currency");
+
+        assert!(buy(&ledger, "buyer", listing.listing_id, &PAYMENT).await.is_err());
+        assert_eq!((ledger.balance("buyer"), ledger.balance("seller")), (100, 0));
+        assert_eq!(ledger.items("buyer", "potion"), 0);
+        assert!(ledger.is_listed(listing.listing_id));
+    }
+
+    #[tokio::test]
+    async fn paying_takes_back_the_payment_when_the_goods_cannot_be_delivered() {
+        let ledger = ledger();
+        let listing = potion_listing();
+        list(&ledger, &listing).await.unwrap();
+        ledger.fail_gives("buyer", "item");
+
+        assert!(buy(&ledger, "buyer", listing.listing_id, &PAYMENT).await.is_err());
+        assert_eq!((ledger.balance("buyer"), ledger.balance("seller")), (100, 0));
+        assert!(ledger.is_listed(listing.listing_id));
+    }
+
+    #[tokio::test]
+    async fn paying_for_an_own_or_expired_listing_fails() {
+        let ledger = ledger();
+        let listing = potion_listing();
+        list(&ledger, &listing).await.unwrap();
+        ledger.balances.lock().unwrap().insert("seller".to_string(), 100);
+        assert!(buy(&ledger, "seller", listing.listing_id, &PAYMENT).await.is_err());
+        assert_eq!(ledger.balance("seller"), 100);
+
+        let expired = MarketListing { expires_at: 0, ..potion_listing() };
+        ledger.save_listing(&expired).await.unwrap();
+        assert_eq!(buy(&ledger, "buyer", expired.listing_id, &PAYMENT).await.unwrap_err(), "Listing has expired");
+        assert_eq!(ledger.balance("buyer"), 100);
+    }
+}
@@ -1,4 +1,43 @@
 pub mod monster_spawner;
 pub mod monster_movement;
 pub mod player_movement;
-pub mod pokemon_collection; 
\ No newline at end of file
+pub mod pokemon_collection;
+pub mod collection_migrations;
+pub mod currency;
+pub mod npc_services;
+pub mod inventory;
+pub mod audit_log;
+pub mod backups;
+pub mod berries;
+pub mod weather;
+pub mod fast_travel;
+pub mod emotes;
+pub mod trading;
+pub mod market;
+pub mod auctions;
+pub mod mail;
+pub mod ranking;
+pub mod battle_history;
+pub mod catch_chain;
+pub mod lobby_buffs;
+pub mod item_pickups;
+pub mod gates;
+pub mod badges;
+pub mod occupancy;
+pub mod monster_persistence;
+pub mod lobby_snapshots;
+pub mod notifications;
+pub mod latency;
+pub mod player_settings;
+pub mod blocks;
+pub mod friends;
+pub mod battle_chat;
+pub mod battle_replays;
+pub mod rich_presence;
+pub mod telemetry;
+pub mod webhooks;
+pub mod tutorial;
+pub mod lobby_events;
+pub mod event_scheduler;
+pub mod player_index;
+pub mod time_sync;
@@ -3,23 +3,24 @@ use rand::Rng;
 use rand::rngs::SmallRng;
 use rand::SeedableRng;
 use tokio::time::Duration;
-use tracing::info;
-use std::collections::{HashMap, HashSet};
+use tracing::{debug, info};
+use std::collections::{HashMap, HashSet, VecDeque};
 use tokio::sync::Mutex;
 
 use crate::monsters::{Monster, MovementPattern};
-use crate::monsters::monster_manager::MonsterManager;
+use crate::monsters::monster_manager::{MonsterManager, SpawnPoint};
 use crate::models::ServerMessage;
 use crate::lobby::Lobby;
 
 // Constants for monster movement
 const DIRECTION_CHANGE_PROBABILITY: f32 = 0.15; // Chance to change direction randomly
-const MOVEMENT_UPDATE_INTERVAL_MS: u64 = 2000; // Time between movement updates
 const MONSTERS_MOVE_PERCENT: f32 = 0.7; // Percentage of spawn points that have a monster move per update
 const ALL_DIRECTIONS: [&str; 4] = ["up", "down", "left", "right"]; // All possible directions
+const MONSTER_LEASH_TILES: u32 = 3; // How far outside its spawn area a monster may wander
+const RETURN_PATH_MAX_TILES: usize = 2048; // Tiles searched for a way back before giving up until the next update
 
 // Handles monster movement logic
-pub async fn run_monster_movement(lobbies: Arc<dashmap::DashMap<String, Arc<Lobby>>>) {
+pub async fn run_monster_movement(lobbies: Arc<dashmap::DashMap<String, Arc<Lobby>>>, update_interval: Duration) {
     info!("Starting monster movement controller");
     
     loop {
@@ -62,17 +63,16 @@ pub async fn run_monster_movement(lobbies: Arc<dashmap::DashMap<String, Arc<Lobb
                 }
                 
                 // Find which spawn point this monster belongs to
-                for spawn_point_entry in lobby.monsters_by_spawn_point.iter() {
-                    let spawn_point_id = spawn_point_entry.key().clone();
-                    let monster_ids = spawn_point_entry.value();
-                    
-                    if monster_ids.contains(&monster.instance_id) {
-                        monsters_by_spawn_point
-                            .entry(spawn_point_id)
-                            .or_insert_with(Vec::new)
-                            .push(monster);
-                        break;
-                    }
+                let spawn_point_id = monster.spawn_point_id.clone().or_else(|| {
+                    lobby.monsters_by_spawn_point.iter()
+                        .find(|entry| entry.value().contains(&monster.instance_id))
+                        .map(|entry| entry.key().clone())
+                });
+                if let Some(spawn_point_id) = spawn_point_id {
+                    monsters_by_spawn_point
+                        .entry(spawn_point_id)
+                        .or_insert_with(Vec::new)
+                        .push(monster);
                 }
             }
             
@@ -100,8 +100,14 @@ pub async fn run_monster_movement(lobbies: Arc<dashmap::DashMap<String, Arc<Lobb
                 if let Some(monster_entry) = lobby.active_monsters.get(&updated_monster.instance_id) {
                     let mutex = monster_entry.value();
                     if let Ok(mut monster) = mutex.try_lock() {
+                        // A battle may have claimed the monster while it was moving; don't overwrite that
+                        if !monster.is_available() {
+                            continue;
+                        }
                         // Update the monster with the new data
+                        let from = (monster.position.x, monster.position.y);
                         *monster = updated_monster.clone();
+                        lobby.occupancy.move_monster(&updated_monster.instance_id, from, (updated_monster.position.x, updated_monster.position.y));
                         
                         // Notify players about the monster movement
                         let monster_move_msg = ServerMessage::MonsterMoved { 
@@ -117,7 +123,7 @@ pub async fn run_monster_movement(lobbies: Arc<dashmap::DashMap<String, Arc<Lobb
         }
         
         // Sleep before the next movement update
-        tokio::time::sleep(Duration::from_millis(MOVEMENT_UPDATE_INTERVAL_MS)).await;
+        tokio::time::sleep(update_interval).await;
     }
 }
 
@@ -188,36 +194,39 @@ async fn move_monster(monster_manager: &Arc<MonsterManager>, lobby: &Arc<Lobby>,
         None => return monster,
     };
     
-    // Set up boundaries
-    let min_x = spawn_point.tile_x;
-    let min_y = spawn_point.tile_y;
-    let max_x = spawn_point.tile_x + spawn_point.width;
-    let max_y = spawn_point.tile_y + spawn_point.height;
+    // Tiles a monster may stand on: clear of obstacles, players and other monsters, on terrain its species can cross
+    let occupied_by_players: HashSet<(u32, u32)> = lobby.player_positions.iter()
+        .map(|player_entry| (player_entry.value().x, player_entry.value().y))
+        .collect();
+    let template = monster_manager.template_repository.templates.get(&monster.template_id);
+    let is_free = |tile: (u32, u32)| monster_manager.map_data.is_valid_position(tile.0, tile.1)
+        && template.is_none_or(|template| template.can_traverse(monster_manager.map_data.terrain_at(tile.0, tile.1)))
+        && !occupied_by_players.contains(&tile)
+        && lobby.occupancy.monster_at(tile).is_none_or(|occupant| occupant == monster.instance_id);
     
-    // Get valid positions from the monster manager
-    let valid_positions = if let Some(valid_pos_map) = monster_manager.map_data.valid_positions.get(spawn_point_id) {
-        // Start with the pre-computed positions
-        let mut positions = valid_pos_map.valid_positions.clone();
-        
-        // Remove positions occupied by players
-        for player_entry in lobby.player_positions.iter() {
-            let player = player_entry.value();
-            positions.remove(&(player.x, player.y));
+    // A monster past its leash heads back to its spawn area instead of wandering
+    if distance_from_area(spawn_point, (monster.position.x, monster.position.y)) > MONSTER_LEASH_TILES {
+        match path_back_to_area(spawn_point, (monster.position.x, monster.position.y), &is_free) {
+            Some(path) => {
+                let (new_x, new_y) = path[(tile_movement as usize).min(path.len()) - 1];
+                update_direction_from_move(&mut monster, path[0].0, path[0].1);
+                monster.position.x = new_x;
+                monster.position.y = new_y;
+            }
+            None => debug!("Monster {} is outside its leash with no way back to spawn point {} yet", monster.instance_id, spawn_point_id),
         }
-        
-        // Remove positions occupied by other monsters
-        for monster_entry in lobby.active_monsters.iter() {
-            if let Ok(other_monster) = monster_entry.value().try_lock() {
-                if other_monster.instance_id != monster.instance_id {
-                    positions.remove(&(other_monster.position.x, other_monster.position.y));
-                }
+        return monster;
+    }
+    
+    // Free tiles within the leash of the spawn area
+    let mut valid_positions = HashSet::new();
+    for x in spawn_point.tile_x.saturating_sub(MONSTER_LEASH_TILES)..spawn_point.tile_x + spawn_point.width + MONSTER_LEASH_TILES {
+        for y in spawn_point.tile_y.saturating_sub(MONSTER_LEASH_TILES)..spawn_point.tile_y + spawn_point.height + MONSTER_LEASH_TILES {
+            if is_free((x, y)) {
+                valid_positions.insert((x, y));
             }
         }
-        
-        positions
-    } else {
-        HashSet::new()
-    };
+    }
     
     // Apply movement based on the pattern
     match &monster.movement_pattern {
@@ -275,13 +284,55 @@ async fn move_monster(monster_manager: &Arc<MonsterManager>, lobby: &Arc<Lobby>,
         }
     }
     
-    // Ensure monster stays within the spawn area boundaries
-    monster.position.x = monster.position.x.clamp(min_x, max_x - 1);
-    monster.position.y = monster.position.y.clamp(min_y, max_y - 1);
-    
     monster
 }
 
+// Tiles between a position and the nearest edge of a spawn area, 0 inside it
+fn distance_from_area(spawn_point: &SpawnPoint, (x, y): (u32, u32)) -> u32 {
+    let axis_distance = |value: u32, start: u32, size: u32| {
+        if value < start {
+            start - value
+        } else {
+            value.saturating_sub((start + size).saturating_sub(1))
+        }
+    };
+    axis_distance(x, spawn_point.tile_x, spawn_point.width).max(axis_distance(y, spawn_point.tile_y, spawn_point.height))
+}
+
+// Shortest walk over free tiles from `start` into the spawn area, excluding `start` itself.
+// None when the area can't be reached within the search budget.
+fn path_back_to_area(spawn_point: &SpawnPoint, start: (u32, u32), is_free: &impl Fn((u32, u32)) -> bool) -> Option<Vec<(u32, u32)>> {
+    let mut came_from: HashMap<(u32, u32), (u32, u32)> = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+    came_from.insert(start, start);
+    while let Some(tile) = queue.pop_front() {
+        if distance_from_area(spawn_point, tile) == 0 && tile != start {
+            let mut path = vec![tile];
+            let mut current = tile;
+            while let Some(&previous) = came_from.get(&current).filter(|previous| **previous != start) {
+                path.push(previous);
+                current = previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if came_from.len() >= RETURN_PATH_MAX_TILES {
+            return None;
+        }
+        for direction in ALL_DIRECTIONS {
+            let (dx, dy) = get_direction_vector(direction);
+            let (Some(x), Some(y)) = (tile.0.checked_add_signed(dx), tile.1.checked_add_signed(dy)) else {
+                continue;
+            };
+            if !came_from.contains_key(&(x, y)) && is_free((x, y)) {
+                came_from.insert((x, y), tile);
+                queue.push_back((x, y));
+            }
+        }
+    }
+    None
+}
+
 // Helper function to update direction based on the move made
 fn update_direction_from_move(monster: &mut Monster, new_x: u32, new_y: u32) {
     if new_x < monster.position.x {
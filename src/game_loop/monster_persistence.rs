@@ -0,0 +1,144 @@
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::lobby::Lobby;
+use crate::monsters::Monster;
+
+// Everything needed to put a lobby's overworld monsters back after a restart
+#[derive(Serialize, Deserialize)]
+struct LobbyMonsterSnapshot {
+    saved_at: u64,
+    monsters: Vec<Monster>,
+    monsters_by_spawn_point: HashMap<String, Vec<String>>, // Spawn point ID → Monster IDs
+}
+
+fn monsters_key(lobby_id: &str) -> String {
+    format!("lobby_monsters:{}", lobby_id)
+}
+
+async fn connection(redis_client: &redis::Client) -> Result<redis::aio::Connection, String> {
+    redis_client
+        .get_async_connection()
+        .await
+        .map_err(|e| format!("Redis connection error: {}", e))
+}
+
+// Copy the lobby's active monsters and the spawn point (ID → monster IDs) bookkeeping for them
+pub async fn collect_monsters(lobby: &Lobby) -> (Vec<Monster>, HashMap<String, Vec<String>>) {
+    let monster_mutexes: Vec<Arc<Mutex<Monster>>> = lobby.active_monsters.iter().map(|entry| entry.value().clone()).collect();
+    let mut monsters = Vec::with_capacity(monster_mutexes.len());
+    for monster_mutex in monster_mutexes {
+        let monster = monster_mutex.lock().await;
+        // Monsters on their way out would come back as ghosts
+        if !monster.despawning {
+            monsters.push(monster.clone());
+        }
+    }
+    let monsters_by_spawn_point = lobby.monsters_by_spawn_point.iter()
+        .map(|entry| {
+            let ids = entry.value().iter().filter(|id| monsters.iter().any(|monster| &monster.instance_id == *id)).cloned().collect();
+            (entry.key().clone(), ids)
+        })
+        .collect();
+    (monsters, monsters_by_spawn_point)
+}
+
+// Put monsters into the lobby along with their tiles and spawn points.
+// Monsters whose ID is already active or whose template is unknown are skipped; returns the ones placed.
+pub fn place_monsters(lobby: &Lobby, monsters: Vec<Monster>, monsters_by_spawn_point: HashMap<String, Vec<String>>) -> Vec<Monster> {
+    // Monsters saved before they tracked their spawn point get it back from the index
+    let origins: HashMap<&str, &str> = monsters_by_spawn_point.iter()
+        .flat_map(|(spawn_point_id, monster_ids)| monster_ids.iter().map(move |id| (id.as_str(), spawn_point_id.as_str())))
+        .collect();
+    let mut placed = Vec::new();
+    for mut monster in monsters {
+        if lobby.active_monsters.contains_key(&monster.instance_id) {
+            continue;
+        }
+        if !lobby.monster_manager.template_repository.templates.contains_key(&monster.template_id) {
+            warn!("Dropping saved monster {} of lobby {}: unknown template {}", monster.instance_id, lobby.id, monster.template_id);
+            continue;
+        }
+        // Battles are never part of saved state
+        monster.in_combat = false;
+        if monster.spawn_point_id.is_none() {
+            monster.spawn_point_id = origins.get(monster.instance_id.as_str()).map(|id| id.to_string());
+        }
+        lobby.occupancy.place_monster(&monster.instance_id, (monster.position.x, monster.position.y));
+        lobby.active_monsters.insert(monster.instance_id.clone(), Arc::new(Mutex::new(monster.clone())));
+        placed.push(monster);
+    }
+    for (spawn_point_id, monster_ids) in monsters_by_spawn_point {
+        let mut monster_ids: Vec<String> = monster_ids.into_iter().filter(|id| placed.iter().any(|monster| &monster.instance_id == id)).collect();
+        if !monster_ids.is_empty() {
+            lobby.monsters_by_spawn_point.entry(spawn_point_id).or_default().append(&mut monster_ids);
+        }
+    }
+    placed
+}
+
+// Write the lobby's active monsters and their spawn points to Redis, returning how many were saved
+pub async fn save_lobby_monsters(redis_client: &redis::Client, lobby: &Lobby) -> Result<usize, String> {
+    let (monsters, monsters_by_spawn_point) = collect_monsters(lobby).await;
+    let snapshot = LobbyMonsterSnapshot {
+        saved_at: chrono::Utc::now().timestamp() as u64,
+        monsters,
+        monsters_by_spawn_point,
+    };
+    let snapshot_json = serde_json::to_string(&snapshot)
+        .map_err(|e| format!("Failed to serialize monsters of lobby {}: {}", lobby.id, e))?;
+    let mut con = connection(redis_client).await?;
+    redis::cmd("SET")
+        .arg(monsters_key(&lobby.id))
+        .arg(snapshot_json)
+        .query_async::<_, ()>(&mut con)
+        .await
+        .map_err(|e| format!("Redis query error: {}", e))?;
+    Ok(snapshot.monsters.len())
+}
+
+// Put a lobby's saved monsters back into the world, returning how many were restored
+pub async fn restore_lobby_monsters(redis_client: &redis::Client, lobby: &Lobby) -> Result<usize, String> {
+    let mut con = connection(redis_client).await?;
+    let snapshot_json: Option<String> = redis::cmd("GET")
+        .arg(monsters_key(&lobby.id))
+        .query_async(&mut con)
+        .await
+        .map_err(|e| format!("Redis query error: {}", e))?;
+    let snapshot: LobbyMonsterSnapshot = match snapshot_json {
+        Some(json) => serde_json::from_str(&json)
+            .map_err(|e| format!("Failed to parse saved monsters of lobby {}: {}", lobby.id, e))?,
+        None => return Ok(0),
+    };
+
+    let restored = place_monsters(lobby, snapshot.monsters, snapshot.monsters_by_spawn_point).len();
+    info!("Restored {} monsters for lobby {} saved at {}", restored, lobby.id, snapshot.saved_at);
+    Ok(restored)
+}
+
+// Save the monsters of every lobby, e.g. right before shutting down
+pub async fn save_all_lobby_monsters(redis_client: &redis::Client, lobbies: &DashMap<String, Arc<Lobby>>) {
+    let lobbies: Vec<Arc<Lobby>> = lobbies.iter().map(|entry| entry.value().clone()).collect();
+    for lobby in lobbies {
+        match save_lobby_monsters(redis_client, &lobby).await {
+            Ok(saved) => info!("Saved {} monsters for lobby {}", saved, lobby.id),
+            Err(e) => error!("Failed to save monsters for lobby {}: {}", lobby.id, e),
+        }
+    }
+}
+
+// Periodically save every lobby's monsters so a crash loses at most one interval
+pub async fn run_monster_persistence(redis_client: redis::Client, lobbies: Arc<DashMap<String, Arc<Lobby>>>, save_interval: Duration) {
+    let mut interval = tokio::time::interval(save_interval);
+    // The first tick completes immediately, and the lobbies were just restored
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        save_all_lobby_monsters(&redis_client, &lobbies).await;
+    }
+}
@@ -1,10 +1,15 @@
+use dashmap::DashMap;
+use serde::Serialize;
 use std::sync::Arc;
 use tokio::time::{Duration, Instant};
-use tracing::info;
+use tracing::{info, warn};
 use rand::{seq::SliceRandom, rngs::SmallRng, SeedableRng, Rng};
 
+use crate::config::Config;
 use crate::models::ServerMessage;
 use crate::lobby::Lobby;
+use crate::game_loop::lobby_events::LobbyEventKind;
+use crate::monsters::monster_manager::{MonsterManager, SpawnPoint};
 
 // Configuration for monster spawner behavior
 pub struct SpawnerConfig {
@@ -14,6 +19,10 @@ pub struct SpawnerConfig {
     pub min_spawn_points: usize,
     // Sleep time between spawn cycles in milliseconds
     pub cycle_interval_ms: u64,
+    // How long a wild monster stays in the world before the rebalancer removes it
+    pub monster_ttl_secs: u64,
+    // Random extra lifetime added per monster so a batch of spawns doesn't vanish at once
+    pub monster_ttl_jitter_secs: u64,
 }
 
 impl Default for SpawnerConfig {
@@ -22,10 +31,127 @@ impl Default for SpawnerConfig {
             spawn_percentage: 0.3,  // Process 30% of spawn points per cycle
             min_spawn_points: 1,    // At least one spawn point per cycle
             cycle_interval_ms: 10000, // 10s between cycles
+            monster_ttl_secs: 300,    // Monsters linger for 5 minutes
+            monster_ttl_jitter_secs: 120,
         }
     }
 }
 
+impl SpawnerConfig {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            cycle_interval_ms: config.ticks.spawner_interval_ms,
+            ..Default::default()
+        }
+    }
+
+    // Spawn points picked per cycle on average, out of `total`
+    fn points_per_cycle(&self, total: usize) -> usize {
+        ((total as f32 * self.spawn_percentage).max(self.min_spawn_points as f32) as usize).min(total)
+    }
+}
+
+// How likely a spawn point is to be picked by the spawner, for tuning spawn_density
+#[derive(Serialize, Debug, Clone)]
+pub struct SpawnPointOdds {
+    pub spawn_point_id: String,
+    pub spawn_density: f32,
+    // Chance of being picked in one spawner cycle
+    pub selection_probability: f64,
+    pub monsters: usize,
+    pub max_monsters: u32,
+    // Picked points skip their spawn while cooling down after a capture or defeat
+    pub cooling_down: bool,
+}
+
+// Chance of each spawn point being picked in one cycle. On average `points_per_cycle` points
+// are picked, each with a chance proportional to its spawn_density (1.0 when unset). Points whose
+// share would pass certainty are always picked and the rest of the budget goes to the others.
+fn selection_probabilities(spawn_points: &[&SpawnPoint], config: &SpawnerConfig) -> Vec<f64> {
+    let weights: Vec<f64> = spawn_points.iter()
+        .map(|spawn_point| spawn_point.spawn_density.unwrap_or(1.0).max(0.0) as f64)
+        .collect();
+    let budget = config.points_per_cycle(spawn_points.len()) as f64;
+    let mut certain = vec![false; spawn_points.len()];
+    loop {
+        let remaining_budget = budget - certain.iter().filter(|certain| **certain).count() as f64;
+        let remaining_weight: f64 = weights.iter().zip(&certain).filter(|(_, certain)| !**certain).map(|(weight, _)| weight).sum();
+        let share = |weight: f64| if remaining_weight > 0.0 { (remaining_budget * weight / remaining_weight).max(0.0) } else { 0.0 };
+        let mut newly_certain = false;
+        for (weight, certain) in weights.iter().zip(certain.iter_mut()) {
+            if !*certain && share(*weight) >= 1.0 {
+                *certain = true;
+                newly_certain = true;
+            }
+        }
+        if !newly_certain {
+            return weights.iter().zip(&certain)
+                .map(|(weight, certain)| if *certain { 1.0 } else { share(*weight) })
+                .collect();
+        }
+    }
+}
+
+// Current odds of every spawn point of a lobby's map, most likely first, including active buffs
+pub fn spawn_point_odds(lobby: &Lobby, config: &SpawnerConfig) -> Vec<SpawnPointOdds> {
+    let spawn_points: Vec<&SpawnPoint> = lobby.monster_manager.map_data.spawn_points.values().collect();
+    let probabilities = selection_probabilities(&spawn_points, config);
+    let buff_multiplier = lobby.buffs.spawn_rate_multiplier() as f64;
+    let mut odds: Vec<SpawnPointOdds> = spawn_points.iter().zip(probabilities)
+        .map(|(spawn_point, selection_probability)| SpawnPointOdds {
+            spawn_point_id: spawn_point.id.clone(),
+            spawn_density: spawn_point.spawn_density.unwrap_or(1.0),
+            selection_probability: (selection_probability * buff_multiplier).min(1.0),
+            monsters: lobby.monsters_by_spawn_point.get(&spawn_point.id).map(|ids| ids.len()).unwrap_or(0),
+            max_monsters: spawn_point.max_monsters,
+            cooling_down: !lobby.respawn_cooldowns.spawn_point_ready(&spawn_point.id),
+        })
+        .collect();
+    odds.sort_by(|a, b| b.selection_probability.total_cmp(&a.selection_probability).then_with(|| a.spawn_point_id.cmp(&b.spawn_point_id)));
+    odds
+}
+
+// Per-lobby cooldowns started when a wild monster is captured or defeated, so the same
+// spawn point and species don't refill on the very next spawner cycle
+pub struct RespawnCooldowns {
+    spawn_point_cooldown: Duration,
+    species_cooldown: Duration,
+    spawn_points: DashMap<String, Instant>, // Spawn point ID → cooldown end
+    species: DashMap<u32, Instant>,         // Template ID → cooldown end
+}
+
+impl RespawnCooldowns {
+    pub fn new(spawn_point_cooldown_sec: u64, species_cooldown_sec: u64) -> Self {
+        Self {
+            spawn_point_cooldown: Duration::from_secs(spawn_point_cooldown_sec),
+            species_cooldown: Duration::from_secs(species_cooldown_sec),
+            spawn_points: DashMap::new(),
+            species: DashMap::new(),
+        }
+    }
+
+    // Start both cooldowns for a monster that was just captured or defeated
+    pub fn start(&self, spawn_point_id: Option<&str>, template_id: u32) {
+        let now = Instant::now();
+        if let Some(spawn_point_id) = spawn_point_id {
+            self.spawn_points.insert(spawn_point_id.to_string(), now + self.spawn_point_cooldown);
+        }
+        self.species.insert(template_id, now + self.species_cooldown);
+    }
+
+    pub fn spawn_point_ready(&self, spawn_point_id: &str) -> bool {
+        let now = Instant::now();
+        self.spawn_points.remove_if(spawn_point_id, |_, ends_at| *ends_at <= now);
+        !self.spawn_points.contains_key(spawn_point_id)
+    }
+
+    pub fn species_ready(&self, template_id: u32) -> bool {
+        let now = Instant::now();
+        self.species.remove_if(&template_id, |_, ends_at| *ends_at <= now);
+        !self.species.contains_key(&template_id)
+    }
+}
+
 // Handles monster spawning across all areas
 pub async fn run_monster_spawner(
     lobbies: Arc<dashmap::DashMap<String, Arc<Lobby>>>,
@@ -46,7 +172,7 @@ pub async fn run_monster_spawner(
             let monster_manager = lobby.monster_manager.clone();
             
             // Get all spawn points for this lobby's map
-            let all_spawn_points: Vec<_> = monster_manager.map_data.spawn_points.iter().collect();
+            let all_spawn_points: Vec<&SpawnPoint> = monster_manager.map_data.spawn_points.values().collect();
             
             // If we have no spawn points, skip this lobby
             if all_spawn_points.is_empty() {
@@ -54,49 +180,42 @@ pub async fn run_monster_spawner(
             }
             
             let mut rng = SmallRng::from_entropy();
+
+            // Clear out monsters that outlived their TTL and refill the emptiest spawn points
+            rebalance_spawn_points(&lobby, &monster_manager, &config, &mut rng).await;
+
+            // Let players know when incense or an event buff wears off
+            if lobby.buffs.prune_expired() {
+                lobby.broadcast_buffs();
+            }
+            let buff_multiplier = lobby.buffs.spawn_rate_multiplier();
             
-            // Filter spawn points based on their individual spawn density
+            // Pick this cycle's spawn points, each with a chance weighted by its spawn density and raised by buffs
+            let probabilities = selection_probabilities(&all_spawn_points, &config);
             let spawn_points_to_process = all_spawn_points.iter()
-                .filter(|(_, spawn_point)| {
-                    // If spawn_density is set, use it as probability to include this point
-                    if let Some(density) = spawn_point.spawn_density {
-                        let random_value: f32 = rng.gen();
-                        random_value <= density
-                    } else {
-                        // Otherwise use global percentage
-                        true
-                    }
-                })
+                .zip(probabilities)
+                .filter(|(_, probability)| rng.gen_bool((probability * buff_multiplier as f64).clamp(0.0, 1.0)))
+                .map(|(spawn_point, _)| *spawn_point)
                 .collect::<Vec<_>>();
             
-            // If using global percentage, select a subset based on config
-            let spawn_points_to_process = if spawn_points_to_process.len() > all_spawn_points.len() / 2 {
-                // Determine how many spawn points to process this cycle
-                let num_to_process = (all_spawn_points.len() as f32 * config.spawn_percentage).max(config.min_spawn_points as f32) as usize;
-                let num_to_process = num_to_process.min(all_spawn_points.len());
-                
-                spawn_points_to_process
-                    .choose_multiple(&mut rng, num_to_process)
-                    .copied()
-                    .collect::<Vec<_>>()
-            } else {
-                // If individual spawn densities filtered enough, use those
-                spawn_points_to_process
-            };
-            
             // Process selected spawn points for this lobby
-            for &(spawn_point_id, spawn_point) in &spawn_points_to_process {
+            for spawn_point in spawn_points_to_process {
+                let spawn_point_id = spawn_point.id.as_str();
                 let now = Instant::now();
                 let key = format!("{}:{}", lobby.id, spawn_point_id);
                 let last_spawn = last_spawn_attempts.entry(key).or_insert(Instant::now() - Duration::from_secs(spawn_point.spawn_interval_sec));
+                // Buffs shorten the wait between spawns
+                let spawn_interval = Duration::from_secs(spawn_point.spawn_interval_sec).div_f32(buff_multiplier.max(1.0));
                 
-                // Check if it's time to attempt a spawn
-                if now.duration_since(*last_spawn).as_secs() >= spawn_point.spawn_interval_sec {
+                // Check if it's time to attempt a spawn and the point isn't cooling down after a capture or defeat
+                if now.duration_since(*last_spawn) >= spawn_interval
+                    && lobby.respawn_cooldowns.spawn_point_ready(spawn_point_id)
+                {
                     // Update last spawn time
                     *last_spawn = now;
                     
                     // Skip if already at max monsters for this spawn point in this lobby
-                    let monsters_in_point = crate::monsters::monster_manager::MonsterManager::get_monsters_in_spawn_point(&lobby, spawn_point_id);
+                    let monsters_in_point = MonsterManager::get_monsters_in_spawn_point(&lobby, spawn_point_id);
                     if monsters_in_point.len() >= spawn_point.max_monsters as usize {
                         continue;
                     }
@@ -119,24 +238,11 @@ pub async fn run_monster_spawner(
                     let mut spawned_count = 0;
                     
                     for _ in 0..spawn_count {
-                        // Get a random monster template for this spawn point
-                        if let Some(template) = monster_manager.get_random_monster_for_spawn_point(spawn_point_id) {
-                            // Use the numeric ID directly
-                            if let Some(new_monster) = monster_manager.spawn_monster(template.id, spawn_point_id, &lobby).await {
-                                spawned_count += 1;
-                                info!("Spawned monster: {} (level {}) at spawn point {}, position: ({}, {}) in lobby {} [{}/{}]", 
-                                    new_monster.name, new_monster.level, spawn_point_id,
-                                    new_monster.position.x, new_monster.position.y, lobby.id,
-                                    spawned_count, spawn_count);
-                                
-                                // Notify only this lobby about the new monster
-                                let monster_spawn_msg = ServerMessage::MonsterSpawned { monster: new_monster.to_display() };
-                                let monster_json = serde_json::to_string(&monster_spawn_msg).unwrap();
-                                
-                                let _ = lobby.tx.send(monster_json);
-                            }
+                        if spawn_and_announce(&lobby, &monster_manager, spawn_point_id, &config, &mut rng).await {
+                            spawned_count += 1;
                         }
                     }
+                    info!("Spawned {}/{} monsters at spawn point {} in lobby {}", spawned_count, spawn_count, spawn_point_id, lobby.id);
                 }
             }
         }
@@ -144,4 +250,109 @@ pub async fn run_monster_spawner(
         // Sleep to avoid high CPU usage
         tokio::time::sleep(Duration::from_millis(config.cycle_interval_ms)).await;
     }
-} 
\ No newline at end of file
+}
+
+// Spawn one monster at a spawn point with a randomised lifetime and tell the lobby about it
+async fn spawn_and_announce(
+    lobby: &Arc<Lobby>,
+    monster_manager: &MonsterManager,
+    spawn_point_id: &str,
+    config: &SpawnerConfig,
+    rng: &mut SmallRng,
+) -> bool {
+    let species_ready = |template_id| lobby.respawn_cooldowns.species_ready(template_id);
+    let Some(template) = monster_manager.get_random_monster_for_spawn_point(spawn_point_id, lobby.weather(), species_ready) else {
+        return false;
+    };
+    let lifetime = config.monster_ttl_secs + rng.gen_range(0..=config.monster_ttl_jitter_secs);
+    let Some(new_monster) = monster_manager.spawn_monster(template.id, spawn_point_id, lobby, Some(lifetime)).await else {
+        return false;
+    };
+    info!("Spawned monster: {} (level {}) at spawn point {}, position: ({}, {}) in lobby {}",
+        new_monster.name, new_monster.level, spawn_point_id,
+        new_monster.position.x, new_monster.position.y, lobby.id);
+    lobby.events.record(LobbyEventKind::MonsterSpawned {
+        instance_id: new_monster.instance_id.clone(),
+        template_id: new_monster.template_id,
+        level: new_monster.level,
+        spawn_point_id: spawn_point_id.to_string(),
+    });
+
+    // Notify only this lobby about the new monster
+    let monster_spawn_msg = ServerMessage::MonsterSpawned { monster: new_monster.to_display() };
+    let monster_json = serde_json::to_string(&monster_spawn_msg).unwrap();
+    let _ = lobby.tx.send(monster_json);
+
+    if new_monster.is_alpha {
+        info!("Alpha {} (level {}) appeared at spawn point {} in lobby {}", new_monster.name, new_monster.level, spawn_point_id, lobby.id);
+        let alpha_msg = ServerMessage::AlphaSpawned {
+            instance_id: new_monster.instance_id.clone(),
+            template_id: new_monster.template_id,
+            name: new_monster.name.clone(),
+            level: new_monster.level,
+            position: new_monster.position.clone(),
+        };
+        let _ = lobby.tx.send(serde_json::to_string(&alpha_msg).unwrap());
+    }
+    true
+}
+
+// Despawn monsters whose TTL ran out (skipping any in battle), then spend the freed
+// capacity on the least populated spawn points, weighted by their spawn_density
+async fn rebalance_spawn_points(
+    lobby: &Arc<Lobby>,
+    monster_manager: &MonsterManager,
+    config: &SpawnerConfig,
+    rng: &mut SmallRng,
+) {
+    let now = chrono::Utc::now().timestamp() as u64;
+    let expired: Vec<String> = lobby.active_monsters.iter()
+        .filter(|entry| match entry.value().try_lock() {
+            Ok(monster) => !monster.in_combat && monster.despawn_time.is_some_and(|despawn_time| despawn_time <= now),
+            // Locked monsters are busy (moving or entering a battle); check them next cycle
+            Err(_) => false,
+        })
+        .map(|entry| entry.key().clone())
+        .collect();
+    if expired.is_empty() {
+        return;
+    }
+
+    let mut despawned = 0;
+    for instance_id in &expired {
+        if monster_manager.despawn_monster(instance_id, lobby).await.is_none() {
+            continue;
+        }
+        despawned += 1;
+        let despawn_msg = ServerMessage::MonsterDespawned { instance_id: instance_id.clone() };
+        if let Err(e) = lobby.broadcast_except(&despawn_msg, &[]).await {
+            warn!("Failed to broadcast despawn of expired monster {}: {}", instance_id, e);
+        }
+    }
+    info!("Despawned {} expired monsters in lobby {}", despawned, lobby.id);
+
+    for _ in 0..despawned {
+        let Some(spawn_point_id) = pick_underpopulated_spawn_point(lobby, monster_manager, rng) else {
+            break;
+        };
+        spawn_and_announce(lobby, monster_manager, &spawn_point_id, config, rng).await;
+    }
+}
+
+// Choose a spawn point with free capacity, favouring emptier points and higher spawn_density
+fn pick_underpopulated_spawn_point(lobby: &Arc<Lobby>, monster_manager: &MonsterManager, rng: &mut SmallRng) -> Option<String> {
+    let weight = |spawn_point: &SpawnPoint| {
+        if spawn_point.max_monsters == 0 || !lobby.respawn_cooldowns.spawn_point_ready(&spawn_point.id) {
+            return 0.0;
+        }
+        let occupied = lobby.monsters_by_spawn_point.get(&spawn_point.id).map(|ids| ids.len()).unwrap_or(0) as f32;
+        let free_share = (1.0 - occupied / spawn_point.max_monsters as f32).max(0.0);
+        free_share * spawn_point.spawn_density.unwrap_or(1.0)
+    };
+    let candidates: Vec<&SpawnPoint> = monster_manager.map_data.spawn_points.values().collect();
+    candidates
+        .choose_weighted(rng, |spawn_point| weight(spawn_point))
+        .ok()
+        .filter(|spawn_point| weight(spawn_point) > 0.0)
+        .map(|spawn_point| spawn_point.id.clone())
+}
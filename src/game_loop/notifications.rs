@@ -0,0 +1,112 @@
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{debug, error};
+
+use crate::game_loop::player_settings::{NotificationKind, PlayerSettingsManager};
+use crate::lobby::{find_player_lobby, Lobby};
+use crate::models::ServerMessage;
+
+// Most notifications kept for an offline player; older ones are dropped first
+const MAX_DIGEST_ENTRIES: isize = 50;
+
+// A notification that arrived while the player was offline
+#[derive(Serialize, Deserialize, Debug, schemars::JsonSchema)]
+pub struct MissedNotification {
+    pub kind: NotificationKind,
+    pub received_at: u64,
+    pub message: ServerMessage,
+}
+
+// Sends notifications the player has not turned off, and keeps the ones they missed
+// while offline for a digest at their next login
+pub struct NotificationManager {
+    redis_client: redis::Client,
+    player_settings_manager: Arc<PlayerSettingsManager>,
+}
+
+impl NotificationManager {
+    pub fn new(redis_client: redis::Client, player_settings_manager: Arc<PlayerSettingsManager>) -> Arc<Self> {
+        Arc::new(Self { redis_client, player_settings_manager })
+    }
+
+    fn digest_key(player_id: &str) -> String {
+        format!("notification_digest:{}", player_id)
+    }
+
+    async fn connection(&self) -> Result<redis::aio::Connection, String> {
+        self.redis_client
+            .get_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection error: {}", e))
+    }
+
+    // Send a notification if the player wants this kind, or keep it for their digest if they are offline
+    pub async fn notify(&self, lobbies: &DashMap<String, Arc<Lobby>>, player_id: &str, kind: NotificationKind, message: ServerMessage) {
+        match self.player_settings_manager.get_settings(player_id).await {
+            Ok(settings) if !settings.notifications.wants(kind) => {
+                debug!("Player {} turned off {:?} notifications", player_id, kind);
+                return;
+            }
+            Ok(_) => {}
+            // Fall back to sending: a missed opt-out is better than a lost notification
+            Err(e) => error!("Failed to load notification preferences of player {}: {}", player_id, e),
+        }
+
+        if let Some(lobby) = find_player_lobby(lobbies, player_id) {
+            match lobby.send_to_player(player_id, &message).await {
+                Ok(()) => return,
+                Err(e) => error!("Failed to send {:?} notification to player {}: {}", kind, player_id, e),
+            }
+        }
+        // Unread mail is already delivered from the mailbox at login
+        if kind == NotificationKind::MailReceived {
+            return;
+        }
+        let missed = MissedNotification {
+            kind,
+            received_at: chrono::Utc::now().timestamp() as u64,
+            message,
+        };
+        if let Err(e) = self.queue_missed(player_id, &missed).await {
+            error!("Failed to keep {:?} notification for player {}: {}", kind, player_id, e);
+        }
+    }
+
+    async fn queue_missed(&self, player_id: &str, missed: &MissedNotification) -> Result<(), String> {
+        let json = serde_json::to_string(missed)
+            .map_err(|e| format!("Failed to serialize notification: {}", e))?;
+        let key = Self::digest_key(player_id);
+        let mut con = self.connection().await?;
+        redis::pipe()
+            .cmd("RPUSH").arg(&key).arg(json).ignore()
+            .cmd("LTRIM").arg(&key).arg(-MAX_DIGEST_ENTRIES).arg(-1).ignore()
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))
+    }
+
+    // Take every notification the player missed while offline, oldest first
+    pub async fn take_digest(&self, player_id: &str) -> Result<Vec<MissedNotification>, String> {
+        let key = Self::digest_key(player_id);
+        let mut con = self.connection().await?;
+        let (saved, _): (Vec<String>, ()) = redis::pipe()
+            .atomic()
+            .cmd("LRANGE").arg(&key).arg(0).arg(-1)
+            .cmd("DEL").arg(&key).ignore()
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+
+        Ok(saved
+            .iter()
+            .filter_map(|json| match serde_json::from_str(json) {
+                Ok(missed) => Some(missed),
+                Err(e) => {
+                    error!("Failed to deserialize missed notification: {}", e);
+                    None
+                }
+            })
+            .collect())
+    }
+}
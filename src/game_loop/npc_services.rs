@@ -0,0 +1,222 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::combat::state::BattleMoveView;
+use crate::game_loop::currency::{CurrencyManager, TransactionReason};
+use crate::game_loop::inventory::InventoryManager;
+use crate::game_loop::pokemon_collection::{PokemonCollectionManager, PokemonUpdate};
+use crate::lobby::Lobby;
+use crate::monsters::monster_manager::NpcService;
+
+// Fee charged by the name rater to rename a Pokemon
+pub const NAME_RATER_FEE: u64 = 500;
+// Fee charged by the move relearner per move
+pub const MOVE_RELEARNER_FEE: u64 = 1000;
+
+// Items the shopkeeper stocks and their prices
+const SHOP_ITEMS: &[(&str, u64)] = &[
+    ("poke_ball", 200),
+    ("great_ball", 600),
+    ("ultra_ball", 1200),
+    ("potion", 300),
+    ("super_potion", 700),
+    ("hyper_potion", 1200),
+    ("full_restore", 3000),
+    ("antidote", 100),
+    ("awakening", 250),
+    ("paralyze_heal", 200),
+    ("burn_heal", 250),
+    ("ice_heal", 250),
+    ("full_heal", 600),
+];
+// The shopkeeper buys stocked items back for this fraction of their price
+const SELL_PRICE_DIVISOR: u64 = 2;
+// Most of one item bought or sold at once
+const MAX_SHOP_QUANTITY: u32 = 99;
+
+// An item on sale at the shopkeeper
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+pub struct ShopItem {
+    pub item_id: String,
+    pub price: u64,
+    // What the shopkeeper pays for one
+    pub sell_price: u64,
+}
+
+pub fn shop_items() -> Vec<ShopItem> {
+    SHOP_ITEMS.iter()
+        .map(|(item_id, price)| ShopItem { item_id: item_id.to_string(), price: *price, sell_price: price / SELL_PRICE_DIVISOR })
+        .collect()
+}
+
+fn shop_price(item_id: &str) -> Option<u64> {
+    SHOP_ITEMS.iter().find(|(id, _)| *id == item_id).map(|(_, price)| *price)
+}
+
+// Price of `quantity` of a stocked item at `unit_price`, checking the quantity
+fn shop_total(unit_price: u64, quantity: u32) -> Result<u64, String> {
+    if quantity == 0 || quantity > MAX_SHOP_QUANTITY {
+        return Err(format!("Quantity must be between 1 and {}", MAX_SHOP_QUANTITY));
+    }
+    Ok(unit_price * quantity as u64)
+}
+
+// Server-side NPC services that spend the player's money on their Pokemon and items
+pub struct NpcServices {
+    pokemon_collection_manager: Arc<PokemonCollectionManager>,
+    currency_manager: Arc<CurrencyManager>,
+    inventory_manager: Arc<InventoryManager>,
+}
+
+impl NpcServices {
+    pub fn new(
+        pokemon_collection_manager: Arc<PokemonCollectionManager>,
+        currency_manager: Arc<CurrencyManager>,
+        inventory_manager: Arc<InventoryManager>,
+    ) -> Self {
+        Self {
+            pokemon_collection_manager,
+            currency_manager,
+            inventory_manager,
+        }
+    }
+
+    // Rename a Pokemon for a fee, returning the player's new balance
+    pub async fn rate_name(&self, lobby: &Lobby, player_id: &str, pokemon_id: &str, name: &str) -> Result<u64, String> {
+        Self::check_in_reach(lobby, player_id, NpcService::NameRater)?;
+        let name = PokemonCollectionManager::validate_nickname(name)?;
+        // Make sure the Pokemon exists before taking any money
+        self.pokemon_collection_manager.get_pokemon(player_id, pokemon_id).await?;
+
+        let balance = self.currency_manager.debit(player_id, NAME_RATER_FEE, TransactionReason::NameRater).await?;
+        let update = PokemonUpdate {
+            name: Some(name),
+            ..Default::default()
+        };
+        if let Err(e) = self.pokemon_collection_manager.update_pokemon(player_id, pokemon_id, &update).await {
+            self.refund(player_id, NAME_RATER_FEE, TransactionReason::NameRater, &e).await;
+            return Err(e);
+        }
+
+        info!("Player {} renamed pokemon {} at the name rater", player_id, pokemon_id);
+        Ok(balance)
+    }
+
+    // Moves the relearner can teach this Pokemon
+    pub async fn relearnable_moves(&self, player_id: &str, pokemon_id: &str) -> Result<Vec<BattleMoveView>, String> {
+        let pokemon = self.pokemon_collection_manager.get_pokemon(player_id, pokemon_id).await?;
+        let manager = &self.pokemon_collection_manager;
+        Ok(manager.relearnable_moves(&pokemon)
+            .into_iter()
+            .filter_map(|move_id| manager.learnable_move_view(move_id))
+            .collect())
+    }
+
+    // Teach a forgotten level-up move for a fee, returning the player's new balance
+    pub async fn relearn_move(
+        &self,
+        lobby: &Lobby,
+        player_id: &str,
+        pokemon_id: &str,
+        move_id: u32,
+        replace_index: Option<usize>,
+    ) -> Result<u64, String> {
+        Self::check_in_reach(lobby, player_id, NpcService::MoveRelearner)?;
+        let pokemon = self.pokemon_collection_manager.get_pokemon(player_id, pokemon_id).await?;
+        if !self.pokemon_collection_manager.relearnable_moves(&pokemon).contains(&move_id) {
+            return Err(format!("{} cannot relearn move {}", pokemon.name, move_id));
+        }
+
+        let balance = self.currency_manager.debit(player_id, MOVE_RELEARNER_FEE, TransactionReason::MoveRelearner).await?;
+        if let Err(e) = self.pokemon_collection_manager.relearn_move(player_id, pokemon_id, move_id, replace_index).await {
+            self.refund(player_id, MOVE_RELEARNER_FEE, TransactionReason::MoveRelearner, &e).await;
+            return Err(e);
+        }
+
+        Ok(balance)
+    }
+
+    // Buy items from the shopkeeper, returning the player's new balance and the price paid
+    pub async fn buy_item(&self, lobby: &Lobby, player_id: &str, item_id: &str, quantity: u32) -> Result<(u64, u64), String> {
+        Self::check_in_reach(lobby, player_id, NpcService::Shopkeeper)?;
+        let price = shop_price(item_id).ok_or_else(|| format!("The shop does not sell {}", item_id))?;
+        let total = shop_total(price, quantity)?;
+
+        let balance = self.currency_manager.debit(player_id, total, TransactionReason::ShopPurchase).await?;
+        if let Err(e) = self.inventory_manager.add_item(player_id, item_id, quantity).await {
+            self.refund(player_id, total, TransactionReason::ShopPurchase, &e).await;
+            return Err(e);
+        }
+
+        info!("Player {} bought {} x{} for {}", player_id, item_id, quantity, total);
+        Ok((balance, total))
+    }
+
+    // Sell stocked items back to the shopkeeper, returning the player's new balance and the amount paid out
+    pub async fn sell_item(&self, lobby: &Lobby, player_id: &str, item_id: &str, quantity: u32) -> Result<(u64, u64), String> {
+        Self::check_in_reach(lobby, player_id, NpcService::Shopkeeper)?;
+        let price = shop_price(item_id).ok_or_else(|| format!("The shop does not buy {}", item_id))?;
+        let total = shop_total(price / SELL_PRICE_DIVISOR, quantity)?;
+
+        self.inventory_manager.remove_item(player_id, item_id, quantity).await?;
+        let balance = match self.currency_manager.credit(player_id, total, TransactionReason::ItemSold).await {
+            Ok(balance) => balance,
+            Err(e) => {
+                error!("Failed to pay player {} for {} x{}, returning the items: {}", player_id, item_id, quantity, e);
+                if let Err(e) = self.inventory_manager.add_item(player_id, item_id, quantity).await {
+                    error!("Failed to return {} x{} to player {}: {}", item_id, quantity, player_id, e);
+                }
+                return Err(e);
+            }
+        };
+
+        info!("Player {} sold {} x{} for {}", player_id, item_id, quantity, total);
+        Ok((balance, total))
+    }
+
+    // Check the player is standing next to an NPC offering the service
+    fn check_in_reach(lobby: &Lobby, player_id: &str, service: NpcService) -> Result<(), String> {
+        let player = lobby.player_positions.get(player_id)
+            .ok_or_else(|| format!("Player {} not found in lobby", player_id))?;
+        let in_reach = lobby.monster_manager.map_data.npcs.values().any(|npc| {
+            npc.service == service && npc.tile_x.abs_diff(player.x) <= 1 && npc.tile_y.abs_diff(player.y) <= 1
+        });
+        if !in_reach {
+            return Err(match service {
+                NpcService::NameRater => "Too far away from the name rater".to_string(),
+                NpcService::MoveRelearner => "Too far away from the move relearner".to_string(),
+                NpcService::Shopkeeper => "Too far away from the shopkeeper".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    // Give the fee back when the paid service could not be performed
+    async fn refund(&self, player_id: &str, fee: u64, reason: TransactionReason, cause: &str) {
+        error!("NPC service {:?} failed for player {}, refunding {}: {}", reason, player_id, fee, cause);
+        if let Err(e) = self.currency_manager.credit(player_id, fee, reason).await {
+            error!("Failed to refund player {}: {}", player_id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shop_buys_items_back_at_half_price() {
+        let great_ball = shop_items().into_iter().find(|item| item.item_id == "great_ball").unwrap();
+        assert_eq!((great_ball.price, great_ball.sell_price), (600, 300));
+        assert_eq!(shop_price("master_ball"), None);
+    }
+
+    #[test]
+    fn shop_quantities_are_bounded() {
+        assert_eq!(shop_total(200, 5), Ok(1000));
+        assert!(shop_total(200, 0).is_err());
+        assert_eq!(shop_total(200, MAX_SHOP_QUANTITY), Ok(200 * MAX_SHOP_QUANTITY as u64));
+        assert!(shop_total(200, MAX_SHOP_QUANTITY + 1).is_err());
+    }
+}
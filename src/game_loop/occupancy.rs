@@ -0,0 +1,56 @@
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+
+// What happens when a player walks into a tile taken by a wild monster
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MonsterCollision {
+    // Players walk through monsters
+    Off,
+    // The move is refused and the player stays where they are
+    #[default]
+    Block,
+    // The player stays put and starts an encounter with the monster
+    Interact,
+}
+
+impl MonsterCollision {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "off" => Some(MonsterCollision::Off),
+            "block" => Some(MonsterCollision::Block),
+            "interact" => Some(MonsterCollision::Interact),
+            _ => None,
+        }
+    }
+}
+
+// Tiles taken by the monsters of a lobby, kept in step with spawns, moves and despawns.
+// Player tiles are read straight from the lobby's player positions.
+#[derive(Default)]
+pub struct TileOccupancy {
+    monsters: DashMap<(u32, u32), String>, // Tile → monster instance ID
+}
+
+impl TileOccupancy {
+    pub fn place_monster(&self, monster_id: &str, tile: (u32, u32)) {
+        self.monsters.insert(tile, monster_id.to_string());
+    }
+
+    pub fn move_monster(&self, monster_id: &str, from: (u32, u32), to: (u32, u32)) {
+        if from == to {
+            return;
+        }
+        self.remove_monster(monster_id, from);
+        self.place_monster(monster_id, to);
+    }
+
+    // Only clears the tile if this monster is still the one recorded on it
+    pub fn remove_monster(&self, monster_id: &str, tile: (u32, u32)) {
+        self.monsters.remove_if(&tile, |_, occupant| occupant == monster_id);
+    }
+
+    pub fn monster_at(&self, tile: (u32, u32)) -> Option<String> {
+        self.monsters.get(&tile).map(|entry| entry.value().clone())
+    }
+}
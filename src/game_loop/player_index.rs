@@ -0,0 +1,68 @@
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+
+// Most players returned by one search
+pub const MAX_SEARCH_RESULTS: usize = 20;
+
+// What a connected player is doing right now
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Presence {
+    Online,
+    Afk,
+    InBattle,
+}
+
+// A connected player found by a search
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+pub struct PlayerSearchResult {
+    pub player_id: String,
+    pub username: String,
+    pub lobby_id: String,
+    // Lowest and highest level in the player's party, None if it is empty
+    pub level_range: Option<(u32, u32)>,
+    pub presence: Presence,
+}
+
+#[derive(Clone, Debug)]
+pub struct IndexedPlayer {
+    pub player_id: String,
+    pub username: String,
+    pub lobby_id: String,
+}
+
+// Players connected to any lobby, for searching them by username
+#[derive(Default)]
+pub struct PlayerIndex {
+    players: DashMap<String, IndexedPlayer>, // Player ID → username and lobby
+}
+
+impl PlayerIndex {
+    pub fn insert(&self, player_id: &str, username: &str, lobby_id: &str) {
+        self.players.insert(player_id.to_string(), IndexedPlayer {
+            player_id: player_id.to_string(),
+            username: username.to_string(),
+            lobby_id: lobby_id.to_string(),
+        });
+    }
+
+    pub fn get(&self, player_id: &str) -> Option<IndexedPlayer> {
+        self.players.get(player_id).map(|entry| entry.value().clone())
+    }
+
+    pub fn remove(&self, player_id: &str) {
+        self.players.remove(player_id);
+    }
+
+    // Players whose username starts with `query`, ignoring case, sorted by username.
+    // An empty query matches everyone online.
+    pub fn search(&self, query: &str) -> Vec<IndexedPlayer> {
+        let query = query.to_lowercase();
+        let mut matches: Vec<IndexedPlayer> = self.players.iter()
+            .filter(|entry| entry.username.to_lowercase().starts_with(&query))
+            .map(|entry| entry.value().clone())
+            .collect();
+        matches.sort_by_key(|player| player.username.to_lowercase());
+        matches
+    }
+}
@@ -7,11 +7,14 @@ use tracing::{info, warn};
 
 use crate::models::{PlayerState, ServerMessage};
 use crate::app_state::AppState;
+use crate::combat::listeners::{BattleOutcomeListener, PvPBattleSummary, WildBattleSummary};
+use crate::game_loop::occupancy::MonsterCollision;
 use crate::lobby::Lobby;
 // Constants for player movement
-const MOVEMENT_UPDATE_INTERVAL_MS: u64 = 50; // Send updates every 100ms
-const MAX_PLAYER_SPEED: u32 = 3; // Maximum allowed movement in a single validation step
+const MAX_STEP_DISTANCE: u32 = 1; // A move may only go to an adjacent tile (or turn in place)
 const MIN_MOVEMENT_VALIDATION_INTERVAL_MS: u64 = 50; // Minimum time between movement validations
+const ENCOUNTER_IMMUNITY: Duration = Duration::from_secs(10); // Grace period after a battle before aggressive monsters engage again
+pub const SURF_ITEM_ID: &str = "hm_surf"; // Key item that lets players cross water
 
 pub struct PlayerMovementManager {
     // Track which players have moved since the last update
@@ -20,6 +23,8 @@ pub struct PlayerMovementManager {
     last_validated_positions: DashMap<String, (PlayerState, Instant)>,
     // Track last broadcast state for each player
     last_broadcast_states: DashMap<String, PlayerState>,
+    // Players aggressive monsters leave alone until the given time
+    encounter_immunity: DashMap<String, Instant>,
 }
 
 impl PlayerMovementManager {
@@ -28,43 +33,118 @@ impl PlayerMovementManager {
             moved_players: DashSet::new(),
             last_validated_positions: DashMap::new(),
             last_broadcast_states: DashMap::new(),
+            encounter_immunity: DashMap::new(),
         }
     }
 
-    // Validate a player movement request
-    pub fn validate_movement(&self, player_id: &str, current_state: &PlayerState, new_x: u32, new_y: u32) -> bool {
+    // Keep aggressive monsters from engaging a player for a while
+    pub fn grant_encounter_immunity(&self, player_id: &str) {
+        self.encounter_immunity.insert(player_id.to_string(), Instant::now() + ENCOUNTER_IMMUNITY);
+    }
+
+    // Forget everything tracked about a player who left
+    pub fn remove_player(&self, player_id: &str) {
+        self.moved_players.remove(player_id);
+        self.last_validated_positions.remove(player_id);
+        self.last_broadcast_states.remove(player_id);
+        self.encounter_immunity.remove(player_id);
+    }
+
+    // Drop records that are no longer needed: expired encounter immunities and the positions
+    // of players who have not moved for `max_idle`. Every map holds at most one record per
+    // player, so this keeps the total bounded by the players active recently.
+    pub fn prune(&self, max_idle: Duration) {
+        let now = Instant::now();
+        self.encounter_immunity.retain(|_, until| now < *until);
+        let idle: Vec<String> = self.last_validated_positions.iter()
+            .filter(|entry| now.duration_since(entry.value().1) > max_idle)
+            .map(|entry| entry.key().clone())
+            .collect();
+        for player_id in idle {
+            self.last_validated_positions.remove(&player_id);
+            self.last_broadcast_states.remove(&player_id);
+        }
+    }
+
+    // Whether anything about the player's movement is still tracked
+    #[cfg(test)]
+    pub(crate) fn is_tracking(&self, player_id: &str) -> bool {
+        self.moved_players.contains(player_id)
+            || self.last_validated_positions.contains_key(player_id)
+            || self.last_broadcast_states.contains_key(player_id)
+            || self.encounter_immunity.contains_key(player_id)
+    }
+
+    fn is_immune_to_encounters(&self, player_id: &str) -> bool {
+        self.encounter_immunity.get(player_id).is_some_and(|until| Instant::now() < *until)
+    }
+
+    // First aggressive monster next to (or on) the player's tile that is free to battle
+    fn adjacent_aggressive_monster(&self, lobby: &Lobby, player: &PlayerState) -> Option<String> {
+        if player.in_combat || self.is_immune_to_encounters(&player.id) {
+            return None;
+        }
+        let tiles = [
+            (Some(player.x), Some(player.y)),
+            (player.x.checked_sub(1), Some(player.y)),
+            (player.x.checked_add(1), Some(player.y)),
+            (Some(player.x), player.y.checked_sub(1)),
+            (Some(player.x), player.y.checked_add(1)),
+        ];
+        tiles.into_iter()
+            .filter_map(|(x, y)| Some((x?, y?)))
+            .filter_map(|tile| lobby.occupancy.monster_at(tile))
+            .find(|monster_id| {
+                let monster_entry = match lobby.active_monsters.get(monster_id) {
+                    Some(entry) => entry,
+                    None => return false,
+                };
+                let monster = match monster_entry.value().try_lock() {
+                    Ok(monster) => monster,
+                    Err(_) => return false,
+                };
+                let aggressive = monster.is_alpha || lobby.monster_manager.template_repository.templates
+                    .get(&monster.template_id)
+                    .is_some_and(|template| template.aggressive);
+                aggressive && monster.is_available()
+            })
+    }
+
+    // Validate a player movement request against the player's current tile and the lobby map.
+    // Returns the tile the player ends up on, which is past the requested one after jumping a
+    // ledge or sliding over ice, or None if the move is not allowed.
+    pub fn validate_movement(&self, player_id: &str, current_state: &PlayerState, new_x: u32, new_y: u32, lobby: &Lobby, can_surf: bool) -> Option<(u32, u32)> {
         // If player is in combat, prevent movement
         if current_state.in_combat {
-            return false;
+            return None;
         }
         
-        // Get the last validated position and timestamp
+        // Ensure minimum time between validations
         if let Some(entry) = self.last_validated_positions.get(player_id) {
-            let (last_state, last_update_time) = entry.value();
-            let time_since_last_update = last_update_time.elapsed().as_millis() as u64;
-            
-            // Ensure minimum time between validations
-            if time_since_last_update < MIN_MOVEMENT_VALIDATION_INTERVAL_MS {
-                return false;
-            }
-            
-            // Calculate distance moved
-            let dx = if new_x > last_state.x { new_x - last_state.x } else { last_state.x - new_x };
-            let dy = if new_y > last_state.y { new_y - last_state.y } else { last_state.y - new_y };
-            
-            // Check if movement is within allowed speed
-            let allowed_distance = MAX_PLAYER_SPEED * (1 + (time_since_last_update as u32 / 100));
-            if dx > allowed_distance || dy > allowed_distance {
-                warn!("Player {} attempted to move too quickly: dx={}, dy={}, allowed={}", 
-                    player_id, dx, dy, allowed_distance);
-                return false;
+            let (_, last_update_time) = entry.value();
+            if (last_update_time.elapsed().as_millis() as u64) < MIN_MOVEMENT_VALIDATION_INTERVAL_MS {
+                return None;
             }
         }
         
-        // Check map boundaries and collisions if needed
-        // (implement collision detection here if needed)
+        // Only single steps from where the server has the player; anything further is a teleport
+        let distance = new_x.abs_diff(current_state.x) + new_y.abs_diff(current_state.y);
+        if distance > MAX_STEP_DISTANCE {
+            warn!("Player {} attempted to move {} tiles from ({}, {}) to ({}, {})",
+                player_id, distance, current_state.x, current_state.y, new_x, new_y);
+            return None;
+        }
         
-        true
+        // Stay inside the map, off obstacle tiles, out of gates the player hasn't cleared and on terrain the player can cross
+        let from = (current_state.x, current_state.y);
+        let destination = lobby.monster_manager.map_data.resolve_player_step(from, (new_x, new_y), can_surf, |tile| {
+            (lobby.monster_collision != MonsterCollision::Off && lobby.occupancy.monster_at(tile).is_some())
+                || lobby.is_gated_for(player_id, tile.0, tile.1)
+        });
+        if destination.is_none() {
+            warn!("Player {} attempted to move onto blocked, impassable or out-of-map tile ({}, {})", player_id, new_x, new_y);
+        }
+        destination
     }
 
     // Check if player state has meaningful changes compared to last broadcast
@@ -116,7 +196,7 @@ impl PlayerMovementManager {
 // Main task to periodically send movement updates
 pub async fn run_player_movement_controller(app_state: Arc<AppState>, movement_manager: Arc<PlayerMovementManager>) {
     info!("Starting player movement controller");
-    let mut interval = time::interval(Duration::from_millis(MOVEMENT_UPDATE_INTERVAL_MS));
+    let mut interval = time::interval(Duration::from_millis(app_state.config.ticks.player_movement_interval_ms));
     
     loop {
         interval.tick().await;
@@ -127,13 +207,31 @@ pub async fn run_player_movement_controller(app_state: Arc<AppState>, movement_m
             
             // Get players that have moved
             let moved_players = movement_manager.get_moved_players(lobby);
+
+            // Aggressive monsters engage players who stopped next to them
+            if app_state.config.game.aggressive_encounters {
+                for player in &moved_players {
+                    if let Some(monster_id) = movement_manager.adjacent_aggressive_monster(lobby, player) {
+                        // Until the battle starts, keep the next ticks from engaging the player again
+                        movement_manager.grant_encounter_immunity(&player.id);
+                        info!("Aggressive monster {} engaged player {} in lobby {}", monster_id, player.id, lobby.id);
+                        let app_state = app_state.clone();
+                        let lobby_id = lobby.id.clone();
+                        let player_id = player.id.clone();
+                        tokio::spawn(async move {
+                            crate::handlers::handle_player_interaction(&app_state, &lobby_id, &player_id, &Some(monster_id)).await;
+                        });
+                    }
+                }
+            }
             
             // Only send updates if there are players that moved
             if !moved_players.is_empty() {
                 // Create batch update message
                 let batch_update = ServerMessage::PlayersMoved { 
                     players: moved_players.clone(),
-                    timestamp: Utc::now().timestamp_millis() as u64
+                    timestamp: Utc::now().timestamp_millis() as u64,
+                    tick: app_state.clock.tick(),
                 };
                 
                 // Send the batch update to all players in the lobby
@@ -147,4 +245,86 @@ pub async fn run_player_movement_controller(app_state: Arc<AppState>, movement_m
         // Clear the moved players list
         movement_manager.clear_moved_players();
     }
-} 
\ No newline at end of file
+}
+
+// Gives players a grace period from aggressive monsters after every battle
+impl BattleOutcomeListener for PlayerMovementManager {
+    fn on_wild_battle_finished(&self, summary: &WildBattleSummary) {
+        self.grant_encounter_immunity(&summary.player_id);
+    }
+
+    fn on_pvp_battle_finished(&self, summary: &PvPBattleSummary) {
+        self.grant_encounter_immunity(&summary.player1_id);
+        self.grant_encounter_immunity(&summary.player2_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monsters::monster_manager::MapData;
+
+    const MAP_WIDTH: u32 = 4;
+    const MAP_HEIGHT: u32 = 3;
+    const OBSTACLE: (u32, u32) = (1, 1);
+
+    fn lobby() -> Lobby {
+        Lobby::for_tests(MapData::for_tests(MAP_WIDTH as usize, MAP_HEIGHT as usize, &[OBSTACLE]))
+    }
+
+    fn player_at(x: u32, y: u32) -> PlayerState {
+        PlayerState { id: "player".to_string(), username: "player".to_string(), x, y, direction: "down".to_string(), in_combat: false, afk: false }
+    }
+
+    // Validate a single move of a player who has not moved before
+    fn validate(from: (u32, u32), to: (u32, u32)) -> Option<(u32, u32)> {
+        PlayerMovementManager::new().validate_movement("player", &player_at(from.0, from.1), to.0, to.1, &lobby(), false)
+    }
+
+    #[test]
+    fn steps_onto_the_last_tiles_inside_the_map() {
+        assert_eq!(validate((MAP_WIDTH - 2, 0), (MAP_WIDTH - 1, 0)), Some((MAP_WIDTH - 1, 0)));
+        assert_eq!(validate((0, MAP_HEIGHT - 2), (0, MAP_HEIGHT - 1)), Some((0, MAP_HEIGHT - 1)));
+    }
+
+    #[test]
+    fn rejects_steps_onto_the_map_width_and_height() {
+        assert_eq!(validate((MAP_WIDTH - 1, 0), (MAP_WIDTH, 0)), None);
+        assert_eq!(validate((0, MAP_HEIGHT - 1), (0, MAP_HEIGHT)), None);
+    }
+
+    #[test]
+    fn rejects_steps_onto_obstacles() {
+        assert_eq!(validate((OBSTACLE.0 - 1, OBSTACLE.1), OBSTACLE), None);
+        assert_eq!(validate((OBSTACLE.0, OBSTACLE.1 + 1), OBSTACLE), None);
+    }
+
+    #[test]
+    fn rejects_two_tile_jumps() {
+        assert_eq!(validate((0, 0), (2, 0)), None);
+        assert_eq!(validate((3, 0), (3, 2)), None);
+    }
+
+    #[test]
+    fn rejects_diagonal_steps() {
+        assert_eq!(validate((2, 0), (3, 1)), None);
+    }
+
+    #[test]
+    fn turning_in_place_keeps_the_player_on_their_tile() {
+        assert_eq!(validate((0, 0), (0, 0)), Some((0, 0)));
+        assert_eq!(validate((MAP_WIDTH - 1, MAP_HEIGHT - 1), (MAP_WIDTH - 1, MAP_HEIGHT - 1)), Some((MAP_WIDTH - 1, MAP_HEIGHT - 1)));
+    }
+
+    #[test]
+    fn rejects_moves_in_combat_and_too_soon_after_the_last_one() {
+        let movement_manager = PlayerMovementManager::new();
+        let lobby = lobby();
+        let mut in_combat = player_at(0, 0);
+        in_combat.in_combat = true;
+        assert_eq!(movement_manager.validate_movement("player", &in_combat, 1, 0, &lobby, false), None);
+
+        movement_manager.register_movement("player".to_string(), player_at(0, 0));
+        assert_eq!(movement_manager.validate_movement("player", &player_at(0, 0), 1, 0, &lobby, false), None);
+    }
+}
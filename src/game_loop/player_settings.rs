@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+// Who may send a player battle challenges
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChallengePolicy {
+    #[default]
+    All,
+    // Only players who are friends both ways
+    Friends,
+    None,
+}
+
+// How much rich presence integrations (e.g. Discord) may show about a player
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RichPresenceSharing {
+    // Lobby, map, battle opponent and party
+    #[default]
+    Full,
+    // Lobby and map only
+    LocationOnly,
+    // Always shown as offline
+    Hidden,
+}
+
+// Kinds of notifications about things that happen away from the player
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    MarketSold,
+    AuctionOutbid,
+    MailReceived,
+    FriendOnline,
+    GuildMessage,
+}
+
+// Which notifications a player wants to receive; everything is on by default
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(default)]
+pub struct NotificationPreferences {
+    pub market_sold: bool,
+    pub auction_outbid: bool,
+    pub mail_received: bool,
+    // There are no friends lists or guilds yet, so nothing of these kinds is sent
+    pub friend_online: bool,
+    pub guild_messages: bool,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self {
+            market_sold: true,
+            auction_outbid: true,
+            mail_received: true,
+            friend_online: true,
+            guild_messages: true,
+        }
+    }
+}
+
+impl NotificationPreferences {
+    pub fn wants(&self, kind: NotificationKind) -> bool {
+        match kind {
+            NotificationKind::MarketSold => self.market_sold,
+            NotificationKind::AuctionOutbid => self.auction_outbid,
+            NotificationKind::MailReceived => self.mail_received,
+            NotificationKind::FriendOnline => self.friend_online,
+            NotificationKind::GuildMessage => self.guild_messages,
+        }
+    }
+}
+
+// Per-player options that persist across sessions
+#[derive(Serialize, Deserialize, Clone, Debug, Default, schemars::JsonSchema)]
+#[serde(default)]
+pub struct PlayerSettings {
+    // Show how effective each move is against the current opponent in battle
+    pub effectiveness_hints: bool,
+    pub accept_challenges: ChallengePolicy,
+    // Look offline to other players: challengers are told the player is not around
+    pub appear_offline: bool,
+    pub notifications: NotificationPreferences,
+    pub rich_presence: RichPresenceSharing,
+    // Language of species and move names, e.g. "fr"; None for English
+    pub locale: Option<String>,
+}
+
+impl PlayerSettings {
+    pub fn accepts_challenge_from(&self, is_friend: bool) -> bool {
+        match self.accept_challenges {
+            ChallengePolicy::All => true,
+            ChallengePolicy::Friends => is_friend,
+            ChallengePolicy::None => false,
+        }
+    }
+}
+
+// Stores player settings in Redis as one JSON blob per player
+pub struct PlayerSettingsManager {
+    redis_client: redis::Client,
+}
+
+impl PlayerSettingsManager {
+    pub fn new(redis_client: redis::Client) -> Arc<Self> {
+        Arc::new(Self { redis_client })
+    }
+
+    fn settings_key(player_id: &str) -> String {
+        format!("player_settings:{}", player_id)
+    }
+
+    async fn connection(&self) -> Result<redis::aio::Connection, String> {
+        self.redis_client
+            .get_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection error: {}", e))
+    }
+
+    // A player's settings, or the defaults if they never changed any
+    pub async fn get_settings(&self, player_id: &str) -> Result<PlayerSettings, String> {
+        let mut con = self.connection().await?;
+        let json: Option<String> = redis::cmd("GET")
+            .arg(Self::settings_key(player_id))
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        match json {
+            Some(json) => serde_json::from_str(&json).map_err(|e| format!("Failed to deserialize player settings: {}", e)),
+            None => Ok(PlayerSettings::default()),
+        }
+    }
+
+    // Apply a change to a player's settings and save them, returning the new settings
+    pub async fn update_settings(&self, player_id: &str, update: impl FnOnce(&mut PlayerSettings)) -> Result<PlayerSettings, String> {
+        let mut settings = self.get_settings(player_id).await?;
+        update(&mut settings);
+        let json = serde_json::to_string(&settings)
+            .map_err(|e| format!("Failed to serialize player settings: {}", e))?;
+        let mut con = self.connection().await?;
+        redis::cmd("SET")
+            .arg(Self::settings_key(player_id))
+            .arg(json)
+            .query_async::<_, ()>(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn challenge_policy_decides_who_may_challenge() {
+        let settings = |accept_challenges| PlayerSettings { accept_challenges, ..PlayerSettings::default() };
+        assert!(settings(ChallengePolicy::All).accepts_challenge_from(false));
+        assert!(settings(ChallengePolicy::Friends).accepts_challenge_from(true));
+        assert!(!settings(ChallengePolicy::Friends).accepts_challenge_from(false));
+        assert!(!settings(ChallengePolicy::None).accepts_challenge_from(true));
+    }
+}
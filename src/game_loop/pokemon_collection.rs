@@ -2,12 +2,13 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 use tracing::{info, warn};
 use uuid::Uuid;
 use rand::Rng;
 
-use crate::monsters::monster::{MonsterMove, PokemonType};
+use crate::monsters::monster::{MonsterMove, PokemonType, MAX_PP_UPS};
 use crate::monsters::monster_manager::MonsterTemplateRepository;
 use crate::combat::state::{StatusCondition, BattleMoveView, MoveCategory as CombatMoveCategory};
 use crate::monsters::Monster;
@@ -16,21 +17,40 @@ use crate::stats::StatSet;
 use crate::stats::{calculate_stats, CalculatedStats};
 use crate::monsters::move_manager::{MoveRepository, MoveCategory as RepoMoveCategory};
 use crate::models::DisplayPokemon;
+use crate::game_loop::collection_migrations::{migrate_collection, COLLECTION_SCHEMA_VERSION};
 
-const MAX_POKEMONS: usize = 6;
+pub const MAX_POKEMONS: usize = 6;
+pub const BOX_SIZE: usize = 30;
 const STARTING_POKEMON_IDS: [u32; 3] = [1, 4, 7];
+const MAX_NICKNAME_LENGTH: usize = 12;
+// How long a capture waits for the player's nickname before it is saved as is
+const NICKNAME_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_MOVES: usize = 4;
+pub const PP_UP_ITEM: &str = "pp_up";
+pub const PP_MAX_ITEM: &str = "pp_max";
+
+// How many PP Ups a single PP item applies, None if the item is not a PP item
+pub fn pp_ups_for_item(item_id: &str) -> Option<u8> {
+    match item_id {
+        PP_UP_ITEM => Some(1),
+        PP_MAX_ITEM => Some(MAX_PP_UPS),
+        _ => None,
+    }
+}
 
 // Manages pokemonmon collections for all players
 pub struct PokemonCollectionManager {
     // Map of player ID to their pokemon collection
     collections: RwLock<HashMap<String, PlayerCollection>>,
+    // Map of player ID to the Pokemon they just captured and may still nickname
+    pending_nicknames: RwLock<HashMap<String, String>>,
     template_manager: Arc<MonsterTemplateRepository>,
     move_repository: Arc<MoveRepository>,
     redis_client: redis::Client,
 }
 
 // Represents a captured PokemonMon
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
 pub struct Pokemon {
     pub id: String,
     pub template_id: u32,
@@ -45,17 +65,116 @@ pub struct Pokemon {
     pub capture_date: u64,
     pub moves: Vec<MonsterMove>,
     pub types: Vec<PokemonType>,
+    #[serde(default)]
+    pub form: Option<String>, // Form ID from the template's forms, None for the regular form
     pub ability: String,
-    pub status_condition: Option<StatusCondition>
+    pub status_condition: Option<StatusCondition>,
+    #[serde(default)]
+    pub is_shiny: bool,
+    // Player who first got this pokemon; anyone else holding it got it in a trade
+    #[serde(default)]
+    pub original_trainer: Option<String>,
+}
+
+impl Pokemon {
+    // Whether `player_id` got this pokemon from another player
+    pub fn is_traded(&self, player_id: &str) -> bool {
+        self.original_trainer.as_deref().is_some_and(|trainer| trainer != player_id)
+    }
 }
 
 // Player's collection of PokemonMons
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct PlayerCollection {
+    // Format version of the stored JSON, see collection_migrations
+    pub schema_version: u32,
     pub player_id: String,
     pub pokemons: HashMap<String, Pokemon>,
     // Six pokemon ordered by index
     pub active_pokemons: Vec<String>,
+    // Pokemon stored outside the party, BOX_SIZE per box
+    #[serde(default)]
+    pub boxes: Vec<Vec<String>>,
+}
+
+// Where a pokemon ended up when it was added to a collection
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(tag = "location", rename_all = "snake_case")]
+pub enum StorageLocation {
+    Party { slot: usize },
+    Box { box_index: usize, box_name: String, slot: usize },
+}
+
+impl PlayerCollection {
+    // Put a pokemon in the first free party slot, or the first box with room once the party is full
+    fn store(&mut self, pokemon_id: String) -> StorageLocation {
+        if self.active_pokemons.len() < MAX_POKEMONS {
+            self.active_pokemons.push(pokemon_id);
+            return StorageLocation::Party { slot: self.active_pokemons.len() - 1 };
+        }
+
+        let box_index = match self.boxes.iter().position(|pc_box| pc_box.len() < BOX_SIZE) {
+            Some(box_index) => box_index,
+            None => {
+                self.boxes.push(Vec::new());
+                self.boxes.len() - 1
+            }
+        };
+        self.boxes[box_index].push(pokemon_id);
+        StorageLocation::Box {
+            box_index,
+            box_name: box_name(box_index),
+            slot: self.boxes[box_index].len() - 1,
+        }
+    }
+
+    // Take a pokemon out of the party or its box
+    fn take(&mut self, pokemon_id: &str) -> Option<Pokemon> {
+        let pokemon = self.pokemons.remove(pokemon_id)?;
+        self.active_pokemons.retain(|id| id != pokemon_id);
+        for pc_box in self.boxes.iter_mut() {
+            pc_box.retain(|id| id != pokemon_id);
+        }
+        // Never leave the player without an active pokemon while they still own one
+        if self.active_pokemons.is_empty() {
+            if let Some(pc_box) = self.boxes.iter_mut().find(|pc_box| !pc_box.is_empty()) {
+                let next_id = pc_box.remove(0);
+                self.active_pokemons.push(next_id);
+            }
+        }
+        Some(pokemon)
+    }
+
+    // Hand the given pokemon of each collection over to the other one
+    fn exchange(collections: &mut [PlayerCollection; 2], pokemon_ids: [&[String]; 2]) -> Result<(), String> {
+        let mut taken: [Vec<Pokemon>; 2] = [Vec::new(), Vec::new()];
+        for index in 0..2 {
+            for pokemon_id in pokemon_ids[index] {
+                let pokemon = collections[index].take(pokemon_id)
+                    .ok_or_else(|| format!("Pokemon {} not found in player {}'s collection", pokemon_id, collections[index].player_id))?;
+                taken[index].push(pokemon);
+            }
+        }
+        for (index, pokemons) in taken.into_iter().enumerate() {
+            let receiver = &mut collections[1 - index];
+            for pokemon in pokemons {
+                let pokemon_id = pokemon.id.clone();
+                receiver.pokemons.insert(pokemon_id.clone(), pokemon);
+                receiver.store(pokemon_id);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Result of a starter choice; repeat attempts get back the starter that was already granted
+pub enum StarterChoice {
+    Chosen(DisplayPokemon),
+    AlreadyChosen(DisplayPokemon),
+}
+
+pub fn box_name(box_index: usize) -> String {
+    format!("Box {}", box_index + 1)
 }
 
 impl PokemonCollectionManager {
@@ -66,42 +185,55 @@ impl PokemonCollectionManager {
     ) -> Arc<Self> {
         Arc::new(Self {
             collections: RwLock::new(HashMap::new()),
+            pending_nicknames: RwLock::new(HashMap::new()),
             template_manager,
             move_repository,
             redis_client,
         })
     }
 
+    // Build the client-facing view of a known move
+    pub fn move_to_view(&self, monster_move: &MonsterMove) -> Option<BattleMoveView> {
+        self.move_repository.get_move(monster_move.id).map(|move_data| {
+            let combat_category = match move_data.damage_class {
+                RepoMoveCategory::Physical => CombatMoveCategory::Physical,
+                RepoMoveCategory::Special => CombatMoveCategory::Special,
+                RepoMoveCategory::Status => CombatMoveCategory::Status,
+            };
+
+            BattleMoveView {
+                move_id: move_data.id,
+                name: move_data.name.clone(),
+                move_type: move_data.move_type,
+                category: combat_category,
+                current_pp: monster_move.pp_remaining,
+                max_pp: monster_move.max_pp(move_data.pp),
+                power: move_data.power,
+                accuracy: move_data.accuracy,
+                description: move_data.description.clone(),
+                effectiveness: None,
+            }
+        })
+    }
+
+    // Build the view of a move the pokemon could learn, with full PP
+    pub fn learnable_move_view(&self, move_id: u32) -> Option<BattleMoveView> {
+        self.move_repository.create_monster_move(move_id)
+            .and_then(|monster_move| self.move_to_view(&monster_move))
+    }
+
     pub fn pokemon_to_display_pokemon(&self, pokemon: &Pokemon) -> DisplayPokemon {
         let calculated_stats = calculate_stats(
-            &self.template_manager.templates.get(&pokemon.template_id).unwrap().base_stats,
+            self.template_manager.templates.get(&pokemon.template_id).unwrap().base_stats_for_form(pokemon.form.as_deref()),
             pokemon.level,
             &pokemon.ivs,
             &pokemon.evs,
             &pokemon.nature,
         );
         
-        let move_views: Vec<BattleMoveView> = pokemon.moves.iter().filter_map(|monster_move| {
-            self.move_repository.get_move(monster_move.id).map(|move_data| {
-                let combat_category = match move_data.damage_class {
-                    RepoMoveCategory::Physical => CombatMoveCategory::Physical,
-                    RepoMoveCategory::Special => CombatMoveCategory::Special,
-                    RepoMoveCategory::Status => CombatMoveCategory::Status,
-                };
-
-                BattleMoveView {
-                    move_id: move_data.id,
-                    name: move_data.name.clone(),
-                    move_type: move_data.move_type,
-                    category: combat_category,
-                    current_pp: monster_move.pp_remaining,
-                    max_pp: move_data.pp,
-                    power: move_data.power,
-                    accuracy: move_data.accuracy,
-                    description: move_data.description.clone(),
-                }
-            })
-        }).collect();
+        let move_views: Vec<BattleMoveView> = pokemon.moves.iter()
+            .filter_map(|monster_move| self.move_to_view(monster_move))
+            .collect();
 
         DisplayPokemon {
             id: pokemon.id.clone(),
@@ -117,6 +249,7 @@ impl PokemonCollectionManager {
             capture_date: pokemon.capture_date,
             moves: move_views,
             types: pokemon.types.clone(),
+            form: pokemon.form.clone(),
             ability: pokemon.ability.clone(),
             status_condition: pokemon.status_condition,
         }
@@ -134,7 +267,7 @@ impl PokemonCollectionManager {
             .expect("Monster template not found");
         
         // Calculate stats (should be equivalent to monster.calculated_stats)
-        let stats = calculate_stats(&template.base_stats, monster.level, &ivs, &evs, &nature);
+        let stats = calculate_stats(template.base_stats_for_form(monster.form.as_deref()), monster.level, &ivs, &evs, &nature);
         
         Pokemon {
             id: Uuid::new_v4().to_string(),
@@ -142,7 +275,7 @@ impl PokemonCollectionManager {
             name: monster.name.clone(),
             level: monster.level,
             exp: 0,
-            max_exp: self.get_next_max_exp(monster.level, monster.template_id),
+            max_exp: self.template_manager.get_exp_for_next_level(monster.template_id, monster.level),
             
             // Use calculated stats
             current_hp: monster.current_hp,
@@ -155,14 +288,20 @@ impl PokemonCollectionManager {
             capture_date: chrono::Utc::now().timestamp() as u64,
             moves: monster.moves.clone(),
             types: monster.types.clone(),
+            form: monster.form.clone(),
             ability: monster.ability.clone(),
             status_condition: monster.status_condition.clone(),
+            is_shiny: monster.is_shiny,
+            original_trainer: None,
         }
     }
 
-    // Add a new pokemon to a player's collection
+    // Add a new pokemon to a player's collection, making them its original trainer unless it
+    // already has one
     // Returns the index of the pokemon if it was added to the active list
-    pub async fn add_pokemon(&self, player_id: &str, pokemon: Pokemon) -> Result<Option<usize>, String> {
+    pub async fn add_pokemon(&self, player_id: &str, mut pokemon: Pokemon) -> Result<StorageLocation, String> {
+        pokemon.original_trainer.get_or_insert_with(|| player_id.to_string());
+
         // First try to load the collection if we don't have it in memory
         self.load_collection_if_needed(player_id).await?;
 
@@ -172,12 +311,7 @@ impl PokemonCollectionManager {
         if let Some(collection) = collections.get_mut(player_id) {
             collection.pokemons.insert(pokemon_id.clone(), pokemon.clone());
 
-            let active_index = if collection.active_pokemons.len() < MAX_POKEMONS {
-                collection.active_pokemons.push(pokemon_id);
-                Some(collection.active_pokemons.len() - 1)
-            } else {
-                None
-            };
+            let location = collection.store(pokemon_id);
 
             // Save to Redis
             match self.save_collection(player_id, collection).await {
@@ -186,7 +320,7 @@ impl PokemonCollectionManager {
                         "Added pokemon {} to player {}'s collection",
                         pokemon.id, player_id
                     );
-                    Ok(active_index)
+                    Ok(location)
                 }
                 Err(e) => {
                     warn!(
@@ -202,9 +336,11 @@ impl PokemonCollectionManager {
             pokemons.insert(pokemon.id.clone(), pokemon.clone());
             
             let collection = PlayerCollection {
+                schema_version: COLLECTION_SCHEMA_VERSION,
                 player_id: player_id.to_string(),
                 pokemons,
                 active_pokemons: vec![pokemon.id.clone()],
+                boxes: Vec::new(),
             };
 
             collections.insert(player_id.to_string(), collection.clone());
@@ -216,7 +352,7 @@ impl PokemonCollectionManager {
                         "Created new collection for player {} with pokemon {}",
                         player_id, pokemon.id
                     );
-                    Ok(Some(0))
+                    Ok(StorageLocation::Party { slot: 0 })
                 }
                 Err(e) => {
                     warn!(
@@ -229,27 +365,135 @@ impl PokemonCollectionManager {
         }
     }
 
+    // Take a pokemon out of a player's collection (e.g. when it is traded away)
+    pub async fn remove_pokemon(&self, player_id: &str, pokemon_id: &str) -> Result<Pokemon, String> {
+        self.load_collection_if_needed(player_id).await?;
+
+        let mut collections = self.collections.write().await;
+        let collection = collections.get_mut(player_id)
+            .ok_or_else(|| format!("Player collection not found for player {}", player_id))?;
+
+        let pokemon = collection.take(pokemon_id)
+            .ok_or_else(|| format!("Pokemon {} not found in player {}'s collection", pokemon_id, player_id))?;
+
+        self.save_collection(player_id, collection).await?;
+        info!("Removed pokemon {} from player {}'s collection", pokemon_id, player_id);
+        Ok(pokemon)
+    }
+
+    // Swap pokemon between two players in one step. Both collections are changed on copies and
+    // written in a single Redis transaction, so on any failure both players keep what they had.
+    pub async fn exchange_pokemon(&self, player_ids: [&str; 2], pokemon_ids: [&[String]; 2]) -> Result<(), String> {
+        for player_id in player_ids {
+            self.load_collection_if_needed(player_id).await?;
+        }
+
+        let mut collections = self.collections.write().await;
+        let collection = |player_id: &str| collections.get(player_id).cloned()
+            .ok_or_else(|| format!("Player collection not found for player {}", player_id));
+        let mut updated = [collection(player_ids[0])?, collection(player_ids[1])?];
+        PlayerCollection::exchange(&mut updated, pokemon_ids)?;
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for (player_id, collection) in player_ids.iter().zip(&updated) {
+            let json = serde_json::to_string(collection)
+                .map_err(|e| format!("Failed to serialize collection: {}", e))?;
+            pipe.cmd("SET").arg(format!("pokemon_collection:{}", player_id)).arg(json).ignore();
+        }
+        let mut con = self.redis_client.get_async_connection().await
+            .map_err(|e| format!("Redis connection error: {}", e))?;
+        pipe.query_async::<_, ()>(&mut con).await
+            .map_err(|e| format!("Redis save error: {}", e))?;
+
+        for (player_id, collection) in player_ids.into_iter().zip(updated) {
+            collections.insert(player_id.to_string(), collection);
+        }
+        info!("Exchanged pokemon between players {} and {}", player_ids[0], player_ids[1]);
+        Ok(())
+    }
+
+    // Move a boxed pokemon into a party slot; the pokemon already in that slot takes its place in the box
+    pub async fn swap_into_party(&self, player_id: &str, pokemon_id: &str, party_slot: usize) -> Result<(), String> {
+        self.load_collection_if_needed(player_id).await?;
+
+        let mut collections = self.collections.write().await;
+        let collection = collections.get_mut(player_id)
+            .ok_or_else(|| format!("Player collection not found for player {}", player_id))?;
+
+        let (box_index, slot) = collection.boxes.iter().enumerate()
+            .find_map(|(box_index, pc_box)| pc_box.iter().position(|id| id == pokemon_id).map(|slot| (box_index, slot)))
+            .ok_or_else(|| format!("Pokemon {} is not in a box", pokemon_id))?;
+
+        if party_slot < collection.active_pokemons.len() {
+            let replaced = std::mem::replace(&mut collection.active_pokemons[party_slot], pokemon_id.to_string());
+            collection.boxes[box_index][slot] = replaced;
+        } else if collection.active_pokemons.len() < MAX_POKEMONS {
+            collection.boxes[box_index].remove(slot);
+            collection.active_pokemons.push(pokemon_id.to_string());
+        } else {
+            return Err(format!("Invalid party slot: {}", party_slot));
+        }
+
+        self.save_collection(player_id, collection).await?;
+        info!("Moved pokemon {} from {} into player {}'s party", pokemon_id, box_name(box_index), player_id);
+        Ok(())
+    }
+
     pub async fn choose_starting_pokemons(
         &self,
         player_id: &str,
         starter_id: u32,
-    ) -> Result<DisplayPokemon, String> {
+    ) -> Result<StarterChoice, String> {
         if !STARTING_POKEMON_IDS.contains(&starter_id) {
             return Err(format!("Invalid starter id: {}", starter_id));
         }
 
+        // Players who got their starter before the claim key existed
         let collection = self.get_collection(player_id).await?;
-        if collection.pokemons.len() > 0 {
-            return Err("Player already has a pokemon".to_string());
+        if let Some(first_pokemon) = collection.active_pokemons.first().and_then(|id| collection.pokemons.get(id)) {
+            return Ok(StarterChoice::AlreadyChosen(self.pokemon_to_display_pokemon(first_pokemon)));
         }
 
         let starter_pokemon_raw = self.template_manager.pokemon_from_template(starter_id, Some(10));
-        
-        self.add_pokemon(player_id, starter_pokemon_raw.clone()).await?;
+
+        // Claim the starter with SET NX so that concurrent requests cannot both grant one
+        let starter_key = format!("starter:{}", player_id);
+        let mut con = match self.redis_client.get_async_connection().await {
+            Ok(con) => con,
+            Err(e) => return Err(format!("Redis connection error: {}", e)),
+        };
+        let claimed: Option<String> = redis::cmd("SET")
+            .arg(&starter_key)
+            .arg(&starter_pokemon_raw.id)
+            .arg("NX")
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        if claimed.is_none() {
+            let existing_id: Option<String> = redis::cmd("GET")
+                .arg(&starter_key)
+                .query_async(&mut con)
+                .await
+                .map_err(|e| format!("Redis query error: {}", e))?;
+            let collection = self.get_collection(player_id).await?;
+            return match existing_id.and_then(|id| collection.pokemons.get(&id).cloned()) {
+                Some(existing) => Ok(StarterChoice::AlreadyChosen(self.pokemon_to_display_pokemon(&existing))),
+                None => Err("Starter is already being assigned".to_string()),
+            };
+        }
+
+        if let Err(e) = self.add_pokemon(player_id, starter_pokemon_raw.clone()).await {
+            // Release the claim so the player can try again
+            if let Err(del_err) = redis::cmd("DEL").arg(&starter_key).query_async::<_, ()>(&mut con).await {
+                warn!("Failed to release starter claim of player {}: {}", player_id, del_err);
+            }
+            return Err(e);
+        }
 
         let display_pokemon = self.pokemon_to_display_pokemon(&starter_pokemon_raw);
 
-        Ok(display_pokemon)
+        Ok(StarterChoice::Chosen(display_pokemon))
     }
 
     // Get a player's collection
@@ -279,22 +523,52 @@ impl PokemonCollectionManager {
         };
 
         if let Some(json) = collection_json {
-            // Parse JSON into PlayerCollection
-            match serde_json::from_str::<PlayerCollection>(&json) {
-                Ok(collection) => {
-                    // Cache in memory
-                    let mut collections = self.collections.write().await;
-                    collections.insert(player_id.to_string(), collection.clone());
-                    Ok(collection)
+            // Upgrade collections stored by older versions before parsing them
+            let raw = serde_json::from_str::<serde_json::Value>(&json)
+                .map_err(|e| format!("Failed to parse collection JSON: {}", e))?;
+            let (migrated, stored_version) = migrate_collection(raw)
+                .map_err(|e| format!("Failed to migrate collection for player {}: {}", player_id, e))?;
+            let mut collection = serde_json::from_value::<PlayerCollection>(migrated)
+                .map_err(|e| format!("Failed to parse collection JSON: {}", e))?;
+
+            // Pokemon saved under an older exp curve keep a stale max_exp; bring them onto their growth rate
+            let mut exp_curve_fixed = false;
+            for pokemon in collection.pokemons.values_mut() {
+                let max_exp = self.template_manager.get_exp_for_next_level(pokemon.template_id, pokemon.level);
+                if pokemon.max_exp != max_exp {
+                    pokemon.max_exp = max_exp;
+                    exp_curve_fixed = true;
                 }
-                Err(e) => Err(format!("Failed to parse collection JSON: {}", e)),
             }
+
+            let schema_migrated = stored_version < COLLECTION_SCHEMA_VERSION;
+            if schema_migrated {
+                info!(
+                    "Migrated pokemon collection for player {} from schema version {} to {}",
+                    player_id, stored_version, COLLECTION_SCHEMA_VERSION
+                );
+            }
+            if exp_curve_fixed {
+                info!("Recalculated exp curves for pokemon collection of player {}", player_id);
+            }
+            if schema_migrated || exp_curve_fixed {
+                if let Err(e) = self.save_collection(player_id, &collection).await {
+                    warn!("Failed to save migrated collection for player {}: {}", player_id, e);
+                }
+            }
+
+            // Cache in memory
+            let mut collections = self.collections.write().await;
+            collections.insert(player_id.to_string(), collection.clone());
+            Ok(collection)
         } else {
             // No collection yet, create an empty one
             let empty_collection = PlayerCollection {
+                schema_version: COLLECTION_SCHEMA_VERSION,
                 player_id: player_id.to_string(),
                 pokemons: HashMap::new(),
                 active_pokemons: Vec::new(),
+                boxes: Vec::new(),
             };
 
             // Cache in memory
@@ -318,6 +592,16 @@ impl PokemonCollectionManager {
         Ok(active_pokemons)
     }
 
+    // Pokemon in one of a player's boxes in slot order, None if the player has no such box
+    pub async fn get_box(&self, player_id: &str, box_index: usize) -> Result<Option<Vec<Pokemon>>, String> {
+        let collection = self.get_collection(player_id).await?;
+        Ok(collection.boxes.get(box_index).map(|pc_box| {
+            pc_box.iter()
+                .filter_map(|id| collection.pokemons.get(id).cloned())
+                .collect()
+        }))
+    }
+
     // Save a collection to Redis
     async fn save_collection(
         &self,
@@ -368,22 +652,6 @@ impl PokemonCollectionManager {
         Ok(())
     }
 
-    pub fn get_next_max_exp(&self, level: u32, template_id: u32) -> u64 {
-        let template = self
-            .template_manager
-            .templates
-            .get(&template_id)
-            .expect("Template not found");
-        let base_exp = template.base_experience;
-
-        // Calculate required exp for next level using standard RPG formula
-        // Each level requires progressively more exp
-        let growth_rate: f64 = 1.2;
-        let next_level_exp = (base_exp as f64 * growth_rate.powf(level as f64)).floor() as u64;
-
-        next_level_exp
-    }
-
     /// Add experience to a Pokemon, possibly leveling it up
     /// Returns the updated Pokemon and whether it leveled up
     pub async fn add_experience_to_pokemon(&self, player_id: &str, pokemon_id: &str, experience: u64) -> Result<(Pokemon, bool), String> {
@@ -411,7 +679,7 @@ impl PokemonCollectionManager {
             
             // Update experience for next level
             pokemon.exp -= pokemon.max_exp;
-            pokemon.max_exp = self.get_next_max_exp(pokemon.level, pokemon.template_id);
+            pokemon.max_exp = self.template_manager.get_exp_for_next_level(pokemon.template_id, pokemon.level);
             
             // TODO: Handle learning new moves on level up
             // This would involve checking the template for moves learnable at this level
@@ -420,7 +688,7 @@ impl PokemonCollectionManager {
             if let Some(template) = self.template_manager.templates.get(&pokemon.template_id) {
                 // Get current stats
                 let old_stats = crate::stats::calculate_stats(
-                    &template.base_stats,
+                    template.base_stats_for_form(pokemon.form.as_deref()),
                     old_level,
                     &pokemon.ivs,
                     &pokemon.evs,
@@ -429,7 +697,7 @@ impl PokemonCollectionManager {
                 
                 // Calculate new stats after level up
                 let new_stats = crate::stats::calculate_stats(
-                    &template.base_stats,
+                    template.base_stats_for_form(pokemon.form.as_deref()),
                     pokemon.level,
                     &pokemon.ivs,
                     &pokemon.evs,
@@ -458,6 +726,190 @@ impl PokemonCollectionManager {
         Ok((collection.pokemons.get(pokemon_id).unwrap().clone(), leveled_up))
     }
 
+    // Store a freshly captured pokemon in memory but hold the save until the player nicknames it
+    // (or skips naming), the nickname prompt times out, or the player disconnects
+    pub async fn hold_captured_pokemon(self: &Arc<Self>, player_id: &str, mut pokemon: Pokemon) -> Result<StorageLocation, String> {
+        pokemon.original_trainer.get_or_insert_with(|| player_id.to_string());
+        self.load_collection_if_needed(player_id).await?;
+
+        let location = {
+            let mut collections = self.collections.write().await;
+            let collection = collections.get_mut(player_id)
+                .ok_or_else(|| format!("Player collection not found for player {}", player_id))?;
+            collection.pokemons.insert(pokemon.id.clone(), pokemon.clone());
+            collection.store(pokemon.id.clone())
+        };
+
+        // A capture still waiting on its name gets saved along with this one
+        self.pending_nicknames.write().await.insert(player_id.to_string(), pokemon.id.clone());
+
+        let manager = Arc::clone(self);
+        let (player_id, pokemon_id) = (player_id.to_string(), pokemon.id);
+        tokio::spawn(async move {
+            tokio::time::sleep(NICKNAME_TIMEOUT).await;
+            // Nothing to do if the player already answered
+            if manager.finalize_capture(&player_id, &pokemon_id, None).await.is_ok() {
+                info!("Nickname prompt for pokemon {} of player {} timed out", pokemon_id, player_id);
+            }
+        });
+
+        Ok(location)
+    }
+
+    // Apply (or skip, when nickname is None) the nickname for a just-captured pokemon and save it
+    pub async fn nickname_captured(&self, player_id: &str, pokemon_id: &str, nickname: Option<String>) -> Result<(), String> {
+        // Validate first so a rejected name leaves the prompt open for another try
+        let nickname = nickname.map(|nickname| Self::validate_nickname(&nickname)).transpose()?;
+        self.finalize_capture(player_id, pokemon_id, nickname).await
+    }
+
+    // Save whatever capture the player still has waiting on a nickname; called on disconnect
+    pub fn release_pending_capture(self: &Arc<Self>, player_id: &str) {
+        let manager = Arc::clone(self);
+        let player_id = player_id.to_string();
+        tokio::spawn(async move {
+            let pokemon_id = manager.pending_nicknames.read().await.get(&player_id).cloned();
+            if let Some(pokemon_id) = pokemon_id {
+                let _ = manager.finalize_capture(&player_id, &pokemon_id, None).await;
+            }
+        });
+    }
+
+    // Name a held capture and write the collection to Redis
+    async fn finalize_capture(&self, player_id: &str, pokemon_id: &str, nickname: Option<String>) -> Result<(), String> {
+        {
+            let mut pending = self.pending_nicknames.write().await;
+            match pending.get(player_id) {
+                Some(pending_id) if pending_id == pokemon_id => {
+                    pending.remove(player_id);
+                }
+                _ => return Err("No captured pokemon awaiting a nickname".to_string()),
+            }
+        }
+
+        let mut collections = self.collections.write().await;
+        let collection = collections.get_mut(player_id)
+            .ok_or_else(|| format!("Player collection not found for player {}", player_id))?;
+        if let Some(nickname) = nickname {
+            let pokemon = collection.pokemons.get_mut(pokemon_id)
+                .ok_or_else(|| format!("Pokemon {} not found in player {}'s collection", pokemon_id, player_id))?;
+            pokemon.name = nickname;
+        }
+        self.save_collection(player_id, collection).await?;
+        info!("Saved captured pokemon {} for player {}", pokemon_id, player_id);
+        Ok(())
+    }
+
+    // Trim a requested nickname and check it is acceptable
+    pub fn validate_nickname(nickname: &str) -> Result<String, String> {
+        let nickname = nickname.trim().to_string();
+        if nickname.is_empty() || nickname.chars().count() > MAX_NICKNAME_LENGTH {
+            return Err(format!("Nickname must be between 1 and {} characters", MAX_NICKNAME_LENGTH));
+        }
+        if !nickname.chars().all(|c| c.is_alphanumeric() || c == ' ' || c == '_') {
+            return Err("Nickname may only contain letters, numbers, spaces and underscores".to_string());
+        }
+        Ok(nickname)
+    }
+
+    // Create a new pokemon of the given species and level, if the species exists
+    pub fn pokemon_from_template(&self, template_id: u32, level: u32) -> Result<Pokemon, String> {
+        if !self.template_manager.templates.contains_key(&template_id) {
+            return Err(format!("Unknown pokemon template {}", template_id));
+        }
+        Ok(self.template_manager.pokemon_from_template(template_id, Some(level)))
+    }
+
+    // Get a single pokemon from a player's collection
+    pub async fn get_pokemon(&self, player_id: &str, pokemon_id: &str) -> Result<Pokemon, String> {
+        let collection = self.get_collection(player_id).await?;
+        collection.pokemons.get(pokemon_id)
+            .cloned()
+            .ok_or_else(|| format!("Pokemon {} not found in player {}'s collection", pokemon_id, player_id))
+    }
+
+    // Moves from the species' level-up learnset at or below the pokemon's level that it does not currently know
+    pub fn relearnable_moves(&self, pokemon: &Pokemon) -> Vec<u32> {
+        let template = match self.template_manager.templates.get(&pokemon.template_id) {
+            Some(template) => template,
+            None => return Vec::new(),
+        };
+
+        let mut move_ids: Vec<u32> = template.moves.iter()
+            .filter(|(move_id, level_learned)| {
+                *level_learned <= pokemon.level
+                    && !pokemon.moves.iter().any(|m| m.id == *move_id)
+                    && self.move_repository.get_move(*move_id).is_some()
+            })
+            .map(|(move_id, _)| *move_id)
+            .collect();
+        move_ids.sort_unstable();
+        move_ids.dedup();
+        move_ids
+    }
+
+    // Teach a pokemon a move it could have learned by level up, optionally replacing a known move
+    pub async fn relearn_move(&self, player_id: &str, pokemon_id: &str, move_id: u32, replace_index: Option<usize>) -> Result<(), String> {
+        let pokemon = self.get_pokemon(player_id, pokemon_id).await?;
+        if !self.relearnable_moves(&pokemon).contains(&move_id) {
+            return Err(format!("{} cannot relearn move {}", pokemon.name, move_id));
+        }
+        let new_move = self.move_repository.create_monster_move(move_id)
+            .ok_or_else(|| format!("Move {} not found", move_id))?;
+
+        let mut collections = self.collections.write().await;
+        let collection = collections.get_mut(player_id)
+            .ok_or_else(|| format!("Player collection not found for player {}", player_id))?;
+        let pokemon = collection.pokemons.get_mut(pokemon_id)
+            .ok_or_else(|| format!("Pokemon {} not found in player {}'s collection", pokemon_id, player_id))?;
+
+        match replace_index {
+            Some(index) if index < pokemon.moves.len() => pokemon.moves[index] = new_move,
+            Some(index) => return Err(format!("Invalid move slot {}", index)),
+            None if pokemon.moves.len() < MAX_MOVES => pokemon.moves.push(new_move),
+            None => return Err(format!("{} already knows {} moves, choose one to forget", pokemon.name, MAX_MOVES)),
+        }
+
+        info!("Player {}'s pokemon {} relearned move {}", player_id, pokemon_id, move_id);
+        self.save_collection(player_id, collection).await
+    }
+
+    // How many more PP Ups one of a pokemon's moves can take
+    pub async fn pp_ups_remaining(&self, player_id: &str, pokemon_id: &str, move_index: usize) -> Result<u8, String> {
+        let pokemon = self.get_pokemon(player_id, pokemon_id).await?;
+        let monster_move = pokemon.moves.get(move_index)
+            .ok_or_else(|| format!("Invalid move slot {}", move_index))?;
+        Ok(MAX_PP_UPS.saturating_sub(monster_move.pp_ups))
+    }
+
+    // Apply PP Ups to a move up to the cap, also granting the PP they add; returns the new max PP
+    pub async fn apply_pp_ups(&self, player_id: &str, pokemon_id: &str, move_index: usize, pp_ups: u8) -> Result<u8, String> {
+        self.load_collection_if_needed(player_id).await?;
+
+        let mut collections = self.collections.write().await;
+        let collection = collections.get_mut(player_id)
+            .ok_or_else(|| format!("Player collection not found for player {}", player_id))?;
+        let pokemon = collection.pokemons.get_mut(pokemon_id)
+            .ok_or_else(|| format!("Pokemon {} not found in player {}'s collection", pokemon_id, player_id))?;
+        let monster_move = pokemon.moves.get_mut(move_index)
+            .ok_or_else(|| format!("Invalid move slot {}", move_index))?;
+        if monster_move.pp_ups >= MAX_PP_UPS {
+            return Err(format!("{}'s move PP can't go any higher", pokemon.name));
+        }
+        let base_pp = self.move_repository.get_move(monster_move.id)
+            .ok_or_else(|| format!("Move {} not found", monster_move.id))?
+            .pp;
+
+        let old_max_pp = monster_move.max_pp(base_pp);
+        monster_move.pp_ups = (monster_move.pp_ups + pp_ups).min(MAX_PP_UPS);
+        let new_max_pp = monster_move.max_pp(base_pp);
+        monster_move.pp_remaining = monster_move.pp_remaining.saturating_add(new_max_pp - old_max_pp).min(new_max_pp);
+
+        info!("Player {}'s pokemon {} raised move {} to {} PP Ups", player_id, pokemon_id, monster_move.id, monster_move.pp_ups);
+        self.save_collection(player_id, collection).await?;
+        Ok(new_max_pp)
+    }
+
     pub async fn update_pokemon(&self, player_id: &str, pokemon_id: &str, update_data: &PokemonUpdate) -> Result<(), String> {
         self.load_collection_if_needed(player_id).await?;
 
@@ -483,6 +935,18 @@ impl PokemonCollectionManager {
         if let Some(current_hp) = update_data.current_hp {
             pokemon.current_hp = current_hp;
         }
+        if let Some(moves) = &update_data.moves {
+            pokemon.moves = moves.clone();
+        }
+        if let Some(status_condition) = update_data.status_condition {
+            pokemon.status_condition = status_condition;
+        }
+        if let Some(evs) = &update_data.evs {
+            pokemon.evs = evs.clone();
+        }
+        if let Some(ability) = &update_data.ability {
+            pokemon.ability = ability.clone();
+        }
 
         // Save the updated collection
         self.save_collection(player_id, collection).await?;
@@ -491,10 +955,55 @@ impl PokemonCollectionManager {
     }
 }
 
+#[derive(Debug, Clone, Default)]
 pub struct PokemonUpdate {
     pub name: Option<String>,
     pub level: Option<u32>,
     pub exp: Option<u64>,
     pub max_exp: Option<u64>,
-    pub current_hp: Option<u32>,    
-}
\ No newline at end of file
+    pub current_hp: Option<u32>,
+    pub moves: Option<Vec<MonsterMove>>,
+    // Some(None) clears the status condition
+    pub status_condition: Option<Option<StatusCondition>>,
+    pub evs: Option<StatSet<u16>>,
+    pub ability: Option<String>,
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collection(player_id: &str, blob: &str) -> PlayerCollection {
+        let (migrated, _) = migrate_collection(serde_json::from_str(blob).expect("collection fixture is valid JSON"))
+            .expect("collection migrates");
+        let mut collection: PlayerCollection = serde_json::from_value(migrated).expect("migrated collection loads");
+        collection.player_id = player_id.to_string();
+        collection
+    }
+
+    #[test]
+    fn trading_away_the_last_party_member_moves_a_boxed_pokemon_into_the_party() {
+        // One pokemon in the party, two in a box
+        let giver = collection("player-1", include_str!("fixtures/collection_v2.json"));
+        let receiver = collection("player-2", r#"{"schema_version": 4, "player_id": "player-2", "pokemons": {}, "active_pokemons": [], "boxes": []}"#);
+        assert_eq!(giver.active_pokemons, ["mon-0"]);
+
+        let mut collections = [giver, receiver];
+        PlayerCollection::exchange(&mut collections, [&["mon-0".to_string()], &[]]).expect("trade goes through");
+
+        let [giver, receiver] = collections;
+        assert_eq!(giver.active_pokemons, ["mon-1"]);
+        assert_eq!(giver.boxes, [["mon-2"]]);
+        assert_eq!(giver.pokemons.len(), 2);
+        assert_eq!(receiver.active_pokemons, ["mon-0"]);
+        assert!(receiver.pokemons.contains_key("mon-0"));
+    }
+
+    #[test]
+    fn taking_a_boxed_pokemon_leaves_the_party_alone() {
+        let mut giver = collection("player-1", include_str!("fixtures/collection_v2.json"));
+        assert!(giver.take("mon-2").is_some());
+        assert_eq!(giver.active_pokemons, ["mon-0"]);
+        assert_eq!(giver.boxes, [["mon-1"]]);
+        assert!(giver.take("mon-2").is_none());
+    }
+}
@@ -0,0 +1,483 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::time::Duration;
+use tracing::{error, info};
+
+use crate::game_loop::event_scheduler::ScheduledEvent;
+use crate::combat::listeners::{BattleOutcomeListener, PvPBattleSummary};
+use crate::combat::state::PvPBattleOutcome;
+use crate::game_loop::badges::{BadgeManager, RANKED_MIN_BADGES};
+use crate::game_loop::mail::{MailAttachment, MailManager};
+
+// Rating every player starts a season with
+const STARTING_RATING: f64 = 1000.0;
+// Maximum rating change from a single battle
+const K_FACTOR: f64 = 32.0;
+// How long a ranked season lasts
+const SEASON_DURATION_SECS: u64 = 28 * 24 * 60 * 60;
+// How often the scheduler checks whether the season is over
+const SEASON_CHECK_SECS: u64 = 60;
+// Number of players shown on a leaderboard
+const LEADERBOARD_SIZE: isize = 100;
+
+// Attempts at a rating update before giving up when other results keep changing the ratings first
+const MAX_RATING_UPDATE_ATTEMPTS: usize = 5;
+
+const CURRENT_SEASON_KEY: &str = "ladder:season";
+const SEASON_ENDS_AT_KEY: &str = "ladder:season_ends_at";
+
+// Move KEYS[1] from season ARGV[1] to ARGV[2] ending at ARGV[3] (KEYS[2]), unless another
+// server already did. Returns 1 when this call started the new season.
+const ADVANCE_SEASON_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) ~= ARGV[1] then
+    return 0
+end
+redis.call('SET', KEYS[1], ARGV[2])
+redis.call('SET', KEYS[2], ARGV[3])
+return 1
+"#;
+
+// Archive a player's final standing and mail their rewards, unless they were already rewarded.
+// KEYS[1] is the season's rewarded set, KEYS[2] the player's season history and KEYS[3..] the
+// hashes the reward mail is written to. ARGV[1] is the player ID, ARGV[2] the season record,
+// then a field and value for each mail hash. Returns 1 when this call rewarded the player.
+const REWARD_PLAYER_SCRIPT: &str = r#"
+if redis.call('SADD', KEYS[1], ARGV[1]) == 0 then
+    return 0
+end
+redis.call('LPUSH', KEYS[2], ARGV[2])
+for index = 3, #KEYS do
+    redis.call('HSET', KEYS[index], ARGV[index * 2 - 3], ARGV[index * 2 - 2])
+end
+return 1
+"#;
+
+// End-of-season rewards: players ranked at or above the threshold get the items.
+// The last tier (rank 0) is for everyone who played at least one ranked battle.
+const SEASON_REWARDS: [(usize, &[(&str, u32)]); 4] = [
+    (1, &[("master_ball", 1), ("full_restore", 5)]),
+    (10, &[("ultra_ball", 10), ("full_restore", 3)]),
+    (100, &[("great_ball", 10)]),
+    (0, &[("poke_ball", 5)]),
+];
+
+// One row of a season's leaderboard
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+pub struct LadderEntry {
+    pub rank: usize,
+    pub player_id: String,
+    pub username: String,
+    pub rating: u32,
+    pub wins: u32,
+    pub losses: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+pub struct Leaderboard {
+    pub season: u32,
+    // Only set for the running season
+    pub ends_at: Option<u64>,
+    pub entries: Vec<LadderEntry>,
+}
+
+// A player's final standing in a past season
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+pub struct SeasonRecord {
+    pub season: u32,
+    pub rank: usize,
+    pub rating: u32,
+    pub wins: u32,
+    pub losses: u32,
+}
+
+// Seasonal ranked ladder driven by PvP results, stored in Redis
+pub struct RankingManager {
+    redis_client: redis::Client,
+    mail_manager: Arc<MailManager>,
+}
+
+impl RankingManager {
+    pub fn new(redis_client: redis::Client, mail_manager: Arc<MailManager>) -> Arc<Self> {
+        Arc::new(Self {
+            redis_client,
+            mail_manager,
+        })
+    }
+
+    async fn connection(&self) -> Result<redis::aio::Connection, String> {
+        self.redis_client
+            .get_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection error: {}", e))
+    }
+
+    // Player ID → rating
+    fn ratings_key(season: u32) -> String {
+        format!("ladder:{}:ratings", season)
+    }
+
+    // Username, wins and losses of a player in a season
+    fn player_key(season: u32, player_id: &str) -> String {
+        format!("ladder:{}:player:{}", season, player_id)
+    }
+
+    fn history_key(player_id: &str) -> String {
+        format!("season_history:{}", player_id)
+    }
+
+    // Players whose final standing of a season was archived and rewarded
+    fn rewarded_key(season: u32) -> String {
+        format!("ladder:{}:rewarded", season)
+    }
+
+    // The running season and when it ends, starting the first season if there is none yet
+    pub async fn current_season(&self) -> Result<(u32, u64), String> {
+        let mut con = self.connection().await?;
+        let (season, ends_at): (Option<u32>, Option<u64>) = redis::pipe()
+            .cmd("GET").arg(CURRENT_SEASON_KEY)
+            .cmd("GET").arg(SEASON_ENDS_AT_KEY)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+
+        match (season, ends_at) {
+            (Some(season), Some(ends_at)) => Ok((season, ends_at)),
+            _ => {
+                let ends_at = chrono::Utc::now().timestamp() as u64 + SEASON_DURATION_SECS;
+                redis::pipe()
+                    .cmd("SET").arg(CURRENT_SEASON_KEY).arg(1).ignore()
+                    .cmd("SET").arg(SEASON_ENDS_AT_KEY).arg(ends_at).ignore()
+                    .query_async::<_, ()>(&mut con)
+                    .await
+                    .map_err(|e| format!("Redis query error: {}", e))?;
+                info!("Started ranked season 1");
+                Ok((1, ends_at))
+            }
+        }
+    }
+
+    // Apply an Elo update for a finished ranked battle
+    pub async fn record_result(&self, winner: (&str, &str), loser: (&str, &str)) -> Result<(), String> {
+        let (season, _) = self.current_season().await?;
+        let (winner_id, winner_name) = winner;
+        let (loser_id, loser_name) = loser;
+        let ratings_key = Self::ratings_key(season);
+
+        // WATCH the ratings so a result recorded in between makes EXEC fail and the update is redone
+        let mut con = self.connection().await?;
+        for _ in 0..MAX_RATING_UPDATE_ATTEMPTS {
+            redis::cmd("WATCH")
+                .arg(&ratings_key)
+                .query_async::<_, ()>(&mut con)
+                .await
+                .map_err(|e| format!("Redis query error: {}", e))?;
+            let (winner_rating, loser_rating): (Option<f64>, Option<f64>) = redis::pipe()
+                .cmd("ZSCORE").arg(&ratings_key).arg(winner_id)
+                .cmd("ZSCORE").arg(&ratings_key).arg(loser_id)
+                .query_async(&mut con)
+                .await
+                .map_err(|e| format!("Redis query error: {}", e))?;
+            let winner_rating = winner_rating.unwrap_or(STARTING_RATING);
+            let loser_rating = loser_rating.unwrap_or(STARTING_RATING);
+            let change = rating_change(winner_rating, loser_rating);
+            let new_loser_rating = (loser_rating - change).max(0.0);
+
+            let committed: Option<()> = redis::pipe()
+                .atomic()
+                .cmd("ZADD").arg(&ratings_key).arg(winner_rating + change).arg(winner_id).ignore()
+                .cmd("ZADD").arg(&ratings_key).arg(new_loser_rating).arg(loser_id).ignore()
+                .cmd("HSET").arg(Self::player_key(season, winner_id)).arg("username").arg(winner_name).ignore()
+                .cmd("HINCRBY").arg(Self::player_key(season, winner_id)).arg("wins").arg(1).ignore()
+                .cmd("HSET").arg(Self::player_key(season, loser_id)).arg("username").arg(loser_name).ignore()
+                .cmd("HINCRBY").arg(Self::player_key(season, loser_id)).arg("losses").arg(1).ignore()
+                .query_async(&mut con)
+                .await
+                .map_err(|e| format!("Redis query error: {}", e))?;
+            if committed.is_some() {
+                info!("Season {}: {} beat {} (±{})", season, winner_id, loser_id, change);
+                return Ok(());
+            }
+        }
+        Err(format!("Ratings of {} and {} kept changing during the update", winner_id, loser_id))
+    }
+
+    // Ranked players of a season from `start`, highest rating first
+    async fn standings(&self, season: u32, start: isize, stop: isize) -> Result<Vec<LadderEntry>, String> {
+        let mut con = self.connection().await?;
+        let ratings: Vec<(String, f64)> = redis::cmd("ZREVRANGE")
+            .arg(Self::ratings_key(season))
+            .arg(start)
+            .arg(stop)
+            .arg("WITHSCORES")
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        if ratings.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut pipe = redis::pipe();
+        for (player_id, _) in &ratings {
+            pipe.cmd("HGETALL").arg(Self::player_key(season, player_id));
+        }
+        let details: Vec<HashMap<String, String>> = pipe
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+
+        Ok(ratings
+            .into_iter()
+            .zip(details)
+            .enumerate()
+            .map(|(index, ((player_id, rating), details))| LadderEntry {
+                rank: start as usize + index + 1,
+                username: details.get("username").cloned().unwrap_or_else(|| player_id.clone()),
+                wins: details.get("wins").and_then(|w| w.parse().ok()).unwrap_or(0),
+                losses: details.get("losses").and_then(|l| l.parse().ok()).unwrap_or(0),
+                player_id,
+                rating: rating as u32,
+            })
+            .collect())
+    }
+
+    // Top of the leaderboard for a season, defaulting to the running one
+    pub async fn leaderboard(&self, season: Option<u32>) -> Result<Leaderboard, String> {
+        let (current, ends_at) = self.current_season().await?;
+        let season = season.unwrap_or(current);
+        if season == 0 || season > current {
+            return Err(format!("Season {} does not exist", season));
+        }
+
+        Ok(Leaderboard {
+            season,
+            ends_at: (season == current).then_some(ends_at),
+            entries: self.standings(season, 0, LEADERBOARD_SIZE - 1).await?,
+        })
+    }
+
+    // Final standings of every past season the player took part in, newest first
+    pub async fn season_history(&self, player_id: &str) -> Result<Vec<SeasonRecord>, String> {
+        let mut con = self.connection().await?;
+        let saved: Vec<String> = redis::cmd("LRANGE")
+            .arg(Self::history_key(player_id))
+            .arg(0)
+            .arg(-1)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+
+        Ok(saved
+            .iter()
+            .filter_map(|json| serde_json::from_str(json).ok())
+            .collect())
+    }
+
+    // Close the running season: archive every player's standing, mail rewards and start the next season.
+    // Safe to run again after a crash or from two servers at once: each player's record, reward mail
+    // and rewarded mark are written in one script, so every player is rewarded exactly once per
+    // season, and the season only advances once every player was.
+    pub async fn end_season(&self) -> Result<u32, String> {
+        let (season, _) = self.current_season().await?;
+        let standings = self.standings(season, 0, -1).await?;
+
+        let mut con = self.connection().await?;
+        for entry in &standings {
+            let record = SeasonRecord {
+                season,
+                rank: entry.rank,
+                rating: entry.rating,
+                wins: entry.wins,
+                losses: entry.losses,
+            };
+            let json = serde_json::to_string(&record)
+                .map_err(|e| format!("Failed to serialize season record: {}", e))?;
+
+            let mail_writes = match SEASON_REWARDS
+                .iter()
+                .find(|(max_rank, _)| *max_rank == 0 || entry.rank <= *max_rank)
+            {
+                Some((_, rewards)) => {
+                    let attachments = rewards
+                        .iter()
+                        .map(|(item_id, quantity)| MailAttachment {
+                            item_id: item_id.to_string(),
+                            quantity: *quantity,
+                        })
+                        .collect();
+                    let body = format!(
+                        "Season {} has ended. You finished rank {} with a rating of {}. Here are your rewards!",
+                        season, entry.rank, entry.rating
+                    );
+                    self.mail_manager.system_mail_writes(&entry.player_id, &format!("Season {} rewards", season), &body, attachments)?
+                }
+                None => Vec::new(),
+            };
+
+            let script = redis::Script::new(REWARD_PLAYER_SCRIPT);
+            let mut invocation = script.key(Self::rewarded_key(season));
+            invocation.key(Self::history_key(&entry.player_id)).arg(&entry.player_id).arg(json);
+            for (key, field, value) in mail_writes {
+                invocation.key(key).arg(field).arg(value);
+            }
+            let rewarded: bool = invocation
+                .invoke_async(&mut con)
+                .await
+                .map_err(|e| format!("Failed to reward player {} for season {}: {}", entry.player_id, season, e))?;
+            if rewarded {
+                info!("Archived season {} and mailed rewards to player {}", season, entry.player_id);
+            }
+        }
+
+        let next_season = season + 1;
+        let ends_at = chrono::Utc::now().timestamp() as u64 + SEASON_DURATION_SECS;
+        let advanced: bool = redis::Script::new(ADVANCE_SEASON_SCRIPT)
+            .key(CURRENT_SEASON_KEY)
+            .key(SEASON_ENDS_AT_KEY)
+            .arg(season)
+            .arg(next_season)
+            .arg(ends_at)
+            .invoke_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+
+        if advanced {
+            info!("Ranked season {} ended with {} players, season {} started", season, standings.len(), next_season);
+        }
+        Ok(next_season)
+    }
+}
+
+// Rating points the winner gains and the loser drops under Elo, at least one
+fn rating_change(winner_rating: f64, loser_rating: f64) -> f64 {
+    let expected_win = 1.0 / (1.0 + 10f64.powf((loser_rating - winner_rating) / 400.0));
+    (K_FACTOR * (1.0 - expected_win)).round().max(1.0)
+}
+
+// Updates ladder ratings after every PvP battle with a winner between players who both hold
+// enough gym badges to play ranked
+pub struct RankedBattleListener {
+    ranking_manager: Arc<RankingManager>,
+    badge_manager: Arc<BadgeManager>,
+}
+
+impl RankedBattleListener {
+    pub fn new(ranking_manager: Arc<RankingManager>, badge_manager: Arc<BadgeManager>) -> Self {
+        Self { ranking_manager, badge_manager }
+    }
+}
+
+impl BattleOutcomeListener for RankedBattleListener {
+    fn on_pvp_battle_finished(&self, summary: &PvPBattleSummary) {
+        let (winner_id, loser_id) = match (&summary.player1_outcome, &summary.player2_outcome) {
+            (PvPBattleOutcome::Victory | PvPBattleOutcome::OpponentDisconnected | PvPBattleOutcome::OpponentSurrendered, _) => {
+                (summary.player1_id.clone(), summary.player2_id.clone())
+            }
+            (_, PvPBattleOutcome::Victory | PvPBattleOutcome::OpponentDisconnected | PvPBattleOutcome::OpponentSurrendered) => {
+                (summary.player2_id.clone(), summary.player1_id.clone())
+            }
+            _ => return,
+        };
+
+        let username_of = |player_id: &str| {
+            summary.lobby.player_positions.get(player_id)
+                .map(|state| state.username.clone())
+                .unwrap_or_else(|| player_id.to_string())
+        };
+        let winner_name = username_of(&winner_id);
+        let loser_name = username_of(&loser_id);
+        let ranking_manager = self.ranking_manager.clone();
+        let badge_manager = self.badge_manager.clone();
+
+        tokio::spawn(async move {
+            for player_id in [&winner_id, &loser_id] {
+                match badge_manager.badge_count(player_id).await {
+                    Ok(badge_count) if badge_count >= RANKED_MIN_BADGES => {}
+                    Ok(_) => {
+                        info!("Battle between {} and {} is unranked: player {} has fewer than {} badges", winner_id, loser_id, player_id, RANKED_MIN_BADGES);
+                        return;
+                    }
+                    Err(e) => {
+                        error!("Failed to check ranked eligibility of player {}: {}", player_id, e);
+                        return;
+                    }
+                }
+            }
+            if let Err(e) = ranking_manager.record_result((&winner_id, &winner_name), (&loser_id, &loser_name)).await {
+                error!("Failed to record ranked result for battle between {} and {}: {}", winner_id, loser_id, e);
+            }
+        });
+    }
+}
+
+// Scheduled event that ends the running season once its time is up and starts the next one
+pub struct SeasonEvents {
+    ranking_manager: Arc<RankingManager>,
+}
+
+impl SeasonEvents {
+    pub fn new(ranking_manager: Arc<RankingManager>) -> Self {
+        Self { ranking_manager }
+    }
+
+    async fn check_season(&self) {
+        let ends_at = match self.ranking_manager.current_season().await {
+            Ok((_, ends_at)) => ends_at,
+            Err(e) => {
+                error!("Failed to read ranked season: {}", e);
+                return;
+            }
+        };
+        if chrono::Utc::now().timestamp() as u64 >= ends_at {
+            if let Err(e) = self.ranking_manager.end_season().await {
+                error!("Failed to end ranked season: {}", e);
+            }
+        }
+    }
+}
+
+impl ScheduledEvent for SeasonEvents {
+    fn name(&self) -> &'static str {
+        "ranked_seasons"
+    }
+
+    fn period(&self) -> Duration {
+        Duration::from_secs(SEASON_CHECK_SECS)
+    }
+
+    fn run(&mut self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        Box::pin(self.check_season())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combat::state::BallType;
+    use crate::game_loop::npc_services::shop_items;
+
+    #[test]
+    fn upsets_move_ratings_more_than_expected_wins() {
+        assert_eq!(rating_change(1000.0, 1000.0), K_FACTOR / 2.0);
+        assert!(rating_change(1000.0, 1400.0) > rating_change(1400.0, 1000.0));
+        assert_eq!(rating_change(3000.0, 0.0), 1.0);
+    }
+
+    #[test]
+    fn season_rewards_are_items_the_game_knows() {
+        let stocked: Vec<String> = shop_items().into_iter().map(|item| item.item_id).collect();
+        for (_, rewards) in SEASON_REWARDS {
+            for (item_id, _) in rewards {
+                assert!(
+                    BallType::from_item_id(item_id).is_some() || stocked.iter().any(|id| id == item_id),
+                    "{} is not a known item",
+                    item_id
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use serde::Serialize;
+
+use crate::combat::manager::BattleManager;
+use crate::game_loop::player_index::Presence;
+use crate::game_loop::pokemon_collection::Pokemon;
+
+// What a player is doing, shaped for rich presence integrations such as Discord
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum RichPresence {
+    // Not connected, appearing offline, or sharing turned off
+    Offline,
+    Online(RichPresenceActivity),
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct RichPresenceActivity {
+    pub username: String,
+    pub lobby_id: String,
+    pub map_id: String,
+    pub presence: Presence,
+    // Set while in battle and the player shares everything
+    pub battle: Option<PresenceBattle>,
+    // The party in slot order, empty unless the player shares everything
+    pub team: Vec<TeamPreviewEntry>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PresenceBattle {
+    pub opponent: PresenceOpponent,
+    // Unix timestamp (seconds) when the battle began, for elapsed time displays
+    pub started_at: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PresenceOpponent {
+    Wild { template_id: u32, name: String, level: u32 },
+    // `name` is None when the opponent appears offline; practice battles are against the server bot
+    Trainer { name: Option<String>, practice: bool },
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct TeamPreviewEntry {
+    pub template_id: u32,
+    pub name: String,
+    pub level: u32,
+    pub is_shiny: bool,
+}
+
+impl From<&Pokemon> for TeamPreviewEntry {
+    fn from(pokemon: &Pokemon) -> Self {
+        Self {
+            template_id: pokemon.template_id,
+            name: pokemon.name.clone(),
+            level: pokemon.level,
+            is_shiny: pokemon.is_shiny,
+        }
+    }
+}
+
+// The battle a player is currently in, with the opponent trainer's player ID for PvP battles
+// so the caller can check whether to reveal their name
+pub async fn current_battle(battle_manager: &BattleManager, player_id: &str) -> Option<(PresenceBattle, Option<String>)> {
+    for battle_id in battle_manager.find_battles_for_player(player_id) {
+        if let Some(battle_mutex) = battle_manager.get_battle_state(battle_id) {
+            let battle_state = battle_mutex.lock().await;
+            let opponent = PresenceOpponent::Wild {
+                template_id: battle_state.wild_pokemon.template_id,
+                name: battle_state.wild_pokemon.name.clone(),
+                level: battle_state.wild_pokemon.level,
+            };
+            return Some((PresenceBattle { opponent, started_at: battle_state.started_at }, None));
+        }
+        if let Some(battle_mutex) = battle_manager.get_pvp_battle_state(battle_id) {
+            let battle_state = battle_mutex.lock().await;
+            let opponent = if battle_state.player1.player_id == player_id { &battle_state.player2 } else { &battle_state.player1 };
+            let practice = battle_state.practice.is_some();
+            let battle = PresenceBattle {
+                opponent: PresenceOpponent::Trainer { name: Some(opponent.name.clone()), practice },
+                started_at: battle_state.started_at,
+            };
+            return Some((battle, (!practice).then(|| opponent.player_id.clone())));
+        }
+    }
+    None
+}
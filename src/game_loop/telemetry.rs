@@ -0,0 +1,284 @@
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio::time::Duration;
+use tracing::{info, warn};
+
+use crate::combat::listeners::{BattleOutcomeListener, PvPBattleSummary, WildBattleSummary};
+use crate::combat::state::{CaptureAttempt, MoveUse, PvPBattleOutcome, WildBattleOutcome};
+
+// Events waiting to be written; further events are dropped while the sink falls behind
+const TELEMETRY_QUEUE_SIZE: usize = 1000;
+// Events written to the sink at once
+const TELEMETRY_BATCH_SIZE: usize = 100;
+// Longest a plain HTTP request may take, from connecting to reading the whole response
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+// Entries kept in the Redis stream sink, trimmed approximately
+const TELEMETRY_STREAM_MAX_LEN: usize = 100_000;
+
+// Where telemetry events are written, set with TELEMETRY_SINK:
+// `redis:<stream key>`, `file:<path>` or an `http://host[:port]/path` URL
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, serde::Deserialize)]
+pub enum TelemetrySinkConfig {
+    RedisStream { stream: String },
+    File { path: String },
+    Http { host: String, port: u16, path: String },
+}
+
+impl TelemetrySinkConfig {
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if let Some(stream) = value.strip_prefix("redis:") {
+            return (!stream.is_empty()).then(|| TelemetrySinkConfig::RedisStream { stream: stream.to_string() });
+        }
+        if let Some(path) = value.strip_prefix("file:") {
+            return (!path.is_empty()).then(|| TelemetrySinkConfig::File { path: path.to_string() });
+        }
+        let (host, port, path) = parse_http_url(value)?;
+        Some(TelemetrySinkConfig::Http { host, port, path })
+    }
+}
+
+// Split an `http://host[:port]/path` URL into host, port and path.
+// Only plain HTTP is spoken; put a proxy in front of endpoints that need TLS.
+fn parse_http_url(value: &str) -> Option<(String, u16, String)> {
+    let rest = value.trim().strip_prefix("http://")?;
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().ok()?),
+        None => (authority, 80),
+    };
+    if host.is_empty() {
+        return None;
+    }
+    Some((host.to_string(), port, path.to_string()))
+}
+
+// Parse an `http://` or `https://` URL for endpoints reached with `tls_client`
+pub fn parse_web_url(value: &str) -> Option<reqwest::Url> {
+    let url = reqwest::Url::parse(value.trim()).ok()?;
+    (matches!(url.scheme(), "http" | "https") && url.host_str().is_some()).then_some(url)
+}
+
+// HTTP client that speaks TLS, for endpoints outside the deployment such as webhooks and backup buckets.
+// Requests that do not finish within HTTP_TIMEOUT count as failed.
+pub fn tls_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .build()
+        .expect("HTTP client builds with the bundled TLS roots")
+}
+
+// One team of a PvP battle, by species only
+#[derive(Debug, Clone, Serialize)]
+pub struct TelemetryTeam {
+    pub species: Vec<u32>,
+    pub outcome: PvPBattleOutcome,
+}
+
+// Anonymized record of a finished battle: species, moves and outcomes, but no player or battle IDs
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TelemetryEvent {
+    WildBattle {
+        ended_at: u64,
+        wild_species: u32,
+        wild_level: u32,
+        team_species: Vec<u32>,
+        outcome: WildBattleOutcome,
+        turns: u32,
+        moves_used: Vec<MoveUse>,
+        capture_attempts: Vec<CaptureAttempt>,
+    },
+    PvpBattle {
+        ended_at: u64,
+        teams: [TelemetryTeam; 2],
+        turns: u32,
+        moves_used: Vec<MoveUse>,
+    },
+}
+
+// Writes batches of events to the configured destination
+pub struct TelemetrySink {
+    config: TelemetrySinkConfig,
+    redis_client: redis::Client,
+}
+
+impl TelemetrySink {
+    pub fn new(config: TelemetrySinkConfig, redis_client: redis::Client) -> Self {
+        Self { config, redis_client }
+    }
+
+    async fn write(&self, events: &[TelemetryEvent]) -> Result<(), String> {
+        let lines = events.iter()
+            .map(|event| serde_json::to_string(event).map_err(|e| format!("Failed to serialize telemetry event: {}", e)))
+            .collect::<Result<Vec<String>, String>>()?;
+        match &self.config {
+            TelemetrySinkConfig::RedisStream { stream } => {
+                let mut con = self.redis_client
+                    .get_async_connection()
+                    .await
+                    .map_err(|e| format!("Redis connection error: {}", e))?;
+                let mut pipe = redis::pipe();
+                for line in &lines {
+                    pipe.cmd("XADD").arg(stream).arg("MAXLEN").arg("~").arg(TELEMETRY_STREAM_MAX_LEN).arg("*").arg("event").arg(line).ignore();
+                }
+                pipe.query_async::<_, ()>(&mut con)
+                    .await
+                    .map_err(|e| format!("Redis query error: {}", e))
+            }
+            TelemetrySinkConfig::File { path } => {
+                let mut file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await
+                    .map_err(|e| format!("Failed to open telemetry file {}: {}", path, e))?;
+                let body = lines.iter().map(|line| format!("{}\n", line)).collect::<String>();
+                file.write_all(body.as_bytes())
+                    .await
+                    .map_err(|e| format!("Failed to write telemetry file {}: {}", path, e))
+            }
+            TelemetrySinkConfig::Http { host, port, path } => {
+                let body = lines.iter().map(|line| format!("{}\n", line)).collect::<String>();
+                http_post(host, *port, path, "application/x-ndjson", &body).await
+            }
+        }
+    }
+}
+
+// POST a body over plain HTTP and check for a 2xx status
+pub async fn http_post(host: &str, port: u16, path: &str, content_type: &str, body: &str) -> Result<(), String> {
+    http_request("POST", host, port, path, &[("Content-Type", content_type.to_string())], body.as_bytes()).await
+}
+
+// The Host header value for a plain HTTP endpoint, with the port unless it is the default
+fn http_host_header(host: &str, port: u16) -> String {
+    if port == 80 {
+        host.to_string()
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+// Send a request with extra headers over plain HTTP and check for a 2xx status.
+// Endpoints that do not answer within HTTP_TIMEOUT count as failed.
+async fn http_request(method: &str, host: &str, port: u16, path: &str, headers: &[(&str, String)], body: &[u8]) -> Result<(), String> {
+    tokio::time::timeout(HTTP_TIMEOUT, send_http_request(method, host, port, path, headers, body))
+        .await
+        .map_err(|_| format!("Request to {}:{} timed out", host, port))?
+}
+
+async fn send_http_request(method: &str, host: &str, port: u16, path: &str, headers: &[(&str, String)], body: &[u8]) -> Result<(), String> {
+    let mut stream = tokio::net::TcpStream::connect((host, port))
+        .await
+        .map_err(|e| format!("Failed to connect to {}:{}: {}", host, port, e))?;
+    let mut request = format!("{} {} HTTP/1.1\r\nHost: {}\r\n", method, path, http_host_header(host, port));
+    for (name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", body.len()));
+    let mut request = request.into_bytes();
+    request.extend_from_slice(body);
+    stream.write_all(&request)
+        .await
+        .map_err(|e| format!("Failed to send request to {}:{}: {}", host, port, e))?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)
+        .await
+        .map_err(|e| format!("Failed to read response from {}:{}: {}", host, port, e))?;
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    match status_line.split_whitespace().nth(1) {
+        Some(status) if status.starts_with('2') => Ok(()),
+        _ => Err(format!("{}:{} answered {:?}", host, port, status_line)),
+    }
+}
+
+// Turns finished battles into telemetry events and queues them for the sink
+pub struct TelemetryListener {
+    tx: mpsc::Sender<TelemetryEvent>,
+}
+
+impl TelemetryListener {
+    // The receiver is handed to `run_telemetry`
+    pub fn new() -> (Self, mpsc::Receiver<TelemetryEvent>) {
+        let (tx, rx) = mpsc::channel(TELEMETRY_QUEUE_SIZE);
+        (Self { tx }, rx)
+    }
+
+    fn emit(&self, event: TelemetryEvent) {
+        // Telemetry is best effort and must never hold up a battle
+        if self.tx.try_send(event).is_err() {
+            warn!("Telemetry queue is full, dropping event");
+        }
+    }
+}
+
+impl BattleOutcomeListener for TelemetryListener {
+    fn on_wild_battle_finished(&self, summary: &WildBattleSummary) {
+        // Battles interrupted by a disconnect say nothing about balance
+        if summary.outcome == WildBattleOutcome::PlayerDisconnected {
+            return;
+        }
+        self.emit(TelemetryEvent::WildBattle {
+            ended_at: chrono::Utc::now().timestamp() as u64,
+            wild_species: summary.wild_pokemon.template_id,
+            wild_level: summary.wild_pokemon.level,
+            team_species: summary.player_team.iter().map(|p| p.template_id).collect(),
+            outcome: summary.outcome.clone(),
+            turns: summary.turns,
+            moves_used: summary.moves_used.clone(),
+            capture_attempts: summary.capture_attempts.clone(),
+        });
+    }
+
+    fn on_pvp_battle_finished(&self, summary: &PvPBattleSummary) {
+        self.emit(TelemetryEvent::PvpBattle {
+            ended_at: chrono::Utc::now().timestamp() as u64,
+            teams: [
+                TelemetryTeam {
+                    species: summary.player1_team.iter().map(|p| p.template_id).collect(),
+                    outcome: summary.player1_outcome.clone(),
+                },
+                TelemetryTeam {
+                    species: summary.player2_team.iter().map(|p| p.template_id).collect(),
+                    outcome: summary.player2_outcome.clone(),
+                },
+            ],
+            turns: summary.turns,
+            moves_used: summary.moves_used.clone(),
+        });
+    }
+}
+
+// Write queued events to the sink in batches, at least once per flush interval
+pub async fn run_telemetry(mut rx: mpsc::Receiver<TelemetryEvent>, sink: TelemetrySink, flush_interval: Duration) {
+    info!("Writing battle telemetry to {:?}", sink.config);
+    let mut interval = tokio::time::interval(flush_interval);
+    let mut batch = Vec::with_capacity(TELEMETRY_BATCH_SIZE);
+    loop {
+        let (flush, closed) = tokio::select! {
+            event = rx.recv() => match event {
+                Some(event) => {
+                    batch.push(event);
+                    (batch.len() >= TELEMETRY_BATCH_SIZE, false)
+                }
+                None => (true, true),
+            },
+            _ = interval.tick() => (true, false),
+        };
+        if flush && !batch.is_empty() {
+            if let Err(e) = sink.write(&batch).await {
+                warn!("Dropping {} telemetry events: {}", batch.len(), e);
+            }
+            batch.clear();
+        }
+        if closed {
+            return;
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use chrono::Utc;
+use dashmap::DashMap;
+use std::sync::Arc;
+use tokio::time::{Duration, Instant};
+
+use crate::lobby::Lobby;
+use crate::models::ServerMessage;
+
+// Server time shared by every system: the wall clock in milliseconds, and a tick number
+// that advances once per player movement tick since the server started
+pub struct ServerClock {
+    started_at: Instant,
+    tick_interval_ms: u64,
+}
+
+impl ServerClock {
+    pub fn new(tick_interval_ms: u64) -> Self {
+        Self { started_at: Instant::now(), tick_interval_ms: tick_interval_ms.max(1) }
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.started_at.elapsed().as_millis() as u64 / self.tick_interval_ms
+    }
+
+    pub fn time_sync(&self) -> ServerMessage {
+        ServerMessage::TimeSync {
+            server_time: Utc::now().timestamp_millis() as u64,
+            tick: self.tick(),
+            tick_interval_ms: self.tick_interval_ms,
+        }
+    }
+}
+
+// Periodically tell every lobby the server time so clients can correct their clock drift
+pub async fn run_time_sync(lobbies: Arc<DashMap<String, Arc<Lobby>>>, clock: Arc<ServerClock>, interval: Duration) {
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        interval.tick().await;
+        let time_sync_json = serde_json::to_string(&clock.time_sync()).unwrap();
+        for lobby in lobbies.iter() {
+            let _ = lobby.tx.send(time_sync_json.clone());
+        }
+    }
+}
@@ -0,0 +1,532 @@
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::game_loop::audit_log::{AuditAction, AuditEntry, AuditLog};
+use crate::game_loop::currency::{CurrencyManager, TransactionReason};
+use crate::game_loop::inventory::InventoryManager;
+use crate::game_loop::pokemon_collection::PokemonCollectionManager;
+use crate::lobby::Lobby;
+use crate::models::ServerMessage;
+
+// What one player puts on the table in a trade
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, schemars::JsonSchema)]
+pub struct TradeOffer {
+    #[serde(default)]
+    pub pokemon_ids: Vec<String>,
+    #[serde(default)]
+    pub items: HashMap<String, u32>,
+    #[serde(default)]
+    pub currency: u64,
+}
+
+impl TradeOffer {
+    fn has_goods(&self) -> bool {
+        self.currency > 0 || self.items.values().any(|quantity| *quantity > 0)
+    }
+}
+
+// One player's side of an open trade
+#[derive(Debug, Clone)]
+struct TradeSide {
+    player_id: String,
+    offer: TradeOffer,
+    confirmed: bool,
+    // Items and currency of the offer have been taken from the player and are held by the trade
+    escrowed: bool,
+}
+
+impl TradeSide {
+    fn new(player_id: &str) -> Self {
+        Self {
+            player_id: player_id.to_string(),
+            offer: TradeOffer::default(),
+            confirmed: false,
+            escrowed: false,
+        }
+    }
+}
+
+// An open trade between two players
+#[derive(Debug)]
+struct TradeSession {
+    trade_id: Uuid,
+    sides: [TradeSide; 2],
+}
+
+impl TradeSession {
+    fn side_index(&self, player_id: &str) -> Option<usize> {
+        self.sides.iter().position(|side| side.player_id == player_id)
+    }
+
+    fn to_view(&self) -> TradeView {
+        TradeView {
+            trade_id: self.trade_id,
+            player1_id: self.sides[0].player_id.clone(),
+            player1_offer: self.sides[0].offer.clone(),
+            player1_confirmed: self.sides[0].confirmed,
+            player2_id: self.sides[1].player_id.clone(),
+            player2_offer: self.sides[1].offer.clone(),
+            player2_confirmed: self.sides[1].confirmed,
+        }
+    }
+}
+
+// Client-facing state of a trade
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
+pub struct TradeView {
+    pub trade_id: Uuid,
+    pub player1_id: String,
+    pub player1_offer: TradeOffer,
+    pub player1_confirmed: bool,
+    pub player2_id: String,
+    pub player2_offer: TradeOffer,
+    pub player2_confirmed: bool,
+}
+
+// Manages player-to-player trades of Pokemon, items and currency.
+// Confirming a trade moves that player's items and currency into escrow; the trade settles once
+// both players confirm, and escrow is returned whenever the trade is changed, cancelled or abandoned.
+pub struct TradeManager {
+    trades: DashMap<Uuid, Arc<Mutex<TradeSession>>>,
+    // Player ID → (open trade, trading partner ID)
+    player_trades: DashMap<String, (Uuid, String)>,
+    // Target player ID → requesting player ID
+    pending_requests: DashMap<String, String>,
+    pokemon_collection_manager: Arc<PokemonCollectionManager>,
+    currency_manager: Arc<CurrencyManager>,
+    inventory_manager: Arc<InventoryManager>,
+    audit_log: Arc<AuditLog>,
+}
+
+impl TradeManager {
+    pub fn new(
+        pokemon_collection_manager: Arc<PokemonCollectionManager>,
+        currency_manager: Arc<CurrencyManager>,
+        inventory_manager: Arc<InventoryManager>,
+        audit_log: Arc<AuditLog>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            trades: DashMap::new(),
+            player_trades: DashMap::new(),
+            pending_requests: DashMap::new(),
+            pokemon_collection_manager,
+            currency_manager,
+            inventory_manager,
+            audit_log,
+        })
+    }
+
+    // The other player in this player's open trade
+    pub fn partner_of(&self, player_id: &str) -> Option<String> {
+        self.player_trades.get(player_id).map(|open_trade| open_trade.1.clone())
+    }
+
+    // Ask another player in the lobby to trade
+    pub async fn request_trade(&self, lobby: &Lobby, requester_id: &str, target_id: &str) -> Result<(), String> {
+        if requester_id == target_id {
+            return Err("You cannot trade with yourself".to_string());
+        }
+        if self.player_trades.contains_key(requester_id) {
+            return Err("You are already trading".to_string());
+        }
+        if self.player_trades.contains_key(target_id) {
+            return Err("That player is already trading".to_string());
+        }
+        let requester_username = match lobby.player_positions.get(requester_id) {
+            Some(state) if state.in_combat => return Err("You cannot trade while in combat".to_string()),
+            Some(state) => state.username.clone(),
+            None => return Err(format!("Player {} not found in lobby", requester_id)),
+        };
+        if !lobby.player_positions.contains_key(target_id) {
+            return Err("Target player not found".to_string());
+        }
+
+        self.pending_requests.insert(target_id.to_string(), requester_id.to_string());
+        let request_msg = ServerMessage::TradeRequestReceived {
+            requester_id: requester_id.to_string(),
+            requester_username,
+        };
+        lobby.send_to_player(target_id, &request_msg).await
+    }
+
+    // Accept or decline a trade request, opening the trade when accepted
+    pub async fn respond_to_request(&self, lobby: &Lobby, target_id: &str, requester_id: &str, accepted: bool) -> Result<(), String> {
+        match self.pending_requests.remove_if(target_id, |_, pending| pending == requester_id) {
+            Some(_) => {}
+            None => return Err("No pending trade request from that player".to_string()),
+        }
+
+        if !accepted {
+            let declined_msg = ServerMessage::TradeCancelled {
+                trade_id: None,
+                reason: "Trade request declined".to_string(),
+            };
+            return lobby.send_to_player(requester_id, &declined_msg).await;
+        }
+
+        if self.player_trades.contains_key(requester_id) || self.player_trades.contains_key(target_id) {
+            return Err("One of the players is already trading".to_string());
+        }
+
+        let trade_id = Uuid::new_v4();
+        let session = TradeSession {
+            trade_id,
+            sides: [TradeSide::new(requester_id), TradeSide::new(target_id)],
+        };
+        let view = session.to_view();
+        self.trades.insert(trade_id, Arc::new(Mutex::new(session)));
+        self.player_trades.insert(requester_id.to_string(), (trade_id, target_id.to_string()));
+        self.player_trades.insert(target_id.to_string(), (trade_id, requester_id.to_string()));
+
+        info!("Opened trade {} between {} and {}", trade_id, requester_id, target_id);
+        Self::send_update(lobby, &view).await;
+        Ok(())
+    }
+
+    fn get_session(&self, trade_id: Uuid, player_id: &str) -> Result<Arc<Mutex<TradeSession>>, String> {
+        match self.player_trades.get(player_id) {
+            Some(open_trade) if open_trade.0 == trade_id => {}
+            _ => return Err(format!("You are not part of trade {}", trade_id)),
+        }
+        self.trades.get(&trade_id)
+            .map(|entry| entry.value().clone())
+            .ok_or_else(|| format!("Trade {} not found", trade_id))
+    }
+
+    // Replace a player's offer; any change un-confirms both sides and returns their escrow
+    pub async fn update_offer(&self, lobby: &Lobby, player_id: &str, trade_id: Uuid, offer: TradeOffer) -> Result<(), String> {
+        let session_mutex = self.get_session(trade_id, player_id)?;
+        let mut session = session_mutex.lock().await;
+        let index = session.side_index(player_id).ok_or("You are not part of this trade")?;
+
+        self.validate_offer(player_id, &offer).await?;
+
+        for side in session.sides.iter_mut() {
+            self.release_escrow(side).await?;
+            side.confirmed = false;
+        }
+        session.sides[index].offer = offer;
+
+        let view = session.to_view();
+        drop(session);
+        Self::send_update(lobby, &view).await;
+        Ok(())
+    }
+
+    // Check the player actually owns everything they offer
+    async fn validate_offer(&self, player_id: &str, offer: &TradeOffer) -> Result<(), String> {
+        if !offer.pokemon_ids.is_empty() {
+            let collection = self.pokemon_collection_manager.get_collection(player_id).await?;
+            for pokemon_id in &offer.pokemon_ids {
+                if !collection.pokemons.contains_key(pokemon_id) {
+                    return Err(format!("You do not own pokemon {}", pokemon_id));
+                }
+            }
+            let mut unique_ids = offer.pokemon_ids.clone();
+            unique_ids.sort();
+            unique_ids.dedup();
+            if unique_ids.len() != offer.pokemon_ids.len() {
+                return Err("The same pokemon was offered twice".to_string());
+            }
+        }
+        for (item_id, quantity) in &offer.items {
+            if self.inventory_manager.get_item_count(player_id, item_id).await? < *quantity {
+                return Err(format!("You do not have {} {}", quantity, item_id));
+            }
+        }
+        if offer.currency > 0 && self.currency_manager.get_balance(player_id).await? < offer.currency {
+            return Err("You do not have enough money".to_string());
+        }
+        Ok(())
+    }
+
+    // Lock in a player's side; settles the trade once both sides have confirmed
+    pub async fn confirm(&self, lobby: &Lobby, player_id: &str, trade_id: Uuid) -> Result<bool, String> {
+        let session_mutex = self.get_session(trade_id, player_id)?;
+        let mut session = session_mutex.lock().await;
+        let index = session.side_index(player_id).ok_or("You are not part of this trade")?;
+
+        // Neither side may be in a battle, or a pokemon could change hands in the middle of it
+        for side in &session.sides {
+            if lobby.player_positions.get(&side.player_id).map(|state| state.in_combat).unwrap_or(false) {
+                return Err(if side.player_id == player_id {
+                    "You cannot trade while in combat".to_string()
+                } else {
+                    "The other player is in combat".to_string()
+                });
+            }
+        }
+
+        if !session.sides[index].confirmed {
+            self.take_escrow(&mut session.sides[index]).await?;
+            session.sides[index].confirmed = true;
+        }
+
+        if !session.sides.iter().all(|side| side.confirmed) {
+            let view = session.to_view();
+            drop(session);
+            Self::send_update(lobby, &view).await;
+            return Ok(false);
+        }
+
+        let offers = session.to_view();
+        let result = self.settle(&mut session).await;
+        let sides = session.sides.clone();
+        let view = session.to_view();
+        drop(session);
+        // A trade still holding escrow it failed to return stays open so cancelling can retry
+        let still_escrowed = sides.iter().any(|side| side.escrowed);
+        if !still_escrowed {
+            self.close_trade(trade_id, &sides);
+        }
+
+        match result {
+            Ok(()) => {
+                info!("Trade {} between {} and {} completed", trade_id, sides[0].player_id, sides[1].player_id);
+                // Before: what each side offered; after: who received it
+                let received: HashMap<&str, &TradeOffer> = HashMap::from([
+                    (sides[0].player_id.as_str(), &offers.player2_offer),
+                    (sides[1].player_id.as_str(), &offers.player1_offer),
+                ]);
+                let player_ids = [sides[0].player_id.clone(), sides[1].player_id.clone()];
+                let audit_entry = AuditEntry::new(player_id, AuditAction::TradeCompleted { trade_id, player_ids })
+                    .with_change(&offers, &received);
+                self.audit_log.record(audit_entry).await;
+                for side in &sides {
+                    let completed_msg = ServerMessage::TradeCompleted { trade_id };
+                    if let Err(e) = lobby.send_to_player(&side.player_id, &completed_msg).await {
+                        warn!("Failed to send trade completion to player {}: {}", side.player_id, e);
+                    }
+                }
+                Ok(true)
+            }
+            Err(e) => {
+                error!("Trade {} failed to settle: {}", trade_id, e);
+                if still_escrowed {
+                    Self::send_update(lobby, &view).await;
+                } else {
+                    Self::send_cancelled(lobby, &sides, trade_id, &format!("Trade failed: {}", e)).await;
+                }
+                Err(e)
+            }
+        }
+    }
+
+    // Cancel a trade, returning all escrowed goods; the trade stays open if any of them can't be returned
+    pub async fn cancel(&self, lobby: &Lobby, player_id: &str, trade_id: Uuid, reason: &str) -> Result<(), String> {
+        let session_mutex = self.get_session(trade_id, player_id)?;
+        let mut session = session_mutex.lock().await;
+        for side in session.sides.iter_mut() {
+            self.release_escrow(side).await?;
+            side.confirmed = false;
+        }
+        let sides = session.sides.clone();
+        drop(session);
+        self.close_trade(trade_id, &sides);
+
+        info!("Trade {} cancelled by {}: {}", trade_id, player_id, reason);
+        Self::send_cancelled(lobby, &sides, trade_id, reason).await;
+        Ok(())
+    }
+
+    // Abandon any trade or request involving a player who left
+    pub async fn handle_disconnect(&self, lobby: &Lobby, player_id: &str) {
+        self.pending_requests.remove(player_id);
+        self.pending_requests.retain(|_, requester| requester != player_id);
+
+        let trade_id = match self.player_trades.get(player_id) {
+            Some(open_trade) => open_trade.0,
+            None => return,
+        };
+        if let Err(e) = self.cancel(lobby, player_id, trade_id, "The other player disconnected").await {
+            error!("Failed to cancel trade {} after disconnect: {}", trade_id, e);
+        }
+    }
+
+    fn close_trade(&self, trade_id: Uuid, sides: &[TradeSide]) {
+        self.trades.remove(&trade_id);
+        for side in sides {
+            self.player_trades.remove_if(&side.player_id, |_, open_trade| open_trade.0 == trade_id);
+        }
+    }
+
+    // Move a side's items and currency into escrow
+    async fn take_escrow(&self, side: &mut TradeSide) -> Result<(), String> {
+        if !side.escrowed {
+            self.withdraw_goods(&side.offer, &side.player_id).await?;
+        }
+        side.escrowed = true;
+        Ok(())
+    }
+
+    // Give a side's escrowed goods back to its owner; the side stays escrowed if that fails
+    async fn release_escrow(&self, side: &mut TradeSide) -> Result<(), String> {
+        if side.escrowed {
+            self.deliver_goods(&side.offer, &side.player_id).await?;
+            side.escrowed = false;
+        }
+        Ok(())
+    }
+
+    // Take an offer's items and currency from a player, putting back anything taken if part of it fails
+    async fn withdraw_goods(&self, offer: &TradeOffer, player_id: &str) -> Result<(), String> {
+        if !offer.has_goods() {
+            return Ok(());
+        }
+
+        if offer.currency > 0 {
+            self.currency_manager.debit(player_id, offer.currency, TransactionReason::Trade).await?;
+        }
+
+        let mut taken: Vec<(&String, u32)> = Vec::new();
+        for (item_id, quantity) in offer.items.iter().filter(|(_, quantity)| **quantity > 0) {
+            match self.inventory_manager.remove_item(player_id, item_id, *quantity).await {
+                Ok(_) => taken.push((item_id, *quantity)),
+                Err(e) => {
+                    for (taken_id, taken_quantity) in taken {
+                        if let Err(e) = self.inventory_manager.add_item(player_id, taken_id, taken_quantity).await {
+                            error!("Failed to return {} to player {}: {}", taken_id, player_id, e);
+                        }
+                    }
+                    if offer.currency > 0 {
+                        if let Err(e) = self.currency_manager.credit(player_id, offer.currency, TransactionReason::Trade).await {
+                            error!("Failed to return money to player {}: {}", player_id, e);
+                        }
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Hand an offer's items and currency to a player, taking back anything given if part of it fails
+    async fn deliver_goods(&self, offer: &TradeOffer, player_id: &str) -> Result<(), String> {
+        if !offer.has_goods() {
+            return Ok(());
+        }
+
+        if offer.currency > 0 {
+            self.currency_manager.credit(player_id, offer.currency, TransactionReason::Trade).await?;
+        }
+
+        let mut given: Vec<(&String, u32)> = Vec::new();
+        for (item_id, quantity) in offer.items.iter().filter(|(_, quantity)| **quantity > 0) {
+            match self.inventory_manager.add_item(player_id, item_id, *quantity).await {
+                Ok(_) => given.push((item_id, *quantity)),
+                Err(e) => {
+                    for (given_id, given_quantity) in given {
+                        if let Err(e) = self.inventory_manager.remove_item(player_id, given_id, given_quantity).await {
+                            error!("Failed to take back {} from player {}: {}", given_id, player_id, e);
+                        }
+                    }
+                    if offer.currency > 0 {
+                        if let Err(e) = self.currency_manager.debit(player_id, offer.currency, TransactionReason::Trade).await {
+                            error!("Failed to take back money from player {}: {}", player_id, e);
+                        }
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Exchange everything on both sides. Pokemon are checked and swapped in one step before
+    // anything else moves, so a failure here returns all escrow instead of completing half a trade.
+    async fn settle(&self, session: &mut TradeSession) -> Result<(), String> {
+        for index in 0..2 {
+            let side = &session.sides[index];
+            let collection = match self.pokemon_collection_manager.get_collection(&side.player_id).await {
+                Ok(collection) => collection,
+                Err(e) => return self.abort_settlement(session, e).await,
+            };
+            if let Some(missing) = side.offer.pokemon_ids.iter().find(|id| !collection.pokemons.contains_key(*id)) {
+                let e = format!("Pokemon {} is no longer available", missing);
+                return self.abort_settlement(session, e).await;
+            }
+            let receives = session.sides[1 - index].offer.pokemon_ids.len();
+            if collection.pokemons.len() - side.offer.pokemon_ids.len() + receives == 0 {
+                let e = format!("Player {} cannot trade away their last pokemon", side.player_id);
+                return self.abort_settlement(session, e).await;
+            }
+        }
+
+        let player_ids = [session.sides[0].player_id.as_str(), session.sides[1].player_id.as_str()];
+        let pokemon_ids = [session.sides[0].offer.pokemon_ids.as_slice(), session.sides[1].offer.pokemon_ids.as_slice()];
+        if let Err(e) = self.pokemon_collection_manager.exchange_pokemon(player_ids, pokemon_ids).await {
+            return self.abort_settlement(session, e).await;
+        }
+
+        // Escrow is only released once both sides are delivered; a failure undoes the
+        // deliveries and the pokemon swap so the escrow can go back to its owners
+        for index in 0..2 {
+            let to = session.sides[1 - index].player_id.clone();
+            if let Err(e) = self.deliver_goods(&session.sides[index].offer, &to).await {
+                self.undo_delivery(session, index).await;
+                return self.abort_settlement(session, e).await;
+            }
+        }
+        for side in session.sides.iter_mut() {
+            side.escrowed = false;
+        }
+        Ok(())
+    }
+
+    // Take back the goods delivered before side `failed` and swap the pokemon back
+    async fn undo_delivery(&self, session: &mut TradeSession, failed: usize) {
+        for index in 0..failed {
+            let (owner, recipient) = (session.sides[index].player_id.clone(), session.sides[1 - index].player_id.clone());
+            if let Err(e) = self.withdraw_goods(&session.sides[index].offer, &recipient).await {
+                // The goods stay with the recipient, so there is no escrow left to return
+                error!("Failed to take back {}'s traded goods from player {}: {}", owner, recipient, e);
+                session.sides[index].escrowed = false;
+            }
+        }
+
+        // Each player now holds what the other offered, so give those back
+        let player_ids = [session.sides[0].player_id.as_str(), session.sides[1].player_id.as_str()];
+        let pokemon_ids = [session.sides[1].offer.pokemon_ids.as_slice(), session.sides[0].offer.pokemon_ids.as_slice()];
+        if let Err(e) = self.pokemon_collection_manager.exchange_pokemon(player_ids, pokemon_ids).await {
+            error!("Failed to swap pokemon back between players {} and {}: {}", player_ids[0], player_ids[1], e);
+        }
+    }
+
+    // Return both sides' escrow and fail with `reason`. A side whose escrow can't be returned
+    // stays escrowed and unconfirmed, and the trade stays open so cancelling can retry.
+    async fn abort_settlement(&self, session: &mut TradeSession, reason: String) -> Result<(), String> {
+        for side in session.sides.iter_mut() {
+            side.confirmed = false;
+            if let Err(e) = self.release_escrow(side).await {
+                error!("Failed to return escrow to player {}: {}", side.player_id, e);
+            }
+        }
+        Err(reason)
+    }
+
+    async fn send_update(lobby: &Lobby, view: &TradeView) {
+        let update_msg = ServerMessage::TradeUpdated { trade: view.clone() };
+        for player_id in [&view.player1_id, &view.player2_id] {
+            if let Err(e) = lobby.send_to_player(player_id, &update_msg).await {
+                warn!("Failed to send trade update to player {}: {}", player_id, e);
+            }
+        }
+    }
+
+    async fn send_cancelled(lobby: &Lobby, sides: &[TradeSide], trade_id: Uuid, reason: &str) {
+        let cancelled_msg = ServerMessage::TradeCancelled {
+            trade_id: Some(trade_id),
+            reason: reason.to_string(),
+        };
+        for side in sides {
+            if let Err(e) = lobby.send_to_player(&side.player_id, &cancelled_msg).await {
+                warn!("Failed to send trade cancellation to player {}: {}", side.player_id, e);
+            }
+        }
+    }
+}
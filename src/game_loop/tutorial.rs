@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::combat::listeners::{BattleOutcomeListener, WildBattleSummary};
+use crate::combat::state::WildBattleOutcome;
+use crate::game_loop::pokemon_collection::PokemonCollectionManager;
+use crate::lobby::Lobby;
+use crate::models::ServerMessage;
+
+// Steps of the onboarding tutorial, in the order players go through them
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TutorialStep {
+    ChooseStarter,
+    // The first wild battle is scripted: the player's Pokémon cannot faint and every ball catches
+    FirstBattle,
+    FirstCapture,
+    Completed,
+}
+
+impl TutorialStep {
+    pub fn next(self) -> Self {
+        match self {
+            TutorialStep::ChooseStarter => TutorialStep::FirstBattle,
+            TutorialStep::FirstBattle => TutorialStep::FirstCapture,
+            TutorialStep::FirstCapture | TutorialStep::Completed => TutorialStep::Completed,
+        }
+    }
+
+    pub fn prompt(self) -> &'static str {
+        match self {
+            TutorialStep::ChooseStarter => "Choose your first Pokémon to begin your journey.",
+            TutorialStep::FirstBattle => "Walk up to a wild Pokémon and interact with it to start your first battle.",
+            TutorialStep::FirstCapture => "Weaken a wild Pokémon, then throw a Poké Ball to catch it.",
+            TutorialStep::Completed => "You're ready! Other trainers can now be challenged to battles.",
+        }
+    }
+
+    // Battling other players unlocks once the first Pokémon has been caught
+    pub fn can_challenge(self) -> bool {
+        self == TutorialStep::Completed
+    }
+}
+
+// Tracks each player's tutorial step in Redis
+pub struct TutorialManager {
+    redis_client: redis::Client,
+}
+
+impl TutorialManager {
+    pub fn new(redis_client: redis::Client) -> Arc<Self> {
+        Arc::new(Self { redis_client })
+    }
+
+    fn tutorial_key(player_id: &str) -> String {
+        format!("tutorial:{}", player_id)
+    }
+
+    async fn connection(&self) -> Result<redis::aio::Connection, String> {
+        self.redis_client
+            .get_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection error: {}", e))
+    }
+
+    async fn stored_step(&self, player_id: &str) -> Result<Option<TutorialStep>, String> {
+        let mut con = self.connection().await?;
+        let json: Option<String> = redis::cmd("GET")
+            .arg(Self::tutorial_key(player_id))
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        json.map(|json| serde_json::from_str(&json).map_err(|e| format!("Failed to deserialize tutorial step: {}", e)))
+            .transpose()
+    }
+
+    // The step a player is on. Players from before the tutorial existed skip it
+    // unless they have not chosen a starter yet.
+    pub async fn get_step(&self, player_id: &str, pokemon_collection_manager: &PokemonCollectionManager) -> Result<TutorialStep, String> {
+        if let Some(step) = self.stored_step(player_id).await? {
+            return Ok(step);
+        }
+        let collection = pokemon_collection_manager.get_collection(player_id).await?;
+        Ok(if collection.pokemons.is_empty() { TutorialStep::ChooseStarter } else { TutorialStep::Completed })
+    }
+
+    // Move a player past `step` if that is the step they are on, returning the step they are on now
+    pub async fn complete(&self, player_id: &str, step: TutorialStep) -> Result<Option<TutorialStep>, String> {
+        let current = self.stored_step(player_id).await?.unwrap_or(TutorialStep::ChooseStarter);
+        if current != step || step == TutorialStep::Completed {
+            return Ok(None);
+        }
+        let next = step.next();
+        let json = serde_json::to_string(&next)
+            .map_err(|e| format!("Failed to serialize tutorial step: {}", e))?;
+        let mut con = self.connection().await?;
+        redis::cmd("SET")
+            .arg(Self::tutorial_key(player_id))
+            .arg(json)
+            .query_async::<_, ()>(&mut con)
+            .await
+            .map_err(|e| format!("Redis query error: {}", e))?;
+        info!("Player {} completed tutorial step {:?}", player_id, step);
+        Ok(Some(next))
+    }
+
+    // Complete `steps` in order and tell the player where they ended up
+    pub async fn complete_and_notify(&self, lobby: &Lobby, player_id: &str, steps: &[TutorialStep]) {
+        let mut reached = None;
+        for &step in steps {
+            match self.complete(player_id, step).await {
+                Ok(Some(next)) => reached = Some(next),
+                Ok(None) => {}
+                Err(e) => {
+                    error!("Failed to update tutorial of player {}: {}", player_id, e);
+                    return;
+                }
+            }
+        }
+        if let Some(step) = reached {
+            let tutorial_msg = ServerMessage::TutorialStep { step, prompt: step.prompt().to_string() };
+            if let Err(e) = lobby.send_to_player(player_id, &tutorial_msg).await {
+                error!("Failed to send tutorial step to player {}: {}", player_id, e);
+            }
+        }
+    }
+}
+
+// Advances the tutorial when a player wins or catches their way through a wild battle
+pub struct TutorialListener {
+    tutorial_manager: Arc<TutorialManager>,
+}
+
+impl TutorialListener {
+    pub fn new(tutorial_manager: Arc<TutorialManager>) -> Self {
+        Self { tutorial_manager }
+    }
+}
+
+impl BattleOutcomeListener for TutorialListener {
+    fn on_wild_battle_finished(&self, summary: &WildBattleSummary) {
+        let steps: &'static [TutorialStep] = match summary.outcome {
+            WildBattleOutcome::Victory => &[TutorialStep::FirstBattle],
+            WildBattleOutcome::Captured => &[TutorialStep::FirstBattle, TutorialStep::FirstCapture],
+            _ => return,
+        };
+        let tutorial_manager = self.tutorial_manager.clone();
+        let lobby = summary.lobby.clone();
+        let player_id = summary.player_id.clone();
+        tokio::spawn(async move {
+            tutorial_manager.complete_and_notify(&lobby, &player_id, steps).await;
+        });
+    }
+}
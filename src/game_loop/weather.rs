@@ -0,0 +1,132 @@
+use dashmap::DashMap;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::time::Duration;
+use tracing::info;
+
+use crate::game_loop::event_scheduler::ScheduledEvent;
+use crate::combat::logic::weather::INDEFINITE_WEATHER_TURNS;
+use crate::combat::state::{WeatherState, WeatherType};
+use crate::lobby::Lobby;
+use crate::models::ServerMessage;
+use crate::monsters::monster::PokemonType;
+
+// How long a weather pattern lasts before the next one is rolled
+const WEATHER_ROTATION_SECS: u64 = 10 * 60;
+// Spawn weight multiplier for species that thrive in the current weather
+const FAVORED_SPAWN_MULTIPLIER: f32 = 2.0;
+// Overworld weather lasts for the whole battle
+const BATTLE_WEATHER_TURNS: u8 = INDEFINITE_WEATHER_TURNS;
+
+// Weather shown in the overworld, shared by every lobby on the same map
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OverworldWeather {
+    #[default]
+    Clear,
+    Rain,
+    Sandstorm,
+    Snow,
+}
+
+impl OverworldWeather {
+    // Relative chance of each weather being picked when rotating
+    const ROTATION_WEIGHTS: [(OverworldWeather, u32); 4] = [
+        (OverworldWeather::Clear, 55),
+        (OverworldWeather::Rain, 25),
+        (OverworldWeather::Sandstorm, 10),
+        (OverworldWeather::Snow, 10),
+    ];
+
+    // Pick the next weather pattern at random
+    pub fn roll() -> OverworldWeather {
+        let total: u32 = Self::ROTATION_WEIGHTS.iter().map(|(_, weight)| weight).sum();
+        let mut roll = rand::thread_rng().gen_range(0..total);
+        for (weather, weight) in Self::ROTATION_WEIGHTS {
+            if roll < weight {
+                return weather;
+            }
+            roll -= weight;
+        }
+        OverworldWeather::Clear
+    }
+
+    // Battle weather that is already active when an encounter starts under this weather
+    pub fn battle_weather(&self) -> Option<WeatherState> {
+        let weather_type = match self {
+            OverworldWeather::Clear => return None,
+            OverworldWeather::Rain => WeatherType::Rain,
+            OverworldWeather::Sandstorm => WeatherType::Sandstorm,
+            OverworldWeather::Snow => WeatherType::Hail,
+        };
+        Some(WeatherState {
+            weather_type,
+            turns_left: BATTLE_WEATHER_TURNS,
+        })
+    }
+
+    // Types that show up more often in this weather
+    pub fn favored_types(&self) -> &'static [PokemonType] {
+        match self {
+            OverworldWeather::Clear => &[],
+            OverworldWeather::Rain => &[PokemonType::Water],
+            OverworldWeather::Sandstorm => &[PokemonType::Rock, PokemonType::Ground, PokemonType::Steel],
+            OverworldWeather::Snow => &[PokemonType::Ice],
+        }
+    }
+
+    // Spawn weight multiplier for a species with the given types
+    pub fn spawn_multiplier(&self, types: &[PokemonType]) -> f32 {
+        if types.iter().any(|t| self.favored_types().contains(t)) {
+            FAVORED_SPAWN_MULTIPLIER
+        } else {
+            1.0
+        }
+    }
+}
+
+// Scheduled event that rolls new weather for each map and tells the lobbies on it
+pub struct WeatherRotation {
+    lobbies: Arc<DashMap<String, Arc<Lobby>>>,
+}
+
+impl WeatherRotation {
+    pub fn new(lobbies: Arc<DashMap<String, Arc<Lobby>>>) -> Self {
+        Self { lobbies }
+    }
+
+    async fn rotate(&self) {
+        let mut weather_by_map: HashMap<String, OverworldWeather> = HashMap::new();
+        let lobbies: Vec<Arc<Lobby>> = self.lobbies.iter().map(|entry| entry.value().clone()).collect();
+        for lobby in lobbies {
+            let weather = *weather_by_map
+                .entry(lobby.map_id.clone())
+                .or_insert_with(OverworldWeather::roll);
+
+            if lobby.weather() == weather {
+                continue;
+            }
+            *lobby.weather.write().unwrap() = weather;
+            info!("Weather in lobby {} changed to {:?}", lobby.id, weather);
+            let _ = lobby.broadcast_except(&ServerMessage::WeatherChanged { weather }, &[]).await;
+        }
+    }
+}
+
+impl ScheduledEvent for WeatherRotation {
+    fn name(&self) -> &'static str {
+        "weather_rotation"
+    }
+
+    fn period(&self) -> Duration {
+        Duration::from_secs(WEATHER_ROTATION_SECS)
+    }
+
+    fn run(&mut self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        Box::pin(self.rotate())
+    }
+}
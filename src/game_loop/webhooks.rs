@@ -0,0 +1,195 @@
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::combat::listeners::{BattleOutcomeListener, PvPBattleSummary, WildBattleSummary};
+use crate::combat::state::{PvPBattleOutcome, WildBattleOutcome};
+use crate::game_loop::backups::to_hex;
+use crate::game_loop::telemetry::{parse_web_url, tls_client};
+
+// Events waiting to be delivered; further events are dropped while the endpoints fall behind
+const WEBHOOK_QUEUE_SIZE: usize = 1000;
+// Header carrying `sha256=<hex HMAC-SHA256 of the body>` when a webhook secret is set
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+// An `http://` or `https://` URL events are POSTed to, listed in WEBHOOK_URLS
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    pub url: String,
+}
+
+impl WebhookEndpoint {
+    pub fn parse(value: &str) -> Option<Self> {
+        parse_web_url(value).map(|url| Self { url: url.to_string() })
+    }
+}
+
+// Something external services (Discord bots, analytics) want to hear about
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WebhookEvent {
+    WildBattleFinished {
+        battle_id: Uuid,
+        lobby_id: String,
+        player_id: String,
+        wild_species: u32,
+        wild_level: u32,
+        outcome: WildBattleOutcome,
+        turns: u32,
+    },
+    PvpBattleFinished {
+        battle_id: Uuid,
+        lobby_id: String,
+        player1_id: String,
+        player2_id: String,
+        player1_outcome: PvPBattleOutcome,
+        turns: u32,
+    },
+    // A shiny or alpha monster was captured
+    RareCapture {
+        battle_id: Uuid,
+        lobby_id: String,
+        player_id: String,
+        species: u32,
+        name: String,
+        level: u32,
+        shiny: bool,
+        alpha: bool,
+    },
+    TradeCompleted {
+        trade_id: Uuid,
+        lobby_id: String,
+        player_ids: [String; 2],
+    },
+}
+
+// Body of every webhook request
+#[derive(Debug, Serialize)]
+struct WebhookPayload<'a> {
+    // Unix time in milliseconds
+    sent_at: i64,
+    #[serde(flatten)]
+    event: &'a WebhookEvent,
+}
+
+// Queues events for delivery by `run_webhooks`; also listens for finished battles
+pub struct Webhooks {
+    tx: mpsc::Sender<WebhookEvent>,
+}
+
+impl Webhooks {
+    // The receiver is handed to `run_webhooks`
+    pub fn new() -> (Self, mpsc::Receiver<WebhookEvent>) {
+        let (tx, rx) = mpsc::channel(WEBHOOK_QUEUE_SIZE);
+        (Self { tx }, rx)
+    }
+
+    pub fn emit(&self, event: WebhookEvent) {
+        // Delivery is best effort and must never hold up the game
+        if self.tx.try_send(event).is_err() {
+            warn!("Webhook queue is full, dropping event");
+        }
+    }
+}
+
+impl BattleOutcomeListener for Webhooks {
+    fn on_wild_battle_finished(&self, summary: &WildBattleSummary) {
+        if summary.outcome == WildBattleOutcome::PlayerDisconnected {
+            return;
+        }
+        self.emit(WebhookEvent::WildBattleFinished {
+            battle_id: summary.battle_id,
+            lobby_id: summary.lobby.id.clone(),
+            player_id: summary.player_id.clone(),
+            wild_species: summary.wild_pokemon.template_id,
+            wild_level: summary.wild_pokemon.level,
+            outcome: summary.outcome.clone(),
+            turns: summary.turns,
+        });
+        if summary.outcome == WildBattleOutcome::Captured && (summary.wild_shiny || summary.wild_alpha) {
+            self.emit(WebhookEvent::RareCapture {
+                battle_id: summary.battle_id,
+                lobby_id: summary.lobby.id.clone(),
+                player_id: summary.player_id.clone(),
+                species: summary.wild_pokemon.template_id,
+                name: summary.wild_pokemon.name.clone(),
+                level: summary.wild_pokemon.level,
+                shiny: summary.wild_shiny,
+                alpha: summary.wild_alpha,
+            });
+        }
+    }
+
+    fn on_pvp_battle_finished(&self, summary: &PvPBattleSummary) {
+        self.emit(WebhookEvent::PvpBattleFinished {
+            battle_id: summary.battle_id,
+            lobby_id: summary.lobby.id.clone(),
+            player1_id: summary.player1_id.clone(),
+            player2_id: summary.player2_id.clone(),
+            player1_outcome: summary.player1_outcome.clone(),
+            turns: summary.turns,
+        });
+    }
+}
+
+// Value of the signature header for `body` signed with `secret`
+pub fn signature(secret: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body.as_bytes());
+    format!("sha256={}", to_hex(&mac.finalize().into_bytes()))
+}
+
+// POST each queued event as JSON to every endpoint, in order, signed with `secret` when set
+pub async fn run_webhooks(mut rx: mpsc::Receiver<WebhookEvent>, endpoints: Vec<WebhookEndpoint>, secret: Option<String>) {
+    info!("Sending webhooks to {:?}", endpoints);
+    let client = tls_client();
+    while let Some(event) = rx.recv().await {
+        let payload = WebhookPayload { sent_at: chrono::Utc::now().timestamp_millis(), event: &event };
+        let body = match serde_json::to_string(&payload) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("Failed to serialize webhook event: {}", e);
+                continue;
+            }
+        };
+        let signature = secret.as_ref().map(|secret| signature(secret, &body));
+        for endpoint in &endpoints {
+            let mut request = client.post(&endpoint.url)
+                .header("Content-Type", "application/json")
+                .body(body.clone());
+            if let Some(signature) = &signature {
+                request = request.header(SIGNATURE_HEADER, signature);
+            }
+            let result = request.send().await.and_then(|response| response.error_for_status());
+            if let Err(e) = result {
+                warn!("Failed to deliver webhook to {}: {}", endpoint.url, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_is_the_hex_hmac_sha256_of_the_body() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            signature("Jefe", "what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn endpoints_accept_http_and_https_urls() {
+        let discord = WebhookEndpoint::parse("https://discord.com/api/webhooks/1/token").expect("https URLs are accepted");
+        assert_eq!(discord.url, "https://discord.com/api/webhooks/1/token");
+        assert!(WebhookEndpoint::parse(" http://bot.internal:8080/events ").is_some());
+        assert!(WebhookEndpoint::parse("ftp://bot.internal/events").is_none());
+        assert!(WebhookEndpoint::parse("discord.com/api/webhooks").is_none());
+    }
+}
@@ -0,0 +1,365 @@
+// Read-only GraphQL API over game data and player state, built with the `graphql` feature.
+// Resolvers read from the same repositories and managers as the websocket and REST handlers.
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Object, Schema, SimpleObject};
+use axum::{response::IntoResponse, Extension, Json};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::game_loop::market::{AskingCondition, ListedGoods, MarketListing, MarketSearchQuery};
+use crate::game_loop::pokemon_collection::Pokemon;
+use crate::game_loop::ranking::Leaderboard;
+use crate::lobby::get_lobby;
+use crate::monsters::locales::Locales;
+use crate::monsters::monster::MonsterTemplate;
+use crate::monsters::monster_manager::MonsterTemplateRepository;
+use crate::monsters::move_manager::MoveData;
+
+pub type GameSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+pub fn build_schema(state: Arc<AppState>) -> GameSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(state)
+        .finish()
+}
+
+// POST /graphql
+pub async fn graphql_handler(
+    Extension(schema): Extension<GameSchema>,
+    Json(request): Json<async_graphql::Request>,
+) -> impl IntoResponse {
+    Json(schema.execute(request).await)
+}
+
+// Name of an enum variant as it appears in JSON, e.g. "special_attack"
+fn serde_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value).ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn unavailable(what: &str) -> async_graphql::Error {
+    async_graphql::Error::new(format!("{} unavailable", what))
+}
+
+// Translate a species into `locale`, keeping English for anything not translated
+fn localize_species(mut species: Species, locale: Option<&str>, locales: &Locales) -> Species {
+    if let Some(name) = locale.and_then(|locale| locales.species_name(locale, species.id)) {
+        species.name = name.to_string();
+    }
+    species
+}
+
+fn localize_move(mut move_view: Move, locale: Option<&str>, locales: &Locales) -> Move {
+    if let Some(locale) = locale {
+        if let Some(name) = locales.move_name(locale, move_view.id) {
+            move_view.name = name.to_string();
+        }
+        if let Some(description) = locales.move_description(locale, move_view.id) {
+            move_view.description = description.to_string();
+        }
+    }
+    move_view
+}
+
+fn template_repository<'a>(ctx: &Context<'a>) -> async_graphql::Result<&'a MonsterTemplateRepository> {
+    let state = ctx.data::<Arc<AppState>>()?;
+    state.monster_manager_factory.as_ref()
+        .map(|factory| factory.template_repository.as_ref())
+        .ok_or_else(|| unavailable("Monster data"))
+}
+
+#[derive(SimpleObject)]
+pub struct Stats {
+    hp: u32,
+    attack: u32,
+    defense: u32,
+    special_attack: u32,
+    special_defense: u32,
+    speed: u32,
+}
+
+#[derive(SimpleObject)]
+pub struct LearnsetEntry {
+    move_id: u32,
+    level: u32,
+}
+
+#[derive(SimpleObject)]
+pub struct Species {
+    id: u32,
+    name: String,
+    types: Vec<String>,
+    abilities: Vec<String>,
+    base_stats: Stats,
+    min_level: u32,
+    max_level: u32,
+    learnset: Vec<LearnsetEntry>,
+}
+
+impl From<&MonsterTemplate> for Species {
+    fn from(template: &MonsterTemplate) -> Self {
+        let stats = &template.base_stats;
+        Self {
+            id: template.id,
+            name: template.name.clone(),
+            types: template.types.iter().map(serde_name).collect(),
+            abilities: template.abilities.clone(),
+            base_stats: Stats {
+                hp: stats.hp,
+                attack: stats.attack,
+                defense: stats.defense,
+                special_attack: stats.special_attack,
+                special_defense: stats.special_defense,
+                speed: stats.speed,
+            },
+            min_level: template.min_level,
+            max_level: template.max_level,
+            learnset: template.moves.iter().map(|&(move_id, level)| LearnsetEntry { move_id, level }).collect(),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct Move {
+    id: u32,
+    name: String,
+    #[graphql(name = "type")]
+    move_type: String,
+    category: String,
+    power: Option<u32>,
+    accuracy: Option<u8>,
+    pp: u8,
+    priority: i8,
+    description: String,
+}
+
+impl From<&MoveData> for Move {
+    fn from(move_data: &MoveData) -> Self {
+        Self {
+            id: move_data.id,
+            name: move_data.name.clone(),
+            move_type: serde_name(&move_data.move_type),
+            category: serde_name(&move_data.damage_class),
+            power: move_data.power,
+            accuracy: move_data.accuracy,
+            pp: move_data.pp,
+            priority: move_data.priority,
+            description: move_data.description.clone(),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct OwnedPokemon {
+    id: String,
+    template_id: u32,
+    name: String,
+    level: u32,
+    types: Vec<String>,
+    is_shiny: bool,
+}
+
+impl From<&Pokemon> for OwnedPokemon {
+    fn from(pokemon: &Pokemon) -> Self {
+        Self {
+            id: pokemon.id.clone(),
+            template_id: pokemon.template_id,
+            name: pokemon.name.clone(),
+            level: pokemon.level,
+            types: pokemon.types.iter().map(serde_name).collect(),
+            is_shiny: pokemon.is_shiny,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct OnlineStatus {
+    username: String,
+    lobby_id: String,
+    // "online", "afk" or "in_battle"
+    presence: String,
+}
+
+#[derive(SimpleObject)]
+pub struct BadgeView {
+    id: String,
+    name: String,
+}
+
+pub struct PlayerProfile {
+    id: String,
+}
+
+#[Object]
+impl PlayerProfile {
+    async fn id(&self) -> &str {
+        &self.id
+    }
+
+    // Where the player is connected, None while offline or appearing offline
+    async fn online(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<OnlineStatus>> {
+        let state = ctx.data::<Arc<AppState>>()?;
+        let Some(player) = state.player_index.get(&self.id) else {
+            return Ok(None);
+        };
+        if let Some(player_settings_manager) = state.player_settings_manager.as_ref() {
+            if player_settings_manager.get_settings(&self.id).await?.appear_offline {
+                return Ok(None);
+            }
+        }
+        let Some(lobby) = get_lobby(state, &player.lobby_id) else {
+            return Ok(None);
+        };
+        let presence = match lobby.player_positions.get(&self.id) {
+            Some(player_state) if player_state.in_combat => "in_battle",
+            Some(player_state) if player_state.afk => "afk",
+            Some(_) => "online",
+            None => return Ok(None),
+        };
+        Ok(Some(OnlineStatus { username: player.username, lobby_id: player.lobby_id, presence: presence.to_string() }))
+    }
+
+    // The player's party in slot order
+    async fn party(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<OwnedPokemon>> {
+        let state = ctx.data::<Arc<AppState>>()?;
+        let pokemon_collection_manager = state.pokemon_collection_manager.as_ref().ok_or_else(|| unavailable("Collections"))?;
+        let pokemons = pokemon_collection_manager.get_active_pokemons(&self.id).await?;
+        Ok(pokemons.iter().map(OwnedPokemon::from).collect())
+    }
+
+    // Gym badges the player has earned, in the order the gyms are meant to be beaten
+    async fn badges(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<BadgeView>> {
+        let state = ctx.data::<Arc<AppState>>()?;
+        let badge_manager = state.badge_manager.as_ref().ok_or_else(|| unavailable("Badges"))?;
+        let badges = badge_manager.badges(&self.id).await?;
+        Ok(badges.into_iter().map(|badge| BadgeView { id: badge.id, name: badge.name }).collect())
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct MarketListingView {
+    listing_id: String,
+    seller_id: String,
+    seller_username: String,
+    // Exactly one of `pokemon` and `item_id` is set
+    pokemon: Option<OwnedPokemon>,
+    item_id: Option<String>,
+    quantity: Option<u32>,
+    // What the seller asks for: an amount of currency, a Pokémon or an item
+    asking_currency: Option<u64>,
+    asking_template_id: Option<u32>,
+    asking_min_level: Option<u32>,
+    asking_item_id: Option<String>,
+    asking_item_quantity: Option<u32>,
+    created_at: u64,
+    expires_at: u64,
+}
+
+impl From<MarketListing> for MarketListingView {
+    fn from(listing: MarketListing) -> Self {
+        let (pokemon, item_id, quantity) = match listing.goods {
+            ListedGoods::Pokemon { pokemon } => (Some(OwnedPokemon::from(pokemon.as_ref())), None, None),
+            ListedGoods::Item { item_id, quantity } => (None, Some(item_id), Some(quantity)),
+        };
+        let mut view = Self {
+            listing_id: listing.listing_id.to_string(),
+            seller_id: listing.seller_id,
+            seller_username: listing.seller_username,
+            pokemon,
+            item_id,
+            quantity,
+            asking_currency: None,
+            asking_template_id: None,
+            asking_min_level: None,
+            asking_item_id: None,
+            asking_item_quantity: None,
+            created_at: listing.created_at,
+            expires_at: listing.expires_at,
+        };
+        match listing.asking {
+            AskingCondition::Currency { amount } => view.asking_currency = Some(amount),
+            AskingCondition::Pokemon { template_id, min_level } => {
+                view.asking_template_id = Some(template_id);
+                view.asking_min_level = Some(min_level);
+            }
+            AskingCondition::Item { item_id, quantity } => {
+                view.asking_item_id = Some(item_id);
+                view.asking_item_quantity = Some(quantity);
+            }
+        }
+        view
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    // Names are in `locale` where translated, English otherwise
+    async fn species(&self, ctx: &Context<'_>, id: u32, locale: Option<String>) -> async_graphql::Result<Option<Species>> {
+        let locales = &ctx.data::<Arc<AppState>>()?.locales;
+        Ok(template_repository(ctx)?.templates.get(&id).map(|template| localize_species(Species::from(template), locale.as_deref(), locales)))
+    }
+
+    // Every species, optionally only those of one type, ordered by ID
+    async fn all_species(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(name = "type")] pokemon_type: Option<String>,
+        locale: Option<String>,
+    ) -> async_graphql::Result<Vec<Species>> {
+        let locales = &ctx.data::<Arc<AppState>>()?.locales;
+        let mut species: Vec<Species> = template_repository(ctx)?.templates.values()
+            .filter(|template| pokemon_type.as_ref().is_none_or(|pokemon_type| template.types.iter().any(|t| serde_name(t) == *pokemon_type)))
+            .map(|template| localize_species(Species::from(template), locale.as_deref(), locales))
+            .collect();
+        species.sort_by_key(|species| species.id);
+        Ok(species)
+    }
+
+    #[graphql(name = "move")]
+    async fn move_by_id(&self, ctx: &Context<'_>, id: u32, locale: Option<String>) -> async_graphql::Result<Option<Move>> {
+        let locales = &ctx.data::<Arc<AppState>>()?.locales;
+        let move_repository = template_repository(ctx)?.move_repository.as_ref().ok_or_else(|| unavailable("Move data"))?;
+        Ok(move_repository.get_move(id).map(|move_data| localize_move(Move::from(move_data), locale.as_deref(), locales)))
+    }
+
+    // Every move, ordered by ID
+    async fn moves(&self, ctx: &Context<'_>, locale: Option<String>) -> async_graphql::Result<Vec<Move>> {
+        let locales = &ctx.data::<Arc<AppState>>()?.locales;
+        let move_repository = template_repository(ctx)?.move_repository.as_ref().ok_or_else(|| unavailable("Move data"))?;
+        let mut moves: Vec<Move> = move_repository.moves.values()
+            .map(|move_data| localize_move(Move::from(move_data), locale.as_deref(), locales))
+            .collect();
+        moves.sort_by_key(|move_view| move_view.id);
+        Ok(moves)
+    }
+
+    async fn player(&self, id: String) -> PlayerProfile {
+        PlayerProfile { id }
+    }
+
+    // Ranked leaderboard of `season`, the running season when left out
+    async fn leaderboard(&self, ctx: &Context<'_>, season: Option<u32>) -> async_graphql::Result<Leaderboard> {
+        let state = ctx.data::<Arc<AppState>>()?;
+        let ranking_manager = state.ranking_manager.as_ref().ok_or_else(|| unavailable("Leaderboard"))?;
+        Ok(ranking_manager.leaderboard(season).await?)
+    }
+
+    // Open market listings, filtered like the /market endpoint
+    async fn market_listings(
+        &self,
+        ctx: &Context<'_>,
+        template_id: Option<u32>,
+        item_id: Option<String>,
+        max_price: Option<u64>,
+        seller_id: Option<String>,
+    ) -> async_graphql::Result<Vec<MarketListingView>> {
+        let state = ctx.data::<Arc<AppState>>()?;
+        let market_manager = state.market_manager.as_ref().ok_or_else(|| unavailable("Market"))?;
+        let query = MarketSearchQuery { template_id, item_id, max_price, seller_id };
+        let listings = market_manager.search(&query).await?;
+        Ok(listings.into_iter().map(MarketListingView::from).collect())
+    }
+}
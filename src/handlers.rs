@@ -1,8 +1,31 @@
 use crate::app_state::AppState;
 use crate::models::{ClientMessage, PlayerState, ServerMessage, DisplayPokemon};
-use crate::lobby::{Lobby, validate_lobby_id, get_lobby};
+use crate::lobby::{Lobby, validate_lobby_id, get_lobby, close_connection, LobbyConnectionGuard, PendingChallenge};
 use crate::redis_manager;
 use crate::game_loop;
+use crate::game_loop::currency::TransactionReason;
+use crate::game_loop::friends::FriendList;
+use crate::game_loop::npc_services::{shop_items, MOVE_RELEARNER_FEE, NAME_RATER_FEE};
+use crate::game_loop::market::MarketSearchQuery;
+use crate::game_loop::pokemon_collection::{box_name, pp_ups_for_item, StarterChoice};
+use crate::game_loop::occupancy::MonsterCollision;
+use crate::game_loop::player_movement::SURF_ITEM_ID;
+use crate::game_loop::lobby_snapshots::{LobbySnapshot, LobbySnapshotManager};
+use crate::game_loop::player_settings::{ChallengePolicy, NotificationKind, RichPresenceSharing};
+use crate::game_loop::rich_presence::{current_battle, PresenceOpponent, RichPresence, RichPresenceActivity, TeamPreviewEntry};
+use crate::game_loop::latency::MAX_REPORTED_RTT_MS;
+use crate::game_loop::tutorial::TutorialStep;
+use crate::game_loop::webhooks::WebhookEvent;
+use crate::monsters::locales::DEFAULT_LOCALE;
+use crate::game_loop::audit_log::{AuditAction, AuditEntry, AuditQuery, ADMIN_ACTOR};
+use crate::game_loop::lobby_events::LobbyEventKind;
+use crate::game_loop::lobby_buffs::buff_for_item;
+use crate::game_loop::player_index::{PlayerSearchResult, Presence, MAX_SEARCH_RESULTS};
+use crate::combat::fairness::{BattleTranscript, FairnessDisclosure};
+use crate::combat::manager::PvPLoadout;
+use crate::combat::gyms::{GymLeader, GymLeaderView, GymOffer};
+use crate::combat::manager::BattleManager;
+use crate::protocol::{negotiate_capabilities, Capability, ClientProtocol, CloseReason, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
@@ -13,14 +36,15 @@ use axum::{
 };
 use axum::extract::ws::Utf8Bytes;
 use chrono::Utc;
-use futures_util::{SinkExt, StreamExt};
+use futures_util::{SinkExt, StreamExt, stream::SplitSink};
+use serde::Deserialize;
 use tokio::time::Instant;
 use tracing::{info, error, warn};
 use uuid::Uuid;
 use std::sync::Arc;
-use crate::monsters::monster_manager::MonsterManager;
+use crate::monsters::monster_manager::{MonsterManager, TerrainType};
 use crate::monsters::Monster;
-use crate::monsters::monster::DisplayMonster;
+use crate::monsters::monster::{DisplayMonster, MonsterPreview, DEFAULT_CATCH_RATE};
 use tokio::sync::Mutex;
 
 // Public lobbies endpoint to fetch list of active lobbies
@@ -60,6 +84,8 @@ pub async fn ws_lobby_handler(
         }
     };
     
+    let ws = limit_message_size(ws, state.config.server.max_message_bytes);
+
     // Get the lobby, but don't create if it doesn't exist
     match get_lobby(&state, &lobby_id) {
         Some(lobby) => ws.on_upgrade(move |socket| handle_lobby_socket(socket, state, lobby, username)),
@@ -76,189 +102,1310 @@ fn validate_username(username: &str) -> bool {
     username.chars().all(|c| c.is_alphanumeric() || c == '_')
 }
 
-// Health check endpoint
-pub async fn health_handler() -> impl IntoResponse {
-    "OK"
+// Market search endpoint, filtered by the same query parameters as `ClientMessage::MarketSearch`
+pub async fn market_search_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<MarketSearchQuery>,
+) -> impl IntoResponse {
+    let market_manager = match state.market_manager.as_ref() {
+        Some(market_manager) => market_manager,
+        None => return (axum::http::StatusCode::SERVICE_UNAVAILABLE, "Market unavailable").into_response(),
+    };
+    match market_manager.search(&query).await {
+        Ok(listings) => Json(listings).into_response(),
+        Err(e) => {
+            error!("Failed to search market: {}", e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to search market").into_response()
+        }
+    }
 }
 
-// Handle WebSocket connection for a lobby
-pub async fn handle_lobby_socket(socket: WebSocket, state: Arc<AppState>, lobby: Arc<Lobby>, username: String) {
-    let (sender, mut receiver) = socket.split();
-    let sender = Arc::new(tokio::sync::Mutex::new(sender));
-    
-    // Clone state for usage throughout this function
-    let state_for_tasks = state.clone();
-    let state_for_disconnect = state.clone();
+#[derive(Deserialize)]
+pub struct LeaderboardQuery {
+    season: Option<u32>,
+}
 
-    // Wait for join message with session token
-    let session_token = if let Some(Ok(Message::Text(text))) = receiver.next().await {
-        match serde_json::from_str::<ClientMessage>(&text) {
-            Ok(ClientMessage::Join { session_token }) => session_token,
-            _ => {
-                tracing::error!("First message must be a join message with session token");
-                return;
-            }
-        }
-    } else {
-        tracing::error!("Failed to receive join message");
-        return;
+// Ranked leaderboard endpoint; `?season=` selects a past season
+pub async fn leaderboard_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<LeaderboardQuery>,
+) -> impl IntoResponse {
+    let ranking_manager = match state.ranking_manager.as_ref() {
+        Some(ranking_manager) => ranking_manager,
+        None => return (axum::http::StatusCode::SERVICE_UNAVAILABLE, "Leaderboard unavailable").into_response(),
     };
+    match ranking_manager.leaderboard(query.season).await {
+        Ok(leaderboard) => Json(leaderboard).into_response(),
+        Err(e) => (axum::http::StatusCode::NOT_FOUND, e).into_response(),
+    }
+}
 
-    // Connect to Redis
-    let mut redis_conn = state_for_tasks.redis.get_async_connection().await.expect("Failed to connect to Redis");
-    
-    // Retrieve or create player id via Redis
-    let player_id = match redis_manager::get_player_id(&mut redis_conn, &session_token).await {
-        Ok(id) => {
-            tracing::info!("Found existing player ID {} for session {}", id, session_token);
-            id
-        },
-        Err(_) => {
-            // Create a new player ID
-            let new_id = Uuid::new_v4().to_string();
-            tracing::info!("Created new player ID {} for session {}", new_id, session_token);
-            new_id
-        }
+#[derive(Deserialize)]
+pub struct BattleHistoryQuery {
+    page: Option<usize>,
+    page_size: Option<usize>,
+}
+
+// Paginated battle history of a player, for profile pages
+pub async fn battle_history_handler(
+    State(state): State<Arc<AppState>>,
+    Path(player_id): Path<String>,
+    Query(query): Query<BattleHistoryQuery>,
+) -> impl IntoResponse {
+    let battle_history_manager = match state.battle_history_manager.as_ref() {
+        Some(battle_history_manager) => battle_history_manager,
+        None => return (axum::http::StatusCode::SERVICE_UNAVAILABLE, "Battle history unavailable").into_response(),
     };
-    tracing::info!("Player connected to lobby {}: {} (session: {})", lobby.id, player_id, session_token);
+    match battle_history_manager.get_history(&player_id, query.page, query.page_size).await {
+        Ok(history) => Json(history).into_response(),
+        Err(e) => {
+            error!("Failed to load battle history for player {}: {}", player_id, e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to load battle history").into_response()
+        }
+    }
+}
 
-    // Store session-token mapping in Redis with permanent persistence
-    // We use a constant 10-year timeout (function will handle this)
-    redis_manager::store_session(
-        &mut redis_conn, 
-        &session_token, 
-        &player_id,
-        0 // The actual value doesn't matter, the function uses a long-term constant
-    ).await.expect("Failed to store session in Redis");
+#[derive(Deserialize)]
+pub struct PlayerSearchQuery {
+    #[serde(default)]
+    username: String,
+}
 
-    // Create a broadcast receiver for lobby events
-    let mut rx = lobby.tx.subscribe();
+// Connected players whose username starts with `?username=`; without it, who is online
+pub async fn player_search_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<PlayerSearchQuery>,
+) -> impl IntoResponse {
+    Json(find_players(&state, &query.username, None).await)
+}
 
-    // Retrieve or initialize player state from Redis
-    let player_state = match redis_manager::get_player_state(&mut redis_conn, &lobby.id, &player_id).await {
-        Ok(state_json) => match serde_json::from_str::<PlayerState>(&state_json) {
-            Ok(state) => state,
-            Err(e) => {
-                tracing::warn!("Error deserializing player state: {}", e);
-                return;
+// Search the connected players of every lobby, leaving out players who appear offline
+// and, when `searcher_id` is given, players blocked by or blocking the searcher
+async fn find_players(state: &Arc<AppState>, query: &str, searcher_id: Option<&str>) -> Vec<PlayerSearchResult> {
+    let mut results = Vec::new();
+    for player in state.player_index.search(query) {
+        if results.len() == MAX_SEARCH_RESULTS {
+            break;
+        }
+        if searcher_id.is_some_and(|searcher_id| {
+            state.block_list_manager.as_ref().is_some_and(|block_list_manager| block_list_manager.is_blocked_between(searcher_id, &player.player_id))
+        }) {
+            continue;
+        }
+        if let Some(player_settings_manager) = state.player_settings_manager.as_ref() {
+            if player_settings_manager.get_settings(&player.player_id).await.is_ok_and(|settings| settings.appear_offline) {
+                continue;
             }
+        }
+        let Some(lobby) = get_lobby(state, &player.lobby_id) else {
+            continue;
+        };
+        let presence = match lobby.player_positions.get(&player.player_id) {
+            Some(player_state) if player_state.in_combat => Presence::InBattle,
+            Some(player_state) if player_state.afk => Presence::Afk,
+            Some(_) => Presence::Online,
+            None => continue,
+        };
+        let level_range = match state.pokemon_collection_manager.as_ref() {
+            Some(pokemon_collection_manager) => pokemon_collection_manager.get_active_pokemons(&player.player_id).await
+                .ok()
+                .and_then(|pokemons| {
+                    let min = pokemons.iter().map(|pokemon| pokemon.level).min()?;
+                    let max = pokemons.iter().map(|pokemon| pokemon.level).max()?;
+                    Some((min, max))
+                }),
+            None => None,
+        };
+        results.push(PlayerSearchResult {
+            player_id: player.player_id,
+            username: player.username,
+            lobby_id: player.lobby_id,
+            level_range,
+            presence,
+        });
+    }
+    results
+}
+
+// A finished PvP battle as sent in its fairness message
+#[derive(Deserialize)]
+pub struct VerifyBattleRequest {
+    transcript: BattleTranscript,
+    disclosure: FairnessDisclosure,
+}
+
+// Re-simulate a PvP battle from its disclosed seed and check it against the transcript
+pub async fn verify_battle_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<VerifyBattleRequest>,
+) -> impl IntoResponse {
+    let battle_manager = match state.battle_manager.as_ref() {
+        Some(battle_manager) => battle_manager,
+        None => return (axum::http::StatusCode::SERVICE_UNAVAILABLE, "Battle verification unavailable").into_response(),
+    };
+    Json(battle_manager.verify_battle(&request.transcript, &request.disclosure)).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct ResumeQuery {
+    session_token: String,
+}
+
+// Where a player left off: the lobby to reconnect to and any PvP battle still waiting for them
+#[derive(serde::Serialize)]
+pub struct ResumeInfo {
+    lobby_id: String,
+    x: u32,
+    y: u32,
+    pvp_battle_id: Option<Uuid>,
+}
+
+// Resume endpoint for a client starting without context; connecting to the returned lobby rejoins the battle
+pub async fn resume_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ResumeQuery>,
+) -> impl IntoResponse {
+    let mut redis_conn = match state.redis.get_async_connection().await {
+        Ok(redis_conn) => redis_conn,
+        Err(e) => {
+            error!("Redis unavailable for resume lookup: {}", e);
+            return (axum::http::StatusCode::SERVICE_UNAVAILABLE, "Game data is temporarily unavailable").into_response();
+        }
+    };
+    let player_id = match redis_manager::get_player_id(&mut redis_conn, &query.session_token).await {
+        Ok(player_id) => player_id,
+        Err(_) => return (axum::http::StatusCode::NOT_FOUND, "Unknown session").into_response(),
+    };
+    let lobby = match redis_manager::get_last_lobby(&mut redis_conn, &player_id).await {
+        Ok(Some(lobby_id)) => match get_lobby(&state, &lobby_id) {
+            Some(lobby) => lobby,
+            None => return (axum::http::StatusCode::NOT_FOUND, "Last lobby no longer exists").into_response(),
         },
-        Err(_) => {
-            // Create a new player state with random position
-            let new_state = PlayerState {
-                id: player_id.clone(),
-                username: username.clone(),
-                x: 5 + rand::random::<u32>() % 16,
-                y: 5,
-                direction: "down".to_string(),
-                in_combat: false,
-            };
-            
-            // Store the new state in Redis
-            let state_json = serde_json::to_string(&new_state).unwrap();
-            if let Err(e) = redis_manager::store_player_state(
-                &mut redis_conn,
-                &lobby.id,
-                &player_id,
-                &state_json,
-            ).await {
-                tracing::error!("Failed to store new player state in Redis: {}", e);
-            }
-            
-            new_state
+        Ok(None) => return (axum::http::StatusCode::NOT_FOUND, "No lobby to resume").into_response(),
+        Err(e) => {
+            error!("Failed to load last lobby for player {}: {}", player_id, e);
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to load last lobby").into_response();
         }
     };
-    tracing::info!("Player state in lobby {}: {:?}", lobby.id, player_state);
 
-    // Add player to lobby state
-    lobby.player_positions.insert(player_id.clone(), player_state.clone());
-    lobby.player_last_active.insert(player_id.clone(), Instant::now());
-    
-    // Store the WebSocket sender in the lobby's player_connections map
-    lobby.player_connections.insert(player_id.clone(), sender.clone());
+    // Prefer the live position if the player is still in the lobby, else the saved one
+    let live_position = lobby.player_positions.get(&player_id).map(|player_state| (player_state.x, player_state.y));
+    let position = match live_position {
+        Some(position) => Some(position),
+        None => redis_manager::get_player_state(&mut redis_conn, &lobby.id, &player_id).await.ok()
+            .and_then(|json| serde_json::from_str::<PlayerState>(&json).ok())
+            .map(|player_state| (player_state.x, player_state.y)),
+    };
+    let Some((x, y)) = position else {
+        return (axum::http::StatusCode::NOT_FOUND, "No lobby to resume").into_response();
+    };
+    let pvp_battle_id = state.battle_manager.as_ref().and_then(|battle_manager| battle_manager.pending_pvp_battle(&player_id));
 
-    // Send welcome message
-    let welcome_msg = ServerMessage::Welcome { 
-        id: player_id.clone(), 
-        username: player_state.username.clone(),
-        x: player_state.x, 
-        y: player_state.y 
+    Json(ResumeInfo { lobby_id: lobby.id.clone(), x, y, pvp_battle_id }).into_response()
+}
+
+// Player behind the session token sent as a bearer token, the same token clients join lobbies with
+async fn authenticated_player(state: &AppState, headers: &axum::http::HeaderMap) -> Result<String, axum::response::Response> {
+    let Some(session_token) = headers.get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return Err((axum::http::StatusCode::UNAUTHORIZED, "Session token required").into_response());
     };
-    if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&welcome_msg).unwrap()))).await {
-        tracing::error!("Failed to send welcome message: {}", e);
-        return;
+    let mut redis_conn = match state.redis.get_async_connection().await {
+        Ok(redis_conn) => redis_conn,
+        Err(e) => {
+            error!("Redis unavailable for session lookup: {}", e);
+            return Err((axum::http::StatusCode::SERVICE_UNAVAILABLE, "Game data is temporarily unavailable").into_response());
+        }
+    };
+    match redis_manager::get_player_id(&mut redis_conn, session_token).await {
+        Ok(player_id) => Ok(player_id),
+        Err(e) if redis_manager::is_outage(&e) => {
+            error!("Redis unavailable for session lookup: {}", e);
+            Err((axum::http::StatusCode::SERVICE_UNAVAILABLE, "Game data is temporarily unavailable").into_response())
+        }
+        Err(_) => Err((axum::http::StatusCode::UNAUTHORIZED, "Unknown session").into_response()),
     }
+}
 
-    // Send current players in lobby
-    let players = lobby.player_positions.iter().map(|entry| entry.value().clone()).collect::<Vec<_>>();
-    let players_msg = ServerMessage::Players { players };
-    if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&players_msg).unwrap()))).await {
-        tracing::error!("Failed to send players message: {}", e);
-        return;
+// JSON response with species and move names in the player's locale
+async fn localized_json<T: serde::Serialize>(state: &AppState, player_id: &str, body: &T) -> axum::response::Response {
+    let locale = match state.player_settings_manager.as_ref() {
+        Some(player_settings_manager) => player_settings_manager.get_settings(player_id).await.ok().and_then(|settings| settings.locale),
+        None => None,
+    };
+    let (Some(locale), Some(factory)) = (locale, state.monster_manager_factory.as_ref()) else {
+        return Json(body).into_response();
+    };
+    match serde_json::to_value(body) {
+        Ok(mut value) => {
+            state.locales.localize_json(&locale, &mut value, &factory.template_repository);
+            Json(value).into_response()
+        }
+        Err(e) => {
+            error!("Failed to serialize response for player {}: {}", player_id, e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to build response").into_response()
+        }
     }
+}
 
-    // Send current monsters to the player if monster manager exists
-    let monsters = MonsterManager::get_monsters_for_lobby(&lobby);
-    let display_monsters = monsters.iter()
-        .filter_map(|m| {
-            if let Ok(monster) = m.try_lock() {
-                Some(monster.to_display())
-            } else {
-                None
-            }
-        })
+// One box of the player's storage, without its contents
+#[derive(serde::Serialize)]
+pub struct BoxSummary {
+    box_index: usize,
+    box_name: String,
+    pokemon_count: usize,
+}
+
+#[derive(serde::Serialize)]
+pub struct CollectionView {
+    party: Vec<DisplayPokemon>,
+    boxes: Vec<BoxSummary>,
+}
+
+// The player's party and an overview of their boxes, for clients without a websocket
+pub async fn my_collection_handler(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+) -> impl IntoResponse {
+    let player_id = match authenticated_player(&state, &headers).await {
+        Ok(player_id) => player_id,
+        Err(response) => return response,
+    };
+    let Some(pokemon_collection_manager) = state.pokemon_collection_manager.as_ref() else {
+        return (axum::http::StatusCode::SERVICE_UNAVAILABLE, "Collections unavailable").into_response();
+    };
+    let collection = match pokemon_collection_manager.get_collection(&player_id).await {
+        Ok(collection) => collection,
+        Err(e) => {
+            error!("Failed to load collection of player {}: {}", player_id, e);
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to load collection").into_response();
+        }
+    };
+    let party = collection.active_pokemons.iter()
+        .filter_map(|id| collection.pokemons.get(id))
+        .map(|pokemon| pokemon_collection_manager.pokemon_to_display_pokemon(pokemon))
         .collect();
-    let monsters_msg = ServerMessage::Monsters { monsters: display_monsters };
-    if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&monsters_msg).unwrap()))).await {
-        tracing::error!("Failed to send monsters message: {}", e);
-        return;
+    let boxes = collection.boxes.iter().enumerate()
+        .map(|(box_index, pc_box)| BoxSummary { box_index, box_name: box_name(box_index), pokemon_count: pc_box.len() })
+        .collect();
+    localized_json(&state, &player_id, &CollectionView { party, boxes }).await
+}
+
+// The Pokémon in one of the player's boxes, numbered from 0
+pub async fn my_box_handler(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(box_index): Path<usize>,
+) -> impl IntoResponse {
+    let player_id = match authenticated_player(&state, &headers).await {
+        Ok(player_id) => player_id,
+        Err(response) => return response,
+    };
+    let Some(pokemon_collection_manager) = state.pokemon_collection_manager.as_ref() else {
+        return (axum::http::StatusCode::SERVICE_UNAVAILABLE, "Collections unavailable").into_response();
+    };
+    match pokemon_collection_manager.get_box(&player_id, box_index).await {
+        Ok(Some(pokemons)) => {
+            let pokemons: Vec<DisplayPokemon> = pokemons.iter()
+                .map(|pokemon| pokemon_collection_manager.pokemon_to_display_pokemon(pokemon))
+                .collect();
+            localized_json(&state, &player_id, &pokemons).await
+        }
+        Ok(None) => (axum::http::StatusCode::NOT_FOUND, "Box not found").into_response(),
+        Err(e) => {
+            error!("Failed to load box {} of player {}: {}", box_index, player_id, e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to load box").into_response()
+        }
     }
+}
 
-    // Send player's Pokémon collection
-    if let Some(pokemon_collection_manager) = &state_for_tasks.pokemon_collection_manager {
-        match pokemon_collection_manager.get_active_pokemons(&player_id).await {
-            Ok(pokemons) => { 
-                // Convert Vec<Pokemon> to Vec<DisplayPokemon>
-                let display_pokemons: Vec<DisplayPokemon> = pokemons
-                    .iter()
-                    .map(|p| pokemon_collection_manager.pokemon_to_display_pokemon(p))
-                    .collect();
+// The player's items and their counts
+pub async fn my_inventory_handler(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+) -> impl IntoResponse {
+    let player_id = match authenticated_player(&state, &headers).await {
+        Ok(player_id) => player_id,
+        Err(response) => return response,
+    };
+    let Some(inventory_manager) = state.inventory_manager.as_ref() else {
+        return (axum::http::StatusCode::SERVICE_UNAVAILABLE, "Inventory unavailable").into_response();
+    };
+    match inventory_manager.get_inventory(&player_id).await {
+        Ok(items) => Json(items).into_response(),
+        Err(e) => {
+            error!("Failed to load inventory of player {}: {}", player_id, e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to load inventory").into_response()
+        }
+    }
+}
 
-                // Use the converted Vec<DisplayPokemon>
-                let active_pokemons_msg: ServerMessage = ServerMessage::ActivePokemons { pokemons: display_pokemons };
-                    
-                info!("Sending pokemon collection message to player {}: {:?}", player_id, active_pokemons_msg);
-                if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&active_pokemons_msg).unwrap()))).await {
-                    tracing::error!("Failed to send pokemon collection message: {}", e);
-                }
-            },
+// GET /me/presence: what the player is doing, for rich presence relays such as a Discord bot.
+// Follows the player's privacy settings: appearing offline or hiding presence reports them offline.
+pub async fn my_presence_handler(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+) -> impl IntoResponse {
+    let player_id = match authenticated_player(&state, &headers).await {
+        Ok(player_id) => player_id,
+        Err(response) => return response,
+    };
+    let settings = match state.player_settings_manager.as_ref() {
+        Some(player_settings_manager) => match player_settings_manager.get_settings(&player_id).await {
+            Ok(settings) => settings,
             Err(e) => {
-                tracing::error!("Failed to fetch pokemon collection for player {}: {}", player_id, e);
+                error!("Failed to load settings of player {}: {}", player_id, e);
+                return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to load settings").into_response();
+            }
+        },
+        None => game_loop::player_settings::PlayerSettings::default(),
+    };
+    if settings.appear_offline || settings.rich_presence == RichPresenceSharing::Hidden {
+        return Json(RichPresence::Offline).into_response();
+    }
+    let Some(player) = state.player_index.get(&player_id) else {
+        return Json(RichPresence::Offline).into_response();
+    };
+    let Some(lobby) = get_lobby(&state, &player.lobby_id) else {
+        return Json(RichPresence::Offline).into_response();
+    };
+    let presence = match lobby.player_positions.get(&player_id) {
+        Some(player_state) if player_state.in_combat => Presence::InBattle,
+        Some(player_state) if player_state.afk => Presence::Afk,
+        Some(_) => Presence::Online,
+        None => return Json(RichPresence::Offline).into_response(),
+    };
+    let mut activity = RichPresenceActivity {
+        username: player.username,
+        lobby_id: player.lobby_id,
+        map_id: lobby.map_id.clone(),
+        presence,
+        battle: None,
+        team: Vec::new(),
+    };
+    if settings.rich_presence == RichPresenceSharing::Full {
+        if let Some(battle_manager) = state.battle_manager.as_ref() {
+            if let Some((mut battle, opponent_id)) = current_battle(battle_manager, &player_id).await {
+                // An opponent who appears offline is not named
+                if let (Some(opponent_id), Some(player_settings_manager)) = (opponent_id, state.player_settings_manager.as_ref()) {
+                    if !player_settings_manager.get_settings(&opponent_id).await.is_ok_and(|settings| !settings.appear_offline) {
+                        battle.opponent = PresenceOpponent::Trainer { name: None, practice: false };
+                    }
+                }
+                activity.battle = Some(battle);
+            }
+        }
+        if let Some(pokemon_collection_manager) = state.pokemon_collection_manager.as_ref() {
+            match pokemon_collection_manager.get_active_pokemons(&player_id).await {
+                Ok(pokemons) => activity.team = pokemons.iter().map(TeamPreviewEntry::from).collect(),
+                Err(e) => warn!("Failed to load party of player {} for rich presence: {}", player_id, e),
             }
         }
     }
+    localized_json(&state, &player_id, &RichPresence::Online(activity)).await
+}
 
-    // Notify others in lobby about the new player
-    let new_player_msg = ServerMessage::PlayerJoined { player: player_state };
-    let _ = lobby.tx.send(serde_json::to_string(&new_player_msg).unwrap());
+// Whether the request carries the configured admin token as a bearer token
+fn is_admin(state: &AppState, headers: &axum::http::HeaderMap) -> bool {
+    let Some(admin_token) = state.config.server.admin_token.as_deref() else {
+        return false;
+    };
+    headers.get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| token == admin_token)
+}
 
-    // Clone references for tasks
-    let player_id_for_receiver = player_id.clone();
-    let lobby_for_receiver = lobby.clone();
-    let sender_for_receiver = sender.clone();
-    let player_id_for_forward = player_id.clone();
-    let lobby_for_forward = lobby.clone();
+// Flagged battles with stored replays, most recent first
+pub async fn admin_replays_handler(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+) -> impl IntoResponse {
+    if !is_admin(&state, &headers) {
+        return (axum::http::StatusCode::UNAUTHORIZED, "Admin token required").into_response();
+    }
+    let replay_manager = match state.battle_replay_manager.as_ref() {
+        Some(replay_manager) => replay_manager,
+        None => return (axum::http::StatusCode::SERVICE_UNAVAILABLE, "Replays unavailable").into_response(),
+    };
+    match replay_manager.list().await {
+        Ok(replays) => Json(replays).into_response(),
+        Err(e) => {
+            error!("Failed to list battle replays: {}", e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to list battle replays").into_response()
+        }
+    }
+}
 
-    // Handle incoming messages from the player
-    let mut player_task = tokio::spawn(async move {
-        while let Some(Ok(msg)) = receiver.next().await {
-            if let Message::Text(text) = msg {
-                // Update last active timestamp in lobby
-                lobby_for_receiver.player_last_active.insert(player_id_for_receiver.clone(), Instant::now());
-                info!("Received message: {}", text);
-                match serde_json::from_str::<ClientMessage>(&text) {
-                    Ok(ClientMessage::Join { session_token }) => {
-                    },
+async fn load_replay(state: &AppState, battle_id: Uuid) -> Result<crate::combat::replay::BattleReplay, axum::response::Response> {
+    let replay_manager = match state.battle_replay_manager.as_ref() {
+        Some(replay_manager) => replay_manager,
+        None => return Err((axum::http::StatusCode::SERVICE_UNAVAILABLE, "Replays unavailable").into_response()),
+    };
+    match replay_manager.get(battle_id).await {
+        Ok(Some(replay)) => Ok(replay),
+        Ok(None) => Err((axum::http::StatusCode::NOT_FOUND, "Replay not found").into_response()),
+        Err(e) => {
+            error!("Failed to load replay of battle {}: {}", battle_id, e);
+            Err((axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to load replay").into_response())
+        }
+    }
+}
+
+// Full replay of a flagged battle
+pub async fn admin_replay_handler(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(battle_id): Path<Uuid>,
+) -> impl IntoResponse {
+    if !is_admin(&state, &headers) {
+        return (axum::http::StatusCode::UNAUTHORIZED, "Admin token required").into_response();
+    }
+    match load_replay(&state, battle_id).await {
+        Ok(replay) => Json(replay).into_response(),
+        Err(response) => response,
+    }
+}
+
+// One turn of a flagged battle, for stepping through it
+pub async fn admin_replay_turn_handler(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path((battle_id, turn_number)): Path<(Uuid, u32)>,
+) -> impl IntoResponse {
+    if !is_admin(&state, &headers) {
+        return (axum::http::StatusCode::UNAUTHORIZED, "Admin token required").into_response();
+    }
+    let replay = match load_replay(&state, battle_id).await {
+        Ok(replay) => replay,
+        Err(response) => return response,
+    };
+    match replay.turns.into_iter().find(|turn| turn.turn_number == turn_number) {
+        Some(turn) => Json(turn).into_response(),
+        None => (axum::http::StatusCode::NOT_FOUND, "Turn not found").into_response(),
+    }
+}
+
+// Names of the stored lobby snapshots
+pub async fn admin_snapshots_handler(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+) -> impl IntoResponse {
+    if !is_admin(&state, &headers) {
+        return (axum::http::StatusCode::UNAUTHORIZED, "Admin token required").into_response();
+    }
+    let snapshot_manager = match state.lobby_snapshot_manager.as_ref() {
+        Some(snapshot_manager) => snapshot_manager,
+        None => return (axum::http::StatusCode::SERVICE_UNAVAILABLE, "Lobby snapshots unavailable").into_response(),
+    };
+    match snapshot_manager.list().await {
+        Ok(names) => Json(names).into_response(),
+        Err(e) => {
+            error!("Failed to list lobby snapshots: {}", e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to list lobby snapshots").into_response()
+        }
+    }
+}
+
+// Snapshot a lobby's monsters, berry plots and weather under a name
+pub async fn admin_take_snapshot_handler(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path((lobby_id, name)): Path<(String, String)>,
+) -> impl IntoResponse {
+    if !is_admin(&state, &headers) {
+        return (axum::http::StatusCode::UNAUTHORIZED, "Admin token required").into_response();
+    }
+    let snapshot_manager = match state.lobby_snapshot_manager.as_ref() {
+        Some(snapshot_manager) => snapshot_manager,
+        None => return (axum::http::StatusCode::SERVICE_UNAVAILABLE, "Lobby snapshots unavailable").into_response(),
+    };
+    let Some(lobby) = get_lobby(&state, &lobby_id) else {
+        return (axum::http::StatusCode::NOT_FOUND, "Lobby not found").into_response();
+    };
+    let snapshot = LobbySnapshotManager::capture(&lobby, &name).await;
+    match snapshot_manager.save(&snapshot).await {
+        Ok(()) => {
+            info!("Took snapshot {} of lobby {}", name, lobby_id);
+            record_admin_command(&state, "take_snapshot", format!("{}/{}", lobby_id, name), None).await;
+            Json(snapshot).into_response()
+        }
+        Err(e) => {
+            error!("Failed to save snapshot {} of lobby {}: {}", name, lobby_id, e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to save lobby snapshot").into_response()
+        }
+    }
+}
+
+// Download a stored snapshot, e.g. to keep it in a file
+pub async fn admin_snapshot_handler(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    if !is_admin(&state, &headers) {
+        return (axum::http::StatusCode::UNAUTHORIZED, "Admin token required").into_response();
+    }
+    let snapshot_manager = match state.lobby_snapshot_manager.as_ref() {
+        Some(snapshot_manager) => snapshot_manager,
+        None => return (axum::http::StatusCode::SERVICE_UNAVAILABLE, "Lobby snapshots unavailable").into_response(),
+    };
+    match snapshot_manager.get(&name).await {
+        Ok(Some(snapshot)) => Json(snapshot).into_response(),
+        Ok(None) => (axum::http::StatusCode::NOT_FOUND, "Snapshot not found").into_response(),
+        Err(e) => {
+            error!("Failed to load lobby snapshot {}: {}", name, e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to load lobby snapshot").into_response()
+        }
+    }
+}
+
+// Upload a snapshot, e.g. one kept in a file or taken on another server
+pub async fn admin_upload_snapshot_handler(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(name): Path<String>,
+    Json(mut snapshot): Json<LobbySnapshot>,
+) -> impl IntoResponse {
+    if !is_admin(&state, &headers) {
+        return (axum::http::StatusCode::UNAUTHORIZED, "Admin token required").into_response();
+    }
+    let snapshot_manager = match state.lobby_snapshot_manager.as_ref() {
+        Some(snapshot_manager) => snapshot_manager,
+        None => return (axum::http::StatusCode::SERVICE_UNAVAILABLE, "Lobby snapshots unavailable").into_response(),
+    };
+    snapshot.name = name;
+    match snapshot_manager.save(&snapshot).await {
+        Ok(()) => {
+            record_admin_command(&state, "upload_snapshot", snapshot.name.clone(), None).await;
+            axum::http::StatusCode::NO_CONTENT.into_response()
+        }
+        Err(e) => {
+            error!("Failed to save uploaded lobby snapshot {}: {}", snapshot.name, e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to save lobby snapshot").into_response()
+        }
+    }
+}
+
+// Replace a lobby's monsters, berry plots and weather with a stored snapshot
+pub async fn admin_restore_snapshot_handler(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path((lobby_id, name)): Path<(String, String)>,
+) -> impl IntoResponse {
+    if !is_admin(&state, &headers) {
+        return (axum::http::StatusCode::UNAUTHORIZED, "Admin token required").into_response();
+    }
+    let (Some(snapshot_manager), Some(berry_manager)) = (state.lobby_snapshot_manager.as_ref(), state.berry_manager.as_ref()) else {
+        return (axum::http::StatusCode::SERVICE_UNAVAILABLE, "Lobby snapshots unavailable").into_response();
+    };
+    let Some(lobby) = get_lobby(&state, &lobby_id) else {
+        return (axum::http::StatusCode::NOT_FOUND, "Lobby not found").into_response();
+    };
+    let snapshot = match snapshot_manager.get(&name).await {
+        Ok(Some(snapshot)) => snapshot,
+        Ok(None) => return (axum::http::StatusCode::NOT_FOUND, "Snapshot not found").into_response(),
+        Err(e) => {
+            error!("Failed to load lobby snapshot {}: {}", name, e);
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to load lobby snapshot").into_response();
+        }
+    };
+    match LobbySnapshotManager::restore(&lobby, snapshot, berry_manager).await {
+        Ok(summary) => {
+            record_admin_command(&state, "restore_snapshot", format!("{}/{}", lobby_id, name), serde_json::to_value(&summary).ok()).await;
+            Json(summary).into_response()
+        }
+        Err(e) => {
+            warn!("Failed to restore snapshot {} into lobby {}: {}", name, lobby_id, e);
+            (axum::http::StatusCode::BAD_REQUEST, e).into_response()
+        }
+    }
+}
+
+// Add an admin API call to the audit log, with what it changed when known
+async fn record_admin_command(state: &AppState, command: &str, target: String, after: Option<serde_json::Value>) {
+    if let Some(audit_log) = state.audit_log.as_ref() {
+        let mut entry = AuditEntry::new(ADMIN_ACTOR, AuditAction::AdminCommand { command: command.to_string(), target });
+        entry.after = after;
+        audit_log.record(entry).await;
+    }
+}
+
+// GET /admin/audit?actor=&action=&since=&limit=: audit entries, newest first
+pub async fn admin_audit_handler(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Query(query): Query<AuditQuery>,
+) -> impl IntoResponse {
+    if !is_admin(&state, &headers) {
+        return (axum::http::StatusCode::UNAUTHORIZED, "Admin token required").into_response();
+    }
+    let Some(audit_log) = state.audit_log.as_ref() else {
+        return (axum::http::StatusCode::SERVICE_UNAVAILABLE, "Audit log unavailable").into_response();
+    };
+    match audit_log.query(&query).await {
+        Ok(entries) => Json(entries).into_response(),
+        Err(e) => {
+            error!("Failed to query audit log: {}", e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to query audit log").into_response()
+        }
+    }
+}
+
+// Recent events of a lobby, oldest first
+pub async fn admin_lobby_events_handler(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(lobby_id): Path<String>,
+) -> impl IntoResponse {
+    if !is_admin(&state, &headers) {
+        return (axum::http::StatusCode::UNAUTHORIZED, "Admin token required").into_response();
+    }
+    match get_lobby(&state, &lobby_id) {
+        Some(lobby) => Json(lobby.events.recent()).into_response(),
+        None => (axum::http::StatusCode::NOT_FOUND, "Lobby not found").into_response(),
+    }
+}
+
+// Chance of each spawn point in a lobby being picked per spawner cycle, for tuning spawn_density
+pub async fn admin_spawn_odds_handler(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(lobby_id): Path<String>,
+) -> impl IntoResponse {
+    if !is_admin(&state, &headers) {
+        return (axum::http::StatusCode::UNAUTHORIZED, "Admin token required").into_response();
+    }
+    match get_lobby(&state, &lobby_id) {
+        Some(lobby) => {
+            let spawner_config = crate::game_loop::monster_spawner::SpawnerConfig::from_config(&state.config);
+            Json(crate::game_loop::monster_spawner::spawn_point_odds(&lobby, &spawner_config)).into_response()
+        }
+        None => (axum::http::StatusCode::NOT_FOUND, "Lobby not found").into_response(),
+    }
+}
+
+// Body of an admin request to start a lobby-wide event buff
+#[derive(Deserialize)]
+pub struct StartLobbyBuffRequest {
+    pub id: String,
+    pub name: String,
+    pub spawn_rate_multiplier: f32,
+    pub duration_secs: u64,
+}
+
+// Start (or extend) an event buff that raises spawn rates in a lobby for a while
+pub async fn admin_start_lobby_buff_handler(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(lobby_id): Path<String>,
+    Json(request): Json<StartLobbyBuffRequest>,
+) -> impl IntoResponse {
+    if !is_admin(&state, &headers) {
+        return (axum::http::StatusCode::UNAUTHORIZED, "Admin token required").into_response();
+    }
+    let Some(lobby) = get_lobby(&state, &lobby_id) else {
+        return (axum::http::StatusCode::NOT_FOUND, "Lobby not found").into_response();
+    };
+    if request.spawn_rate_multiplier <= 0.0 || request.duration_secs == 0 {
+        return (axum::http::StatusCode::BAD_REQUEST, "Buff needs a positive multiplier and duration").into_response();
+    }
+    let buff = lobby.buffs.activate(&request.id, &request.name, request.spawn_rate_multiplier, request.duration_secs, None);
+    lobby.broadcast_buffs();
+    record_admin_command(&state, "start_lobby_buff", format!("{}/{}", lobby_id, request.id), serde_json::to_value(&buff).ok()).await;
+    Json(buff).into_response()
+}
+
+// Health check endpoint
+// Build and effective configuration info, for monitoring and debugging deployments
+#[derive(serde::Serialize)]
+pub struct HealthInfo {
+    status: &'static str,
+    name: &'static str,
+    version: &'static str,
+    lobbies: usize,
+    players_online: usize,
+    ticks: crate::config::TickConfig,
+    combat_repairs: Option<crate::combat::manager::CombatRepairMetrics>,
+    queued_player_writes: usize,
+    // Lobby ID → 95th percentile round trip of its players, for lobbies with measurements
+    latency_p95_ms: std::collections::HashMap<String, u32>,
+}
+
+pub async fn health_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(HealthInfo {
+        status: if state.persistence.is_degraded() { "degraded" } else { "ok" },
+        name: env!("CARGO_PKG_NAME"),
+        version: env!("CARGO_PKG_VERSION"),
+        lobbies: state.lobbies.len(),
+        players_online: state.lobbies.iter().map(|lobby| lobby.player_connections.len()).sum(),
+        ticks: state.config.ticks.clone(),
+        combat_repairs: state.battle_manager.as_ref().map(|battle_manager| battle_manager.combat_repair_metrics()),
+        queued_player_writes: state.persistence.pending_writes(),
+        latency_p95_ms: state.lobbies.iter()
+            .filter_map(|lobby| lobby.latency.p95_ms().map(|p95| (lobby.id.clone(), p95)))
+            .collect(),
+    })
+}
+
+// Connection attempts made while a player joins before telling them the service is unavailable
+const JOIN_REDIS_ATTEMPTS: u32 = 3;
+
+// Messages a connection may send per window before it is closed as rate limited
+const MAX_MESSAGES_PER_WINDOW: u32 = 60;
+const MESSAGE_WINDOW: std::time::Duration = std::time::Duration::from_secs(1);
+
+// Unparseable messages in a row before a connection is closed with a protocol error
+const MAX_INVALID_MESSAGES: u32 = 10;
+
+// Tell a joining player their save cannot be reached right now, then close the socket
+async fn reject_unavailable(sender: &Arc<Mutex<SplitSink<WebSocket, Message>>>) {
+    let msg = ServerMessage::ServiceUnavailable {
+        message: "Game data is temporarily unavailable. Please try again in a moment.".to_string(),
+    };
+    if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&msg).unwrap()))).await {
+        tracing::error!("Failed to send service unavailable message: {}", e);
+    }
+    close_connection(sender, CloseReason::ServiceUnavailable).await;
+}
+
+// Handle WebSocket connection for a lobby
+pub async fn handle_lobby_socket(socket: WebSocket, state: Arc<AppState>, lobby: Arc<Lobby>, username: String) {
+    let (sender, mut receiver) = socket.split();
+    let sender = Arc::new(tokio::sync::Mutex::new(sender));
+    
+    // Clone state for usage throughout this function
+    let state_for_tasks = state.clone();
+    let state_for_disconnect = state.clone();
+
+    // Wait for join message with session token
+    let (session_token, protocol_version, capabilities) = if let Some(Ok(Message::Text(text))) = receiver.next().await {
+        match serde_json::from_str::<ClientMessage>(&text) {
+            Ok(ClientMessage::Join { session_token, protocol_version, capabilities }) => {
+                (session_token, protocol_version, negotiate_capabilities(&capabilities))
+            },
+            _ => {
+                tracing::error!("First message must be a join message with session token");
+                close_connection(&sender, CloseReason::AuthFailed).await;
+                return;
+            }
+        }
+    } else {
+        tracing::error!("Failed to receive join message");
+        close_connection(&sender, CloseReason::AuthFailed).await;
+        return;
+    };
+
+    // Turn away clients too old to understand this server
+    if let Some(version) = protocol_version.filter(|version| *version < MIN_PROTOCOL_VERSION) {
+        tracing::warn!("Rejected client with protocol version {} (minimum {})", version, MIN_PROTOCOL_VERSION);
+        let msg = ServerMessage::Error {
+            message: format!("Protocol version {} is no longer supported, please update your client", version),
+        };
+        if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&msg).unwrap()))).await {
+            tracing::error!("Failed to send protocol version error: {}", e);
+        }
+        close_connection(&sender, CloseReason::OutdatedClient).await;
+        return;
+    }
+
+    // Connect to Redis; without it the player's save cannot be loaded, so tell them instead of dropping them
+    let mut redis_conn = match redis_manager::connect_with_retry(&state_for_tasks.redis, JOIN_REDIS_ATTEMPTS).await {
+        Ok(redis_conn) => redis_conn,
+        Err(e) => {
+            tracing::error!("Redis unavailable while player joined lobby {}: {}", lobby.id, e);
+            reject_unavailable(&sender).await;
+            return;
+        }
+    };
+    
+    // Retrieve or create player id via Redis
+    let player_id = match redis_manager::get_player_id(&mut redis_conn, &session_token).await {
+        Ok(id) => {
+            tracing::info!("Found existing player ID {} for session {}", id, session_token);
+            id
+        },
+        Err(e) if redis_manager::is_outage(&e) => {
+            tracing::error!("Redis unavailable while looking up session {}: {}", session_token, e);
+            reject_unavailable(&sender).await;
+            return;
+        }
+        Err(_) => {
+            // Create a new player ID
+            let new_id = Uuid::new_v4().to_string();
+            tracing::info!("Created new player ID {} for session {}", new_id, session_token);
+            new_id
+        }
+    };
+    tracing::info!("Player connected to lobby {}: {} (session: {})", lobby.id, player_id, session_token);
+
+    // Store session-token mapping in Redis with permanent persistence
+    // We use a constant 10-year timeout (function will handle this)
+    if let Err(e) = redis_manager::store_session(
+        &mut redis_conn, 
+        &session_token, 
+        &player_id,
+        0 // The actual value doesn't matter, the function uses a long-term constant
+    ).await {
+        tracing::error!("Failed to store session in Redis: {}", e);
+        reject_unavailable(&sender).await;
+        return;
+    }
+
+    // Remember this lobby so the player can be routed back here later
+    if let Err(e) = redis_manager::store_last_lobby(&mut redis_conn, &player_id, &lobby.id).await {
+        tracing::warn!("Failed to store last lobby for player {}: {}", player_id, e);
+    }
+
+    // Create a broadcast receiver for lobby events
+    let mut rx = lobby.tx.subscribe();
+
+    // Retrieve or initialize player state from Redis
+    let player_state = match redis_manager::get_player_state(&mut redis_conn, &lobby.id, &player_id).await {
+        Ok(state_json) => match serde_json::from_str::<PlayerState>(&state_json) {
+            // Nobody is AFK right as they join
+            Ok(state) => PlayerState { afk: false, ..state },
+            Err(e) => {
+                tracing::warn!("Error deserializing player state: {}", e);
+                close_connection(&sender, CloseReason::ServerError).await;
+                return;
+            }
+        },
+        Err(e) if redis_manager::is_outage(&e) => {
+            tracing::error!("Redis unavailable while loading player {}: {}", player_id, e);
+            reject_unavailable(&sender).await;
+            return;
+        }
+        Err(_) => {
+            // Create a new player state with random position
+            let new_state = PlayerState {
+                id: player_id.clone(),
+                username: username.clone(),
+                x: 5 + rand::random::<u32>() % 16,
+                y: 5,
+                direction: "down".to_string(),
+                in_combat: false,
+                afk: false,
+            };
+            
+            // Store the new state in Redis
+            let state_json = serde_json::to_string(&new_state).unwrap();
+            if let Err(e) = redis_manager::store_player_state(
+                &mut redis_conn,
+                &lobby.id,
+                &player_id,
+                &state_json,
+            ).await {
+                tracing::error!("Failed to store new player state in Redis: {}", e);
+            }
+            
+            new_state
+        }
+    };
+    tracing::info!("Player state in lobby {}: {:?}", lobby.id, player_state);
+
+    // Add player to lobby state
+    lobby.player_positions.insert(player_id.clone(), player_state.clone());
+    lobby.player_last_active.insert(player_id.clone(), Instant::now());
+    lobby.player_last_input.insert(player_id.clone(), Instant::now());
+    state.player_index.insert(&player_id, &player_state.username, &lobby.id);
+    lobby.events.record(LobbyEventKind::PlayerJoined { player_id: player_id.clone(), username: player_state.username.clone() });
+    
+    // Remember the connection's protocol before anything can be sent to it
+    let client_protocol = ClientProtocol { version: protocol_version, capabilities };
+    lobby.player_protocols.insert(player_id.clone(), client_protocol.clone());
+
+    // Store the WebSocket sender in the lobby's player_connections map,
+    // closing any older connection of the same player so only the newest one stays in control
+    if let Some(previous) = lobby.player_connections.insert(player_id.clone(), sender.clone()) {
+        tracing::info!("Player {} connected again, closing their previous connection", player_id);
+        close_connection(&previous, CloseReason::Kicked).await;
+    }
+    let connection_guard = LobbyConnectionGuard::new(state.clone(), lobby.clone(), player_id.clone(), sender.clone());
+
+    // Translate names in everything sent to this player from here on
+    if let Some(player_settings_manager) = &state_for_tasks.player_settings_manager {
+        if let Ok(settings) = player_settings_manager.get_settings(&player_id).await {
+            lobby.set_player_locale(&player_id, settings.locale);
+        }
+    }
+
+    // Enable the negotiated capabilities for this connection
+    if client_protocol.has(Capability::VerboseBattleEvents) {
+        if let Some(battle_manager) = &state_for_tasks.battle_manager {
+            battle_manager.set_damage_breakdown(&player_id, true);
+        }
+    }
+
+    // Send welcome message; versioned clients also learn which of their capabilities are enabled
+    let welcome_msg = ServerMessage::Welcome { 
+        id: player_id.clone(), 
+        username: player_state.username.clone(),
+        x: player_state.x, 
+        y: player_state.y,
+        protocol_version: client_protocol.version.map(|_| PROTOCOL_VERSION),
+        capabilities: client_protocol.version.map(|_| client_protocol.capabilities),
+    };
+    if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&welcome_msg).unwrap()))).await {
+        tracing::error!("Failed to send welcome message: {}", e);
+        return;
+    }
+    if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&state.clock.time_sync()).unwrap()))).await {
+        tracing::error!("Failed to send time sync message: {}", e);
+        return;
+    }
+
+    // Send current players in lobby
+    let players = lobby.player_positions.iter().map(|entry| entry.value().clone()).collect::<Vec<_>>();
+    let players_msg = ServerMessage::Players { players };
+    if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&players_msg).unwrap()))).await {
+        tracing::error!("Failed to send players message: {}", e);
+        return;
+    }
+
+    // Send current monsters to the player if monster manager exists
+    let monsters = MonsterManager::get_monsters_for_lobby(&lobby);
+    let display_monsters = monsters.iter()
+        .filter_map(|m| {
+            if let Ok(monster) = m.try_lock() {
+                Some(monster.to_display())
+            } else {
+                None
+            }
+        })
+        .collect();
+    let monsters_msg = ServerMessage::Monsters { monsters: display_monsters };
+    if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&monsters_msg).unwrap()))).await {
+        tracing::error!("Failed to send monsters message: {}", e);
+        return;
+    }
+
+    // Send player's Pokémon collection
+    if let Some(pokemon_collection_manager) = &state_for_tasks.pokemon_collection_manager {
+        match pokemon_collection_manager.get_active_pokemons(&player_id).await {
+            Ok(pokemons) => { 
+                // Convert Vec<Pokemon> to Vec<DisplayPokemon>
+                let display_pokemons: Vec<DisplayPokemon> = pokemons
+                    .iter()
+                    .map(|p| pokemon_collection_manager.pokemon_to_display_pokemon(p))
+                    .collect();
+
+                // Use the converted Vec<DisplayPokemon>
+                let active_pokemons_msg: ServerMessage = ServerMessage::ActivePokemons { pokemons: display_pokemons };
+                    
+                info!("Sending pokemon collection message to player {}: {:?}", player_id, active_pokemons_msg);
+                if let Err(e) = lobby.send_to_player(&player_id, &active_pokemons_msg).await {
+                    tracing::error!("Failed to send pokemon collection message: {}", e);
+                }
+            },
+            Err(e) => {
+                tracing::error!("Failed to fetch pokemon collection for player {}: {}", player_id, e);
+            }
+        }
+    }
+
+    // Send player's current balance
+    if let Some(currency_manager) = &state_for_tasks.currency_manager {
+        match currency_manager.get_balance(&player_id).await {
+            Ok(balance) => {
+                let balance_msg = ServerMessage::BalanceUpdate { balance, delta: 0, reason: None };
+                if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&balance_msg).unwrap()))).await {
+                    tracing::error!("Failed to send balance message: {}", e);
+                }
+            },
+            Err(e) => {
+                tracing::error!("Failed to fetch balance for player {}: {}", player_id, e);
+            }
+        }
+    }
+
+    // Send player's inventory
+    if let Some(inventory_manager) = &state_for_tasks.inventory_manager {
+        match inventory_manager.get_inventory(&player_id).await {
+            Ok(items) => {
+                let inventory_msg = ServerMessage::Inventory { items };
+                if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&inventory_msg).unwrap()))).await {
+                    tracing::error!("Failed to send inventory message: {}", e);
+                }
+            },
+            Err(e) => {
+                tracing::error!("Failed to fetch inventory for player {}: {}", player_id, e);
+            }
+        }
+        match inventory_manager.get_equipped_crystal(&player_id).await {
+            Ok(item_id) => {
+                let crystal_msg = ServerMessage::EquippedCrystal { item_id };
+                if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&crystal_msg).unwrap()))).await {
+                    tracing::error!("Failed to send equipped crystal message: {}", e);
+                }
+            },
+            Err(e) => {
+                tracing::error!("Failed to fetch equipped crystal for player {}: {}", player_id, e);
+            }
+        }
+    }
+
+    // Pick the tutorial up where the player left it
+    let step = tutorial_step(&state_for_tasks, &player_id).await;
+    if step != TutorialStep::Completed {
+        let tutorial_msg = ServerMessage::TutorialStep { step, prompt: step.prompt().to_string() };
+        if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&tutorial_msg).unwrap()))).await {
+            tracing::error!("Failed to send tutorial step message: {}", e);
+        }
+    }
+
+    // Send berry plots in the lobby
+    let plots = lobby.berry_plots.iter().map(|entry| entry.value().clone()).collect::<Vec<_>>();
+    let plots_msg = ServerMessage::BerryPlots { plots };
+    if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&plots_msg).unwrap()))).await {
+        tracing::error!("Failed to send berry plots message: {}", e);
+    }
+
+    // Send the player's block list, which also loads it for blocking checks
+    if let Some(block_list_manager) = &state_for_tasks.block_list_manager {
+        match block_list_manager.blocked_players(&player_id).await {
+            Ok(blocked) => {
+                let block_list_msg = ServerMessage::BlockList { player_ids: blocked.into_iter().collect() };
+                if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&block_list_msg).unwrap()))).await {
+                    tracing::error!("Failed to send block list message: {}", e);
+                }
+            },
+            Err(e) => {
+                tracing::error!("Failed to fetch block list for player {}: {}", player_id, e);
+            }
+        }
+    }
+
+    // Send the player's friend list, which also caches it for friendship checks
+    if let Some(friend_list_manager) = &state_for_tasks.friend_list_manager {
+        match friend_list_manager.friend_list(&player_id).await {
+            Ok(FriendList { friends, pending }) => {
+                let friend_list_msg = ServerMessage::FriendList { friends, pending };
+                if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&friend_list_msg).unwrap()))).await {
+                    tracing::error!("Failed to send friend list message: {}", e);
+                }
+            },
+            Err(e) => {
+                tracing::error!("Failed to fetch friend list for player {}: {}", player_id, e);
+            }
+        }
+    }
+
+    // Send the landmarks this player can fast travel to
+    if let Some(fast_travel_manager) = &state_for_tasks.fast_travel_manager {
+        match fast_travel_manager.visited_landmarks(&player_id).await {
+            Ok(visited) => {
+                let landmarks = lobby.monster_manager.map_data.landmarks.values()
+                    .filter(|landmark| visited.contains(&landmark.id))
+                    .cloned()
+                    .collect();
+                let landmarks_msg = ServerMessage::VisitedLandmarks { landmarks };
+                if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&landmarks_msg).unwrap()))).await {
+                    tracing::error!("Failed to send visited landmarks message: {}", e);
+                }
+            },
+            Err(e) => {
+                tracing::error!("Failed to fetch visited landmarks for player {}: {}", player_id, e);
+            }
+        }
+    }
+
+    // Send the gates this player has cleared on the lobby's map
+    if let Some(gate_manager) = &state_for_tasks.gate_manager {
+        match gate_manager.load_cleared_gates(&lobby, &player_id).await {
+            Ok(cleared) => {
+                let mut gate_ids: Vec<String> = cleared.into_iter().collect();
+                gate_ids.sort();
+                let gates_msg = ServerMessage::ClearedGates { gate_ids };
+                if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&gates_msg).unwrap()))).await {
+                    tracing::error!("Failed to send cleared gates message: {}", e);
+                }
+            },
+            Err(e) => {
+                tracing::error!("Failed to fetch cleared gates for player {}: {}", player_id, e);
+            }
+        }
+    }
+
+    // Send the gym leaders on the lobby's map and the badges this player holds
+    if let Some(battle_manager) = &state_for_tasks.battle_manager {
+        let gyms = battle_manager.gyms().on_map(&lobby.map_id).map(GymLeaderView::from).collect();
+        let gyms_msg = ServerMessage::GymLeaders { gyms };
+        if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&gyms_msg).unwrap()))).await {
+            tracing::error!("Failed to send gym leaders message: {}", e);
+        }
+    }
+    if let Some(badge_manager) = &state_for_tasks.badge_manager {
+        match badge_manager.badges(&player_id).await {
+            Ok(badges) => {
+                let level_cap = badge_manager.obedience_level_cap(badges.len());
+                if let Some(battle_manager) = &state_for_tasks.battle_manager {
+                    battle_manager.set_obedience_level_cap(&player_id, Some(level_cap));
+                }
+                let badges_msg = ServerMessage::Badges { obedience_level_cap: level_cap, badges };
+                if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&badges_msg).unwrap()))).await {
+                    tracing::error!("Failed to send badges message: {}", e);
+                }
+            },
+            Err(e) => {
+                tracing::error!("Failed to fetch badges for player {}: {}", player_id, e);
+            }
+        }
+    }
+
+    // Send current overworld weather
+    let weather_msg = ServerMessage::WeatherChanged { weather: lobby.weather() };
+    if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&weather_msg).unwrap()))).await {
+        tracing::error!("Failed to send weather message: {}", e);
+    }
+
+    // Send items lying on the map
+    let pickups_msg = ServerMessage::ItemPickups { pickups: lobby.item_pickups.visible(&lobby) };
+    if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&pickups_msg).unwrap()))).await {
+        tracing::error!("Failed to send item pickups message: {}", e);
+    }
+
+    // Send incense and event buffs running in the lobby
+    let buffs = lobby.buffs.active();
+    if !buffs.is_empty() {
+        let buffs_msg = ServerMessage::LobbyBuffs { buffs };
+        if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&buffs_msg).unwrap()))).await {
+            tracing::error!("Failed to send lobby buffs message: {}", e);
+        }
+    }
+
+    // Send running auctions and any auction results the player missed while offline
+    if let Some(auction_manager) = &state_for_tasks.auction_manager {
+        let mut auction_msgs = vec![ServerMessage::Auctions { auctions: auction_manager.active_auctions() }];
+        match auction_manager.take_notifications(&player_id).await {
+            Ok(notifications) => auction_msgs.extend(notifications),
+            Err(e) => tracing::error!("Failed to fetch auction notifications for player {}: {}", player_id, e),
+        }
+        for auction_msg in auction_msgs {
+            if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&auction_msg).unwrap()))).await {
+                tracing::error!("Failed to send auction message: {}", e);
+            }
+        }
+    }
+
+    // Deliver mail that arrived while the player was away
+    if let Some(mail_manager) = &state_for_tasks.mail_manager {
+        match mail_manager.get_mailbox(&player_id).await {
+            Ok(mails) => {
+                let unread: Vec<_> = mails.into_iter().filter(|mail| !mail.read).collect();
+                let unread_count = unread.len();
+                for mail in unread {
+                    let mail_msg = ServerMessage::MailReceived { mail, unread_count };
+                    if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&mail_msg).unwrap()))).await {
+                        tracing::error!("Failed to send mail message: {}", e);
+                    }
+                }
+            },
+            Err(e) => {
+                tracing::error!("Failed to fetch mailbox for player {}: {}", player_id, e);
+            }
+        }
+    }
+
+    // Send the player's saved settings and apply the ones the battle system needs
+    if let Some(player_settings_manager) = &state_for_tasks.player_settings_manager {
+        match player_settings_manager.get_settings(&player_id).await {
+            Ok(settings) => {
+                if let Some(battle_manager) = &state_for_tasks.battle_manager {
+                    battle_manager.set_effectiveness_hints(&player_id, settings.effectiveness_hints);
+                }
+                let settings_msg = ServerMessage::PlayerSettings { settings };
+                if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&settings_msg).unwrap()))).await {
+                    tracing::error!("Failed to send settings message: {}", e);
+                }
+            },
+            Err(e) => {
+                tracing::error!("Failed to load settings for player {}: {}", player_id, e);
+            }
+        }
+    }
+
+    // Send a digest of the notifications that arrived while the player was offline
+    if let Some(notification_manager) = &state_for_tasks.notification_manager {
+        match notification_manager.take_digest(&player_id).await {
+            Ok(notifications) if !notifications.is_empty() => {
+                let digest_msg = ServerMessage::NotificationDigest { notifications };
+                if let Err(e) = sender.lock().await.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&digest_msg).unwrap()))).await {
+                    tracing::error!("Failed to send notification digest: {}", e);
+                }
+            },
+            Ok(_) => {}
+            Err(e) => {
+                tracing::error!("Failed to fetch notification digest for player {}: {}", player_id, e);
+            }
+        }
+    }
+
+    // Notify others in lobby about the new player
+    let new_player_msg = ServerMessage::PlayerJoined { player: player_state };
+    let _ = lobby.tx.send(serde_json::to_string(&new_player_msg).unwrap());
+
+    // Rejoin a PvP battle that is still waiting out this player's disconnect grace period
+    if let Some(battle_manager) = state_for_tasks.battle_manager.as_ref() {
+        if let Some(battle_id) = battle_manager.resume_pvp_battle(&player_id, &lobby).await {
+            info!("Player {} rejoined PvP battle {}", player_id, battle_id);
+        }
+    }
+
+    // Clone references for tasks
+    let player_id_for_receiver = player_id.clone();
+    let lobby_for_receiver = lobby.clone();
+    let sender_for_receiver = sender.clone();
+    let player_id_for_forward = player_id.clone();
+    let lobby_for_forward = lobby.clone();
+
+    // Handle incoming messages from the player
+    let mut player_task = tokio::spawn(async move {
+        let mut window_start = Instant::now();
+        let mut window_messages = 0;
+        let mut invalid_messages = 0;
+        while let Some(result) = receiver.next().await {
+            let msg = match result {
+                Ok(msg) => msg,
+                Err(e) => {
+                    if is_message_too_large(e) {
+                        warn!("Player {} sent a message over {} bytes, closing connection", player_id_for_receiver, state_for_tasks.config.server.max_message_bytes);
+                        close_connection(&sender_for_receiver, CloseReason::MessageTooLarge).await;
+                    }
+                    break;
+                }
+            };
+            if let Message::Text(text) = msg {
+                // Close connections flooding the server with messages
+                if window_start.elapsed() >= MESSAGE_WINDOW {
+                    window_start = Instant::now();
+                    window_messages = 0;
+                }
+                window_messages += 1;
+                if window_messages > MAX_MESSAGES_PER_WINDOW {
+                    warn!("Player {} exceeded {} messages per second, closing connection", player_id_for_receiver, MAX_MESSAGES_PER_WINDOW);
+                    close_connection(&sender_for_receiver, CloseReason::Ratelimited).await;
+                    break;
+                }
+
+                // Update last active timestamp in lobby
+                lobby_for_receiver.player_last_active.insert(player_id_for_receiver.clone(), Instant::now());
+                info!("Received message: {}", text);
+                let client_message = serde_json::from_str::<ClientMessage>(&text);
+                if client_message.is_ok() {
+                    invalid_messages = 0;
+                }
+                // Pings are sent by the client on its own, anything else means the player is at the keyboard
+                if matches!(client_message, Ok(ref message) if !matches!(message, ClientMessage::Ping { .. }))
+                    && lobby_for_receiver.record_input(&player_id_for_receiver)
+                {
+                    lobby_for_receiver.broadcast_afk(&player_id_for_receiver, false);
+                }
+                match client_message {
+                    Ok(ClientMessage::Join { .. }) => {
+                    },
                     Ok(ClientMessage::Move { x, y, direction }) => {
                         // Get current player state
                         let current_state = match lobby_for_receiver.player_positions.get(&player_id_for_receiver) {
@@ -266,317 +1413,1277 @@ pub async fn handle_lobby_socket(socket: WebSocket, state: Arc<AppState>, lobby:
                             None => continue,
                         };
 
-                        // Validate movement with the movement manager
-                        let movement_valid = state_for_tasks.player_movement_manager.as_ref().unwrap()
-                            .validate_movement(&player_id_for_receiver, &current_state, x, y);
+                        // A wild monster on the target tile blocks the move, or starts an encounter with it
+                        let blocking_monster = match lobby_for_receiver.monster_collision {
+                            MonsterCollision::Off => None,
+                            _ if (x, y) == (current_state.x, current_state.y) => None,
+                            _ => lobby_for_receiver.occupancy.monster_at((x, y)),
+                        };
+                        if let Some(monster_id) = &blocking_monster {
+                            if lobby_for_receiver.monster_collision == MonsterCollision::Interact && !current_state.in_combat {
+                                handle_player_interaction(&state_for_tasks, &lobby_for_receiver.id, &player_id_for_receiver, &Some(monster_id.clone())).await;
+                            }
+                        }
+
+                        // Crossing water takes the surf key item
+                        let can_surf = match (lobby_for_receiver.monster_manager.map_data.terrain_at(x, y), state_for_tasks.inventory_manager.as_ref()) {
+                            (TerrainType::Water, Some(inventory_manager)) => inventory_manager
+                                .get_item_count(&player_id_for_receiver, SURF_ITEM_ID)
+                                .await
+                                .is_ok_and(|count| count > 0),
+                            _ => false,
+                        };
+
+                        // Validate movement with the movement manager; ledges and ice can carry the player past the requested tile
+                        let destination = match blocking_monster {
+                            Some(_) => None,
+                            None => state_for_tasks.player_movement_manager.as_ref().unwrap()
+                                .validate_movement(&player_id_for_receiver, &current_state, x, y, &lobby_for_receiver, can_surf),
+                        };
+
+                        // Only process movement if it's valid
+                        if let Some((x, y)) = destination {
+                            let updated_player = PlayerState {
+                                id: player_id_for_receiver.clone(),
+                                username: current_state.username.clone(),
+                                x,
+                                y,
+                                direction,
+                                in_combat: current_state.in_combat,
+                                afk: false,
+                            };
+                            
+                            // Update player state in Redis, or queue it while Redis is down
+                            let player_json = serde_json::to_string(&updated_player).unwrap();
+                            if state_for_tasks.persistence.store_player_state(
+                                &state_for_tasks.redis,
+                                &lobby_for_receiver.id,
+                                &player_id_for_receiver,
+                                &player_json,
+                            ).await {
+                                crate::lobby::broadcast_service_status(&state_for_tasks, false);
+                            }
+                            
+                            // Update player state in lobby
+                            lobby_for_receiver.player_positions.insert(player_id_for_receiver.clone(), updated_player.clone());
+                            
+                            // Register the validated movement in the movement manager
+                            state_for_tasks.player_movement_manager.as_ref().unwrap()
+                                .register_movement(player_id_for_receiver.clone(), updated_player.clone());
+
+                            // Unlock landmarks the player walks up to for fast travel
+                            if let Some(fast_travel_manager) = state_for_tasks.fast_travel_manager.as_ref() {
+                                if let Some(landmark) = fast_travel_manager.visit_nearby_landmark(&lobby_for_receiver, &player_id_for_receiver, x, y).await {
+                                    let visited_msg = ServerMessage::LandmarkVisited { landmark };
+                                    if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &visited_msg).await {
+                                        error!("Failed to send landmark visit to player {}: {}", player_id_for_receiver, e);
+                                    }
+                                }
+                            }
+                        } else {
+                            // Send correction message to the client who tried invalid movement
+                            let correction_msg = ServerMessage::PlayersMoved { 
+                                players: vec![current_state],
+                                timestamp: Utc::now().timestamp_millis() as u64,
+                                tick: state_for_tasks.clock.tick(),
+                            };
+                            
+                            if let Err(e) = sender_for_receiver.lock().await.send(
+                                Message::Text(Utf8Bytes::from(serde_json::to_string(&correction_msg).unwrap()))
+                            ).await {
+                                tracing::error!("Failed to send correction message: {}", e);
+                            }
+                        }                    },
+                    Ok(ClientMessage::Ping { client_time, rtt_ms }) => {
+                        let latency_ms = match rtt_ms {
+                            Some(rtt_ms) if rtt_ms <= MAX_REPORTED_RTT_MS => Some(lobby_for_receiver.latency.record(&player_id_for_receiver, rtt_ms)),
+                            _ => lobby_for_receiver.latency.average_ms(&player_id_for_receiver),
+                        };
+                        let pong_msg = ServerMessage::Pong {
+                            client_time,
+                            server_time: Utc::now().timestamp_millis() as u64,
+                            latency_ms,
+                        };
+                        let mut sender_lock = sender_for_receiver.lock().await;
+                        if let Err(e) = sender_lock.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&pong_msg).unwrap()))).await {
+                            tracing::error!("Failed to send pong message: {}", e);
+                            break;
+                        }
+                    },
+                    Ok(ClientMessage::Interact { monster_id }) => {
+                        handle_player_interaction(&state_for_tasks, &lobby_for_receiver.id, &player_id_for_receiver, &monster_id).await;
+                    },
+                    Ok(ClientMessage::ChooseStarter { starter_id }) => {
+                        let pokemon_collection_manager = state_for_tasks.pokemon_collection_manager.as_ref().unwrap();
+                        let display_pokemon = match pokemon_collection_manager.choose_starting_pokemons(&player_id_for_receiver, starter_id).await {
+                            Ok(StarterChoice::Chosen(pokemon)) => pokemon,
+                            Ok(StarterChoice::AlreadyChosen(pokemon)) => {
+                                let already_chosen_msg = ServerMessage::StarterAlreadyChosen { pokemon };
+                                if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &already_chosen_msg).await {
+                                    tracing::error!("Failed to send starter already chosen message: {}", e);
+                                }
+                                continue;
+                            }
+                            Err(e) => {
+                                tracing::error!("Failed to choose starter pokemon: {}", e);
+                                let error_msg = ServerMessage::Error { message: format!("Failed to choose starter: {}", e) };
+                                if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                    tracing::error!("Failed to send error message to player {}: {}", player_id_for_receiver, send_err);
+                                }
+                                continue;
+                            }
+                        };
+                        let new_pokemon_msg = ServerMessage::NewPokemon {
+                            pokemon: display_pokemon, 
+                            active_index: Some(0),
+                        };
+                        if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &new_pokemon_msg).await {
+                            tracing::error!("Failed to send new pokemon message: {}", e);
+                        }
+                        if let Some(tutorial_manager) = state_for_tasks.tutorial_manager.as_ref() {
+                            tutorial_manager.complete_and_notify(&lobby_for_receiver, &player_id_for_receiver, &[TutorialStep::ChooseStarter]).await;
+                        }
+                    },
+                    Ok(ClientMessage::CombatAction { battle_id, action }) => {
+                        // Get the battle manager
+                        if let Some(battle_manager) = state_for_tasks.battle_manager.as_ref() {
+                            // Handle the action, passing the lobby for message sending
+                            match battle_manager.handle_player_action(&player_id_for_receiver, battle_id, action, &lobby_for_receiver, state_for_tasks.pokemon_collection_manager.as_ref().unwrap()).await {
+                                Ok(_) => {
+                                    info!("Player {} submitted action for battle {} and turn was processed", player_id_for_receiver, battle_id);
+                                },
+                                Err(e) => {
+                                    error!("Failed to handle player action for battle {}: {}", battle_id, e);
+                                    // Send error back to the player
+                                    let error_msg = ServerMessage::Error { message: e };
+                                    if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                        error!("Failed to send error message to player {}: {}", player_id_for_receiver, send_err);
+                                    }
+                                }
+                            }
+                        } else {
+                            error!("Battle manager not found when handling combat action");
+                        }
+                    },
+                    Ok(ClientMessage::ChallengePlayer { target_player_id, rules, use_rental }) => {
+                        info!("Player {} is challenging player {}", player_id_for_receiver, target_player_id);
+
+                        // The rules are stored with the challenge and bind both players, so refuse unplayable ones now
+                        if let Err(reason) = rules.validate() {
+                            let challenge_failed_msg = ServerMessage::ChallengeFailed { reason };
+                            if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &challenge_failed_msg).await {
+                                error!("Failed to send challenge failed message: {}", e);
+                            }
+                            continue;
+                        }
+
+                        // Battling other players unlocks at the end of the tutorial
+                        let reason = if !tutorial_step(&state_for_tasks, &player_id_for_receiver).await.can_challenge() {
+                            Some("Catch your first Pokémon before challenging other players")
+                        } else if !tutorial_step(&state_for_tasks, &target_player_id).await.can_challenge() {
+                            Some("Target player has not finished the tutorial yet")
+                        } else {
+                            None
+                        };
+                        if let Some(reason) = reason {
+                            let challenge_failed_msg = ServerMessage::ChallengeFailed { reason: reason.to_string() };
+                            if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &challenge_failed_msg).await {
+                                error!("Failed to send challenge failed message: {}", e);
+                            }
+                            continue;
+                        }
+
+                        // Respect blocks and the target's privacy settings; players appearing offline look absent
+                        if let Some(reason) = challenge_refusal(&state_for_tasks, &player_id_for_receiver, &target_player_id).await {
+                            let challenge_failed_msg = ServerMessage::ChallengeFailed { reason: reason.to_string() };
+                            if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &challenge_failed_msg).await {
+                                error!("Failed to send challenge failed message: {}", e);
+                            }
+                            continue;
+                        }
+                        
+                        // Verify challenger is not in combat
+                        if let Some(challenger_state) = lobby_for_receiver.player_positions.get(&player_id_for_receiver) {
+                            if challenger_state.value().in_combat {
+                                let challenge_failed_msg = ServerMessage::ChallengeFailed { 
+                                    reason: "You are already in combat".to_string() 
+                                };
+                                if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &challenge_failed_msg).await {
+                                    error!("Failed to send challenge failed message: {}", e);
+                                }
+                                continue;
+                            }
+                            
+                            // Check if player is trying to challenge themselves
+                            if player_id_for_receiver == target_player_id {
+                                let challenge_failed_msg = ServerMessage::ChallengeFailed { 
+                                    reason: "You cannot challenge yourself".to_string() 
+                                };
+                                if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &challenge_failed_msg).await {
+                                    error!("Failed to send challenge failed message: {}", e);
+                                }
+                                continue;
+                            }
+                            
+                            // Verify target player exists and is online
+                            if let Some(target_player_state) = lobby_for_receiver.player_positions.get(&target_player_id) {
+                                // Verify target player is not in combat
+                                if target_player_state.value().in_combat {
+                                    let challenge_failed_msg = ServerMessage::ChallengeFailed { 
+                                        reason: "Target player is already in combat".to_string() 
+                                    };
+                                    if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &challenge_failed_msg).await {
+                                        error!("Failed to send challenge failed message: {}", e);
+                                    }
+                                    continue;
+                                }
+
+                                // AFK players could not answer the challenge anyway
+                                if target_player_state.value().afk {
+                                    let challenge_failed_msg = ServerMessage::ChallengeFailed { 
+                                        reason: "Target player is away".to_string() 
+                                    };
+                                    if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &challenge_failed_msg).await {
+                                        error!("Failed to send challenge failed message: {}", e);
+                                    }
+                                    continue;
+                                }
+                                
+                                // All checks passed, send challenge to target player. It is kept so the
+                                // answer can only accept what was actually offered.
+                                lobby_for_receiver.pending_challenges.insert(
+                                    (player_id_for_receiver.clone(), target_player_id.clone()),
+                                    PendingChallenge { rules, challenger_rental: use_rental },
+                                );
+                                let challenge_received_msg = ServerMessage::ChallengeReceived { 
+                                    challenger_id: player_id_for_receiver.clone(),
+                                    challenger_username: challenger_state.value().username.clone(),
+                                    rules,
+                                    challenger_rental: use_rental,
+                                };
+                                
+                                if let Err(e) = lobby_for_receiver.send_to_player(&target_player_id, &challenge_received_msg).await {
+                                    // Target player might have disconnected
+                                    lobby_for_receiver.pending_challenges.remove(&(player_id_for_receiver.clone(), target_player_id.clone()));
+                                    let challenge_failed_msg = ServerMessage::ChallengeFailed { 
+                                        reason: "Failed to send challenge to target player".to_string() 
+                                    };
+                                    if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &challenge_failed_msg).await {
+                                        error!("Failed to send challenge failed message: {}", send_err);
+                                    }
+                                    error!("Failed to send challenge to target player: {}", e);
+                                }
+                            } else {
+                                // Target player not found
+                                let challenge_failed_msg = ServerMessage::ChallengeFailed { 
+                                    reason: "Target player not found".to_string() 
+                                };
+                                if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &challenge_failed_msg).await {
+                                    error!("Failed to send challenge failed message: {}", e);
+                                }
+                            }
+                        }
+                    },
+                    Ok(ClientMessage::RespondToChallenge { challenger_id, accepted, use_rental }) => {
+                        info!("Player {} is responding to challenge from {}: accepted={}", player_id_for_receiver, challenger_id, accepted);
+
+                        // Only a challenge that was actually sent can be answered, and only once
+                        let Some((_, challenge)) = lobby_for_receiver.pending_challenges.remove(&(challenger_id.clone(), player_id_for_receiver.clone())) else {
+                            let response_failed_msg = ServerMessage::ChallengeFailed {
+                                reason: "No challenge from this player to respond to".to_string()
+                            };
+                            if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response_failed_msg).await {
+                                error!("Failed to send response failed message: {}", e);
+                            }
+                            continue;
+                        };
+                        let PendingChallenge { rules, challenger_rental } = challenge;
+
+                        // Blocks and settings may have changed since the challenge was sent; a challenge
+                        // that would be turned away now cannot start a battle either
+                        if accepted {
+                            if let Some(reason) = challenge_refusal(&state_for_tasks, &challenger_id, &player_id_for_receiver).await {
+                                let response_failed_msg = ServerMessage::ChallengeFailed { reason: reason.to_string() };
+                                if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response_failed_msg).await {
+                                    error!("Failed to send response failed message: {}", e);
+                                }
+                                continue;
+                            }
+                        }
+
+                        // Verify both players exist and are online
+                        if !lobby_for_receiver.player_positions.contains_key(&challenger_id) {
+                            let response_failed_msg = ServerMessage::ChallengeFailed { 
+                                reason: "Challenger is no longer online".to_string() 
+                            };
+                            if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response_failed_msg).await {
+                                error!("Failed to send response failed message: {}", e);
+                            }
+                            continue;
+                        }
+                        
+                        // Challenges are only sent after the tutorial; check again in case the challenge is stale
+                        if accepted && !(tutorial_step(&state_for_tasks, &player_id_for_receiver).await.can_challenge()
+                            && tutorial_step(&state_for_tasks, &challenger_id).await.can_challenge())
+                        {
+                            let response_failed_msg = ServerMessage::ChallengeFailed {
+                                reason: "Both players must finish the tutorial before battling".to_string()
+                            };
+                            if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response_failed_msg).await {
+                                error!("Failed to send response failed message: {}", e);
+                            }
+                            continue;
+                        }
 
-                        // Only process movement if it's valid
-                        if movement_valid {
-                            let updated_player = PlayerState {
-                                id: player_id_for_receiver.clone(),
-                                username: current_state.username.clone(),
-                                x,
-                                y,
-                                direction,
-                                in_combat: current_state.in_combat,
+                        // Verify neither player is in combat
+                        let challenger_in_combat = lobby_for_receiver.player_positions.get(&challenger_id)
+                            .map(|state| state.value().in_combat)
+                            .unwrap_or(false);
+                            
+                        let responder_in_combat = lobby_for_receiver.player_positions.get(&player_id_for_receiver)
+                            .map(|state| state.value().in_combat)
+                            .unwrap_or(false);
+                        
+                        if challenger_in_combat || responder_in_combat {
+                            let response_failed_msg = ServerMessage::ChallengeFailed { 
+                                reason: "A player is already in combat".to_string() 
                             };
+                            if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response_failed_msg).await {
+                                error!("Failed to send response failed message: {}", e);
+                            }
                             
-                            // Update player state in Redis
-                            if let Ok(mut redis_conn) = state_for_tasks.redis.get_async_connection().await {
-                                let player_json = serde_json::to_string(&updated_player).unwrap();
-                                let _ = redis_manager::store_player_state(
-                                    &mut redis_conn,
-                                    &lobby_for_receiver.id,
-                                    &player_id_for_receiver,
-                                    &player_json,
-                                ).await.unwrap_or_else(|e| {
-                                    tracing::error!("Failed to persist player state: {}", e);
-                                });
+                            // Also notify the challenger if they're not in combat
+                            if !challenger_in_combat {
+                                if let Err(e) = lobby_for_receiver.send_to_player(&challenger_id, &response_failed_msg).await {
+                                    error!("Failed to send response failed message to challenger: {}", e);
+                                }
                             }
                             
-                            // Update player state in lobby
-                            lobby_for_receiver.player_positions.insert(player_id_for_receiver.clone(), updated_player.clone());
+                            continue;
+                        }
+                        
+                        // Get responder's username
+                        let responder_username = lobby_for_receiver.player_positions.get(&player_id_for_receiver)
+                            .map(|state| state.value().username.clone())
+                            .unwrap_or_else(|| "Unknown".to_string());
+                        
+                        // Send response to challenger
+                        let challenge_response_msg = ServerMessage::ChallengeResponse { 
+                            target_player_id: player_id_for_receiver.clone(),
+                            target_username: responder_username,
+                            accepted,
+                            rules,
+                        };
+                        
+                        if let Err(e) = lobby_for_receiver.send_to_player(&challenger_id, &challenge_response_msg).await {
+                            error!("Failed to send challenge response to challenger: {}", e);
+                            continue;
+                        }
+                        
+                        // If accepted, proceed to start the PvP battle
+                        if accepted {
+                            info!("Challenge accepted! Starting PvP battle between {} and {}", challenger_id, player_id_for_receiver);
                             
-                            // Register the validated movement in the movement manager
-                            state_for_tasks.player_movement_manager.as_ref().unwrap()
-                                .register_movement(player_id_for_receiver.clone(), updated_player.clone());
+                            // Get the battle manager
+                            if let Some(battle_manager) = state_for_tasks.battle_manager.as_ref() {
+                                // Mark both players as in combat (temporarily) to prevent race conditions
+                                if let Some(mut challenger_state) = lobby_for_receiver.player_positions.get_mut(&challenger_id) {
+                                    challenger_state.value_mut().in_combat = true;
+                                }
+                                
+                                if let Some(mut responder_state) = lobby_for_receiver.player_positions.get_mut(&player_id_for_receiver) {
+                                    responder_state.value_mut().in_combat = true;
+                                }
+                                
+                                let challenger_crystal = equipped_crystal_type(&state_for_tasks, &challenger_id).await;
+                                let responder_crystal = equipped_crystal_type(&state_for_tasks, &player_id_for_receiver).await;
+                                // Draft challenges open a pick/ban phase first; the battle starts once it is over
+                                let started = if rules.draft {
+                                    battle_manager.start_draft(
+                                        &challenger_id,
+                                        &player_id_for_receiver,
+                                        &lobby_for_receiver,
+                                        state_for_tasks.pokemon_collection_manager.as_ref().unwrap(),
+                                        rules,
+                                        [challenger_crystal, responder_crystal],
+                                    ).await
+                                } else {
+                                    // Call the battle manager to start the battle
+                                    battle_manager.start_pvp_battle(
+                                        &challenger_id, 
+                                        &player_id_for_receiver, 
+                                        &lobby_for_receiver, 
+                                        state_for_tasks.pokemon_collection_manager.as_ref().unwrap(),
+                                        rules,
+                                        (
+                                            PvPLoadout {
+                                                crystal_type: challenger_crystal,
+                                                use_rental: challenger_rental,
+                                                drafted_team: None,
+                                            },
+                                            PvPLoadout {
+                                                crystal_type: responder_crystal,
+                                                use_rental,
+                                                drafted_team: None,
+                                            },
+                                        ),
+                                    ).await
+                                };
+                                match started {
+                                    Ok(id) => {
+                                        info!("PvP {} {} successfully started", if rules.draft { "draft" } else { "battle" }, id);
+                                        // Battle state is now managed by the battle manager
+                                    },
+                                    Err(e) => {
+                                        error!("Failed to start PvP battle: {}", e);
+                                        
+                                        // Send error message to both players
+                                        let error_msg = ServerMessage::Error {
+                                            message: format!("Failed to start battle: {}", e),
+                                        };
+                                        
+                                        if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                            error!("Failed to send error message to responder: {}", send_err);
+                                        }
+                                        
+                                        if let Err(send_err) = lobby_for_receiver.send_to_player(&challenger_id, &error_msg).await {
+                                            error!("Failed to send error message to challenger: {}", send_err);
+                                        }
+                                        
+                                        // Reset combat status for both players
+                                        if let Some(mut challenger_state) = lobby_for_receiver.player_positions.get_mut(&challenger_id) {
+                                            challenger_state.value_mut().in_combat = false;
+                                        }
+                                        
+                                        if let Some(mut responder_state) = lobby_for_receiver.player_positions.get_mut(&player_id_for_receiver) {
+                                            responder_state.value_mut().in_combat = false;
+                                        }
+                                    }
+                                }
+                            } else {
+                                error!("Battle manager not available for PvP battle");
+                                
+                                // Send error message to both players
+                                let error_msg = ServerMessage::Error {
+                                    message: "Battle system unavailable".to_string(),
+                                };
+                                
+                                if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                    error!("Failed to send error message to responder: {}", e);
+                                }
+                                
+                                if let Err(e) = lobby_for_receiver.send_to_player(&challenger_id, &error_msg).await {
+                                    error!("Failed to send error message to challenger: {}", e);
+                                }
+                                
+                                // Reset combat status for both players
+                                if let Some(mut challenger_state) = lobby_for_receiver.player_positions.get_mut(&challenger_id) {
+                                    challenger_state.value_mut().in_combat = false;
+                                }
+                                
+                                if let Some(mut responder_state) = lobby_for_receiver.player_positions.get_mut(&player_id_for_receiver) {
+                                    responder_state.value_mut().in_combat = false;
+                                }
+                            }
+                        }
+                    },
+                    Ok(ClientMessage::StartPracticeBattle { difficulty }) => {
+                        let (Some(battle_manager), Some(pokemon_collection_manager)) = (state_for_tasks.battle_manager.as_ref(), state_for_tasks.pokemon_collection_manager.as_ref()) else {
+                            error!("Battle manager not found when starting a practice battle");
+                            continue;
+                        };
+                        let in_combat = lobby_for_receiver.player_positions.get(&player_id_for_receiver)
+                            .is_some_and(|state| state.value().in_combat);
+                        let result = if in_combat {
+                            Err("You are already in combat".to_string())
                         } else {
-                            // Send correction message to the client who tried invalid movement
-                            let correction_msg = ServerMessage::PlayersMoved { 
-                                players: vec![current_state],
-                                timestamp: Utc::now().timestamp_millis() as u64
+                            let crystal = equipped_crystal_type(&state_for_tasks, &player_id_for_receiver).await;
+                            battle_manager.start_practice_battle(&player_id_for_receiver, difficulty, &lobby_for_receiver, pokemon_collection_manager, crystal).await
+                        };
+                        if let Err(e) = result {
+                            let error_msg = ServerMessage::Error { message: format!("Failed to start practice battle: {}", e) };
+                            if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                error!("Failed to send error message to player {}: {}", player_id_for_receiver, send_err);
+                            }
+                        }
+                    },
+                    Ok(ClientMessage::TalkToGymLeader { gym_id }) => {
+                        let response = match gym_leader_offer(&state_for_tasks, &lobby_for_receiver, &player_id_for_receiver, &gym_id).await {
+                            Ok(offer) => ServerMessage::GymLeaderOffer { gym_id, offer },
+                            Err(e) => ServerMessage::Error { message: format!("Failed to talk to gym leader: {}", e) },
+                        };
+                        if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response).await {
+                            error!("Failed to send gym leader offer to player {}: {}", player_id_for_receiver, e);
+                        }
+                    },
+                    Ok(ClientMessage::ChallengeGym { gym_id }) => {
+                        if let Err(e) = challenge_gym(&state_for_tasks, &lobby_for_receiver, &player_id_for_receiver, &gym_id).await {
+                            let error_msg = ServerMessage::Error { message: format!("Failed to challenge gym: {}", e) };
+                            if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                error!("Failed to send error message to player {}: {}", player_id_for_receiver, send_err);
+                            }
+                        }
+                    },
+                    Ok(ClientMessage::FindPlayer { username }) => {
+                        let players = find_players(&state_for_tasks, &username, Some(&player_id_for_receiver)).await;
+                        let results_msg = ServerMessage::PlayerSearchResults { query: username, players };
+                        if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &results_msg).await {
+                            error!("Failed to send player search results to player {}: {}", player_id_for_receiver, e);
+                        }
+                    },
+                    Ok(ClientMessage::NicknameCaptured { pokemon_id, nickname }) => {
+                        let pokemon_collection_manager = state_for_tasks.pokemon_collection_manager.as_ref().unwrap();
+                        if let Err(e) = pokemon_collection_manager.nickname_captured(&player_id_for_receiver, &pokemon_id, nickname).await {
+                            let error_msg = ServerMessage::Error { message: format!("Failed to nickname pokemon: {}", e) };
+                            if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                error!("Failed to send error message to player {}: {}", player_id_for_receiver, send_err);
+                            }
+                            continue;
+                        }
+
+                        // Send the refreshed collection so the new name shows up
+                        if let Ok(pokemons) = pokemon_collection_manager.get_active_pokemons(&player_id_for_receiver).await {
+                            let active_pokemons_msg = ServerMessage::ActivePokemons {
+                                pokemons: pokemons.iter().map(|p| pokemon_collection_manager.pokemon_to_display_pokemon(p)).collect()
                             };
-                            
-                            if let Err(e) = sender_for_receiver.lock().await.send(
-                                Message::Text(Utf8Bytes::from(serde_json::to_string(&correction_msg).unwrap()))
-                            ).await {
-                                tracing::error!("Failed to send correction message: {}", e);
+                            if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &active_pokemons_msg).await {
+                                error!("Failed to send pokemon collection to player {}: {}", player_id_for_receiver, e);
                             }
-                        }                    },
-                    Ok(ClientMessage::Ping) => {
-                        let pong_msg = ServerMessage::Pong;
-                        let mut sender_lock = sender_for_receiver.lock().await;
-                        if let Err(e) = sender_lock.send(Message::Text(Utf8Bytes::from(serde_json::to_string(&pong_msg).unwrap()))).await {
-                            tracing::error!("Failed to send pong message: {}", e);
-                            break;
                         }
                     },
-                    Ok(ClientMessage::Interact { monster_id }) => {
-                        handle_player_interaction(&state_for_tasks, &lobby_for_receiver.id, &player_id_for_receiver, &monster_id).await;
+                    Ok(ClientMessage::SwapIntoParty { pokemon_id, party_slot }) => {
+                        let in_combat = lobby_for_receiver.player_positions.get(&player_id_for_receiver).is_some_and(|player| player.in_combat);
+                        let pokemon_collection_manager = state_for_tasks.pokemon_collection_manager.as_ref().unwrap();
+                        let result = if in_combat {
+                            Err("Cannot change your party during a battle".to_string())
+                        } else {
+                            pokemon_collection_manager.swap_into_party(&player_id_for_receiver, &pokemon_id, party_slot).await
+                        };
+                        if let Err(e) = result {
+                            let error_msg = ServerMessage::Error { message: format!("Failed to swap pokemon: {}", e) };
+                            if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                error!("Failed to send error message to player {}: {}", player_id_for_receiver, send_err);
+                            }
+                            continue;
+                        }
+
+                        if let Ok(pokemons) = pokemon_collection_manager.get_active_pokemons(&player_id_for_receiver).await {
+                            let active_pokemons_msg = ServerMessage::ActivePokemons {
+                                pokemons: pokemons.iter().map(|p| pokemon_collection_manager.pokemon_to_display_pokemon(p)).collect()
+                            };
+                            if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &active_pokemons_msg).await {
+                                error!("Failed to send pokemon collection to player {}: {}", player_id_for_receiver, e);
+                            }
+                        }
+                    },
+                    Ok(ClientMessage::UsePpItem { item_id, pokemon_id, move_index }) => {
+                        if let Err(e) = use_pp_item(&state_for_tasks, &player_id_for_receiver, &item_id, &pokemon_id, move_index).await {
+                            let error_msg = ServerMessage::Error { message: format!("Failed to use {}: {}", item_id, e) };
+                            if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                error!("Failed to send error message to player {}: {}", player_id_for_receiver, send_err);
+                            }
+                            continue;
+                        }
+
+                        // Send the refreshed collection so the raised max PP shows up
+                        let pokemon_collection_manager = state_for_tasks.pokemon_collection_manager.as_ref().unwrap();
+                        if let Ok(pokemons) = pokemon_collection_manager.get_active_pokemons(&player_id_for_receiver).await {
+                            let active_pokemons_msg = ServerMessage::ActivePokemons {
+                                pokemons: pokemons.iter().map(|p| pokemon_collection_manager.pokemon_to_display_pokemon(p)).collect()
+                            };
+                            if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &active_pokemons_msg).await {
+                                error!("Failed to send pokemon collection to player {}: {}", player_id_for_receiver, e);
+                            }
+                        }
+                    },
+                    Ok(ClientMessage::UseKeyItem { gate_id }) => {
+                        let Some(gate_manager) = state_for_tasks.gate_manager.as_ref() else {
+                            error!("Gate manager not found when using key item");
+                            continue;
+                        };
+                        match gate_manager.clear_gate(&lobby_for_receiver, &player_id_for_receiver, &gate_id).await {
+                            Ok(_) => {
+                                let _ = lobby_for_receiver.broadcast_except(&ServerMessage::GateCleared {
+                                    gate_id,
+                                    player_id: player_id_for_receiver.clone(),
+                                }, &[]).await;
+                            }
+                            Err(e) => {
+                                let error_msg = ServerMessage::Error { message: format!("Failed to clear {}: {}", gate_id, e) };
+                                if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                    error!("Failed to send error message to player {}: {}", player_id_for_receiver, send_err);
+                                }
+                            }
+                        }
+                    },
+                    Ok(ClientMessage::UseLobbyBuffItem { item_id }) => {
+                        let Some(inventory_manager) = state_for_tasks.inventory_manager.as_ref() else {
+                            continue;
+                        };
+                        let result = match buff_for_item(&item_id) {
+                            Some((name, multiplier, duration_secs)) => inventory_manager.remove_item(&player_id_for_receiver, &item_id, 1).await
+                                .map(|_| lobby_for_receiver.buffs.activate(&item_id, name, multiplier, duration_secs, Some(player_id_for_receiver.clone()))),
+                            None => Err(format!("{} does not buff the lobby", item_id)),
+                        };
+                        match result {
+                            Ok(buff) => {
+                                info!("Player {} used {} in lobby {} ({}s left)", player_id_for_receiver, item_id, lobby_for_receiver.id, buff.remaining_secs);
+                                lobby_for_receiver.broadcast_buffs();
+                            }
+                            Err(e) => {
+                                let error_msg = ServerMessage::Error { message: format!("Failed to use {}: {}", item_id, e) };
+                                if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                    error!("Failed to send error message to player {}: {}", player_id_for_receiver, send_err);
+                                }
+                            }
+                        }
+                    },
+                    Ok(ClientMessage::EquipCrystal { item_id }) => {
+                        let inventory_manager = match state_for_tasks.inventory_manager.as_ref() {
+                            Some(inventory_manager) => inventory_manager,
+                            None => continue,
+                        };
+                        let response = match inventory_manager.equip_crystal(&player_id_for_receiver, item_id.as_deref()).await {
+                            Ok(()) => ServerMessage::EquippedCrystal { item_id },
+                            Err(e) => ServerMessage::Error { message: format!("Failed to equip crystal: {}", e) },
+                        };
+                        if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response).await {
+                            error!("Failed to send equipped crystal to player {}: {}", player_id_for_receiver, e);
+                        }
+                    },
+                    Ok(ClientMessage::GetBattleLog { battle_id }) => {
+                        if let Some(battle_manager) = state_for_tasks.battle_manager.as_ref() {
+                            let response = match battle_manager.get_battle_log(battle_id, &player_id_for_receiver).await {
+                                Ok((turn_number, events)) => ServerMessage::BattleLog { battle_id, turn_number, events },
+                                Err(e) => ServerMessage::Error { message: e },
+                            };
+                            if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response).await {
+                                error!("Failed to send battle log to player {}: {}", player_id_for_receiver, e);
+                            }
+                        } else {
+                            error!("Battle manager not found when fetching battle log");
+                        }
+                    },
+                    Ok(ClientMessage::RequestFullBattleState { battle_id }) => {
+                        if let Some(battle_manager) = state_for_tasks.battle_manager.as_ref() {
+                            if let Err(e) = battle_manager.resend_battle_request(battle_id, &player_id_for_receiver, &lobby_for_receiver).await {
+                                let error_msg = ServerMessage::Error { message: e };
+                                if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                    error!("Failed to send error message to player {}: {}", player_id_for_receiver, send_err);
+                                }
+                            }
+                        } else {
+                            error!("Battle manager not found when re-sending battle state");
+                        }
+                    },
+                    Ok(ClientMessage::SetAutoBattle { battle_id, enabled }) => {
+                        let (Some(battle_manager), Some(pokemon_collection_manager)) = (state_for_tasks.battle_manager.as_ref(), state_for_tasks.pokemon_collection_manager.as_ref()) else {
+                            error!("Battle manager not found when setting auto-battle");
+                            continue;
+                        };
+                        if let Err(e) = battle_manager.set_auto_battle(&player_id_for_receiver, battle_id, enabled, &lobby_for_receiver, pokemon_collection_manager).await {
+                            let error_msg = ServerMessage::Error { message: e };
+                            if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                error!("Failed to send error message to player {}: {}", player_id_for_receiver, send_err);
+                            }
+                        }
+                    },
+                    Ok(ClientMessage::DraftSelect { draft_id, template_id }) => {
+                        let (Some(battle_manager), Some(pokemon_collection_manager)) = (state_for_tasks.battle_manager.as_ref(), state_for_tasks.pokemon_collection_manager.as_ref()) else {
+                            error!("Battle manager not found when drafting");
+                            continue;
+                        };
+                        if let Err(e) = battle_manager.handle_draft_selection(&player_id_for_receiver, draft_id, template_id, &lobby_for_receiver, pokemon_collection_manager).await {
+                            let error_msg = ServerMessage::Error { message: e };
+                            if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                error!("Failed to send error message to player {}: {}", player_id_for_receiver, send_err);
+                            }
+                        }
+                    },
+                    Ok(ClientMessage::PlantBerry { x, y, berry }) => {
+                        let berry_manager = match state_for_tasks.berry_manager.as_ref() {
+                            Some(berry_manager) => berry_manager,
+                            None => {
+                                error!("Berry manager not found when planting berry");
+                                continue;
+                            }
+                        };
+                        if let Err(e) = berry_manager.plant(&lobby_for_receiver, &player_id_for_receiver, x, y, berry).await {
+                            let error_msg = ServerMessage::Error { message: format!("Failed to plant berry: {}", e) };
+                            if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                error!("Failed to send error message to player {}: {}", player_id_for_receiver, send_err);
+                            }
+                        }
+                    },
+                    Ok(ClientMessage::HarvestBerry { x, y }) => {
+                        let berry_manager = match state_for_tasks.berry_manager.as_ref() {
+                            Some(berry_manager) => berry_manager,
+                            None => {
+                                error!("Berry manager not found when harvesting berry");
+                                continue;
+                            }
+                        };
+                        let response = match berry_manager.harvest(&lobby_for_receiver, &player_id_for_receiver, x, y).await {
+                            Ok((berry, quantity)) => ServerMessage::BerryHarvested { berry, quantity },
+                            Err(e) => ServerMessage::Error { message: format!("Failed to harvest berry: {}", e) },
+                        };
+                        let harvested = matches!(response, ServerMessage::BerryHarvested { .. });
+                        if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response).await {
+                            error!("Failed to send harvest result to player {}: {}", player_id_for_receiver, e);
+                        }
+                        if harvested {
+                            send_inventory(&state_for_tasks, &lobby_for_receiver, &player_id_for_receiver).await;
+                        }
+                    },
+                    Ok(ClientMessage::PickUpItem { spawn_id }) => {
+                        let Some(inventory_manager) = state_for_tasks.inventory_manager.as_ref() else {
+                            error!("Inventory manager not found when picking up item");
+                            continue;
+                        };
+                        let response = match lobby_for_receiver.item_pickups.pick_up(&lobby_for_receiver, inventory_manager, &player_id_for_receiver, &spawn_id).await {
+                            Ok(pickup) => ServerMessage::ItemReceived { item_id: pickup.item_id, quantity: pickup.quantity },
+                            Err(e) => ServerMessage::Error { message: format!("Failed to pick up item: {}", e) },
+                        };
+                        let picked_up = matches!(response, ServerMessage::ItemReceived { .. });
+                        if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response).await {
+                            error!("Failed to send pickup result to player {}: {}", player_id_for_receiver, e);
+                        }
+                        if picked_up {
+                            send_inventory(&state_for_tasks, &lobby_for_receiver, &player_id_for_receiver).await;
+                        }
+                    },
+                    Ok(ClientMessage::GetInventory) => {
+                        send_inventory(&state_for_tasks, &lobby_for_receiver, &player_id_for_receiver).await;
                     },
-                    Ok(ClientMessage::ChooseStarter { starter_id }) => {
-                        let pokemon_collection_manager = state_for_tasks.pokemon_collection_manager.as_ref().unwrap();
-                        let display_pokemon = match pokemon_collection_manager.choose_starting_pokemons(&player_id_for_receiver, starter_id).await {
-                            Ok(pokemon) => pokemon,
+                    Ok(ClientMessage::FastTravel { landmark_id }) => {
+                        let fast_travel_manager = match state_for_tasks.fast_travel_manager.as_ref() {
+                            Some(fast_travel_manager) => fast_travel_manager,
+                            None => {
+                                error!("Fast travel manager not found when fast traveling");
+                                continue;
+                            }
+                        };
+                        match fast_travel_manager.fast_travel(&lobby_for_receiver, &player_id_for_receiver, &landmark_id).await {
+                            Ok(new_state) => {
+                                // Broadcast the new position with the next movement update
+                                state_for_tasks.player_movement_manager.as_ref().unwrap()
+                                    .register_movement(player_id_for_receiver.clone(), new_state.clone());
+                                let travel_msg = ServerMessage::FastTravelComplete { landmark_id, x: new_state.x, y: new_state.y };
+                                if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &travel_msg).await {
+                                    error!("Failed to send fast travel result to player {}: {}", player_id_for_receiver, e);
+                                }
+                            }
                             Err(e) => {
-                                tracing::error!("Failed to choose starter pokemon: {}", e);
-                                let error_msg = ServerMessage::Error { message: format!("Failed to choose starter: {}", e) };
+                                let error_msg = ServerMessage::Error { message: format!("Failed to fast travel: {}", e) };
                                 if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
-                                    tracing::error!("Failed to send error message to player {}: {}", player_id_for_receiver, send_err);
+                                    error!("Failed to send error message to player {}: {}", player_id_for_receiver, send_err);
                                 }
-                                continue;
                             }
+                        }
+                    },
+                    Ok(ClientMessage::RequestTrade { target_player_id }) => {
+                        let blocked = state_for_tasks.block_list_manager.as_ref()
+                            .is_some_and(|block_list_manager| block_list_manager.is_blocked_between(&player_id_for_receiver, &target_player_id));
+                        let result = match state_for_tasks.trade_manager.as_ref() {
+                            _ if blocked => Err("You cannot trade with this player".to_string()),
+                            Some(trade_manager) => trade_manager.request_trade(&lobby_for_receiver, &player_id_for_receiver, &target_player_id).await,
+                            None => Err("Trading is unavailable".to_string()),
                         };
-                        let new_pokemon_msg = ServerMessage::NewPokemon {
-                            pokemon: display_pokemon, 
-                            active_index: Some(0),
+                        send_trade_error(&lobby_for_receiver, &player_id_for_receiver, result).await;
+                    },
+                    Ok(ClientMessage::RespondToTrade { requester_id, accepted }) => {
+                        let result = match state_for_tasks.trade_manager.as_ref() {
+                            Some(trade_manager) => trade_manager.respond_to_request(&lobby_for_receiver, &player_id_for_receiver, &requester_id, accepted).await,
+                            None => Err("Trading is unavailable".to_string()),
                         };
-                        if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &new_pokemon_msg).await {
-                            tracing::error!("Failed to send new pokemon message: {}", e);
+                        send_trade_error(&lobby_for_receiver, &player_id_for_receiver, result).await;
+                    },
+                    Ok(trade_message @ (ClientMessage::UpdateTradeOffer { .. } | ClientMessage::ConfirmTrade { .. } | ClientMessage::CancelTrade { .. })) => {
+                        let trade_manager = match state_for_tasks.trade_manager.as_ref() {
+                            Some(trade_manager) => trade_manager,
+                            None => continue,
+                        };
+                        // Escrow moves goods on both sides, so both players get their holdings refreshed
+                        let partner_id = trade_manager.partner_of(&player_id_for_receiver);
+                        let result = match trade_message {
+                            ClientMessage::UpdateTradeOffer { trade_id, offer } => {
+                                trade_manager.update_offer(&lobby_for_receiver, &player_id_for_receiver, trade_id, offer).await
+                            }
+                            ClientMessage::ConfirmTrade { trade_id } => {
+                                let result = trade_manager.confirm(&lobby_for_receiver, &player_id_for_receiver, trade_id).await;
+                                if let (Ok(true), Some(webhooks), Some(partner_id)) = (&result, state_for_tasks.webhooks.as_ref(), partner_id.as_ref()) {
+                                    webhooks.emit(WebhookEvent::TradeCompleted {
+                                        trade_id,
+                                        lobby_id: lobby_for_receiver.id.clone(),
+                                        player_ids: [player_id_for_receiver.clone(), partner_id.clone()],
+                                    });
+                                }
+                                result.map(|_| ())
+                            }
+                            ClientMessage::CancelTrade { trade_id } => {
+                                trade_manager.cancel(&lobby_for_receiver, &player_id_for_receiver, trade_id, "The other player cancelled the trade").await
+                            }
+                            _ => continue,
+                        };
+                        send_trade_error(&lobby_for_receiver, &player_id_for_receiver, result).await;
+                        send_holdings(&state_for_tasks, &lobby_for_receiver, &player_id_for_receiver).await;
+                        if let Some(partner_id) = partner_id {
+                            send_holdings(&state_for_tasks, &lobby_for_receiver, &partner_id).await;
                         }
                     },
-                    Ok(ClientMessage::CombatAction { battle_id, action }) => {
-                        // Get the battle manager
-                        if let Some(battle_manager) = state_for_tasks.battle_manager.as_ref() {
-                            // Handle the action, passing the lobby for message sending
-                            match battle_manager.handle_player_action(&player_id_for_receiver, battle_id, action, &lobby_for_receiver, state_for_tasks.pokemon_collection_manager.as_ref().unwrap()).await {
-                                Ok(_) => {
-                                    info!("Player {} submitted action for battle {} and turn was processed", player_id_for_receiver, battle_id);
-                                },
-                                Err(e) => {
-                                    error!("Failed to handle player action for battle {}: {}", battle_id, e);
-                                    // Send error back to the player
-                                    let error_msg = ServerMessage::Error { message: e };
-                                    if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
-                                        error!("Failed to send error message to player {}: {}", player_id_for_receiver, send_err);
-                                    }
+                    Ok(ClientMessage::MarketSearch { query }) => {
+                        let market_manager = match state_for_tasks.market_manager.as_ref() {
+                            Some(market_manager) => market_manager,
+                            None => continue,
+                        };
+                        let response = match market_manager.search(&query).await {
+                            Ok(listings) => ServerMessage::MarketListings { listings },
+                            Err(e) => ServerMessage::Error { message: format!("Market search failed: {}", e) },
+                        };
+                        if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response).await {
+                            error!("Failed to send market listings to player {}: {}", player_id_for_receiver, e);
+                        }
+                    },
+                    Ok(ClientMessage::MarketList { goods, asking }) => {
+                        let market_manager = match state_for_tasks.market_manager.as_ref() {
+                            Some(market_manager) => market_manager,
+                            None => continue,
+                        };
+                        let username = match lobby_for_receiver.player_positions.get(&player_id_for_receiver) {
+                            Some(player_state) => player_state.username.clone(),
+                            None => continue,
+                        };
+                        let response = match market_manager.create_listing(&lobby_for_receiver, &player_id_for_receiver, &username, goods, asking).await {
+                            Ok(listing) => ServerMessage::MarketListingCreated { listing },
+                            Err(e) => ServerMessage::Error { message: format!("Failed to create listing: {}", e) },
+                        };
+                        if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response).await {
+                            error!("Failed to send market result to player {}: {}", player_id_for_receiver, e);
+                        }
+                        send_holdings(&state_for_tasks, &lobby_for_receiver, &player_id_for_receiver).await;
+                    },
+                    Ok(ClientMessage::MarketAccept { listing_id, offered_pokemon_id }) => {
+                        let market_manager = match state_for_tasks.market_manager.as_ref() {
+                            Some(market_manager) => market_manager,
+                            None => continue,
+                        };
+                        let response = match market_manager.accept_listing(&lobby_for_receiver, &player_id_for_receiver, listing_id, offered_pokemon_id).await {
+                            Ok(listing) => {
+                                if let Some(notification_manager) = state_for_tasks.notification_manager.as_ref() {
+                                    game_loop::market::notify_seller(&state_for_tasks.lobbies, notification_manager, &listing).await;
                                 }
+                                ServerMessage::MarketPurchaseComplete { listing }
                             }
-                        } else {
-                            error!("Battle manager not found when handling combat action");
+                            Err(e) => ServerMessage::Error { message: format!("Failed to buy listing: {}", e) },
+                        };
+                        if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response).await {
+                            error!("Failed to send market result to player {}: {}", player_id_for_receiver, e);
                         }
+                        send_holdings(&state_for_tasks, &lobby_for_receiver, &player_id_for_receiver).await;
                     },
-                    Ok(ClientMessage::ChallengePlayer { target_player_id }) => {
-                        info!("Player {} is challenging player {}", player_id_for_receiver, target_player_id);
-                        
-                        // Verify challenger is not in combat
-                        if let Some(challenger_state) = lobby_for_receiver.player_positions.get(&player_id_for_receiver) {
-                            if challenger_state.value().in_combat {
-                                let challenge_failed_msg = ServerMessage::ChallengeFailed { 
-                                    reason: "You are already in combat".to_string() 
+                    Ok(ClientMessage::MarketCancel { listing_id }) => {
+                        let market_manager = match state_for_tasks.market_manager.as_ref() {
+                            Some(market_manager) => market_manager,
+                            None => continue,
+                        };
+                        let response = match market_manager.cancel_listing(&player_id_for_receiver, listing_id).await {
+                            Ok(()) => ServerMessage::MarketListingCancelled { listing_id },
+                            Err(e) => ServerMessage::Error { message: format!("Failed to cancel listing: {}", e) },
+                        };
+                        if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response).await {
+                            error!("Failed to send market result to player {}: {}", player_id_for_receiver, e);
+                        }
+                        send_holdings(&state_for_tasks, &lobby_for_receiver, &player_id_for_receiver).await;
+                    },
+                    Ok(ClientMessage::GetAuctions) => {
+                        let auction_manager = match state_for_tasks.auction_manager.as_ref() {
+                            Some(auction_manager) => auction_manager,
+                            None => continue,
+                        };
+                        let auctions_msg = ServerMessage::Auctions { auctions: auction_manager.active_auctions() };
+                        if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &auctions_msg).await {
+                            error!("Failed to send auctions to player {}: {}", player_id_for_receiver, e);
+                        }
+                    },
+                    Ok(ClientMessage::PlaceBid { auction_id, amount }) => {
+                        let auction_manager = match state_for_tasks.auction_manager.as_ref() {
+                            Some(auction_manager) => auction_manager,
+                            None => continue,
+                        };
+                        let username = match lobby_for_receiver.player_positions.get(&player_id_for_receiver) {
+                            Some(player_state) => player_state.username.clone(),
+                            None => continue,
+                        };
+                        match auction_manager.place_bid(&player_id_for_receiver, &username, auction_id, amount).await {
+                            Ok(outcome) => {
+                                let balance_msg = ServerMessage::BalanceUpdate {
+                                    balance: outcome.bidder_balance,
+                                    delta: -(amount as i64),
+                                    reason: Some(TransactionReason::AuctionBid),
                                 };
-                                if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &challenge_failed_msg).await {
-                                    error!("Failed to send challenge failed message: {}", e);
+                                if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &balance_msg).await {
+                                    error!("Failed to send balance update to player {}: {}", player_id_for_receiver, e);
+                                }
+                                if let Some(notification_manager) = state_for_tasks.notification_manager.as_ref() {
+                                    game_loop::auctions::announce_bid(&state_for_tasks.lobbies, notification_manager, &outcome).await;
                                 }
-                                continue;
                             }
-                            
-                            // Check if player is trying to challenge themselves
-                            if player_id_for_receiver == target_player_id {
-                                let challenge_failed_msg = ServerMessage::ChallengeFailed { 
-                                    reason: "You cannot challenge yourself".to_string() 
-                                };
-                                if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &challenge_failed_msg).await {
-                                    error!("Failed to send challenge failed message: {}", e);
+                            Err(e) => {
+                                let error_msg = ServerMessage::Error { message: format!("Bid rejected: {}", e) };
+                                if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                    error!("Failed to send bid error to player {}: {}", player_id_for_receiver, send_err);
                                 }
-                                continue;
                             }
-                            
-                            // Verify target player exists and is online
-                            if let Some(target_player_state) = lobby_for_receiver.player_positions.get(&target_player_id) {
-                                // Verify target player is not in combat
-                                if target_player_state.value().in_combat {
-                                    let challenge_failed_msg = ServerMessage::ChallengeFailed { 
-                                        reason: "Target player is already in combat".to_string() 
-                                    };
-                                    if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &challenge_failed_msg).await {
-                                        error!("Failed to send challenge failed message: {}", e);
-                                    }
-                                    continue;
+                        }
+                    },
+                    Ok(ClientMessage::SendMail { recipient_id, subject, body, attachments }) => {
+                        let mail_manager = match state_for_tasks.mail_manager.as_ref() {
+                            Some(mail_manager) => mail_manager,
+                            None => continue,
+                        };
+                        let username = match lobby_for_receiver.player_positions.get(&player_id_for_receiver) {
+                            Some(player_state) => player_state.username.clone(),
+                            None => continue,
+                        };
+                        let has_attachments = !attachments.is_empty();
+                        match mail_manager.send_mail(&player_id_for_receiver, &username, &recipient_id, subject, body, attachments).await {
+                            Ok(mail) => {
+                                let sent_msg = ServerMessage::MailSent { mail_id: mail.mail_id };
+                                if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &sent_msg).await {
+                                    error!("Failed to send mail confirmation to player {}: {}", player_id_for_receiver, e);
                                 }
-                                
-                                // All checks passed, send challenge to target player
-                                let challenge_received_msg = ServerMessage::ChallengeReceived { 
-                                    challenger_id: player_id_for_receiver.clone(),
-                                    challenger_username: challenger_state.value().username.clone(),
-                                };
-                                
-                                if let Err(e) = lobby_for_receiver.send_to_player(&target_player_id, &challenge_received_msg).await {
-                                    // Target player might have disconnected
-                                    let challenge_failed_msg = ServerMessage::ChallengeFailed { 
-                                        reason: "Failed to send challenge to target player".to_string() 
-                                    };
-                                    if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &challenge_failed_msg).await {
-                                        error!("Failed to send challenge failed message: {}", send_err);
-                                    }
-                                    error!("Failed to send challenge to target player: {}", e);
+                                if has_attachments {
+                                    send_holdings(&state_for_tasks, &lobby_for_receiver, &player_id_for_receiver).await;
                                 }
-                            } else {
-                                // Target player not found
-                                let challenge_failed_msg = ServerMessage::ChallengeFailed { 
-                                    reason: "Target player not found".to_string() 
-                                };
-                                if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &challenge_failed_msg).await {
-                                    error!("Failed to send challenge failed message: {}", e);
+
+                                // Deliver right away if the recipient is online and wants mail notifications
+                                if let Some(notification_manager) = state_for_tasks.notification_manager.as_ref() {
+                                    let unread_count = mail_manager.unread_count(&recipient_id).await.unwrap_or(1);
+                                    let mail_msg = ServerMessage::MailReceived { mail, unread_count };
+                                    notification_manager.notify(&state_for_tasks.lobbies, &recipient_id, NotificationKind::MailReceived, mail_msg).await;
+                                }
+                            }
+                            Err(e) => {
+                                let error_msg = ServerMessage::Error { message: format!("Failed to send mail: {}", e) };
+                                if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                    error!("Failed to send mail error to player {}: {}", player_id_for_receiver, send_err);
                                 }
                             }
                         }
                     },
-                    Ok(ClientMessage::RespondToChallenge { challenger_id, accepted }) => {
-                        info!("Player {} is responding to challenge from {}: accepted={}", player_id_for_receiver, challenger_id, accepted);
-                        
-                        // TODO: Security improvement needed - maintain a record of active challenges
-                        // to verify that 'challenger_id' actually sent a challenge to this player.
-                        // Without this check, players could fake responses to challenges that were never sent.
-                        // This could be implemented with a DashMap of active challenges in the Lobby struct
-                        // or by adding a temporary challenge_requests field to PlayerState.
-                        
-                        // Verify both players exist and are online
-                        if !lobby_for_receiver.player_positions.contains_key(&challenger_id) {
-                            let response_failed_msg = ServerMessage::ChallengeFailed { 
-                                reason: "Challenger is no longer online".to_string() 
-                            };
-                            if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response_failed_msg).await {
-                                error!("Failed to send response failed message: {}", e);
+                    Ok(ClientMessage::GetMailbox) => {
+                        send_mailbox(&state_for_tasks, &lobby_for_receiver, &player_id_for_receiver).await;
+                    },
+                    Ok(ClientMessage::ReadMail { mail_id }) => {
+                        let mail_manager = match state_for_tasks.mail_manager.as_ref() {
+                            Some(mail_manager) => mail_manager,
+                            None => continue,
+                        };
+                        if let Err(e) = mail_manager.mark_read(&player_id_for_receiver, mail_id).await {
+                            let error_msg = ServerMessage::Error { message: format!("Failed to read mail: {}", e) };
+                            if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                error!("Failed to send mail error to player {}: {}", player_id_for_receiver, send_err);
                             }
                             continue;
                         }
-                        
-                        // Verify neither player is in combat
-                        let challenger_in_combat = lobby_for_receiver.player_positions.get(&challenger_id)
-                            .map(|state| state.value().in_combat)
-                            .unwrap_or(false);
-                            
-                        let responder_in_combat = lobby_for_receiver.player_positions.get(&player_id_for_receiver)
-                            .map(|state| state.value().in_combat)
-                            .unwrap_or(false);
-                        
-                        if challenger_in_combat || responder_in_combat {
-                            let response_failed_msg = ServerMessage::ChallengeFailed { 
-                                reason: "A player is already in combat".to_string() 
-                            };
-                            if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response_failed_msg).await {
-                                error!("Failed to send response failed message: {}", e);
+                        send_mailbox(&state_for_tasks, &lobby_for_receiver, &player_id_for_receiver).await;
+                    },
+                    Ok(ClientMessage::ClaimMailAttachments { mail_id }) => {
+                        let mail_manager = match state_for_tasks.mail_manager.as_ref() {
+                            Some(mail_manager) => mail_manager,
+                            None => continue,
+                        };
+                        let response = match mail_manager.claim_attachments(&player_id_for_receiver, mail_id).await {
+                            Ok(attachments) => ServerMessage::MailAttachmentsClaimed { mail_id, attachments },
+                            Err(e) => ServerMessage::Error { message: format!("Failed to claim attachments: {}", e) },
+                        };
+                        if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response).await {
+                            error!("Failed to send claim result to player {}: {}", player_id_for_receiver, e);
+                        }
+                        send_holdings(&state_for_tasks, &lobby_for_receiver, &player_id_for_receiver).await;
+                    },
+                    Ok(ClientMessage::DeleteMail { mail_id }) => {
+                        let mail_manager = match state_for_tasks.mail_manager.as_ref() {
+                            Some(mail_manager) => mail_manager,
+                            None => continue,
+                        };
+                        if let Err(e) = mail_manager.delete_mail(&player_id_for_receiver, mail_id).await {
+                            let error_msg = ServerMessage::Error { message: format!("Failed to delete mail: {}", e) };
+                            if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                error!("Failed to send mail error to player {}: {}", player_id_for_receiver, send_err);
                             }
-                            
-                            // Also notify the challenger if they're not in combat
-                            if !challenger_in_combat {
-                                if let Err(e) = lobby_for_receiver.send_to_player(&challenger_id, &response_failed_msg).await {
-                                    error!("Failed to send response failed message to challenger: {}", e);
+                            continue;
+                        }
+                        send_mailbox(&state_for_tasks, &lobby_for_receiver, &player_id_for_receiver).await;
+                    },
+                    Ok(ClientMessage::GetLeaderboard { season }) => {
+                        let ranking_manager = match state_for_tasks.ranking_manager.as_ref() {
+                            Some(ranking_manager) => ranking_manager,
+                            None => continue,
+                        };
+                        let response = match ranking_manager.leaderboard(season).await {
+                            Ok(leaderboard) => ServerMessage::Leaderboard { leaderboard },
+                            Err(e) => ServerMessage::Error { message: format!("Failed to load leaderboard: {}", e) },
+                        };
+                        if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response).await {
+                            error!("Failed to send leaderboard to player {}: {}", player_id_for_receiver, e);
+                        }
+                    },
+                    Ok(ClientMessage::GetSeasonHistory) => {
+                        let ranking_manager = match state_for_tasks.ranking_manager.as_ref() {
+                            Some(ranking_manager) => ranking_manager,
+                            None => continue,
+                        };
+                        let response = match ranking_manager.season_history(&player_id_for_receiver).await {
+                            Ok(records) => ServerMessage::SeasonHistory { records },
+                            Err(e) => ServerMessage::Error { message: format!("Failed to load season history: {}", e) },
+                        };
+                        if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response).await {
+                            error!("Failed to send season history to player {}: {}", player_id_for_receiver, e);
+                        }
+                    },
+                    Ok(ClientMessage::GetBattleHistory { page, page_size }) => {
+                        let battle_history_manager = match state_for_tasks.battle_history_manager.as_ref() {
+                            Some(battle_history_manager) => battle_history_manager,
+                            None => continue,
+                        };
+                        let response = match battle_history_manager.get_history(&player_id_for_receiver, page, page_size).await {
+                            Ok(history) => ServerMessage::BattleHistory { history },
+                            Err(e) => ServerMessage::Error { message: format!("Failed to load battle history: {}", e) },
+                        };
+                        if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response).await {
+                            error!("Failed to send battle history to player {}: {}", player_id_for_receiver, e);
+                        }
+                    },
+                    Ok(ClientMessage::SetDamageBreakdown { enabled }) => {
+                        if let Some(battle_manager) = state_for_tasks.battle_manager.as_ref() {
+                            battle_manager.set_damage_breakdown(&player_id_for_receiver, enabled);
+                        }
+                    },
+                    Ok(ClientMessage::SetEffectivenessHints { enabled }) => {
+                        let player_settings_manager = match state_for_tasks.player_settings_manager.as_ref() {
+                            Some(player_settings_manager) => player_settings_manager,
+                            None => continue,
+                        };
+                        let response = match player_settings_manager.update_settings(&player_id_for_receiver, |settings| settings.effectiveness_hints = enabled).await {
+                            Ok(settings) => {
+                                if let Some(battle_manager) = state_for_tasks.battle_manager.as_ref() {
+                                    battle_manager.set_effectiveness_hints(&player_id_for_receiver, settings.effectiveness_hints);
                                 }
+                                ServerMessage::PlayerSettings { settings }
+                            },
+                            Err(e) => ServerMessage::Error { message: format!("Failed to save settings: {}", e) },
+                        };
+                        if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response).await {
+                            error!("Failed to send settings to player {}: {}", player_id_for_receiver, e);
+                        }
+                    },
+                    Ok(ClientMessage::UpdateSettings { accept_challenges, appear_offline, notifications, rich_presence, locale }) => {
+                        let player_settings_manager = match state_for_tasks.player_settings_manager.as_ref() {
+                            Some(player_settings_manager) => player_settings_manager,
+                            None => continue,
+                        };
+                        if let Some(locale) = locale.as_deref().filter(|locale| !state_for_tasks.locales.is_supported(locale)) {
+                            let error_msg = ServerMessage::Error { message: format!("Unsupported locale {}", locale) };
+                            if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                error!("Failed to send error message to player {}: {}", player_id_for_receiver, e);
+                            }
+                            continue;
+                        }
+                        let update = |settings: &mut game_loop::player_settings::PlayerSettings| {
+                            if let Some(accept_challenges) = accept_challenges {
+                                settings.accept_challenges = accept_challenges;
+                            }
+                            if let Some(appear_offline) = appear_offline {
+                                settings.appear_offline = appear_offline;
+                            }
+                            if let Some(notifications) = notifications {
+                                settings.notifications = notifications;
+                            }
+                            if let Some(rich_presence) = rich_presence {
+                                settings.rich_presence = rich_presence;
+                            }
+                            if let Some(locale) = locale {
+                                settings.locale = (locale != DEFAULT_LOCALE).then_some(locale);
+                            }
+                        };
+                        let response = match player_settings_manager.update_settings(&player_id_for_receiver, update).await {
+                            Ok(settings) => {
+                                lobby_for_receiver.set_player_locale(&player_id_for_receiver, settings.locale.clone());
+                                ServerMessage::PlayerSettings { settings }
+                            }
+                            Err(e) => ServerMessage::Error { message: format!("Failed to save settings: {}", e) },
+                        };
+                        if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response).await {
+                            error!("Failed to send settings to player {}: {}", player_id_for_receiver, e);
+                        }
+                    },
+                    Ok(ClientMessage::BattleChat { battle_id, message_id }) => {
+                        let battle_chat_manager = match state_for_tasks.battle_chat_manager.as_ref() {
+                            Some(battle_chat_manager) => battle_chat_manager,
+                            None => continue,
+                        };
+                        let block_list_manager = state_for_tasks.block_list_manager.clone();
+                        let is_muted = |recipient: &str| block_list_manager.as_ref()
+                            .is_some_and(|block_list_manager| block_list_manager.is_blocked_between(recipient, &player_id_for_receiver));
+                        if let Err(e) = battle_chat_manager.send_message(&lobby_for_receiver, battle_id, &player_id_for_receiver, &message_id, is_muted).await {
+                            let error_msg = ServerMessage::Error { message: e };
+                            if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                error!("Failed to send error message to player {}: {}", player_id_for_receiver, send_err);
+                            }
+                        }
+                    },
+                    Ok(ClientMessage::BlockPlayer { player_id: target_player_id }) => {
+                        let block_list_manager = match state_for_tasks.block_list_manager.as_ref() {
+                            Some(block_list_manager) => block_list_manager,
+                            None => continue,
+                        };
+                        let result = block_list_manager.block(&player_id_for_receiver, &target_player_id).await;
+                        send_block_list(&lobby_for_receiver, &player_id_for_receiver, result).await;
+                    },
+                    Ok(ClientMessage::UnblockPlayer { player_id: target_player_id }) => {
+                        let block_list_manager = match state_for_tasks.block_list_manager.as_ref() {
+                            Some(block_list_manager) => block_list_manager,
+                            None => continue,
+                        };
+                        let result = block_list_manager.unblock(&player_id_for_receiver, &target_player_id).await;
+                        send_block_list(&lobby_for_receiver, &player_id_for_receiver, result).await;
+                    },
+                    Ok(ClientMessage::GetBlockList) => {
+                        let block_list_manager = match state_for_tasks.block_list_manager.as_ref() {
+                            Some(block_list_manager) => block_list_manager,
+                            None => continue,
+                        };
+                        let result = block_list_manager.blocked_players(&player_id_for_receiver).await;
+                        send_block_list(&lobby_for_receiver, &player_id_for_receiver, result).await;
+                    },
+                    Ok(ClientMessage::AddFriend { player_id: target_player_id }) => {
+                        let friend_list_manager = match state_for_tasks.friend_list_manager.as_ref() {
+                            Some(friend_list_manager) => friend_list_manager,
+                            None => continue,
+                        };
+                        let result = friend_list_manager.add_friend(&player_id_for_receiver, &target_player_id).await;
+                        send_friend_list(&lobby_for_receiver, &player_id_for_receiver, result).await;
+                    },
+                    Ok(ClientMessage::RemoveFriend { player_id: target_player_id }) => {
+                        let friend_list_manager = match state_for_tasks.friend_list_manager.as_ref() {
+                            Some(friend_list_manager) => friend_list_manager,
+                            None => continue,
+                        };
+                        let result = friend_list_manager.remove_friend(&player_id_for_receiver, &target_player_id).await;
+                        send_friend_list(&lobby_for_receiver, &player_id_for_receiver, result).await;
+                    },
+                    Ok(ClientMessage::GetFriendList) => {
+                        let friend_list_manager = match state_for_tasks.friend_list_manager.as_ref() {
+                            Some(friend_list_manager) => friend_list_manager,
+                            None => continue,
+                        };
+                        let result = friend_list_manager.friend_list(&player_id_for_receiver).await;
+                        send_friend_list(&lobby_for_receiver, &player_id_for_receiver, result).await;
+                    },
+                    Ok(ClientMessage::ExamineMonster { monster_id }) => {
+                        let response = match examine_monster(&lobby_for_receiver, &player_id_for_receiver, &monster_id).await {
+                            Ok(monster) => ServerMessage::MonsterPreview { monster },
+                            Err(e) => ServerMessage::Error { message: e },
+                        };
+                        if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response).await {
+                            error!("Failed to send monster preview to player {}: {}", player_id_for_receiver, e);
+                        }
+                    },
+                    Ok(ClientMessage::Emote { emote_id }) => {
+                        let emote_manager = match state_for_tasks.emote_manager.as_ref() {
+                            Some(emote_manager) => emote_manager,
+                            None => {
+                                error!("Emote manager not found when sending emote");
+                                continue;
+                            }
+                        };
+                        let block_list_manager = state_for_tasks.block_list_manager.clone();
+                        let is_muted = |recipient: &str| block_list_manager.as_ref()
+                            .is_some_and(|block_list_manager| block_list_manager.is_blocked_between(recipient, &player_id_for_receiver));
+                        if let Err(e) = emote_manager.send_emote(&lobby_for_receiver, &player_id_for_receiver, &emote_id, is_muted).await {
+                            let error_msg = ServerMessage::Error { message: e };
+                            if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
+                                error!("Failed to send error message to player {}: {}", player_id_for_receiver, send_err);
                             }
-                            
-                            continue;
                         }
-                        
-                        // Get responder's username
-                        let responder_username = lobby_for_receiver.player_positions.get(&player_id_for_receiver)
-                            .map(|state| state.value().username.clone())
-                            .unwrap_or_else(|| "Unknown".to_string());
-                        
-                        // Send response to challenger
-                        let challenge_response_msg = ServerMessage::ChallengeResponse { 
-                            target_player_id: player_id_for_receiver.clone(),
-                            target_username: responder_username,
-                            accepted,
+                    },
+                    Ok(ClientMessage::RenamePokemon { pokemon_id, name }) => {
+                        let npc_services = match state_for_tasks.npc_services.as_ref() {
+                            Some(npc_services) => npc_services,
+                            None => {
+                                error!("NPC services not found when renaming pokemon");
+                                continue;
+                            }
                         };
-                        
-                        if let Err(e) = lobby_for_receiver.send_to_player(&challenger_id, &challenge_response_msg).await {
-                            error!("Failed to send challenge response to challenger: {}", e);
-                            continue;
+                        let result = npc_services.rate_name(&lobby_for_receiver, &player_id_for_receiver, &pokemon_id, &name).await;
+                        send_npc_service_result(&state_for_tasks, &lobby_for_receiver, &player_id_for_receiver, result, NAME_RATER_FEE, TransactionReason::NameRater).await;
+                    },
+                    Ok(ClientMessage::GetRelearnableMoves { pokemon_id }) => {
+                        let npc_services = match state_for_tasks.npc_services.as_ref() {
+                            Some(npc_services) => npc_services,
+                            None => {
+                                error!("NPC services not found when listing relearnable moves");
+                                continue;
+                            }
+                        };
+                        let response = match npc_services.relearnable_moves(&player_id_for_receiver, &pokemon_id).await {
+                            Ok(moves) => ServerMessage::RelearnableMoves { pokemon_id, moves, fee: MOVE_RELEARNER_FEE },
+                            Err(e) => ServerMessage::Error { message: e },
+                        };
+                        if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &response).await {
+                            error!("Failed to send relearnable moves to player {}: {}", player_id_for_receiver, e);
                         }
-                        
-                        // If accepted, proceed to start the PvP battle
-                        if accepted {
-                            info!("Challenge accepted! Starting PvP battle between {} and {}", challenger_id, player_id_for_receiver);
-                            
-                            // Get the battle manager
-                            if let Some(battle_manager) = state_for_tasks.battle_manager.as_ref() {
-                                // Mark both players as in combat (temporarily) to prevent race conditions
-                                if let Some(mut challenger_state) = lobby_for_receiver.player_positions.get_mut(&challenger_id) {
-                                    challenger_state.value_mut().in_combat = true;
-                                }
-                                
-                                if let Some(mut responder_state) = lobby_for_receiver.player_positions.get_mut(&player_id_for_receiver) {
-                                    responder_state.value_mut().in_combat = true;
-                                }
-                                
-                                // Call the battle manager to start the battle
-                                match battle_manager.start_pvp_battle(
-                                    &challenger_id, 
-                                    &player_id_for_receiver, 
-                                    &lobby_for_receiver, 
-                                    state_for_tasks.pokemon_collection_manager.as_ref().unwrap()
-                                ).await {
-                                    Ok(battle_id) => {
-                                        info!("PvP battle {} successfully started", battle_id);
-                                        // Battle state is now managed by the battle manager
-                                    },
-                                    Err(e) => {
-                                        error!("Failed to start PvP battle: {}", e);
-                                        
-                                        // Send error message to both players
-                                        let error_msg = ServerMessage::Error {
-                                            message: format!("Failed to start battle: {}", e),
-                                        };
-                                        
-                                        if let Err(send_err) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
-                                            error!("Failed to send error message to responder: {}", send_err);
-                                        }
-                                        
-                                        if let Err(send_err) = lobby_for_receiver.send_to_player(&challenger_id, &error_msg).await {
-                                            error!("Failed to send error message to challenger: {}", send_err);
-                                        }
-                                        
-                                        // Reset combat status for both players
-                                        if let Some(mut challenger_state) = lobby_for_receiver.player_positions.get_mut(&challenger_id) {
-                                            challenger_state.value_mut().in_combat = false;
-                                        }
-                                        
-                                        if let Some(mut responder_state) = lobby_for_receiver.player_positions.get_mut(&player_id_for_receiver) {
-                                            responder_state.value_mut().in_combat = false;
-                                        }
-                                    }
-                                }
-                            } else {
-                                error!("Battle manager not available for PvP battle");
-                                
-                                // Send error message to both players
-                                let error_msg = ServerMessage::Error {
-                                    message: "Battle system unavailable".to_string(),
-                                };
-                                
-                                if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &error_msg).await {
-                                    error!("Failed to send error message to responder: {}", e);
-                                }
-                                
-                                if let Err(e) = lobby_for_receiver.send_to_player(&challenger_id, &error_msg).await {
-                                    error!("Failed to send error message to challenger: {}", e);
-                                }
-                                
-                                // Reset combat status for both players
-                                if let Some(mut challenger_state) = lobby_for_receiver.player_positions.get_mut(&challenger_id) {
-                                    challenger_state.value_mut().in_combat = false;
-                                }
-                                
-                                if let Some(mut responder_state) = lobby_for_receiver.player_positions.get_mut(&player_id_for_receiver) {
-                                    responder_state.value_mut().in_combat = false;
-                                }
+                    },
+                    Ok(ClientMessage::RelearnMove { pokemon_id, move_id, replace_move_index }) => {
+                        let npc_services = match state_for_tasks.npc_services.as_ref() {
+                            Some(npc_services) => npc_services,
+                            None => {
+                                error!("NPC services not found when relearning move");
+                                continue;
                             }
+                        };
+                        let result = npc_services.relearn_move(&lobby_for_receiver, &player_id_for_receiver, &pokemon_id, move_id, replace_move_index).await;
+                        send_npc_service_result(&state_for_tasks, &lobby_for_receiver, &player_id_for_receiver, result, MOVE_RELEARNER_FEE, TransactionReason::MoveRelearner).await;
+                    },
+                    Ok(ClientMessage::GetShopItems) => {
+                        let shop_msg = ServerMessage::ShopItems { items: shop_items() };
+                        if let Err(e) = lobby_for_receiver.send_to_player(&player_id_for_receiver, &shop_msg).await {
+                            error!("Failed to send shop items to player {}: {}", player_id_for_receiver, e);
                         }
                     },
+                    Ok(ClientMessage::BuyItem { item_id, quantity }) => {
+                        let npc_services = match state_for_tasks.npc_services.as_ref() {
+                            Some(npc_services) => npc_services,
+                            None => {
+                                error!("NPC services not found when buying an item");
+                                continue;
+                            }
+                        };
+                        let result = npc_services.buy_item(&lobby_for_receiver, &player_id_for_receiver, &item_id, quantity).await
+                            .map(|(balance, price)| (balance, -(price as i64)));
+                        send_shop_result(&state_for_tasks, &lobby_for_receiver, &player_id_for_receiver, result, TransactionReason::ShopPurchase).await;
+                    },
+                    Ok(ClientMessage::SellItem { item_id, quantity }) => {
+                        let npc_services = match state_for_tasks.npc_services.as_ref() {
+                            Some(npc_services) => npc_services,
+                            None => {
+                                error!("NPC services not found when selling an item");
+                                continue;
+                            }
+                        };
+                        let result = npc_services.sell_item(&lobby_for_receiver, &player_id_for_receiver, &item_id, quantity).await
+                            .map(|(balance, payout)| (balance, payout as i64));
+                        send_shop_result(&state_for_tasks, &lobby_for_receiver, &player_id_for_receiver, result, TransactionReason::ItemSold).await;
+                    },
                     Err(e) => {
                         tracing::error!("Failed to parse client message: {}", e);
+                        invalid_messages += 1;
+                        if invalid_messages >= MAX_INVALID_MESSAGES {
+                            warn!("Player {} sent {} invalid messages in a row, closing connection", player_id_for_receiver, invalid_messages);
+                            close_connection(&sender_for_receiver, CloseReason::ProtocolError).await;
+                            break;
+                        }
                     },
                 }
             }
@@ -602,6 +2709,11 @@ pub async fn handle_lobby_socket(socket: WebSocket, state: Arc<AppState>, lobby:
     // Player disconnected from lobby
     tracing::info!("Player disconnected from lobby {}: {}", lobby_for_forward.id, player_id_for_forward);
 
+    // A newer connection of the same player took over, and everything below now belongs to it
+    if connection_guard.replaced() {
+        return;
+    }
+
     // Check if player was in combat and clean up any active battles
     if let Some(player_state) = lobby_for_forward.player_positions.get(&player_id_for_forward) {
         if player_state.value().in_combat {
@@ -614,6 +2726,11 @@ pub async fn handle_lobby_socket(socket: WebSocket, state: Arc<AppState>, lobby:
                 
                 if let Some(pokemon_collection_manager) = state_for_disconnect.pokemon_collection_manager.as_ref() {
                     for battle_id in active_battles {
+                        // PvP battles wait for the player to reconnect before being decided
+                        if battle_manager.get_pvp_battle_state(battle_id).is_some() {
+                            battle_manager.handle_pvp_disconnect(battle_id, &player_id_for_forward, &lobby_for_forward, pokemon_collection_manager).await;
+                            continue;
+                        }
                         tracing::info!("Ending battle {} due to player disconnect", battle_id);
                         if let Err(e) = battle_manager.end_battle(battle_id, &lobby_for_forward, pokemon_collection_manager, true).await {
                             tracing::error!("Failed to end battle {} on player disconnect: {}", battle_id, e);
@@ -625,17 +2742,331 @@ pub async fn handle_lobby_socket(socket: WebSocket, state: Arc<AppState>, lobby:
     }
     info!("Player {} disconnected from lobby {}", player_id_for_forward, lobby_for_forward.id);
 
-    // Clean up player resources
-    lobby_for_forward.player_positions.remove(&player_id_for_forward);
-    lobby_for_forward.player_last_active.remove(&player_id_for_forward);
-    lobby_for_forward.player_connections.remove(&player_id_for_forward);
-    
-    // Notify other players about the disconnection
-    let leave_msg = ServerMessage::PlayerLeft { id: player_id_for_forward };
-    let _ = lobby_for_forward.tx.send(serde_json::to_string(&leave_msg).unwrap());
+    // Clean up player resources and notify other players about the disconnection
+    drop(connection_guard);
+
+    // Return anything the player had put into escrow for an unfinished trade
+    if let Some(trade_manager) = state_for_disconnect.trade_manager.as_ref() {
+        trade_manager.handle_disconnect(&lobby_for_forward, &player_id_for_forward).await;
+    }
+}
+
+// Make reads of messages over `max_message_bytes` fail while being read, before they are
+// buffered in full
+fn limit_message_size(ws: WebSocketUpgrade, max_message_bytes: usize) -> WebSocketUpgrade {
+    ws.max_message_size(max_message_bytes).max_frame_size(max_message_bytes)
+}
+
+// Whether a websocket read failed because the message exceeded the configured size limits
+fn is_message_too_large(error: axum::Error) -> bool {
+    matches!(error.into_inner().downcast_ref::<tungstenite::Error>(), Some(tungstenite::Error::Capacity(_)))
+}
+
+// Type of the crystal the player has equipped, for starting a battle
+async fn equipped_crystal_type(state: &Arc<AppState>, player_id: &str) -> Option<crate::monsters::PokemonType> {
+    let inventory_manager = state.inventory_manager.as_ref()?;
+    match inventory_manager.get_equipped_crystal(player_id).await {
+        Ok(item_id) => item_id.as_deref().and_then(game_loop::inventory::crystal_type),
+        Err(e) => {
+            error!("Failed to fetch equipped crystal for player {}: {}", player_id, e);
+            None
+        }
+    }
+}
+
+// The gym leader `gym_id` when the player stands close enough to talk to them
+fn gym_leader_in_reach<'a>(battle_manager: &'a BattleManager, lobby: &Lobby, player_id: &str, gym_id: &str) -> Result<&'a GymLeader, String> {
+    let gym = battle_manager.gyms().get(gym_id)
+        .ok_or_else(|| format!("Unknown gym {}", gym_id))?;
+    let player = lobby.player_positions.get(player_id)
+        .ok_or_else(|| format!("Player {} not found in lobby", player_id))?;
+    if !gym.is_within_reach(&lobby.map_id, player.x, player.y) {
+        return Err("Too far away from the gym leader".to_string());
+    }
+    Ok(gym)
+}
+
+// What the gym leader the player is standing next to offers them
+async fn gym_leader_offer(state: &Arc<AppState>, lobby: &Arc<Lobby>, player_id: &str, gym_id: &str) -> Result<GymOffer, String> {
+    let (Some(battle_manager), Some(badge_manager)) = (state.battle_manager.as_ref(), state.badge_manager.as_ref()) else {
+        return Err("Gyms are not available".to_string());
+    };
+    let gym = gym_leader_in_reach(battle_manager, lobby, player_id, gym_id)?;
+    badge_manager.gym_offer(player_id, gym).await
+}
+
+// Start a battle against the gym leader the player is standing next to, as a first challenge
+// or a rematch once the leader offers one
+async fn challenge_gym(state: &Arc<AppState>, lobby: &Arc<Lobby>, player_id: &str, gym_id: &str) -> Result<Uuid, String> {
+    let (Some(battle_manager), Some(pokemon_collection_manager), Some(badge_manager)) = (state.battle_manager.as_ref(), state.pokemon_collection_manager.as_ref(), state.badge_manager.as_ref()) else {
+        return Err("Gyms are not available".to_string());
+    };
+    let gym = gym_leader_in_reach(battle_manager, lobby, player_id, gym_id)?;
+    if lobby.player_positions.get(player_id).is_some_and(|player| player.in_combat) {
+        return Err("You are already in combat".to_string());
+    }
+    let tier = badge_manager.challenge_tier(player_id, gym).await?;
+    let crystal = equipped_crystal_type(state, player_id).await;
+    battle_manager.start_gym_battle(player_id, gym, tier, lobby, pokemon_collection_manager, crystal).await
+}
+
+// Why a block or the target's privacy settings turn away a challenge from the challenger, if they do
+async fn challenge_refusal(state: &Arc<AppState>, challenger_id: &str, target_id: &str) -> Option<&'static str> {
+    let blocked = state.block_list_manager.as_ref()
+        .is_some_and(|block_list_manager| block_list_manager.is_blocked_between(challenger_id, target_id));
+    if blocked {
+        return Some("You cannot challenge this player");
+    }
+
+    let player_settings_manager = state.player_settings_manager.as_ref()?;
+    let target_settings = match player_settings_manager.get_settings(target_id).await {
+        Ok(settings) => settings,
+        Err(e) => {
+            error!("Failed to fetch settings for player {}: {}", target_id, e);
+            return None;
+        }
+    };
+    // Friendship is only looked up when the target's policy depends on it
+    let friends = target_settings.accept_challenges == ChallengePolicy::Friends
+        && is_friend(state, challenger_id, target_id).await;
+    if target_settings.appear_offline {
+        Some("Target player not found")
+    } else if !target_settings.accepts_challenge_from(friends) {
+        Some("Target player is not accepting challenges")
+    } else {
+        None
+    }
+}
+
+// Whether two players are friends both ways; false when it cannot be checked
+async fn is_friend(state: &Arc<AppState>, player_a: &str, player_b: &str) -> bool {
+    let Some(friend_list_manager) = state.friend_list_manager.as_ref() else {
+        return false;
+    };
+    friend_list_manager.are_friends(player_a, player_b).await.unwrap_or_else(|e| {
+        error!("Failed to check friendship between {} and {}: {}", player_a, player_b, e);
+        false
+    })
+}
+
+// The player's tutorial step; players are treated as done with it when it cannot be loaded
+async fn tutorial_step(state: &Arc<AppState>, player_id: &str) -> TutorialStep {
+    let (Some(tutorial_manager), Some(pokemon_collection_manager)) = (state.tutorial_manager.as_ref(), state.pokemon_collection_manager.as_ref()) else {
+        return TutorialStep::Completed;
+    };
+    match tutorial_manager.get_step(player_id, pokemon_collection_manager).await {
+        Ok(step) => step,
+        Err(e) => {
+            error!("Failed to fetch tutorial step for player {}: {}", player_id, e);
+            TutorialStep::Completed
+        }
+    }
+}
+
+// Send the player's current inventory
+async fn send_inventory(state: &Arc<AppState>, lobby: &Arc<Lobby>, player_id: &str) {
+    let inventory_manager = match state.inventory_manager.as_ref() {
+        Some(inventory_manager) => inventory_manager,
+        None => {
+            error!("Inventory manager not found when sending inventory");
+            return;
+        }
+    };
+    let response = match inventory_manager.get_inventory(player_id).await {
+        Ok(items) => ServerMessage::Inventory { items },
+        Err(e) => ServerMessage::Error { message: e },
+    };
+    if let Err(e) = lobby.send_to_player(player_id, &response).await {
+        error!("Failed to send inventory to player {}: {}", player_id, e);
+    }
+}
+
+// Tell the player why a trade action failed
+async fn send_trade_error(lobby: &Arc<Lobby>, player_id: &str, result: Result<(), String>) {
+    if let Err(e) = result {
+        let error_msg = ServerMessage::Error { message: format!("Trade failed: {}", e) };
+        if let Err(send_err) = lobby.send_to_player(player_id, &error_msg).await {
+            error!("Failed to send error message to player {}: {}", player_id, send_err);
+        }
+    }
+}
+
+// Send the player's block list after a change, or the error if it failed
+async fn send_block_list(lobby: &Arc<Lobby>, player_id: &str, result: Result<std::collections::HashSet<String>, String>) {
+    let msg = match result {
+        Ok(blocked) => ServerMessage::BlockList { player_ids: blocked.into_iter().collect() },
+        Err(e) => ServerMessage::Error { message: e },
+    };
+    if let Err(e) = lobby.send_to_player(player_id, &msg).await {
+        error!("Failed to send block list to player {}: {}", player_id, e);
+    }
+}
+
+// Send the player's friend list after a change, or the error if it failed
+async fn send_friend_list(lobby: &Arc<Lobby>, player_id: &str, result: Result<FriendList, String>) {
+    let msg = match result {
+        Ok(FriendList { friends, pending }) => ServerMessage::FriendList { friends, pending },
+        Err(e) => ServerMessage::Error { message: e },
+    };
+    if let Err(e) = lobby.send_to_player(player_id, &msg).await {
+        error!("Failed to send friend list to player {}: {}", player_id, e);
+    }
+}
+
+// Send the player's balance, inventory and team after they changed outside of a battle
+async fn send_holdings(state: &Arc<AppState>, lobby: &Arc<Lobby>, player_id: &str) {
+    if let Some(currency_manager) = state.currency_manager.as_ref() {
+        match currency_manager.get_balance(player_id).await {
+            Ok(balance) => {
+                let balance_msg = ServerMessage::BalanceUpdate { balance, delta: 0, reason: None };
+                if let Err(e) = lobby.send_to_player(player_id, &balance_msg).await {
+                    error!("Failed to send balance update to player {}: {}", player_id, e);
+                }
+            }
+            Err(e) => error!("Failed to fetch balance for player {}: {}", player_id, e),
+        }
+    }
+
+    send_inventory(state, lobby, player_id).await;
+
+    if let Some(pokemon_collection_manager) = state.pokemon_collection_manager.as_ref() {
+        if let Ok(pokemons) = pokemon_collection_manager.get_active_pokemons(player_id).await {
+            let active_pokemons_msg = ServerMessage::ActivePokemons {
+                pokemons: pokemons.iter().map(|p| pokemon_collection_manager.pokemon_to_display_pokemon(p)).collect()
+            };
+            if let Err(e) = lobby.send_to_player(player_id, &active_pokemons_msg).await {
+                error!("Failed to send pokemon collection to player {}: {}", player_id, e);
+            }
+        }
+    }
+}
+
+// Report the outcome of buying or selling at the shop: the balance change and new inventory, or the error
+async fn send_shop_result(
+    state: &Arc<AppState>,
+    lobby: &Arc<Lobby>,
+    player_id: &str,
+    result: Result<(u64, i64), String>,
+    reason: TransactionReason,
+) {
+    let (balance, delta) = match result {
+        Ok(change) => change,
+        Err(e) => {
+            let error_msg = ServerMessage::Error { message: e };
+            if let Err(send_err) = lobby.send_to_player(player_id, &error_msg).await {
+                error!("Failed to send error message to player {}: {}", player_id, send_err);
+            }
+            return;
+        }
+    };
+
+    let balance_msg = ServerMessage::BalanceUpdate { balance, delta, reason: Some(reason) };
+    if let Err(e) = lobby.send_to_player(player_id, &balance_msg).await {
+        error!("Failed to send balance update to player {}: {}", player_id, e);
+    }
+    send_inventory(state, lobby, player_id).await;
+}
+
+// Report the outcome of a paid NPC service: the new balance and refreshed team, or the error
+async fn send_npc_service_result(
+    state: &Arc<AppState>,
+    lobby: &Arc<Lobby>,
+    player_id: &str,
+    result: Result<u64, String>,
+    fee: u64,
+    reason: TransactionReason,
+) {
+    let balance = match result {
+        Ok(balance) => balance,
+        Err(e) => {
+            let error_msg = ServerMessage::Error { message: e };
+            if let Err(send_err) = lobby.send_to_player(player_id, &error_msg).await {
+                error!("Failed to send error message to player {}: {}", player_id, send_err);
+            }
+            return;
+        }
+    };
+
+    let balance_msg = ServerMessage::BalanceUpdate { balance, delta: -(fee as i64), reason: Some(reason) };
+    if let Err(e) = lobby.send_to_player(player_id, &balance_msg).await {
+        error!("Failed to send balance update to player {}: {}", player_id, e);
+    }
+
+    if let Some(pokemon_collection_manager) = state.pokemon_collection_manager.as_ref() {
+        if let Ok(pokemons) = pokemon_collection_manager.get_active_pokemons(player_id).await {
+            let active_pokemons_msg = ServerMessage::ActivePokemons {
+                pokemons: pokemons.iter().map(|p| pokemon_collection_manager.pokemon_to_display_pokemon(p)).collect()
+            };
+            if let Err(e) = lobby.send_to_player(player_id, &active_pokemons_msg).await {
+                error!("Failed to send pokemon collection to player {}: {}", player_id, e);
+            }
+        }
+    }
 }
 
 // Handler for player interacting with a monster to start combat
+// Send a player their whole mailbox
+async fn send_mailbox(state: &Arc<AppState>, lobby: &Arc<Lobby>, player_id: &str) {
+    let Some(mail_manager) = &state.mail_manager else {
+        return;
+    };
+    let response = match mail_manager.get_mailbox(player_id).await {
+        Ok(mails) => {
+            let unread_count = mails.iter().filter(|mail| !mail.read).count();
+            ServerMessage::Mailbox { mails, unread_count }
+        }
+        Err(e) => ServerMessage::Error { message: format!("Failed to load mailbox: {}", e) },
+    };
+    if let Err(e) = lobby.send_to_player(player_id, &response).await {
+        error!("Failed to send mailbox to player {}: {}", player_id, e);
+    }
+}
+
+// How far away (in tiles) a player can examine a monster from
+const EXAMINE_RANGE: u32 = 6;
+
+// Build the public preview of a monster near the player without starting a battle
+async fn examine_monster(lobby: &Arc<Lobby>, player_id: &str, monster_id: &str) -> Result<MonsterPreview, String> {
+    let (player_x, player_y) = match lobby.player_positions.get(player_id) {
+        Some(player) => (player.x, player.y),
+        None => return Err("Player not found in lobby".to_string()),
+    };
+    let monster_mutex = match lobby.active_monsters.get(monster_id) {
+        Some(entry) => entry.value().clone(),
+        None => return Err("Monster not found".to_string()),
+    };
+    let monster = monster_mutex.lock().await;
+    if monster.despawning {
+        return Err("Monster not found".to_string());
+    }
+    if monster.position.x.abs_diff(player_x) + monster.position.y.abs_diff(player_y) > EXAMINE_RANGE {
+        return Err("Monster is too far away to examine".to_string());
+    }
+    let catch_rate = lobby.monster_manager.template_repository.templates.get(&monster.template_id)
+        .map_or(DEFAULT_CATCH_RATE, |template| template.catch_rate);
+    Ok(monster.to_preview(catch_rate))
+}
+
+// Take a PP item from the player's inventory and apply it to a move, refunding it if that fails
+async fn use_pp_item(state: &Arc<AppState>, player_id: &str, item_id: &str, pokemon_id: &str, move_index: usize) -> Result<(), String> {
+    let pp_ups = pp_ups_for_item(item_id).ok_or_else(|| format!("{} cannot be used on a move", item_id))?;
+    let pokemon_collection_manager = state.pokemon_collection_manager.as_ref().ok_or("Pokemon collection manager not available")?;
+    let inventory_manager = state.inventory_manager.as_ref().ok_or("Inventory manager not available")?;
+
+    // Check the cap before taking the item
+    if pokemon_collection_manager.pp_ups_remaining(player_id, pokemon_id, move_index).await? == 0 {
+        return Err("This move's PP can't go any higher".to_string());
+    }
+    inventory_manager.remove_item(player_id, item_id, 1).await?;
+    if let Err(e) = pokemon_collection_manager.apply_pp_ups(player_id, pokemon_id, move_index, pp_ups).await {
+        if let Err(refund_err) = inventory_manager.add_item(player_id, item_id, 1).await {
+            error!("Failed to refund {} to player {}: {}", item_id, player_id, refund_err);
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
 pub async fn handle_player_interaction(
     state: &Arc<AppState>,
     lobby_id: &str,
@@ -700,7 +3131,9 @@ pub async fn handle_player_interaction(
     };
     
     // Start the wild battle
-    match battle_manager.start_wild_battle(player_id, &monster_instance_id, &lobby, pokemon_collection_manager).await {
+    let crystal = equipped_crystal_type(state, player_id).await;
+    let scripted = tutorial_step(state, player_id).await == TutorialStep::FirstBattle;
+    match battle_manager.start_wild_battle(player_id, &monster_instance_id, &lobby, pokemon_collection_manager, crystal, scripted).await {
         Ok(battle_id) => {
             tracing::info!("Started wild battle {} between player {} and monster {}", 
                 battle_id, player_id, monster_instance_id);
@@ -745,8 +3178,8 @@ async fn find_monsters_near_player(
     for monster_mutex in lobby_monsters {
         // Try to lock the monster to check its properties
         if let Ok(monster) = monster_mutex.try_lock() {
-            // Skip monsters in combat
-            if monster.in_combat {
+            // Skip monsters in combat or on their way out of the world
+            if !monster.is_available() {
                 continue;
             }
             
@@ -773,3 +3206,62 @@ async fn find_monsters_near_player(
     
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{connected_socket, ClientSocket};
+    use tokio_tungstenite::tungstenite;
+
+    const MAX_MESSAGE_BYTES: usize = 1024;
+
+    // Server end that reads messages the way a player's connection task does, replying with
+    // the size of every text message it accepts
+    async fn serve_reads(socket: WebSocket) {
+        let (sender, mut receiver) = socket.split();
+        let sender = Mutex::new(sender);
+        while let Some(result) = receiver.next().await {
+            match result {
+                Ok(Message::Text(text)) => {
+                    let _ = sender.lock().await.send(Message::Text(text.len().to_string().into())).await;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    if is_message_too_large(e) {
+                        close_connection(&sender, CloseReason::MessageTooLarge).await;
+                    }
+                    break;
+                }
+            }
+        }
+    }
+
+    async fn limited_connection() -> ClientSocket {
+        let (socket, client) = connected_socket(|ws| limit_message_size(ws, MAX_MESSAGE_BYTES)).await;
+        tokio::spawn(serve_reads(socket));
+        client
+    }
+
+    async fn send_text(client: &mut ClientSocket, len: usize) -> tungstenite::Message {
+        client.send(tungstenite::Message::text("x".repeat(len))).await.expect("client sends");
+        client.next().await.expect("server replies").expect("reply is readable")
+    }
+
+    #[tokio::test]
+    async fn message_of_exactly_the_limit_is_accepted() {
+        let mut client = limited_connection().await;
+        let reply = send_text(&mut client, MAX_MESSAGE_BYTES).await;
+        assert_eq!(reply, tungstenite::Message::text(MAX_MESSAGE_BYTES.to_string()));
+    }
+
+    #[tokio::test]
+    async fn message_one_byte_over_the_limit_closes_the_connection() {
+        let mut client = limited_connection().await;
+        let reply = send_text(&mut client, MAX_MESSAGE_BYTES + 1).await;
+        let tungstenite::Message::Close(Some(frame)) = reply else {
+            panic!("expected a close frame, got {:?}", reply);
+        };
+        assert_eq!(u16::from(frame.code), CloseReason::MessageTooLarge.code());
+        assert_eq!(frame.reason.as_str(), CloseReason::MessageTooLarge.reason());
+    }
+}
@@ -8,4 +8,10 @@ pub mod redis_manager;
 pub mod monsters;
 pub mod game_loop;
 pub mod stats;
-pub mod combat;
\ No newline at end of file
+pub mod combat;
+pub mod schema;
+pub mod protocol;
+#[cfg(test)]
+mod test_support;
+#[cfg(feature = "graphql")]
+pub mod graphql;
\ No newline at end of file
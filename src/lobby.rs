@@ -1,7 +1,22 @@
 use crate::app_state::AppState;
+use crate::combat::state::BattleRules;
+use crate::game_loop::berries::BerryPlot;
+use crate::game_loop::catch_chain::CatchChains;
+use crate::game_loop::lobby_buffs::LobbyBuffs;
+use crate::game_loop::item_pickups::ItemPickups;
+use crate::game_loop::latency::LatencyTracker;
+use crate::game_loop::lobby_events::{LobbyEventKind, LobbyEventLog};
+use crate::game_loop::occupancy::{MonsterCollision, TileOccupancy};
+use crate::game_loop::monster_spawner::RespawnCooldowns;
+use crate::game_loop::weather::OverworldWeather;
 use crate::models::{PlayerState, ServerMessage};
+use crate::protocol::{downgrade_to_v1, ClientProtocol, CloseReason};
 use crate::monsters::monster_manager::MonsterManager;
+#[cfg(test)]
+use crate::monsters::monster_manager::{MapData, MonsterTemplateRepository};
 use crate::monsters::Monster;
+use crate::monsters::locales::{Locales, DEFAULT_LOCALE};
+use std::collections::HashSet;
 use std::sync::Arc;
 use dashmap::DashMap;
 use tokio::sync::{broadcast, Mutex};
@@ -10,27 +25,177 @@ use tokio::time::Duration;
 use regex::Regex;
 use axum::extract::ws::Message;
 use futures_util::{SinkExt, stream::SplitSink};
-use axum::extract::ws::{Utf8Bytes, WebSocket};
+use axum::extract::ws::{CloseFrame, Utf8Bytes, WebSocket};
+
+// Send a close frame telling the client why the server is ending its connection
+pub async fn close_connection(sender: &Mutex<SplitSink<WebSocket, Message>>, reason: CloseReason) {
+    let frame = CloseFrame { code: reason.code(), reason: Utf8Bytes::from_static(reason.reason()) };
+    if let Err(e) = sender.lock().await.send(Message::Close(Some(frame))).await {
+        tracing::debug!("Failed to send close frame ({}): {}", reason.reason(), e);
+    }
+}
+
+// Removes a connected player from the lobby when dropped, so an early return, a panic or
+// a cancelled connection task cannot leave a ghost behind. Does nothing once a newer
+// connection of the same player has taken over.
+pub struct LobbyConnectionGuard {
+    state: Arc<AppState>,
+    lobby: Arc<Lobby>,
+    player_id: String,
+    sender: Arc<Mutex<SplitSink<WebSocket, Message>>>,
+}
+
+impl LobbyConnectionGuard {
+    pub fn new(state: Arc<AppState>, lobby: Arc<Lobby>, player_id: String, sender: Arc<Mutex<SplitSink<WebSocket, Message>>>) -> Self {
+        Self { state, lobby, player_id, sender }
+    }
+
+    // Whether a newer connection of the same player replaced this one
+    pub fn replaced(&self) -> bool {
+        self.lobby.player_connections.get(&self.player_id)
+            .is_some_and(|current| !Arc::ptr_eq(current.value(), &self.sender))
+    }
+}
+
+impl Drop for LobbyConnectionGuard {
+    fn drop(&mut self) {
+        if self.replaced() {
+            return;
+        }
+        let (state, lobby, player_id) = (&self.state, &self.lobby, &self.player_id);
+        lobby.player_positions.remove(player_id);
+        lobby.player_last_active.remove(player_id);
+        lobby.player_last_input.remove(player_id);
+        lobby.player_connections.remove(player_id);
+        lobby.latency.remove(player_id);
+        lobby.player_locales.remove(player_id);
+        lobby.player_protocols.remove(player_id);
+        lobby.cleared_gates.remove(player_id);
+        lobby.pending_challenges.retain(|(challenger_id, target_id), _| challenger_id != player_id && target_id != player_id);
+        lobby.catch_chains.reset(player_id);
+        state.player_index.remove(player_id);
+        if let Some(player_movement_manager) = state.player_movement_manager.as_ref() {
+            player_movement_manager.remove_player(player_id);
+        }
+        if let Some(fast_travel_manager) = state.fast_travel_manager.as_ref() {
+            fast_travel_manager.forget_player(player_id);
+        }
+        if let Some(emote_manager) = state.emote_manager.as_ref() {
+            emote_manager.forget_player(player_id);
+        }
+        if let Some(block_list_manager) = state.block_list_manager.as_ref() {
+            block_list_manager.forget_player(player_id);
+        }
+        if let Some(friend_list_manager) = state.friend_list_manager.as_ref() {
+            friend_list_manager.forget_player(player_id);
+        }
+        if let Some(battle_chat_manager) = state.battle_chat_manager.as_ref() {
+            battle_chat_manager.forget_player(player_id);
+        }
+        if let Some(pokemon_collection_manager) = state.pokemon_collection_manager.as_ref() {
+            pokemon_collection_manager.release_pending_capture(player_id);
+        }
+        if let Some(battle_manager) = state.battle_manager.as_ref() {
+            battle_manager.abandon_draft(player_id, lobby);
+            battle_manager.set_damage_breakdown(player_id, false);
+            battle_manager.set_effectiveness_hints(player_id, false);
+            battle_manager.set_obedience_level_cap(player_id, None);
+        }
+        lobby.events.record(LobbyEventKind::PlayerLeft { player_id: player_id.clone(), reason: "disconnected" });
+
+        // Notify other players about the disconnection
+        let leave_msg = ServerMessage::PlayerLeft { id: player_id.clone() };
+        let _ = lobby.tx.send(serde_json::to_string(&leave_msg).unwrap());
+    }
+}
 
 // Lobby struct representing a game lobby
 pub struct Lobby {
     pub id: String,
     pub player_positions: DashMap<String, PlayerState>,
     pub player_last_active: DashMap<String, Instant>,
+    pub player_last_input: DashMap<String, Instant>, // Player ID → last message other than a ping, for AFK detection
     pub tx: broadcast::Sender<String>,
     pub map_id: String,  // Map ID for this lobby
     pub active_monsters: DashMap<String, Arc<Mutex<Monster>>>, // Monster instance ID → Monster
     pub monsters_by_spawn_point: DashMap<String, Vec<String>>, // Spawn point ID → Monster IDs
     pub monster_manager: Arc<MonsterManager>, // Lobby-specific monster manager
     pub player_connections: DashMap<String, Arc<tokio::sync::Mutex<SplitSink<WebSocket, Message>>>>, // Player ID → WebSocket sender
+    pub berry_plots: DashMap<(u32, u32), BerryPlot>, // Tile position → planted berry
+    pub weather: std::sync::RwLock<OverworldWeather>, // Current overworld weather of this lobby's map
+    pub respawn_cooldowns: RespawnCooldowns, // Spawn points / species recently emptied by captures and defeats
+    pub catch_chains: CatchChains, // Player ID → consecutive captures of one species
+    pub buffs: LobbyBuffs, // Temporary spawn boosts from incense and live events
+    pub item_pickups: ItemPickups, // Map items picked up and waiting to respawn
+    pub occupancy: TileOccupancy, // Tiles taken by monsters
+    pub monster_collision: MonsterCollision, // How players and monsters collide in this lobby
+    pub latency: LatencyTracker, // Player ID → measured round trip times
+    pub events: LobbyEventLog, // Recent joins, leaves, battles and spawns, for debugging
+    pub locales: Arc<Locales>, // Translated species and move names
+    pub player_locales: DashMap<String, String>, // Player ID → chosen locale, for players not using English
+    pub player_protocols: DashMap<String, ClientProtocol>, // Player ID → protocol version and capabilities of their connection
+    pub cleared_gates: DashMap<String, HashSet<String>>, // Player ID → gates on this map they cleared with a key item or badge
+    pub pending_challenges: DashMap<(String, String), PendingChallenge>, // (Challenger ID, target ID) → challenge awaiting an answer
 } 
 
+// A challenge sent to another player, kept until they answer so the battle is played on the challenger's terms
+#[derive(Debug, Clone, Copy)]
+pub struct PendingChallenge {
+    pub rules: BattleRules,
+    pub challenger_rental: bool,
+}
+
 impl Lobby {
-    // Send a message to a specific player in the lobby
+    // Current overworld weather
+    pub fn weather(&self) -> OverworldWeather {
+        *self.weather.read().unwrap()
+    }
+
+    // Whether a gate the player hasn't cleared covers the tile
+    pub fn is_gated_for(&self, player_id: &str, tile_x: u32, tile_y: u32) -> bool {
+        self.monster_manager.map_data.gate_at(tile_x, tile_y).is_some_and(|gate| {
+            !self.cleared_gates.get(player_id).is_some_and(|cleared| cleared.contains(&gate.id))
+        })
+    }
+
+    // Show species and move names to a player in `locale`; None or English turns translation off
+    pub fn set_player_locale(&self, player_id: &str, locale: Option<String>) {
+        match locale {
+            Some(locale) if locale != DEFAULT_LOCALE && self.locales.is_supported(&locale) => {
+                self.player_locales.insert(player_id.to_string(), locale);
+            }
+            _ => {
+                self.player_locales.remove(player_id);
+            }
+        }
+    }
+
+    // Whether the player's client predates the reshaped battle messages.
+    // Players without a recorded protocol are treated like clients that left the version out.
+    pub fn is_legacy_client(&self, player_id: &str) -> bool {
+        self.player_protocols.get(player_id).is_none_or(|protocol| protocol.is_legacy())
+    }
+
+    // Send a message to a specific player in the lobby, translated into their locale and
+    // shaped for their protocol version
     pub async fn send_to_player(&self, player_id: &str, message: &ServerMessage) -> Result<(), String> {
         if let Some(sender) = self.player_connections.get(player_id) {
-            let message_json = serde_json::to_string(message)
-                .map_err(|e| format!("Failed to serialize message: {}", e))?;
+            let locale = self.player_locales.get(player_id).map(|locale| locale.clone());
+            let legacy = self.is_legacy_client(player_id);
+            let message_json = if locale.is_some() || legacy {
+                let mut value = serde_json::to_value(message)
+                    .map_err(|e| format!("Failed to serialize message: {}", e))?;
+                if let Some(locale) = &locale {
+                    self.locales.localize_json(locale, &mut value, &self.monster_manager.template_repository);
+                }
+                if legacy {
+                    downgrade_to_v1(&mut value);
+                }
+                value.to_string()
+            } else {
+                serde_json::to_string(message)
+                    .map_err(|e| format!("Failed to serialize message: {}", e))?
+            };
             
             let mut sender_lock = sender.lock().await;
             sender_lock.send(Message::Text(Utf8Bytes::from(message_json)))
@@ -42,6 +207,64 @@ impl Lobby {
         }
     }
     
+    // Note that the player did something. Returns true if they were AFK until now.
+    pub fn record_input(&self, player_id: &str) -> bool {
+        self.player_last_input.insert(player_id.to_string(), Instant::now());
+        match self.player_positions.get_mut(player_id) {
+            Some(mut player_state) if player_state.afk => {
+                player_state.afk = false;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Tell everyone in the lobby that a player went AFK or came back
+    pub fn broadcast_afk(&self, player_id: &str, afk: bool) {
+        let afk_msg = ServerMessage::PlayerAfk { id: player_id.to_string(), afk };
+        let _ = self.tx.send(serde_json::to_string(&afk_msg).unwrap());
+    }
+
+    // Tell everyone in the lobby which buffs are running and for how long
+    pub fn broadcast_buffs(&self) {
+        let buffs_msg = ServerMessage::LobbyBuffs { buffs: self.buffs.active() };
+        let _ = self.tx.send(serde_json::to_string(&buffs_msg).unwrap());
+    }
+
+    // Close a player's connection; its handler then cleans up after them like any other disconnect
+    pub async fn close_player(&self, player_id: &str, reason: CloseReason) {
+        let sender = match self.player_connections.get(player_id) {
+            Some(sender) => sender.value().clone(),
+            None => return,
+        };
+        close_connection(&sender, reason).await;
+    }
+
+    // Close every connection in the lobby
+    pub async fn close_all(&self, reason: CloseReason) {
+        let senders: Vec<_> = self.player_connections.iter().map(|entry| entry.value().clone()).collect();
+        for sender in senders {
+            close_connection(&sender, reason).await;
+        }
+    }
+
+    // Spawn point a monster was spawned at
+    pub fn spawn_point_of(&self, monster_id: &str) -> Option<String> {
+        self.monsters_by_spawn_point
+            .iter()
+            .find(|entry| entry.value().iter().any(|id| id == monster_id))
+            .map(|entry| entry.key().clone())
+    }
+
+    // IDs of players within `radius` tiles of a position
+    pub fn players_near(&self, x: u32, y: u32, radius: u32) -> Vec<String> {
+        self.player_positions
+            .iter()
+            .filter(|entry| entry.value().x.abs_diff(x) <= radius && entry.value().y.abs_diff(y) <= radius)
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+
     // Broadcast a message to all players in the lobby except for specific players
     pub async fn broadcast_except(&self, message: &ServerMessage, exclude_player_ids: &[&str]) -> Result<(), String> {
         let message_json = serde_json::to_string(message)
@@ -52,6 +275,45 @@ impl Lobby {
     }
 }
 
+#[cfg(test)]
+impl Lobby {
+    // Empty lobby on a map built in memory, with no monster templates loaded
+    pub(crate) fn for_tests(map_data: MapData) -> Self {
+        let template_repository = Arc::new(MonsterTemplateRepository {
+            templates: std::collections::HashMap::new(),
+            move_repository: None,
+            ability_repository: None,
+        });
+        Lobby {
+            id: "TEST-0000".to_string(),
+            player_positions: DashMap::new(),
+            player_last_active: DashMap::new(),
+            player_last_input: DashMap::new(),
+            tx: broadcast::channel(16).0,
+            map_id: map_data.map_id.clone(),
+            active_monsters: DashMap::new(),
+            monsters_by_spawn_point: DashMap::new(),
+            monster_manager: Arc::new(MonsterManager { template_repository, map_data }),
+            player_connections: DashMap::new(),
+            berry_plots: DashMap::new(),
+            weather: std::sync::RwLock::new(OverworldWeather::default()),
+            respawn_cooldowns: RespawnCooldowns::new(0, 0),
+            catch_chains: CatchChains::default(),
+            buffs: LobbyBuffs::default(),
+            item_pickups: ItemPickups::default(),
+            occupancy: TileOccupancy::default(),
+            monster_collision: MonsterCollision::default(),
+            latency: LatencyTracker::default(),
+            events: LobbyEventLog::default(),
+            locales: Arc::new(Locales::default()),
+            player_locales: DashMap::new(),
+            player_protocols: DashMap::new(),
+            cleared_gates: DashMap::new(),
+            pending_challenges: DashMap::new(),
+        }
+    }
+}
+
 // Create a new lobby or get an existing one
 // pub async fn get_or_create_lobby(state: &Arc<AppState>, lobby_id: &str, map_id: Option<&str>) -> Arc<Lobby> {
 //     let map_id_str = map_id.unwrap_or("map1").to_string();
@@ -111,6 +373,13 @@ pub fn get_lobby(state: &Arc<AppState>, lobby_id: &str) -> Option<Arc<Lobby>> {
     state.lobbies.get(lobby_id).map(|lobby_ref| lobby_ref.clone())
 }
 
+// Find the lobby a player is currently connected to, if any
+pub fn find_player_lobby(lobbies: &DashMap<String, Arc<Lobby>>, player_id: &str) -> Option<Arc<Lobby>> {
+    lobbies.iter()
+        .find(|entry| entry.value().player_connections.contains_key(player_id))
+        .map(|entry| entry.value().clone())
+}
+
 // Validate lobby ID format
 pub fn validate_lobby_id(lobby_id: &str) -> bool {
     let re = Regex::new(r"^[A-Z0-9]{4}-[A-Z0-9]{4}$").unwrap();
@@ -148,27 +417,206 @@ pub async fn cleanup_inactive_lobbies(state: &Arc<AppState>) {
             
             // Before removing the player from memory, make sure their state is persisted
             if let Some(player_state) = lobby.player_positions.get(&player_id) {
-                // Connect to Redis to save player state
-                if let Ok(mut redis_conn) = state.redis.get_async_connection().await {
-                    let state_json = serde_json::to_string(player_state.value()).unwrap_or_default();
-                    let _ = crate::redis_manager::store_player_state(
-                        &mut redis_conn,
-                        &lobby.id,
-                        &player_id,
-                        &state_json,
-                    ).await.unwrap_or_else(|e| {
-                        tracing::error!("Failed to persist player state during cleanup: {}", e);
-                    });
+                // Save player state, queueing it if Redis is down
+                let state_json = serde_json::to_string(player_state.value()).unwrap_or_default();
+                drop(player_state);
+                if state.persistence.store_player_state(&state.redis, &lobby.id, &player_id, &state_json).await {
+                    broadcast_service_status(state, false);
                 }
             }
             
             lobby.player_positions.remove(&player_id);
             lobby.player_last_active.remove(&player_id);
-            lobby.player_connections.remove(&player_id);
+            lobby.player_last_input.remove(&player_id);
+            if let Some((_, sender)) = lobby.player_connections.remove(&player_id) {
+                close_connection(&sender, CloseReason::Kicked).await;
+            }
+            lobby.catch_chains.reset(&player_id);
+            lobby.latency.remove(&player_id);
+            state.player_index.remove(&player_id);
+            if let Some(player_movement_manager) = state.player_movement_manager.as_ref() {
+                player_movement_manager.remove_player(&player_id);
+            }
+            lobby.events.record(LobbyEventKind::PlayerLeft { player_id: player_id.clone(), reason: "inactive" });
 
             // Notify other players in the lobby
             let leave_msg = ServerMessage::PlayerLeft { id: player_id };
             let _ = lobby.tx.send(serde_json::to_string(&leave_msg).unwrap());
         }
     }
-} 
\ No newline at end of file
+
+    // Movement records of players who left without a clean disconnect
+    if let Some(player_movement_manager) = state.player_movement_manager.as_ref() {
+        player_movement_manager.prune(timeout);
+    }
+}
+
+// Mark players who only sent pings for a while as AFK, and disconnect those idle past the idle timeout
+pub async fn update_afk_players(state: &Arc<AppState>) {
+    let now = Instant::now();
+    let afk_after = Duration::from_secs(state.config.game.afk_after_sec);
+    let idle_timeout = match state.config.game.idle_timeout_sec {
+        0 => None,
+        timeout => Some(Duration::from_secs(timeout)),
+    };
+
+    let lobbies: Vec<Arc<Lobby>> = state.lobbies.iter().map(|entry| entry.value().clone()).collect();
+    for lobby in lobbies {
+        let idle_players: Vec<(String, Duration)> = lobby
+            .player_last_input
+            .iter()
+            .map(|entry| (entry.key().clone(), now.duration_since(*entry.value())))
+            .filter(|(_, idle)| *idle >= afk_after)
+            .collect();
+
+        for (player_id, idle) in idle_players {
+            if idle_timeout.is_some_and(|timeout| idle >= timeout) {
+                tracing::info!("Disconnecting idle player {} from lobby {}", player_id, lobby.id);
+                lobby.close_player(&player_id, CloseReason::IdleTimeout).await;
+                continue;
+            }
+            let went_afk = match lobby.player_positions.get_mut(&player_id) {
+                Some(mut player_state) if !player_state.afk => {
+                    player_state.afk = true;
+                    true
+                }
+                _ => false,
+            };
+            if went_afk {
+                lobby.broadcast_afk(&player_id, true);
+            }
+        }
+    }
+}
+
+// Tell every connected player whether their progress is currently being saved
+pub fn broadcast_service_status(state: &Arc<AppState>, available: bool) {
+    let msg = if available {
+        ServerMessage::ServiceRestored
+    } else {
+        ServerMessage::ServiceUnavailable {
+            message: "Saving is temporarily unavailable. You can keep playing; progress will be saved once the service recovers.".to_string(),
+        }
+    };
+    let json = serde_json::to_string(&msg).unwrap();
+    for lobby in state.lobbies.iter() {
+        let _ = lobby.tx.send(json.clone());
+    }
+}
+
+// Periodically write out player states queued during a Redis outage
+pub async fn run_persistence_recovery(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(5));
+    loop {
+        interval.tick().await;
+        if state.persistence.flush(&state.redis).await {
+            broadcast_service_status(&state, true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::game_loop::player_movement::PlayerMovementManager;
+    use crate::test_support::{connected_socket, ClientSocket};
+    use futures_util::StreamExt;
+
+    const PLAYER_ID: &str = "player";
+
+    fn test_state() -> Arc<AppState> {
+        let redis_client = redis::Client::open("redis://127.0.0.1/").expect("valid redis URL");
+        AppState::new(redis_client, Config::default()).with_player_movement_manager(Arc::new(PlayerMovementManager::new()))
+    }
+
+    fn test_lobby() -> Arc<Lobby> {
+        Arc::new(Lobby::for_tests(MapData::for_tests(4, 4, &[])))
+    }
+
+    type Sender = Arc<Mutex<SplitSink<WebSocket, Message>>>;
+
+    async fn connection() -> (Sender, ClientSocket) {
+        let (socket, client) = connected_socket(|ws| ws).await;
+        let (sender, _receiver) = socket.split();
+        (Arc::new(Mutex::new(sender)), client)
+    }
+
+    // Register a connected player everywhere the join handler does
+    fn join(state: &Arc<AppState>, lobby: &Lobby, sender: &Sender) {
+        let player_state = PlayerState {
+            id: PLAYER_ID.to_string(),
+            username: PLAYER_ID.to_string(),
+            x: 1,
+            y: 1,
+            direction: "down".to_string(),
+            in_combat: false,
+            afk: false,
+        };
+        lobby.player_positions.insert(PLAYER_ID.to_string(), player_state.clone());
+        lobby.player_last_active.insert(PLAYER_ID.to_string(), Instant::now());
+        lobby.player_connections.insert(PLAYER_ID.to_string(), sender.clone());
+        state.player_index.insert(PLAYER_ID, PLAYER_ID, &lobby.id);
+        state.player_movement_manager.as_ref().unwrap().register_movement(PLAYER_ID.to_string(), player_state);
+    }
+
+    fn assert_purged(state: &AppState, lobby: &Lobby) {
+        assert!(!lobby.player_positions.contains_key(PLAYER_ID));
+        assert!(!lobby.player_last_active.contains_key(PLAYER_ID));
+        assert!(!lobby.player_connections.contains_key(PLAYER_ID));
+        assert!(state.player_index.get(PLAYER_ID).is_none());
+        assert!(!state.player_movement_manager.as_ref().unwrap().is_tracking(PLAYER_ID));
+    }
+
+    #[tokio::test]
+    async fn guard_purges_player_when_its_task_panics() {
+        let (state, lobby) = (test_state(), test_lobby());
+        let (sender, _client) = connection().await;
+        join(&state, &lobby, &sender);
+
+        let guard = LobbyConnectionGuard::new(state.clone(), lobby.clone(), PLAYER_ID.to_string(), sender);
+        let task = tokio::spawn(async move {
+            let _guard = guard;
+            panic!("connection task crashed");
+        });
+        assert!(task.await.unwrap_err().is_panic());
+        assert_purged(&state, &lobby);
+    }
+
+    #[tokio::test]
+    async fn guard_purges_player_when_its_task_is_aborted() {
+        let (state, lobby) = (test_state(), test_lobby());
+        let (sender, _client) = connection().await;
+        join(&state, &lobby, &sender);
+
+        let guard = LobbyConnectionGuard::new(state.clone(), lobby.clone(), PLAYER_ID.to_string(), sender);
+        let task = tokio::spawn(async move {
+            let _guard = guard;
+            std::future::pending::<()>().await;
+        });
+        tokio::task::yield_now().await;
+        assert!(lobby.player_positions.contains_key(PLAYER_ID));
+        task.abort();
+        assert!(task.await.unwrap_err().is_cancelled());
+        assert_purged(&state, &lobby);
+    }
+
+    #[tokio::test]
+    async fn guard_of_a_replaced_connection_leaves_the_new_one_alone() {
+        let (state, lobby) = (test_state(), test_lobby());
+        let (old_sender, _old_client) = connection().await;
+        join(&state, &lobby, &old_sender);
+        let old_guard = LobbyConnectionGuard::new(state.clone(), lobby.clone(), PLAYER_ID.to_string(), old_sender);
+
+        let (new_sender, _new_client) = connection().await;
+        join(&state, &lobby, &new_sender);
+        assert!(old_guard.replaced());
+        drop(old_guard);
+
+        assert!(lobby.player_positions.contains_key(PLAYER_ID));
+        assert!(state.player_index.get(PLAYER_ID).is_some());
+        assert!(state.player_movement_manager.as_ref().unwrap().is_tracking(PLAYER_ID));
+        let connection = lobby.player_connections.get(PLAYER_ID).expect("new connection kept");
+        assert!(Arc::ptr_eq(connection.value(), &new_sender));
+    }
+}
@@ -1,6 +1,6 @@
 pub use game_server::*;
 
-use axum::{routing::get, Router};
+use axum::{routing::{get, post}, Router};
 use std::sync::Arc;
 use tower_http::cors::CorsLayer;
 use tokio::time::Duration;
@@ -12,6 +12,9 @@ async fn main() {
     dotenv::dotenv().ok();
 
     let config = config::Config::from_env();
+    if let Err(e) = config.validate() {
+        panic!("Invalid configuration: {}", e);
+    }
     let redis_url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
     let redis_client = redis_manager::init_redis_client(&redis_url).await;
     
@@ -19,11 +22,12 @@ async fn main() {
     state.initialize_default_lobbies().await;
 
     // Load move data from moves.json
-    let move_repository = monsters::MoveRepository::new(&config.monsters.moves_path, &config.monsters.type_chart_path);
+    let move_repository = monsters::MoveRepository::new(&config.monsters.moves_path, &config.monsters.type_chart_path, &config.monsters.content_packs);
     
     // Load monster templates
-    let monster_template_repository = monsters::monster_manager::MonsterTemplateRepository::new(&config.monsters.templates_path).await;
+    let monster_template_repository = monsters::monster_manager::MonsterTemplateRepository::new(&config.monsters.templates_path, &config.monsters.content_packs).await;
     let monster_template_repository = monster_template_repository.with_move_repository(move_repository.clone());
+    let monster_template_repository = monster_template_repository.with_ability_repository(monsters::AbilityRepository::new(&config.monsters.abilities_path));
     
     let monster_manager_factory = Arc::new(monsters::monster_manager::MonsterManagerFactory {
         template_repository: monster_template_repository.clone(),
@@ -38,13 +42,159 @@ async fn main() {
     );
     
     // Create the battle manager, passing the template repository
-    let battle_manager = Arc::new(combat::manager::BattleManager::new(monster_template_repository.clone()));
+    let battle_manager = Arc::new(
+        combat::manager::BattleManager::new(monster_template_repository.clone())
+            .with_banlist(combat::team_validation::Banlist::load(&config.monsters.banlist_path))
+            .with_rental_teams(combat::rental_teams::RentalTeams::load(&config.monsters.rental_teams_path, &monster_template_repository))
+            .with_gyms(combat::gyms::Gyms::load(&config.monsters.gyms_path, &monster_template_repository))
+            .with_capture_odds_hidden(config.game.hide_capture_odds)
+    );
+
+    // Append-only trail of currency changes, trades and admin commands
+    let audit_log = game_loop::audit_log::AuditLog::new(redis_client.clone());
+
+    // Create the currency manager and pay out trainer battle winners
+    let currency_manager = game_loop::currency::CurrencyManager::new(redis_client.clone(), audit_log.clone());
+    battle_manager.register_outcome_listener(Arc::new(game_loop::currency::BattlePayoutListener::new(currency_manager.clone())));
+    // Record every finished battle for player profiles
+    let battle_history_manager = game_loop::battle_history::BattleHistoryManager::new(redis_client.clone());
+    battle_manager.register_outcome_listener(Arc::new(game_loop::battle_history::BattleHistoryListener::new(battle_history_manager.clone())));
+
+    // Keep turn-by-turn replays of disputed battles for admin review
+    let battle_replay_manager = game_loop::battle_replays::BattleReplayManager::new(redis_client.clone());
+    battle_manager.register_outcome_listener(Arc::new(game_loop::battle_replays::BattleReplayListener::new(battle_replay_manager.clone())));
+    // Walk new players through their first starter, battle and capture
+    let tutorial_manager = game_loop::tutorial::TutorialManager::new(redis_client.clone());
+    battle_manager.register_outcome_listener(Arc::new(game_loop::tutorial::TutorialListener::new(tutorial_manager.clone())));
+    // Create the inventory and restore each lobby's berry plots
+    let inventory_manager = game_loop::inventory::InventoryManager::new(redis_client.clone());
+    let berry_manager = game_loop::berries::BerryManager::new(redis_client.clone(), inventory_manager.clone());
+    for lobby in state.lobbies.iter() {
+        if let Err(e) = berry_manager.load_lobby_plots(lobby.value()).await {
+            tracing::error!("Failed to load berry plots for lobby {}: {}", lobby.key(), e);
+        }
+    }
+
+    let fast_travel_manager = game_loop::fast_travel::FastTravelManager::new(
+        redis_client.clone(),
+        pokemon_collection_manager.clone(),
+        inventory_manager.clone()
+    );
+
+    // Badges from gym leaders, awarded when a player wins a gym battle
+    let badge_manager = game_loop::badges::BadgeManager::new(redis_client.clone(), battle_manager.clone());
+    battle_manager.register_outcome_listener(Arc::new(game_loop::badges::BadgeListener::new(badge_manager.clone())));
+
+    let gate_manager = game_loop::gates::GateManager::new(redis_client.clone(), inventory_manager.clone(), badge_manager.clone());
+
+    let trade_manager = game_loop::trading::TradeManager::new(
+        pokemon_collection_manager.clone(),
+        currency_manager.clone(),
+        inventory_manager.clone(),
+        audit_log.clone()
+    );
+
+    let market_manager = game_loop::market::MarketManager::new(
+        redis_client.clone(),
+        pokemon_collection_manager.clone(),
+        currency_manager.clone(),
+        inventory_manager.clone()
+    );
+
+    let auction_manager = game_loop::auctions::AuctionManager::new(
+        redis_client.clone(),
+        pokemon_collection_manager.clone(),
+        currency_manager.clone(),
+        inventory_manager.clone()
+    );
+
+    let friend_list_manager = game_loop::friends::FriendListManager::new(redis_client.clone());
+    let mail_manager = game_loop::mail::MailManager::new(redis_client.clone(), inventory_manager.clone(), friend_list_manager.clone());
+
+    // Create the ranked ladder and rate every trainer battle
+    let ranking_manager = game_loop::ranking::RankingManager::new(redis_client.clone(), mail_manager.clone());
+    battle_manager.register_outcome_listener(Arc::new(game_loop::ranking::RankedBattleListener::new(ranking_manager.clone(), badge_manager.clone())));
+
+    // Aggressive monsters leave players alone for a moment after each battle
+    battle_manager.register_outcome_listener(player_movement_manager.clone());
+
+    // Anonymized battle statistics for balancing, when a sink is configured
+    if let Some(sink_config) = config.telemetry.sink.clone() {
+        let (telemetry_listener, telemetry_rx) = game_loop::telemetry::TelemetryListener::new();
+        battle_manager.register_outcome_listener(Arc::new(telemetry_listener));
+        let sink = game_loop::telemetry::TelemetrySink::new(sink_config, redis_client.clone());
+        let flush_interval = Duration::from_millis(config.telemetry.flush_interval_ms);
+        tokio::spawn(async move {
+            game_loop::telemetry::run_telemetry(telemetry_rx, sink, flush_interval).await;
+        });
+    }
+
+    // Battle results, rare captures and trades for external services, when endpoints are configured
+    let webhooks = (!config.webhooks.endpoints.is_empty()).then(|| {
+        let (webhooks, webhook_rx) = game_loop::webhooks::Webhooks::new();
+        let webhooks = Arc::new(webhooks);
+        battle_manager.register_outcome_listener(webhooks.clone());
+        let endpoints = config.webhooks.endpoints.clone();
+        let secret = config.webhooks.secret.clone();
+        tokio::spawn(async move {
+            game_loop::webhooks::run_webhooks(webhook_rx, endpoints, secret).await;
+        });
+        webhooks
+    });
+
+    // Periodic archives of player data, when a backup directory is configured
+    if let Some(directory) = config.backups.directory.clone() {
+        let backup_manager = game_loop::backups::BackupManager::new(redis_client.clone(), directory, config.backups.retain, config.backups.bucket.clone());
+        let backup_interval = Duration::from_millis(config.backups.interval_ms);
+        tokio::spawn(async move {
+            game_loop::backups::run_backups(backup_manager, backup_interval).await;
+        });
+    }
+
+    // Quick-message chat between PvP opponents, kept open briefly after each battle
+    let battle_chat_manager = game_loop::battle_chat::BattleChatManager::new(battle_manager.clone());
+    battle_manager.register_outcome_listener(battle_chat_manager.clone());
+
+    let npc_services = Arc::new(game_loop::npc_services::NpcServices::new(
+        pokemon_collection_manager.clone(),
+        currency_manager.clone(),
+        inventory_manager.clone()
+    ));
+
+    let player_settings_manager = game_loop::player_settings::PlayerSettingsManager::new(redis_client.clone());
     
     let state = state
         .with_monster_manager_factory(monster_manager_factory.clone())
         .with_player_movement_manager(player_movement_manager.clone())
         .with_pokemon_collection_manager(pokemon_collection_manager.clone())
-        .with_battle_manager(battle_manager.clone());
+        .with_battle_manager(battle_manager.clone())
+        .with_currency_manager(currency_manager.clone())
+        .with_npc_services(npc_services.clone())
+        .with_inventory_manager(inventory_manager.clone())
+        .with_berry_manager(berry_manager.clone())
+        .with_fast_travel_manager(fast_travel_manager.clone())
+        .with_gate_manager(gate_manager.clone())
+        .with_badge_manager(badge_manager.clone())
+        .with_emote_manager(Arc::new(game_loop::emotes::EmoteManager::new()))
+        .with_trade_manager(trade_manager.clone())
+        .with_market_manager(market_manager.clone())
+        .with_auction_manager(auction_manager.clone())
+        .with_mail_manager(mail_manager.clone())
+        .with_ranking_manager(ranking_manager.clone())
+        .with_battle_history_manager(battle_history_manager.clone())
+        .with_player_settings_manager(player_settings_manager.clone())
+        .with_notification_manager(game_loop::notifications::NotificationManager::new(redis_client.clone(), player_settings_manager.clone()))
+        .with_block_list_manager(game_loop::blocks::BlockListManager::new(redis_client.clone()))
+        .with_friend_list_manager(friend_list_manager.clone())
+        .with_battle_chat_manager(battle_chat_manager.clone())
+        .with_battle_replay_manager(battle_replay_manager.clone())
+        .with_lobby_snapshot_manager(game_loop::lobby_snapshots::LobbySnapshotManager::new(redis_client.clone()))
+        .with_tutorial_manager(tutorial_manager.clone())
+        .with_audit_log(audit_log.clone());
+    let state = match webhooks {
+        Some(webhooks) => state.with_webhooks(webhooks),
+        None => state,
+    };
     
     let cors = CorsLayer::new()
         .allow_origin(config.server.cors_origins.iter().map(|origin| origin.parse().unwrap()).collect::<Vec<_>>())
@@ -55,6 +205,36 @@ async fn main() {
         .route("/ws/{lobby_id}", get(handlers::ws_lobby_handler))
         .route("/lobbies", get(handlers::public_lobbies_handler))
         .route("/health", get(handlers::health_handler))
+        .route("/market", get(handlers::market_search_handler))
+        .route("/leaderboard", get(handlers::leaderboard_handler))
+        .route("/players", get(handlers::player_search_handler))
+        .route("/players/{player_id}/battles", get(handlers::battle_history_handler))
+        .route("/me/resume", get(handlers::resume_handler))
+        .route("/me/collection", get(handlers::my_collection_handler))
+        .route("/me/boxes/{box_index}", get(handlers::my_box_handler))
+        .route("/me/inventory", get(handlers::my_inventory_handler))
+        .route("/me/presence", get(handlers::my_presence_handler))
+        .route("/battles/verify", post(handlers::verify_battle_handler))
+        .route("/admin/replays", get(handlers::admin_replays_handler))
+        .route("/admin/replays/{battle_id}", get(handlers::admin_replay_handler))
+        .route("/admin/replays/{battle_id}/turns/{turn_number}", get(handlers::admin_replay_turn_handler))
+        .route("/admin/snapshots", get(handlers::admin_snapshots_handler))
+        .route("/admin/snapshots/{name}", get(handlers::admin_snapshot_handler).put(handlers::admin_upload_snapshot_handler))
+        .route("/admin/lobbies/{lobby_id}/snapshots/{name}", post(handlers::admin_take_snapshot_handler))
+        .route("/admin/lobbies/{lobby_id}/restore/{name}", post(handlers::admin_restore_snapshot_handler))
+        .route("/admin/lobbies/{lobby_id}/events", get(handlers::admin_lobby_events_handler))
+        .route("/admin/lobbies/{lobby_id}/spawn_odds", get(handlers::admin_spawn_odds_handler))
+        .route("/admin/lobbies/{lobby_id}/buffs", post(handlers::admin_start_lobby_buff_handler))
+        .route("/admin/audit", get(handlers::admin_audit_handler));
+
+    // Read-only GraphQL API, only in builds with the `graphql` feature
+    #[cfg(feature = "graphql")]
+    let app = app.route(
+        "/graphql",
+        post(graphql::graphql_handler).layer(axum::Extension(graphql::build_schema(state.clone()))),
+    );
+
+    let app = app
         .layer(cors)
         .with_state(state.clone());
 
@@ -63,7 +243,24 @@ async fn main() {
     let addr = config.server_addr();
     tracing::info!("Starting server on {}", addr);
     let listener = tokio::net::TcpListener::bind(addr).await.expect("Failed to bind port");
-    axum::serve(listener, app).await.expect("Server failed");
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal(state.clone()))
+        .await
+        .expect("Server failed");
+}
+
+// Wait for Ctrl+C, then save what would otherwise be lost with the process and tell clients why they are disconnected
+async fn shutdown_signal(state: Arc<app_state::AppState>) {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        tracing::error!("Failed to listen for the shutdown signal: {}", e);
+        std::future::pending::<()>().await;
+    }
+    tracing::info!("Shutting down, saving lobby monsters");
+    game_loop::monster_persistence::save_all_lobby_monsters(&state.redis, &state.lobbies).await;
+    let lobbies: Vec<_> = state.lobbies.iter().map(|entry| entry.value().clone()).collect();
+    for lobby in lobbies {
+        lobby.close_all(protocol::CloseReason::ServerShutdown).await;
+    }
 }
 
 fn spawn_background_tasks(state: Arc<app_state::AppState>) {
@@ -73,20 +270,40 @@ fn spawn_background_tasks(state: Arc<app_state::AppState>) {
         loop {
             interval.tick().await;
             lobby::cleanup_inactive_lobbies(&state_clone).await;
+            lobby::update_afk_players(&state_clone).await;
         }
     });
     
+    let state_for_persistence = state.clone();
+    tokio::spawn(async move {
+        lobby::run_persistence_recovery(state_for_persistence).await;
+    });
+
     let lobbies_for_spawner = Arc::new(state.lobbies.clone());
+    let spawner_config = game_loop::monster_spawner::SpawnerConfig::from_config(&state.config);
     tokio::spawn(async move {
         game_loop::monster_spawner::run_monster_spawner(
             lobbies_for_spawner,
-            game_loop::monster_spawner::SpawnerConfig::default()
+            spawner_config
         ).await;
     });
     
+    let lobbies_for_monster_saves = Arc::new(state.lobbies.clone());
+    let redis_for_monster_saves = state.redis.clone();
+    let monster_save_interval = Duration::from_millis(state.config.ticks.monster_save_interval_ms);
+    tokio::spawn(async move {
+        game_loop::monster_persistence::run_monster_persistence(redis_for_monster_saves, lobbies_for_monster_saves, monster_save_interval).await;
+    });
+
+    let lobbies_for_latency = Arc::new(state.lobbies.clone());
+    tokio::spawn(async move {
+        game_loop::latency::run_latency_report(lobbies_for_latency).await;
+    });
+
     let lobbies_for_movement = Arc::new(state.lobbies.clone());
+    let monster_movement_interval = Duration::from_millis(state.config.ticks.monster_movement_interval_ms);
     tokio::spawn(async move {
-        game_loop::monster_movement::run_monster_movement(lobbies_for_movement).await;
+        game_loop::monster_movement::run_monster_movement(lobbies_for_movement, monster_movement_interval).await;
     });
     
     
@@ -96,5 +313,69 @@ fn spawn_background_tasks(state: Arc<app_state::AppState>) {
         game_loop::player_movement::run_player_movement_controller(state_for_player_movement, player_movement_manager).await;
     });
 
+    let lobbies_for_time_sync = Arc::new(state.lobbies.clone());
+    let clock = state.clock.clone();
+    let time_sync_interval = Duration::from_millis(state.config.ticks.time_sync_interval_ms);
+    tokio::spawn(async move {
+        game_loop::time_sync::run_time_sync(lobbies_for_time_sync, clock, time_sync_interval).await;
+    });
+
+    let market_manager = state.market_manager.clone().unwrap();
+    tokio::spawn(async move {
+        game_loop::market::run_market_expiry(market_manager).await;
+    });
+
+    // Weather, auctions and ranked seasons share one scheduler task
+    let state_for_events = state.clone();
+    tokio::spawn(async move {
+        let mut event_scheduler = game_loop::event_scheduler::EventScheduler::new();
+        event_scheduler.add(game_loop::weather::WeatherRotation::new(Arc::new(state_for_events.lobbies.clone())));
+        let auction_manager = state_for_events.auction_manager.clone().unwrap();
+        event_scheduler.add(game_loop::auctions::AuctionEvents::start(state_for_events.clone(), auction_manager).await);
+        event_scheduler.add(game_loop::ranking::SeasonEvents::new(state_for_events.ranking_manager.clone().unwrap()));
+        event_scheduler.run().await;
+    });
+
+    // Auto-resolve wild battles the player stopped responding in
+    let battle_manager = state.battle_manager.clone().unwrap();
+    let pokemon_collection_manager = state.pokemon_collection_manager.clone().unwrap();
+    let state_for_battle_timeouts = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(15));
+        loop {
+            interval.tick().await;
+            battle_manager.expire_idle_wild_battles(&state_for_battle_timeouts.lobbies, &pokemon_collection_manager).await;
+        }
+    });
+
+    // Clear in_combat flags left behind by missed battle cleanup
+    let battle_manager = state.battle_manager.clone().unwrap();
+    let state_for_combat_sweep = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            let repaired = battle_manager.repair_orphaned_combat_flags(&state_for_combat_sweep.lobbies).await;
+            if repaired.players_repaired > 0 || repaired.monsters_repaired > 0 {
+                let totals = battle_manager.combat_repair_metrics();
+                tracing::warn!(
+                    "Combat sweep repaired {} players and {} monsters ({} / {} since startup)",
+                    repaired.players_repaired, repaired.monsters_repaired, totals.players_repaired, totals.monsters_repaired
+                );
+            }
+        }
+    });
+
+    let lobbies_for_berries = Arc::new(state.lobbies.clone());
+    let berry_manager = state.berry_manager.clone().unwrap();
+    tokio::spawn(async move {
+        game_loop::berries::run_berry_growth(lobbies_for_berries, berry_manager).await;
+    });
+
+    let lobbies_for_items = Arc::new(state.lobbies.clone());
+    tokio::spawn(async move {
+        game_loop::item_pickups::run_item_respawns(lobbies_for_items).await;
+    });
+
 
 }
\ No newline at end of file
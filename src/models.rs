@@ -1,19 +1,44 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 use crate::{
+    combat::fairness::{BattleTranscript, FairnessDisclosure},
+    combat::team_validation::TeamViolation,
+    combat::ai::PracticeDifficulty,
+    combat::gyms::{Badge, GymLeaderView, GymOffer},
+    combat::draft::{DraftAction, DraftPoolEntry},
     combat::state::{
         BallType, BattleEndReason, BattleEvent, BattlePokemonPrivateView, BattlePokemonPublicView,
         BattlePokemonTeamOverview, FieldState, PlayerAction, SwitchReason, WildBattleOutcome,
-        BattleMoveView, StatusCondition,
+        BattleMoveView, StatusCondition, CapturedPokemonView, BattleRules, PlayerSideState,
     },
-    game_loop::pokemon_collection::Pokemon,
-    monsters::monster::{DisplayMonster, PokemonType},
+    game_loop::{
+        player_index::PlayerSearchResult,
+        berries::{BerryPlot, BerryType},
+        auctions::{Auction, AuctionPrize},
+        battle_history::BattleHistoryPage,
+        currency::TransactionReason,
+        lobby_buffs::LobbyBuff,
+        item_pickups::ItemPickup,
+        mail::{Mail, MailAttachment},
+        ranking::{Leaderboard, SeasonRecord},
+        market::{AskingCondition, ListingRequest, MarketListing, MarketSearchQuery},
+        notifications::MissedNotification,
+        npc_services::ShopItem,
+        player_settings::{ChallengePolicy, NotificationPreferences, PlayerSettings, RichPresenceSharing},
+        pokemon_collection::Pokemon,
+        trading::{TradeOffer, TradeView},
+        tutorial::TutorialStep,
+        weather::OverworldWeather,
+    },
+    protocol::Capability,
+    monsters::{monster::{DisplayMonster, MonsterPreview, PokemonType, Position}, monster_manager::Landmark},
     stats::{CalculatedStats, nature::Nature},
 };
 
 // Player state
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
 pub struct PlayerState {
     pub id: String,
     pub username: String,  // Added username field
@@ -22,22 +47,40 @@ pub struct PlayerState {
     pub direction: String,
     #[serde(skip)]
     pub in_combat: bool, // Whether player is in combat
+    #[serde(default)]
+    pub afk: bool, // Whether the player has not done anything for a while
 }
 
 // Client messages
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, schemars::JsonSchema)]
 #[serde(tag = "type")]
 pub enum ClientMessage {
     #[serde(rename = "join")]
-    Join { session_token: String },
+    Join {
+        session_token: String,
+        // Left out by clients that predate protocol versioning
+        #[serde(default)]
+        protocol_version: Option<u32>,
+        // Optional features the client supports, see protocol::Capability
+        #[serde(default)]
+        capabilities: Vec<String>,
+    },
     #[serde(rename = "move")]
     Move {
         x: u32,
         y: u32,
         direction: String,
     },
+    // Both fields are optional so older clients can keep sending a bare ping
     #[serde(rename = "ping")]
-    Ping,
+    Ping {
+        // Client clock when the ping was sent, echoed back in the pong
+        #[serde(default)]
+        client_time: Option<u64>,
+        // Round trip the client measured from the previous pong
+        #[serde(default)]
+        rtt_ms: Option<u32>,
+    },
     #[serde(rename = "interact")]
     Interact {
         monster_id: Option<String>,
@@ -53,19 +96,318 @@ pub enum ClientMessage {
         action: PlayerAction,
     },
     // New player challenge messages
+    // `use_rental` asks for a server rental team, only allowed with fewer than six active Pokémon
     #[serde(rename = "challenge_player")]
     ChallengePlayer {
         target_player_id: String,
+        #[serde(default)]
+        rules: BattleRules,
+        #[serde(default)]
+        use_rental: bool,
     },
+    // The rules and the challenger's rental choice are the ones the challenger sent;
+    // `use_rental` asks for a rental team for the responder
     #[serde(rename = "respond_to_challenge")]
     RespondToChallenge {
         challenger_id: String,
         accepted: bool,
+        #[serde(default)]
+        use_rental: bool,
+    },
+    // PvP-style battle against the server bot; nothing from it is saved
+    #[serde(rename = "start_practice_battle")]
+    StartPracticeBattle {
+        difficulty: PracticeDifficulty,
+    },
+    // Talk to the gym leader the player is standing next to, to hear whether they offer a
+    // challenge or a rematch
+    #[serde(rename = "talk_to_gym_leader")]
+    TalkToGymLeader {
+        gym_id: String,
+    },
+    // Challenge the gym leader the player is standing next to for their badge, or to a rematch
+    #[serde(rename = "challenge_gym")]
+    ChallengeGym {
+        gym_id: String,
+    },
+    // Search connected players by username prefix; an empty username lists who is online
+    #[serde(rename = "find_player")]
+    FindPlayer {
+        username: String,
+    },
+    // Optional nickname for a Pokémon right after capture (None keeps the species name)
+    #[serde(rename = "nickname_captured")]
+    NicknameCaptured {
+        pokemon_id: String,
+        nickname: Option<String>,
+    },
+    // Fetch all logged events of the current battle (e.g. after rejoining or lagging)
+    #[serde(rename = "get_battle_log")]
+    GetBattleLog {
+        battle_id: Uuid,
+    },
+    // Ask for the current action request again with the full team overview (delta fallback)
+    #[serde(rename = "request_full_battle_state")]
+    RequestFullBattleState {
+        battle_id: Uuid,
+    },
+    // Let the server play a wild battle for the player; any action the player sends takes back control
+    #[serde(rename = "set_auto_battle")]
+    SetAutoBattle {
+        battle_id: Uuid,
+        enabled: bool,
+    },
+    // Ban or pick a species from the pool when it is the player's turn in a draft
+    #[serde(rename = "draft_select")]
+    DraftSelect {
+        draft_id: Uuid,
+        template_id: u32,
+    },
+    // Plant one of the player's berries on the soil tile at (x, y)
+    #[serde(rename = "plant_berry")]
+    PlantBerry {
+        x: u32,
+        y: u32,
+        berry: BerryType,
+    },
+    // Harvest the ripe berry plant at (x, y)
+    #[serde(rename = "harvest_berry")]
+    HarvestBerry {
+        x: u32,
+        y: u32,
+    },
+    // Pick up the item lying at an item spawn next to the player
+    #[serde(rename = "pick_up_item")]
+    PickUpItem {
+        spawn_id: String,
+    },
+    #[serde(rename = "get_inventory")]
+    GetInventory,
+    // Ask another player to trade
+    #[serde(rename = "request_trade")]
+    RequestTrade {
+        target_player_id: String,
+    },
+    #[serde(rename = "respond_to_trade")]
+    RespondToTrade {
+        requester_id: String,
+        accepted: bool,
+    },
+    // Replace this player's side of the trade (Pokémon, items and currency)
+    #[serde(rename = "update_trade_offer")]
+    UpdateTradeOffer {
+        trade_id: Uuid,
+        offer: TradeOffer,
+    },
+    #[serde(rename = "confirm_trade")]
+    ConfirmTrade {
+        trade_id: Uuid,
+    },
+    #[serde(rename = "cancel_trade")]
+    CancelTrade {
+        trade_id: Uuid,
+    },
+    // Put a Pokémon or item on the global trade station
+    #[serde(rename = "market_list")]
+    MarketList {
+        goods: ListingRequest,
+        asking: AskingCondition,
+    },
+    #[serde(rename = "market_search")]
+    MarketSearch {
+        #[serde(default)]
+        query: MarketSearchQuery,
+    },
+    // Buy a listing; offered_pokemon_id is required when the seller asks for a Pokémon
+    #[serde(rename = "market_accept")]
+    MarketAccept {
+        listing_id: Uuid,
+        offered_pokemon_id: Option<String>,
+    },
+    #[serde(rename = "market_cancel")]
+    MarketCancel {
+        listing_id: Uuid,
+    },
+    #[serde(rename = "get_auctions")]
+    GetAuctions,
+    // Bid on a running auction; the amount is held until the player is outbid
+    #[serde(rename = "place_bid")]
+    PlaceBid {
+        auction_id: Uuid,
+        amount: u64,
+    },
+    // Send mail to a friend, online or not; attached items leave the sender's inventory
+    #[serde(rename = "send_mail")]
+    SendMail {
+        recipient_id: String,
+        subject: String,
+        body: String,
+        #[serde(default)]
+        attachments: Vec<MailAttachment>,
+    },
+    #[serde(rename = "get_mailbox")]
+    GetMailbox,
+    #[serde(rename = "read_mail")]
+    ReadMail {
+        mail_id: Uuid,
+    },
+    #[serde(rename = "claim_mail_attachments")]
+    ClaimMailAttachments {
+        mail_id: Uuid,
+    },
+    #[serde(rename = "delete_mail")]
+    DeleteMail {
+        mail_id: Uuid,
+    },
+    // Leaderboard of a ranked season; the running season when `season` is omitted
+    #[serde(rename = "get_leaderboard")]
+    GetLeaderboard {
+        season: Option<u32>,
+    },
+    #[serde(rename = "get_season_history")]
+    GetSeasonHistory,
+    // Page through the player's own finished battles, newest first
+    #[serde(rename = "get_battle_history")]
+    GetBattleHistory {
+        page: Option<usize>,
+        page_size: Option<usize>,
+    },
+    // Receive a damage breakdown event for every hit in your battles
+    #[serde(rename = "set_damage_breakdown")]
+    SetDamageBreakdown {
+        enabled: bool,
+    },
+    // Include type effectiveness hints on moves in battle (saved to the player's settings)
+    #[serde(rename = "set_effectiveness_hints")]
+    SetEffectivenessHints {
+        enabled: bool,
+    },
+    // Change privacy settings; omitted fields keep their current value
+    #[serde(rename = "update_settings")]
+    UpdateSettings {
+        #[serde(default)]
+        accept_challenges: Option<ChallengePolicy>,
+        #[serde(default)]
+        appear_offline: Option<bool>,
+        // Which notification kinds to receive
+        #[serde(default)]
+        notifications: Option<NotificationPreferences>,
+        // What the /me/presence relay may reveal
+        #[serde(default)]
+        rich_presence: Option<RichPresenceSharing>,
+        // Language of species and move names; "en" switches back to English
+        #[serde(default)]
+        locale: Option<String>,
+    },
+    // Send a canned quick message to your PvP opponent, during or shortly after the battle
+    #[serde(rename = "battle_chat")]
+    BattleChat {
+        battle_id: Uuid,
+        message_id: String,
+    },
+    // Block a player: hides their emotes and battle chat and turns away their challenges and trade requests
+    #[serde(rename = "block_player")]
+    BlockPlayer {
+        player_id: String,
+    },
+    #[serde(rename = "unblock_player")]
+    UnblockPlayer {
+        player_id: String,
+    },
+    #[serde(rename = "get_block_list")]
+    GetBlockList,
+    // Add a friend; players are friends once both added each other
+    #[serde(rename = "add_friend")]
+    AddFriend {
+        player_id: String,
+    },
+    #[serde(rename = "remove_friend")]
+    RemoveFriend {
+        player_id: String,
+    },
+    #[serde(rename = "get_friend_list")]
+    GetFriendList,
+    // Move a boxed pokemon (e.g. a capture made with a full party) into a party slot
+    #[serde(rename = "swap_into_party")]
+    SwapIntoParty {
+        pokemon_id: String,
+        party_slot: usize,
+    },
+    // Spend a PP Up or PP Max on one of a pokemon's moves
+    #[serde(rename = "use_pp_item")]
+    UsePpItem {
+        item_id: String,
+        pokemon_id: String,
+        move_index: usize,
+    },
+    // Clear a gate next to the player, such as a tree to cut, with the key item or badge it needs
+    #[serde(rename = "use_key_item")]
+    UseKeyItem {
+        gate_id: String,
+    },
+    // Use an item such as incense that boosts spawns for everyone in the lobby for a while
+    #[serde(rename = "use_lobby_buff_item")]
+    UseLobbyBuffItem {
+        item_id: String,
+    },
+    // Equip a crystal from the inventory to enable a super move in battle; None unequips
+    #[serde(rename = "equip_crystal")]
+    EquipCrystal {
+        #[serde(default)]
+        item_id: Option<String>,
+    },
+    // Look at a wild monster without starting a battle
+    #[serde(rename = "examine_monster")]
+    ExamineMonster {
+        monster_id: String,
+    },
+    // Show an emote from the catalog to nearby players
+    #[serde(rename = "emote")]
+    Emote {
+        emote_id: String,
+    },
+    // Fly to a previously visited landmark
+    #[serde(rename = "fast_travel")]
+    FastTravel {
+        landmark_id: String,
+    },
+    // Name rater: rename one of the player's Pokémon for a fee while standing next to the name rater NPC
+    #[serde(rename = "rename_pokemon")]
+    RenamePokemon {
+        pokemon_id: String,
+        name: String,
+    },
+    // Move relearner: list the moves a Pokémon can relearn
+    #[serde(rename = "get_relearnable_moves")]
+    GetRelearnableMoves {
+        pokemon_id: String,
+    },
+    // Move relearner: teach a move for a fee while next to the move relearner NPC, replacing the move at replace_move_index when the moveset is full
+    #[serde(rename = "relearn_move")]
+    RelearnMove {
+        pokemon_id: String,
+        move_id: u32,
+        replace_move_index: Option<usize>,
+    },
+    // Shopkeeper: list the items on sale and what the shopkeeper pays for them
+    #[serde(rename = "get_shop_items")]
+    GetShopItems,
+    // Shopkeeper: buy items while standing next to the shopkeeper NPC
+    #[serde(rename = "buy_item")]
+    BuyItem {
+        item_id: String,
+        quantity: u32,
+    },
+    // Shopkeeper: sell stocked items back while standing next to the shopkeeper NPC
+    #[serde(rename = "sell_item")]
+    SellItem {
+        item_id: String,
+        quantity: u32,
     },
 }
 
 // New struct for client-friendly Pokemon display
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, schemars::JsonSchema)]
 pub struct DisplayPokemon {
     pub id: String,
     pub template_id: u32,
@@ -80,18 +422,29 @@ pub struct DisplayPokemon {
     pub capture_date: u64,
     pub moves: Vec<BattleMoveView>, // Use the detailed move view
     pub types: Vec<PokemonType>,
+    pub form: Option<String>,
     pub ability: String,
     pub status_condition: Option<StatusCondition>,
 }
 
 // Server messages
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, schemars::JsonSchema)]
 #[serde(tag = "type")]
 pub enum ServerMessage {
     #[serde(rename = "error")]
     Error { message: String },
     #[serde(rename = "welcome")]
-    Welcome { id: String, username: String, x: u32, y: u32 },
+    Welcome {
+        id: String,
+        username: String,
+        x: u32,
+        y: u32,
+        // Only sent to versioned clients: the server's protocol version and the capabilities enabled for this connection
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        protocol_version: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        capabilities: Option<Vec<Capability>>,
+    },
     #[serde(rename = "players")]
     Players { players: Vec<PlayerState> },
     #[serde(rename = "player_joined")]
@@ -100,12 +453,33 @@ pub enum ServerMessage {
     PlayerMoved { player: PlayerState },
     #[serde(rename = "player_left")]
     PlayerLeft { id: String },
+    // A player went away from the keyboard or came back
+    #[serde(rename = "player_afk")]
+    PlayerAfk { id: String, afk: bool },
     #[serde(rename = "pong")]
-    Pong,
+    Pong {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        client_time: Option<u64>,
+        server_time: u64,
+        // Moving average of the round trips this connection reported
+        #[serde(skip_serializing_if = "Option::is_none")]
+        latency_ms: Option<u32>,
+    },
+    // Server clock for scheduling interpolation and events: sent on join and periodically after
+    #[serde(rename = "time_sync")]
+    TimeSync {
+        server_time: u64,
+        tick: u64,
+        tick_interval_ms: u64,
+    },
+    // `tick` is the server tick the movement happened on, see time_sync
     #[serde(rename = "players_moved")]
-    PlayersMoved { players: Vec<PlayerState>, timestamp: u64 },
+    PlayersMoved { players: Vec<PlayerState>, timestamp: u64, tick: u64 },
     #[serde(rename = "monster_spawned")]
     MonsterSpawned { monster: DisplayMonster },
+    // Map marker for a freshly spawned alpha, sent alongside its monster_spawned
+    #[serde(rename = "alpha_spawned")]
+    AlphaSpawned { instance_id: String, template_id: u32, name: String, level: u32, position: Position },
     #[serde(rename = "monster_moved")]
     MonsterMoved { monster: DisplayMonster },
     #[serde(rename = "monster_despawned")]
@@ -142,22 +516,67 @@ pub enum ServerMessage {
         // Whether this player goes first
         player1_id: String,
         player2_id: String,
+        // Hash of the battle's RNG seed, which is revealed in battle_fairness when the battle ends
+        seed_commitment: String,
     },
     #[serde(rename = "request_action")]
     RequestAction {
         turn_number: u32,
         active_pokemon_state: BattlePokemonPrivateView,
+        // Full team, or only the entries changed since the previous revision when team_overview_is_delta is set
         team_overview: Vec<BattlePokemonTeamOverview>,
+        team_revision: u32,
+        team_overview_is_delta: bool,
         other_pokemon_state: BattlePokemonPublicView,
         can_switch: bool,
         must_switch: bool,
         field_state: FieldState,
     },
+    // PvP counterpart of request_action, carrying the state of both sides of the field
+    #[serde(rename = "pvp_request_action")]
+    PvPRequestAction {
+        battle_id: Uuid,
+        turn_number: u32,
+        active_pokemon_state: BattlePokemonPrivateView,
+        // Full team, or only the entries changed since the previous revision when team_overview_is_delta is set
+        team_overview: Vec<BattlePokemonTeamOverview>,
+        team_revision: u32,
+        team_overview_is_delta: bool,
+        opponent_pokemon_state: BattlePokemonPublicView,
+        opponent_team_size: usize,
+        opponent_remaining: usize,
+        // Screens and hazards on each side
+        own_side: PlayerSideState,
+        opponent_side: PlayerSideState,
+        can_switch: bool,
+        must_switch: bool,
+        opponent_must_switch: bool,
+        field_state: FieldState,
+        // Unix timestamp (seconds) the battle started at
+        battle_started_at: u64,
+        // How long a disconnected opponent has to come back before forfeiting
+        disconnect_grace_secs: u64,
+    },
     #[serde(rename = "turn_update")]
     TurnUpdate {
         turn_number: u32,
         events: Vec<BattleEvent>,
     },
+    #[serde(rename = "battle_log")]
+    BattleLog {
+        battle_id: Uuid,
+        turn_number: u32,
+        events: Vec<BattleEvent>,
+    },
+    #[serde(rename = "opponent_disconnected")]
+    OpponentDisconnected {
+        battle_id: Uuid,
+        grace_period_secs: u64,
+    },
+    #[serde(rename = "opponent_reconnected")]
+    OpponentReconnected {
+        battle_id: Uuid,
+    },
     #[serde(rename = "request_switch")]
     RequestSwitch {
         reason: SwitchReason,
@@ -173,22 +592,344 @@ pub enum ServerMessage {
     BattleEnd {
         outcome: WildBattleOutcome,
         reason: BattleEndReason,
-        pokemon_captured: Option<BattlePokemonPrivateView>,
+        pokemon_captured: Option<CapturedPokemonView>,
     },
     // New player challenge messages
     #[serde(rename = "challenge_received")]
     ChallengeReceived {
         challenger_id: String,
         challenger_username: String,
+        rules: BattleRules,
+        challenger_rental: bool,
     },
     #[serde(rename = "challenge_response")]
     ChallengeResponse {
         target_player_id: String,
         target_username: String,
         accepted: bool,
+        rules: BattleRules,
     },
     #[serde(rename = "challenge_failed")]
     ChallengeFailed {
         reason: String,
     },
+    // Sent to both players when a team breaks the rules of the accepted challenge; no battle starts
+    #[serde(rename = "pvp_team_rejected")]
+    PvPTeamRejected {
+        player_id: String,
+        username: String,
+        violations: Vec<TeamViolation>,
+    },
+    // Sent to both players when an accepted draft challenge opens its pick/ban phase
+    #[serde(rename = "draft_started")]
+    DraftStarted {
+        draft_id: Uuid,
+        player_ids: [String; 2],
+        pool: Vec<DraftPoolEntry>,
+        level: u32,
+        bans_per_player: usize,
+        team_size: usize,
+        pick_secs: u64,
+    },
+    // Whose turn it is to ban or pick; `deadline` is a Unix timestamp (seconds)
+    #[serde(rename = "draft_turn")]
+    DraftTurn {
+        draft_id: Uuid,
+        player_id: String,
+        action: DraftAction,
+        deadline: u64,
+    },
+    // A ban or pick, `timed_out` when the server made it for a player who ran out of time
+    #[serde(rename = "draft_selection")]
+    DraftSelection {
+        draft_id: Uuid,
+        player_id: String,
+        action: DraftAction,
+        template_id: u32,
+        timed_out: bool,
+    },
+    #[serde(rename = "draft_cancelled")]
+    DraftCancelled {
+        draft_id: Uuid,
+        reason: String,
+    },
+    #[serde(rename = "relearnable_moves")]
+    RelearnableMoves {
+        pokemon_id: String,
+        moves: Vec<BattleMoveView>,
+        fee: u64,
+    },
+    #[serde(rename = "shop_items")]
+    ShopItems {
+        items: Vec<ShopItem>,
+    },
+    #[serde(rename = "inventory")]
+    Inventory {
+        items: HashMap<String, u32>,
+    },
+    #[serde(rename = "berry_plots")]
+    BerryPlots {
+        plots: Vec<BerryPlot>,
+    },
+    #[serde(rename = "berry_plot_updated")]
+    BerryPlotUpdated {
+        plot: BerryPlot,
+    },
+    #[serde(rename = "berry_plot_removed")]
+    BerryPlotRemoved {
+        x: u32,
+        y: u32,
+    },
+    #[serde(rename = "berry_harvested")]
+    BerryHarvested {
+        berry: BerryType,
+        quantity: u32,
+    },
+    // Items lying on the lobby's map, sent on join
+    #[serde(rename = "item_pickups")]
+    ItemPickups {
+        pickups: Vec<ItemPickup>,
+    },
+    // Someone picked up an item; it reappears at `respawn_at` (Unix timestamp, seconds)
+    #[serde(rename = "item_picked_up")]
+    ItemPickedUp {
+        spawn_id: String,
+        player_id: String,
+        respawn_at: i64,
+    },
+    // An item that was picked up is back on the map
+    #[serde(rename = "item_respawned")]
+    ItemRespawned {
+        pickup: ItemPickup,
+    },
+    // Sent to the player who picked up an item
+    #[serde(rename = "item_received")]
+    ItemReceived {
+        item_id: String,
+        quantity: u32,
+    },
+    #[serde(rename = "weather_changed")]
+    WeatherChanged {
+        weather: OverworldWeather,
+    },
+    // Buffs running in the lobby; sent on join and whenever one starts, is extended or wears off
+    #[serde(rename = "lobby_buffs")]
+    LobbyBuffs {
+        buffs: Vec<LobbyBuff>,
+    },
+    // Gates on the map the player has cleared, sent on join
+    #[serde(rename = "cleared_gates")]
+    ClearedGates {
+        gate_ids: Vec<String>,
+    },
+    // A player cleared a gate; it stays in place for everyone else
+    #[serde(rename = "gate_cleared")]
+    GateCleared {
+        gate_id: String,
+        player_id: String,
+    },
+    // Gym leaders standing on the lobby's map, sent on join
+    #[serde(rename = "gym_leaders")]
+    GymLeaders {
+        gyms: Vec<GymLeaderView>,
+    },
+    // Badges the player holds and the highest level their Pokémon obey at, sent on join
+    #[serde(rename = "badges")]
+    Badges {
+        badges: Vec<Badge>,
+        obedience_level_cap: u32,
+    },
+    // What a gym leader offers the player, sent when they talk to the leader and after they win
+    #[serde(rename = "gym_leader_offer")]
+    GymLeaderOffer {
+        gym_id: String,
+        offer: GymOffer,
+    },
+    // The player beat a gym leader and earned their badge
+    #[serde(rename = "badge_earned")]
+    BadgeEarned {
+        badge: Badge,
+        badge_count: usize,
+        obedience_level_cap: u32,
+    },
+    #[serde(rename = "visited_landmarks")]
+    VisitedLandmarks {
+        landmarks: Vec<Landmark>,
+    },
+    #[serde(rename = "landmark_visited")]
+    LandmarkVisited {
+        landmark: Landmark,
+    },
+    #[serde(rename = "fast_travel_complete")]
+    FastTravelComplete {
+        landmark_id: String,
+        x: u32,
+        y: u32,
+    },
+    #[serde(rename = "player_emote")]
+    PlayerEmote {
+        player_id: String,
+        emote_id: String,
+    },
+    #[serde(rename = "trade_request_received")]
+    TradeRequestReceived {
+        requester_id: String,
+        requester_username: String,
+    },
+    #[serde(rename = "trade_updated")]
+    TradeUpdated {
+        trade: TradeView,
+    },
+    #[serde(rename = "trade_completed")]
+    TradeCompleted {
+        trade_id: Uuid,
+    },
+    #[serde(rename = "trade_cancelled")]
+    TradeCancelled {
+        trade_id: Option<Uuid>,
+        reason: String,
+    },
+    #[serde(rename = "market_listings")]
+    MarketListings {
+        listings: Vec<MarketListing>,
+    },
+    #[serde(rename = "market_listing_created")]
+    MarketListingCreated {
+        listing: MarketListing,
+    },
+    #[serde(rename = "market_listing_cancelled")]
+    MarketListingCancelled {
+        listing_id: Uuid,
+    },
+    #[serde(rename = "market_purchase_complete")]
+    MarketPurchaseComplete {
+        listing: MarketListing,
+    },
+    #[serde(rename = "market_listing_sold")]
+    MarketListingSold {
+        listing_id: Uuid,
+    },
+    #[serde(rename = "auctions")]
+    Auctions {
+        auctions: Vec<Auction>,
+    },
+    // Sent when an auction starts or receives a new highest bid
+    #[serde(rename = "auction_updated")]
+    AuctionUpdated {
+        auction: Auction,
+    },
+    #[serde(rename = "auction_outbid")]
+    AuctionOutbid {
+        auction_id: Uuid,
+        refunded: u64,
+    },
+    #[serde(rename = "auction_ended")]
+    AuctionEnded {
+        auction_id: Uuid,
+        winner_username: Option<String>,
+    },
+    // Sent to the winner, or queued until their next login if they are offline
+    #[serde(rename = "auction_won")]
+    AuctionWon {
+        auction_id: Uuid,
+        prize: AuctionPrize,
+        amount: u64,
+    },
+    // Sent when new mail arrives, and for every unread mail on connect
+    #[serde(rename = "mail_received")]
+    MailReceived {
+        mail: Mail,
+        unread_count: usize,
+    },
+    #[serde(rename = "mailbox")]
+    Mailbox {
+        mails: Vec<Mail>,
+        unread_count: usize,
+    },
+    #[serde(rename = "mail_sent")]
+    MailSent {
+        mail_id: Uuid,
+    },
+    #[serde(rename = "mail_attachments_claimed")]
+    MailAttachmentsClaimed {
+        mail_id: Uuid,
+        attachments: Vec<MailAttachment>,
+    },
+    #[serde(rename = "leaderboard")]
+    Leaderboard {
+        leaderboard: Leaderboard,
+    },
+    #[serde(rename = "season_history")]
+    SeasonHistory {
+        records: Vec<SeasonRecord>,
+    },
+    #[serde(rename = "battle_history")]
+    BattleHistory {
+        history: BattleHistoryPage,
+    },
+    #[serde(rename = "catch_chain_updated")]
+    CatchChainUpdated { template_id: u32, chain_length: u32 },
+    #[serde(rename = "player_settings")]
+    PlayerSettings { settings: PlayerSettings },
+    // Redis is unreachable: progress is kept in memory and saved once it recovers
+    #[serde(rename = "service_unavailable")]
+    ServiceUnavailable { message: String },
+    #[serde(rename = "service_restored")]
+    ServiceRestored,
+    #[serde(rename = "battle_chat")]
+    BattleChat {
+        battle_id: Uuid,
+        sender_id: String,
+        message_id: String,
+    },
+    #[serde(rename = "block_list")]
+    BlockList { player_ids: Vec<String> },
+    // `pending` are players this one added who have not added them back yet
+    #[serde(rename = "friend_list")]
+    FriendList { friends: Vec<String>, pending: Vec<String> },
+    #[serde(rename = "monster_preview")]
+    MonsterPreview { monster: MonsterPreview },
+    // Notifications that arrived while the player was offline, sent once at login
+    #[serde(rename = "notification_digest")]
+    NotificationDigest {
+        notifications: Vec<MissedNotification>,
+    },
+    // Whether the server is playing the player's turns in a wild battle
+    #[serde(rename = "auto_battle_status")]
+    AutoBattleStatus {
+        battle_id: Uuid,
+        enabled: bool,
+    },
+    #[serde(rename = "equipped_crystal")]
+    EquippedCrystal {
+        item_id: Option<String>,
+    },
+    // Sent to both players after a PvP battle ends, so anyone can re-simulate it with POST /battles/verify
+    #[serde(rename = "battle_fairness")]
+    BattleFairness {
+        disclosure: FairnessDisclosure,
+        // Holds full battle state, described only loosely in the schema
+        #[schemars(with = "serde_json::Value")]
+        transcript: BattleTranscript,
+    },
+    // Current onboarding step and what to do next; challenges unlock once the tutorial is completed
+    #[serde(rename = "tutorial_step")]
+    TutorialStep {
+        step: TutorialStep,
+        prompt: String,
+    },
+    // Sent instead of new_pokemon when the player already has a starter
+    #[serde(rename = "starter_already_chosen")]
+    StarterAlreadyChosen { pokemon: DisplayPokemon },
+    #[serde(rename = "player_search_results")]
+    PlayerSearchResults {
+        query: String,
+        players: Vec<PlayerSearchResult>,
+    },
+    #[serde(rename = "balance_update")]
+    BalanceUpdate {
+        balance: u64,
+        delta: i64,
+        reason: Option<TransactionReason>,
+    },
 }
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::combat::state::{StatusCondition, WeatherType};
+
+use super::monster::PokemonType;
+use super::move_manager::Stat;
+
+/// What an ability does, and when it kicks in
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AbilityEffect {
+    /// Changes the opposing Pokémon's stat when the holder enters battle (Intimidate)
+    OpponentStatOnEntry { stat: Stat, stages: i8 },
+    /// Moves of these types deal no damage to the holder (Levitate)
+    TypeImmunity { move_types: Vec<PokemonType> },
+    /// Scales damage the holder takes from moves of these types (Thick Fat)
+    DamageTakenMultiplier { move_types: Vec<PokemonType>, multiplier: f32 },
+    /// The holder can't be given these status conditions (Limber)
+    StatusImmunity { statuses: Vec<StatusCondition> },
+    /// Changes the holder's stat at the end of every turn (Speed Boost)
+    EndOfTurnStat { stat: Stat, stages: i8 },
+    /// Starts this weather when the holder enters battle (Drizzle)
+    WeatherOnEntry { weather: WeatherType },
+}
+
+/// An ability as described in the abilities file
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AbilityData {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub effects: Vec<AbilityEffect>,
+}
+
+impl AbilityData {
+    /// Whether the holder takes no damage from moves of `move_type`
+    pub fn is_immune_to_type(&self, move_type: PokemonType) -> bool {
+        self.effects.iter().any(|effect| matches!(effect, AbilityEffect::TypeImmunity { move_types } if move_types.contains(&move_type)))
+    }
+
+    /// Multiplier on damage the holder takes from moves of `move_type`, 1.0 when the ability doesn't affect it
+    pub fn damage_taken_multiplier(&self, move_type: PokemonType) -> f32 {
+        self.effects.iter()
+            .filter_map(|effect| match effect {
+                AbilityEffect::DamageTakenMultiplier { move_types, multiplier } if move_types.contains(&move_type) => Some(*multiplier),
+                _ => None,
+            })
+            .product()
+    }
+
+    /// Whether the holder can't be given `status`
+    pub fn prevents_status(&self, status: StatusCondition) -> bool {
+        self.effects.iter().any(|effect| matches!(effect, AbilityEffect::StatusImmunity { statuses } if statuses.contains(&status)))
+    }
+}
+
+/// Repository for ability data, keyed by the ability IDs stored on Pokémon (e.g. "speed-boost").
+/// Abilities missing from the file have no effect in battle.
+#[derive(Debug, Default)]
+pub struct AbilityRepository {
+    pub abilities: HashMap<String, AbilityData>,
+}
+
+impl AbilityRepository {
+    /// Create a new AbilityRepository from the specified file path
+    pub fn new(abilities_path: &str) -> Arc<Self> {
+        let abilities = Self::load_abilities(abilities_path);
+        info!("Loaded {} abilities from {}", abilities.len(), abilities_path);
+        Arc::new(AbilityRepository { abilities })
+    }
+
+    fn load_abilities(path: &str) -> HashMap<String, AbilityData> {
+        match File::open(Path::new(path)) {
+            Ok(file) => {
+                let reader = BufReader::new(file);
+                match serde_json::from_reader(reader) {
+                    Ok(abilities) => abilities,
+                    Err(e) => {
+                        warn!("Failed to parse abilities JSON: {}", e);
+                        HashMap::new()
+                    }
+                }
+            },
+            Err(e) => {
+                warn!("Failed to open abilities file {}: {}", path, e);
+                HashMap::new()
+            }
+        }
+    }
+
+    /// Get ability data by ID
+    pub fn get_ability(&self, ability_id: &str) -> Option<&AbilityData> {
+        self.abilities.get(ability_id)
+    }
+}
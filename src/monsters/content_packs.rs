@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::{info, warn};
+
+/// An overlay file layered over the base templates and moves, e.g. for an event or regional forms.
+/// Each entry needs an `id`; its fields replace those of the base entry with that ID,
+/// and entries with a new ID are added whole.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ContentPack {
+    /// Shown in logs and conflict reports; the file path is used when missing
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub pokemons: Vec<Value>,
+    /// Keyed by move ID like moves.json; only the values are used
+    #[serde(default)]
+    pub moves: Map<String, Value>,
+}
+
+/// Two packs changing the same field of the same entry. Packs apply in order, so the later one wins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackConflict {
+    pub entry_id: u32,
+    pub field: String,
+    pub overridden_pack: String,
+    pub winning_pack: String,
+}
+
+/// Load the content packs at `paths`, in order. Packs that cannot be read are skipped with a warning.
+pub fn load_content_packs(paths: &[String]) -> Vec<ContentPack> {
+    let mut packs = Vec::new();
+    for path in paths {
+        let file = match File::open(Path::new(path)) {
+            Ok(file) => file,
+            Err(e) => {
+                warn!("Failed to open content pack {}: {}", path, e);
+                continue;
+            }
+        };
+        match serde_json::from_reader::<_, ContentPack>(BufReader::new(file)) {
+            Ok(mut pack) => {
+                if pack.name.is_empty() {
+                    pack.name = path.clone();
+                }
+                packs.push(pack);
+            }
+            Err(e) => warn!("Failed to parse content pack {}: {}", path, e),
+        }
+    }
+    packs
+}
+
+/// Layer the `kind` entries of every pack over `entries`, in pack order.
+/// Entries that would no longer parse after the overlay are left unchanged.
+pub fn apply_content_packs<'a, T: Serialize + DeserializeOwned>(
+    kind: &str,
+    entries: &mut HashMap<u32, T>,
+    packs: impl IntoIterator<Item = (&'a str, Vec<&'a Value>)>,
+) -> Vec<PackConflict> {
+    let mut conflicts = Vec::new();
+    // (entry ID, field) → pack that last changed it
+    let mut changed_by: HashMap<(u32, String), &str> = HashMap::new();
+
+    for (pack_name, pack_entries) in packs {
+        let (mut changed, mut added) = (0, 0);
+        for pack_entry in pack_entries {
+            let Some(fields) = pack_entry.as_object() else {
+                warn!("Content pack {}: skipping {} entry that is not an object", pack_name, kind);
+                continue;
+            };
+            let Some(id) = fields.get("id").and_then(Value::as_u64).and_then(|id| u32::try_from(id).ok()) else {
+                warn!("Content pack {}: skipping {} entry without a valid id", pack_name, kind);
+                continue;
+            };
+
+            let existing = entries.get(&id);
+            let mut merged = match existing.map(serde_json::to_value) {
+                Some(Ok(Value::Object(base))) => base,
+                _ => Map::new(),
+            };
+            for (field, value) in fields {
+                merged.insert(field.clone(), value.clone());
+            }
+            let entry = match serde_json::from_value::<T>(Value::Object(merged)) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    warn!("Content pack {}: ignoring invalid {} {}: {}", pack_name, kind, id, e);
+                    continue;
+                }
+            };
+
+            for field in fields.keys().filter(|field| *field != "id") {
+                if let Some(previous) = changed_by.insert((id, field.clone()), pack_name) {
+                    if previous != pack_name {
+                        conflicts.push(PackConflict {
+                            entry_id: id,
+                            field: field.clone(),
+                            overridden_pack: previous.to_string(),
+                            winning_pack: pack_name.to_string(),
+                        });
+                    }
+                }
+            }
+            if existing.is_some() {
+                changed += 1;
+            } else {
+                added += 1;
+            }
+            entries.insert(id, entry);
+        }
+        if changed + added > 0 {
+            info!("Content pack {}: changed {} and added {} {} entries", pack_name, changed, added, kind);
+        }
+    }
+
+    for conflict in &conflicts {
+        warn!(
+            "Content pack conflict: {} {} field {} set by both {} and {}; using {}",
+            kind, conflict.entry_id, conflict.field, conflict.overridden_pack, conflict.winning_pack, conflict.winning_pack
+        );
+    }
+    conflicts
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::monsters::monster_manager::MonsterTemplateRepository;
+
+/// Locale of the names and descriptions in the templates and moves files
+pub const DEFAULT_LOCALE: &str = "en";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct LocalizedText {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+/// Translations for one locale, read from `<locale>.json` in the locales directory.
+/// Anything left out falls back to English.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct LocaleTable {
+    /// Keyed by template ID
+    #[serde(default)]
+    species: HashMap<u32, LocalizedText>,
+    /// Keyed by move ID
+    #[serde(default)]
+    moves: HashMap<u32, LocalizedText>,
+}
+
+/// Species and move names and descriptions in every available locale
+#[derive(Debug, Default)]
+pub struct Locales {
+    tables: HashMap<String, LocaleTable>,
+}
+
+impl Locales {
+    /// Load every `<locale>.json` file in `directory`. Files that cannot be read are skipped with a warning.
+    pub fn load(directory: &str) -> Self {
+        let entries = match std::fs::read_dir(Path::new(directory)) {
+            Ok(entries) => entries,
+            Err(e) => {
+                info!("No locales loaded from {}: {}", directory, e);
+                return Self::default();
+            }
+        };
+        let mut tables = HashMap::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(locale) = path.file_stem().and_then(|stem| stem.to_str()).map(str::to_string) else {
+                continue;
+            };
+            if path.extension().is_none_or(|extension| extension != "json") || locale == DEFAULT_LOCALE {
+                continue;
+            }
+            let table = File::open(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|file| serde_json::from_reader::<_, LocaleTable>(BufReader::new(file)).map_err(|e| e.to_string()));
+            match table {
+                Ok(table) => {
+                    info!("Loaded locale {} ({} species, {} moves)", locale, table.species.len(), table.moves.len());
+                    tables.insert(locale, table);
+                }
+                Err(e) => warn!("Skipping locale file {}: {}", path.display(), e),
+            }
+        }
+        Self { tables }
+    }
+
+    /// Whether names can be shown in `locale`; English always can
+    pub fn is_supported(&self, locale: &str) -> bool {
+        locale == DEFAULT_LOCALE || self.tables.contains_key(locale)
+    }
+
+    pub fn species_name(&self, locale: &str, template_id: u32) -> Option<&str> {
+        self.tables.get(locale)?.species.get(&template_id)?.name.as_deref()
+    }
+
+    pub fn move_name(&self, locale: &str, move_id: u32) -> Option<&str> {
+        self.tables.get(locale)?.moves.get(&move_id)?.name.as_deref()
+    }
+
+    pub fn move_description(&self, locale: &str, move_id: u32) -> Option<&str> {
+        self.tables.get(locale)?.moves.get(&move_id)?.description.as_deref()
+    }
+
+    /// Translate the species and move names and descriptions anywhere in a serialized message.
+    /// Objects with a `move_id` get their `name` and `description` replaced; objects with a
+    /// `template_id` get their `name` replaced only while it is still the English species name,
+    /// so nicknames are left alone.
+    pub fn localize_json(&self, locale: &str, value: &mut Value, templates: &MonsterTemplateRepository) {
+        if !self.tables.contains_key(locale) {
+            return;
+        }
+        match value {
+            Value::Object(object) => {
+                if let Some(move_id) = object.get("move_id").and_then(Value::as_u64) {
+                    let move_id = move_id as u32;
+                    if let (Some(name), Some(Value::String(_))) = (self.move_name(locale, move_id), object.get("name")) {
+                        object.insert("name".to_string(), Value::String(name.to_string()));
+                    }
+                    if let (Some(description), Some(Value::String(_))) = (self.move_description(locale, move_id), object.get("description")) {
+                        object.insert("description".to_string(), Value::String(description.to_string()));
+                    }
+                } else if let Some(template_id) = object.get("template_id").and_then(Value::as_u64) {
+                    let template_id = template_id as u32;
+                    let is_species_name = match (object.get("name").and_then(Value::as_str), templates.templates.get(&template_id)) {
+                        (Some(name), Some(template)) => name.eq_ignore_ascii_case(&template.name),
+                        _ => false,
+                    };
+                    if let (true, Some(name)) = (is_species_name, self.species_name(locale, template_id)) {
+                        object.insert("name".to_string(), Value::String(name.to_string()));
+                    }
+                }
+                for child in object.values_mut() {
+                    self.localize_json(locale, child, templates);
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.localize_json(locale, item, templates);
+                }
+            }
+            _ => {}
+        }
+    }
+}
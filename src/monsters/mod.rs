@@ -1,6 +1,10 @@
 pub mod monster;
 pub mod monster_manager;
 pub mod move_manager;
+pub mod ability_manager;
+pub mod content_packs;
+pub mod locales;
 
 pub use monster::{Monster, MonsterTemplate, Position, MovementPattern, PokemonType};
-pub use move_manager::MoveRepository; 
\ No newline at end of file
+pub use move_manager::MoveRepository;
+pub use ability_manager::AbilityRepository; 
\ No newline at end of file
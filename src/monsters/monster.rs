@@ -7,15 +7,16 @@ use std::sync::Arc;
 use crate::stats::{BaseStats, CalculatedStats, calculate_stats, StatSet};
 use crate::stats::nature::Nature;
 
-use crate::combat::state::StatusCondition;
+use crate::combat::state::{StatusCondition, WeatherType};
+use crate::monsters::monster_manager::TerrainType;
 /// Represents a monster's position in the game world using tile coordinates
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct Position {
     pub x: u32,
     pub y: u32,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, Copy, schemars::JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum PokemonType {
     Normal, Fire, Water, Grass, Electric, Ice, Fighting, Poison, Ground,
@@ -33,6 +34,43 @@ pub enum MovementPattern {
     Stationary,       // Don't move
 }
 
+/// Chance that a newly spawned monster is an alpha
+pub const ALPHA_SPAWN_CHANCE: f64 = 0.02;
+/// Levels added on top of an alpha's rolled level
+const ALPHA_LEVEL_BOOST: u32 = 5;
+/// Multiplier applied to every calculated stat of an alpha
+const ALPHA_STAT_MULTIPLIER: f32 = 1.5;
+/// Multiplier applied to the experience an alpha is worth
+pub const ALPHA_REWARD_MULTIPLIER: u32 = 2;
+
+/// Modifiers rolled by the spawner on top of a regular wild spawn
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SpawnModifiers {
+    pub is_alpha: bool,
+    pub is_shiny: bool,
+    /// Lowest value any IV can roll (raised by catch chains)
+    pub iv_floor: u8,
+}
+
+/// Alternate form of a species. Fields left out fall back to the species' own values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonsterForm {
+    pub id: String,
+    #[serde(default)]
+    pub types: Option<Vec<PokemonType>>,
+    #[serde(default)]
+    pub base_stats: Option<BaseStats>,
+    /// Sprite key for clients; the species sprite is used when missing
+    #[serde(default)]
+    pub sprite: Option<String>,
+    /// Spawn point IDs where wild monsters of this species appear in this form
+    #[serde(default)]
+    pub spawn_areas: Vec<String>,
+    /// Battle weather that turns the species into this form until the weather ends
+    #[serde(default)]
+    pub battle_weather: Option<WeatherType>,
+}
+
 /// Static template defining a monster type's base properties
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MonsterTemplate {
@@ -41,6 +79,9 @@ pub struct MonsterTemplate {
     pub types: Vec<PokemonType>,
     pub abilities: Vec<String>,
     pub base_experience: u32,
+    /// How easily wild monsters of this species are caught, from 1 (hardest) to 255 (easiest)
+    #[serde(default = "default_catch_rate")]
+    pub catch_rate: u8,
     pub min_level: u32,
     pub max_level: u32,
     pub base_stats: BaseStats,
@@ -48,6 +89,60 @@ pub struct MonsterTemplate {
     pub moves: Vec<(u32, u32)>, // (move_id, level_learned)
     pub spawn_rate: f32,
     pub growth_rate: GrowthRate,
+    /// Level ranges used instead of `min_level..=max_level` in specific spawn areas (spawn point ID → (min, max))
+    #[serde(default)]
+    pub area_level_ranges: std::collections::HashMap<String, (u32, u32)>,
+    /// Wild monsters of this species start an encounter with players who stop next to them
+    #[serde(default)]
+    pub aggressive: bool,
+    #[serde(default)]
+    pub forms: Vec<MonsterForm>,
+    /// Terrain wild monsters of this species can move onto besides ground, grass and ice.
+    /// Water and Flying types cross water even when it is not listed.
+    #[serde(default)]
+    pub terrain_affinities: Vec<TerrainType>,
+}
+
+/// Catch rate of species whose template does not give one
+pub const DEFAULT_CATCH_RATE: u8 = 45;
+
+fn default_catch_rate() -> u8 {
+    DEFAULT_CATCH_RATE
+}
+
+impl MonsterTemplate {
+    /// Whether wild monsters of this species can move onto a tile of `terrain`
+    pub fn can_traverse(&self, terrain: TerrainType) -> bool {
+        match terrain {
+            TerrainType::Ground | TerrainType::Grass | TerrainType::Ice => true,
+            TerrainType::Water => self.terrain_affinities.contains(&terrain)
+                || self.types.iter().any(|pokemon_type| matches!(pokemon_type, PokemonType::Water | PokemonType::Flying)),
+            _ => self.terrain_affinities.contains(&terrain),
+        }
+    }
+
+    pub fn form(&self, form_id: &str) -> Option<&MonsterForm> {
+        self.forms.iter().find(|form| form.id == form_id)
+    }
+
+    /// Types of the given form, or of the species itself for None and unknown forms
+    pub fn types_for_form(&self, form_id: Option<&str>) -> &[PokemonType] {
+        form_id.and_then(|id| self.form(id))
+            .and_then(|form| form.types.as_deref())
+            .unwrap_or(&self.types)
+    }
+
+    /// Base stats of the given form, or of the species itself for None and unknown forms
+    pub fn base_stats_for_form(&self, form_id: Option<&str>) -> &BaseStats {
+        form_id.and_then(|id| self.form(id))
+            .and_then(|form| form.base_stats.as_ref())
+            .unwrap_or(&self.base_stats)
+    }
+
+    /// Form wild monsters of this species take when spawning at `spawn_point_id`
+    pub fn regional_form(&self, spawn_point_id: &str) -> Option<&MonsterForm> {
+        self.forms.iter().find(|form| form.spawn_areas.iter().any(|area| area == spawn_point_id))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,8 +157,60 @@ pub enum GrowthRate {
     Fast
 }
 
+impl GrowthRate {
+    /// Total experience a monster with this growth rate has accumulated on reaching `level`
+    pub fn total_exp_for_level(&self, level: u32) -> u64 {
+        let level = level as i64;
+        let total = match self {
+            GrowthRate::Fast => 4 * level.pow(3) / 5,
+            GrowthRate::Medium => level.pow(3),
+            // The medium-slow curve dips below zero at level 1
+            GrowthRate::MediumSlow => 6 * level.pow(3) / 5 - 15 * level.pow(2) + 100 * level - 140,
+            GrowthRate::Slow => 5 * level.pow(3) / 4,
+        };
+        total.max(0) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Known totals at levels 1, 2 and 100 for each curve
+    const KNOWN_TOTALS: [(GrowthRate, [u64; 3]); 4] = [
+        (GrowthRate::Fast, [0, 6, 800_000]),
+        (GrowthRate::Medium, [1, 8, 1_000_000]),
+        (GrowthRate::MediumSlow, [0, 9, 1_059_860]),
+        (GrowthRate::Slow, [1, 10, 1_250_000]),
+    ];
+
+    #[test]
+    fn totals_match_known_values() {
+        for (growth_rate, totals) in KNOWN_TOTALS {
+            for (level, total) in [1, 2, 100].into_iter().zip(totals) {
+                assert_eq!(growth_rate.total_exp_for_level(level), total, "{:?} at level {}", growth_rate, level);
+            }
+        }
+    }
+
+    #[test]
+    fn medium_slow_is_clamped_at_zero_below_level_two() {
+        assert_eq!(GrowthRate::MediumSlow.total_exp_for_level(0), 0);
+        assert_eq!(GrowthRate::MediumSlow.total_exp_for_level(1), 0);
+    }
+
+    #[test]
+    fn totals_never_decrease() {
+        for (growth_rate, _) in KNOWN_TOTALS {
+            for level in 1..100 {
+                assert!(growth_rate.total_exp_for_level(level + 1) >= growth_rate.total_exp_for_level(level), "{:?} at level {}", growth_rate, level);
+            }
+        }
+    }
+}
+
 /// Lightweight monster representation for client display
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct DisplayMonster {
     pub instance_id: String,
     pub template_id: u32,
@@ -75,6 +222,49 @@ pub struct DisplayMonster {
     pub current_hp: u32,
     pub name: String,
     pub types: Vec<PokemonType>,
+    pub form: Option<String>,
+    pub is_alpha: bool,
+    pub is_shiny: bool,
+}
+
+/// Rough indication of how hard a wild monster is to catch
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CatchDifficulty {
+    Easy,
+    Moderate,
+    Hard,
+}
+
+impl CatchDifficulty {
+    /// Difficulty for the chance of a Poké Ball. At full HP that chance is at most a third, so
+    /// the bands sit lower than they would for a weakened monster.
+    pub fn from_chance(chance: f64) -> Self {
+        if chance >= 0.25 {
+            CatchDifficulty::Easy
+        } else if chance >= 0.1 {
+            CatchDifficulty::Moderate
+        } else {
+            CatchDifficulty::Hard
+        }
+    }
+}
+
+/// Public information a player can see by examining a monster before engaging it
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct MonsterPreview {
+    pub instance_id: String,
+    pub template_id: u32,
+    pub name: String,
+    pub level: u32,
+    pub types: Vec<PokemonType>,
+    pub form: Option<String>,
+    pub is_alpha: bool,
+    pub is_shiny: bool,
+    pub in_combat: bool,
+    /// Chance a ball thrown right now would succeed, before any bait or mud
+    pub estimated_capture_chance: f64,
+    pub catch_difficulty: CatchDifficulty,
 }
 
 /// Active monster instance in the game world
@@ -92,40 +282,91 @@ pub struct Monster {
     pub current_hp: u32,
     pub status_condition: Option<StatusCondition>,
     pub types: Vec<PokemonType>,
+    /// Form ID from the template's forms; None is the species' regular form
+    #[serde(default)]
+    pub form: Option<String>,
     pub ability: String,
     pub moves: Vec<MonsterMove>,
     pub in_combat: bool,
+    /// Set once a battle has decided this monster leaves the world, until it is removed from the lobby
+    #[serde(skip)]
+    pub despawning: bool,
     pub calculated_stats: CalculatedStats,
     pub ivs: StatSet<u8>,      // Adding IVs for wild monsters similar to Pokemon
     pub evs: StatSet<u16>,     // Adding EVs for wild monsters similar to Pokemon  
     pub nature: Nature,        // Adding nature for wild monsters similar to Pokemon
+    #[serde(default)]
+    pub is_alpha: bool,        // Rare boss spawn with boosted level and stats
+    #[serde(default)]
+    pub is_shiny: bool,
+    /// Spawn point the monster came from; it wanders on a leash around that area
+    #[serde(default)]
+    pub spawn_point_id: Option<String>,
 }
 
+/// Most PP Ups that can be applied to a single move
+pub const MAX_PP_UPS: u8 = 3;
+
 /// Represents a move that a monster can use
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct MonsterMove {
     pub id: u32,
     pub pp_remaining: u8,
+    /// PP Ups applied to this move, up to `MAX_PP_UPS`. Collections stored before
+    /// PP Ups existed have no such field and load with none applied.
+    #[serde(default)]
+    pub pp_ups: u8,
+}
+
+impl MonsterMove {
+    /// Max PP of this move given the move's base PP; each PP Up adds a fifth of the base
+    pub fn max_pp(&self, base_pp: u8) -> u8 {
+        base_pp.saturating_add(base_pp / 5 * self.pp_ups.min(MAX_PP_UPS))
+    }
 }
 
 impl Monster {
-    /// Creates a new monster instance from a template at a specific position and level
+    /// Whether a player may start a battle with (or otherwise engage) this monster
+    pub fn is_available(&self) -> bool {
+        !self.in_combat && !self.despawning
+    }
+
+    /// Creates a new monster instance from a template at a specific position and level.
+    /// Alphas get a level boost, perfect IVs and scaled-up stats on top of the regular roll.
     pub fn new(
         template: &MonsterTemplate, 
+        form: Option<&MonsterForm>,
         position: Position, 
         level: u32, 
-        move_repository: Option<&Arc<crate::monsters::move_manager::MoveRepository>>
+        move_repository: Option<&Arc<crate::monsters::move_manager::MoveRepository>>,
+        modifiers: SpawnModifiers,
     ) -> Self {
-        // Generate random IVs (0-31 for each stat)
-        let ivs = StatSet {
-            hp: rand::thread_rng().gen_range(0..=31),
-            attack: rand::thread_rng().gen_range(0..=31),
-            defense: rand::thread_rng().gen_range(0..=31),
-            special_attack: rand::thread_rng().gen_range(0..=31),
-            special_defense: rand::thread_rng().gen_range(0..=31),
-            speed: rand::thread_rng().gen_range(0..=31),
+        let form_id = form.map(|form| form.id.as_str());
+        let is_alpha = modifiers.is_alpha;
+        let level = if is_alpha { (level + ALPHA_LEVEL_BOOST).min(100) } else { level };
+
+        // Generate random IVs (iv_floor-31 for each stat)
+        let iv_floor = modifiers.iv_floor.min(31);
+        let mut ivs = StatSet {
+            hp: rand::thread_rng().gen_range(iv_floor..=31),
+            attack: rand::thread_rng().gen_range(iv_floor..=31),
+            defense: rand::thread_rng().gen_range(iv_floor..=31),
+            special_attack: rand::thread_rng().gen_range(iv_floor..=31),
+            special_defense: rand::thread_rng().gen_range(iv_floor..=31),
+            speed: rand::thread_rng().gen_range(iv_floor..=31),
         };
         
+        if is_alpha {
+            ivs = StatSet {
+                hp: 31,
+                attack: 31,
+                defense: 31,
+                special_attack: 31,
+                special_defense: 31,
+                speed: 31,
+            };
+        }
+        
         // Start with zero EVs for wild monsters
         let evs = StatSet {
             hp: 0,
@@ -140,7 +381,10 @@ impl Monster {
         let nature = Nature::random();
         
         // Calculate stats using the same formula as for Pokemon
-        let calculated_stats = calculate_stats(&template.base_stats, level, &ivs, &evs, &nature);
+        let mut calculated_stats = calculate_stats(template.base_stats_for_form(form_id), level, &ivs, &evs, &nature);
+        if is_alpha {
+            calculated_stats = Self::apply_alpha_stats(calculated_stats);
+        }
         
         // Get monster moves
         let moves = if let Some(move_repo) = move_repository {
@@ -154,6 +398,7 @@ impl Monster {
                 .map(|(move_id, _)| MonsterMove {
                     id: *move_id,
                     pp_remaining: 20, // Default PP value
+                    pp_ups: 0,
                 })
                 .collect()
         };
@@ -177,13 +422,31 @@ impl Monster {
             current_hp: calculated_stats.hp, // Using the calculated HP
             status_condition: None,
             calculated_stats,
-            types: template.types.clone(),
+            types: template.types_for_form(form_id).to_vec(),
+            form: form_id.map(str::to_string),
             ability,
             moves,
             in_combat: false,
+            despawning: false,
             ivs,
             evs,
             nature,
+            is_alpha,
+            is_shiny: modifiers.is_shiny,
+            spawn_point_id: None,
+        }
+    }
+
+    /// Scale every stat of an alpha
+    pub fn apply_alpha_stats(stats: CalculatedStats) -> CalculatedStats {
+        let scale = |stat: u32| (stat as f32 * ALPHA_STAT_MULTIPLIER).round() as u32;
+        CalculatedStats {
+            hp: scale(stats.hp),
+            attack: scale(stats.attack),
+            defense: scale(stats.defense),
+            special_attack: scale(stats.special_attack),
+            special_defense: scale(stats.special_defense),
+            speed: scale(stats.speed),
         }
     }
     
@@ -200,6 +463,35 @@ impl Monster {
             current_hp: self.current_hp,
             name: self.name.clone(),
             types: self.types.clone(),
+            form: self.form.clone(),
+            is_alpha: self.is_alpha,
+            is_shiny: self.is_shiny,
+        }
+    }
+
+    /// Builds the public preview shown when a player examines this monster, estimating the
+    /// capture chance of a Poké Ball from the species' `catch_rate`
+    pub fn to_preview(&self, catch_rate: u8) -> MonsterPreview {
+        let estimated_capture_chance = crate::combat::logic::wild_battle::capture_chance(
+            self.current_hp,
+            self.calculated_stats.hp,
+            catch_rate,
+            self.status_condition,
+            &crate::combat::state::BallType::PokeBall,
+            None,
+        );
+        MonsterPreview {
+            instance_id: self.instance_id.clone(),
+            template_id: self.template_id,
+            name: self.name.clone(),
+            level: self.level,
+            types: self.types.clone(),
+            form: self.form.clone(),
+            is_alpha: self.is_alpha,
+            is_shiny: self.is_shiny,
+            in_combat: self.in_combat,
+            estimated_capture_chance,
+            catch_difficulty: CatchDifficulty::from_chance(estimated_capture_chance),
         }
     }
 } 
\ No newline at end of file
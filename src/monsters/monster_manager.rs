@@ -13,13 +13,20 @@ use tokio::sync::{RwLock, Mutex};
 use tracing::{info, warn};
 
 use crate::game_loop::pokemon_collection::Pokemon;
+use crate::game_loop::weather::OverworldWeather;
 use crate::lobby::Lobby;
-use crate::monsters::monster::MonsterMove;
-use crate::monsters::{Monster, MonsterTemplate, Position};
+use crate::monsters::monster::{GrowthRate, MonsterMove, SpawnModifiers, ALPHA_SPAWN_CHANCE};
+use crate::monsters::{content_packs, Monster, MonsterTemplate, Position};
 use crate::stats::calculate_stats;
 use crate::stats::nature::Nature;
 use crate::stats::{StatSet, BaseStats, StatName};
 
+/// Seconds before a picked-up item comes back when its map object doesn't say
+pub const DEFAULT_ITEM_RESPAWN_SECS: u64 = 600;
+
+/// Width and height of a map tile in pixels, the unit Tiled object positions are given in
+const TILE_SIZE: f64 = 32.0;
+
 pub const DEFAULT_ALLOWED_MONSTER_IDS: [u32; 50] = [
     1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26,
     27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50,
@@ -37,11 +44,32 @@ pub struct SpawnPoint {
     pub max_monsters: u32,
     pub spawn_interval_sec: u64,
     pub spawn_density: Option<f32>, // Probability weight for spawn selection
+    #[serde(default)]
+    pub min_level: Option<u32>, // Lowest level monsters spawn at in this area
+    #[serde(default)]
+    pub max_level: Option<u32>, // Highest level monsters spawn at in this area
+}
+
+impl SpawnPoint {
+    /// Levels a template can spawn at in this area: the template's range for the area (or its
+    /// default range) narrowed to the area's bounds. `None` if the two don't overlap.
+    pub fn level_range_for(&self, template: &MonsterTemplate) -> Option<(u32, u32)> {
+        let (template_min, template_max) = template
+            .area_level_ranges
+            .get(&self.id)
+            .copied()
+            .unwrap_or((template.min_level, template.max_level));
+        let min_level = template_min.max(self.min_level.unwrap_or(1));
+        let max_level = template_max.min(self.max_level.unwrap_or(u32::MAX));
+        (min_level <= max_level).then_some((min_level, max_level))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MapSpawnArea {
     pub map_id: String,
+    #[serde(default)]
+    pub min_level: Option<u32>,
     pub max_level: u32,
     pub spawn_points: Vec<SpawnPoint>,
 }
@@ -51,12 +79,142 @@ pub struct MonsterTemplates {
     pub pokemons: Vec<MonsterTemplate>,
 }
 
+/// Kinds of landmarks a player can fast travel to
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LandmarkKind {
+    Warp,
+    Heal,
+}
+
+/// A named location on the map that players unlock for fast travel by visiting it
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct Landmark {
+    pub id: String,
+    pub name: String,
+    pub kind: LandmarkKind,
+    pub tile_x: u32,
+    pub tile_y: u32,
+}
+
+/// A spot on the map where an item lies for players to pick up, coming back a while after it is taken
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ItemSpawn {
+    pub id: String,
+    pub item_id: String,
+    pub quantity: u32,
+    pub tile_x: u32,
+    pub tile_y: u32,
+    pub respawn_secs: u64,
+}
+
+/// What a player needs before they can clear a gate
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(tag = "kind", content = "id", rename_all = "snake_case")]
+pub enum GateRequirement {
+    KeyItem(String),
+    Badge(String),
+}
+
+/// Tiles that stay blocked for a player until they clear them with a key item or badge,
+/// like a tree cut down with Cut or a locked door
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct MapGate {
+    pub id: String,
+    pub requirement: GateRequirement,
+    pub tile_x: u32,
+    pub tile_y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl MapGate {
+    pub fn covers(&self, tile_x: u32, tile_y: u32) -> bool {
+        (self.tile_x..self.tile_x + self.width).contains(&tile_x) && (self.tile_y..self.tile_y + self.height).contains(&tile_y)
+    }
+
+    /// Whether the tile is on or right next to the gate
+    pub fn is_within_reach(&self, tile_x: u32, tile_y: u32) -> bool {
+        tile_x + 1 >= self.tile_x && tile_x <= self.tile_x + self.width
+            && tile_y + 1 >= self.tile_y && tile_y <= self.tile_y + self.height
+    }
+}
+
+/// Paid services an NPC on the map offers
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NpcService {
+    NameRater,
+    MoveRelearner,
+    Shopkeeper,
+}
+
+/// An NPC standing on the map that players walk up to for a service
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct MapNpc {
+    pub id: String,
+    pub service: NpcService,
+    pub tile_x: u32,
+    pub tile_y: u32,
+}
+
+/// Kind of ground a map tile has, read from the terrain layers of a Tiled map
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TerrainType {
+    #[default]
+    Ground,
+    Grass,
+    /// Players need to be able to surf to cross it
+    Water,
+    /// Players slide across it until they reach other ground or hit something
+    Ice,
+    /// One-way drops, only crossed by jumping off them in the direction they face
+    LedgeUp,
+    LedgeDown,
+    LedgeLeft,
+    LedgeRight,
+}
+
+impl TerrainType {
+    /// Tiled tile layer each terrain is painted on
+    const LAYERS: [(&'static str, TerrainType); 7] = [
+        ("grass", TerrainType::Grass),
+        ("water", TerrainType::Water),
+        ("ice", TerrainType::Ice),
+        ("ledges_up", TerrainType::LedgeUp),
+        ("ledges_down", TerrainType::LedgeDown),
+        ("ledges_left", TerrainType::LedgeLeft),
+        ("ledges_right", TerrainType::LedgeRight),
+    ];
+
+    /// Step a ledge may be jumped off in, None for terrain that isn't a ledge
+    pub fn ledge_step(self) -> Option<(i32, i32)> {
+        match self {
+            TerrainType::LedgeUp => Some((0, -1)),
+            TerrainType::LedgeDown => Some((0, 1)),
+            TerrainType::LedgeLeft => Some((-1, 0)),
+            TerrainType::LedgeRight => Some((1, 0)),
+            _ => None,
+        }
+    }
+}
+
 /// Represents collision data for a map
 #[derive(Clone)]
 pub struct ObstacleMap {
     pub width: usize,
     pub height: usize,
     pub data: Vec<bool>, // true = obstacle, false = clear
+    pub terrain: Vec<TerrainType>, // Ground everywhere no terrain layer paints
+}
+
+/// Tiles covered by an object of a Tiled object layer
+struct TileRect {
+    tile_x: u32,
+    tile_y: u32,
+    width: u32,
+    height: u32,
 }
 
 /// Cache of valid positions for a spawn area
@@ -70,6 +228,7 @@ pub struct ValidPositionsMap {
 pub struct MonsterTemplateRepository {
     pub templates: HashMap<u32, MonsterTemplate>,
     pub move_repository: Option<Arc<crate::monsters::move_manager::MoveRepository>>,
+    pub ability_repository: Option<Arc<crate::monsters::ability_manager::AbilityRepository>>,
 }
 
 /// Map-specific data for monster management
@@ -78,6 +237,11 @@ pub struct MapData {
     pub spawn_points: HashMap<String, SpawnPoint>,
     pub obstacle_map: ObstacleMap,
     pub valid_positions: HashMap<String, ValidPositionsMap>,
+    pub soil_tiles: HashSet<(u32, u32)>, // Tiles where berries can be planted
+    pub landmarks: HashMap<String, Landmark>, // Landmark ID → fast travel destination
+    pub item_spawns: HashMap<String, ItemSpawn>, // Item spawn ID → item lying on the map
+    pub gates: HashMap<String, MapGate>, // Gate ID → tiles blocked until a key item or badge clears them
+    pub npcs: HashMap<String, MapNpc>, // NPC ID → NPC offering a service
 }
 
 /// Manages monster spawning, movement, and lifecycle for a specific lobby
@@ -93,7 +257,8 @@ pub struct MonsterManagerFactory {
 }
 
 impl MonsterTemplateRepository {
-    pub async fn new(templates_path: &str) -> Arc<Self> {
+    /// Load the base templates, then layer the templates of each content pack over them in order
+    pub async fn new(templates_path: &str, content_pack_paths: &[String]) -> Arc<Self> {
         let templates = Self::load_templates(templates_path);
 
         let mut template_map = HashMap::new();
@@ -102,9 +267,20 @@ impl MonsterTemplateRepository {
             template_map.insert(template.id, template.clone());
         }
 
+        let packs = content_packs::load_content_packs(content_pack_paths);
+        let conflicts = content_packs::apply_content_packs(
+            "template",
+            &mut template_map,
+            packs.iter().map(|pack| (pack.name.as_str(), pack.pokemons.iter().collect())),
+        );
+        if !packs.is_empty() {
+            info!("Loaded {} templates with {} content packs ({} conflicts)", template_map.len(), packs.len(), conflicts.len());
+        }
+
         Arc::new(MonsterTemplateRepository {
             templates: template_map,
             move_repository: None,
+            ability_repository: None,
         })
     }
 
@@ -112,6 +288,15 @@ impl MonsterTemplateRepository {
         Arc::new(MonsterTemplateRepository {
             templates: self.templates.clone(),
             move_repository: Some(move_repository),
+            ability_repository: self.ability_repository.clone(),
+        })
+    }
+
+    pub fn with_ability_repository(self: Arc<Self>, ability_repository: Arc<crate::monsters::ability_manager::AbilityRepository>) -> Arc<Self> {
+        Arc::new(MonsterTemplateRepository {
+            templates: self.templates.clone(),
+            move_repository: self.move_repository.clone(),
+            ability_repository: Some(ability_repository),
         })
     }
 
@@ -174,7 +359,7 @@ impl MonsterTemplateRepository {
             name: template.name.clone(),
             level,
             exp: 0,
-            max_exp: (template.base_experience as f64 * 1.2_f64.powf(level as f64)) as u64,
+            max_exp: self.get_exp_for_next_level(template.id, level),
             current_hp: stats.hp,  // Full HP for a new Pokemon
             ivs,
             evs,
@@ -182,8 +367,11 @@ impl MonsterTemplateRepository {
             capture_date: chrono::Utc::now().timestamp() as u64,
             moves,
             types: template.types.clone(),
+            form: None,
             ability,
             status_condition: None,
+            is_shiny: false,
+            original_trainer: None,
         }
     }
 
@@ -253,39 +441,69 @@ impl MonsterTemplateRepository {
                 MonsterMove {
                     id: move_id,
                     pp_remaining: default_pp,
+                    pp_ups: 0,
                 }
             })
             .collect()
     }
     
-    fn get_max_exp_for_level(&self, template_id: u32, level: u32) -> u64 {
-        let template = self.templates.get(&template_id).expect("Template not found");
-        
-        match template.growth_rate {
-            crate::monsters::monster::GrowthRate::Fast => {
-                (4 * level.pow(3)) as u64 / 5
-            },
-            crate::monsters::monster::GrowthRate::Medium => {
-                level.pow(3) as u64
-            },
-            crate::monsters::monster::GrowthRate::MediumSlow => {
-                ((6 * level.pow(3)) / 5 - 15 * level.pow(2) + 100 * level - 140) as u64
-            },
-            crate::monsters::monster::GrowthRate::Slow => {
-                (5 * level.pow(3)) as u64 / 4
-            }
+    /// Total experience needed to reach `level`, following the template's growth rate.
+    /// Unknown templates fall back to the medium curve.
+    pub fn total_exp_for_level(&self, template_id: u32, level: u32) -> u64 {
+        match self.templates.get(&template_id) {
+            Some(template) => template.growth_rate.total_exp_for_level(level),
+            None => GrowthRate::Medium.total_exp_for_level(level),
         }
     }
     
+    /// Experience needed to go from `current_level` to the next level (the `max_exp` of a monster at that level)
     pub fn get_exp_for_next_level(&self, template_id: u32, current_level: u32) -> u64 {
-        if current_level == 0 {
-            return self.get_max_exp_for_level(template_id, 1);
-        }
-        
-        let current_total_exp = self.get_max_exp_for_level(template_id, current_level);
-        let next_level_total_exp = self.get_max_exp_for_level(template_id, current_level + 1);
+        let current_total_exp = self.total_exp_for_level(template_id, current_level.max(1));
+        let next_level_total_exp = self.total_exp_for_level(template_id, current_level.max(1) + 1);
         
-        next_level_total_exp - current_total_exp
+        // Never zero, so leveling loops always consume experience
+        next_level_total_exp.saturating_sub(current_total_exp).max(1)
+    }
+}
+
+#[cfg(test)]
+impl MonsterTemplateRepository {
+    /// Every species in resources/pokemon.json, without moves or abilities
+    pub(crate) fn for_tests() -> Self {
+        let templates = Self::load_templates("resources/pokemon.json").pokemons.into_iter()
+            .map(|template| (template.id, template))
+            .collect();
+        MonsterTemplateRepository { templates, move_repository: None, ability_repository: None }
+    }
+}
+
+#[cfg(test)]
+impl MapData {
+    /// Map of `width` by `height` ground tiles with obstacles on `obstacles` and nothing else
+    pub(crate) fn for_tests(width: usize, height: usize, obstacles: &[(u32, u32)]) -> Self {
+        let mut data = vec![false; width * height];
+        for &(x, y) in obstacles {
+            data[y as usize * width + x as usize] = true;
+        }
+        MapData {
+            map_id: "test_map".to_string(),
+            spawn_points: HashMap::new(),
+            obstacle_map: ObstacleMap { width, height, data, terrain: vec![TerrainType::Ground; width * height] },
+            valid_positions: HashMap::new(),
+            soil_tiles: HashSet::new(),
+            landmarks: HashMap::new(),
+            item_spawns: HashMap::new(),
+            gates: HashMap::new(),
+            npcs: HashMap::new(),
+        }
+    }
+
+    /// Paint `terrain` on each of `tiles`
+    pub(crate) fn with_terrain(mut self, terrain: TerrainType, tiles: &[(u32, u32)]) -> Self {
+        for &(x, y) in tiles {
+            self.obstacle_map.terrain[y as usize * self.obstacle_map.width + x as usize] = terrain;
+        }
+        self
     }
 }
 
@@ -295,10 +513,26 @@ impl MapData {
             return Err(format!("Map file not found: {}", map_path));
         }
 
-        let obstacle_map = Self::load_obstacle_map(map_path);
+        let file = File::open(Path::new(map_path)).expect("Failed to open map file");
+        let reader = BufReader::new(file);
+        let map_data: serde_json::Value =
+            serde_json::from_reader(reader).expect("Failed to parse map JSON");
+        let layers = map_data["layers"].as_array().map(Vec::as_slice).unwrap_or_default();
+
+        let obstacle_map = Self::load_obstacle_map(&map_data);
         let (spawn_points, valid_positions) =
-            Self::generate_spawn_points_from_map(map_path, &obstacle_map);
+            Self::generate_spawn_points_from_map(map_path, &map_data, &obstacle_map);
         info!("No of Spawn points: {:?}", spawn_points.len());
+        let soil_tiles = Self::load_soil_tiles(layers, &obstacle_map);
+        info!("No of berry soil tiles: {:?}", soil_tiles.len());
+        let landmarks = Self::load_landmarks(map_path, layers);
+        info!("No of landmarks: {:?}", landmarks.len());
+        let item_spawns = Self::load_item_spawns(map_path, layers, &obstacle_map);
+        info!("No of item spawns: {:?}", item_spawns.len());
+        let gates = Self::load_gates(map_path, layers);
+        info!("No of gates: {:?}", gates.len());
+        let npcs = Self::load_npcs(map_path, layers);
+        info!("No of NPCs: {:?}", npcs.len());
         let mut spawn_point_map = HashMap::new();
         for spawn_point in &spawn_points {
             spawn_point_map.insert(spawn_point.id.clone(), spawn_point.clone());
@@ -309,16 +543,21 @@ impl MapData {
             spawn_points: spawn_point_map,
             obstacle_map,
             valid_positions,
+            soil_tiles,
+            landmarks,
+            item_spawns,
+            gates,
+            npcs,
         })
     }
 
-    /// Loads obstacle data from a Tiled map file
-    fn load_obstacle_map(map_path: &str) -> ObstacleMap {
-        let file = File::open(Path::new(map_path)).expect("Failed to open map file");
-        let reader = BufReader::new(file);
-        let map_data: serde_json::Value =
-            serde_json::from_reader(reader).expect("Failed to parse map JSON");
+    /// Whether a berry can be planted on this tile
+    pub fn is_soil_tile(&self, tile_x: u32, tile_y: u32) -> bool {
+        self.soil_tiles.contains(&(tile_x, tile_y))
+    }
 
+    /// Loads obstacle data from a parsed Tiled map
+    fn load_obstacle_map(map_data: &serde_json::Value) -> ObstacleMap {
         let width = map_data["width"].as_u64().unwrap_or(70) as usize;
         let height = map_data["height"].as_u64().unwrap_or(70) as usize;
 
@@ -340,83 +579,307 @@ impl MapData {
             }
         }
 
+        // Later terrain layers win where several paint the same tile
+        let mut terrain = vec![TerrainType::Ground; width * height];
+        for layer in layers {
+            let Some(terrain_type) = TerrainType::LAYERS.iter()
+                .find(|(name, _)| layer["name"].as_str() == Some(name))
+                .map(|(_, terrain_type)| *terrain_type)
+            else {
+                continue;
+            };
+            if let Some(data) = layer["data"].as_array() {
+                for (i, tile) in data.iter().enumerate().take(width * height) {
+                    if tile.as_u64().unwrap_or(0) != 0 {
+                        terrain[i] = terrain_type;
+                    }
+                }
+            }
+        }
+
         ObstacleMap {
             width,
             height,
             data: obstacle_data,
+            terrain,
+        }
+    }
+
+    /// Objects of the Tiled object layer called `name`, empty if the map doesn't have it
+    fn object_layer<'a>(layers: &'a [serde_json::Value], name: &str) -> &'a [serde_json::Value] {
+        layers.iter()
+            .find(|layer| layer["name"].as_str() == Some(name))
+            .and_then(|layer| layer["objects"].as_array())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Tiles a Tiled object covers, converted from its pixel position and size. Points and
+    /// objects smaller than a tile cover the one tile they start on.
+    fn object_tile_rect(object: &serde_json::Value) -> TileRect {
+        let tiles = |key: &str, default: f64| (object[key].as_f64().unwrap_or(default) / TILE_SIZE) as u32;
+        TileRect {
+            tile_x: tiles("x", 0.0),
+            tile_y: tiles("y", 0.0),
+            width: tiles("width", TILE_SIZE).max(1),
+            height: tiles("height", TILE_SIZE).max(1),
         }
     }
 
-    /// Extracts spawn point data from a Tiled map file and generates valid positions
+    /// Loads plantable soil tiles from the "berry_soil" object layer of a Tiled map
+    fn load_soil_tiles(layers: &[serde_json::Value], obstacle_map: &ObstacleMap) -> HashSet<(u32, u32)> {
+        let mut soil_tiles = HashSet::new();
+        for object in Self::object_layer(layers, "berry_soil") {
+            let rect = Self::object_tile_rect(object);
+            for tile_x in rect.tile_x..rect.tile_x + rect.width {
+                for tile_y in rect.tile_y..rect.tile_y + rect.height {
+                    if tile_x as usize >= obstacle_map.width || tile_y as usize >= obstacle_map.height {
+                        continue;
+                    }
+                    let index = (tile_y as usize) * obstacle_map.width + (tile_x as usize);
+                    if !obstacle_map.data[index] {
+                        soil_tiles.insert((tile_x, tile_y));
+                    }
+                }
+            }
+        }
+
+        soil_tiles
+    }
+
+    /// Loads fast travel landmarks from the "landmarks" object layer of a Tiled map
+    fn load_landmarks(map_path: &str, layers: &[serde_json::Value]) -> HashMap<String, Landmark> {
+        let mut landmarks = HashMap::new();
+        for object in Self::object_layer(layers, "landmarks") {
+            let id = match object["name"].as_str() {
+                Some(id) if !id.is_empty() => id.to_string(),
+                _ => {
+                    warn!("Skipping landmark without a name in {}", map_path);
+                    continue;
+                }
+            };
+
+            let mut name = id.clone();
+            let mut kind = LandmarkKind::Warp;
+            if let Some(properties) = object.get("properties").and_then(|p| p.as_array()) {
+                for prop in properties {
+                    match (prop.get("name").and_then(|v| v.as_str()), prop.get("value").and_then(|v| v.as_str())) {
+                        (Some("display_name"), Some(value)) => name = value.to_string(),
+                        (Some("kind"), Some("heal")) => kind = LandmarkKind::Heal,
+                        _ => {}
+                    }
+                }
+            }
+
+            let rect = Self::object_tile_rect(object);
+            let landmark = Landmark {
+                id: id.clone(),
+                name,
+                kind,
+                tile_x: rect.tile_x,
+                tile_y: rect.tile_y,
+            };
+            landmarks.insert(id, landmark);
+        }
+
+        landmarks
+    }
+
+    /// Loads item pickups from the "item_spawns" object layer of a Tiled map. Each object
+    /// needs an `item_id` property and may set `quantity` and `respawn_secs`.
+    fn load_item_spawns(
+        map_path: &str,
+        layers: &[serde_json::Value],
+        obstacle_map: &ObstacleMap,
+    ) -> HashMap<String, ItemSpawn> {
+        let mut item_spawns = HashMap::new();
+        for object in Self::object_layer(layers, "item_spawns") {
+            let mut item_id = None;
+            let mut quantity = 1;
+            let mut respawn_secs = DEFAULT_ITEM_RESPAWN_SECS;
+            if let Some(properties) = object.get("properties").and_then(|p| p.as_array()) {
+                for prop in properties {
+                    let value = &prop["value"];
+                    match prop.get("name").and_then(|v| v.as_str()) {
+                        Some("item_id") => item_id = value.as_str().map(str::to_string),
+                        Some("quantity") => quantity = value.as_u64().unwrap_or(1).max(1) as u32,
+                        Some("respawn_secs") => respawn_secs = value.as_u64().unwrap_or(DEFAULT_ITEM_RESPAWN_SECS),
+                        _ => {}
+                    }
+                }
+            }
+            let Some(item_id) = item_id.filter(|item_id| !item_id.is_empty()) else {
+                warn!("Skipping item spawn without an item_id in {}", map_path);
+                continue;
+            };
+
+            let TileRect { tile_x, tile_y, .. } = Self::object_tile_rect(object);
+            if tile_x as usize >= obstacle_map.width || tile_y as usize >= obstacle_map.height
+                || obstacle_map.data[tile_y as usize * obstacle_map.width + tile_x as usize] {
+                warn!("Skipping item spawn on a blocked tile ({}, {}) in {}", tile_x, tile_y, map_path);
+                continue;
+            }
+
+            // Fall back to the Tiled object ID when the object isn't named
+            let id = match object["name"].as_str() {
+                Some(name) if !name.is_empty() => name.to_string(),
+                _ => format!("item_{}", object["id"].as_u64().unwrap_or(item_spawns.len() as u64)),
+            };
+            item_spawns.insert(id.clone(), ItemSpawn { id, item_id, quantity, tile_x, tile_y, respawn_secs });
+        }
+
+        item_spawns
+    }
+
+    /// Loads gates from the "gates" object layer of a Tiled map. Each object needs a name
+    /// and either a `required_item` or a `required_badge` property.
+    fn load_gates(map_path: &str, layers: &[serde_json::Value]) -> HashMap<String, MapGate> {
+        let mut gates = HashMap::new();
+        for object in Self::object_layer(layers, "gates") {
+            let id = match object["name"].as_str() {
+                Some(id) if !id.is_empty() => id.to_string(),
+                _ => {
+                    warn!("Skipping gate without a name in {}", map_path);
+                    continue;
+                }
+            };
+
+            let mut requirement = None;
+            if let Some(properties) = object.get("properties").and_then(|p| p.as_array()) {
+                for prop in properties {
+                    match (prop.get("name").and_then(|v| v.as_str()), prop.get("value").and_then(|v| v.as_str())) {
+                        (Some("required_item"), Some(value)) => requirement = Some(GateRequirement::KeyItem(value.to_string())),
+                        (Some("required_badge"), Some(value)) => requirement = Some(GateRequirement::Badge(value.to_string())),
+                        _ => {}
+                    }
+                }
+            }
+            let Some(requirement) = requirement else {
+                warn!("Skipping gate {} without a required_item or required_badge in {}", id, map_path);
+                continue;
+            };
+
+            let rect = Self::object_tile_rect(object);
+            let gate = MapGate {
+                id: id.clone(),
+                requirement,
+                tile_x: rect.tile_x,
+                tile_y: rect.tile_y,
+                width: rect.width,
+                height: rect.height,
+            };
+            gates.insert(id, gate);
+        }
+
+        gates
+    }
+
+    /// Loads service NPCs from the "npcs" object layer of a Tiled map. Each object needs a name
+    /// and a `service` property.
+    fn load_npcs(map_path: &str, layers: &[serde_json::Value]) -> HashMap<String, MapNpc> {
+        let mut npcs = HashMap::new();
+        for object in Self::object_layer(layers, "npcs") {
+            let id = match object["name"].as_str() {
+                Some(id) if !id.is_empty() => id.to_string(),
+                _ => {
+                    warn!("Skipping NPC without a name in {}", map_path);
+                    continue;
+                }
+            };
+
+            let service = object.get("properties").and_then(|p| p.as_array())
+                .and_then(|properties| properties.iter().find(|prop| prop["name"].as_str() == Some("service")))
+                .and_then(|prop| serde_json::from_value::<NpcService>(prop["value"].clone()).ok());
+            let Some(service) = service else {
+                warn!("Skipping NPC {} without a known service in {}", id, map_path);
+                continue;
+            };
+
+            let rect = Self::object_tile_rect(object);
+            let npc = MapNpc {
+                id: id.clone(),
+                service,
+                tile_x: rect.tile_x,
+                tile_y: rect.tile_y,
+            };
+            npcs.insert(id, npc);
+        }
+
+        npcs
+    }
+
+    /// Extracts spawn point data from a parsed Tiled map and generates valid positions
     fn generate_spawn_points_from_map(
         map_path: &str,
+        map_data: &serde_json::Value,
         obstacle_map: &ObstacleMap,
     ) -> (Vec<SpawnPoint>, HashMap<String, ValidPositionsMap>) {
-        let file = File::open(Path::new(map_path)).expect("Failed to open map file");
-        let reader = BufReader::new(file);
-        let map_data: serde_json::Value =
-            serde_json::from_reader(reader).expect("Failed to parse map JSON");
-
         let mut spawn_points = Vec::new();
         let mut valid_positions_map = HashMap::new();
 
-        let layers = map_data["layers"].as_array().unwrap();
-        for layer in layers {
-            if let Some(name) = layer["name"].as_str() {
-                if name == "monster_spawn" {
-                    if let Some(objects) = layer["objects"].as_array() {
-                        for (i, object) in objects.iter().enumerate() {
-                            let id = format!("spawn_area_{}", i + 1);
-
-                            // Convert pixel coordinates to tile coordinates (32px tile size)
-                            let x = (object["x"].as_f64().unwrap() / 32.0) as u32;
-                            let y = (object["y"].as_f64().unwrap() / 32.0) as u32;
-                            let width = (object["width"].as_f64().unwrap() / 32.0) as u32;
-                            let height = (object["height"].as_f64().unwrap() / 32.0) as u32;
-
-                            let mut spawn_density = None;
-                            if let Some(properties) =
-                                object.get("properties").and_then(|p| p.as_array())
-                            {
-                                for prop in properties {
-                                    if let (Some("spawn_density"), Some(value)) = (
-                                        prop.get("name").and_then(|v| v.as_str()),
-                                        prop.get("value").and_then(|v| v.as_f64()),
-                                    ) {
-                                        spawn_density = Some(value as f32);
-                                    }
-                                }
-                            }
+        // Map-wide level bounds (the map's spawn area) apply on top of each spawn point's own
+        let map_level_property = |name: &str| {
+            map_data["properties"].as_array()?.iter()
+                .find(|prop| prop["name"].as_str() == Some(name))?
+                .get("value")?
+                .as_f64()
+                .map(|value| value as u32)
+        };
+        let map_spawn_area = MapSpawnArea {
+            map_id: map_path.to_string(),
+            min_level: map_level_property("min_level"),
+            max_level: map_level_property("max_level").unwrap_or(u32::MAX),
+            spawn_points: Vec::new(),
+        };
 
-                            let spawn_point = SpawnPoint {
-                                id: id.clone(),
-                                tile_x: x,
-                                tile_y: y,
-                                width,
-                                height,
-                                allowed_monsters: DEFAULT_ALLOWED_MONSTER_IDS.to_vec(),
-                                max_monsters: 3,
-                                spawn_interval_sec: 15,
-                                spawn_density,
-                            };
-
-                            let valid_positions =
-                                Self::generate_valid_positions(&spawn_point, obstacle_map);
-
-                            valid_positions_map.insert(
-                                id.clone(),
-                                ValidPositionsMap {
-                                    spawn_point_id: id.clone(),
-                                    valid_positions,
-                                },
-                            );
-
-                            spawn_points.push(spawn_point);
-                        }
+        let layers = map_data["layers"].as_array().map(Vec::as_slice).unwrap_or_default();
+        for (i, object) in Self::object_layer(layers, "monster_spawn").iter().enumerate() {
+            let id = format!("spawn_area_{}", i + 1);
+            let rect = Self::object_tile_rect(object);
+
+            let mut spawn_density = None;
+            let mut min_level = None;
+            let mut max_level = None;
+            if let Some(properties) =
+                object.get("properties").and_then(|p| p.as_array())
+            {
+                for prop in properties {
+                    let value = prop.get("value").and_then(|v| v.as_f64());
+                    match (prop.get("name").and_then(|v| v.as_str()), value) {
+                        (Some("spawn_density"), Some(value)) => spawn_density = Some(value as f32),
+                        (Some("min_level"), Some(value)) => min_level = Some(value as u32),
+                        (Some("max_level"), Some(value)) => max_level = Some(value as u32),
+                        _ => {}
                     }
-                    break;
                 }
             }
+
+            let spawn_point = SpawnPoint {
+                id: id.clone(),
+                tile_x: rect.tile_x,
+                tile_y: rect.tile_y,
+                width: rect.width,
+                height: rect.height,
+                allowed_monsters: DEFAULT_ALLOWED_MONSTER_IDS.to_vec(),
+                max_monsters: 3,
+                spawn_interval_sec: 15,
+                spawn_density,
+                min_level: min_level.max(map_spawn_area.min_level),
+                max_level: Some(max_level.unwrap_or(u32::MAX).min(map_spawn_area.max_level)),
+            };
+
+            let valid_positions =
+                Self::generate_valid_positions(&spawn_point, obstacle_map);
+
+            valid_positions_map.insert(
+                id.clone(),
+                ValidPositionsMap {
+                    spawn_point_id: id.clone(),
+                    valid_positions,
+                },
+            );
+
+            spawn_points.push(spawn_point);
         }
 
         if spawn_points.is_empty() {
@@ -431,6 +894,8 @@ impl MapData {
                 max_monsters: 3,
                 spawn_interval_sec: 15,
                 spawn_density: None,
+                min_level: map_spawn_area.min_level,
+                max_level: Some(map_spawn_area.max_level),
             };
 
             let valid_positions =
@@ -487,6 +952,62 @@ impl MapData {
         !self.obstacle_map.data.get(index).copied().unwrap_or(true)
     }
 
+    /// Gate covering a tile, if any
+    pub fn gate_at(&self, tile_x: u32, tile_y: u32) -> Option<&MapGate> {
+        self.gates.values().find(|gate| gate.covers(tile_x, tile_y))
+    }
+
+    /// Terrain of a tile, Ground outside the map
+    pub fn terrain_at(&self, tile_x: u32, tile_y: u32) -> TerrainType {
+        if tile_x as usize >= self.obstacle_map.width || tile_y as usize >= self.obstacle_map.height {
+            return TerrainType::Ground;
+        }
+        let index = tile_y as usize * self.obstacle_map.width + tile_x as usize;
+        self.obstacle_map.terrain.get(index).copied().unwrap_or_default()
+    }
+
+    /// Where a player stepping from `from` to the adjacent tile `to` ends up, or None if the
+    /// terrain doesn't allow the step. Ledges are only jumped off in the direction they face,
+    /// landing on the tile past them; ice keeps the player sliding until they reach other
+    /// terrain or the next tile is blocked. `is_blocked` reports tiles taken by monsters.
+    pub fn resolve_player_step(
+        &self,
+        from: (u32, u32),
+        to: (u32, u32),
+        can_surf: bool,
+        is_blocked: impl Fn((u32, u32)) -> bool,
+    ) -> Option<(u32, u32)> {
+        if from == to {
+            return Some(to);
+        }
+        let step = (to.0 as i64 - from.0 as i64, to.1 as i64 - from.1 as i64);
+        let next = |(x, y): (u32, u32)| Some((x.checked_add_signed(step.0 as i32)?, y.checked_add_signed(step.1 as i32)?));
+        let can_stand = |tile: (u32, u32)| self.is_valid_position(tile.0, tile.1)
+            && !is_blocked(tile)
+            && (can_surf || self.terrain_at(tile.0, tile.1) != TerrainType::Water);
+
+        if !can_stand(to) {
+            return None;
+        }
+        match self.terrain_at(to.0, to.1).ledge_step() {
+            Some(ledge_step) if ledge_step != (step.0 as i32, step.1 as i32) => return None,
+            Some(_) => {
+                let landing = next(to).filter(|landing| can_stand(*landing) && self.terrain_at(landing.0, landing.1).ledge_step().is_none())?;
+                return Some(landing);
+            }
+            None => {}
+        }
+
+        let mut position = to;
+        while self.terrain_at(position.0, position.1) == TerrainType::Ice {
+            match next(position).filter(|tile| can_stand(*tile) && self.terrain_at(tile.0, tile.1).ledge_step().is_none()) {
+                Some(tile) => position = tile,
+                None => break,
+            }
+        }
+        Some(position)
+    }
+
     /// Checks if position is in the pre-computed valid positions set
     pub fn is_position_in_valid_set(&self, spawn_point_id: &str, tile_x: u32, tile_y: u32) -> bool {
         if let Some(valid_pos_map) = self.valid_positions.get(spawn_point_id) {
@@ -497,8 +1018,8 @@ impl MapData {
 }
 
 impl MonsterManagerFactory {
-    pub async fn new(templates_path: &str) -> Arc<Self> {
-        let template_repository = MonsterTemplateRepository::new(templates_path).await;
+    pub async fn new(templates_path: &str, content_pack_paths: &[String]) -> Arc<Self> {
+        let template_repository = MonsterTemplateRepository::new(templates_path, content_pack_paths).await;
 
         Arc::new(MonsterManagerFactory {
             template_repository,
@@ -508,9 +1029,10 @@ impl MonsterManagerFactory {
 
     pub async fn new_with_move_repository(
         templates_path: &str,
+        content_pack_paths: &[String],
         move_repository: Arc<crate::monsters::move_manager::MoveRepository>,
     ) -> Arc<Self> {
-        let template_repository = MonsterTemplateRepository::new(templates_path).await;
+        let template_repository = MonsterTemplateRepository::new(templates_path, content_pack_paths).await;
         let template_repository = template_repository.with_move_repository(move_repository);
 
         Arc::new(MonsterManagerFactory {
@@ -533,6 +1055,11 @@ impl MonsterManagerFactory {
                     spawn_points: map_data.spawn_points.clone(),
                     obstacle_map: map_data.obstacle_map.clone(),
                     valid_positions: map_data.valid_positions.clone(),
+                    soil_tiles: map_data.soil_tiles.clone(),
+                    landmarks: map_data.landmarks.clone(),
+                    item_spawns: map_data.item_spawns.clone(),
+                    gates: map_data.gates.clone(),
+                    npcs: map_data.npcs.clone(),
                 },
             }));
         }
@@ -556,6 +1083,11 @@ impl MonsterManagerFactory {
                 spawn_points: map_data.spawn_points.clone(),
                 obstacle_map: map_data.obstacle_map.clone(),
                 valid_positions: map_data.valid_positions.clone(),
+                soil_tiles: map_data.soil_tiles.clone(),
+                landmarks: map_data.landmarks.clone(),
+                item_spawns: map_data.item_spawns.clone(),
+                gates: map_data.gates.clone(),
+                npcs: map_data.npcs.clone(),
             },
         }))
     }
@@ -595,12 +1127,14 @@ impl MonsterManager {
         None
     }
 
-    /// Creates a new monster at the specified spawn point
+    /// Creates a new monster at the specified spawn point.
+    /// Monsters given a lifetime are removed by the spawner's rebalancer once it runs out.
     pub async fn spawn_monster(
         &self,
         template_id: u32,
         spawn_point_id: &str,
         lobby: &Arc<Lobby>,
+        lifetime_secs: Option<u64>,
     ) -> Option<Monster> {
         let template = match self.template_repository.templates.get(&template_id) {
             Some(template) => template,
@@ -641,9 +1175,18 @@ impl MonsterManager {
             }
         };
 
-        // Determine a random level for the monster
-        let min_level = template.min_level;
-        let max_level = template.max_level;
+        // Determine a random level for the monster within both the template's and the area's bounds
+        let (min_level, max_level) = match spawn_point.level_range_for(template) {
+            Some(range) => range,
+            None => {
+                tracing::warn!(
+                    "Template {} has no level range allowed at spawn point {}",
+                    template_id,
+                    spawn_point_id
+                );
+                return None;
+            }
+        };
         let level = if min_level == max_level {
             min_level
         } else {
@@ -652,17 +1195,31 @@ impl MonsterManager {
         };
 
         // Create a new monster instance, passing the move repository if available
-        let monster = Monster::new(
+        // Roll alpha/shiny, with catch chains on this species improving shiny odds and IVs
+        let mut rng = SmallRng::from_entropy();
+        let chain_bonus = lobby.catch_chains.bonus_for(template_id);
+        let modifiers = SpawnModifiers {
+            is_alpha: rng.gen_bool(ALPHA_SPAWN_CHANCE),
+            is_shiny: rng.gen_bool(chain_bonus.shiny_chance),
+            iv_floor: chain_bonus.iv_floor,
+        };
+        // Species with regional forms spawn in the form of this area
+        let mut monster = Monster::new(
             template, 
+            template.regional_form(spawn_point_id),
             position, 
             level, 
             self.template_repository.move_repository.as_ref(),
+            modifiers,
         );
+        monster.despawn_time = lifetime_secs.map(|lifetime| monster.spawn_time + lifetime);
+        monster.spawn_point_id = Some(spawn_point_id.to_string());
 
         // Update lobby's active monsters
         lobby
             .active_monsters
             .insert(monster.instance_id.clone(), Arc::new(Mutex::new(monster.clone())));
+        lobby.occupancy.place_monster(&monster.instance_id, (monster.position.x, monster.position.y));
 
         // Add to spawn point mapping in the lobby
         lobby
@@ -683,6 +1240,11 @@ impl MonsterManager {
     /// Removes a monster from a lobby
     pub async fn despawn_monster(&self, instance_id: &str, lobby: &Arc<Lobby>) -> Option<Arc<Mutex<Monster>>> {
         let monster = lobby.active_monsters.remove(instance_id)?.1; // Extract the Monster from the tuple
+        let tile = {
+            let monster = monster.lock().await;
+            (monster.position.x, monster.position.y)
+        };
+        lobby.occupancy.remove_monster(instance_id, tile);
 
         // Find all spawn points that contain this monster
         let spawn_points: Vec<String> = lobby
@@ -705,17 +1267,22 @@ impl MonsterManager {
         Some(monster)
     }
 
-    /// Selects a random monster type based on spawn rate weighting
+    /// Selects a random monster type based on spawn rate weighting, favoring species suited to the weather.
+    /// Species rejected by `is_available` (e.g. on respawn cooldown) are never picked.
     pub fn get_random_monster_for_spawn_point(
         &self,
         spawn_point_id: &str,
+        weather: OverworldWeather,
+        is_available: impl Fn(u32) -> bool,
     ) -> Option<&MonsterTemplate> {
         let spawn_point = self.map_data.spawn_points.get(spawn_point_id)?;
 
         let allowed_templates: Vec<&MonsterTemplate> = spawn_point
             .allowed_monsters
             .iter()
+            .filter(|id| is_available(**id))
             .filter_map(|id| self.template_repository.templates.get(id))
+            .filter(|template| spawn_point.level_range_for(template).is_some())
             .collect();
 
         if allowed_templates.is_empty() {
@@ -723,13 +1290,14 @@ impl MonsterManager {
         }
 
         // Weighted random selection
-        let total_spawn_rate: f32 = allowed_templates.iter().map(|t| t.spawn_rate).sum();
+        let spawn_weight = |t: &MonsterTemplate| t.spawn_rate * weather.spawn_multiplier(&t.types);
+        let total_spawn_rate: f32 = allowed_templates.iter().map(|t| spawn_weight(t)).sum();
         let mut rng = SmallRng::from_entropy();
         let random_value = rng.gen_range(0.0..total_spawn_rate);
 
         let mut cumulative = 0.0;
         for template in &allowed_templates {
-            cumulative += template.spawn_rate;
+            cumulative += spawn_weight(template);
             if random_value <= cumulative {
                 return Some(template);
             }
@@ -773,3 +1341,166 @@ impl MonsterManager {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GROWTH_RATES: [GrowthRate; 4] = [GrowthRate::Fast, GrowthRate::Medium, GrowthRate::MediumSlow, GrowthRate::Slow];
+
+    fn first_species() -> MonsterTemplate {
+        MonsterTemplateRepository::load_templates("resources/pokemon.json").pokemons.remove(0)
+    }
+
+    /// Copies of the first species, one per growth rate, with IDs from 1000 up
+    fn repository_with_every_growth_rate() -> MonsterTemplateRepository {
+        let species = first_species();
+        let templates = GROWTH_RATES.into_iter().enumerate()
+            .map(|(index, growth_rate)| {
+                let id = 1000 + index as u32;
+                (id, MonsterTemplate { id, growth_rate, ..species.clone() })
+            })
+            .collect();
+        MonsterTemplateRepository { templates, move_repository: None, ability_repository: None }
+    }
+
+    #[test]
+    fn exp_for_next_level_is_never_zero() {
+        let repository = repository_with_every_growth_rate();
+        for &template_id in repository.templates.keys() {
+            for level in 0..=100 {
+                assert!(repository.get_exp_for_next_level(template_id, level) > 0, "template {} at level {}", template_id, level);
+            }
+        }
+    }
+
+    #[test]
+    fn exp_for_next_level_follows_the_template_growth_rate() {
+        let repository = repository_with_every_growth_rate();
+        // Medium-slow goes from 0 at level 1 (clamped) to 9 at level 2
+        assert_eq!(repository.get_exp_for_next_level(1002, 1), 9);
+        assert_eq!(repository.get_exp_for_next_level(1001, 1), 7);
+        assert_eq!(repository.get_exp_for_next_level(1000, 99), 800_000 - 4 * 99u64.pow(3) / 5);
+    }
+
+    fn species_spawning_at(min_level: u32, max_level: u32, area_level_ranges: &[(&str, (u32, u32))]) -> MonsterTemplate {
+        MonsterTemplate {
+            min_level,
+            max_level,
+            area_level_ranges: area_level_ranges.iter().map(|(area, range)| (area.to_string(), *range)).collect(),
+            ..first_species()
+        }
+    }
+
+    fn area(id: &str, min_level: Option<u32>, max_level: Option<u32>) -> SpawnPoint {
+        SpawnPoint {
+            id: id.to_string(),
+            tile_x: 0,
+            tile_y: 0,
+            width: 10,
+            height: 10,
+            allowed_monsters: Vec::new(),
+            max_monsters: 5,
+            spawn_interval_sec: 30,
+            spawn_density: None,
+            min_level,
+            max_level,
+        }
+    }
+
+    #[test]
+    fn level_range_follows_the_template_in_unbounded_areas() {
+        let species = species_spawning_at(3, 12, &[]);
+        assert_eq!(area("meadow", None, None).level_range_for(&species), Some((3, 12)));
+    }
+
+    #[test]
+    fn level_range_is_narrowed_to_the_area_bounds() {
+        let species = species_spawning_at(1, 100, &[]);
+        assert_eq!(area("meadow", Some(10), Some(20)).level_range_for(&species), Some((10, 20)));
+        assert_eq!(area("meadow", Some(10), None).level_range_for(&species), Some((10, 100)));
+        assert_eq!(area("meadow", None, Some(20)).level_range_for(&species), Some((1, 20)));
+        let species = species_spawning_at(15, 30, &[]);
+        assert_eq!(area("meadow", Some(10), Some(20)).level_range_for(&species), Some((15, 20)));
+    }
+
+    #[test]
+    fn level_range_uses_the_template_range_for_the_area() {
+        let species = species_spawning_at(3, 12, &[("cave", (25, 35))]);
+        assert_eq!(area("cave", None, None).level_range_for(&species), Some((25, 35)));
+        assert_eq!(area("cave", Some(30), Some(40)).level_range_for(&species), Some((30, 35)));
+        assert_eq!(area("meadow", None, None).level_range_for(&species), Some((3, 12)));
+    }
+
+    #[test]
+    fn level_range_is_none_when_template_and_area_do_not_overlap() {
+        let species = species_spawning_at(3, 12, &[("cave", (25, 35))]);
+        assert_eq!(area("meadow", Some(20), Some(30)).level_range_for(&species), None);
+        assert_eq!(area("cave", Some(1), Some(10)).level_range_for(&species), None);
+    }
+
+    #[test]
+    fn unknown_templates_use_the_medium_curve() {
+        let repository = repository_with_every_growth_rate();
+        assert_eq!(repository.total_exp_for_level(1, 100), 1_000_000);
+    }
+
+    /// 8 by 3 map with an obstacle at (7, 1)
+    fn terrain_map() -> MapData {
+        MapData::for_tests(8, 3, &[(7, 1)])
+    }
+
+    fn step(map_data: &MapData, from: (u32, u32), to: (u32, u32)) -> Option<(u32, u32)> {
+        map_data.resolve_player_step(from, to, false, |_| false)
+    }
+
+    #[test]
+    fn ice_slides_the_player_to_the_first_other_tile() {
+        let map_data = terrain_map().with_terrain(TerrainType::Ice, &[(1, 0), (2, 0), (3, 0)]);
+        assert_eq!(step(&map_data, (0, 0), (1, 0)), Some((4, 0)));
+        // Sliding follows the step direction, back the way the player came
+        assert_eq!(step(&map_data, (4, 0), (3, 0)), Some((0, 0)));
+    }
+
+    #[test]
+    fn ice_stops_before_blocked_tiles() {
+        let map_data = terrain_map().with_terrain(TerrainType::Ice, &[(4, 1), (5, 1), (6, 1)]);
+        assert_eq!(step(&map_data, (3, 1), (4, 1)), Some((6, 1)));
+        // A monster on the ice stops the slide in front of it
+        assert_eq!(map_data.resolve_player_step((3, 1), (4, 1), false, |tile| tile == (6, 1)), Some((5, 1)));
+    }
+
+    #[test]
+    fn ledges_are_only_jumped_in_the_direction_they_face() {
+        let map_data = terrain_map().with_terrain(TerrainType::LedgeDown, &[(2, 1)]);
+        assert_eq!(step(&map_data, (2, 0), (2, 1)), Some((2, 2)));
+        assert_eq!(step(&map_data, (2, 2), (2, 1)), None);
+        assert_eq!(step(&map_data, (1, 1), (2, 1)), None);
+    }
+
+    #[test]
+    fn ledges_need_a_free_tile_to_land_on() {
+        let map_data = terrain_map().with_terrain(TerrainType::LedgeRight, &[(6, 1)]);
+        assert_eq!(step(&map_data, (5, 1), (6, 1)), None);
+        let map_data = terrain_map().with_terrain(TerrainType::LedgeRight, &[(1, 1), (2, 1)]);
+        assert_eq!(step(&map_data, (0, 1), (1, 1)), None);
+    }
+
+    #[test]
+    fn water_needs_surf() {
+        let map_data = terrain_map().with_terrain(TerrainType::Water, &[(1, 2)]);
+        assert_eq!(step(&map_data, (0, 2), (1, 2)), None);
+        assert_eq!(map_data.resolve_player_step((0, 2), (1, 2), true, |_| false), Some((1, 2)));
+        // Ice doesn't slide a player into water they can't surf
+        let map_data = map_data.with_terrain(TerrainType::Ice, &[(2, 2)]);
+        assert_eq!(step(&map_data, (3, 2), (2, 2)), Some((2, 2)));
+    }
+
+    #[test]
+    fn map1_paints_every_terrain_layer() {
+        let map_data = MapData::new("map1", "resources/map1.json").unwrap();
+        for (layer, terrain) in TerrainType::LAYERS {
+            assert!(map_data.obstacle_map.terrain.contains(&terrain), "map1 has no {} tiles", layer);
+        }
+    }
+}
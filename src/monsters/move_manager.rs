@@ -9,6 +9,7 @@ use tracing::{info, warn};
 
 use crate::{combat::state::StatusCondition, monsters::monster::MonsterMove};
 
+use super::content_packs;
 use super::monster::PokemonType;
 
 /// Represents a move in the game
@@ -29,7 +30,15 @@ pub struct MoveData {
     pub effect: EffectData,
     pub secondary_effect: Option<SecondaryEffectData>,
     pub description: String,
-    // Add flags later if needed (e.g., is_contact, is_punch, ignores_substitute)
+    /// Whether the user touches the target; physical moves do unless the data says otherwise
+    #[serde(default)]
+    pub makes_contact: Option<bool>,
+}
+
+impl MoveData {
+    pub fn makes_contact(&self) -> bool {
+        self.makes_contact.unwrap_or(self.damage_class == MoveCategory::Physical)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -251,10 +260,19 @@ pub struct MoveRepository {
 }
 
 impl MoveRepository {
-    /// Create a new MoveRepository from the specified file path
-    pub fn new(moves_path: &str, type_chart_path: &str) -> Arc<Self> {
-        let moves = Self::load_moves(moves_path);
+    /// Create a new MoveRepository from the specified file path, layering the moves of each content pack over it in order
+    pub fn new(moves_path: &str, type_chart_path: &str, content_pack_paths: &[String]) -> Arc<Self> {
+        let mut moves = Self::load_moves(moves_path);
         info!("Loaded {} moves from {}", moves.len(), moves_path);
+        let packs = content_packs::load_content_packs(content_pack_paths);
+        let conflicts = content_packs::apply_content_packs(
+            "move",
+            &mut moves,
+            packs.iter().map(|pack| (pack.name.as_str(), pack.moves.values().collect())),
+        );
+        if !packs.is_empty() {
+            info!("Loaded {} moves with {} content packs ({} conflicts)", moves.len(), packs.len(), conflicts.len());
+        }
         let type_chart = Self::load_type_chart(type_chart_path);
         info!("Loaded {} type chart from {}", type_chart.len(), type_chart_path);
         Arc::new(MoveRepository { moves, type_chart })
@@ -310,6 +328,7 @@ impl MoveRepository {
             MonsterMove {
                 id: move_id,
                 pp_remaining: move_data.pp,
+                pp_ups: 0,
             }
         })
     }
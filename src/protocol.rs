@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+// Version of the websocket protocol spoken by this server. Clients that leave it out of their
+// join message are treated as version 1. Version 1 clients get the messages reshaped since then
+// (request_action, pvp_request_action and battle_end) in their original shape, see downgrade_to_v1.
+// Fields and message types added since are sent to every client, which should ignore what it does
+// not know.
+pub const PROTOCOL_VERSION: u32 = 2;
+// Oldest client protocol version still accepted
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+// First version that understands the reshaped battle messages and team overview deltas
+const RESHAPED_MESSAGES_VERSION: u32 = 2;
+
+// Optional protocol features a client can ask for when joining. Binary encoding and delta
+// movement are not offered: every message is JSON text and movement is always sent in full.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    // Per-hit damage breakdown events in battle, same as sending set_damage_breakdown
+    VerboseBattleEvents,
+}
+
+// Every capability this server knows how to honour
+pub const SUPPORTED_CAPABILITIES: &[Capability] = &[Capability::VerboseBattleEvents];
+
+// What a connection told the server about itself in its join message
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClientProtocol {
+    // None for clients that predate protocol versioning
+    pub version: Option<u32>,
+    // Negotiated capabilities, see negotiate_capabilities
+    pub capabilities: Vec<Capability>,
+}
+
+impl ClientProtocol {
+    // Whether messages must be sent to this client in their version 1 shape
+    pub fn is_legacy(&self) -> bool {
+        self.version.unwrap_or(MIN_PROTOCOL_VERSION) < RESHAPED_MESSAGES_VERSION
+    }
+
+    pub fn has(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+}
+
+// Keep the requested capabilities this server supports. Unknown names are ignored so newer
+// clients can ask for features an older server does not have yet.
+pub fn negotiate_capabilities(requested: &[String]) -> Vec<Capability> {
+    let mut negotiated = Vec::new();
+    for name in requested {
+        let capability = match serde_json::from_value::<Capability>(Value::String(name.clone())) {
+            Ok(capability) => capability,
+            Err(_) => continue,
+        };
+        if SUPPORTED_CAPABILITIES.contains(&capability) && !negotiated.contains(&capability) {
+            negotiated.push(capability);
+        }
+    }
+    negotiated
+}
+
+// Fields of request_action in protocol version 1
+const V1_REQUEST_ACTION_FIELDS: &[&str] = &[
+    "type", "turn_number", "active_pokemon_state", "team_overview", "other_pokemon_state",
+    "can_switch", "must_switch", "field_state",
+];
+
+// Rewrite a serialized server message into its protocol version 1 shape. Team overviews are not
+// touched: the battle manager always sends version 1 clients their full team.
+pub fn downgrade_to_v1(message: &mut Value) {
+    let object = match message.as_object_mut() {
+        Some(object) => object,
+        None => return,
+    };
+    match object.get("type").and_then(Value::as_str) {
+        // PvP action requests used to share request_action with wild battles
+        Some("pvp_request_action") => {
+            object.insert("type".to_string(), Value::from("request_action"));
+            if let Some(opponent) = object.remove("opponent_pokemon_state") {
+                object.insert("other_pokemon_state".to_string(), opponent);
+            }
+            object.retain(|key, _| V1_REQUEST_ACTION_FIELDS.contains(&key.as_str()));
+        }
+        Some("request_action") => {
+            object.retain(|key, _| V1_REQUEST_ACTION_FIELDS.contains(&key.as_str()));
+        }
+        // Captures used to be described by the battle view of the caught pokemon
+        Some("battle_end") => {
+            if let Some(Value::Object(captured)) = object.get_mut("pokemon_captured") {
+                *captured = captured_to_v1(captured);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Fields the capture view shares with the version 1 battle view
+const V1_CAPTURED_FIELDS: &[&str] = &["template_id", "name", "level", "current_hp", "max_hp", "types", "ability", "moves"];
+
+fn captured_to_v1(captured: &Map<String, Value>) -> Map<String, Value> {
+    let mut view: Map<String, Value> = captured.iter()
+        .filter(|(key, _)| V1_CAPTURED_FIELDS.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    let current_hp = captured.get("current_hp").and_then(Value::as_f64).unwrap_or(0.0);
+    let max_hp = captured.get("max_hp").and_then(Value::as_f64).unwrap_or(0.0);
+    view.insert("current_hp_percent".to_string(), json!(if max_hp == 0.0 { 0.0 } else { current_hp / max_hp }));
+    view.insert("status".to_string(), Value::Null);
+    view.insert("volatile_statuses".to_string(), json!([]));
+    view.insert("stat_modifiers".to_string(), json!(crate::stats::BattleStatModifiers::default()));
+    view.insert("is_fainted".to_string(), Value::from(false));
+    view.insert("team_index".to_string(), Value::from(captured.get("active_index").and_then(Value::as_u64).unwrap_or(0)));
+    view
+}
+
+// Why the server closed a websocket, sent as the close frame's code and reason.
+// Codes are in the 4000-4999 range reserved for applications; clients should key off the code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CloseReason {
+    // The first message was not a join message with a session token
+    AuthFailed,
+    // The client sent messages faster than the server accepts them
+    Ratelimited,
+    // The server removed the player, e.g. for inactivity or because they logged in elsewhere
+    Kicked,
+    // The server is shutting down
+    ServerShutdown,
+    // The client kept sending messages the server could not understand
+    ProtocolError,
+    // The client's protocol version is older than MIN_PROTOCOL_VERSION
+    OutdatedClient,
+    // The player's save could not be reached
+    ServiceUnavailable,
+    // The player's save could not be loaded
+    ServerError,
+    // The player was idle for longer than the idle timeout
+    IdleTimeout,
+    // The client sent a message larger than the server's max_message_bytes
+    MessageTooLarge,
+}
+
+impl CloseReason {
+    pub fn code(&self) -> u16 {
+        match self {
+            CloseReason::AuthFailed => 4001,
+            CloseReason::Ratelimited => 4002,
+            CloseReason::Kicked => 4003,
+            CloseReason::ServerShutdown => 4004,
+            CloseReason::ProtocolError => 4005,
+            CloseReason::OutdatedClient => 4006,
+            CloseReason::ServiceUnavailable => 4007,
+            CloseReason::ServerError => 4008,
+            CloseReason::IdleTimeout => 4009,
+            CloseReason::MessageTooLarge => 4010,
+        }
+    }
+
+    pub fn reason(&self) -> &'static str {
+        match self {
+            CloseReason::AuthFailed => "auth_failed",
+            CloseReason::Ratelimited => "ratelimited",
+            CloseReason::Kicked => "kicked",
+            CloseReason::ServerShutdown => "server_shutdown",
+            CloseReason::ProtocolError => "protocol_error",
+            CloseReason::OutdatedClient => "outdated_client",
+            CloseReason::ServiceUnavailable => "service_unavailable",
+            CloseReason::ServerError => "server_error",
+            CloseReason::IdleTimeout => "idle_timeout",
+            CloseReason::MessageTooLarge => "message_too_large",
+        }
+    }
+}
@@ -1,16 +1,120 @@
+use dashmap::DashMap;
 use redis::AsyncCommands;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+// Delay before the first reconnect attempt; doubled after every failure
+const RETRY_BASE_DELAY_MS: u64 = 100;
+// Longest delay between two reconnect attempts
+const RETRY_MAX_DELAY_MS: u64 = 5_000;
+// Connection attempts made at startup before giving up
+const STARTUP_CONNECT_ATTEMPTS: u32 = 8;
 
 pub async fn init_redis_client(redis_url: &str) -> redis::Client {
     let client = redis::Client::open(redis_url).expect("Failed to create Redis client");
     
-    // Test the connection
-    let mut con = client.get_async_connection().await.expect("Failed to connect to Redis");
+    // Test the connection, waiting for Redis to come up if it is still starting
+    let mut con = connect_with_retry(&client, STARTUP_CONNECT_ATTEMPTS).await.expect("Failed to connect to Redis");
     let _: String = redis::cmd("PING").query_async(&mut con).await.expect("Redis connection test failed");
     
     tracing::info!("Successfully connected to Redis at {}", redis_url);
     client
 }
 
+// Open a connection, retrying with exponential backoff up to `attempts` times
+pub async fn connect_with_retry(client: &redis::Client, attempts: u32) -> redis::RedisResult<redis::aio::Connection> {
+    let mut delay = Duration::from_millis(RETRY_BASE_DELAY_MS);
+    let mut attempt = 1;
+    loop {
+        match client.get_async_connection().await {
+            Ok(con) => return Ok(con),
+            Err(e) if attempt >= attempts => return Err(e),
+            Err(e) => {
+                tracing::warn!("Redis connection attempt {}/{} failed: {}; retrying in {:?}", attempt, attempts, e, delay);
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(Duration::from_millis(RETRY_MAX_DELAY_MS));
+                attempt += 1;
+            }
+        }
+    }
+}
+
+// Whether an error means Redis itself is unreachable, rather than e.g. a missing key
+pub fn is_outage(error: &redis::RedisError) -> bool {
+    error.is_io_error() || error.is_connection_dropped() || error.is_connection_refusal() || error.is_timeout()
+}
+
+// Player state writes that could not reach Redis, kept until it comes back.
+// While any write is pending the server runs in degraded mode: movement keeps
+// working in memory and only the latest state per player is retained.
+#[derive(Default)]
+pub struct PersistenceQueue {
+    degraded: AtomicBool,
+    pending: DashMap<(String, String), String>, // (lobby ID, player ID) → player state JSON
+}
+
+impl PersistenceQueue {
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Relaxed)
+    }
+
+    pub fn pending_writes(&self) -> usize {
+        self.pending.len()
+    }
+
+    // Persist a player's state, queueing it if Redis is unreachable.
+    // Returns true if this write switched the server into degraded mode.
+    pub async fn store_player_state(&self, client: &redis::Client, lobby_id: &str, player_id: &str, state_json: &str) -> bool {
+        let key = (lobby_id.to_string(), player_id.to_string());
+        if !self.is_degraded() {
+            let result = match client.get_async_connection().await {
+                Ok(mut con) => store_player_state(&mut con, lobby_id, player_id, state_json).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => {
+                    self.pending.remove(&key);
+                    return false;
+                }
+                Err(e) => tracing::error!("Failed to persist player state, queueing it: {}", e),
+            }
+        }
+        self.pending.insert(key, state_json.to_string());
+        !self.degraded.swap(true, Ordering::Relaxed)
+    }
+
+    // Write out every queued state. Returns true if this left degraded mode.
+    pub async fn flush(&self, client: &redis::Client) -> bool {
+        if !self.is_degraded() {
+            return false;
+        }
+        let mut con = match client.get_async_connection().await {
+            Ok(con) => con,
+            Err(e) => {
+                tracing::warn!("Redis still unavailable, {} player states queued: {}", self.pending.len(), e);
+                return false;
+            }
+        };
+        let keys: Vec<(String, String)> = self.pending.iter().map(|entry| entry.key().clone()).collect();
+        for key in keys {
+            // Take the latest state; a newer one queued during the write is left for the next flush
+            let Some(state_json) = self.pending.get(&key).map(|entry| entry.value().clone()) else {
+                continue;
+            };
+            if let Err(e) = store_player_state(&mut con, &key.0, &key.1, &state_json).await {
+                tracing::warn!("Failed to flush queued player state, will retry: {}", e);
+                return false;
+            }
+            self.pending.remove_if(&key, |_, queued| *queued == state_json);
+        }
+        if !self.pending.is_empty() {
+            return false;
+        }
+        tracing::info!("Redis available again, queued player states flushed");
+        self.degraded.swap(false, Ordering::Relaxed)
+    }
+}
+
 pub async fn store_session(
     redis_conn: &mut redis::aio::Connection,
     session_token: &str, 
@@ -35,6 +139,22 @@ pub async fn get_player_id(
     redis_conn.get(&format!("session:{}", session_token)).await
 }
 
+// Remember which lobby a player was last in, so a new connection can resume there
+pub async fn store_last_lobby(
+    redis_conn: &mut redis::aio::Connection,
+    player_id: &str,
+    lobby_id: &str,
+) -> redis::RedisResult<()> {
+    redis_conn.set(format!("last_lobby:{}", player_id), lobby_id).await
+}
+
+pub async fn get_last_lobby(
+    redis_conn: &mut redis::aio::Connection,
+    player_id: &str,
+) -> redis::RedisResult<Option<String>> {
+    redis_conn.get(format!("last_lobby:{}", player_id)).await
+}
+
 pub async fn store_player_state(
     redis_conn: &mut redis::aio::Connection,
     lobby_id: &str,
@@ -0,0 +1,158 @@
+use schemars::schema_for;
+use serde_json::json;
+
+use crate::models::{ClientMessage, ServerMessage};
+
+// JSON Schema of every message on the websocket protocol, for the frontend's type codegen
+pub fn protocol_schema() -> serde_json::Value {
+    json!({
+        "client_message": schema_for!(ClientMessage),
+        "server_message": schema_for!(ServerMessage),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Map, Value};
+
+    // Nesting depth past which optional values are left out and lists left empty
+    const MAX_EXAMPLE_DEPTH: usize = 6;
+
+    // Smallest value `schema` accepts, with optional fields filled in down to MAX_EXAMPLE_DEPTH
+    // so the snapshots show the nested shapes too
+    fn example(schema: &Value, definitions: &Map<String, Value>, depth: usize) -> Value {
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            let name = reference.trim_start_matches("#/definitions/");
+            return example(&definitions[name], definitions, depth);
+        }
+        if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+            return values[0].clone();
+        }
+        if let Some(value) = schema.get("const") {
+            return value.clone();
+        }
+        for key in ["allOf", "oneOf", "anyOf"] {
+            if let Some(options) = schema.get(key).and_then(Value::as_array) {
+                let option = options.iter()
+                    .find(|option| option.get("type").and_then(Value::as_str) != Some("null"))
+                    .unwrap_or(&options[0]);
+                return example(option, definitions, depth);
+            }
+        }
+        let schema_type = match schema.get("type") {
+            Some(Value::Array(types)) if depth >= MAX_EXAMPLE_DEPTH && types.contains(&Value::from("null")) => return Value::Null,
+            Some(Value::Array(types)) => types.iter().find(|t| *t != "null").and_then(Value::as_str).unwrap_or("null"),
+            Some(Value::String(schema_type)) => schema_type.as_str(),
+            _ => return Value::Null,
+        };
+        match schema_type {
+            "object" => {
+                let required: Vec<&str> = schema.get("required").and_then(Value::as_array)
+                    .map(|required| required.iter().filter_map(Value::as_str).collect())
+                    .unwrap_or_default();
+                let mut object = Map::new();
+                if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+                    for (name, property) in properties {
+                        if depth < MAX_EXAMPLE_DEPTH || required.contains(&name.as_str()) {
+                            object.insert(name.clone(), example(property, definitions, depth + 1));
+                        }
+                    }
+                }
+                Value::Object(object)
+            }
+            "array" => match schema.get("items") {
+                Some(Value::Array(items)) => Value::Array(items.iter().map(|item| example(item, definitions, depth + 1)).collect()),
+                Some(item) => {
+                    let min_items = schema.get("minItems").and_then(Value::as_u64).unwrap_or(0) as usize;
+                    let len = if depth < MAX_EXAMPLE_DEPTH { min_items.max(1) } else { min_items };
+                    Value::Array(vec![example(item, definitions, depth + 1); len])
+                }
+                None => Value::Array(Vec::new()),
+            },
+            "string" if schema.get("format").and_then(Value::as_str) == Some("uuid") => Value::from(uuid::Uuid::nil().to_string()),
+            // Numeric so strings carrying numbers, like fairness seeds, parse too
+            "string" => Value::from("1"),
+            "integer" => Value::from(1),
+            "number" => Value::from(1.5),
+            "boolean" => Value::from(true),
+            _ => Value::Null,
+        }
+    }
+
+    // One example per variant of a tagged message enum, keyed by its "type" tag
+    fn variant_examples(schema: &Value) -> Vec<(String, Value)> {
+        let definitions = schema["definitions"].as_object().cloned().unwrap_or_default();
+        schema["oneOf"].as_array().expect("messages are a tagged enum").iter()
+            .map(|variant| {
+                let tag = variant["properties"]["type"]["enum"][0].as_str().expect("every message has a type tag");
+                (tag.to_string(), example(variant, &definitions, 0))
+            })
+            .collect()
+    }
+
+    // Fill in fields the schema leaves open, which the generated examples cannot cover
+    fn complete_loose_fields(tag: &str, example: &mut Value) {
+        if tag == "battle_fairness" {
+            let player = |player_id: &str| json!({
+                "player_id": player_id,
+                "name": player_id,
+                "active_pokemon_index": 0,
+                "side_effects": crate::combat::state::PlayerSideState::default(),
+                "last_action_submitted": null,
+                "must_switch": false,
+                "team": [],
+            });
+            example["transcript"] = json!({
+                "battle_id": uuid::Uuid::nil(),
+                "player1": player("player1"),
+                "player2": player("player2"),
+                "field_state": crate::combat::state::FieldState::default(),
+                "turns": [],
+            });
+        }
+    }
+
+    #[test]
+    fn client_messages_keep_their_wire_shape() {
+        let schema = protocol_schema();
+        for (tag, example) in variant_examples(&schema["client_message"]) {
+            let message: ClientMessage = serde_json::from_value(example.clone())
+                .unwrap_or_else(|e| panic!("client message {} does not parse from {}: {}", tag, example, e));
+            insta::assert_json_snapshot!(format!("client_{}", tag), message);
+        }
+    }
+
+    #[test]
+    fn server_messages_keep_their_wire_shape() {
+        let schema = protocol_schema();
+        for (tag, mut example) in variant_examples(&schema["server_message"]) {
+            complete_loose_fields(&tag, &mut example);
+            let message: ServerMessage = serde_json::from_value(example.clone())
+                .unwrap_or_else(|e| panic!("server message {} does not parse from {}: {}", tag, example, e));
+            insta::assert_json_snapshot!(format!("server_{}", tag), message);
+        }
+    }
+
+    #[test]
+    fn legacy_clients_get_version_one_shapes() {
+        let schema = protocol_schema();
+        for (tag, example) in variant_examples(&schema["server_message"]) {
+            if !["request_action", "pvp_request_action", "battle_end"].contains(&tag.as_str()) {
+                continue;
+            }
+            let message: ServerMessage = serde_json::from_value(example).expect("server message parses");
+            let mut value = serde_json::to_value(&message).expect("server message serializes");
+            crate::protocol::downgrade_to_v1(&mut value);
+            insta::assert_json_snapshot!(format!("legacy_server_{}", tag), value);
+        }
+    }
+
+    #[test]
+    fn clients_without_a_version_are_legacy() {
+        use crate::protocol::{ClientProtocol, PROTOCOL_VERSION};
+        assert!(ClientProtocol::default().is_legacy());
+        assert!(ClientProtocol { version: Some(1), capabilities: Vec::new() }.is_legacy());
+        assert!(!ClientProtocol { version: Some(PROTOCOL_VERSION), capabilities: Vec::new() }.is_legacy());
+    }
+}
@@ -4,7 +4,7 @@ pub mod nature;
 use nature::Nature;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, schemars::JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum StatName {
     Attack,
@@ -16,7 +16,7 @@ pub enum StatName {
     Evasion,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default, schemars::JsonSchema)]
 pub struct StatSet<T> {
     pub hp: T,
     pub attack: T,
@@ -30,7 +30,7 @@ pub type BaseStats = StatSet<u32>;
 pub type CalculatedStats = StatSet<u32>;
 
 // StatStages needs special handling because it has additional fields
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, schemars::JsonSchema)]
 pub struct BattleStatModifiers {
     // inherit the common stats
     pub battle_stats: StatSet<i8>,
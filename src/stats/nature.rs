@@ -2,7 +2,7 @@ use super::StatName;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, Copy, schemars::JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Nature {
     Hardy,
@@ -0,0 +1,30 @@
+// Helpers for tests that need a live websocket connection
+use axum::extract::ws::{WebSocket, WebSocketUpgrade};
+use axum::routing::get;
+use axum::Router;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+pub type ClientSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+// Connect a client to a throwaway local server and return the server's end of the
+// connection with the client's. `configure` sets up the upgrade the way a handler would.
+pub async fn connected_socket<F>(configure: F) -> (WebSocket, ClientSocket)
+where
+    F: Fn(WebSocketUpgrade) -> WebSocketUpgrade + Clone + Send + Sync + 'static,
+{
+    let (socket_tx, mut socket_rx) = mpsc::unbounded_channel();
+    let app = Router::new().route("/", get(move |ws: WebSocketUpgrade| async move {
+        configure(ws).on_upgrade(move |socket| async move {
+            let _ = socket_tx.send(socket);
+        })
+    }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind test server");
+    let address = listener.local_addr().expect("test server address");
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    let (client, _) = tokio_tungstenite::connect_async(format!("ws://{}/", address)).await.expect("connect to test server");
+    let socket = socket_rx.recv().await.expect("server accepted the connection");
+    (socket, client)
+}